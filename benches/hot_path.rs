@@ -0,0 +1,180 @@
+// Benchmarks for the work the monitor does on every tick between reading a quote and sending a
+// trade: decoding the simulated quote, evaluating it, and building and signing the transaction.
+// The bot is a binary crate, so the modules are compiled in directly, without their unit tests
+#[path = "../src/client.rs"]
+#[allow(dead_code, unused_imports)]
+mod client;
+#[path = "../src/evaluator.rs"]
+#[allow(dead_code, unused_imports)]
+mod evaluator;
+#[path = "../src/spread_stats.rs"]
+#[allow(dead_code, unused_imports)]
+mod spread_stats;
+#[path = "../src/strategy.rs"]
+#[allow(dead_code, unused_imports)]
+mod strategy;
+#[path = "../src/venue.rs"]
+#[allow(dead_code, unused_imports)]
+mod venue;
+
+use criterion::{black_box, criterion_group, criterion_main, BatchSize, Criterion};
+use evaluator::{EvaluatorParams, Quote};
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use strategy::MarketContext;
+
+const LOAN_AMOUNT: u64 = 1_000_000_000;
+const SOL_PRICE: f64 = 150.0;
+// Accounts per swap leg, about what a Whirlpool swap with tick arrays takes
+const LEG_ACCOUNTS: usize = 11;
+
+fn quote() -> Quote {
+    Quote {
+        jupiter_price: 1_003_000_000,
+        raydium_price: 1_000_000_000,
+        venues: Vec::new(),
+    }
+}
+
+fn bench_quote(c: &mut Criterion) {
+    let mut data = 1_003_000_000u64.to_le_bytes().to_vec();
+    data.extend_from_slice(&1_000_000_000u64.to_le_bytes());
+    let encoded = base64::encode(&data);
+
+    let mut group = c.benchmark_group("quote");
+    group.bench_function("decode_return_data", |b| {
+        b.iter(|| Quote::from_return_data(&base64::decode(black_box(&encoded)).unwrap()).unwrap())
+    });
+    group.bench_function("spread_bps", |b| b.iter(|| spread_stats::spread_bps(black_box(&quote()))));
+    group.finish();
+}
+
+fn bench_evaluate(c: &mut Criterion) {
+    let params = EvaluatorParams::default();
+    let quote = quote();
+    let evaluations: Vec<_> = (0..4)
+        .map(|i| evaluator::evaluate_route(LOAN_AMOUNT, LOAN_AMOUNT + 2_000_000 + i, SOL_PRICE, &params))
+        .collect();
+
+    let mut group = c.benchmark_group("evaluate");
+    group.bench_function("pair", |b| {
+        b.iter(|| evaluator::evaluate(black_box(&quote), LOAN_AMOUNT, SOL_PRICE, &params))
+    });
+    group.bench_function("route", |b| {
+        b.iter(|| evaluator::evaluate_route(LOAN_AMOUNT, black_box(LOAN_AMOUNT + 2_000_000), SOL_PRICE, &params))
+    });
+    group.bench_function("batch_of_4", |b| b.iter(|| evaluator::evaluate_batch(black_box(&evaluations))));
+    group.bench_function("opportunity_id", |b| {
+        b.iter(|| evaluator::opportunity_id(black_box(250_000_000), "BONK", "GOAT"))
+    });
+    group.finish();
+}
+
+fn bench_decide(c: &mut Criterion) {
+    let params = EvaluatorParams::default();
+    let quote = quote();
+    let ctx = MarketContext {
+        pair_key: "BONK/GOAT",
+        quote: &quote,
+        loan_amount: LOAN_AMOUNT,
+        sol_price: SOL_PRICE,
+        oracle_price: Some(1_001_000_000),
+        params: &params,
+    };
+
+    let mut group = c.benchmark_group("decide");
+    for config in ["spread", "oracle:50", "zscore:2.5:300"] {
+        let strategy = strategy::from_name(config).unwrap();
+        // Fill the z-score window so the benchmark measures a warm pair
+        for _ in 0..300 {
+            strategy.decide(&ctx);
+        }
+        group.bench_function(strategy.name(), |b| b.iter(|| strategy.decide(black_box(&ctx))));
+    }
+    group.finish();
+}
+
+fn route_args(legs: usize) -> client::TryArbitrageArgs {
+    let mints = [Pubkey::new_unique().to_bytes(), Pubkey::new_unique().to_bytes()];
+    client::TryArbitrageArgs {
+        amount: LOAN_AMOUNT,
+        min_profit_lamports: 1_000_000,
+        expiry_slot: 250_000_150,
+        loan_provider: client::LoanProvider::Solend {
+            accounts_offset: (legs * LEG_ACCOUNTS) as u8,
+        },
+        legs: (0..legs)
+            .map(|i| client::RouteLeg {
+                venue: 3,
+                input_mint: mints[i % 2],
+                output_mint: mints[(i + 1) % 2],
+                min_amount_out: LOAN_AMOUNT,
+                accounts_offset: (i * LEG_ACCOUNTS) as u8,
+                accounts_len: LEG_ACCOUNTS as u8,
+                venue_data: vec![1; 33],
+            })
+            .collect(),
+    }
+}
+
+fn trade_accounts(user: Pubkey) -> client::TryArbitrageAccounts {
+    client::TryArbitrageAccounts {
+        user,
+        token_a_account: Pubkey::new_unique(),
+        vault: Pubkey::new_unique(),
+        token_a_mint: Pubkey::new_unique(),
+        associated_token_program: None,
+        token_program: spl_token_program(),
+        profit_destination: Pubkey::new_unique(),
+        fee_collector_account: Pubkey::new_unique(),
+        price_oracle: None,
+    }
+}
+
+fn spl_token_program() -> Pubkey {
+    "TokenkegQfeZyiNwAJbNbGucJGLdTbrLNLJKGTJhUGJJ".parse().unwrap()
+}
+
+fn bench_build(c: &mut Criterion) {
+    let program_id = client::program_id();
+    let wallet = Keypair::new();
+    let accounts = trade_accounts(wallet.pubkey());
+    let remaining: Vec<AccountMeta> = (0..3 * LEG_ACCOUNTS + 8)
+        .map(|_| AccountMeta::new(Pubkey::new_unique(), false))
+        .collect();
+    let blockhash = Hash::new_unique();
+
+    let mut group = c.benchmark_group("build");
+    for legs in [2, 3] {
+        let args = route_args(legs);
+        let remaining = remaining[..legs * LEG_ACCOUNTS + 8].to_vec();
+        group.bench_function(format!("try_arbitrage_{}_legs", legs), |b| {
+            b.iter_batched(
+                || remaining.clone(),
+                |remaining| client::try_arbitrage(&program_id, &accounts, black_box(&args), remaining).unwrap(),
+                BatchSize::SmallInput,
+            )
+        });
+    }
+
+    // The instruction as sent: behind compute budget instructions, compiled and signed
+    let instruction = client::try_arbitrage(&program_id, &accounts, &route_args(3), remaining.clone()).unwrap();
+    group.bench_function("signed_transaction", |b| {
+        b.iter(|| {
+            let instructions = [
+                ComputeBudgetInstruction::set_compute_unit_limit(400_000),
+                ComputeBudgetInstruction::set_compute_unit_price(10_000),
+                instruction.clone(),
+            ];
+            Transaction::new_signed_with_payer(&instructions, Some(&wallet.pubkey()), &[&wallet], black_box(blockhash))
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_quote, bench_evaluate, bench_decide, bench_build);
+criterion_main!(benches);
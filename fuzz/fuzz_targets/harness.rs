@@ -0,0 +1,251 @@
+// Shared setup for the fuzz targets: the compiled arbitrage_contract.so under solana-program-test
+// with its config and whitelist initialized and a funded operator, but no venue or loan programs.
+// Every fuzzed trade is simulated against the same state, so inputs can't affect each other
+use anchor_lang::{InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use arbitrage_contract::{ConfigParams, CONFIG_SEED, VAULT_SEED, WHITELIST_SEED};
+use solana_program_test::{ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::{AccountMeta, Instruction, InstructionError};
+use solana_sdk::packet::PACKET_DATA_SIZE;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signer;
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::cell::RefCell;
+use tokio::runtime::Runtime;
+
+const TOKEN_BALANCE: u64 = 1_000_000_000;
+const VENUE_PROGRAMS: [Pubkey; 4] = [
+    arbitrage_contract::JUPITER_V6_PROGRAM_ID,
+    arbitrage_contract::RAYDIUM_AMM_PROGRAM_ID,
+    arbitrage_contract::RAYDIUM_CLMM_PROGRAM_ID,
+    arbitrage_contract::WHIRLPOOL_PROGRAM_ID,
+];
+
+pub struct Harness {
+    runtime: Runtime,
+    context: ProgramTestContext,
+    pub program_id: Pubkey,
+    // Named accounts of every fuzzed trade, as the program's own client declares them
+    pub accounts: arbitrage_contract::accounts::TryArbitrage,
+    // Mints a fuzzed route may name: the borrowed token, a second token and WSOL
+    pub mints: Vec<Pubkey>,
+    // Accounts a fuzzed route may pass as remaining accounts: venue and loan programs, vaults, token
+    // accounts, mints, the instructions sysvar and accounts that don't exist
+    pub remaining_accounts: Vec<AccountMeta>,
+}
+
+thread_local! {
+    static HARNESS: RefCell<Option<Harness>> = const { RefCell::new(None) };
+}
+
+// Runs `f` against the harness, starting it on first use
+pub fn with_harness<R>(f: impl FnOnce(&mut Harness) -> R) -> R {
+    HARNESS.with(|harness| f(harness.borrow_mut().get_or_insert_with(Harness::start)))
+}
+
+fn mint_account() -> Account {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    let mint = spl_token::state::Mint {
+        decimals: 6,
+        is_initialized: true,
+        ..Default::default()
+    };
+    spl_token::state::Mint::pack(mint, &mut data).unwrap();
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    let account = spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    };
+    spl_token::state::Account::pack(account, &mut data).unwrap();
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+impl Harness {
+    fn start() -> Self {
+        let runtime = Runtime::new().unwrap();
+        let program_id = arbitrage_contract::ID;
+        let program_test = ProgramTest::new("arbitrage_contract", program_id, None);
+        let mut context = runtime.block_on(program_test.start_with_context());
+        let operator = context.payer.pubkey();
+
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+        context.set_account(&mint_a, &mint_account().into());
+        context.set_account(&mint_b, &mint_account().into());
+        let vault_authority = Pubkey::find_program_address(&[VAULT_SEED], &program_id).0;
+        let vault_a = get_associated_token_address(&vault_authority, &mint_a);
+        let vault_b = get_associated_token_address(&vault_authority, &mint_b);
+        context.set_account(&vault_a, &token_account(&mint_a, &vault_authority, 0).into());
+        context.set_account(&vault_b, &token_account(&mint_b, &vault_authority, 0).into());
+        let token_a_account = Pubkey::new_unique();
+        let token_b_account = Pubkey::new_unique();
+        let profit_destination = Pubkey::new_unique();
+        let fee_collector_account = Pubkey::new_unique();
+        context.set_account(&token_a_account, &token_account(&mint_a, &operator, TOKEN_BALANCE).into());
+        context.set_account(&token_b_account, &token_account(&mint_b, &operator, TOKEN_BALANCE).into());
+        context.set_account(&profit_destination, &token_account(&mint_a, &operator, 0).into());
+        context.set_account(&fee_collector_account, &token_account(&mint_a, &operator, 0).into());
+
+        let mut remaining_accounts: Vec<AccountMeta> =
+            VENUE_PROGRAMS.iter().map(|program| AccountMeta::new_readonly(*program, false)).collect();
+        remaining_accounts.push(AccountMeta::new_readonly(arbitrage_contract::SOLEND_PROGRAM_ID, false));
+        remaining_accounts.push(AccountMeta::new_readonly(spl_token::id(), false));
+        remaining_accounts.push(AccountMeta::new_readonly(solana_sdk::sysvar::instructions::id(), false));
+        // A whitelisted pool that doesn't exist, and an account that isn't whitelisted either
+        let pool = Pubkey::new_unique();
+        for account in [vault_a, vault_b, token_a_account, token_b_account, pool, Pubkey::new_unique()] {
+            remaining_accounts.push(AccountMeta::new(account, false));
+        }
+        remaining_accounts.push(AccountMeta::new_readonly(mint_a, false));
+        remaining_accounts.push(AccountMeta::new_readonly(mint_b, false));
+
+        let mut harness = Self {
+            runtime,
+            context,
+            program_id,
+            accounts: arbitrage_contract::accounts::TryArbitrage {
+                config: Pubkey::find_program_address(&[CONFIG_SEED], &program_id).0,
+                whitelist: Pubkey::find_program_address(&[WHITELIST_SEED], &program_id).0,
+                user: operator,
+                token_a_account,
+                vault_authority,
+                vault: vault_a,
+                token_a_mint: mint_a,
+                associated_token_program: None,
+                system_program: solana_sdk::system_program::id(),
+                token_program: spl_token::id(),
+                profit_destination,
+                fee_collector_account,
+                instructions_sysvar: solana_sdk::sysvar::instructions::id(),
+                price_oracle: None,
+            },
+            mints: vec![mint_a, mint_b, spl_token::native_mint::id()],
+            remaining_accounts,
+        };
+        harness.initialize(pool);
+        harness
+    }
+
+    fn initialize(&mut self, pool: Pubkey) {
+        let admin = self.context.payer.pubkey();
+        let params = ConfigParams {
+            operator: admin,
+            paused: false,
+            max_trade_size: u64::MAX,
+            flash_loan_fee_bps: 0,
+            max_slippage_bps: 100,
+            fee_collector: admin,
+            profit_fee_bps: 0,
+            allow_unlisted_jupiter_pools: false,
+            price_oracle: Pubkey::default(),
+        };
+        let instructions = [
+            Instruction {
+                program_id: self.program_id,
+                accounts: arbitrage_contract::accounts::InitializeConfig {
+                    config: self.accounts.config,
+                    admin,
+                    system_program: solana_sdk::system_program::id(),
+                }
+                .to_account_metas(None),
+                data: arbitrage_contract::instruction::InitializeConfig { params }.data(),
+            },
+            Instruction {
+                program_id: self.program_id,
+                accounts: arbitrage_contract::accounts::InitializeWhitelist {
+                    whitelist: self.accounts.whitelist,
+                    config: self.accounts.config,
+                    admin,
+                    system_program: solana_sdk::system_program::id(),
+                }
+                .to_account_metas(None),
+                data: arbitrage_contract::instruction::InitializeWhitelist {
+                    programs: VENUE_PROGRAMS.to_vec(),
+                    pools: vec![pool],
+                }
+                .data(),
+            },
+        ];
+        let payer = &self.context.payer;
+        let blockhash = self.context.last_blockhash;
+        let transaction = Transaction::new_signed_with_payer(&instructions, Some(&payer.pubkey()), &[payer], blockhash);
+        self.runtime
+            .block_on(self.context.banks_client.process_transaction(transaction))
+            .expect("Failed to initialize the program; is SBF_OUT_DIR pointing at arbitrage_contract.so?");
+    }
+
+    // Picks a remaining account by a fuzzed index
+    pub fn remaining_account(&self, index: u8) -> AccountMeta {
+        self.remaining_accounts[index as usize % self.remaining_accounts.len()].clone()
+    }
+
+    // A trade instruction with the harness's named accounts followed by `remaining_accounts`
+    pub fn trade(&self, data: Vec<u8>, remaining_accounts: Vec<AccountMeta>) -> Instruction {
+        let mut accounts = self.accounts.to_account_metas(None);
+        accounts.extend(remaining_accounts);
+        Instruction {
+            program_id: self.program_id,
+            accounts,
+            data,
+        }
+    }
+
+    // Simulates `instruction` with the largest compute limit and checks the outcome. No trade can
+    // succeed here, since no venue or loan program is deployed, so any failure must be one the
+    // program reports: a custom error, or a runtime error from a CPI into a missing program.
+    // Transactions too large to send are skipped
+    pub fn check(&mut self, instruction: Instruction) {
+        let payer = &self.context.payer;
+        let transaction = Transaction::new_signed_with_payer(
+            &[ComputeBudgetInstruction::set_compute_unit_limit(1_400_000), instruction],
+            Some(&payer.pubkey()),
+            &[payer],
+            self.context.last_blockhash,
+        );
+        if bincode::serialized_size(&transaction).unwrap() as usize > PACKET_DATA_SIZE {
+            return;
+        }
+        let simulation = self
+            .runtime
+            .block_on(self.context.banks_client.simulate_transaction(transaction))
+            .unwrap();
+        let logs = simulation.simulation_details.map(|details| details.logs).unwrap_or_default();
+
+        if logs.iter().any(|log| log.contains("panicked")) {
+            panic!("program panicked:\n{}", logs.join("\n"));
+        }
+        match simulation.result {
+            Some(Ok(())) => panic!("trade succeeded with no venue to trade on:\n{}", logs.join("\n")),
+            Some(Err(TransactionError::InstructionError(_, InstructionError::ProgramFailedToComplete))) => {
+                panic!("program failed to complete (panic or out-of-bounds access):\n{}", logs.join("\n"))
+            }
+            Some(Err(TransactionError::InstructionError(_, InstructionError::ComputationalBudgetExceeded))) => {
+                panic!("program ran out of compute:\n{}", logs.join("\n"))
+            }
+            _ => {}
+        }
+    }
+}
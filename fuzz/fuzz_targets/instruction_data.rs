@@ -0,0 +1,23 @@
+// Raw instruction data for try_arbitrage and try_arbitrage_batch: whatever follows the discriminator
+// must be rejected by the program's decoder or its checks, never panic or read out of bounds
+#![no_main]
+
+use anchor_lang::Discriminator;
+use arbitrage_contract::instruction::{TryArbitrage, TryArbitrageBatch};
+use libfuzzer_sys::fuzz_target;
+
+// Only route_args picks the harness's mints
+#[allow(dead_code)]
+mod harness;
+
+use harness::with_harness;
+
+fuzz_target!(|input: (bool, Vec<u8>, Vec<u8>)| {
+    let (batch, remaining, data) = input;
+    with_harness(|harness| {
+        let remaining_accounts = remaining.iter().take(24).map(|index| harness.remaining_account(*index)).collect();
+        let mut instruction_data = if batch { TryArbitrageBatch::DISCRIMINATOR } else { TryArbitrage::DISCRIMINATOR }.to_vec();
+        instruction_data.extend_from_slice(&data);
+        harness.check(harness.trade(instruction_data, remaining_accounts));
+    });
+});
@@ -0,0 +1,109 @@
+// Well-formed try_arbitrage and try_arbitrage_batch arguments with fuzzed contents: legs whose
+// account slices run past the remaining accounts, unknown venues, truncated venue data, loan
+// accounts at any offset, and any amounts, minimums and expiry slots
+#![no_main]
+
+use anchor_lang::InstructionData;
+use arbitrage_contract::{BatchRoute, RouteLeg};
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+
+mod harness;
+
+use harness::{with_harness, Harness};
+
+#[derive(Debug, Arbitrary)]
+struct Leg {
+    venue: u8,
+    // Indexes into the harness's mints
+    input_mint: u8,
+    output_mint: u8,
+    min_amount_out: u64,
+    accounts_offset: u8,
+    accounts_len: u8,
+    venue_data: Vec<u8>,
+}
+
+#[derive(Debug, Arbitrary)]
+enum LoanProvider {
+    Marginfi,
+    Solend { accounts_offset: u8 },
+}
+
+#[derive(Debug, Arbitrary)]
+struct Route {
+    amount: u64,
+    min_profit_lamports: u64,
+    legs: Vec<Leg>,
+}
+
+#[derive(Debug, Arbitrary)]
+struct Trade {
+    // try_arbitrage_batch with every route, or try_arbitrage with the first
+    batch: bool,
+    amount: u64,
+    min_profit_lamports: u64,
+    expiry_slot: u64,
+    loan_provider: LoanProvider,
+    routes: Vec<Route>,
+    // Indexes into the harness's remaining accounts
+    remaining_accounts: Vec<u8>,
+}
+
+fn leg(harness: &Harness, leg: &Leg) -> RouteLeg {
+    let mint = |index: u8| harness.mints[index as usize % harness.mints.len()];
+    RouteLeg {
+        venue: leg.venue,
+        input_mint: mint(leg.input_mint),
+        output_mint: mint(leg.output_mint),
+        min_amount_out: leg.min_amount_out,
+        accounts_offset: leg.accounts_offset,
+        accounts_len: leg.accounts_len,
+        venue_data: leg.venue_data.clone(),
+    }
+}
+
+fuzz_target!(|trade: Trade| {
+    with_harness(|harness| {
+        let loan_provider = match trade.loan_provider {
+            LoanProvider::Marginfi => arbitrage_contract::LoanProvider::Marginfi,
+            LoanProvider::Solend { accounts_offset } => arbitrage_contract::LoanProvider::Solend { accounts_offset },
+        };
+        let routes: Vec<BatchRoute> = trade
+            .routes
+            .iter()
+            .map(|route| BatchRoute {
+                amount: route.amount,
+                min_profit_lamports: route.min_profit_lamports,
+                legs: route.legs.iter().map(|l| leg(harness, l)).collect(),
+            })
+            .collect();
+        let remaining_accounts = trade
+            .remaining_accounts
+            .iter()
+            .take(24)
+            .map(|index| harness.remaining_account(*index))
+            .collect();
+
+        let data = if trade.batch {
+            arbitrage_contract::instruction::TryArbitrageBatch {
+                amount: trade.amount,
+                min_profit_lamports: trade.min_profit_lamports,
+                expiry_slot: trade.expiry_slot,
+                loan_provider,
+                routes,
+            }
+            .data()
+        } else {
+            arbitrage_contract::instruction::TryArbitrage {
+                amount: trade.amount,
+                min_profit_lamports: trade.min_profit_lamports,
+                expiry_slot: trade.expiry_slot,
+                loan_provider,
+                legs: routes.into_iter().next().map(|route| route.legs).unwrap_or_default(),
+            }
+            .data()
+        };
+        harness.check(harness.trade(data, remaining_accounts));
+    });
+});
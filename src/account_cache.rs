@@ -0,0 +1,315 @@
+use crate::commands;
+use crate::landing::Backend;
+use crate::retry::Backoff;
+use crate::rpc::{RpcApi, MAX_ACCOUNTS_PER_REQUEST};
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::pubsub_client::PubsubClient;
+use solana_client::rpc_config::{RpcAccountInfoConfig, RpcSimulateTransactionConfig, RpcTransactionConfig};
+use solana_client::rpc_response::{RpcResult, RpcSimulateTransactionResult};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Cache of the accounts quoting and evaluation read (Pyth feeds, pool states), each with the slot
+// of its last update. Entries come from the per-cycle getMultipleAccounts batch and, with
+// ACCOUNT_SUBSCRIPTIONS on, from accountSubscribe notifications; an update never replaces a later
+// one. An entry older than its TTL is refetched on read rather than served, so a failed batch or a
+// silently dead subscription can't leave the evaluator on old pool state. Accounts outside the
+// cache, and reads that ask for a commitment or need the response slot, go to the wrapped RpcApi.
+// Updates that change an account are noted, so the monitor can evaluate only the pairs whose
+// accounts moved
+
+// Delay before resubscribing, doubling per failed attempt
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+
+pub struct CacheConfig {
+    // Age after which a fetched entry is refetched on read
+    pub ttl: Duration,
+    // The same for accounts with a live subscription, whose entries only change when the account does
+    pub subscribed_ttl: Duration,
+    // accountSubscribe endpoint; None leaves the cache to the batches
+    pub ws_url: Option<String>,
+}
+
+impl CacheConfig {
+    pub fn from_env() -> Self {
+        let millis = |name: &str, default: u64| {
+            Duration::from_millis(env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default))
+        };
+        Self {
+            ttl: millis("ACCOUNT_CACHE_TTL_MS", 5_000),
+            subscribed_ttl: millis("ACCOUNT_CACHE_SUBSCRIBED_TTL_MS", 30_000),
+            ws_url: env::var("ACCOUNT_SUBSCRIPTIONS")
+                .map(|v| v == "true")
+                .unwrap_or(false)
+                .then(commands::ws_url),
+        }
+    }
+}
+
+struct CachedAccount {
+    // None for an account that didn't exist at `slot`
+    account: Option<Account>,
+    slot: Slot,
+    updated: Instant,
+}
+
+pub struct AccountCache {
+    inner: Arc<dyn RpcApi>,
+    config: CacheConfig,
+    entries: Mutex<HashMap<Pubkey, CachedAccount>>,
+    // Accounts with a subscription thread, and those of them currently connected
+    subscribed: Mutex<HashSet<Pubkey>>,
+    live: Mutex<HashSet<Pubkey>>,
+    // Accounts whose cached state changed since the last take_changed
+    changed: Mutex<HashSet<Pubkey>>,
+}
+
+impl AccountCache {
+    pub fn new(inner: Arc<dyn RpcApi>, config: CacheConfig) -> Self {
+        Self {
+            inner,
+            config,
+            entries: Mutex::new(HashMap::new()),
+            subscribed: Mutex::new(HashSet::new()),
+            live: Mutex::new(HashSet::new()),
+            changed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    // Caches `account` as of `slot` unless the entry already holds a later update, noting it as
+    // changed unless the entry held the same state
+    pub fn store(&self, pubkey: Pubkey, account: Option<Account>, slot: Slot) {
+        let mut entries = self.entries.lock().unwrap();
+        let previous = entries.get(&pubkey);
+        if previous.is_none_or(|entry| entry.slot <= slot) {
+            if previous.is_none_or(|entry| entry.account != account) {
+                self.changed.lock().unwrap().insert(pubkey);
+            }
+            entries.insert(
+                pubkey,
+                CachedAccount {
+                    account,
+                    slot,
+                    updated: Instant::now(),
+                },
+            );
+        }
+    }
+
+    // Accounts whose state changed since the last call, including ones cached for the first time
+    pub fn take_changed(&self) -> HashSet<Pubkey> {
+        std::mem::take(&mut *self.changed.lock().unwrap())
+    }
+
+    // Drops the entry, so the next read fetches the account
+    pub fn invalidate(&self, pubkey: &Pubkey) {
+        self.entries.lock().unwrap().remove(pubkey);
+    }
+
+    // Slot of the cached entry's last update
+    #[cfg(test)]
+    pub fn slot(&self, pubkey: &Pubkey) -> Option<Slot> {
+        self.entries.lock().unwrap().get(pubkey).map(|entry| entry.slot)
+    }
+
+    // Fetches `pubkeys` into the cache, 100 per request. Accounts with a live subscription whose
+    // entries are still fresh are left to it
+    pub fn prefetch(&self, pubkeys: &[Pubkey]) -> ClientResult<()> {
+        let live = self.live.lock().unwrap().clone();
+        let stale: Vec<Pubkey> = pubkeys
+            .iter()
+            .filter(|pubkey| !live.contains(pubkey) || self.fresh(pubkey).is_none())
+            .copied()
+            .collect();
+        self.fetch(&stale, true)?;
+        Ok(())
+    }
+
+    // Subscribes to each of `pubkeys` not subscribed yet, one websocket per account. A subscription
+    // that closes or fails reconnects with backoff; until it's back the account falls back to the
+    // batches and the short TTL, and on reconnect it's refetched for the updates missed meanwhile
+    pub fn subscribe(self: &Arc<Self>, pubkeys: &[Pubkey]) {
+        let Some(ws_url) = &self.config.ws_url else {
+            return;
+        };
+        for &pubkey in pubkeys {
+            if !self.subscribed.lock().unwrap().insert(pubkey) {
+                continue;
+            }
+            let cache = Arc::clone(self);
+            let ws_url = ws_url.clone();
+            std::thread::spawn(move || {
+                let config = RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    commitment: Some(cache.inner.commitment()),
+                    ..RpcAccountInfoConfig::default()
+                };
+                let mut backoff = Backoff::new(RECONNECT_BASE_DELAY, RECONNECT_MAX_DELAY);
+                loop {
+                    match PubsubClient::account_subscribe(&ws_url, &pubkey, Some(config.clone())) {
+                        Ok((_subscription, receiver)) => {
+                            backoff.reset();
+                            cache.live.lock().unwrap().insert(pubkey);
+                            // Fetched after subscribing, so no update falls between the two
+                            if let Err(e) = cache.fetch(&[pubkey], true) {
+                                println!("Failed to refetch account {} after subscribing: {}", pubkey, e);
+                            }
+                            for response in receiver {
+                                if let Some(account) = response.value.decode::<Account>() {
+                                    cache.store(pubkey, Some(account), response.context.slot);
+                                }
+                            }
+                            cache.live.lock().unwrap().remove(&pubkey);
+                            println!("Account subscription for {} closed, reconnecting", pubkey);
+                        }
+                        Err(e) => println!("Failed to subscribe to account {}: {}", pubkey, e),
+                    }
+                    std::thread::sleep(backoff.next_delay());
+                }
+            });
+        }
+    }
+
+    // The cached account while its entry is within its TTL
+    fn fresh(&self, pubkey: &Pubkey) -> Option<Option<Account>> {
+        let ttl = if self.live.lock().unwrap().contains(pubkey) {
+            self.config.subscribed_ttl
+        } else {
+            self.config.ttl
+        };
+        let entries = self.entries.lock().unwrap();
+        let entry = entries.get(pubkey)?;
+        (entry.updated.elapsed() <= ttl).then(|| entry.account.clone())
+    }
+
+    fn is_cached(&self, pubkey: &Pubkey) -> bool {
+        self.entries.lock().unwrap().contains_key(pubkey)
+    }
+
+    // Fetches `pubkeys` with the slot of each response, refreshing their entries; `add` also
+    // caches accounts that have none yet
+    fn fetch(&self, pubkeys: &[Pubkey], add: bool) -> ClientResult<Vec<Option<Account>>> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+            let response = self.inner.get_multiple_accounts_with_commitment(chunk, self.inner.commitment())?;
+            for (pubkey, account) in chunk.iter().zip(&response.value) {
+                if add || self.is_cached(pubkey) {
+                    self.store(*pubkey, account.clone(), response.context.slot);
+                }
+            }
+            accounts.extend(response.value);
+        }
+        Ok(accounts)
+    }
+}
+
+impl RpcApi for AccountCache {
+    fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    fn get_slot(&self) -> ClientResult<Slot> {
+        self.inner.get_slot()
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.inner.get_latest_blockhash()
+    }
+
+    fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)> {
+        self.inner.get_latest_blockhash_with_height()
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        if let Some(Some(account)) = self.fresh(pubkey) {
+            return Ok(account);
+        }
+        if self.is_cached(pubkey) {
+            if let Some(account) = self.fetch(&[*pubkey], false)?.remove(0) {
+                return Ok(account);
+            }
+        }
+        // Uncached, or missing from the chain; the wrapped RpcApi gives the usual error for the latter
+        self.inner.get_account(pubkey)
+    }
+
+    fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        self.inner.get_multiple_accounts_with_commitment(pubkeys, commitment)
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.inner.simulate_transaction_with_config(transaction, config)
+    }
+
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        self.inner.get_fee_for_message(message)
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.inner.send_and_confirm_transaction(transaction)
+    }
+
+    fn send_and_confirm_transaction_timed(
+        &self,
+        transaction: &Transaction,
+        last_valid_block_height: u64,
+    ) -> ClientResult<(Signature, Duration, Backend)> {
+        self.inner.send_and_confirm_transaction_timed(transaction, last_valid_block_height)
+    }
+
+    fn send_and_confirm_bundle_timed(
+        &self,
+        transactions: &[Transaction],
+        last_valid_block_height: u64,
+    ) -> ClientResult<(Vec<Signature>, Duration)> {
+        self.inner.send_and_confirm_bundle_timed(transactions, last_valid_block_height)
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.inner.get_transaction_with_config(signature, config)
+    }
+
+    // Only the accounts without a fresh entry are fetched; stale entries are refreshed on the way
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        let cached: Vec<Option<Option<Account>>> = pubkeys.iter().map(|pubkey| self.fresh(pubkey)).collect();
+        let missing: Vec<Pubkey> = pubkeys
+            .iter()
+            .zip(&cached)
+            .filter(|(_, account)| account.is_none())
+            .map(|(pubkey, _)| *pubkey)
+            .collect();
+        if missing.is_empty() {
+            return Ok(cached.into_iter().flatten().collect());
+        }
+        let mut fetched = self.fetch(&missing, false)?.into_iter();
+        Ok(cached
+            .into_iter()
+            .map(|account| account.unwrap_or_else(|| fetched.next().flatten()))
+            .collect())
+    }
+}
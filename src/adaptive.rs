@@ -0,0 +1,142 @@
+use std::collections::{HashMap, VecDeque};
+use std::env;
+
+// Outcome of one execution attempt, as seen by the tuner
+#[derive(Debug, Clone, Copy)]
+pub struct Fill {
+    pub landed: bool,
+    pub expected_profit: i64,
+    pub realized_profit: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct TunerConfig {
+    pub window: usize,
+    pub step: f64,
+    pub min_threshold: f64,
+    pub max_threshold: f64,
+    pub min_landing_rate: f64,
+    // Realized/expected below this counts as adverse selection
+    pub adverse_ratio: f64,
+}
+
+impl TunerConfig {
+    pub fn from_env() -> Self {
+        let parse = |name: &str, default: f64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Self {
+            window: parse("ADAPTIVE_WINDOW", 20.0) as usize,
+            step: parse("ADAPTIVE_STEP", 0.1),
+            min_threshold: parse("ADAPTIVE_MIN_THRESHOLD", 0.1),
+            max_threshold: parse("ADAPTIVE_MAX_THRESHOLD", 3.0),
+            min_landing_rate: parse("ADAPTIVE_MIN_LANDING_RATE", 0.5),
+            adverse_ratio: parse("ADAPTIVE_ADVERSE_RATIO", 0.8),
+        }
+    }
+}
+
+// Per-pair min_profit_threshold that moves with each pair's recent fills
+pub struct ThresholdTuner {
+    config: TunerConfig,
+    base_threshold: f64,
+    thresholds: HashMap<String, f64>,
+    fills: HashMap<String, VecDeque<Fill>>,
+}
+
+impl ThresholdTuner {
+    pub fn new(config: TunerConfig, base_threshold: f64) -> Self {
+        Self {
+            config,
+            base_threshold,
+            thresholds: HashMap::new(),
+            fills: HashMap::new(),
+        }
+    }
+
+    pub fn threshold(&self, pair_key: &str) -> f64 {
+        self.thresholds.get(pair_key).copied().unwrap_or(self.base_threshold)
+    }
+
+    // Records a fill and, once the window is full, nudges the pair's threshold:
+    // fills at or above expectation tighten it, adverse selection or poor landing widens it
+    pub fn observe(&mut self, pair_key: &str, fill: Fill) -> f64 {
+        let fills = self.fills.entry(pair_key.to_string()).or_default();
+        fills.push_back(fill);
+        if fills.len() > self.config.window {
+            fills.pop_front();
+        }
+        let current = self.thresholds.get(pair_key).copied().unwrap_or(self.base_threshold);
+        if fills.len() < self.config.window {
+            return current;
+        }
+
+        let landed: Vec<&Fill> = fills.iter().filter(|f| f.landed).collect();
+        let landing_rate = landed.len() as f64 / fills.len() as f64;
+        let expected: i64 = landed.iter().map(|f| f.expected_profit).sum();
+        let realized: i64 = landed.iter().map(|f| f.realized_profit).sum();
+        let realization = if expected > 0 { realized as f64 / expected as f64 } else { 0.0 };
+
+        let next = if landing_rate < self.config.min_landing_rate || realization < self.config.adverse_ratio {
+            current * (1.0 + self.config.step)
+        } else if realization >= 1.0 {
+            current * (1.0 - self.config.step)
+        } else {
+            current
+        };
+        let next = next.clamp(self.config.min_threshold, self.config.max_threshold);
+
+        if next != current {
+            println!(
+                "Adjusted min profit threshold for {}: {:.3}% -> {:.3}% (landing {:.0}%, realized/expected {:.2})",
+                pair_key,
+                current,
+                next,
+                landing_rate * 100.0,
+                realization
+            );
+            // Start a fresh window so the next adjustment reflects the new threshold
+            fills.clear();
+        }
+        self.thresholds.insert(pair_key.to_string(), next);
+        next
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tuner() -> ThresholdTuner {
+        let config = TunerConfig {
+            window: 2,
+            step: 0.5,
+            min_threshold: 0.1,
+            max_threshold: 1.0,
+            min_landing_rate: 0.5,
+            adverse_ratio: 0.8,
+        };
+        ThresholdTuner::new(config, 0.4)
+    }
+
+    #[test]
+    fn threshold_follows_each_pairs_fills() {
+        let fill = |landed, realized_profit| Fill {
+            landed,
+            expected_profit: 100,
+            realized_profit,
+        };
+        let mut tuner = tuner();
+        // Nothing moves until the window is full
+        assert_eq!(tuner.observe("A/B", fill(true, 100)), 0.4);
+        assert!((tuner.observe("A/B", fill(true, 120)) - 0.2).abs() < 1e-9);
+        // Adverse selection widens it, as does failing to land, up to the limit
+        tuner.observe("A/B", fill(true, 50));
+        assert!((tuner.observe("A/B", fill(true, 60)) - 0.3).abs() < 1e-9);
+        tuner.observe("A/B", fill(false, 0));
+        assert!((tuner.observe("A/B", fill(false, 0)) - 0.45).abs() < 1e-9);
+        for _ in 0..10 {
+            tuner.observe("A/B", fill(false, 0));
+        }
+        assert_eq!(tuner.threshold("A/B"), 1.0);
+        assert_eq!(tuner.threshold("C/D"), 0.4);
+    }
+}
@@ -0,0 +1,99 @@
+use crate::pair_stats::PairAnalytics;
+use std::collections::BTreeMap;
+use std::env;
+use std::fmt::Write as _;
+
+// Splits a global notional cap across the pairs by what their journaled trades earned. A pair's
+// weight is its edge, the average net USD per executed trade, times its hit rate, the share of its
+// attempts that made money. Every pair keeps an exploration floor, so new pairs get traded enough to
+// be judged and faded ones can come back; the rest goes to the pairs with an edge. A pair's cap
+// never exceeds its own loan amount
+
+#[derive(Debug, Clone)]
+pub struct AllocationConfig {
+    // Lamports lent across all pairs at once
+    pub total_lamports: u64,
+    // Days of journal the pairs are judged on
+    pub lookback_days: u64,
+    // Share of the total each pair gets regardless of its record
+    pub exploration_floor: f64,
+    // Attempts before a pair's record counts
+    pub min_attempts: u64,
+}
+
+impl AllocationConfig {
+    // None unless ALLOCATION_TOTAL_LAMPORTS is set
+    pub fn from_env() -> Option<Self> {
+        let total_lamports = env::var("ALLOCATION_TOTAL_LAMPORTS").ok().filter(|v| !v.is_empty())?;
+        let parse = |name: &str, default: f64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Some(Self {
+            total_lamports: total_lamports.parse().expect("Invalid ALLOCATION_TOTAL_LAMPORTS"),
+            lookback_days: parse("ALLOCATION_LOOKBACK_DAYS", 7.0) as u64,
+            exploration_floor: parse("ALLOCATION_EXPLORATION_FLOOR", 0.05),
+            min_attempts: parse("ALLOCATION_MIN_ATTEMPTS", 10.0) as u64,
+        })
+    }
+}
+
+// Loan caps per pair key
+#[derive(Debug, Clone, Default)]
+pub struct Allocation {
+    pub caps: BTreeMap<String, u64>,
+    // What a pair added since the allocation gets
+    pub floor: u64,
+}
+
+impl Allocation {
+    pub fn cap(&self, pair_key: &str) -> u64 {
+        self.caps.get(pair_key).copied().unwrap_or(self.floor)
+    }
+
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for (pair, cap) in &self.caps {
+            let _ = writeln!(out, "{} {:.3} SOL", pair, *cap as f64 / 1_000_000_000.0);
+        }
+        out
+    }
+}
+
+// Caps for `pairs`, each given by its key and loan amount, from their performance over the lookback
+pub fn allocate(config: &AllocationConfig, pairs: &[(String, u64)], analytics: &PairAnalytics) -> Allocation {
+    if pairs.is_empty() {
+        return Allocation::default();
+    }
+    let total = config.total_lamports;
+    let floor = ((total as f64 * config.exploration_floor) as u64).min(total / pairs.len() as u64);
+    let mut caps: BTreeMap<String, u64> = pairs.iter().map(|(key, loan)| (key.clone(), floor.min(*loan))).collect();
+    let weights: BTreeMap<&String, f64> = pairs
+        .iter()
+        .filter_map(|(key, _)| {
+            let stats = analytics.get(key).filter(|stats| stats.attempts >= config.min_attempts)?;
+            let edge = stats.avg_realized_profit_usd()?;
+            let weight = edge.max(0.0) * stats.winning as f64 / stats.attempts as f64;
+            (weight > 0.0).then_some((key, weight))
+        })
+        .collect();
+
+    // Share out what's left by weight; whatever a pair can't take at its loan amount goes round again
+    let mut remaining = total.saturating_sub(caps.values().sum());
+    while remaining > 0 {
+        let open: Vec<(&String, f64, u64)> = pairs
+            .iter()
+            .filter_map(|(key, loan)| Some((key, *weights.get(key)?, loan.saturating_sub(caps[key]))))
+            .filter(|(_, _, room)| *room > 0)
+            .collect();
+        let weight_sum: f64 = open.iter().map(|(_, weight, _)| weight).sum();
+        let mut given = 0;
+        for (key, weight, room) in open {
+            let share = ((remaining as f64 * weight / weight_sum) as u64).min(room);
+            *caps.get_mut(key).unwrap() += share;
+            given += share;
+        }
+        if given == 0 {
+            break;
+        }
+        remaining -= given;
+    }
+    Allocation { caps, floor }
+}
@@ -1,408 +1,1408 @@
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Token, TokenAccount};
-use mango::*;
-use jupiter_core::*;
-use raydium_amm::*;
+use anchor_lang::solana_program::instruction::{AccountMeta, Instruction};
+use anchor_lang::solana_program::program::{invoke_signed, set_return_data};
+use anchor_lang::solana_program::sysvar::instructions::{self as instructions_sysvar, load_instruction_at_checked};
+use anchor_lang::system_program;
+use anchor_lang::Discriminator;
+use anchor_spl::associated_token::{self, AssociatedToken};
+use anchor_spl::token::spl_token::native_mint;
+use anchor_spl::token_2022::spl_token_2022::extension::transfer_fee::TransferFeeConfig;
+use anchor_spl::token_2022::spl_token_2022::extension::{BaseStateWithExtensions, StateWithExtensions};
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::{self as token, Mint, TokenAccount, TokenInterface};
 
 declare_id!("atXVy7bPRA1j81moNmmhhioKtAAu8XxzUDjN9L8ZUmW");
 
+// Entry points; the handlers live on ArbitrageContract below
 #[program]
 pub mod arbitrage_contract {
     use super::*;
 
-    pub struct ArbitrageContract {
-        pub mango_program: Program<Mango>,
-        pub jupiter_program: Program<Jupiter>,
-        pub raydium_program: Program<Raydium>,
-    }
-
-    #[derive(Accounts)]
-    pub struct Initialize {
-        #[account(mut)]
-        pub user: Signer<'info>,
-        pub system_program: Program<'info, System>,
-    }
-
-    #[derive(Accounts)]
-    pub struct TryArbitrage<'info> {
-        #[account(mut)]
-        pub user: Signer<'info>,
-        #[account(mut)]
-        pub token_a_account: Account<'info, TokenAccount>,
-        #[account(mut)]
-        pub token_b_account: Account<'info, TokenAccount>,
-        #[account(mut)]
-        pub mango_account: AccountInfo<'info>,
-        pub mango_program: Program<'info, Mango>,
-        pub jupiter_program: Program<'info, Jupiter>,
-        pub raydium_program: Program<'info, Raydium>,
-        pub token_program: Program<'info, Token>,
-        #[account(mut)]
-        pub profit_destination: Account<'info, TokenAccount>,
-    }
-
-    #[error_code]
-    pub enum ErrorCode {
-        #[msg("Error in calculation")]
-        CalculationError,
-        #[msg("Insufficient profit for arbitrage")]
-        InsufficientProfit,
-        #[msg("Invalid token account")]
-        InvalidTokenAccount,
-        #[msg("Slippage tolerance exceeded")]
-        SlippageExceeded,
-    }
-
-    impl ArbitrageContract {
-        pub fn try_arbitrage(ctx: Context<TryArbitrage>) -> Result<()> {
-            // 1. Get prices from both DEXes
-            let jupiter_price = Self::get_jupiter_price(&ctx.accounts)?;
-            let raydium_price = Self::get_raydium_price(&ctx.accounts)?;
-
-            // 2. Check if arbitrage is profitable (including fees)
-            if Self::is_profitable(jupiter_price, raydium_price, amount) {
-                // 3. Execute flash loan from Mango
-                
-                Self::execute_flash_loan(ctx.accounts, amount, token_a, |borrowed_sol| {
-                    // 1. Convert borrowed SOL to token_a using Jupiter
-                    let initial_token_amount = Self::swap_sol_to_token(
-                        borrowed_sol,
-                        token_a,
-                        &ctx.accounts.jupiter_program
-                    )?;
-
-                    // 2. Execute the arbitrage between token_a and token_b
-                    let profit_in_token = if jupiter_price > raydium_price {
-                        Self::swap_on_raydium(initial_token_amount, token_a, token_b)?;
-                        Self::swap_on_jupiter(initial_token_amount, token_b, token_a)?
-                    } else {
-                        Self::swap_on_jupiter(initial_token_amount, token_a, token_b)?;
-                        Self::swap_on_raydium(initial_token_amount, token_b, token_a)?
-                    };
-
-                    // 3. Convert profit back to SOL for loan repayment
-                    Self::swap_token_to_sol(
-                        profit_in_token,
-                        token_a,
-                        &ctx.accounts.jupiter_program
-                    )?;
-
-                    Ok(())
-                })?;
-            }
+    pub fn initialize_config(ctx: Context<InitializeConfig>, params: ConfigParams) -> Result<()> {
+        ArbitrageContract::initialize_config(ctx, params)
+    }
 
-            // After successful arbitrage, transfer profits
-            if profit > 0 {
-                // Transfer the profit to your wallet
-                token::transfer(
-                    CpiContext::new(
-                        ctx.accounts.token_program.to_account_info(),
-                        token::Transfer {
-                            from: ctx.accounts.token_a_account.to_account_info(),
-                            to: ctx.accounts.profit_destination.to_account_info(),
-                            authority: ctx.accounts.user.to_account_info(),
-                        },
-                    ),
-                    profit,
-                )?;
-            }
+    pub fn update_config(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+        ArbitrageContract::update_config(ctx, params)
+    }
 
-            Ok(())
-        }
+    pub fn initialize_whitelist(ctx: Context<InitializeWhitelist>, programs: Vec<Pubkey>, pools: Vec<Pubkey>) -> Result<()> {
+        ArbitrageContract::initialize_whitelist(ctx, programs, pools)
+    }
 
-        fn get_jupiter_price(accounts: &TryArbitrage) -> Result<u64> {
-            // Create a quote request to Jupiter
-            let quote_request = jupiter_core::QuoteRequest {
-                input_mint: token_a,
-                output_mint: token_b,
-                amount,
-                slippage_bps: 300, // 0.3% slippage
-                only_direct_routes: true, // For faster price checks
-            };
+    pub fn update_whitelist(ctx: Context<UpdateWhitelist>, programs: Vec<Pubkey>, pools: Vec<Pubkey>) -> Result<()> {
+        ArbitrageContract::update_whitelist(ctx, programs, pools)
+    }
 
-            // Get the quote from Jupiter
-            let quote = jupiter_core::quote(
-                &accounts.jupiter_program,
-                &quote_request,
-            )?;
+    pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
+        ArbitrageContract::migrate(ctx)
+    }
+
+    pub fn quote(ctx: Context<QuotePools>, amount: u64, fee_bps: [u16; 2]) -> Result<()> {
+        ArbitrageContract::quote(ctx, amount, fee_bps)
+    }
+
+    pub fn try_arbitrage<'info>(
+        ctx: Context<'_, '_, '_, 'info, TryArbitrage<'info>>,
+        amount: u64,
+        min_profit_lamports: u64,
+        expiry_slot: u64,
+        loan_provider: LoanProvider,
+        legs: Vec<RouteLeg>,
+    ) -> Result<()> {
+        ArbitrageContract::try_arbitrage(ctx, amount, min_profit_lamports, expiry_slot, loan_provider, legs)
+    }
+
+    pub fn try_arbitrage_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, TryArbitrage<'info>>,
+        amount: u64,
+        min_profit_lamports: u64,
+        expiry_slot: u64,
+        loan_provider: LoanProvider,
+        routes: Vec<BatchRoute>,
+    ) -> Result<()> {
+        ArbitrageContract::try_arbitrage_batch(ctx, amount, min_profit_lamports, expiry_slot, loan_provider, routes)
+    }
+}
+
+// Instruction handlers and the helpers they share
+pub struct ArbitrageContract;
+
+pub const CONFIG_SEED: &[u8] = b"config";
+// Authority of the vault token accounts that hold funds while a route runs
+pub const VAULT_SEED: &[u8] = b"vault";
+
+// Program-wide settings, owned by the admin; every trade is checked against them
+#[account]
+pub struct Config {
+    pub admin: Pubkey,
+    // The bot wallet allowed to execute trades
+    pub operator: Pubkey,
+    pub paused: bool,
+    pub max_trade_size: u64,
+    pub flash_loan_fee_bps: u16,
+    pub max_slippage_bps: u16,
+    // Owner of the token accounts that receive the profit share
+    pub fee_collector: Pubkey,
+    // Share of every trade's net profit paid to the fee collector
+    pub profit_fee_bps: u16,
+    // Lets Jupiter legs route through pools the whitelist doesn't list, as long as their AMM programs
+    // are; off, every pool account in a Jupiter route plan must be listed
+    pub allow_unlisted_jupiter_pools: bool,
+    // Set while a try_arbitrage is running, so it can't be entered again from inside itself
+    pub locked: bool,
+    pub bump: u8,
+    // Layout version, see STATE_VERSION
+    pub version: u8,
+    // Pyth feed every trade's minimum profit is priced with; the default key turns the check off (v2)
+    pub price_oracle: Pubkey,
+}
+
+impl Config {
+    // Size before the version field was added
+    pub const V0_LEN: usize = 32 + 32 + 1 + 8 + 2 + 2 + 32 + 2 + 1 + 1 + 1;
+    pub const LEN: usize = Self::V0_LEN + 1 + 32;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ConfigParams {
+    pub operator: Pubkey,
+    pub paused: bool,
+    pub max_trade_size: u64,
+    pub flash_loan_fee_bps: u16,
+    pub max_slippage_bps: u16,
+    pub fee_collector: Pubkey,
+    pub profit_fee_bps: u16,
+    pub allow_unlisted_jupiter_pools: bool,
+    pub price_oracle: Pubkey,
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(init, payer = admin, space = 8 + Config::LEN, seeds = [CONFIG_SEED], bump)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+pub const WHITELIST_SEED: &[u8] = b"whitelist";
+pub const MAX_WHITELISTED_PROGRAMS: usize = 16;
+pub const MAX_WHITELISTED_POOLS: usize = 64;
+
+// AMM programs and pools `try_arbitrage` may swap through, maintained by the admin
+#[account]
+pub struct Whitelist {
+    pub programs: Vec<Pubkey>,
+    pub pools: Vec<Pubkey>,
+    pub bump: u8,
+    // Layout version, see STATE_VERSION
+    pub version: u8,
+}
+
+impl Whitelist {
+    // Size before the version field was added
+    pub const V0_LEN: usize = 4 + 32 * MAX_WHITELISTED_PROGRAMS + 4 + 32 * MAX_WHITELISTED_POOLS + 1;
+    pub const LEN: usize = Self::V0_LEN + 1;
+}
+
+#[derive(Accounts)]
+pub struct InitializeWhitelist<'info> {
+    #[account(init, payer = admin, space = 8 + Whitelist::LEN, seeds = [WHITELIST_SEED], bump)]
+    pub whitelist: Account<'info, Whitelist>,
+    #[account(seeds = [CONFIG_SEED], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateWhitelist<'info> {
+    #[account(mut, seeds = [WHITELIST_SEED], bump = whitelist.bump)]
+    pub whitelist: Account<'info, Whitelist>,
+    #[account(seeds = [CONFIG_SEED], bump = config.bump, has_one = admin)]
+    pub config: Account<'info, Config>,
+    pub admin: Signer<'info>,
+}
+
+// Current layout version of the program's accounts. Bump it when a layout changes and add a step
+// to `migrate`; the version byte stays where v1 put it and new fields go after it, so `migrate` can
+// always read it: at the end of the v1 config, and right after the whitelist's bump, past its
+// entries. Accounts created before versioning have no version byte (v0)
+pub const STATE_VERSION: u8 = 2;
+
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    // Raw accounts: an outdated layout doesn't deserialize as the current one
+    #[account(mut, seeds = [CONFIG_SEED], bump, owner = crate::ID)]
+    pub config: AccountInfo<'info>,
+    #[account(mut, seeds = [WHITELIST_SEED], bump, owner = crate::ID)]
+    pub whitelist: AccountInfo<'info>,
+    // Pays the rent for any space the new layouts add
+    #[account(mut)]
+    pub admin: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+// Only the accounts every trade needs; each leg's venue accounts come from `remaining_accounts`
+pub struct TryArbitrage<'info> {
+    #[account(mut, seeds = [CONFIG_SEED], bump = config.bump)]
+    pub config: Account<'info, Config>,
+    #[account(seeds = [WHITELIST_SEED], bump = whitelist.bump)]
+    pub whitelist: Account<'info, Whitelist>,
+    #[account(mut)]
+    pub user: Signer<'info>,
+    // Token account of the borrowed token; the route starts and ends here. The loan is already in
+    // it: the bot wraps this instruction in marginfi's start_flashloan/borrow and repay/end_flashloan
+    #[account(mut)]
+    pub token_a_account: InterfaceAccount<'info, TokenAccount>,
+    // PDA owning every vault; it signs the swaps, so only this program can move vault funds
+    #[account(seeds = [VAULT_SEED], bump)]
+    pub vault_authority: AccountInfo<'info>,
+    // Vault for the borrowed token, the vault authority's associated token account: the loan moves
+    // here before the first leg and the route's output is paid back out of it after the last.
+    // For WSOL it is created by the instruction and closed again at the end
+    #[account(mut)]
+    pub vault: AccountInfo<'info>,
+    // Mint of the borrowed token, for checked transfers and its Token-2022 transfer fee
+    #[account(address = token_a_account.mint)]
+    pub token_a_mint: InterfaceAccount<'info, Mint>,
+    // Only needed for WSOL routes
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Program<'info, System>,
+    // SPL Token or Token-2022, whichever owns the borrowed mint
+    pub token_program: Interface<'info, TokenInterface>,
+    #[account(mut)]
+    pub profit_destination: InterfaceAccount<'info, TokenAccount>,
+    // The fee collector's account for the borrowed token
+    #[account(
+        mut,
+        token::mint = token_a_account.mint,
+        constraint = fee_collector_account.owner == config.fee_collector @ ErrorCode::InvalidFeeCollector
+    )]
+    pub fee_collector_account: InterfaceAccount<'info, TokenAccount>,
+    // Read for the transaction's compute unit limit
+    #[account(address = instructions_sysvar::ID)]
+    pub instructions_sysvar: AccountInfo<'info>,
+    // The config's Pyth feed, which the bot priced the trade's minimum profit with; required and
+    // checked for freshness whenever the config names one
+    pub price_oracle: Option<AccountInfo<'info>>,
+}
+
+// Token vaults of the two constant-product pools `quote` prices; base is the token sold, quote the token bought
+#[derive(Accounts)]
+pub struct QuotePools<'info> {
+    pub first_base_vault: InterfaceAccount<'info, TokenAccount>,
+    pub first_quote_vault: InterfaceAccount<'info, TokenAccount>,
+    pub second_base_vault: InterfaceAccount<'info, TokenAccount>,
+    pub second_quote_vault: InterfaceAccount<'info, TokenAccount>,
+}
+
+// One swap of a multi-leg route, as encoded by the off-chain bot
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct RouteLeg {
+    pub venue: u8,
+    pub input_mint: Pubkey,
+    pub output_mint: Pubkey,
+    pub min_amount_out: u64,
+    // This leg's slice of `remaining_accounts`, laid out as its venue expects
+    pub accounts_offset: u8,
+    pub accounts_len: u8,
+    // Venue instruction built off-chain; for Jupiter legs, the full `shared_accounts_route` data
+    pub venue_data: Vec<u8>,
+}
+
+// One route of a try_arbitrage_batch, run on `amount` of the batch's loan
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct BatchRoute {
+    pub amount: u64,
+    // Least this route alone must gain; zero for none
+    pub min_profit_lamports: u64,
+    pub legs: Vec<RouteLeg>,
+}
 
-            // Extract the output amount from the quote
-            let output_amount = quote.out_amount;
+// Outcome of a completed execution, reported by the callers' events
+pub struct Settlement {
+    pub start_mint: Pubkey,
+    pub amount_out: u64,
+    pub flash_loan_fee: u64,
+    pub dex_fees: u64,
+    pub net_profit: u64,
+    pub profit_fee: u64,
+    // Per route: amount in, amount out, venue fees
+    pub route_results: Vec<(u64, u64, u64)>,
+    pub slot: u64,
+}
+
+// Where the loan comes from
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum LoanProvider {
+    // Borrowed by marginfi instructions around this one, or not at all; the funds are already in
+    // the token account and repaid after this instruction
+    Marginfi,
+    // Borrowed and repaid by Solend flash loan instructions around this one, which Solend requires
+    // to be top-level; the reserve, whose fee the repayment adds, is at `accounts_offset` in
+    // `remaining_accounts`
+    Solend { accounts_offset: u8 },
+}
+
+// Emitted for every completed arbitrage; the off-chain journal takes its figures from here
+#[event]
+pub struct ArbExecuted {
+    pub token_a: Pubkey,
+    pub token_b: Pubkey,
+    // Mints traversed, starting and ending with the borrowed token
+    pub route: Vec<Pubkey>,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub flash_loan_fee: u64,
+    pub dex_fees: u64,
+    pub net_profit: u64,
+    // Part of net_profit paid to the fee collector
+    pub profit_fee: u64,
+    pub slot: u64,
+}
+
+// Aggregate figures of a try_arbitrage_batch; each route also gets its own ArbExecuted
+#[event]
+pub struct BatchExecuted {
+    pub token_a: Pubkey,
+    pub routes: u8,
+    pub loan_amount: u64,
+    pub amount_out: u64,
+    pub flash_loan_fee: u64,
+    pub dex_fees: u64,
+    pub net_profit: u64,
+    pub profit_fee: u64,
+    pub slot: u64,
+}
+
+pub const VENUE_JUPITER: u8 = 0;
+pub const VENUE_RAYDIUM: u8 = 1;
+pub const VENUE_RAYDIUM_CLMM: u8 = 2;
+pub const VENUE_WHIRLPOOL: u8 = 3;
+pub const MAX_ROUTE_LEGS: usize = 6;
+pub const MAX_BATCH_ROUTES: usize = 4;
+// Jupiter v6 program and the Anchor sighash of its `shared_accounts_route` instruction
+pub const JUPITER_V6_PROGRAM_ID: Pubkey = pubkey!("JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4");
+pub const JUPITER_SHARED_ACCOUNTS_ROUTE: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
+// Accounts `shared_accounts_route` takes before the route plan's own pool accounts
+pub const JUPITER_ROUTE_FIXED_ACCOUNTS: usize = 13;
+// Indexes of the transfer authority and the source and destination token accounts among them
+pub const JUPITER_TRANSFER_AUTHORITY_ACCOUNT: usize = 2;
+pub const JUPITER_SOURCE_ACCOUNT: usize = 3;
+pub const JUPITER_DESTINATION_ACCOUNT: usize = 6;
+pub const JUPITER_DESTINATION_MINT_ACCOUNT: usize = 8;
+// Trailing args after the route plan: in_amount u64, quoted_out_amount u64, slippage_bps u16, platform_fee_bps u8
+pub const JUPITER_ROUTE_TRAILER_LEN: usize = 19;
+// Raydium AMM v4 program and its `swap_base_in` instruction tag
+pub const RAYDIUM_AMM_PROGRAM_ID: Pubkey = pubkey!("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8");
+const RAYDIUM_SWAP_BASE_IN: u8 = 9;
+pub const RAYDIUM_LEG_ACCOUNTS: usize = 18;
+pub const RAYDIUM_SOURCE_ACCOUNT: usize = 15;
+pub const RAYDIUM_DESTINATION_ACCOUNT: usize = 16;
+pub const RAYDIUM_OUTPUT_MINT_ACCOUNT: usize = 17;
+// Raydium CLMM program and the Anchor sighash of its `swap_v2` instruction
+pub const RAYDIUM_CLMM_PROGRAM_ID: Pubkey = pubkey!("CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK");
+pub const RAYDIUM_CLMM_SWAP_V2: [u8; 8] = [43, 4, 237, 11, 26, 201, 30, 98];
+// Leg accounts before the tick arrays
+pub const RAYDIUM_CLMM_FIXED_ACCOUNTS: usize = 13;
+pub const RAYDIUM_CLMM_OUTPUT_MINT_ACCOUNT: usize = 12;
+// Orca Whirlpool program and the Anchor sighash of its `swap` instruction
+pub const WHIRLPOOL_PROGRAM_ID: Pubkey = pubkey!("whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc");
+pub const WHIRLPOOL_SWAP: [u8; 8] = [248, 198, 158, 145, 225, 117, 135, 200];
+pub const WHIRLPOOL_LEG_ACCOUNTS: usize = 12;
+pub const WHIRLPOOL_OUTPUT_MINT_ACCOUNT: usize = 11;
+// Whirlpool's sqrt price bounds, used as the limit so the swap is bounded only by its output check
+const WHIRLPOOL_MIN_SQRT_PRICE: u128 = 4_295_048_016;
+const WHIRLPOOL_MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_055;
+pub const SOLEND_PROGRAM_ID: Pubkey = pubkey!("So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo");
+// Loan accounts: [reserve]
+pub const SOLEND_LOAN_ACCOUNTS: usize = 1;
+// Offset of `config.fees.flash_loan_fee_wad` in Solend's reserve account
+const SOLEND_FLASH_LOAN_FEE_OFFSET: usize = 314;
+const WAD: u128 = 1_000_000_000_000_000_000;
+pub const COMPUTE_BUDGET_PROGRAM_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+// ComputeBudgetInstruction::SetComputeUnitLimit tag, followed by the limit as u32
+const SET_COMPUTE_UNIT_LIMIT: u8 = 2;
+// Runtime defaults when a transaction sets no limit
+const DEFAULT_INSTRUCTION_COMPUTE_UNITS: u64 = 200_000;
+const MAX_COMPUTE_UNITS: u64 = 1_400_000;
+// Least compute a route can finish in: the instruction's own work, Solend's borrow and repay, and
+// each leg by venue. Kept low so a limit calibrated from simulations is never refused; the check
+// only catches limits the route can't possibly fit in
+const BASE_COMPUTE_UNITS: u64 = 30_000;
+const SOLEND_COMPUTE_UNITS: u64 = 30_000;
+// Owner of every Pyth v2 price account
+pub const PYTH_PROGRAM_ID: Pubkey = pubkey!("FsJ3A3u2vn5cTVofAjvy6y5kwABJAqYWpe4975bi2epH");
+// Pyth v2 price account: magic and account type, then the aggregate price's status and publish slot
+const PYTH_MAGIC: u32 = 0xa1b2c3d4;
+const PYTH_PRICE_ACCOUNT_TYPE: u32 = 3;
+const PYTH_STATUS_OFFSET: usize = 224;
+const PYTH_PUB_SLOT_OFFSET: usize = 232;
+const PYTH_STATUS_TRADING: u32 = 1;
+// Oldest aggregate a trade may be priced with, about ten seconds
+pub const MAX_ORACLE_AGE_SLOTS: u64 = 25;
+// Upper bound on the slippage the bot may request for a swap (10%)
+pub const MAX_SLIPPAGE_BPS: u16 = 1000;
+
+#[error_code]
+pub enum ErrorCode {
+    #[msg("Error in calculation")]
+    CalculationError,
+    #[msg("Insufficient profit for arbitrage")]
+    InsufficientProfit,
+    #[msg("Invalid token account")]
+    InvalidTokenAccount,
+    #[msg("Slippage tolerance exceeded")]
+    SlippageExceeded,
+    #[msg("Route is not a valid cycle")]
+    InvalidRoute,
+    #[msg("Leg accounts do not match the venue")]
+    InvalidLegAccounts,
+    #[msg("Trading is paused")]
+    Paused,
+    #[msg("Signer is not the configured operator")]
+    Unauthorized,
+    #[msg("Trade size exceeds the configured cap")]
+    TradeTooLarge,
+    #[msg("Invalid config parameters")]
+    InvalidConfig,
+    #[msg("Loan accounts do not match the provider")]
+    InvalidLoanAccounts,
+    #[msg("Transaction landed after its expiry slot")]
+    Expired,
+    #[msg("Fee collector account is not owned by the configured collector")]
+    InvalidFeeCollector,
+    #[msg("Program or pool is not whitelisted")]
+    NotWhitelisted,
+    #[msg("Whitelist is full")]
+    WhitelistFull,
+    #[msg("try_arbitrage is already running")]
+    Locked,
+    #[msg("Account layout is outdated; run migrate")]
+    OutdatedAccount,
+    #[msg("Flash loan did not deliver the borrowed amount")]
+    FlashBorrowFailed,
+    #[msg("Route returned less than the loan and its fee")]
+    RepayShortfall,
+    // One slippage error per leg position, so a revert names the hop that fell short
+    #[msg("Leg 1 output below its minimum")]
+    Leg1SlippageExceeded,
+    #[msg("Leg 2 output below its minimum")]
+    Leg2SlippageExceeded,
+    #[msg("Leg 3 output below its minimum")]
+    Leg3SlippageExceeded,
+    #[msg("Leg 4 output below its minimum")]
+    Leg4SlippageExceeded,
+    #[msg("Leg 5 output below its minimum")]
+    Leg5SlippageExceeded,
+    #[msg("Leg 6 output below its minimum")]
+    Leg6SlippageExceeded,
+    #[msg("Oracle price is stale")]
+    OracleStale,
+    #[msg("A batched route returned less than its minimum profit")]
+    RouteUnprofitable,
+    #[msg("Compute unit limit is too low for the route")]
+    InsufficientComputeBudget,
+}
+
+impl ArbitrageContract {
+    pub fn initialize_config(ctx: Context<InitializeConfig>, params: ConfigParams) -> Result<()> {
+        let config = &mut ctx.accounts.config;
+        config.admin = ctx.accounts.admin.key();
+        config.bump = ctx.bumps.config;
+        config.version = STATE_VERSION;
+        Self::apply_config(config, params)
+    }
+
+    // Output of selling `amount` of the base token into each pool, from the pools' current reserves.
+    // Read-only, meant to be simulated. Return data: first pool's output (u64 LE), then the
+    // second's (u64 LE)
+    pub fn quote(ctx: Context<QuotePools>, amount: u64, fee_bps: [u16; 2]) -> Result<()> {
+        let pools = &ctx.accounts;
+        require_keys_eq!(pools.first_base_vault.mint, pools.second_base_vault.mint, ErrorCode::InvalidTokenAccount);
+        require_keys_eq!(pools.first_quote_vault.mint, pools.second_quote_vault.mint, ErrorCode::InvalidTokenAccount);
 
-            // Calculate the effective price (output amount per input token)
-            let price = (output_amount)
-                .checked_mul(PRICE_PRECISION)
-                .ok_or(ErrorCode::CalculationError)?
-                .checked_div(amount)
-                .ok_or(ErrorCode::CalculationError)?;
+        let first = Self::constant_product_out(amount, pools.first_base_vault.amount, pools.first_quote_vault.amount, fee_bps[0])?;
+        let second =
+            Self::constant_product_out(amount, pools.second_base_vault.amount, pools.second_quote_vault.amount, fee_bps[1])?;
 
-            Ok(price)
+        let mut data = Vec::with_capacity(16);
+        data.extend_from_slice(&first.to_le_bytes());
+        data.extend_from_slice(&second.to_le_bytes());
+        set_return_data(&data);
+        Ok(())
+    }
+
+    // x * y = k output for `amount_in` after the pool's fee
+    fn constant_product_out(amount_in: u64, reserve_in: u64, reserve_out: u64, fee_bps: u16) -> Result<u64> {
+        require!(fee_bps < 10_000, ErrorCode::CalculationError);
+        let amount_in = amount_in as u128 * (10_000 - fee_bps as u128) / 10_000;
+        let denominator = reserve_in as u128 + amount_in;
+        require!(denominator > 0, ErrorCode::CalculationError);
+        u64::try_from(amount_in * reserve_out as u128 / denominator).map_err(|_| error!(ErrorCode::CalculationError))
+    }
+
+    pub fn update_config(ctx: Context<UpdateConfig>, params: ConfigParams) -> Result<()> {
+        Self::apply_config(&mut ctx.accounts.config, params)
+    }
+
+    pub fn initialize_whitelist(ctx: Context<InitializeWhitelist>, programs: Vec<Pubkey>, pools: Vec<Pubkey>) -> Result<()> {
+        let whitelist = &mut ctx.accounts.whitelist;
+        whitelist.bump = ctx.bumps.whitelist;
+        whitelist.version = STATE_VERSION;
+        Self::apply_whitelist(whitelist, programs, pools)
+    }
+
+    // Replaces both lists; the admin sends the full set each time
+    pub fn update_whitelist(ctx: Context<UpdateWhitelist>, programs: Vec<Pubkey>, pools: Vec<Pubkey>) -> Result<()> {
+        Self::apply_whitelist(&mut ctx.accounts.whitelist, programs, pools)
+    }
+
+    // Upgrades the config and whitelist to the current layout, one version at a time. Safe to call
+    // again: accounts already at STATE_VERSION are left alone
+    pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
+        let accounts = &ctx.accounts;
+        // The admin is the config's first field in every version
+        let admin = {
+            let data = accounts.config.try_borrow_data()?;
+            require!(data.len() >= 8 + Config::V0_LEN, ErrorCode::OutdatedAccount);
+            require!(data[..8] == Config::DISCRIMINATOR, ErrorCode::InvalidConfig);
+            Pubkey::new_from_array(data[8..40].try_into().unwrap())
+        };
+        require_keys_eq!(admin, accounts.admin.key(), ErrorCode::Unauthorized);
+        let whitelist_version_offset = {
+            let data = accounts.whitelist.try_borrow_data()?;
+            require!(data.len() >= 8 + Whitelist::V0_LEN, ErrorCode::OutdatedAccount);
+            require!(data[..8] == Whitelist::DISCRIMINATOR, ErrorCode::InvalidConfig);
+            Self::whitelist_version_offset(&data)?
+        };
+
+        Self::migrate_account(accounts, &accounts.config, 8 + Config::V0_LEN, Config::LEN)?;
+        Self::migrate_account(accounts, &accounts.whitelist, whitelist_version_offset, Whitelist::LEN)
+    }
+
+    // The whitelist's lists are serialized with their length, so its bump and version byte move
+    // with the number of entries
+    fn whitelist_version_offset(data: &[u8]) -> Result<usize> {
+        let entries = |offset: usize, max: usize| -> Result<usize> {
+            let len = u32::from_le_bytes(data[offset..offset + 4].try_into().unwrap()) as usize;
+            require!(len <= max, ErrorCode::InvalidConfig);
+            Ok(len)
+        };
+        let pools_offset = 8 + 4 + 32 * entries(8, MAX_WHITELISTED_PROGRAMS)?;
+        let bump_offset = pools_offset + 4 + 32 * entries(pools_offset, MAX_WHITELISTED_POOLS)?;
+        Ok(bump_offset + 1)
+    }
+
+    fn migrate_account<'info>(
+        accounts: &Migrate<'info>,
+        account: &AccountInfo<'info>,
+        version_offset: usize,
+        len: usize,
+    ) -> Result<()> {
+        let mut version = match account.try_borrow_data()?.get(version_offset) {
+            Some(&version) => version,
+            None => 0,
+        };
+        require!(version <= STATE_VERSION, ErrorCode::InvalidConfig);
+        if version == STATE_VERSION {
+            return Ok(());
         }
 
-        fn get_raydium_price(accounts: &TryArbitrage) -> Result<u64> {
-            // Get the pool state for the token pair
-            let pool = raydium_amm::state::AmmInfo::load(
-                &accounts.raydium_program,
-                token_a,
-                token_b,
+        // Grow to the current layout, topping up rent from the admin
+        let rent = Rent::get()?.minimum_balance(8 + len);
+        if account.lamports() < rent {
+            system_program::transfer(
+                CpiContext::new(
+                    accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: accounts.admin.to_account_info(),
+                        to: account.clone(),
+                    },
+                ),
+                rent - account.lamports(),
             )?;
-
-            // Get the current reserves from the pool
-            let (reserve_a, reserve_b) = (
-                pool.token_a_reserve,
-                pool.token_b_reserve,
-            );
-
-            // Calculate the output amount using the constant product formula (x * y = k)
-            // new_reserve_a = reserve_a + amount
-            // new_reserve_b = k / new_reserve_a
-            // output_amount = reserve_b - new_reserve_b
-            let k = reserve_a
-                .checked_mul(reserve_b)
-                .ok_or(ErrorCode::CalculationError)?;
-            
-            let new_reserve_a = reserve_a
-                .checked_add(amount)
-                .ok_or(ErrorCode::CalculationError)?;
-            
-            let new_reserve_b = k
-                .checked_div(new_reserve_a)
-                .ok_or(ErrorCode::CalculationError)?;
-            
-            let output_amount = reserve_b
-                .checked_sub(new_reserve_b)
-                .ok_or(ErrorCode::CalculationError)?;
-
-            // Apply Raydium's fee (0.25% typical fee)
-            let fee_numerator = 25;
-            let fee_denominator = 10000;
-            let output_after_fees = output_amount
-                .checked_mul(fee_denominator - fee_numerator)
-                .ok_or(ErrorCode::CalculationError)?
-                .checked_div(fee_denominator)
-                .ok_or(ErrorCode::CalculationError)?;
-
-            // Calculate the effective price (output amount per input token)
-            let price = output_after_fees
-                .checked_mul(PRICE_PRECISION)
-                .ok_or(ErrorCode::CalculationError)?
-                .checked_div(amount)
-                .ok_or(ErrorCode::CalculationError)?;
-
-            Ok(price)
+        }
+        if account.data_len() < 8 + len {
+            account.realloc(8 + len, true)?;
         }
 
-        fn is_profitable(price_a: u64, price_b: u64, amount: u64) -> bool {
-            // Updated to account for additional Jupiter swap fees
-            let mango_fee = Self::calculate_mango_fee(amount);
-            let dex_fees = Self::calculate_dex_fees(amount);
-            let jupiter_conversion_fees = Self::calculate_jupiter_conversion_fees(amount);
-            let gas_cost = Self::estimate_gas_cost();
-            
-            let potential_profit = (price_a.max(price_b) - price_a.min(price_b)) * amount;
-            potential_profit > (mango_fee + dex_fees + jupiter_conversion_fees + gas_cost)
+        while version < STATE_VERSION {
+            match version {
+                // v1 only appends the version byte, zero-filled by the realloc
+                0 => {}
+                // v2 appends the config's price_oracle, zero-filled (no oracle) by the realloc; the
+                // whitelist is unchanged
+                1 => {}
+                _ => return err!(ErrorCode::InvalidConfig),
+            }
+            version += 1;
         }
+        account.try_borrow_mut_data()?[version_offset] = STATE_VERSION;
+        Ok(())
+    }
+
+    fn apply_whitelist(whitelist: &mut Whitelist, programs: Vec<Pubkey>, pools: Vec<Pubkey>) -> Result<()> {
+        require!(programs.len() <= MAX_WHITELISTED_PROGRAMS, ErrorCode::WhitelistFull);
+        require!(pools.len() <= MAX_WHITELISTED_POOLS, ErrorCode::WhitelistFull);
+        whitelist.programs = programs;
+        whitelist.pools = pools;
+        Ok(())
+    }
 
-        fn calculate_mango_fee(amount: u64) -> u64 {
-            // Mango flash loan fee is typically 0.2%
-            amount
-                .checked_mul(20)
-                .unwrap_or(0)
-                .checked_div(10000)
-                .unwrap_or(0)
+    fn apply_config(config: &mut Config, params: ConfigParams) -> Result<()> {
+        require!(params.max_slippage_bps <= MAX_SLIPPAGE_BPS, ErrorCode::InvalidConfig);
+        require!(params.flash_loan_fee_bps <= 10_000, ErrorCode::InvalidConfig);
+        require!(params.profit_fee_bps <= 10_000, ErrorCode::InvalidConfig);
+        config.operator = params.operator;
+        config.paused = params.paused;
+        config.max_trade_size = params.max_trade_size;
+        config.flash_loan_fee_bps = params.flash_loan_fee_bps;
+        config.max_slippage_bps = params.max_slippage_bps;
+        config.fee_collector = params.fee_collector;
+        config.profit_fee_bps = params.profit_fee_bps;
+        config.allow_unlisted_jupiter_pools = params.allow_unlisted_jupiter_pools;
+        config.price_oracle = params.price_oracle;
+        Ok(())
+    }
+
+    // Rejects trades while paused, from anyone but the operator, above the size cap, or before
+    // the accounts have been migrated
+    fn check_config(accounts: &TryArbitrage, amount: u64) -> Result<()> {
+        let config = &accounts.config;
+        require!(config.version == STATE_VERSION, ErrorCode::OutdatedAccount);
+        require!(accounts.whitelist.version == STATE_VERSION, ErrorCode::OutdatedAccount);
+        require!(!config.paused, ErrorCode::Paused);
+        require!(!config.locked, ErrorCode::Locked);
+        require_keys_eq!(accounts.user.key(), config.operator, ErrorCode::Unauthorized);
+        require!(amount <= config.max_trade_size, ErrorCode::TradeTooLarge);
+        Ok(())
+    }
+
+    // Runs the legs in order on `amount` of the first leg's input token, borrowed from the chosen
+    // provider. The bot composes the route, so any cycle over the supported venues can be executed
+    pub fn try_arbitrage<'info>(
+        ctx: Context<'_, '_, '_, 'info, TryArbitrage<'info>>,
+        amount: u64,
+        min_profit_lamports: u64,
+        expiry_slot: u64,
+        loan_provider: LoanProvider,
+        legs: Vec<RouteLeg>,
+    ) -> Result<()> {
+        let route = BatchRoute {
+            amount,
+            min_profit_lamports: 0,
+            legs,
+        };
+        let settlement = Self::execute_routes(ctx, amount, min_profit_lamports, expiry_slot, loan_provider, std::slice::from_ref(&route))?;
+
+        let mut mints: Vec<Pubkey> = route.legs.iter().map(|leg| leg.input_mint).collect();
+        mints.push(settlement.start_mint);
+        emit!(ArbExecuted {
+            token_a: settlement.start_mint,
+            token_b: route.legs[0].output_mint,
+            route: mints,
+            amount_in: amount,
+            amount_out: settlement.amount_out,
+            flash_loan_fee: settlement.flash_loan_fee,
+            dex_fees: settlement.dex_fees,
+            net_profit: settlement.net_profit,
+            profit_fee: settlement.profit_fee,
+            slot: settlement.slot,
+        });
+        Ok(())
+    }
+
+    // Several independent cycles over the same borrowed token, run one after another on a single
+    // loan of `amount`, so small opportunities share the transaction and loan costs. Each route
+    // must return its own `min_profit_lamports` and the batch as a whole `min_profit_lamports`;
+    // the loan must cover the largest route
+    pub fn try_arbitrage_batch<'info>(
+        ctx: Context<'_, '_, '_, 'info, TryArbitrage<'info>>,
+        amount: u64,
+        min_profit_lamports: u64,
+        expiry_slot: u64,
+        loan_provider: LoanProvider,
+        routes: Vec<BatchRoute>,
+    ) -> Result<()> {
+        require!(!routes.is_empty() && routes.len() <= MAX_BATCH_ROUTES, ErrorCode::InvalidRoute);
+        let settlement = Self::execute_routes(ctx, amount, min_profit_lamports, expiry_slot, loan_provider, &routes)?;
+
+        // One event per route with its own figures; the loan and profit fees are only known for
+        // the batch as a whole and are reported once, in BatchExecuted
+        for (route, (amount_in, amount_out, dex_fees)) in routes.iter().zip(&settlement.route_results) {
+            let mut mints: Vec<Pubkey> = route.legs.iter().map(|leg| leg.input_mint).collect();
+            mints.push(settlement.start_mint);
+            emit!(ArbExecuted {
+                token_a: settlement.start_mint,
+                token_b: route.legs[0].output_mint,
+                route: mints,
+                amount_in: *amount_in,
+                amount_out: *amount_out,
+                flash_loan_fee: 0,
+                dex_fees: *dex_fees,
+                net_profit: amount_out.saturating_sub(*amount_in),
+                profit_fee: 0,
+                slot: settlement.slot,
+            });
         }
+        emit!(BatchExecuted {
+            token_a: settlement.start_mint,
+            routes: routes.len() as u8,
+            loan_amount: amount,
+            amount_out: settlement.amount_out,
+            flash_loan_fee: settlement.flash_loan_fee,
+            dex_fees: settlement.dex_fees,
+            net_profit: settlement.net_profit,
+            profit_fee: settlement.profit_fee,
+            slot: settlement.slot,
+        });
+        Ok(())
+    }
+
+    // Shared body of try_arbitrage and try_arbitrage_batch: borrows `amount`, runs every route
+    // through the vault, repays the loan and pays out the profit
+    fn execute_routes<'info>(
+        ctx: Context<'_, '_, '_, 'info, TryArbitrage<'info>>,
+        amount: u64,
+        min_profit_lamports: u64,
+        expiry_slot: u64,
+        loan_provider: LoanProvider,
+        routes: &[BatchRoute],
+    ) -> Result<Settlement> {
+        Self::check_config(ctx.accounts, amount)?;
+
+        // Quotes are only good for a few slots; a transaction stuck in the queue must not trade
+        // against a market that has moved since
+        let slot = Clock::get()?.slot;
+        require!(slot <= expiry_slot, ErrorCode::Expired);
+        Self::check_oracle(&ctx.accounts.config, ctx.accounts.price_oracle.as_ref(), slot)?;
+
+        // Anchor only writes accounts back when the instruction returns, so the lock is written
+        // out right away for a nested invocation to see it
+        ctx.accounts.config.locked = true;
+        ctx.accounts.config.exit(&crate::ID)?;
 
-        fn calculate_dex_fees(amount: u64) -> u64 {
-            // Jupiter fee: 0.3%
-            let jupiter_fee = amount
-                .checked_mul(30)
-                .unwrap_or(0)
-                .checked_div(10000)
-                .unwrap_or(0);
-            
-            // Raydium fee: 0.25%
-            let raydium_fee = amount
-                .checked_mul(25)
-                .unwrap_or(0)
-                .checked_div(10000)
-                .unwrap_or(0);
-            
-            // Return total fees for both swaps
-            jupiter_fee.checked_add(raydium_fee).unwrap_or(0)
+        // Every route must be a cycle starting and ending in the borrowed token
+        let start_mint = routes[0].legs.first().ok_or_else(|| error!(ErrorCode::InvalidRoute))?.input_mint;
+        for route in routes {
+            let legs = &route.legs;
+            require!(legs.len() >= 2 && legs.len() <= MAX_ROUTE_LEGS, ErrorCode::InvalidRoute);
+            for pair in legs.windows(2) {
+                require!(pair[0].output_mint == pair[1].input_mint, ErrorCode::InvalidRoute);
+            }
+            require!(legs[0].input_mint == start_mint, ErrorCode::InvalidRoute);
+            require!(legs[legs.len() - 1].output_mint == start_mint, ErrorCode::InvalidRoute);
+            require!(route.amount <= amount, ErrorCode::InvalidRoute);
         }
 
-        fn estimate_gas_cost() -> u64 {
-            // Estimate gas cost in lamports
-            // Flash loan + 2 swaps typically costs around 0.01 SOL
-            // 1 SOL = 1_000_000_000 lamports
-            // 0.01 SOL = 10_000_000 lamports
-            10_000_000
+        // Refuse a transaction whose compute limit can't cover the routes before anything is
+        // borrowed, instead of running out of compute mid-route with an opaque meter error
+        let mut required_units = BASE_COMPUTE_UNITS;
+        if matches!(loan_provider, LoanProvider::Solend { .. }) {
+            required_units += SOLEND_COMPUTE_UNITS;
+        }
+        for route in routes {
+            required_units += route.legs.iter().map(|leg| Self::leg_compute_units(leg.venue)).sum::<u64>();
         }
+        require!(
+            Self::compute_unit_limit(&ctx.accounts.instructions_sysvar)? >= required_units,
+            ErrorCode::InsufficientComputeBudget
+        );
 
-        fn execute_flash_loan<F>(accounts: &TryArbitrage, amount: u64, token: Pubkey, operation: F) -> Result<()>
-        where F: FnOnce(u64) -> Result<()> {
-            // Implement Mango flash loan logic
-            // 1. Borrow funds from Mango
-            // 2. Pass the borrowed amount to the operation function
-            // 3. Repay the loan with fees
-            let borrowed_funds = amount; // Placeholder for borrowed funds
-
-            operation(borrowed_funds)?;
-
-            // Repay the loan - placeholder logic
-            let repay_amount = amount + Self::calculate_mango_fee(amount);
-            token::transfer(
-                CpiContext::new(accounts.token_program.to_account_info(), token::Transfer {
-                    from: accounts.token_a_account.to_account_info(),
-                    to: accounts.mango_account.to_account_info(),
-                    authority: accounts.user.to_account_info(),
-                }),
-                repay_amount,
-            )?;
+        let balance_before = ctx.accounts.token_a_account.amount;
+        let reserve = match loan_provider {
+            LoanProvider::Marginfi => {
+                // WSOL routes may top up from plain SOL below; anything else must already hold the loan
+                require!(start_mint == native_mint::ID || balance_before >= amount, ErrorCode::FlashBorrowFailed);
+                None
+            }
+            LoanProvider::Solend { accounts_offset } => {
+                // The borrow instruction before this one must have delivered the loan
+                require!(balance_before >= amount, ErrorCode::FlashBorrowFailed);
+                Some(Self::solend_reserve(ctx.remaining_accounts, accounts_offset)?)
+            }
+        };
 
-            Ok(())
+        // Intermediate funds only ever sit in program-owned vaults, so no other instruction in the
+        // transaction can take them mid-route
+        let vault_bump = ctx.bumps.vault_authority;
+        let vault_seeds: &[&[u8]] = &[VAULT_SEED, &[vault_bump]];
+        let is_wsol = start_mint == native_mint::ID;
+        if is_wsol {
+            Self::open_wsol_vault(ctx.accounts)?;
         }
+        Self::require_vault(&ctx.accounts.vault, &ctx.accounts.vault_authority.key())?;
+        require_keys_eq!(
+            Self::token_mint(&ctx.accounts.vault)?,
+            ctx.accounts.token_a_account.mint,
+            ErrorCode::InvalidTokenAccount
+        );
+        let vault_before = Self::token_balance(&ctx.accounts.vault)?;
 
-        fn swap_on_jupiter(amount: u64, token_a: Pubkey, token_b: Pubkey) -> Result<()> {
-            // Create swap instruction
-            let swap_instruction = jupiter_core::SwapInstruction {
-                input_mint: token_a,
-                output_mint: token_b,
-                amount,
-                slippage_bps: 300, // 0.01% slippage tolerance
-                platform_fee_bps: 0, // No additional platform fee
-            };
-
-            // Execute the swap through Jupiter's CPI
-            jupiter_core::swap(
+        // A WSOL route may be funded partly with plain SOL from the wallet: the shortfall is sent as
+        // lamports and synced into the vault's token balance
+        let from_token_account = if is_wsol { amount.min(ctx.accounts.token_a_account.amount) } else { amount };
+        let wrapped_lamports = amount - from_token_account;
+        Self::transfer_checked(
+            ctx.accounts,
+            ctx.accounts.token_a_account.to_account_info(),
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.user.to_account_info(),
+            from_token_account,
+            &[],
+        )?;
+        if wrapped_lamports > 0 {
+            system_program::transfer(
                 CpiContext::new(
-                    ctx.accounts.jupiter_program.to_account_info(),
-                    jupiter_core::Swap {
-                        user: ctx.accounts.user.to_account_info(),
-                        user_token_account_a: ctx.accounts.token_a_account.to_account_info(),
-                        user_token_account_b: ctx.accounts.token_b_account.to_account_info(),
-                        token_program: ctx.accounts.token_program.to_account_info(),
+                    ctx.accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: ctx.accounts.user.to_account_info(),
+                        to: ctx.accounts.vault.to_account_info(),
                     },
                 ),
-                swap_instruction,
+                wrapped_lamports,
             )?;
+            token::sync_native(CpiContext::new(
+                ctx.accounts.token_program.to_account_info(),
+                token::SyncNative {
+                    account: ctx.accounts.vault.to_account_info(),
+                },
+            ))?;
+        }
+
+        let mut dex_fees = 0;
+        // A Token-2022 transfer fee may have been withheld on the way in
+        let mut vault_funds = Self::token_balance(&ctx.accounts.vault)?.saturating_sub(vault_before);
+        let mut route_results = Vec::with_capacity(routes.len());
+
+        for route in routes {
+            let route_in = route.amount.min(vault_funds);
+            let (route_out, route_fees) =
+                Self::run_legs(ctx.accounts, ctx.remaining_accounts, &route.legs, route_in, vault_seeds)?;
+            // A batched route must pay for itself; zero means the route has no minimum of its own
+            if route.min_profit_lamports > 0 {
+                require!(
+                    route_out >= route_in.saturating_add(route.min_profit_lamports),
+                    ErrorCode::RouteUnprofitable
+                );
+            }
+            vault_funds = vault_funds - route_in + route_out;
+            dex_fees += route_fees;
+            route_results.push((route_in, route_out, route_fees));
+        }
+        let leg_amount = vault_funds;
 
-            Ok(())
+        // Pay everything the route returned to the vault back to the token account, for the
+        // loan repayment and the profit transfer
+        let returned = Self::token_balance(&ctx.accounts.vault)?.saturating_sub(vault_before);
+        Self::transfer_checked(
+            ctx.accounts,
+            ctx.accounts.vault.to_account_info(),
+            ctx.accounts.token_a_account.to_account_info(),
+            ctx.accounts.vault_authority.to_account_info(),
+            returned,
+            &[vault_seeds],
+        )?;
+        if is_wsol {
+            // The WSOL vault only lives for this instruction; its rent goes back to the wallet
+            token::close_account(CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::CloseAccount {
+                    account: ctx.accounts.vault.to_account_info(),
+                    destination: ctx.accounts.user.to_account_info(),
+                    authority: ctx.accounts.vault_authority.to_account_info(),
+                },
+                &[vault_seeds],
+            ))?;
         }
 
-        fn swap_on_raydium(amount: u64, token_a: Pubkey, token_b: Pubkey) -> Result<()> {
-            // Get pool state and authority
-            let pool = raydium_amm::state::AmmInfo::load(
-                &ctx.accounts.raydium_program,
-                token_a,
-                token_b,
+        let amount_out = leg_amount;
+        let flash_loan_fee = match reserve {
+            Some(reserve) => Self::solend_flash_loan_fee(reserve, amount)?,
+            None => Self::calculate_flash_loan_fee(amount, ctx.accounts.config.flash_loan_fee_bps),
+        };
+        // The cycle must at least cover the loan and its fee
+        require!(amount_out >= amount.saturating_add(flash_loan_fee), ErrorCode::RepayShortfall);
+
+        // The loan and its fee are repaid after this instruction, so that fee is still owed
+        let fee_owed = flash_loan_fee;
+
+        // Profit is the actual balance change of the token account across the whole instruction,
+        // not the sum of the legs' reported outputs, less any loan fee still owed;
+        // anything short of the caller's minimum reverts the whole transaction
+        ctx.accounts.token_a_account.reload()?;
+        let profit = ctx
+            .accounts
+            .token_a_account
+            .amount
+            .saturating_sub(balance_before)
+            .saturating_sub(wrapped_lamports)
+            .saturating_sub(fee_owed);
+        // Paying the profit out costs the mint's transfer fee too, so the minimum applies after it
+        let payout_fee = Self::transfer_fee(&ctx.accounts.token_a_mint.to_account_info(), profit)?;
+        require!(profit.saturating_sub(payout_fee) >= min_profit_lamports, ErrorCode::InsufficientProfit);
+
+        let profit_fee = Self::calculate_profit_fee(profit, ctx.accounts.config.profit_fee_bps)?;
+
+        // After successful arbitrage, transfer profits; the loan and its fee stay for the repay
+        if profit_fee > 0 {
+            // The collector's share comes off the top
+            Self::transfer_checked(
+                ctx.accounts,
+                ctx.accounts.token_a_account.to_account_info(),
+                ctx.accounts.fee_collector_account.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                profit_fee,
+                &[],
             )?;
-            
-            let pool_authority = Pubkey::find_program_address(
-                &[pool.to_account_info().key.as_ref()],
-                ctx.accounts.raydium_program.key,
-            ).0;
-
-            // Create swap instruction
-            let swap_instruction = raydium_amm::instruction::Swap {
-                amount_in: amount,
-                minimum_amount_out: amount
-                    .checked_mul(995) // 0.5% slippage
-                    .ok_or(ErrorCode::CalculationError)?
-                    .checked_div(1000)
-                    .ok_or(ErrorCode::CalculationError)?,
+        }
+        if profit > profit_fee {
+            // Transfer the profit to your wallet
+            Self::transfer_checked(
+                ctx.accounts,
+                ctx.accounts.token_a_account.to_account_info(),
+                ctx.accounts.profit_destination.to_account_info(),
+                ctx.accounts.user.to_account_info(),
+                profit - profit_fee,
+                &[],
+            )?;
+        }
+
+        ctx.accounts.config.locked = false;
+        Ok(Settlement {
+            start_mint,
+            amount_out,
+            flash_loan_fee,
+            dex_fees,
+            net_profit: profit.saturating_sub(payout_fee),
+            profit_fee,
+            route_results,
+            slot,
+        })
+    }
+
+    // Runs one route's legs on `amount` already in the vault; returns what the last leg delivered
+    // and the venue fees paid on the way
+    fn run_legs<'info>(
+        accounts: &TryArbitrage<'info>,
+        remaining_accounts: &[AccountInfo<'info>],
+        legs: &[RouteLeg],
+        amount: u64,
+        vault_seeds: &[&[u8]],
+    ) -> Result<(u64, u64)> {
+        let mut dex_fees = 0;
+        let mut leg_amount = amount;
+        for (index, leg) in legs.iter().enumerate() {
+            let leg_accounts = Self::leg_accounts(remaining_accounts, leg)?;
+            dex_fees += Self::venue_fee(leg_amount, leg.venue);
+            leg_amount = match leg.venue {
+                VENUE_JUPITER => Self::swap_on_jupiter(accounts, leg_accounts, &leg.venue_data, leg_amount, vault_seeds)?,
+                VENUE_RAYDIUM => Self::swap_on_raydium(accounts, leg_accounts, leg, leg_amount, vault_seeds)?,
+                VENUE_RAYDIUM_CLMM => Self::swap_on_raydium_clmm(accounts, leg_accounts, leg_amount, vault_seeds)?,
+                VENUE_WHIRLPOOL => Self::swap_on_whirlpool(accounts, leg_accounts, leg, leg_amount, vault_seeds)?,
+                _ => return err!(ErrorCode::InvalidRoute),
             };
 
-            // Execute the swap through Raydium's CPI
-            raydium_amm::swap(
-                CpiContext::new(
-                    ctx.accounts.raydium_program.to_account_info(),
-                    raydium_amm::Swap {
-                        amm: pool.to_account_info(),
-                        authority: pool_authority,
-                        user: ctx.accounts.user.to_account_info(),
-                        source_token: ctx.accounts.token_a_account.to_account_info(),
-                        destination_token: ctx.accounts.token_b_account.to_account_info(),
-                        token_program: ctx.accounts.token_program.to_account_info(),
-                    },
-                ),
-                swap_instruction,
-            )?;
+            // Per-leg slippage: every hop must deliver at least what the bot quoted minus its tolerance,
+            // less whatever transfer fee the output mint withholds on the way into the vault
+            let output_mint = Self::leg_output_mint(leg, leg_accounts)?;
+            let min_amount_out = leg
+                .min_amount_out
+                .saturating_sub(Self::transfer_fee(output_mint, leg.min_amount_out)?);
+            if leg_amount < min_amount_out {
+                return Err(Self::leg_slippage_error(index).into());
+            }
+        }
+        Ok((leg_amount, dex_fees))
+    }
 
-            Ok(())
+    // The price the minimum profit was converted with must still be current: the config's feed is
+    // passed, its aggregate is trading and was published within MAX_ORACLE_AGE_SLOTS. A missing
+    // feed, or anything that isn't the configured Pyth price account, can't vouch for the price
+    // either and counts as stale. Without a configured feed there is nothing to check
+    fn check_oracle(config: &Config, oracle: Option<&AccountInfo>, slot: u64) -> Result<()> {
+        if config.price_oracle == Pubkey::default() {
+            return Ok(());
         }
+        let oracle = oracle.ok_or(ErrorCode::OracleStale)?;
+        require_keys_eq!(oracle.key(), config.price_oracle, ErrorCode::OracleStale);
+        require_keys_eq!(*oracle.owner, PYTH_PROGRAM_ID, ErrorCode::OracleStale);
+        let data = oracle.try_borrow_data()?;
+        let read_u32 = |offset: usize| data.get(offset..offset + 4).map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()));
+        let pub_slot = data
+            .get(PYTH_PUB_SLOT_OFFSET..PYTH_PUB_SLOT_OFFSET + 8)
+            .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+        require!(
+            read_u32(0) == Some(PYTH_MAGIC)
+                && read_u32(8) == Some(PYTH_PRICE_ACCOUNT_TYPE)
+                && read_u32(PYTH_STATUS_OFFSET) == Some(PYTH_STATUS_TRADING)
+                && pub_slot.is_some_and(|pub_slot| slot.saturating_sub(pub_slot) <= MAX_ORACLE_AGE_SLOTS),
+            ErrorCode::OracleStale
+        );
+        Ok(())
+    }
 
-        // New helper functions
-        fn swap_sol_to_token(
-            sol_amount: u64,
-            token: Pubkey,
-            jupiter_program: &Program<Jupiter>
-        ) -> Result<u64> {
-            let wsol_mint = "So11111111111111111111111111111111111111112";
-            let swap_instruction = jupiter_core::SwapInstruction {
-                input_mint: Pubkey::from_str(wsol_mint)?,
-                output_mint: token,
-                amount: sol_amount,
-                slippage_bps: 300,
-                platform_fee_bps: 0,
-            };
+    fn leg_compute_units(venue: u8) -> u64 {
+        match venue {
+            VENUE_JUPITER => 60_000,
+            VENUE_RAYDIUM => 25_000,
+            VENUE_RAYDIUM_CLMM | VENUE_WHIRLPOOL => 50_000,
+            _ => 0,
+        }
+    }
 
-            // Execute swap through Jupiter
-            let result = jupiter_core::swap(
-                CpiContext::new(
-                    jupiter_program.to_account_info(),
-                    jupiter_core::Swap {
-                        user: ctx.accounts.user.to_account_info(),
-                        user_token_account_a: ctx.accounts.token_a_account.to_account_info(),
-                        user_token_account_b: ctx.accounts.token_b_account.to_account_info(),
-                        token_program: ctx.accounts.token_program.to_account_info(),
-                    },
-                ),
-                swap_instruction,
-            )?;
+    // The transaction's compute unit limit: its SetComputeUnitLimit, or the runtime's default of
+    // 200k per instruction
+    fn compute_unit_limit(instructions: &AccountInfo) -> Result<u64> {
+        let count = {
+            let data = instructions.try_borrow_data()?;
+            u16::from_le_bytes(data.get(..2).ok_or(ErrorCode::CalculationError)?.try_into().unwrap())
+        };
+        let mut other_instructions = 0;
+        for index in 0..count {
+            let instruction = load_instruction_at_checked(index as usize, instructions)?;
+            if instruction.program_id != COMPUTE_BUDGET_PROGRAM_ID {
+                other_instructions += 1;
+            } else if instruction.data.len() >= 5 && instruction.data[0] == SET_COMPUTE_UNIT_LIMIT {
+                return Ok(u32::from_le_bytes(instruction.data[1..5].try_into().unwrap()) as u64);
+            }
+        }
+        Ok((other_instructions * DEFAULT_INSTRUCTION_COMPUTE_UNITS).min(MAX_COMPUTE_UNITS))
+    }
 
-            Ok(result.amount_out)
+    fn leg_slippage_error(index: usize) -> ErrorCode {
+        match index {
+            0 => ErrorCode::Leg1SlippageExceeded,
+            1 => ErrorCode::Leg2SlippageExceeded,
+            2 => ErrorCode::Leg3SlippageExceeded,
+            3 => ErrorCode::Leg4SlippageExceeded,
+            4 => ErrorCode::Leg5SlippageExceeded,
+            _ => ErrorCode::Leg6SlippageExceeded,
         }
+    }
 
-        fn swap_token_to_sol(
-            token_amount: u64,
-            token: Pubkey,
-            jupiter_program: &Program<Jupiter>
-        ) -> Result<u64> {
-            let wsol_mint = "So11111111111111111111111111111111111111112";
-            let swap_instruction = jupiter_core::SwapInstruction {
-                input_mint: token,
-                output_mint: Pubkey::from_str(wsol_mint)?,
-                amount: token_amount,
-                slippage_bps: 300,
-                platform_fee_bps: 0,
-            };
+    fn leg_accounts<'a, 'info>(remaining: &'a [AccountInfo<'info>], leg: &RouteLeg) -> Result<&'a [AccountInfo<'info>]> {
+        let start = leg.accounts_offset as usize;
+        let end = start + leg.accounts_len as usize;
+        remaining.get(start..end).ok_or_else(|| error!(ErrorCode::InvalidLegAccounts))
+    }
 
-            // Execute swap through Jupiter
-            let result = jupiter_core::swap(
-                CpiContext::new(
-                    jupiter_program.to_account_info(),
-                    jupiter_core::Swap {
-                        user: ctx.accounts.user.to_account_info(),
-                        user_token_account_a: ctx.accounts.token_a_account.to_account_info(),
-                        user_token_account_b: ctx.accounts.token_b_account.to_account_info(),
-                        token_program: ctx.accounts.token_program.to_account_info(),
-                    },
-                ),
-                swap_instruction,
-            )?;
+    fn solend_reserve<'a, 'info>(remaining: &'a [AccountInfo<'info>], offset: u8) -> Result<&'a AccountInfo<'info>> {
+        let start = offset as usize;
+        let loan_accounts = remaining
+            .get(start..start + SOLEND_LOAN_ACCOUNTS)
+            .ok_or_else(|| error!(ErrorCode::InvalidLoanAccounts))?;
+        require!(loan_accounts[0].owner == &SOLEND_PROGRAM_ID, ErrorCode::InvalidLoanAccounts);
+        Ok(&loan_accounts[0])
+    }
 
-            // Return the amount of SOL received
-            Ok(result.amount_out)
+    // Fee Solend will charge on repayment, from the reserve's flash_loan_fee_wad (rounded up like Solend does)
+    fn solend_flash_loan_fee(reserve: &AccountInfo, amount: u64) -> Result<u64> {
+        let data = reserve.try_borrow_data()?;
+        let fee_wad = data
+            .get(SOLEND_FLASH_LOAN_FEE_OFFSET..SOLEND_FLASH_LOAN_FEE_OFFSET + 8)
+            .ok_or_else(|| error!(ErrorCode::InvalidLoanAccounts))?;
+        let fee_wad = u64::from_le_bytes(fee_wad.try_into().unwrap()) as u128;
+        let fee = (amount as u128 * fee_wad).div_ceil(WAD);
+        u64::try_from(fee).map_err(|_| error!(ErrorCode::CalculationError))
+    }
+
+    fn calculate_profit_fee(profit: u64, fee_bps: u16) -> Result<u64> {
+        let fee = profit as u128 * fee_bps as u128 / 10_000;
+        u64::try_from(fee).map_err(|_| error!(ErrorCode::CalculationError))
+    }
+
+    fn calculate_flash_loan_fee(amount: u64, fee_bps: u16) -> u64 {
+        // marginfi charges no flash loan fee; banks with an origination fee set it in the config.
+        // Solend loans use the reserve's fee instead
+        amount
+            .checked_mul(fee_bps as u64)
+            .unwrap_or(0)
+            .checked_div(10000)
+            .unwrap_or(0)
+    }
+
+    // Swap fee charged by a route leg's venue
+    fn venue_fee(amount: u64, venue: u8) -> u64 {
+        let fee_bps = match venue {
+            // CLMM pools have several fee tiers; 25 bps is the common one
+            VENUE_RAYDIUM | VENUE_RAYDIUM_CLMM => 25,
+            _ => 30,
+        };
+        amount
+            .checked_mul(fee_bps)
+            .unwrap_or(0)
+            .checked_div(10000)
+            .unwrap_or(0)
+    }
+
+    // Leg accounts: [Jupiter v6 program, then `shared_accounts_route` accounts as returned by the
+    // swap-instructions API for the vault authority]. The route plan comes from Jupiter and is
+    // forwarded as is, with only in_amount replaced by what the previous leg actually delivered
+    fn swap_on_jupiter<'info>(
+        accounts: &TryArbitrage<'info>,
+        leg_accounts: &[AccountInfo<'info>],
+        venue_data: &[u8],
+        amount: u64,
+        vault_seeds: &[&[u8]],
+    ) -> Result<u64> {
+        require!(leg_accounts.len() > JUPITER_ROUTE_FIXED_ACCOUNTS, ErrorCode::InvalidLegAccounts);
+        require_keys_eq!(leg_accounts[0].key(), JUPITER_V6_PROGRAM_ID, ErrorCode::InvalidLegAccounts);
+        require!(
+            venue_data.len() > JUPITER_SHARED_ACCOUNTS_ROUTE.len() + JUPITER_ROUTE_TRAILER_LEN
+                && venue_data[..8] == JUPITER_SHARED_ACCOUNTS_ROUTE,
+            ErrorCode::InvalidRoute
+        );
+
+        let route_accounts = &leg_accounts[1..];
+        let vault_authority = accounts.vault_authority.key();
+        // Jupiter CPIs into every AMM program in the route plan; each one must be approved, so a
+        // fake pool can't be reached through a program the admin doesn't know. The plan's other
+        // accounts (pool states, their vaults and authorities) must be listed pools or our own
+        // vaults, unless the admin lets Jupiter pick any pool of the approved programs
+        Self::require_whitelisted_program(accounts, &leg_accounts[0])?;
+        for account in &route_accounts[JUPITER_ROUTE_FIXED_ACCOUNTS..] {
+            // The plan passes the token programs along with each AMM's own accounts
+            if account.key() == anchor_spl::token::ID || account.key() == anchor_spl::token_2022::ID {
+                continue;
+            }
+            if account.executable {
+                Self::require_whitelisted_program(accounts, account)?;
+            } else if !accounts.config.allow_unlisted_jupiter_pools {
+                require!(
+                    accounts.whitelist.pools.contains(&account.key()) || Self::is_vault(account, &vault_authority),
+                    ErrorCode::NotWhitelisted
+                );
+            }
+        }
+        require_keys_eq!(route_accounts[JUPITER_TRANSFER_AUTHORITY_ACCOUNT].key(), vault_authority, ErrorCode::InvalidLegAccounts);
+        Self::require_vault(&route_accounts[JUPITER_SOURCE_ACCOUNT], &vault_authority)?;
+        let destination = &route_accounts[JUPITER_DESTINATION_ACCOUNT];
+        Self::require_vault(destination, &vault_authority)?;
+        let balance_before = Self::token_balance(destination)?;
+
+        let mut data = venue_data.to_vec();
+        let in_amount_at = data.len() - JUPITER_ROUTE_TRAILER_LEN;
+        data[in_amount_at..in_amount_at + 8].copy_from_slice(&amount.to_le_bytes());
+
+        let instruction = Instruction {
+            program_id: JUPITER_V6_PROGRAM_ID,
+            accounts: route_accounts
+                .iter()
+                .map(|account| AccountMeta {
+                    pubkey: account.key(),
+                    is_signer: account.is_signer || account.key() == vault_authority,
+                    is_writable: account.is_writable,
+                })
+                .collect(),
+            data,
+        };
+        invoke_signed(&instruction, leg_accounts, &[vault_seeds])?;
+
+        Ok(Self::token_balance(destination)?.saturating_sub(balance_before))
+    }
+
+    // Leg accounts: [Raydium AMM program, then `swap_base_in`'s accounts from the pool on: pool,
+    // pool authority, open orders, target orders, pool coin and pc vaults, OpenBook program, market,
+    // bids, asks, event queue, market coin and pc vaults, market vault signer, source token account,
+    // destination token account; then the output mint]. The vault authority is added as the source owner
+    fn swap_on_raydium<'info>(
+        accounts: &TryArbitrage<'info>,
+        leg_accounts: &[AccountInfo<'info>],
+        leg: &RouteLeg,
+        amount: u64,
+        vault_seeds: &[&[u8]],
+    ) -> Result<u64> {
+        require!(leg_accounts.len() == RAYDIUM_LEG_ACCOUNTS, ErrorCode::InvalidLegAccounts);
+        require_keys_eq!(leg_accounts[0].key(), RAYDIUM_AMM_PROGRAM_ID, ErrorCode::InvalidLegAccounts);
+        Self::require_whitelisted_program(accounts, &leg_accounts[0])?;
+        require!(accounts.whitelist.pools.contains(&leg_accounts[1].key()), ErrorCode::NotWhitelisted);
+        let vault_authority = accounts.vault_authority.key();
+        Self::require_vault(&leg_accounts[RAYDIUM_SOURCE_ACCOUNT], &vault_authority)?;
+        let destination = &leg_accounts[RAYDIUM_DESTINATION_ACCOUNT];
+        Self::require_vault(destination, &vault_authority)?;
+        let balance_before = Self::token_balance(destination)?;
+
+        // swap_base_in: amount_in, minimum_amount_out. The minimum is the leg's own, in output
+        // token units as the bot quoted it
+        let mut data = vec![RAYDIUM_SWAP_BASE_IN];
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&leg.min_amount_out.to_le_bytes());
+
+        let mut metas = vec![AccountMeta::new_readonly(accounts.token_program.key(), false)];
+        metas.extend(leg_accounts[1..RAYDIUM_OUTPUT_MINT_ACCOUNT].iter().map(|account| AccountMeta {
+            pubkey: account.key(),
+            is_signer: false,
+            is_writable: account.is_writable,
+        }));
+        metas.push(AccountMeta::new_readonly(vault_authority, true));
+        let instruction = Instruction {
+            program_id: RAYDIUM_AMM_PROGRAM_ID,
+            accounts: metas,
+            data,
+        };
+        let mut account_infos = vec![accounts.token_program.to_account_info(), accounts.vault_authority.to_account_info()];
+        account_infos.extend_from_slice(leg_accounts);
+        invoke_signed(&instruction, &account_infos, &[vault_seeds])?;
+
+        Ok(Self::token_balance(destination)?.saturating_sub(balance_before))
+    }
+
+    // Leg accounts: [Raydium CLMM program, amm config, pool state, source token account, destination
+    // token account, input vault, output vault, observation state, token program, Token-2022 program,
+    // memo program, input mint, output mint, then the tick arrays the swap may cross]. The vault
+    // authority is inserted as the payer
+    fn swap_on_raydium_clmm<'info>(
+        accounts: &TryArbitrage<'info>,
+        leg_accounts: &[AccountInfo<'info>],
+        amount: u64,
+        vault_seeds: &[&[u8]],
+    ) -> Result<u64> {
+        require!(leg_accounts.len() > RAYDIUM_CLMM_FIXED_ACCOUNTS, ErrorCode::InvalidLegAccounts);
+        require_keys_eq!(leg_accounts[0].key(), RAYDIUM_CLMM_PROGRAM_ID, ErrorCode::InvalidLegAccounts);
+        Self::require_whitelisted_program(accounts, &leg_accounts[0])?;
+        require!(accounts.whitelist.pools.contains(&leg_accounts[2].key()), ErrorCode::NotWhitelisted);
+        let vault_authority = accounts.vault_authority.key();
+        Self::require_vault(&leg_accounts[3], &vault_authority)?;
+        let destination = &leg_accounts[4];
+        Self::require_vault(destination, &vault_authority)?;
+        let balance_before = Self::token_balance(destination)?;
+
+        // amount, other_amount_threshold, sqrt_price_limit_x64 (0: no limit), is_base_input. The
+        // per-leg min_amount_out is the binding check, so the venue gets no threshold of its own
+        let mut data = RAYDIUM_CLMM_SWAP_V2.to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&0u128.to_le_bytes());
+        data.push(1);
+
+        let mut metas = vec![AccountMeta::new_readonly(vault_authority, true)];
+        metas.extend(leg_accounts[1..].iter().map(|account| AccountMeta {
+            pubkey: account.key(),
+            is_signer: false,
+            is_writable: account.is_writable,
+        }));
+        let instruction = Instruction {
+            program_id: RAYDIUM_CLMM_PROGRAM_ID,
+            accounts: metas,
+            data,
+        };
+        let mut account_infos = vec![accounts.vault_authority.to_account_info()];
+        account_infos.extend_from_slice(leg_accounts);
+        invoke_signed(&instruction, &account_infos, &[vault_seeds])?;
+
+        Ok(Self::token_balance(destination)?.saturating_sub(balance_before))
+    }
+
+    // Leg accounts: [Whirlpool program, token program, whirlpool, token account A, token vault A,
+    // token account B, token vault B, tick array 0, tick array 1, tick array 2, oracle, output mint].
+    // The vault authority is inserted as the token authority
+    fn swap_on_whirlpool<'info>(
+        accounts: &TryArbitrage<'info>,
+        leg_accounts: &[AccountInfo<'info>],
+        leg: &RouteLeg,
+        amount: u64,
+        vault_seeds: &[&[u8]],
+    ) -> Result<u64> {
+        require!(leg_accounts.len() == WHIRLPOOL_LEG_ACCOUNTS, ErrorCode::InvalidLegAccounts);
+        require_keys_eq!(leg_accounts[0].key(), WHIRLPOOL_PROGRAM_ID, ErrorCode::InvalidLegAccounts);
+        require_keys_eq!(leg_accounts[1].key(), anchor_spl::token::ID, ErrorCode::InvalidLegAccounts);
+        Self::require_whitelisted_program(accounts, &leg_accounts[0])?;
+        require!(accounts.whitelist.pools.contains(&leg_accounts[2].key()), ErrorCode::NotWhitelisted);
+        let vault_authority = accounts.vault_authority.key();
+        Self::require_vault(&leg_accounts[3], &vault_authority)?;
+        Self::require_vault(&leg_accounts[5], &vault_authority)?;
+
+        // Selling token A for token B, or the other way around
+        let a_to_b = Self::token_mint(&leg_accounts[3])? == leg.input_mint;
+        let destination = if a_to_b { &leg_accounts[5] } else { &leg_accounts[3] };
+        let balance_before = Self::token_balance(destination)?;
+
+        // amount, other_amount_threshold, sqrt_price_limit, amount_specified_is_input, a_to_b. The
+        // per-leg min_amount_out is the binding check, so the venue gets no threshold of its own
+        let sqrt_price_limit = if a_to_b { WHIRLPOOL_MIN_SQRT_PRICE } else { WHIRLPOOL_MAX_SQRT_PRICE };
+        let mut data = WHIRLPOOL_SWAP.to_vec();
+        data.extend_from_slice(&amount.to_le_bytes());
+        data.extend_from_slice(&0u64.to_le_bytes());
+        data.extend_from_slice(&sqrt_price_limit.to_le_bytes());
+        data.push(1);
+        data.push(a_to_b as u8);
+
+        let account_meta = |account: &AccountInfo| AccountMeta {
+            pubkey: account.key(),
+            is_signer: false,
+            is_writable: account.is_writable,
+        };
+        let mut metas = vec![account_meta(&leg_accounts[1]), AccountMeta::new_readonly(vault_authority, true)];
+        metas.extend(leg_accounts[2..WHIRLPOOL_OUTPUT_MINT_ACCOUNT].iter().map(account_meta));
+        let instruction = Instruction {
+            program_id: WHIRLPOOL_PROGRAM_ID,
+            accounts: metas,
+            data,
+        };
+        let mut account_infos = vec![accounts.vault_authority.to_account_info()];
+        account_infos.extend_from_slice(leg_accounts);
+        invoke_signed(&instruction, &account_infos, &[vault_seeds])?;
+
+        Ok(Self::token_balance(destination)?.saturating_sub(balance_before))
+    }
+
+    // Swap CPIs return nothing, so each leg's output is measured as the change in its
+    // destination token account's balance
+    fn token_balance(account: &AccountInfo) -> Result<u64> {
+        Ok(TokenAccount::try_deserialize(&mut &account.try_borrow_data()?[..])?.amount)
+    }
+
+    fn require_whitelisted_program(accounts: &TryArbitrage, program: &AccountInfo) -> Result<()> {
+        require!(accounts.whitelist.programs.contains(&program.key()), ErrorCode::NotWhitelisted);
+        Ok(())
+    }
+
+    // Creates the vault authority's WSOL account (if a previous trade didn't leave one behind),
+    // paid for by the wallet
+    fn open_wsol_vault(accounts: &TryArbitrage) -> Result<()> {
+        let associated_token_program = accounts
+            .associated_token_program
+            .as_ref()
+            .ok_or_else(|| error!(ErrorCode::InvalidTokenAccount))?;
+        associated_token::create_idempotent(CpiContext::new(
+            associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: accounts.user.to_account_info(),
+                associated_token: accounts.vault.to_account_info(),
+                authority: accounts.vault_authority.to_account_info(),
+                mint: accounts.token_a_mint.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+                token_program: accounts.token_program.to_account_info(),
+            },
+        ))
+    }
+
+    // Moves the borrowed token between the wallet's accounts and the vault
+    fn transfer_checked<'info>(
+        accounts: &TryArbitrage<'info>,
+        from: AccountInfo<'info>,
+        to: AccountInfo<'info>,
+        authority: AccountInfo<'info>,
+        amount: u64,
+        signer_seeds: &[&[&[u8]]],
+    ) -> Result<()> {
+        token::transfer_checked(
+            CpiContext::new_with_signer(
+                accounts.token_program.to_account_info(),
+                token::TransferChecked {
+                    from,
+                    mint: accounts.token_a_mint.to_account_info(),
+                    to,
+                    authority,
+                },
+                signer_seeds,
+            ),
+            amount,
+            accounts.token_a_mint.decimals,
+        )
+    }
+
+    // Fee a Token-2022 mint with the transfer fee extension withholds from a transfer of `amount`;
+    // zero for SPL Token mints
+    fn transfer_fee(mint: &AccountInfo, amount: u64) -> Result<u64> {
+        if mint.owner != &anchor_spl::token_2022::ID {
+            return Ok(0);
+        }
+        let data = mint.try_borrow_data()?;
+        let mint_state = StateWithExtensions::<MintState>::unpack(&data)?;
+        match mint_state.get_extension::<TransferFeeConfig>() {
+            Ok(config) => config
+                .calculate_epoch_fee(Clock::get()?.epoch, amount)
+                .ok_or_else(|| error!(ErrorCode::CalculationError)),
+            Err(_) => Ok(0),
         }
+    }
+
+    // The mint a leg delivers, as passed in its accounts
+    fn leg_output_mint<'a, 'info>(leg: &RouteLeg, leg_accounts: &'a [AccountInfo<'info>]) -> Result<&'a AccountInfo<'info>> {
+        let index = match leg.venue {
+            VENUE_JUPITER => 1 + JUPITER_DESTINATION_MINT_ACCOUNT,
+            VENUE_RAYDIUM_CLMM => RAYDIUM_CLMM_OUTPUT_MINT_ACCOUNT,
+            VENUE_WHIRLPOOL => WHIRLPOOL_OUTPUT_MINT_ACCOUNT,
+            _ => RAYDIUM_OUTPUT_MINT_ACCOUNT,
+        };
+        let mint = leg_accounts.get(index).ok_or_else(|| error!(ErrorCode::InvalidLegAccounts))?;
+        require_keys_eq!(mint.key(), leg.output_mint, ErrorCode::InvalidLegAccounts);
+        Ok(mint)
+    }
+
+    fn token_mint(account: &AccountInfo) -> Result<Pubkey> {
+        Ok(TokenAccount::try_deserialize(&mut &account.try_borrow_data()?[..])?.mint)
+    }
+
+    // Swap inputs and outputs must be vaults, so no leg can route funds to an outside account
+    fn require_vault(account: &AccountInfo, vault_authority: &Pubkey) -> Result<()> {
+        let token_account = TokenAccount::try_deserialize(&mut &account.try_borrow_data()?[..])?;
+        require_keys_eq!(token_account.owner, *vault_authority, ErrorCode::InvalidLegAccounts);
+        Ok(())
+    }
 
-        fn calculate_jupiter_conversion_fees(amount: u64) -> u64 {
-            // Jupiter fee for SOL -> token and token -> SOL (0.3% each way)
-            amount
-                .checked_mul(60) // 0.6% total
-                .unwrap_or(0)
-                .checked_div(10000)
-                .unwrap_or(0)
+    // A token account, owned by a token program, whose authority is the vault authority
+    fn is_vault(account: &AccountInfo, vault_authority: &Pubkey) -> bool {
+        if account.owner != &anchor_spl::token::ID && account.owner != &anchor_spl::token_2022::ID {
+            return false;
         }
+        let Ok(data) = account.try_borrow_data() else {
+            return false;
+        };
+        matches!(TokenAccount::try_deserialize(&mut &data[..]), Ok(token_account) if token_account.owner == *vault_authority)
     }
 }
@@ -0,0 +1,180 @@
+use crate::failure::FailureClass;
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fs::{File, OpenOptions};
+use std::hash::{BuildHasher, Hasher};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+// Append-only log of every state a trade went through, from the decision to the journal entry,
+// keyed by the trade ID assigned at the decision. Each record carries the hash of the one before
+// it, so a record edited or removed after the fact breaks the chain at that point
+
+// Hash the first record points back to
+const GENESIS_HASH: &str = "0000000000000000000000000000000000000000000000000000000000000000";
+// Tail of the file read to find the last record; records are far smaller
+const TAIL_BYTES: u64 = 64 * 1024;
+
+// Serializes appends within the process, so two writers can't chain onto the same record
+static APPEND: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AuditEvent {
+    // The evaluator or strategy chose to trade
+    Decided {
+        key: String,
+        loan_amount: u64,
+        expected_profit_lamports: i64,
+        reason: String,
+    },
+    // The transaction was built and its simulation cleared the required profit
+    Built { simulated_profit_lamports: i64 },
+    Signed { signature: String },
+    Confirmed { signature: String },
+    Failed {
+        error: String,
+        failure_class: FailureClass,
+    },
+    Journaled,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditRecord {
+    pub seq: u64,
+    pub timestamp_ms: u64,
+    pub trade_id: String,
+    pub event: AuditEvent,
+    pub prev_hash: String,
+    // Over the record's JSON with this field empty
+    pub hash: String,
+}
+
+impl AuditRecord {
+    fn compute_hash(&self) -> Result<String, serde_json::Error> {
+        let unhashed = AuditRecord {
+            hash: String::new(),
+            ..self.clone()
+        };
+        Ok(blake3::hash(serde_json::to_string(&unhashed)?.as_bytes()).to_hex().to_string())
+    }
+}
+
+pub struct AuditLog {
+    path: PathBuf,
+}
+
+impl AuditLog {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    // Chains a record of `event` onto the last one in the file
+    pub fn append(&self, trade_id: &str, event: AuditEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let _guard = APPEND.lock().unwrap();
+        let (seq, prev_hash) = match self.last()? {
+            Some(last) => (last.seq + 1, last.hash),
+            None => (0, GENESIS_HASH.to_string()),
+        };
+        let mut record = AuditRecord {
+            seq,
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            trade_id: trade_id.to_string(),
+            event,
+            prev_hash,
+            hash: String::new(),
+        };
+        record.hash = record.compute_hash()?;
+        let mut file = OpenOptions::new().create(true).append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&record)?)?;
+        Ok(())
+    }
+
+    pub fn load(&self) -> Result<Vec<AuditRecord>, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let reader = BufReader::new(File::open(&self.path)?);
+        let mut records = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            records.push(serde_json::from_str(&line)?);
+        }
+        Ok(records)
+    }
+
+    // Checks every record's hash and link, returning the number of records or the first broken one
+    pub fn verify(&self) -> Result<usize, Box<dyn std::error::Error>> {
+        let records = self.load()?;
+        let mut prev_hash = GENESIS_HASH.to_string();
+        for (index, record) in records.iter().enumerate() {
+            if record.seq != index as u64 || record.prev_hash != prev_hash {
+                return Err(format!("Audit chain broken at record {}: missing or reordered record before it", index).into());
+            }
+            if record.compute_hash()? != record.hash {
+                return Err(format!("Audit chain broken at record {}: contents don't match its hash", index).into());
+            }
+            prev_hash = record.hash.clone();
+        }
+        Ok(records.len())
+    }
+
+    fn last(&self) -> Result<Option<AuditRecord>, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(None);
+        }
+        let mut file = File::open(&self.path)?;
+        let len = file.metadata()?.len();
+        file.seek(SeekFrom::Start(len.saturating_sub(TAIL_BYTES)))?;
+        let mut tail = Vec::new();
+        file.read_to_end(&mut tail)?;
+        // The cut may fall inside a character of an earlier record; only the last line is parsed
+        let tail = String::from_utf8_lossy(&tail);
+        match tail.lines().rev().find(|line| !line.trim().is_empty()) {
+            Some(line) => Ok(Some(serde_json::from_str(line)?)),
+            None => Ok(None),
+        }
+    }
+}
+
+// Random (version 4) UUID identifying a trade from its decision on
+pub fn trade_id() -> String {
+    let random = || RandomState::new().build_hasher().finish();
+    let mut bytes = [0u8; 16];
+    bytes[..8].copy_from_slice(&random().to_le_bytes());
+    bytes[8..].copy_from_slice(&random().to_le_bytes());
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn records_chain_and_edits_break_the_chain() {
+        let path = std::env::temp_dir().join(format!("audit-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let log = AuditLog::new(&path);
+        let id = trade_id();
+        assert_eq!((id.len(), &id[14..15]), (36, "4"));
+        log.append(&id, AuditEvent::Built { simulated_profit_lamports: 5_000 }).unwrap();
+        log.append(&id, AuditEvent::Signed { signature: "sig".to_string() }).unwrap();
+        log.append(&id, AuditEvent::Journaled).unwrap();
+        assert_eq!(log.verify().unwrap(), 3);
+        assert_eq!(log.load().unwrap()[2].seq, 2);
+
+        let edited = std::fs::read_to_string(&path).unwrap().replace("5000", "9000");
+        std::fs::write(&path, edited).unwrap();
+        let error = log.verify().unwrap_err().to_string();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(error, "Audit chain broken at record 0: contents don't match its hash");
+    }
+}
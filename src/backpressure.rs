@@ -0,0 +1,77 @@
+use std::env;
+use std::time::{Duration, Instant};
+
+// Backpressure between evaluation and execution. A volatile moment can make dozens of pairs
+// executable in the same poll, and sending them one after another means the last ones go out on
+// quotes seconds old. Executable opportunities are held in a queue of OPPORTUNITY_QUEUE_CAPACITY
+// that sheds the lowest expected value first when full, the executor takes the highest first, and
+// anything older than OPPORTUNITY_MAX_AGE_MS by the time its turn comes is shed as stale
+
+#[derive(Debug, Clone)]
+pub struct BackpressureConfig {
+    pub capacity: usize,
+    pub max_age: Duration,
+}
+
+impl BackpressureConfig {
+    pub fn from_env() -> Self {
+        let parse = |name: &str, default: u64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Self {
+            capacity: parse("OPPORTUNITY_QUEUE_CAPACITY", 8).max(1) as usize,
+            max_age: Duration::from_millis(parse("OPPORTUNITY_MAX_AGE_MS", 2_000)),
+        }
+    }
+}
+
+struct Pending<T> {
+    item: T,
+    expected_value: f64,
+    evaluated: Instant,
+}
+
+pub struct OpportunityQueue<T> {
+    config: BackpressureConfig,
+    pending: Vec<Pending<T>>,
+}
+
+impl<T> OpportunityQueue<T> {
+    pub fn new(config: BackpressureConfig) -> Self {
+        Self {
+            config,
+            pending: Vec::new(),
+        }
+    }
+
+    // Queues `item`, evaluated at `evaluated`; once over capacity the lowest expected value, which
+    // may be `item` itself, is shed and returned
+    pub fn push(&mut self, item: T, expected_value: f64, evaluated: Instant) -> Option<T> {
+        self.pending.push(Pending {
+            item,
+            expected_value,
+            evaluated,
+        });
+        if self.pending.len() <= self.config.capacity {
+            return None;
+        }
+        let lowest = (0..self.pending.len())
+            .min_by(|&a, &b| self.pending[a].expected_value.total_cmp(&self.pending[b].expected_value))?;
+        Some(self.pending.swap_remove(lowest).item)
+    }
+
+    // Removes and returns everything older than the maximum age at `now`
+    pub fn shed_stale(&mut self, now: Instant) -> Vec<T> {
+        let max_age = self.config.max_age;
+        let (stale, fresh): (Vec<_>, Vec<_>) = std::mem::take(&mut self.pending)
+            .into_iter()
+            .partition(|pending| now.saturating_duration_since(pending.evaluated) > max_age);
+        self.pending = fresh;
+        stale.into_iter().map(|pending| pending.item).collect()
+    }
+
+    // The highest expected value left
+    pub fn pop(&mut self) -> Option<T> {
+        let highest = (0..self.pending.len())
+            .max_by(|&a, &b| self.pending[a].expected_value.total_cmp(&self.pending[b].expected_value))?;
+        Some(self.pending.swap_remove(highest).item)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::costs::CostBreakdown;
+use crate::evaluator::{self, EvaluatorParams};
+use crate::market_data::MarketSnapshot;
+use crate::report::lamports_to_sol;
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Copy)]
+pub struct BacktestScenario {
+    pub params: EvaluatorParams,
+    // Scales the flash loan and DEX fees, e.g. 1.5 to assume 50% worse fees than modelled
+    pub fee_multiplier: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct BacktestResult {
+    pub opportunities: usize,
+    pub gross_profit_lamports: i64,
+    pub fee_lamports: i64,
+    pub net_profit_lamports: i64,
+    pub losing_trades: usize,
+}
+
+// Runs every snapshot through the live evaluator as if the bot had traded each opportunity
+pub fn run(snapshots: &[MarketSnapshot], scenario: &BacktestScenario) -> BacktestResult {
+    let mut result = BacktestResult::default();
+
+    for snapshot in snapshots {
+        let evaluation = evaluator::evaluate(
+            &snapshot.quote,
+            snapshot.loan_amount,
+            snapshot.sol_price,
+            &scenario.params,
+        );
+        let expected_profit = match evaluation.expected_profit() {
+            Some(profit) => profit as i64,
+            None => continue,
+        };
+
+        let protocol_fees = CostBreakdown::estimate_protocol_fees(evaluation.trade_amount).total_lamports();
+        let fees = (protocol_fees as f64 * scenario.fee_multiplier) as i64
+            + scenario.params.estimated_gas_cost as i64;
+        let net = expected_profit - fees;
+
+        result.opportunities += 1;
+        result.gross_profit_lamports += expected_profit;
+        result.fee_lamports += fees;
+        result.net_profit_lamports += net;
+        if net < 0 {
+            result.losing_trades += 1;
+        }
+    }
+
+    result
+}
+
+pub fn run_grid(
+    snapshots: &[MarketSnapshot],
+    scenarios: &[BacktestScenario],
+) -> Vec<(BacktestScenario, BacktestResult)> {
+    scenarios
+        .iter()
+        .map(|scenario| (*scenario, run(snapshots, scenario)))
+        .collect()
+}
+
+pub fn render(results: &[(BacktestScenario, BacktestResult)]) -> String {
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        "{:>16} {:>12} {:>8} {:>8} {:>8} {:>14} {:>14} {:>14}",
+        "threshold", "gas", "fee x", "trades", "losers", "gross SOL", "fees SOL", "net SOL"
+    );
+    for (scenario, result) in results {
+        let _ = writeln!(
+            out,
+            "{:>16} {:>12} {:>8.2} {:>8} {:>8} {:>14.6} {:>14.6} {:>14.6}",
+            scenario.params.min_profit_threshold.to_string(),
+            scenario.params.estimated_gas_cost,
+            scenario.fee_multiplier,
+            result.opportunities,
+            result.losing_trades,
+            lamports_to_sol(result.gross_profit_lamports),
+            lamports_to_sol(result.fee_lamports),
+            lamports_to_sol(result.net_profit_lamports),
+        );
+    }
+    out
+}
@@ -0,0 +1,207 @@
+use crate::client;
+use crate::costs::WSOL_MINT;
+use crate::discovery;
+use crate::http_client;
+use crate::network;
+use crate::pair_store::PairStore;
+use crate::rpc::RpcApi;
+use anchor_spl::token::spl_token;
+use pyth_sdk_solana::state::SolanaPriceAccount;
+use serde_json::json;
+use solana_account_decoder::UiAccountEncoding;
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_response::{Response, RpcKeyedAccount};
+use solana_sdk::account::Account;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Signer};
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+
+// Overview of the wallet's holdings and the program's vaults: balances, USD values where a Pyth
+// feed prices the mint, and the rent locked in token accounts
+
+// Byte offsets in SPL token accounts and mints; Token-2022 shares the base layout
+const TOKEN_MINT_OFFSET: usize = 0;
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+pub const MINT_DECIMALS_OFFSET: usize = 44;
+
+#[derive(Debug, Clone)]
+pub struct TokenAccount {
+    pub address: Pubkey,
+    pub mint: Pubkey,
+    pub amount: u64,
+    pub lamports: u64,
+    pub token_program: Pubkey,
+}
+
+impl TokenAccount {
+    // Lamports returned when the account is closed; a WSOL account's lamports include the wrapped SOL
+    pub fn rent_lamports(&self) -> u64 {
+        if self.mint.to_string() == WSOL_MINT {
+            self.lamports.saturating_sub(self.amount)
+        } else {
+            self.lamports
+        }
+    }
+}
+
+// Every SPL Token and Token-2022 account owned by `owner`
+pub fn token_accounts(rpc: &RpcClient, owner: &Pubkey) -> Result<Vec<TokenAccount>, Box<dyn std::error::Error>> {
+    let mut accounts = Vec::new();
+    for token_program in [spl_token::id(), anchor_spl::token_2022::ID] {
+        // Raw account data; RpcClient's own helper asks for jsonParsed
+        let response: Response<Vec<RpcKeyedAccount>> = rpc.send(
+            RpcRequest::GetTokenAccountsByOwner,
+            json!([
+                owner.to_string(),
+                { "programId": token_program.to_string() },
+                { "encoding": UiAccountEncoding::Base64, "commitment": rpc.commitment().commitment },
+            ]),
+        )?;
+        for keyed in response.value {
+            let account: Account = keyed.account.decode().ok_or("Undecodable token account")?;
+            let mint = account.data.get(TOKEN_MINT_OFFSET..TOKEN_MINT_OFFSET + 32);
+            let amount = account.data.get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8);
+            let (Some(mint), Some(amount)) = (mint, amount) else {
+                continue;
+            };
+            accounts.push(TokenAccount {
+                address: Pubkey::from_str(&keyed.pubkey)?,
+                mint: Pubkey::new_from_array(mint.try_into().unwrap()),
+                amount: u64::from_le_bytes(amount.try_into().unwrap()),
+                lamports: account.lamports,
+                token_program,
+            });
+        }
+    }
+    Ok(accounts)
+}
+
+// USD price of each mint a configured Pyth feed covers: WSOL through the SOL feed, and every pair's
+// tokens through their TOKEN_A/B_PYTH_ACCOUNT or stored oracle feeds
+fn usd_prices(rpc: &RpcClient) -> Result<HashMap<Pubkey, f64>, Box<dyn std::error::Error>> {
+    let mut feeds = vec![(
+        Pubkey::from_str(WSOL_MINT)?,
+        env::var("PYTH_SOL_USD_ACCOUNT")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| network::addresses().pyth_sol_usd.to_string()),
+    )];
+    let store = PairStore::from_env();
+    if store.exists() {
+        for pair in store.load()? {
+            if let Some((oracle_a, oracle_b)) = pair.oracle_accounts {
+                feeds.push((Pubkey::from_str(&pair.token_a)?, oracle_a));
+                feeds.push((Pubkey::from_str(&pair.token_b)?, oracle_b));
+            }
+        }
+    } else {
+        for (token, feed) in [("BONK_TOKEN_ADDRESS", "TOKEN_A_PYTH_ACCOUNT"), ("GOAT_TOKEN_ADDRESS", "TOKEN_B_PYTH_ACCOUNT")] {
+            if let (Ok(token), Ok(feed)) = (env::var(token), env::var(feed)) {
+                if !token.is_empty() && !feed.is_empty() {
+                    feeds.push((Pubkey::from_str(&token)?, feed));
+                }
+            }
+        }
+    }
+
+    let mut prices = HashMap::new();
+    for (mint, feed) in feeds {
+        // A feed that can't be read leaves the mint unpriced rather than failing the overview
+        let feed_address = Pubkey::from_str(&feed)?;
+        let price = rpc
+            .get_account_data(&feed_address)
+            .ok()
+            .and_then(|data| {
+                let price: &SolanaPriceAccount = pyth_sdk_solana::state::load_price_account(&data).ok()?;
+                // The SDK's Pubkey comes from a newer solana crate than ours
+                Some(price.to_price_feed(&feed_address.to_bytes().into()))
+            })
+            .map(|feed| feed.get_price_unchecked());
+        match price {
+            Some(price) => {
+                prices.insert(mint, price.price as f64 * 10f64.powi(price.expo));
+            }
+            None => println!("Failed to read Pyth feed {} for {}", feed, mint),
+        }
+    }
+    Ok(prices)
+}
+
+fn print_token_accounts(
+    rpc: &RpcClient,
+    accounts: &[TokenAccount],
+    symbols: &HashMap<String, String>,
+    prices: &HashMap<Pubkey, f64>,
+) -> Result<f64, Box<dyn std::error::Error>> {
+    let mints: Vec<Pubkey> = accounts.iter().map(|account| account.mint).collect();
+    let decimals: Vec<u8> = rpc
+        .get_multiple_accounts_chunked(&mints)?
+        .into_iter()
+        .map(|mint| mint.and_then(|mint| mint.data.get(MINT_DECIMALS_OFFSET).copied()).unwrap_or(0))
+        .collect();
+
+    let mut total_usd = 0.0;
+    for (account, decimals) in accounts.iter().zip(decimals) {
+        let mint = account.mint.to_string();
+        let units = account.amount as f64 / 10f64.powi(decimals as i32);
+        let value = prices.get(&account.mint).map(|price| units * price);
+        total_usd += value.unwrap_or(0.0);
+        println!(
+            "  {:<8} {} {} {} (account {}, rent {:.6} SOL)",
+            symbols.get(&mint).map_or("?", String::as_str),
+            mint,
+            units,
+            value.map_or("unpriced".to_string(), |value| format!("${:.2}", value)),
+            account.address,
+            account.rent_lamports() as f64 / LAMPORTS_PER_SOL as f64,
+        );
+    }
+    Ok(total_usd)
+}
+
+// `balances`: the wallet's SOL and token balances, then the token accounts of the program's vault
+// authority, which custodies route tokens during a trade
+pub async fn run(wallet_keypair_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = read_keypair_file(wallet_keypair_path)?.pubkey();
+    let rpc_url = env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+    let rpc = RpcClient::new(rpc_url);
+    let symbols = match discovery::token_symbols(&http_client::client()).await {
+        Ok(symbols) => symbols,
+        Err(e) => {
+            println!("Failed to fetch token symbols: {}", e);
+            HashMap::new()
+        }
+    };
+    let prices = usd_prices(&rpc)?;
+
+    let lamports = rpc.get_balance(&wallet)?;
+    let sol = lamports as f64 / LAMPORTS_PER_SOL as f64;
+    let sol_usd = prices.get(&Pubkey::from_str(WSOL_MINT)?).map(|price| sol * price);
+    println!("Wallet {}", wallet);
+    println!(
+        "  SOL      {} ({})",
+        sol,
+        sol_usd.map_or("unpriced".to_string(), |value| format!("${:.2}", value))
+    );
+
+    let accounts = token_accounts(&rpc, &wallet)?;
+    let rent: u64 = accounts.iter().map(TokenAccount::rent_lamports).sum();
+    println!(
+        "{} token accounts, {:.6} SOL locked as rent:",
+        accounts.len(),
+        rent as f64 / LAMPORTS_PER_SOL as f64
+    );
+    let tokens_usd = print_token_accounts(&rpc, &accounts, &symbols, &prices)?;
+    println!("Priced total ${:.2}", sol_usd.unwrap_or(0.0) + tokens_usd);
+
+    // Trades empty the vaults before they end, so these should hold nothing between trades
+    let vault_authority = client::vault_authority_address(&client::program_id());
+    let vaults = token_accounts(&rpc, &vault_authority)?;
+    println!("Program vaults (authority {}): {} token accounts", vault_authority, vaults.len());
+    print_token_accounts(&rpc, &vaults, &symbols, &prices)?;
+    Ok(())
+}
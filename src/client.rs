@@ -0,0 +1,234 @@
+use borsh::BorshSerialize;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+// Typed instruction builders for the arbitrage program, mirroring idl/arbitrage_contract.json.
+// Argument structs are Borsh-encoded after Anchor's 8-byte discriminator, exactly like the
+// program's generated instruction decoder expects
+
+pub const PROGRAM_ID: &str = "atXVy7bPRA1j81moNmmhhioKtAAu8XxzUDjN9L8ZUmW";
+
+pub fn program_id() -> Pubkey {
+    Pubkey::from_str(PROGRAM_ID).unwrap()
+}
+
+// Anchor instruction discriminator: the first 8 bytes of sha256("global:<name>")
+pub fn sighash(name: &str) -> [u8; 8] {
+    let hash = solana_sdk::hash::hash(format!("global:{}", name).as_bytes()).to_bytes();
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+// The program's admin config account, checked by every trade instruction
+pub fn config_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], program_id).0
+}
+
+// Programs and pools the admin has approved for swaps
+pub fn whitelist_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"whitelist"], program_id).0
+}
+
+// Owner of the program's vault token accounts (one associated token account per route mint)
+pub fn vault_authority_address(program_id: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(&[b"vault"], program_id).0
+}
+
+// Program's `ConfigParams` argument
+#[derive(Debug, Clone, BorshSerialize)]
+pub struct ConfigParams {
+    pub operator: [u8; 32],
+    pub paused: bool,
+    pub max_trade_size: u64,
+    pub flash_loan_fee_bps: u16,
+    pub max_slippage_bps: u16,
+    pub fee_collector: [u8; 32],
+    pub profit_fee_bps: u16,
+    pub allow_unlisted_jupiter_pools: bool,
+    pub price_oracle: [u8; 32],
+}
+
+#[derive(Debug, Clone, BorshSerialize)]
+pub struct WhitelistArgs {
+    pub programs: Vec<[u8; 32]>,
+    pub pools: Vec<[u8; 32]>,
+}
+
+// Program's `RouteLeg` argument
+#[derive(Debug, Clone, BorshSerialize)]
+pub struct RouteLeg {
+    pub venue: u8,
+    pub input_mint: [u8; 32],
+    pub output_mint: [u8; 32],
+    pub min_amount_out: u64,
+    pub accounts_offset: u8,
+    pub accounts_len: u8,
+    pub venue_data: Vec<u8>,
+}
+
+// Program's `LoanProvider` argument
+#[derive(Debug, Clone, Copy, BorshSerialize)]
+pub enum LoanProvider {
+    Marginfi,
+    Solend { accounts_offset: u8 },
+}
+
+#[derive(Debug, Clone, BorshSerialize)]
+pub struct TryArbitrageArgs {
+    pub amount: u64,
+    pub min_profit_lamports: u64,
+    pub expiry_slot: u64,
+    pub loan_provider: LoanProvider,
+    pub legs: Vec<RouteLeg>,
+}
+
+// Program's `BatchRoute` argument
+#[derive(Debug, Clone, BorshSerialize)]
+pub struct BatchRoute {
+    pub amount: u64,
+    pub min_profit_lamports: u64,
+    pub legs: Vec<RouteLeg>,
+}
+
+#[derive(Debug, Clone, BorshSerialize)]
+pub struct TryArbitrageBatchArgs {
+    pub amount: u64,
+    pub min_profit_lamports: u64,
+    pub expiry_slot: u64,
+    pub loan_provider: LoanProvider,
+    pub routes: Vec<BatchRoute>,
+}
+
+#[derive(Debug, Clone, BorshSerialize)]
+pub struct QuoteArgs {
+    pub amount: u64,
+    pub fee_bps: [u16; 2],
+}
+
+// Named accounts of `try_arbitrage`; the config, whitelist and vault authority PDAs are derived
+#[derive(Debug, Clone)]
+pub struct TryArbitrageAccounts {
+    pub user: Pubkey,
+    pub token_a_account: Pubkey,
+    pub vault: Pubkey,
+    pub token_a_mint: Pubkey,
+    // Only needed to open the WSOL vault
+    pub associated_token_program: Option<Pubkey>,
+    pub token_program: Pubkey,
+    pub profit_destination: Pubkey,
+    pub fee_collector_account: Pubkey,
+    // Pyth feed the minimum profit was priced with, checked on-chain for staleness
+    pub price_oracle: Option<Pubkey>,
+}
+
+impl TryArbitrageAccounts {
+    // Account metas in the program's declaration order; absent optional accounts are passed as
+    // the program id
+    pub fn to_account_metas(&self, program_id: &Pubkey) -> Vec<AccountMeta> {
+        vec![
+            AccountMeta::new(config_address(program_id), false),
+            AccountMeta::new_readonly(whitelist_address(program_id), false),
+            AccountMeta::new(self.user, true),
+            AccountMeta::new(self.token_a_account, false),
+            AccountMeta::new_readonly(vault_authority_address(program_id), false),
+            AccountMeta::new(self.vault, false),
+            AccountMeta::new_readonly(self.token_a_mint, false),
+            AccountMeta::new_readonly(self.associated_token_program.unwrap_or(*program_id), false),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+            AccountMeta::new_readonly(self.token_program, false),
+            AccountMeta::new(self.profit_destination, false),
+            AccountMeta::new(self.fee_collector_account, false),
+            AccountMeta::new_readonly(solana_sdk::sysvar::instructions::id(), false),
+            AccountMeta::new_readonly(self.price_oracle.unwrap_or(*program_id), false),
+        ]
+    }
+}
+
+// `initialize_config`, creating the config PDA with `admin` as its admin
+pub fn initialize_config(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    params: &ConfigParams,
+) -> Result<Instruction, Box<dyn std::error::Error>> {
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(config_address(program_id), false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+        ],
+        data: encode("initialize_config", params)?,
+    })
+}
+
+// `initialize_whitelist`; `admin` must be the config's admin
+pub fn initialize_whitelist(
+    program_id: &Pubkey,
+    admin: &Pubkey,
+    args: &WhitelistArgs,
+) -> Result<Instruction, Box<dyn std::error::Error>> {
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vec![
+            AccountMeta::new(whitelist_address(program_id), false),
+            AccountMeta::new_readonly(config_address(program_id), false),
+            AccountMeta::new(*admin, true),
+            AccountMeta::new_readonly(solana_sdk::system_program::id(), false),
+        ],
+        data: encode("initialize_whitelist", args)?,
+    })
+}
+
+// `try_arbitrage`, with each leg's venue accounts and any loan accounts appended as remaining
+// accounts at the offsets recorded in `args`
+pub fn try_arbitrage(
+    program_id: &Pubkey,
+    accounts: &TryArbitrageAccounts,
+    args: &TryArbitrageArgs,
+    remaining_accounts: Vec<AccountMeta>,
+) -> Result<Instruction, Box<dyn std::error::Error>> {
+    let mut metas = accounts.to_account_metas(program_id);
+    metas.extend(remaining_accounts);
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: metas,
+        data: encode("try_arbitrage", args)?,
+    })
+}
+
+// `try_arbitrage_batch`; takes the same accounts as `try_arbitrage`, with every route's leg accounts
+// in `remaining_accounts`
+pub fn try_arbitrage_batch(
+    program_id: &Pubkey,
+    accounts: &TryArbitrageAccounts,
+    args: &TryArbitrageBatchArgs,
+    remaining_accounts: Vec<AccountMeta>,
+) -> Result<Instruction, Box<dyn std::error::Error>> {
+    let mut metas = accounts.to_account_metas(program_id);
+    metas.extend(remaining_accounts);
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: metas,
+        data: encode("try_arbitrage_batch", args)?,
+    })
+}
+
+// `quote` over the four pool vaults, in the order of the program's `QuotePools` accounts
+pub fn quote(program_id: &Pubkey, vaults: &[Pubkey], args: &QuoteArgs) -> Result<Instruction, Box<dyn std::error::Error>> {
+    if vaults.len() != 4 {
+        return Err("quote needs four vault accounts".into());
+    }
+    Ok(Instruction {
+        program_id: *program_id,
+        accounts: vaults.iter().map(|vault| AccountMeta::new_readonly(*vault, false)).collect(),
+        data: encode("quote", args)?,
+    })
+}
+
+fn encode<T: BorshSerialize>(name: &str, args: &T) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    let mut data = sighash(name).to_vec();
+    args.serialize(&mut data)?;
+    Ok(data)
+}
@@ -0,0 +1,110 @@
+use crate::balances::{self, TokenAccount};
+use crate::pair_store::PairStore;
+use crate::route;
+use anchor_spl::token_2022::spl_token_2022;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Signer};
+use solana_sdk::transaction::Transaction;
+use std::collections::BTreeSet;
+use std::env;
+use std::str::FromStr;
+
+// Closes the wallet's empty token accounts, left over from old pairs and temporary WSOL accounts, and
+// returns their rent to the wallet. Accounts for the mints the configured pairs and routes trade are
+// kept, since trades expect them to exist
+
+// Close instructions per transaction, well within the size limit
+const CLOSES_PER_TRANSACTION: usize = 20;
+
+// Every mint the pairs (the pair store's, or the pair in the environment) and the routes in
+// ROUTES_FILE trade
+fn configured_mints() -> Result<BTreeSet<Pubkey>, Box<dyn std::error::Error>> {
+    let mut mints = BTreeSet::new();
+    let store = PairStore::from_env();
+    if store.exists() {
+        for pair in store.load()? {
+            mints.insert(Pubkey::from_str(&pair.token_a)?);
+            mints.insert(Pubkey::from_str(&pair.token_b)?);
+        }
+    } else {
+        for name in ["BONK_TOKEN_ADDRESS", "GOAT_TOKEN_ADDRESS"] {
+            if let Some(mint) = env::var(name).ok().filter(|v| !v.is_empty()) {
+                mints.insert(Pubkey::from_str(&mint)?);
+            }
+        }
+    }
+    if let Some(routes_file) = env::var("ROUTES_FILE").ok().filter(|v| !v.is_empty()) {
+        for route in route::load_routes(std::path::Path::new(&routes_file))? {
+            for leg in &route.legs {
+                mints.insert(Pubkey::from_str(&leg.input_mint)?);
+                mints.insert(Pubkey::from_str(&leg.output_mint)?);
+            }
+        }
+    }
+    Ok(mints)
+}
+
+// Empty accounts whose mint is not in `keep`
+pub fn closable(accounts: Vec<TokenAccount>, keep: &BTreeSet<Pubkey>) -> Vec<TokenAccount> {
+    accounts
+        .into_iter()
+        .filter(|account| account.amount == 0 && !keep.contains(&account.mint))
+        .collect()
+}
+
+// `close-empty-accounts [--dry-run] [--all]`: lists the closable accounts, then closes them unless
+// --dry-run is given. --all also closes empty accounts of configured mints
+pub fn run(args: &[String], wallet_keypair_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = read_keypair_file(wallet_keypair_path)?;
+    let owner = wallet.pubkey();
+    let rpc_url = env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+    let rpc = RpcClient::new(rpc_url);
+
+    let keep = if args.iter().any(|arg| arg == "--all") {
+        BTreeSet::new()
+    } else {
+        configured_mints()?
+    };
+    let accounts = closable(balances::token_accounts(&rpc, &owner)?, &keep);
+    if accounts.is_empty() {
+        println!("No empty token accounts to close");
+        return Ok(());
+    }
+    let rent: u64 = accounts.iter().map(TokenAccount::rent_lamports).sum();
+    println!(
+        "{} empty token accounts, {:.6} SOL of rent to reclaim:",
+        accounts.len(),
+        rent as f64 / LAMPORTS_PER_SOL as f64
+    );
+    for account in &accounts {
+        println!("  {} (mint {}, {} lamports)", account.address, account.mint, account.rent_lamports());
+    }
+    if args.iter().any(|arg| arg == "--dry-run") {
+        println!("Dry run; nothing closed");
+        return Ok(());
+    }
+
+    // A batch that fails, e.g. on a Token-2022 account still holding withheld fees, doesn't stop the rest
+    let mut closed = 0;
+    for batch in accounts.chunks(CLOSES_PER_TRANSACTION) {
+        let instructions = batch
+            .iter()
+            .map(|account| {
+                spl_token_2022::instruction::close_account(&account.token_program, &account.address, &owner, &owner, &[])
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let transaction =
+            Transaction::new_signed_with_payer(&instructions, Some(&owner), &[&wallet], rpc.get_latest_blockhash()?);
+        match rpc.send_and_confirm_transaction(&transaction) {
+            Ok(signature) => {
+                closed += batch.len();
+                println!("Closed {} accounts in {}", batch.len(), signature);
+            }
+            Err(e) => println!("Failed to close {} accounts: {}", batch.len(), e),
+        }
+    }
+    println!("Closed {} of {} accounts", closed, accounts.len());
+    Ok(())
+}
@@ -0,0 +1,584 @@
+use crate::audit::AuditLog;
+use crate::backtest::{self, BacktestScenario};
+use crate::balances;
+use crate::close_accounts;
+use crate::discovery::{self, DiscoveryConfig};
+use crate::doctor;
+use crate::evaluator::{self, EvaluatorParams};
+use crate::events;
+use crate::http_client;
+use crate::import_history;
+use crate::journal::{self, TradeJournal};
+use crate::market_data;
+use crate::network;
+use crate::notifier::Notifier;
+use crate::pair_stats::PairAnalytics;
+use crate::pair_store::{self, PairStore, StoredPair};
+use crate::reconcile;
+use crate::report::SECONDS_PER_DAY;
+use crate::retry::Backoff;
+use crate::snapshot;
+use crate::test_env;
+use crate::token_list::TokenLists;
+use crate::token_safety::{SafetyConfig, SafetyScorer};
+use crate::withdraw;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::pubsub_client::PubsubClient;
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::{RpcTransactionConfig, RpcTransactionLogsConfig, RpcTransactionLogsFilter};
+use solana_client::rpc_response::RpcConfirmedTransactionStatusWithSignature;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_transaction_status::UiTransactionEncoding;
+use std::collections::HashMap;
+use std::env;
+use std::path::Path;
+use std::str::FromStr;
+use std::time::Duration;
+
+// Value following `--name` in the argument list, if present
+pub fn flag_value(args: &[String], name: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == name)
+        .and_then(|index| args.get(index + 1))
+        .cloned()
+}
+
+// Comma separated list flag, falling back to a single default value
+pub fn flag_list<T: FromStr + Copy>(args: &[String], name: &str, default: T) -> Vec<T> {
+    match flag_value(args, name) {
+        Some(list) => list
+            .split(',')
+            .map(|item| item.trim().parse().ok().unwrap_or_else(|| panic!("Invalid value for {}: {}", name, item)))
+            .collect(),
+        None => vec![default],
+    }
+}
+
+pub fn journal_from_env() -> TradeJournal {
+    TradeJournal::new(env::var("TRADE_JOURNAL_PATH").unwrap_or_else(|_| "trades.jsonl".to_string()))
+}
+
+// `report`: report on the previous UTC day, the same window the monitor sends automatically
+pub async fn report() {
+    let yesterday = journal::unix_timestamp() / SECONDS_PER_DAY - 1;
+    crate::send_daily_report(&journal_from_env(), &Notifier::from_env(), yesterday, &HashMap::new()).await;
+}
+
+// `backtest <data> [--thresholds 0.3,0.5,$2] [--gas-costs 5000,10000000] [--fee-multipliers 1,1.5]`
+pub fn backtest(args: &[String]) {
+    let data_path = args.first().expect("Usage: backtest <recorded data file or directory> [options]");
+    let snapshots = market_data::load_snapshots(Path::new(data_path)).expect("Failed to load recorded market data");
+    println!("Loaded {} snapshots from {}", snapshots.len(), data_path);
+
+    let defaults = EvaluatorParams::default();
+    let slippage_tolerance = flag_list(args, "--slippage", defaults.slippage_tolerance)[0];
+
+    let mut scenarios = Vec::new();
+    for min_profit_threshold in flag_list(args, "--thresholds", defaults.min_profit_threshold) {
+        for estimated_gas_cost in flag_list(args, "--gas-costs", defaults.estimated_gas_cost) {
+            for fee_multiplier in flag_list(args, "--fee-multipliers", 1.0) {
+                scenarios.push(BacktestScenario {
+                    params: EvaluatorParams {
+                        min_profit_threshold,
+                        slippage_tolerance,
+                        estimated_gas_cost,
+                        ..defaults
+                    },
+                    fee_multiplier,
+                });
+            }
+        }
+    }
+
+    let results = backtest::run_grid(&snapshots, &scenarios);
+    print!("{}", backtest::render(&results));
+}
+
+// `replay <opportunity or trade id>`: re-run a journaled or recorded opportunity through the evaluator
+pub fn replay(args: &[String]) {
+    let opportunity_id = args.first().expect("Usage: replay <opportunity or trade id>");
+    let params = EvaluatorParams::default();
+    let mut found = false;
+
+    if let Some(entry) = journal_from_env().find(opportunity_id).expect("Failed to load trade journal") {
+        found = true;
+        println!("Journal entry {} ({:?}) at slot {}", entry.opportunity_id, entry.status, entry.slot);
+        println!("  pair: {}", entry.pair_key());
+        if !entry.trade_id.is_empty() {
+            println!("  trade: {} (its transitions: audit {})", entry.trade_id, entry.trade_id);
+        }
+        println!("  signature: {}", entry.signature.as_deref().unwrap_or("-"));
+        if let Some(error) = &entry.error {
+            println!("  error: {}", error);
+        }
+        if let Some(class) = entry.failure_class {
+            println!("  failure class: {}", class);
+        }
+        println!("  expected profit: {}", entry.expected_profit_lamports);
+        println!("  costs: {:?}", entry.costs);
+        if let Some(quote) = &entry.quote {
+            println!("Re-evaluated from journaled quote {:?}:", quote);
+            println!("{}", evaluator::evaluate(quote, entry.loan_amount, entry.sol_price, &params).explain());
+        }
+    }
+
+    match env::var("RECORD_DIR") {
+        Ok(dir) => {
+            let snapshots = market_data::load_snapshots(Path::new(&dir)).expect("Failed to load recorded market data");
+            if let Some(snapshot) = snapshots.iter().find(|s| &s.opportunity_id == opportunity_id) {
+                found = true;
+                println!("Recorded state for {} at slot {}:", snapshot.pair_key(), snapshot.slot);
+                println!("  sol price: {}", snapshot.sol_price);
+                for account in &snapshot.accounts {
+                    println!("  account {} ({} bytes base64)", account.pubkey, account.data.len());
+                }
+                println!("Re-evaluated from recorded quote {:?}:", snapshot.quote);
+                println!(
+                    "{}",
+                    evaluator::evaluate(&snapshot.quote, snapshot.loan_amount, snapshot.sol_price, &params).explain()
+                );
+            }
+        }
+        Err(_) => println!("RECORD_DIR not set, skipping recorded account state"),
+    }
+
+    if !found {
+        println!("No journal entry or recorded snapshot with id {}", opportunity_id);
+    }
+}
+
+// `audit [<trade id>]`: verifies the audit log's hash chain, then prints one trade's transitions
+pub fn audit(args: &[String]) {
+    let log = AuditLog::new(env::var("AUDIT_LOG_PATH").unwrap_or_else(|_| "audit.jsonl".to_string()));
+    match log.verify() {
+        Ok(count) => println!("{} audit records, hash chain intact", count),
+        Err(e) => {
+            println!("{}", e);
+            std::process::exit(1);
+        }
+    }
+    let Some(trade_id) = args.first() else {
+        return;
+    };
+    let records: Vec<_> = log
+        .load()
+        .expect("Failed to load audit log")
+        .into_iter()
+        .filter(|record| &record.trade_id == trade_id)
+        .collect();
+    if records.is_empty() {
+        println!("No audit records for trade {}", trade_id);
+    }
+    for record in records {
+        println!(
+            "#{} at {} ms: {}",
+            record.seq,
+            record.timestamp_ms,
+            serde_json::to_string(&record.event).unwrap_or_default()
+        );
+    }
+}
+
+// `discover`: one-off run of pair discovery, printing the proposals without adding them
+pub async fn discover() {
+    let config = DiscoveryConfig::from_env();
+    let token_lists = TokenLists::from_env().expect("Invalid token list");
+    let mut pairs = discovery::discover_pairs(&http_client::client(), &config)
+        .await
+        .expect("Pair discovery failed");
+    pairs.retain(|pair| token_lists.check_all([pair.token_a.as_str(), pair.token_b.as_str()]).is_ok());
+
+    // Pairs below the minimum safety score are held back, as in the monitor
+    let safety = SafetyConfig::from_env();
+    let min_score = safety.min_score;
+    if min_score > 0.0 {
+        let http = http_client::client();
+        let scorer = SafetyScorer::new(
+            safety,
+            &env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string()),
+        );
+        for pair in &mut pairs {
+            pair.safety_score = Some(scorer.signals(&http, pair).await.score());
+        }
+    }
+    let (pairs, held): (Vec<_>, Vec<_>) = pairs.into_iter().partition(|pair| pair.safety_score.is_none_or(|score| score >= min_score));
+
+    let print = |pair: &discovery::DiscoveredPair| {
+        let score = pair.safety_score.map_or(String::new(), |score| format!(", safety {:.0}", score));
+        println!(
+            "  {}/{} {} {} on {} (${:.0}{})",
+            pair.symbol_a,
+            pair.symbol_b,
+            pair.token_a,
+            pair.token_b,
+            pair.venues.join(","),
+            pair.liquidity_usd,
+            score
+        );
+    };
+    println!("{} pairs listed on at least {} of {}:", pairs.len(), config.min_venues, config.venues.join(","));
+    pairs.iter().for_each(print);
+    if !held.is_empty() {
+        println!("{} held back below safety score {:.0}:", held.len(), min_score);
+        held.iter().for_each(print);
+    }
+}
+
+// SOLANA_WS_URL, or the websocket endpoint of SOLANA_RPC_URL
+pub fn ws_url() -> String {
+    env::var("SOLANA_WS_URL").ok().filter(|v| !v.is_empty()).unwrap_or_else(|| {
+        env::var("SOLANA_RPC_URL")
+            .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string())
+            .replacen("http", "ws", 1)
+    })
+}
+
+// `listen`: follows the program's logs and journals finalized ArbExecuted events whose
+// transaction is not journaled yet, e.g. trades sent by another instance of the bot. A dropped
+// subscription reconnects with backoff, then journals the program's transactions finalized while
+// it was down
+pub fn listen() {
+    let program = Pubkey::from_str(network::addresses().program).expect("Invalid program id");
+    let ws_url = ws_url();
+    let journal = journal_from_env();
+    let rpc_client = RpcClient::new_with_commitment(
+        env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string()),
+        CommitmentConfig::finalized(),
+    );
+
+    // Newest program transaction seen, where a gap after a reconnect starts
+    let mut last_signature = newest_signatures(&rpc_client, &program, None)
+        .ok()
+        .and_then(|signatures| signatures.first().map(|status| status.signature.clone()));
+    let mut backoff = Backoff::new(Duration::from_millis(500), Duration::from_secs(30));
+    loop {
+        // Finalized, so the monitor has long since journaled its own executions
+        match PubsubClient::logs_subscribe(
+            &ws_url,
+            RpcTransactionLogsFilter::Mentions(vec![program.to_string()]),
+            RpcTransactionLogsConfig {
+                commitment: Some(CommitmentConfig::finalized()),
+            },
+        ) {
+            Ok((_subscription, receiver)) => {
+                backoff.reset();
+                println!("Listening for ArbExecuted events from {} on {}", program, ws_url);
+                if let Some(until) = last_signature.clone() {
+                    match fill_gap(&rpc_client, &journal, &program, &until) {
+                        Ok(Some(newest)) => last_signature = Some(newest),
+                        Ok(None) => {}
+                        Err(e) => println!("Failed to fill the gap since {}: {}", until, e),
+                    }
+                }
+                for response in receiver {
+                    let logs = response.value;
+                    last_signature = Some(logs.signature.clone());
+                    if logs.err.is_none() {
+                        journal_events(&journal, &logs.signature, &logs.logs);
+                    }
+                }
+                println!("Log subscription closed, reconnecting");
+            }
+            Err(e) => println!("Failed to subscribe to program logs: {}", e),
+        }
+        std::thread::sleep(backoff.next_delay());
+    }
+}
+
+// The program's finalized transactions newer than `until`, newest first, up to 1000
+fn newest_signatures(
+    rpc_client: &RpcClient,
+    program: &Pubkey,
+    until: Option<Signature>,
+) -> ClientResult<Vec<RpcConfirmedTransactionStatusWithSignature>> {
+    rpc_client.get_signatures_for_address_with_config(
+        program,
+        GetConfirmedSignaturesForAddress2Config {
+            until,
+            limit: until.is_none().then_some(1),
+            commitment: Some(CommitmentConfig::finalized()),
+            ..GetConfirmedSignaturesForAddress2Config::default()
+        },
+    )
+}
+
+// Journals the events of the program's transactions after `until`, oldest first, returning the
+// newest signature
+fn fill_gap(
+    rpc_client: &RpcClient,
+    journal: &TradeJournal,
+    program: &Pubkey,
+    until: &str,
+) -> Result<Option<String>, Box<dyn std::error::Error>> {
+    let signatures = newest_signatures(rpc_client, program, Some(Signature::from_str(until)?))?;
+    if !signatures.is_empty() {
+        println!("Catching up on {} transactions missed while disconnected", signatures.len());
+    }
+    for status in signatures.iter().rev().filter(|status| status.err.is_none()) {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(CommitmentConfig::finalized()),
+            max_supported_transaction_version: Some(0),
+        };
+        let transaction = rpc_client.get_transaction_with_config(&Signature::from_str(&status.signature)?, config)?;
+        let logs: Option<Vec<String>> = transaction.transaction.meta.and_then(|meta| meta.log_messages.into());
+        journal_events(journal, &status.signature, &logs.unwrap_or_default());
+    }
+    Ok(signatures.first().map(|status| status.signature.clone()))
+}
+
+// Journals the ArbExecuted events in a transaction's logs unless it's journaled already
+fn journal_events(journal: &TradeJournal, signature: &str, logs: &[String]) {
+    for event in events::parse_logs(logs) {
+        match journal.contains_signature(signature) {
+            Ok(true) => continue,
+            Ok(false) => {}
+            Err(e) => {
+                println!("Failed to load trade journal: {}", e);
+                continue;
+            }
+        }
+        println!("Journaling on-chain execution {} ({} lamports net)", signature, event.net_profit);
+        if let Err(e) = journal.record(&event.to_journal_entry(signature)) {
+            println!("Failed to write trade journal: {}", e);
+        }
+    }
+}
+
+// `test-env [--setup-only]`: the monitor against a local validator with the configured pairs' mainnet
+// accounts cloned and the program deployed from target/deploy
+pub fn test_env(args: &[String]) {
+    if let Err(e) = test_env::run(args, "wallet-keypair.json") {
+        println!("Test environment failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `doctor [--network devnet]`: checks the RPC, wallet, program, feeds, token accounts and pools the
+// monitor depends on, exiting non-zero if any check fails
+pub fn doctor(args: &[String]) {
+    if !doctor::run(args, "wallet-keypair.json") {
+        std::process::exit(1);
+    }
+}
+
+// `withdraw-profits [--mint <mint|sol>] [--amount <base units>] [--dry-run]`: sends the wallet's
+// balances above the retained buffers to WITHDRAW_DESTINATION
+pub fn withdraw_profits(args: &[String]) {
+    if let Err(e) = withdraw::run(args, "wallet-keypair.json") {
+        println!("Withdrawal failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `balances`: the wallet's SOL and token balances with USD values and locked rent, and the
+// program's vault balances
+pub async fn balances() {
+    if let Err(e) = balances::run("wallet-keypair.json").await {
+        println!("Failed to read balances: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `snapshot [--out <path>]`: writes the decoded state of the configured pairs' and routes' pools,
+// vaults and oracles at the current slot to a JSON file
+pub fn snapshot(args: &[String]) {
+    if let Err(e) = snapshot::run(args) {
+        println!("Snapshot failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `close-empty-accounts [--dry-run] [--all]`: closes the wallet's empty token accounts, except those
+// of configured mints, and reclaims their rent
+pub fn close_empty_accounts(args: &[String]) {
+    if let Err(e) = close_accounts::run(args, "wallet-keypair.json") {
+        println!("Closing empty accounts failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `reconcile [--since <unix timestamp>] [--record]`: matches the wallet's program transactions on
+// chain to the journal, exiting non-zero if any trade is unrecorded or recorded wrongly
+pub fn reconcile(args: &[String]) {
+    if let Err(e) = reconcile::run(args, "wallet-keypair.json") {
+        println!("Reconciliation failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `import-history [--since <unix timestamp>] [--out <path>] [--force]`: rebuilds the trade journal
+// from the program's transactions and events on chain
+pub fn import_history(args: &[String]) {
+    if let Err(e) = import_history::run(args, "wallet-keypair.json") {
+        println!("History import failed: {}", e);
+        std::process::exit(1);
+    }
+}
+
+// `pairs list | add <token A> <token B> [options] | remove | enable | disable <token A> <token B>`:
+// manages the pair store; a running monitor applies the change on its next cycle
+pub fn pairs(args: &[String]) {
+    if let Err(e) = manage_pairs(args) {
+        println!("{}", e);
+        std::process::exit(1);
+    }
+}
+
+const PAIRS_USAGE: &str = "Usage: pairs list | stats [--since <unix timestamp>] | add <token A> <token B> [--loan-amount <lamports>] [--pools <a,b>] \
+    [--quote-vaults <a,b,c,d>] [--oracles <a,b>] [--strategy <name>] [--execution-policy <name>] [--min-profit-threshold <x>] [--dexes <labels>] [--poll-interval-ms <ms>] \
+    [--slippage-tolerance <percent>] [--slippage-bps <bps>] [--cooldown-secs <secs>] [--venues <venue:pool,...>] \
+    [--disabled] | remove|enable|disable <token A> <token B>";
+
+fn manage_pairs(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let store = PairStore::from_env();
+    let pairs = store.load()?;
+    let action = args.first().ok_or(PAIRS_USAGE)?;
+    if action == "list" {
+        println!("{} pairs in {}:", pairs.len(), store.path.display());
+        for pair in &pairs {
+            println!(
+                "  [{}] {} loan {} strategy {} policy {} threshold {} dexes {} slippage {} min out {} cooldown {} poll {} pools {} quote vaults {} venues {}",
+                if pair.enabled { "on " } else { "off" },
+                pair.key(),
+                pair.loan_amount,
+                pair.strategy.as_deref().unwrap_or("spread"),
+                pair.execution_policy.as_deref().unwrap_or("immediate"),
+                pair.min_profit_threshold.map_or("default".to_string(), |threshold| threshold.to_string()),
+                pair.dexes.as_deref().unwrap_or("any"),
+                pair.slippage_tolerance.map_or("default".to_string(), |tolerance| format!("{}%", tolerance)),
+                pair.slippage_bps.map_or("default".to_string(), |bps| format!("{} bps", bps)),
+                pair.cooldown_secs.map_or("default".to_string(), |secs| format!("{}s", secs)),
+                pair.poll_interval_ms.map_or("tier".to_string(), |ms| format!("{}ms", ms)),
+                pair.pool_accounts.len(),
+                pair.quote_vaults.len(),
+                pair.venues.len() + 2
+            );
+        }
+        return Ok(());
+    }
+    if action == "stats" {
+        let since = match flag_value(args, "--since") {
+            Some(since) => since.parse()?,
+            None => 0,
+        };
+        let entries = journal_from_env().load()?;
+        let analytics = PairAnalytics::from_entries(entries.iter().filter(|entry| entry.timestamp >= since));
+        println!("Journaled performance per pair since {}:", since);
+        print!("{}", analytics.render());
+        return Ok(());
+    }
+
+    let (token_a, token_b) = match (args.get(1), args.get(2)) {
+        (Some(a), Some(b)) => (Pubkey::from_str(a)?.to_string(), Pubkey::from_str(b)?.to_string()),
+        _ => return Err(PAIRS_USAGE.into()),
+    };
+    let key = format!("{}/{}", token_a, token_b);
+    let created = !store.exists();
+    match action.as_str() {
+        "add" => {
+            let existing = pairs.iter().find(|pair| pair.key() == key);
+            let pair = stored_pair_from_args(token_a, token_b, &args[3..], existing)?;
+            TokenLists::from_env()?.check_all([pair.token_a.as_str(), pair.token_b.as_str()])?;
+            let replaced = store.put(pair)?;
+            println!("{} {}", if replaced { "Updated" } else { "Added" }, key);
+        }
+        "remove" => {
+            store.remove(&key)?.ok_or_else(|| format!("No stored pair {}", key))?;
+            println!("Removed {}", key);
+        }
+        "enable" | "disable" => {
+            let enabled = action == "enable";
+            if !store.set_enabled(&key, enabled)? {
+                return Err(format!("No stored pair {}", key).into());
+            }
+            println!("{}d {}", if enabled { "Enable" } else { "Disable" }, key);
+        }
+        _ => return Err(PAIRS_USAGE.into()),
+    }
+    if created {
+        println!(
+            "Created {}; the monitor now takes its pairs from it instead of BONK_TOKEN_ADDRESS and GOAT_TOKEN_ADDRESS",
+            store.path.display()
+        );
+    }
+    Ok(())
+}
+
+// The pair as given by the `pairs add` options, starting from its stored settings when it exists
+pub fn stored_pair_from_args(
+    token_a: String,
+    token_b: String,
+    args: &[String],
+    existing: Option<&StoredPair>,
+) -> Result<StoredPair, Box<dyn std::error::Error>> {
+    let list = |name: &str| {
+        flag_value(args, name).map(|list| list.split(',').map(|item| item.trim().to_string()).filter(|item| !item.is_empty()).collect::<Vec<_>>())
+    };
+    let mut pair = existing.cloned().unwrap_or_else(|| StoredPair {
+        token_a,
+        token_b,
+        loan_amount: env::var("LOAN_AMOUNT").ok().and_then(|v| v.parse().ok()).unwrap_or(500_000_000),
+        enabled: true,
+        pool_accounts: Vec::new(),
+        quote_vaults: Vec::new(),
+        oracle_accounts: None,
+        strategy: None,
+        execution_policy: None,
+        min_profit_threshold: None,
+        dexes: None,
+        poll_interval_ms: None,
+        slippage_tolerance: None,
+        slippage_bps: None,
+        cooldown_secs: None,
+        venues: Vec::new(),
+    });
+    if let Some(loan_amount) = flag_value(args, "--loan-amount") {
+        pair.loan_amount = loan_amount.parse()?;
+    }
+    if let Some(pools) = list("--pools") {
+        pair.pool_accounts = pools;
+    }
+    if let Some(vaults) = list("--quote-vaults") {
+        pair.quote_vaults = vaults;
+    }
+    if let Some(oracles) = list("--oracles") {
+        match oracles.as_slice() {
+            [oracle_a, oracle_b] => pair.oracle_accounts = Some((oracle_a.clone(), oracle_b.clone())),
+            _ => return Err("--oracles takes the token A and token B feeds".into()),
+        }
+    }
+    if let Some(strategy) = flag_value(args, "--strategy") {
+        pair.strategy = Some(strategy);
+    }
+    if let Some(policy) = flag_value(args, "--execution-policy") {
+        pair.execution_policy = Some(policy);
+    }
+    if let Some(threshold) = flag_value(args, "--min-profit-threshold") {
+        pair.min_profit_threshold = Some(threshold.parse()?);
+    }
+    if let Some(dexes) = flag_value(args, "--dexes") {
+        pair.dexes = Some(dexes);
+    }
+    if let Some(interval) = flag_value(args, "--poll-interval-ms") {
+        pair.poll_interval_ms = Some(interval.parse()?);
+    }
+    if let Some(tolerance) = flag_value(args, "--slippage-tolerance") {
+        pair.slippage_tolerance = Some(tolerance.parse()?);
+    }
+    if let Some(bps) = flag_value(args, "--slippage-bps") {
+        pair.slippage_bps = Some(bps.parse()?);
+    }
+    if let Some(cooldown) = flag_value(args, "--cooldown-secs") {
+        pair.cooldown_secs = Some(cooldown.parse()?);
+    }
+    if let Some(venues) = flag_value(args, "--venues") {
+        pair.venues = pair_store::parse_venues(&venues)?;
+    }
+    if args.iter().any(|arg| arg == "--disabled") {
+        pair.enabled = false;
+    }
+    Ok(pair)
+}
@@ -0,0 +1,133 @@
+use solana_sdk::clock::Slot;
+use std::collections::{BTreeSet, HashMap, VecDeque};
+use std::env;
+
+// Other bots arbitraging the monitored pairs. A transaction from another wallet that writes both
+// of a pair's pools is taken for an arbitrage of the pair; the pair's competition score is the
+// share of its latest checks whose slot, or the one after, saw one. Contested pairs get a wider
+// profit threshold and a higher priority fee, scaling with the score
+
+#[derive(Debug, Clone)]
+pub struct CompetitionConfig {
+    // Latest checks per pair the score is taken over
+    pub window: usize,
+    // Percentage points added to the profit threshold at a score of 1
+    pub threshold_bump: f64,
+    // Priority fee multiplier at a score of 1
+    pub fee_multiplier: f64,
+}
+
+impl CompetitionConfig {
+    pub fn from_env() -> Self {
+        let parse = |name: &str, default: f64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Self {
+            window: parse("COMPETITION_WINDOW", 50.0) as usize,
+            threshold_bump: parse("COMPETITION_THRESHOLD_BUMP", 0.5),
+            fee_multiplier: parse("COMPETITION_FEE_MULTIPLIER", 3.0),
+        }
+    }
+}
+
+pub struct CompetitionTracker {
+    config: CompetitionConfig,
+    // Per pair, the slots of its latest checks and the slots others arbitraged it in since the
+    // oldest of them
+    checks: HashMap<String, VecDeque<Slot>>,
+    arbitraged: HashMap<String, BTreeSet<Slot>>,
+}
+
+impl CompetitionTracker {
+    pub fn new(config: CompetitionConfig) -> Self {
+        Self {
+            config,
+            checks: HashMap::new(),
+            arbitraged: HashMap::new(),
+        }
+    }
+
+    // Another wallet arbitraged the pair in `slot`
+    pub fn record_arbitrage(&mut self, pair_key: &str, slot: Slot) {
+        self.arbitraged.entry(pair_key.to_string()).or_default().insert(slot);
+    }
+
+    // The pair was checked at `slot`
+    pub fn record_check(&mut self, pair_key: &str, slot: Slot) {
+        let checks = self.checks.entry(pair_key.to_string()).or_default();
+        checks.push_back(slot);
+        if checks.len() > self.config.window {
+            checks.pop_front();
+        }
+        if let (Some(&oldest), Some(arbitraged)) = (checks.front(), self.arbitraged.get_mut(pair_key)) {
+            arbitraged.retain(|slot| *slot >= oldest);
+        }
+    }
+
+    // Share of the pair's latest checks another wallet arbitraged it at, from 0 to 1
+    pub fn score(&self, pair_key: &str) -> f64 {
+        let (Some(checks), Some(arbitraged)) = (self.checks.get(pair_key), self.arbitraged.get(pair_key)) else {
+            return 0.0;
+        };
+        if checks.is_empty() {
+            return 0.0;
+        }
+        let contested = checks
+            .iter()
+            .filter(|slot| arbitraged.range(**slot..=*slot + 1).next().is_some())
+            .count();
+        contested as f64 / checks.len() as f64
+    }
+
+    // Percentage points to add to the pair's profit threshold
+    pub fn threshold_bump(&self, pair_key: &str) -> f64 {
+        self.config.threshold_bump * self.score(pair_key)
+    }
+
+    // Factor to raise the pair's priority fee by
+    pub fn fee_multiplier(&self, pair_key: &str) -> f64 {
+        1.0 + (self.config.fee_multiplier - 1.0).max(0.0) * self.score(pair_key)
+    }
+
+    // Prometheus text format, one gauge per checked pair
+    pub fn metrics(&self) -> String {
+        let mut text = String::from(
+            "# HELP arb_competition_score Share of a pair's latest checks other wallets arbitraged it at\n\
+             # TYPE arb_competition_score gauge\n",
+        );
+        let mut pairs: Vec<&String> = self.checks.keys().collect();
+        pairs.sort();
+        for pair in pairs {
+            text.push_str(&format!("arb_competition_score{{pair=\"{}\"}} {}\n", pair, self.score(pair)));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn score_is_the_share_of_checks_others_arbitraged() {
+        let mut tracker = CompetitionTracker::new(CompetitionConfig {
+            window: 4,
+            threshold_bump: 0.5,
+            fee_multiplier: 3.0,
+        });
+        // An arbitrage in the check's slot or the next one contests it
+        tracker.record_arbitrage("A/B", 103);
+        for slot in [100, 102, 103, 110] {
+            tracker.record_check("A/B", slot);
+        }
+        assert_eq!(tracker.score("A/B"), 0.5);
+        assert_eq!(tracker.threshold_bump("A/B"), 0.25);
+        assert_eq!(tracker.fee_multiplier("A/B"), 2.0);
+        assert!(tracker.metrics().contains("arb_competition_score{pair=\"A/B\"} 0.5\n"));
+
+        // Once the contested checks leave the window, so does the arbitrage
+        for slot in [111, 112, 113] {
+            tracker.record_check("A/B", slot);
+        }
+        assert_eq!(tracker.score("A/B"), 0.0);
+        assert_eq!(tracker.fee_multiplier("C/D"), 1.0);
+    }
+}
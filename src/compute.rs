@@ -0,0 +1,141 @@
+use crate::costs::LAMPORTS_PER_SIGNATURE;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::instruction::Instruction;
+use std::collections::{HashMap, VecDeque};
+use std::env;
+
+// Per-transaction compute unit cap; simulations run at this limit so they never hit the meter
+pub const MAX_COMPUTE_UNITS: u32 = 1_400_000;
+// Recent simulations kept per pair or route
+const SAMPLE_WINDOW: usize = 20;
+
+#[derive(Debug, Clone)]
+pub struct ComputeConfig {
+    // Priority fee per compute unit
+    pub price_micro_lamports: u64,
+    // Headroom added on top of the highest recent usage
+    pub margin: f64,
+    // Limit used before a pair or route has been simulated
+    pub default_units: u32,
+}
+
+impl ComputeConfig {
+    pub fn from_env() -> Self {
+        Self {
+            price_micro_lamports: env::var("CU_PRICE_MICRO_LAMPORTS").ok().and_then(|v| v.parse().ok()).unwrap_or(0),
+            margin: env::var("CU_LIMIT_MARGIN").ok().and_then(|v| v.parse().ok()).unwrap_or(0.1),
+            default_units: env::var("CU_DEFAULT_UNITS").ok().and_then(|v| v.parse().ok()).unwrap_or(400_000),
+        }
+    }
+}
+
+// Compute unit estimates per pair or route, calibrated from `units_consumed` in simulations
+pub struct ComputeUnitEstimator {
+    config: ComputeConfig,
+    samples: HashMap<String, VecDeque<u64>>,
+    // Factors the priority fee is raised by, per pair or route
+    price_multipliers: HashMap<String, f64>,
+}
+
+impl ComputeUnitEstimator {
+    pub fn new(config: ComputeConfig) -> Self {
+        Self {
+            config,
+            samples: HashMap::new(),
+            price_multipliers: HashMap::new(),
+        }
+    }
+
+    pub fn observe(&mut self, key: &str, units_consumed: u64) {
+        let samples = self.samples.entry(key.to_string()).or_default();
+        samples.push_back(units_consumed);
+        if samples.len() > SAMPLE_WINDOW {
+            samples.pop_front();
+        }
+    }
+
+    pub fn is_calibrated(&self, key: &str) -> bool {
+        self.samples.get(key).is_some_and(|samples| !samples.is_empty())
+    }
+
+    // Tight limit: the highest recent usage plus the configured margin
+    pub fn limit(&self, key: &str) -> u32 {
+        let peak = match self.samples.get(key).and_then(|samples| samples.iter().max()) {
+            Some(&peak) => peak,
+            None => return self.config.default_units,
+        };
+        ((peak as f64 * (1.0 + self.config.margin)).ceil() as u32).min(MAX_COMPUTE_UNITS)
+    }
+
+    pub fn set_price_multiplier(&mut self, key: &str, multiplier: f64) {
+        self.price_multipliers.insert(key.to_string(), multiplier);
+    }
+
+    // Priority fee per compute unit for `key`
+    pub fn price(&self, key: &str) -> u64 {
+        let multiplier = self.price_multipliers.get(key).copied().unwrap_or(1.0);
+        (self.config.price_micro_lamports as f64 * multiplier).round() as u64
+    }
+
+    // Expected fee in lamports; the priority fee is charged on the requested limit, not on usage
+    pub fn fee_lamports(&self, key: &str) -> u64 {
+        LAMPORTS_PER_SIGNATURE + self.limit(key) as u64 * self.price(key) / 1_000_000
+    }
+
+    // Compute budget instructions to prepend to a transaction for `key`
+    pub fn budget_instructions(&self, key: &str) -> Vec<Instruction> {
+        self.budget_instructions_at(self.limit(key), self.price(key))
+    }
+
+    pub fn budget_instructions_with_limit(&self, units: u32) -> Vec<Instruction> {
+        self.budget_instructions_at(units, self.config.price_micro_lamports)
+    }
+
+    fn budget_instructions_at(&self, units: u32, price_micro_lamports: u64) -> Vec<Instruction> {
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(units)];
+        if price_micro_lamports > 0 {
+            instructions.push(ComputeBudgetInstruction::set_compute_unit_price(price_micro_lamports));
+        }
+        instructions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn limit_is_calibrated_from_the_peak_of_recent_simulations() {
+        let mut estimator = ComputeUnitEstimator::new(ComputeConfig {
+            price_micro_lamports: 1_000,
+            margin: 0.5,
+            default_units: 400_000,
+        });
+        assert!(!estimator.is_calibrated("A/B"));
+        assert_eq!(estimator.limit("A/B"), 400_000);
+        estimator.observe("A/B", 100_000);
+        estimator.observe("A/B", 200_000);
+        assert_eq!(estimator.limit("A/B"), 300_000);
+        // The fee is charged on the limit, not on what's used
+        assert_eq!(estimator.fee_lamports("A/B"), LAMPORTS_PER_SIGNATURE + 300);
+        estimator.observe("C/D", 2_000_000);
+        assert_eq!(estimator.limit("C/D"), MAX_COMPUTE_UNITS);
+
+        estimator.set_price_multiplier("A/B", 2.5);
+        assert_eq!(estimator.price("A/B"), 2_500);
+        assert_eq!(
+            estimator.budget_instructions("A/B"),
+            vec![
+                ComputeBudgetInstruction::set_compute_unit_limit(300_000),
+                ComputeBudgetInstruction::set_compute_unit_price(2_500),
+            ]
+        );
+        // A zero priority fee leaves the price instruction out
+        let free = ComputeUnitEstimator::new(ComputeConfig {
+            price_micro_lamports: 0,
+            margin: 0.0,
+            default_units: 1,
+        });
+        assert_eq!(free.budget_instructions_with_limit(300_000).len(), 1);
+    }
+}
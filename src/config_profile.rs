@@ -0,0 +1,106 @@
+use crate::commands;
+use std::env;
+use std::sync::OnceLock;
+
+// Named bundles of threshold, fee and risk settings, picked with `--config-profile <name>` or
+// CONFIG_PROFILE instead of setting each variable, or switched through the control API while the
+// monitor runs. A profile overwrites the environment like the devnet registry does; <PROFILE>_<NAME>,
+// e.g. CONSERVATIVE_MIN_PROFIT_THRESHOLD, replaces one of its values. The monitor journals the
+// active profile on every entry, so results can be compared across profiles
+
+const CONSERVATIVE: &[(&str, &str)] = &[
+    ("MIN_PROFIT_THRESHOLD", "1.0"),
+    ("SLIPPAGE_TOLERANCE", "0.1"),
+    ("SLIPPAGE_BPS", "100"),
+    ("SLIPPAGE_MAX_BPS", "200"),
+    ("JITO_TIP_PROFIT_SHARE", "0.05"),
+    ("JITO_TIP_MAX_LAMPORTS", "1000000"),
+    ("EXECUTION_POLICY", "confirm:2"),
+    ("TRADE_COOLDOWN_SECS", "30"),
+    ("OPPORTUNITY_QUEUE_CAPACITY", "2"),
+    ("COMPETITION_DETECTION", "true"),
+];
+
+const AGGRESSIVE: &[(&str, &str)] = &[
+    ("MIN_PROFIT_THRESHOLD", "0.2"),
+    ("SLIPPAGE_TOLERANCE", "0.2"),
+    ("SLIPPAGE_BPS", "300"),
+    ("SLIPPAGE_MAX_BPS", "800"),
+    ("ADAPTIVE_SLIPPAGE", "true"),
+    ("ADAPTIVE_THRESHOLDS", "true"),
+    ("JITO_TIP_PROFIT_SHARE", "0.3"),
+    ("JITO_TIP_MAX_LAMPORTS", "20000000"),
+    ("EXECUTION_POLICY", "immediate"),
+    ("TRADE_COOLDOWN_SECS", "0"),
+    ("OPPORTUNITY_QUEUE_CAPACITY", "16"),
+];
+
+// Trades small and on any spread, journaled apart from real results
+const TESTING: &[(&str, &str)] = &[
+    ("MIN_PROFIT_THRESHOLD", "0"),
+    ("SLIPPAGE_TOLERANCE", "1.0"),
+    ("LOAN_AMOUNT", "10000000"),
+    ("DISCOVERY_LOAN_AMOUNT", "10000000"),
+    ("DISCOVERY_AUTO_ADD", "false"),
+    ("ADAPTIVE_THRESHOLDS", "false"),
+    ("TRADE_JOURNAL_PATH", "trades-testing.jsonl"),
+];
+
+pub const PROFILES: &[(&str, &[(&str, &str)])] =
+    &[("conservative", CONSERVATIVE), ("aggressive", AGGRESSIVE), ("testing", TESTING)];
+
+// The settings of `name`
+pub fn settings(name: &str) -> Result<&'static [(&'static str, &'static str)], String> {
+    PROFILES
+        .iter()
+        .find(|(profile, _)| *profile == name)
+        .map(|(_, settings)| *settings)
+        .ok_or_else(|| {
+            let names: Vec<&str> = PROFILES.iter().map(|(profile, _)| *profile).collect();
+            format!("Unknown config profile {}; expected one of {}", name, names.join(", "))
+        })
+}
+
+// `--config-profile <name>`, falling back to CONFIG_PROFILE; None when neither is set
+pub fn from_args(args: &[String]) -> Option<String> {
+    commands::flag_value(args, "--config-profile").or_else(|| env::var("CONFIG_PROFILE").ok().filter(|v| !v.is_empty()))
+}
+
+// Every setting some profile sets, as the environment had it before the first profile was applied
+static BASELINE: OnceLock<Vec<(&'static str, Option<String>)>> = OnceLock::new();
+
+fn baseline() -> &'static [(&'static str, Option<String>)] {
+    BASELINE.get_or_init(|| {
+        let mut names: Vec<&str> = PROFILES.iter().flat_map(|(_, settings)| settings.iter().map(|(setting, _)| *setting)).collect();
+        names.sort();
+        names.dedup();
+        names.into_iter().map(|name| (name, env::var(name).ok())).collect()
+    })
+}
+
+// Overwrites the environment with the profile's settings, and leaves its name in CONFIG_PROFILE
+// for the journal
+pub fn apply(name: &str) -> Result<(), String> {
+    baseline();
+    let prefix = name.to_uppercase();
+    for (setting, value) in settings(name)? {
+        let value = env::var(format!("{}_{}", prefix, setting)).unwrap_or_else(|_| value.to_string());
+        env::set_var(setting, value);
+    }
+    env::set_var("CONFIG_PROFILE", name);
+    println!("Using the {} config profile", name);
+    Ok(())
+}
+
+// Replaces the applied profile with `name`: the settings only the old profile set go back to their
+// values from before any profile, then the new profile is applied
+pub fn switch(name: &str) -> Result<(), String> {
+    settings(name)?;
+    for (setting, value) in baseline() {
+        match value {
+            Some(value) => env::set_var(setting, value),
+            None => env::remove_var(setting),
+        }
+    }
+    apply(name)
+}
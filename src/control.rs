@@ -0,0 +1,306 @@
+use crate::config_profile;
+use crate::pair_stats::PairAnalytics;
+use crate::pair_store::{PairStore, StoredPair};
+use crate::spread_matrix::SpreadMatrix;
+use crate::token_list::TokenLists;
+use axum::body::Bytes;
+use axum::extract::{Path, State};
+use axum::http::{header, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{get, post};
+use axum::Router;
+use serde::Serialize;
+use std::env;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+// HTTP control API for the running monitor (CONTROL_API_ADDR), JSON in and out:
+//   GET    /pairs                               the pair store
+//   POST   /pairs                               adds a pair, or replaces the one with its tokens
+//   DELETE /pairs/<token A>/<token B>           removes a pair
+//   POST   /pairs/<token A>/<token B>/enable    and /disable
+//   GET    /analytics                           each pair's performance since the monitor started
+//   GET    /spreads                             each pair's venue spread matrix at its latest check
+//   GET    /profile                             the active config profile and the ones to pick from
+//   PUT    /profile                             switches to {"name": <profile>}
+// Pair changes are written to the pair store and wake the monitor, which reloads it at once; a
+// profile switch wakes it too and is applied before its next scan. There is no authentication, so
+// bind it to localhost or a private network
+
+pub struct ControlConfig {
+    // e.g. 127.0.0.1:8700
+    pub addr: SocketAddr,
+}
+
+impl ControlConfig {
+    pub fn from_env() -> Option<Self> {
+        env::var("CONTROL_API_ADDR")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|addr| Self {
+                addr: addr.parse().expect("Invalid CONTROL_API_ADDR"),
+            })
+    }
+}
+
+// What the API shares with the monitor
+pub struct Control {
+    // None when the monitor trades the pair configured in the environment
+    pairs_path: Option<PathBuf>,
+    token_lists: TokenLists,
+    // The monitor's own, updated as it evaluates and trades
+    pair_analytics: Arc<Mutex<PairAnalytics>>,
+    spread_matrix: Arc<Mutex<SpreadMatrix>>,
+    pairs_changed: AtomicBool,
+    // Set by the monitor, at startup and after each switch
+    active_profile: Mutex<Option<String>>,
+    requested_profile: Mutex<Option<String>>,
+    wake: Notify,
+}
+
+impl Control {
+    pub fn new(
+        pairs_path: Option<PathBuf>,
+        token_lists: TokenLists,
+        pair_analytics: Arc<Mutex<PairAnalytics>>,
+        spread_matrix: Arc<Mutex<SpreadMatrix>>,
+    ) -> Self {
+        Self {
+            pairs_path,
+            token_lists,
+            pair_analytics,
+            spread_matrix,
+            pairs_changed: AtomicBool::new(false),
+            active_profile: Mutex::new(None),
+            requested_profile: Mutex::new(None),
+            wake: Notify::new(),
+        }
+    }
+
+    // Whether the API changed the pair store since the last call
+    pub fn take_pairs_changed(&self) -> bool {
+        self.pairs_changed.swap(false, Ordering::Relaxed)
+    }
+
+    // The profile switch asked for since the last call, the latest when there were several
+    pub fn take_requested_profile(&self) -> Option<String> {
+        self.requested_profile.lock().unwrap().take()
+    }
+
+    pub fn set_active_profile(&self, name: Option<String>) {
+        *self.active_profile.lock().unwrap() = name;
+    }
+
+    // Resolves once the API has changed something, at once if it did since the last wake
+    pub async fn woken(&self) {
+        self.wake.notified().await
+    }
+
+    fn pairs_changed(&self) {
+        self.pairs_changed.store(true, Ordering::Relaxed);
+        self.wake.notify_one();
+    }
+
+    fn store(&self) -> Result<PairStore, ApiError> {
+        match &self.pairs_path {
+            Some(path) => Ok(PairStore::new(path.clone())),
+            None => Err(ApiError(
+                StatusCode::CONFLICT,
+                "The monitor trades the pair in its environment; create the pair store with `pairs add` and restart it".to_string(),
+            )),
+        }
+    }
+}
+
+struct ApiError(StatusCode, String);
+
+impl From<Box<dyn std::error::Error>> for ApiError {
+    fn from(e: Box<dyn std::error::Error>) -> Self {
+        Self(StatusCode::INTERNAL_SERVER_ERROR, e.to_string())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.0, json(&serde_json::json!({ "error": self.1 }))).into_response()
+    }
+}
+
+fn json(value: &impl Serialize) -> Response {
+    match serde_json::to_string_pretty(value) {
+        Ok(body) => ([(header::CONTENT_TYPE, "application/json")], body).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+fn not_found(key: &str) -> ApiError {
+    ApiError(StatusCode::NOT_FOUND, format!("No stored pair {}", key))
+}
+
+// Serves the API for as long as the process runs
+pub fn spawn(config: ControlConfig, control: Arc<Control>) {
+    tokio::spawn(async move {
+        println!("Control API listening on {}", config.addr);
+        if let Err(e) = axum::Server::bind(&config.addr).serve(router(control).into_make_service()).await {
+            println!("Control API stopped: {}", e);
+        }
+    });
+}
+
+fn router(control: Arc<Control>) -> Router {
+    Router::new()
+        .route("/pairs", get(list_pairs).post(put_pair))
+        .route("/pairs/:token_a/:token_b", axum::routing::delete(remove_pair))
+        .route("/pairs/:token_a/:token_b/enable", post(enable_pair))
+        .route("/pairs/:token_a/:token_b/disable", post(disable_pair))
+        .route("/analytics", get(pair_analytics))
+        .route("/spreads", get(spread_matrix))
+        .route("/profile", get(profile).put(switch_profile))
+        .with_state(control)
+}
+
+async fn list_pairs(State(control): State<Arc<Control>>) -> Result<Response, ApiError> {
+    Ok(json(&control.store()?.load()?))
+}
+
+// The body is a pair as stored, with the same defaults for the fields left out
+async fn put_pair(State(control): State<Arc<Control>>, body: Bytes) -> Result<Response, ApiError> {
+    let store = control.store()?;
+    let pair: StoredPair = serde_json::from_slice(&body).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+    pair.validate().map_err(|e| ApiError(StatusCode::BAD_REQUEST, e))?;
+    control
+        .token_lists
+        .check_all([pair.token_a.as_str(), pair.token_b.as_str()])
+        .map_err(|e| ApiError(StatusCode::BAD_REQUEST, e))?;
+    let status = if store.put(pair.clone())? { StatusCode::OK } else { StatusCode::CREATED };
+    control.pairs_changed();
+    Ok((status, json(&pair)).into_response())
+}
+
+async fn remove_pair(State(control): State<Arc<Control>>, Path((token_a, token_b)): Path<(String, String)>) -> Result<Response, ApiError> {
+    let key = format!("{}/{}", token_a, token_b);
+    let removed = control.store()?.remove(&key)?.ok_or_else(|| not_found(&key))?;
+    control.pairs_changed();
+    Ok(json(&removed))
+}
+
+async fn enable_pair(State(control): State<Arc<Control>>, Path((token_a, token_b)): Path<(String, String)>) -> Result<Response, ApiError> {
+    set_enabled(&control, &format!("{}/{}", token_a, token_b), true)
+}
+
+async fn disable_pair(State(control): State<Arc<Control>>, Path((token_a, token_b)): Path<(String, String)>) -> Result<Response, ApiError> {
+    set_enabled(&control, &format!("{}/{}", token_a, token_b), false)
+}
+
+fn set_enabled(control: &Control, key: &str, enabled: bool) -> Result<Response, ApiError> {
+    if !control.store()?.set_enabled(key, enabled)? {
+        return Err(not_found(key));
+    }
+    control.pairs_changed();
+    Ok(json(&serde_json::json!({ "pair": key, "enabled": enabled })))
+}
+
+async fn pair_analytics(State(control): State<Arc<Control>>) -> Response {
+    json(&control.pair_analytics.lock().unwrap().to_json())
+}
+
+async fn spread_matrix(State(control): State<Arc<Control>>) -> Response {
+    json(&control.spread_matrix.lock().unwrap().to_json())
+}
+
+async fn profile(State(control): State<Arc<Control>>) -> Response {
+    let profiles: Vec<&str> = config_profile::PROFILES.iter().map(|(name, _)| *name).collect();
+    json(&serde_json::json!({ "active": *control.active_profile.lock().unwrap(), "profiles": profiles }))
+}
+
+#[derive(serde::Deserialize)]
+struct ProfileRequest {
+    name: String,
+}
+
+// Accepted rather than done: the monitor switches between scans
+async fn switch_profile(State(control): State<Arc<Control>>, body: Bytes) -> Result<Response, ApiError> {
+    let request: ProfileRequest = serde_json::from_slice(&body).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e.to_string()))?;
+    config_profile::settings(&request.name).map_err(|e| ApiError(StatusCode::BAD_REQUEST, e))?;
+    *control.requested_profile.lock().unwrap() = Some(request.name.clone());
+    control.wake.notify_one();
+    Ok((StatusCode::ACCEPTED, json(&serde_json::json!({ "requested": request.name }))).into_response())
+}
+
+// Serves the API on a free local port and returns its URL
+#[cfg(test)]
+pub fn serve_local(control: Arc<Control>) -> String {
+    let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(axum::Server::from_tcp(listener).unwrap().serve(router(control).into_make_service()));
+    format!("http://{}", addr)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::pubkey::Pubkey;
+
+    fn pair_json(token_a: &str, token_b: &str) -> serde_json::Value {
+        serde_json::json!({ "token_a": token_a, "token_b": token_b, "loan_amount": 1_000_000 })
+    }
+
+    #[tokio::test]
+    async fn pair_changes_are_stored_and_wake_the_monitor() {
+        let path = std::env::temp_dir().join(format!("control-pairs-{}.json", std::process::id()));
+        let control = Arc::new(Control::new(Some(path.clone()), TokenLists::default(), Arc::default(), Arc::default()));
+        let url = serve_local(control.clone());
+        let http = reqwest::Client::new();
+        let (token_a, token_b) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+
+        let added = http.post(format!("{}/pairs", url)).json(&pair_json(&token_a, &token_b)).send().await.unwrap();
+        assert_eq!(added.status(), reqwest::StatusCode::CREATED);
+        assert!(control.take_pairs_changed());
+        assert!(!control.take_pairs_changed());
+        // Woken at once by the change made before it waited
+        tokio::time::timeout(std::time::Duration::from_secs(1), control.woken()).await.unwrap();
+
+        let disabled = http.post(format!("{}/pairs/{}/{}/disable", url, token_a, token_b)).send().await.unwrap();
+        assert_eq!(disabled.status(), reqwest::StatusCode::OK);
+        let listed: Vec<StoredPair> = http.get(format!("{}/pairs", url)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(listed.len(), 1);
+        assert!(!listed[0].enabled);
+
+        let removed = http.delete(format!("{}/pairs/{}/{}", url, token_a, token_b)).send().await.unwrap();
+        assert_eq!(removed.status(), reqwest::StatusCode::OK);
+        assert!(PairStore::new(path.clone()).load().unwrap().is_empty());
+        let missing = http.delete(format!("{}/pairs/{}/{}", url, token_a, token_b)).send().await.unwrap();
+        assert_eq!(missing.status(), reqwest::StatusCode::NOT_FOUND);
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[tokio::test]
+    async fn invalid_and_listed_out_pairs_are_rejected() {
+        let path = std::env::temp_dir().join(format!("control-rejected-{}.json", std::process::id()));
+        let blocked = Pubkey::new_unique().to_string();
+        let token_lists = TokenLists {
+            allowlist: None,
+            blocklist: [blocked.clone()].into_iter().collect(),
+        };
+        let control = Arc::new(Control::new(Some(path.clone()), token_lists, Arc::default(), Arc::default()));
+        let url = serve_local(control.clone());
+        let http = reqwest::Client::new();
+
+        let no_loan = serde_json::json!({ "token_a": Pubkey::new_unique().to_string(), "token_b": Pubkey::new_unique().to_string(), "loan_amount": 0 });
+        let listed_out = pair_json(&blocked, &Pubkey::new_unique().to_string());
+        for body in [no_loan, listed_out, serde_json::json!({ "token_a": "x" })] {
+            let response = http.post(format!("{}/pairs", url)).json(&body).send().await.unwrap();
+            assert_eq!(response.status(), reqwest::StatusCode::BAD_REQUEST);
+        }
+        assert!(!control.take_pairs_changed());
+        assert!(!path.exists());
+
+        // Without a pair store there is nothing to edit
+        let url = serve_local(Arc::new(Control::new(None, TokenLists::default(), Arc::default(), Arc::default())));
+        let response = http.get(format!("{}/pairs", url)).send().await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::CONFLICT);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::events::{self, ArbExecutedEvent};
+use crate::network;
+use crate::rpc::RpcApi;
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::{clock::Slot, commitment_config::CommitmentConfig, pubkey::Pubkey, signature::Signature};
+use solana_transaction_status::{
+    EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding, UiTransactionStatusMeta, UiTransactionTokenBalance,
+};
+use std::str::FromStr;
+
+pub const WSOL_MINT: &str = network::MAINNET.wsol;
+
+// Base fee charged per signature, independent of any priority fee
+pub const LAMPORTS_PER_SIGNATURE: u64 = 5000;
+
+// Fee rates charged by the program's flash loan source and the two venues, in bps
+pub const MANGO_FLASH_LOAN_FEE_BPS: u64 = 20;
+pub const JUPITER_FEE_BPS: u64 = 30;
+pub const RAYDIUM_FEE_BPS: u64 = 25;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CostBreakdown {
+    pub base_fee_lamports: u64,
+    pub priority_fee_lamports: u64,
+    pub jito_tip_lamports: u64,
+    pub flash_loan_fee_lamports: u64,
+    pub dex_fee_lamports: u64,
+    // Profit share paid to the program's fee collector
+    #[serde(default)]
+    pub profit_fee_lamports: u64,
+    pub rent_paid_lamports: u64,
+    pub rent_reclaimed_lamports: u64,
+}
+
+impl CostBreakdown {
+    // Net cost of the trade; reclaimed rent offsets rent paid
+    pub fn total_lamports(&self) -> i64 {
+        let paid = self.base_fee_lamports
+            + self.priority_fee_lamports
+            + self.jito_tip_lamports
+            + self.flash_loan_fee_lamports
+            + self.dex_fee_lamports
+            + self.profit_fee_lamports
+            + self.rent_paid_lamports;
+        paid as i64 - self.rent_reclaimed_lamports as i64
+    }
+
+    // Flash loan and swap fees implied by the loan size, mirroring the program's fee math
+    pub fn estimate_protocol_fees(loan_amount: u64) -> Self {
+        Self {
+            flash_loan_fee_lamports: bps_of(loan_amount, MANGO_FLASH_LOAN_FEE_BPS),
+            dex_fee_lamports: bps_of(loan_amount, JUPITER_FEE_BPS) + bps_of(loan_amount, RAYDIUM_FEE_BPS),
+            ..Default::default()
+        }
+    }
+}
+
+// Costs and realized result of a confirmed execution, read back from its status metadata
+#[derive(Debug, Clone, Default)]
+pub struct ExecutionReceipt {
+    pub costs: CostBreakdown,
+    // Gross profit the wallet actually received in lamports and the profit token, before costs
+    pub realized_profit_lamports: i64,
+    // The program's ArbExecuted event, which overrides balance-derived figures when present
+    pub event: Option<ArbExecutedEvent>,
+    // Slot the transaction landed in
+    pub slot: Slot,
+}
+
+impl ExecutionReceipt {
+    pub fn fetch(
+        rpc_client: &dyn RpcApi,
+        signature: &Signature,
+        loan_amount: u64,
+        profit_mint: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let config = RpcTransactionConfig {
+            encoding: Some(UiTransactionEncoding::Base64),
+            commitment: Some(CommitmentConfig::confirmed()),
+            max_supported_transaction_version: Some(0),
+        };
+        Self::from_confirmed(rpc_client.get_transaction_with_config(signature, config)?, loan_amount, profit_mint)
+    }
+
+    // The receipt of a confirmed transaction already fetched
+    pub fn from_confirmed(
+        confirmed: EncodedConfirmedTransactionWithStatusMeta,
+        loan_amount: u64,
+        profit_mint: &str,
+    ) -> Result<Self, Box<dyn std::error::Error>> {
+        let slot = confirmed.slot;
+        let meta = confirmed
+            .transaction
+            .meta
+            .ok_or("Confirmed transaction has no status metadata")?;
+        let transaction = confirmed
+            .transaction
+            .transaction
+            .decode()
+            .ok_or("Failed to decode confirmed transaction")?;
+
+        let account_keys = transaction.message.static_account_keys();
+        let num_signatures = transaction.signatures.len() as u64;
+
+        let mut costs = CostBreakdown::estimate_protocol_fees(loan_amount);
+        costs.base_fee_lamports = LAMPORTS_PER_SIGNATURE * num_signatures;
+        costs.priority_fee_lamports = meta.fee.saturating_sub(costs.base_fee_lamports);
+
+        let tip_accounts: Vec<Pubkey> = network::addresses()
+            .jito_tip_accounts
+            .iter()
+            .filter_map(|address| Pubkey::from_str(address).ok())
+            .collect();
+
+        for (index, key) in account_keys.iter().enumerate() {
+            let pre = meta.pre_balances.get(index).copied().unwrap_or(0);
+            let post = meta.post_balances.get(index).copied().unwrap_or(0);
+
+            if tip_accounts.contains(key) {
+                costs.jito_tip_lamports += post.saturating_sub(pre);
+            } else if index > 0 && pre == 0 && post > 0 {
+                // Account created by this transaction, funded to rent exemption
+                costs.rent_paid_lamports += post;
+            } else if index > 0 && pre > 0 && post == 0 {
+                // Account closed by this transaction, lamports returned to the payer
+                costs.rent_reclaimed_lamports += pre;
+            }
+        }
+
+        // The wallet's lamport change with fees, tips and rent added back, plus any of the profit
+        // token it gained, as the pre-send simulation measures it. The wallet signs last, after
+        // the fee payer when a separate one pays, and only gets the fee back when it paid it
+        let wallet_index = (transaction.message.header().num_required_signatures as usize).saturating_sub(1);
+        let wallet_delta = meta.post_balances.get(wallet_index).copied().unwrap_or(0) as i64
+            - meta.pre_balances.get(wallet_index).copied().unwrap_or(0) as i64;
+        let wallet = account_keys.get(wallet_index).map(|key| key.to_string()).unwrap_or_default();
+        let token_delta = token_balance_delta(&meta, &wallet, profit_mint);
+        let fee_paid = if wallet_index == 0 { meta.fee as i64 } else { 0 };
+
+        let mut realized_profit_lamports = wallet_delta
+            + fee_paid
+            + costs.jito_tip_lamports as i64
+            + costs.rent_paid_lamports as i64
+            - costs.rent_reclaimed_lamports as i64
+            + token_delta;
+
+        // On-chain truth: take fees and profit from the program's event when it emitted one
+        let logs: Option<Vec<String>> = meta.log_messages.clone().into();
+        let event = events::parse_logs(&logs.unwrap_or_default()).into_iter().next();
+        if let Some(event) = &event {
+            costs.flash_loan_fee_lamports = event.flash_loan_fee;
+            costs.dex_fee_lamports = event.dex_fees;
+            costs.profit_fee_lamports = event.profit_fee;
+            realized_profit_lamports = event.gross_profit_lamports();
+        }
+
+        Ok(Self {
+            costs,
+            realized_profit_lamports,
+            event,
+            slot,
+        })
+    }
+}
+
+// Change in `owner`'s balance of `mint` across the transaction, in raw token units
+pub fn token_balance_delta(meta: &UiTransactionStatusMeta, owner: &str, mint: &str) -> i64 {
+    let sum = |balances: Option<Vec<UiTransactionTokenBalance>>| -> i64 {
+        balances
+            .unwrap_or_default()
+            .iter()
+            .filter(|balance| balance.mint == mint)
+            .filter(|balance| Option::<String>::from(balance.owner.clone()).as_deref() == Some(owner))
+            .filter_map(|balance| balance.ui_token_amount.amount.parse::<i64>().ok())
+            .sum()
+    };
+    sum(meta.post_token_balances.clone().into()) - sum(meta.pre_token_balances.clone().into())
+}
+
+// How far the realized profit fell short of the expected profit, in bps of the expected profit;
+// negative when it beat it. None without a positive expectation to measure against
+pub fn profit_slippage_bps(expected_profit: i64, realized_profit: i64) -> Option<i64> {
+    (expected_profit > 0).then(|| ((expected_profit as i128 - realized_profit as i128) * 10_000 / expected_profit as i128) as i64)
+}
+
+pub fn bps_of(amount: u64, bps: u64) -> u64 {
+    (amount as u128 * bps as u128 / 10_000) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use solana_sdk::instruction::{AccountMeta, Instruction};
+    use solana_sdk::transaction::{Transaction, VersionedTransaction};
+    use solana_transaction_status::{EncodedTransaction, EncodedTransactionWithStatusMeta, TransactionBinaryEncoding};
+
+    const RENT: u64 = 2_039_280;
+
+    #[test]
+    fn receipt_separates_fees_tips_and_rent() {
+        let wallet = Pubkey::new_unique();
+        let tip = Pubkey::from_str(network::addresses().jito_tip_accounts[0]).unwrap();
+        let (opened, closed, wsol_account) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let instruction = Instruction::new_with_bytes(
+            Pubkey::new_unique(),
+            &[],
+            [tip, opened, closed, wsol_account].iter().map(|key| AccountMeta::new(*key, false)).collect(),
+        );
+        let transaction = Transaction::new_with_payer(&[instruction], Some(&wallet));
+
+        // 5000 lamports per signature, the rest of the fee is the priority fee
+        let fee = 15_000;
+        let balances = |key: &Pubkey| -> (u64, u64) {
+            match key {
+                key if *key == wallet => (1_000_000_000, 1_000_000_000 - fee - 10_000 - RENT + 1_500_000 + 20_000),
+                key if *key == tip => (5_000_000, 5_010_000),
+                key if *key == opened => (0, RENT),
+                key if *key == closed => (1_500_000, 0),
+                _ => (RENT, RENT),
+            }
+        };
+        let keys = &transaction.message.account_keys;
+        let wsol_index = keys.iter().position(|key| *key == wsol_account).unwrap();
+        let token_balance = |amount: &str| {
+            serde_json::json!([{
+                "accountIndex": wsol_index,
+                "mint": WSOL_MINT,
+                "owner": wallet.to_string(),
+                "uiTokenAmount": { "uiAmount": null, "decimals": 9, "amount": amount, "uiAmountString": amount },
+            }])
+        };
+        let meta: UiTransactionStatusMeta = serde_json::from_value(serde_json::json!({
+            "err": null,
+            "status": { "Ok": null },
+            "fee": fee,
+            "preBalances": keys.iter().map(|key| balances(key).0).collect::<Vec<_>>(),
+            "postBalances": keys.iter().map(|key| balances(key).1).collect::<Vec<_>>(),
+            "preTokenBalances": token_balance("0"),
+            "postTokenBalances": token_balance("30000"),
+        }))
+        .unwrap();
+        let encoded = base64::encode(bincode::serialize(&VersionedTransaction::from(transaction)).unwrap());
+        let confirmed = EncodedConfirmedTransactionWithStatusMeta {
+            slot: 42,
+            transaction: EncodedTransactionWithStatusMeta {
+                transaction: EncodedTransaction::Binary(encoded, TransactionBinaryEncoding::Base64),
+                meta: Some(meta),
+                version: None,
+            },
+            block_time: None,
+        };
+
+        let receipt = ExecutionReceipt::from_confirmed(confirmed, 1_000_000, WSOL_MINT).unwrap();
+        let costs = &receipt.costs;
+        assert_eq!((costs.base_fee_lamports, costs.priority_fee_lamports), (5_000, 10_000));
+        assert_eq!(costs.jito_tip_lamports, 10_000);
+        assert_eq!((costs.rent_paid_lamports, costs.rent_reclaimed_lamports), (RENT, 1_500_000));
+        // Without the program's event, the protocol fees are estimated from the loan
+        assert_eq!(costs.flash_loan_fee_lamports, 2_000);
+        assert_eq!(costs.dex_fee_lamports, 5_500);
+        assert_eq!(costs.total_lamports(), 5_000 + 10_000 + 10_000 + 2_000 + 5_500 + RENT as i64 - 1_500_000);
+        // The lamports and WSOL the wallet gained, before any of the costs
+        assert_eq!(receipt.realized_profit_lamports, 50_000);
+        assert_eq!(receipt.slot, 42);
+    }
+
+    #[test]
+    fn protocol_fees_follow_the_loan_size() {
+        let costs = CostBreakdown::estimate_protocol_fees(1_000_000);
+        assert_eq!(costs.flash_loan_fee_lamports, 2_000);
+        assert_eq!(costs.dex_fee_lamports, 5_500);
+        // Transaction-level costs only come from the confirmed transaction
+        assert_eq!(costs.base_fee_lamports + costs.priority_fee_lamports + costs.jito_tip_lamports, 0);
+        assert_eq!(bps_of(u64::MAX, 10_000), u64::MAX);
+    }
+
+    #[test]
+    fn profit_slippage_is_measured_against_the_expectation() {
+        assert_eq!(profit_slippage_bps(1_000, 900), Some(1_000));
+        // Beating the expectation is negative slippage
+        assert_eq!(profit_slippage_bps(1_000, 1_100), Some(-1_000));
+        assert_eq!(profit_slippage_bps(0, 100), None);
+    }
+}
@@ -0,0 +1,333 @@
+use crate::http_client;
+use crate::retry::{self, RetryPolicy};
+use crate::{network, raydium_clmm};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::env;
+
+const JUPITER_TOKEN_LIST: &str = "https://tokens.jup.ag/tokens?tags=verified";
+const RAYDIUM_POOL_LIST: &str =
+    "https://api-v3.raydium.io/pools/info/list?poolType=all&poolSortField=liquidity&sortType=desc&pageSize=1000&page=1";
+const ORCA_WHIRLPOOL_LIST: &str = "https://api.mainnet.orca.so/v1/whirlpool/list";
+
+#[derive(Debug, Clone)]
+pub struct DiscoveryConfig {
+    pub venues: Vec<String>,
+    pub min_venues: usize,
+    pub min_liquidity_usd: f64,
+    pub allow_mint_authority: bool,
+    pub auto_add: bool,
+    pub interval_secs: u64,
+}
+
+impl DiscoveryConfig {
+    pub fn from_env() -> Self {
+        Self {
+            venues: env::var("DISCOVERY_VENUES")
+                .unwrap_or_else(|_| "jupiter,raydium,orca".to_string())
+                .split(',')
+                .map(|v| v.trim().to_lowercase())
+                .filter(|v| !v.is_empty())
+                .collect(),
+            min_venues: env_parse("DISCOVERY_MIN_VENUES", 2),
+            min_liquidity_usd: env_parse("DISCOVERY_MIN_LIQUIDITY_USD", 50_000.0),
+            allow_mint_authority: env_parse("DISCOVERY_ALLOW_MINT_AUTHORITY", false),
+            auto_add: env_parse("DISCOVERY_AUTO_ADD", false),
+            interval_secs: env_parse("DISCOVERY_INTERVAL_SECS", 3600),
+        }
+    }
+}
+
+fn env_parse<T: std::str::FromStr>(name: &str, default: T) -> T {
+    env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveredPair {
+    pub token_a: String,
+    pub token_b: String,
+    pub symbol_a: String,
+    pub symbol_b: String,
+    pub venues: Vec<String>,
+    // Deepest pool liquidity seen on any venue
+    pub liquidity_usd: f64,
+    // 24h volume summed over all venues
+    #[serde(default)]
+    pub volume_24h_usd: f64,
+    // LP token of the deepest Raydium AMM pool, and the share of it burned
+    #[serde(default)]
+    pub lp_mint: Option<String>,
+    #[serde(default)]
+    pub lp_burned_share: Option<f64>,
+    // Scam heuristics score out of 100, when scored
+    #[serde(default)]
+    pub safety_score: Option<f64>,
+    // Pools the bot can decode, deepest first
+    #[serde(default)]
+    pub pools: Vec<ListedPool>,
+}
+
+// A listed pool: "raydium" for a Raydium AMM v4 pool, "raydium_clmm" or "orca" for a Whirlpool
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListedPool {
+    pub venue: String,
+    pub address: String,
+    pub liquidity_usd: f64,
+}
+
+#[derive(Deserialize)]
+struct JupiterToken {
+    address: String,
+    symbol: String,
+    freeze_authority: Option<String>,
+    mint_authority: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct RaydiumPoolList {
+    data: RaydiumPoolPage,
+}
+
+#[derive(Deserialize)]
+struct RaydiumPoolPage {
+    data: Vec<RaydiumPool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RaydiumPool {
+    id: String,
+    program_id: String,
+    mint_a: RaydiumMint,
+    mint_b: RaydiumMint,
+    #[serde(default)]
+    tvl: f64,
+    #[serde(default)]
+    day: RaydiumPeriodStats,
+    // AMM pools only; concentrated liquidity pools have no LP token
+    #[serde(default)]
+    lp_mint: Option<RaydiumMint>,
+    // Percent of the LP supply burned
+    #[serde(default)]
+    burn_percent: Option<f64>,
+}
+
+#[derive(Deserialize, Default)]
+struct RaydiumPeriodStats {
+    #[serde(default)]
+    volume: f64,
+}
+
+#[derive(Deserialize)]
+struct RaydiumMint {
+    address: String,
+}
+
+#[derive(Deserialize)]
+struct OrcaPoolList {
+    whirlpools: Vec<OrcaPool>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct OrcaPool {
+    address: String,
+    token_a: OrcaMint,
+    token_b: OrcaMint,
+    #[serde(default)]
+    tvl: f64,
+    #[serde(default)]
+    volume: OrcaVolume,
+}
+
+#[derive(Deserialize, Default)]
+struct OrcaVolume {
+    #[serde(default)]
+    day: f64,
+}
+
+#[derive(Deserialize)]
+struct OrcaMint {
+    mint: String,
+}
+
+// Order-independent key so A/B and B/A count as the same pair
+pub fn pair_key(a: &str, b: &str) -> (String, String) {
+    if a < b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Listing {
+    pub venues: HashSet<String>,
+    pub liquidity_usd: f64,
+    pub volume_24h_usd: f64,
+    // The deepest Raydium AMM pool's LP mint, burned share and liquidity
+    pub raydium_lp: Option<(String, f64, f64)>,
+    pub pools: Vec<ListedPool>,
+}
+
+// One of the token or pool lists, retried on transient failures
+async fn get_json<T: DeserializeOwned>(http: &reqwest::Client, url: &str) -> Result<T, reqwest::Error> {
+    retry::http(&RetryPolicy::BULK, url, || async {
+        http.get(url).timeout(http_client::timeout_for(url)).send().await?.error_for_status()?.json().await
+    })
+    .await
+}
+
+// Pool listings per order-independent pair across the configured pool venues
+pub async fn fetch_listings(
+    http: &reqwest::Client,
+    venues: &[String],
+) -> Result<HashMap<(String, String), Listing>, Box<dyn std::error::Error>> {
+    let mut listings: HashMap<(String, String), Listing> = HashMap::new();
+    let mut add_listing = |a: &str, b: &str, venue: &str, pool: Option<ListedPool>, tvl: f64, volume: f64, lp: Option<(String, f64)>| {
+        let listing = listings.entry(pair_key(a, b)).or_default();
+        listing.venues.insert(venue.to_string());
+        listing.pools.extend(pool);
+        listing.liquidity_usd = listing.liquidity_usd.max(tvl);
+        listing.volume_24h_usd += volume;
+        if let Some((lp_mint, burned_share)) = lp {
+            if listing.raydium_lp.as_ref().is_none_or(|(_, _, deepest)| tvl > *deepest) {
+                listing.raydium_lp = Some((lp_mint, burned_share, tvl));
+            }
+        }
+    };
+
+    if venues.iter().any(|v| v == "raydium") {
+        let pools: RaydiumPoolList = get_json(http, RAYDIUM_POOL_LIST).await?;
+        for pool in pools.data.data {
+            let lp = pool.lp_mint.map(|lp_mint| (lp_mint.address, pool.burn_percent.unwrap_or(0.0) / 100.0));
+            // Standard (CPMM) pools count towards the venue but have no decoder here
+            let venue = if pool.program_id == network::addresses().raydium_amm {
+                Some("raydium")
+            } else if pool.program_id == raydium_clmm::PROGRAM_ID {
+                Some("raydium_clmm")
+            } else {
+                None
+            };
+            let listed = venue.map(|venue| ListedPool {
+                venue: venue.to_string(),
+                address: pool.id.clone(),
+                liquidity_usd: pool.tvl,
+            });
+            add_listing(&pool.mint_a.address, &pool.mint_b.address, "raydium", listed, pool.tvl, pool.day.volume, lp);
+        }
+    }
+    if venues.iter().any(|v| v == "orca") {
+        let pools: OrcaPoolList = get_json(http, ORCA_WHIRLPOOL_LIST).await?;
+        for pool in pools.whirlpools {
+            let listed = ListedPool {
+                venue: "orca".to_string(),
+                address: pool.address.clone(),
+                liquidity_usd: pool.tvl,
+            };
+            add_listing(&pool.token_a.mint, &pool.token_b.mint, "orca", Some(listed), pool.tvl, pool.volume.day, None);
+        }
+    }
+
+    for listing in listings.values_mut() {
+        listing.pools.sort_by(|a, b| b.liquidity_usd.total_cmp(&a.liquidity_usd));
+    }
+    Ok(listings)
+}
+
+// Symbols of the verified tokens, by mint
+pub async fn token_symbols(http: &reqwest::Client) -> Result<HashMap<String, String>, Box<dyn std::error::Error>> {
+    let tokens: Vec<JupiterToken> = get_json(http, JUPITER_TOKEN_LIST).await?;
+    Ok(tokens.into_iter().map(|t| (t.address, t.symbol)).collect())
+}
+
+// Pulls the venue lists and returns pairs of verified tokens with pools on enough venues
+pub async fn discover_pairs(
+    http: &reqwest::Client,
+    config: &DiscoveryConfig,
+) -> Result<Vec<DiscoveredPair>, Box<dyn std::error::Error>> {
+    let tokens: Vec<JupiterToken> = get_json(http, JUPITER_TOKEN_LIST).await?;
+
+    // Safety filter: only verified tokens that cannot be frozen (or minted, unless allowed)
+    let safe: HashMap<String, String> = tokens
+        .into_iter()
+        .filter(|t| t.freeze_authority.is_none())
+        .filter(|t| config.allow_mint_authority || t.mint_authority.is_none())
+        .map(|t| (t.address, t.symbol))
+        .collect();
+
+    let mut listings = fetch_listings(http, &config.venues).await?;
+    listings.retain(|(a, b), _| safe.contains_key(a) && safe.contains_key(b));
+
+    let jupiter_enabled = config.venues.iter().any(|v| v == "jupiter");
+    let mut pairs: Vec<DiscoveredPair> = listings
+        .into_iter()
+        .map(|((a, b), listing)| {
+            let mut venues = listing.venues;
+            // Jupiter routes any pair of verified tokens that has a pool somewhere
+            if jupiter_enabled {
+                venues.insert("jupiter".to_string());
+            }
+            let mut venues: Vec<String> = venues.into_iter().collect();
+            venues.sort();
+            DiscoveredPair {
+                symbol_a: safe[&a].clone(),
+                symbol_b: safe[&b].clone(),
+                token_a: a,
+                token_b: b,
+                venues,
+                liquidity_usd: listing.liquidity_usd,
+                volume_24h_usd: listing.volume_24h_usd,
+                lp_mint: listing.raydium_lp.as_ref().map(|(mint, _, _)| mint.clone()),
+                lp_burned_share: listing.raydium_lp.as_ref().map(|(_, burned, _)| *burned),
+                safety_score: None,
+                pools: listing.pools,
+            }
+        })
+        .filter(|pair| pair.venues.len() >= config.min_venues)
+        .filter(|pair| pair.liquidity_usd >= config.min_liquidity_usd)
+        .collect();
+
+    pairs.sort_by(|a, b| b.liquidity_usd.total_cmp(&a.liquidity_usd));
+    Ok(pairs)
+}
+
+pub fn write_proposals(path: &str, pairs: &[DiscoveredPair]) -> Result<(), Box<dyn std::error::Error>> {
+    std::fs::write(path, serde_json::to_string_pretty(pairs)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn venue_lists_are_read_and_keyed_in_either_order() {
+        assert_eq!(pair_key("B", "A"), pair_key("A", "B"));
+        assert_eq!(pair_key("B", "A"), ("A".to_string(), "B".to_string()));
+
+        let raydium: RaydiumPoolList = serde_json::from_value(serde_json::json!({
+            "data": {"data": [{
+                "id": "pool",
+                "programId": "program",
+                "mintA": {"address": "A"},
+                "mintB": {"address": "B"},
+                "tvl": 75_000.0,
+                "day": {"volume": 12_000.0},
+                "lpMint": {"address": "lp"},
+                "burnPercent": 98.5,
+            }]}
+        }))
+        .unwrap();
+        let pool = &raydium.data.data[0];
+        assert_eq!((pool.tvl, pool.day.volume, pool.burn_percent), (75_000.0, 12_000.0, Some(98.5)));
+        assert_eq!(pool.lp_mint.as_ref().map(|lp| lp.address.as_str()), Some("lp"));
+        // Concentrated pools list no LP token, and quiet pools no volume
+        let orca: OrcaPoolList = serde_json::from_value(serde_json::json!({
+            "whirlpools": [{"address": "whirlpool", "tokenA": {"mint": "B"}, "tokenB": {"mint": "A"}}]
+        }))
+        .unwrap();
+        assert_eq!((orca.whirlpools[0].tvl, orca.whirlpools[0].volume.day), (0.0, 0.0));
+    }
+}
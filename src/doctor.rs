@@ -0,0 +1,615 @@
+use crate::client;
+use crate::costs;
+use crate::flash_loan::FlashLoanProvider;
+use crate::graph;
+use crate::network::{self, Network};
+use crate::pair_store::PairStore;
+use crate::route;
+use crate::test_env;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use flate2::read::ZlibDecoder;
+use pyth_sdk_solana::state::SolanaPriceAccount;
+use solana_client::client_error::Result as ClientResult;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use std::collections::{BTreeMap, BTreeSet};
+use std::env;
+use std::io::Read;
+use std::str::FromStr;
+use std::time::Instant;
+
+// Preflight checks of everything the monitor depends on, each failure with what to do about it.
+// Checks carry on past a failure so one run reports every problem
+
+// Enough for a few hundred sends at the default priority fee
+const MIN_WALLET_BALANCE_LAMPORTS: u64 = LAMPORTS_PER_SOL / 20;
+// About ten seconds; the monitor prices gas off the feed every cycle
+const MAX_PYTH_AGE_SLOTS: u64 = 25;
+// Program Config layout: discriminator, admin, operator, paused
+const CONFIG_OPERATOR_OFFSET: usize = 40;
+const CONFIG_PAUSED_OFFSET: usize = 72;
+// Anchor IDL account layout: discriminator, authority, length, zlib-compressed JSON
+const IDL_LENGTH_OFFSET: usize = 40;
+const IDL_DATA_OFFSET: usize = 44;
+const LOCAL_IDL_PATH: &str = "idl/arbitrage_contract.json";
+// Parts of the IDL that change the instruction or account encoding
+const IDL_SECTIONS: [&str; 5] = ["instructions", "accounts", "types", "events", "errors"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    Warn,
+    Fail,
+    Skip,
+}
+
+#[derive(Default)]
+struct Report {
+    warnings: usize,
+    failures: usize,
+}
+
+impl Report {
+    fn print(&mut self, status: Status, check: &str, detail: &str, fix: Option<&str>) {
+        let label = match status {
+            Status::Ok => "ok",
+            Status::Warn => "WARN",
+            Status::Fail => "FAIL",
+            Status::Skip => "skip",
+        };
+        println!("[{:<4}] {}: {}", label, check, detail);
+        if let Some(fix) = fix {
+            println!("       -> {}", fix);
+        }
+        match status {
+            Status::Warn => self.warnings += 1,
+            Status::Fail => self.failures += 1,
+            _ => {}
+        }
+    }
+
+    fn ok(&mut self, check: &str, detail: &str) {
+        self.print(Status::Ok, check, detail, None);
+    }
+
+    fn warn(&mut self, check: &str, detail: &str, fix: &str) {
+        self.print(Status::Warn, check, detail, Some(fix));
+    }
+
+    fn fail(&mut self, check: &str, detail: &str, fix: &str) {
+        self.print(Status::Fail, check, detail, Some(fix));
+    }
+}
+
+// Runs every check against SOLANA_RPC_URL; false if any failed
+pub fn run(args: &[String], wallet_keypair_path: &str) -> bool {
+    let network = match Network::from_args(args) {
+        Ok(network) => network,
+        Err(e) => {
+            println!("{}", e);
+            return false;
+        }
+    };
+    let rpc_url = env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+    let rpc = RpcClient::new(rpc_url);
+    let mut report = Report::default();
+
+    // Nothing else can be checked without the RPC
+    if let Some(slot) = check_rpc(&mut report, &rpc, network) {
+        let wallet = check_wallet(&mut report, &rpc, wallet_keypair_path);
+        let whitelisted_pools = check_program(&mut report, &rpc, wallet.as_ref().map(Keypair::pubkey));
+        check_idl(&mut report, &rpc);
+        check_pyth_feeds(&mut report, &rpc, slot);
+        match start_mints() {
+            Ok(mints) => {
+                if let Some(wallet) = &wallet {
+                    check_token_accounts(&mut report, &rpc, &wallet.pubkey(), &mints, wallet_keypair_path);
+                }
+            }
+            Err(e) => report.fail("Start mints", &e.to_string(), "Fix BONK_TOKEN_ADDRESS and ROUTES_FILE"),
+        }
+        check_pools(&mut report, &rpc, whitelisted_pools.as_deref());
+        report.print(
+            Status::Skip,
+            "Address lookup tables",
+            "none used; the bot sends legacy transactions",
+            None,
+        );
+    }
+
+    println!("{} failed, {} warnings", report.failures, report.warnings);
+    report.failures == 0
+}
+
+// None when the account doesn't exist
+fn fetch(rpc: &RpcClient, address: &Pubkey) -> ClientResult<Option<Account>> {
+    Ok(rpc.get_account_with_commitment(address, rpc.commitment())?.value)
+}
+
+// The current slot, if the RPC answers
+fn check_rpc(report: &mut Report, rpc: &RpcClient, network: Network) -> Option<u64> {
+    let started = Instant::now();
+    let version = match rpc.get_version() {
+        Ok(version) => version,
+        Err(e) => {
+            report.fail(
+                "RPC",
+                &format!("{} unreachable: {}", rpc.url(), e),
+                "Check SOLANA_RPC_URL and that the endpoint accepts requests from this host",
+            );
+            return None;
+        }
+    };
+    report.ok(
+        "RPC",
+        &format!("{} runs solana-core {} ({} ms)", rpc.url(), version.solana_core, started.elapsed().as_millis()),
+    );
+
+    if let Err(e) = rpc.get_health() {
+        report.warn("RPC health", &e.to_string(), "The node is behind the cluster; use another endpoint or wait for it to catch up");
+    }
+    match (rpc.get_genesis_hash(), network.genesis_hash()) {
+        (Ok(hash), Some(expected)) if hash.to_string() == expected => report.ok("Cluster", &format!("{:?}", network)),
+        (Ok(hash), None) => report.ok("Cluster", &format!("{:?} (genesis hash {})", network, hash)),
+        (Ok(hash), Some(_)) => report.fail(
+            "Cluster",
+            &format!("{} is not {:?} (genesis hash {})", rpc.url(), network, hash),
+            "Point SOLANA_RPC_URL at the intended cluster, or pass --network devnet or localnet",
+        ),
+        (Err(e), _) => report.fail("Cluster", &e.to_string(), "Check SOLANA_RPC_URL"),
+    }
+    match rpc.get_slot() {
+        Ok(slot) => Some(slot),
+        Err(e) => {
+            report.fail("RPC", &format!("getSlot failed: {}", e), "Check SOLANA_RPC_URL");
+            None
+        }
+    }
+}
+
+fn check_wallet(report: &mut Report, rpc: &RpcClient, path: &str) -> Option<Keypair> {
+    let wallet = match read_keypair_file(path) {
+        Ok(wallet) => wallet,
+        Err(e) => {
+            report.fail(
+                "Wallet",
+                &format!("{}: {}", path, e),
+                &format!("Put the bot's keypair at {}, e.g. with `solana-keygen new -o {}`", path, path),
+            );
+            return None;
+        }
+    };
+    let sol = |lamports: u64| lamports as f64 / LAMPORTS_PER_SOL as f64;
+    match rpc.get_balance(&wallet.pubkey()) {
+        Ok(0) => report.fail(
+            "Wallet balance",
+            &format!("{} holds no SOL", wallet.pubkey()),
+            "Fund the wallet; it pays every transaction fee",
+        ),
+        Ok(balance) if balance < MIN_WALLET_BALANCE_LAMPORTS => report.warn(
+            "Wallet balance",
+            &format!("{} holds {} SOL", wallet.pubkey(), sol(balance)),
+            &format!("Top the wallet up to at least {} SOL", sol(MIN_WALLET_BALANCE_LAMPORTS)),
+        ),
+        Ok(balance) => report.ok("Wallet balance", &format!("{} holds {} SOL", wallet.pubkey(), sol(balance))),
+        Err(e) => report.fail("Wallet balance", &e.to_string(), "Check SOLANA_RPC_URL"),
+    }
+    Some(wallet)
+}
+
+// Checks the program, its config and its whitelist; returns the whitelisted pools
+fn check_program(report: &mut Report, rpc: &RpcClient, wallet: Option<Pubkey>) -> Option<Vec<Pubkey>> {
+    let program_id = client::program_id();
+
+    match fetch(rpc, &program_id) {
+        Ok(Some(account)) if account.executable => {
+            report.ok("Program", &format!("{} deployed (loader {})", program_id, account.owner))
+        }
+        Ok(Some(_)) => report.fail(
+            "Program",
+            &format!("{} exists but is not executable", program_id),
+            "PROGRAM_ID in src/client.rs must be the program's address, not another account",
+        ),
+        Ok(None) => {
+            report.fail(
+                "Program",
+                &format!("{} is not deployed on this cluster", program_id),
+                "Deploy it with `anchor deploy`, or set PROGRAM_ID in src/client.rs to the deployed id",
+            );
+            return None;
+        }
+        Err(e) => report.fail("Program", &e.to_string(), "Check SOLANA_RPC_URL"),
+    }
+
+    let config_address = client::config_address(&program_id);
+    match fetch(rpc, &config_address) {
+        Ok(Some(config)) if config.data.len() > CONFIG_PAUSED_OFFSET => {
+            let operator = Pubkey::new_from_array(
+                config.data[CONFIG_OPERATOR_OFFSET..CONFIG_OPERATOR_OFFSET + 32].try_into().unwrap(),
+            );
+            if wallet.is_some_and(|wallet| wallet != operator) {
+                report.fail(
+                    "Program config",
+                    &format!("operator is {}, not the wallet", operator),
+                    "Have the admin set the wallet as operator with update_config",
+                );
+            } else if config.data[CONFIG_PAUSED_OFFSET] != 0 {
+                report.fail("Program config", "trading is paused", "Have the admin clear `paused` with update_config");
+            } else {
+                report.ok("Program config", &format!("{}, operator {}", config_address, operator));
+            }
+        }
+        Ok(Some(_)) => report.fail("Program config", "account too short", "Have the admin run `migrate`"),
+        Ok(None) => report.fail(
+            "Program config",
+            &format!("{} not initialized", config_address),
+            "Have the admin run initialize_config with the wallet as operator",
+        ),
+        Err(e) => report.fail("Program config", &e.to_string(), "Check SOLANA_RPC_URL"),
+    }
+
+    let whitelist_address = client::whitelist_address(&program_id);
+    match fetch(rpc, &whitelist_address) {
+        Ok(Some(whitelist)) => match whitelisted(&whitelist.data) {
+            Some((programs, pools)) => {
+                report.ok("Whitelist", &format!("{} programs, {} pools", programs.len(), pools.len()));
+                Some(pools)
+            }
+            None => {
+                report.fail("Whitelist", "malformed account", "Have the admin run `migrate`");
+                None
+            }
+        },
+        Ok(None) => {
+            report.fail(
+                "Whitelist",
+                &format!("{} not initialized", whitelist_address),
+                "Have the admin run initialize_whitelist with the venue programs and pools",
+            );
+            None
+        }
+        Err(e) => {
+            report.fail("Whitelist", &e.to_string(), "Check SOLANA_RPC_URL");
+            None
+        }
+    }
+}
+
+// The whitelist's program and pool lists, Borsh-encoded after the discriminator
+fn whitelisted(data: &[u8]) -> Option<(Vec<Pubkey>, Vec<Pubkey>)> {
+    let mut offset = 8;
+    let mut list = || {
+        let len = u32::from_le_bytes(data.get(offset..offset + 4)?.try_into().ok()?) as usize;
+        offset += 4;
+        let keys = data
+            .get(offset..offset + 32 * len)?
+            .chunks(32)
+            .map(|key| Pubkey::new_from_array(key.try_into().unwrap()))
+            .collect::<Vec<_>>();
+        offset += 32 * len;
+        Some(keys)
+    };
+    let programs = list()?;
+    let pools = list()?;
+    Some((programs, pools))
+}
+
+// Compares the IDL published on-chain with idl/arbitrage_contract.json, which the client mirrors
+fn check_idl(report: &mut Report, rpc: &RpcClient) {
+    let program_id = client::program_id();
+    let local: serde_json::Value = match std::fs::read_to_string(LOCAL_IDL_PATH)
+        .map_err(|e| e.to_string())
+        .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+    {
+        Ok(idl) => idl,
+        Err(e) => {
+            report.fail("IDL", &format!("{}: {}", LOCAL_IDL_PATH, e), "Regenerate it with `anchor build`");
+            return;
+        }
+    };
+
+    let base = Pubkey::find_program_address(&[], &program_id).0;
+    let idl_address = Pubkey::create_with_seed(&base, "anchor:idl", &program_id).unwrap();
+    match fetch(rpc, &idl_address) {
+        Ok(Some(account)) => match decode_idl(&account.data) {
+            Ok(deployed) => {
+                let differing: Vec<&str> =
+                    IDL_SECTIONS.iter().copied().filter(|section| local.get(section) != deployed.get(section)).collect();
+                if differing.is_empty() {
+                    report.ok("IDL", &format!("on-chain IDL matches {}", LOCAL_IDL_PATH));
+                } else {
+                    report.fail(
+                        "IDL",
+                        &format!("on-chain IDL differs from {} in {}", LOCAL_IDL_PATH, differing.join(", ")),
+                        "Deploy the current build and run `anchor idl upgrade`, or update the client to the deployed interface",
+                    );
+                }
+            }
+            Err(e) => report.fail(
+                "IDL",
+                &format!("unreadable IDL account {}: {}", idl_address, e),
+                "Republish it with `anchor idl upgrade`",
+            ),
+        },
+        Ok(None) => report.warn(
+            "IDL",
+            "no IDL published on-chain, so the deployed interface can't be compared",
+            &format!("Publish it with `anchor idl init -f {} {}`", LOCAL_IDL_PATH, program_id),
+        ),
+        Err(e) => report.fail("IDL", &e.to_string(), "Check SOLANA_RPC_URL"),
+    }
+}
+
+fn decode_idl(data: &[u8]) -> Result<serde_json::Value, Box<dyn std::error::Error>> {
+    let len = data
+        .get(IDL_LENGTH_OFFSET..IDL_DATA_OFFSET)
+        .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()) as usize)
+        .ok_or("account too short")?;
+    let compressed = data.get(IDL_DATA_OFFSET..IDL_DATA_OFFSET + len).ok_or("account too short")?;
+    let mut json = String::new();
+    ZlibDecoder::new(compressed).read_to_string(&mut json)?;
+    Ok(serde_json::from_str(&json)?)
+}
+
+// The SOL/USD feed pricing gas and the pair's own feeds, when set, must be trading and recent
+fn check_pyth_feeds(report: &mut Report, rpc: &RpcClient, slot: u64) {
+    let mut feeds = vec![(
+        "PYTH_SOL_USD_ACCOUNT",
+        env::var("PYTH_SOL_USD_ACCOUNT")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .unwrap_or_else(|| network::addresses().pyth_sol_usd.to_string()),
+    )];
+    for name in ["TOKEN_A_PYTH_ACCOUNT", "TOKEN_B_PYTH_ACCOUNT"] {
+        if let Some(address) = env::var(name).ok().filter(|v| !v.is_empty()) {
+            feeds.push((name, address));
+        }
+    }
+
+    for (name, address) in feeds {
+        let check = format!("Pyth feed {}", name);
+        let data = match Pubkey::from_str(&address).map_err(|e| e.to_string()).and_then(|address| {
+            rpc.get_account_data(&address).map_err(|e| e.to_string())
+        }) {
+            Ok(data) => data,
+            Err(e) => {
+                report.fail(&check, &format!("{}: {}", address, e), &format!("Set {} to a Pyth price account on this cluster", name));
+                continue;
+            }
+        };
+        let price: &SolanaPriceAccount = match pyth_sdk_solana::state::load_price_account(&data) {
+            Ok(price) => price,
+            Err(e) => {
+                report.fail(
+                    &check,
+                    &format!("{} is not a Pyth price account: {}", address, e),
+                    &format!("Set {} to the feed's price account, not its product account", name),
+                );
+                continue;
+            }
+        };
+        let age = slot.saturating_sub(price.agg.pub_slot);
+        if price.agg.status != pyth_sdk_solana::state::PriceStatus::Trading {
+            report.fail(
+                &check,
+                &format!("{} has status {:?}", address, price.agg.status),
+                "The feed isn't publishing; check it on pyth.network or configure another feed",
+            );
+        } else if age > MAX_PYTH_AGE_SLOTS {
+            report.fail(
+                &check,
+                &format!("{} last updated {} slots ago", address, age),
+                "The feed is stale; check it on pyth.network or configure another feed",
+            );
+        } else {
+            let value = price.agg.price as f64 * 10f64.powi(price.expo);
+            report.ok(&check, &format!("{} at {:.4}, updated {} slots ago", address, value, age));
+        }
+    }
+}
+
+// Mints trades start in, each with the largest loan taken in it: every pair's token A and the first
+// leg of every route in ROUTES_FILE
+pub fn start_mints() -> Result<BTreeMap<Pubkey, u64>, Box<dyn std::error::Error>> {
+    let mut mints = BTreeMap::new();
+    let store = PairStore::from_env();
+    if store.exists() {
+        for pair in store.load()?.iter().filter(|pair| pair.enabled) {
+            let amount = mints.entry(Pubkey::from_str(&pair.token_a)?).or_insert(0);
+            *amount = (*amount).max(pair.loan_amount);
+        }
+    } else if let Some(token_a) = env::var("BONK_TOKEN_ADDRESS").ok().filter(|v| !v.is_empty()) {
+        let loan_amount = env::var("LOAN_AMOUNT").unwrap_or_else(|_| "500000000".to_string()).parse()?;
+        mints.insert(Pubkey::from_str(&token_a)?, loan_amount);
+    }
+    if let Some(routes_file) = env::var("ROUTES_FILE").ok().filter(|v| !v.is_empty()) {
+        for route in route::load_routes(std::path::Path::new(&routes_file))? {
+            let amount = mints.entry(Pubkey::from_str(route.start_mint())?).or_insert(0);
+            *amount = (*amount).max(route.loan_amount);
+        }
+    }
+    Ok(mints)
+}
+
+// Every start mint needs token accounts for the wallet, the program's vault (except WSOL, which
+// the trade opens itself) and the fee collector. Without a flash loan, the wallet must also hold
+// the loan amount
+fn check_token_accounts(
+    report: &mut Report,
+    rpc: &RpcClient,
+    wallet: &Pubkey,
+    mints: &BTreeMap<Pubkey, u64>,
+    wallet_keypair_path: &str,
+) {
+    let vault_authority = client::vault_authority_address(&client::program_id());
+    let fee_collector = match env::var("FEE_COLLECTOR").ok().filter(|v| !v.is_empty()) {
+        Some(address) => match Pubkey::from_str(&address) {
+            Ok(address) => address,
+            Err(e) => {
+                report.fail("Fee collector", &format!("{}: {}", address, e), "Set FEE_COLLECTOR to the config's fee collector");
+                return;
+            }
+        },
+        None => *wallet,
+    };
+    let own_balance = matches!(FlashLoanProvider::from_env(), FlashLoanProvider::OwnBalance);
+
+    for (mint, loan_amount) in mints {
+        let token_program = match fetch(rpc, mint) {
+            Ok(Some(account)) => account.owner,
+            Ok(None) => {
+                report.fail(
+                    "Mint",
+                    &format!("{} does not exist on this cluster", mint),
+                    "Check BONK_TOKEN_ADDRESS and the routes in ROUTES_FILE",
+                );
+                continue;
+            }
+            Err(e) => {
+                report.fail("Mint", &format!("{}: {}", mint, e), "Check SOLANA_RPC_URL");
+                continue;
+            }
+        };
+        let is_wsol = mint.to_string() == costs::WSOL_MINT;
+        let mut owners = vec![("Wallet token account", *wallet)];
+        if !is_wsol {
+            owners.push(("Vault token account", vault_authority));
+        }
+        owners.push(("Fee collector token account", fee_collector));
+
+        for (check, owner) in owners {
+            let address = get_associated_token_address_with_program_id(&owner, mint, &token_program);
+            match fetch(rpc, &address) {
+                Ok(Some(_)) => report.ok(check, &format!("{} for {}", address, mint)),
+                // The bot opens missing vaults ahead of the first trade through them
+                Ok(None) if owner == vault_authority => report.warn(
+                    check,
+                    &format!("{} for {} is missing; the first trade through it pays its rent", address, mint),
+                    &format!(
+                        "spl-token create-account {} --owner {} --fee-payer {} --program-id {}",
+                        mint, owner, wallet_keypair_path, token_program
+                    ),
+                ),
+                Ok(None) => report.fail(
+                    check,
+                    &format!("{} for {} is missing", address, mint),
+                    &format!(
+                        "spl-token create-account {} --owner {} --fee-payer {} --program-id {}",
+                        mint, owner, wallet_keypair_path, token_program
+                    ),
+                ),
+                Err(e) => report.fail(check, &format!("{}: {}", address, e), "Check SOLANA_RPC_URL"),
+            }
+        }
+
+        if own_balance {
+            let address = get_associated_token_address_with_program_id(wallet, mint, &token_program);
+            let balance = rpc
+                .get_token_account_balance(&address)
+                .ok()
+                .and_then(|balance| balance.amount.parse::<u64>().ok())
+                .unwrap_or(0);
+            // Routes starting in SOL top the trade up from the wallet's SOL
+            if balance < *loan_amount && !is_wsol {
+                report.warn(
+                    "Trade balance",
+                    &format!("no flash loan configured and the wallet holds {} of {}, below the loan of {}", balance, mint, loan_amount),
+                    "Fund the wallet's token account, lower LOAN_AMOUNT, or configure a flash loan provider",
+                );
+            }
+        }
+    }
+}
+
+// Pools of the pairs, ROUTES_FILE and POOLS_FILE must exist and be whitelisted, and every pair's
+// quote vaults must be four token accounts
+fn check_pools(report: &mut Report, rpc: &RpcClient, whitelisted_pools: Option<&[Pubkey]>) {
+    let mut pools = BTreeSet::new();
+    // Quote vaults by pair
+    let mut quote_vaults = Vec::new();
+    let collected: Result<(), Box<dyn std::error::Error>> = (|| {
+        let store = PairStore::from_env();
+        if store.exists() {
+            for pair in store.load()?.iter().filter(|pair| pair.enabled) {
+                for pool in &pair.pool_accounts {
+                    pools.insert(Pubkey::from_str(pool)?);
+                }
+                let vaults: Vec<Pubkey> = pair.quote_vaults.iter().map(|vault| Pubkey::from_str(vault)).collect::<Result<_, _>>()?;
+                quote_vaults.push((pair.key(), vaults));
+            }
+        } else {
+            pools.extend(test_env::address_list("POOL_ACCOUNTS")?);
+            quote_vaults.push(("QUOTE_VAULTS".to_string(), test_env::address_list("QUOTE_VAULTS")?));
+        }
+        if let Some(routes_file) = env::var("ROUTES_FILE").ok().filter(|v| !v.is_empty()) {
+            for route in route::load_routes(std::path::Path::new(&routes_file))? {
+                for pool in route.legs.iter().filter_map(|leg| leg.pool.as_deref()) {
+                    pools.insert(Pubkey::from_str(pool)?);
+                }
+            }
+        }
+        if let Some(pools_file) = env::var("POOLS_FILE").ok().filter(|v| !v.is_empty()) {
+            for pool in graph::load_pools(std::path::Path::new(&pools_file))? {
+                if let Some(pool) = &pool.pool {
+                    pools.insert(Pubkey::from_str(pool)?);
+                }
+            }
+        }
+        Ok(())
+    })();
+    if let Err(e) = collected {
+        report.fail("Pools", &e.to_string(), "Fix the pair store or POOL_ACCOUNTS, ROUTES_FILE and POOLS_FILE");
+    }
+
+    for pool in &pools {
+        match fetch(rpc, pool) {
+            Ok(Some(_)) if whitelisted_pools.is_some_and(|whitelisted| !whitelisted.contains(pool)) => report.fail(
+                "Pool",
+                &format!("{} is not whitelisted; trades through it fail with NotWhitelisted", pool),
+                "Have the admin add it with update_whitelist",
+            ),
+            Ok(Some(account)) => report.ok("Pool", &format!("{} (owner {})", pool, account.owner)),
+            Ok(None) => report.fail(
+                "Pool",
+                &format!("{} does not exist on this cluster", pool),
+                "Remove it from POOL_ACCOUNTS, ROUTES_FILE or POOLS_FILE, or use the pool's address on this cluster",
+            ),
+            Err(e) => report.fail("Pool", &format!("{}: {}", pool, e), "Check SOLANA_RPC_URL"),
+        }
+    }
+
+    for (pair, vaults) in &quote_vaults {
+        let check = format!("Quote vaults {}", pair);
+        if vaults.len() != 4 {
+            report.fail(
+                &check,
+                &format!("{} accounts listed; the pair can't be quoted", vaults.len()),
+                "List the four pool vaults: first base, first quote, second base, second quote",
+            );
+            continue;
+        }
+        for vault in vaults {
+            match fetch(rpc, vault) {
+                Ok(Some(account))
+                    if account.owner == anchor_spl::token::ID || account.owner == anchor_spl::token_2022::ID =>
+                {
+                    report.ok(&check, &vault.to_string())
+                }
+                Ok(Some(account)) => report.fail(
+                    &check,
+                    &format!("{} is owned by {}, not a token program", vault, account.owner),
+                    "Quote vaults are the pools' token vaults, not the pool accounts",
+                ),
+                Ok(None) => report.fail(
+                    &check,
+                    &format!("{} does not exist on this cluster", vault),
+                    "Use vaults of pools on this cluster",
+                ),
+                Err(e) => report.fail(&check, &format!("{}: {}", vault, e), "Check SOLANA_RPC_URL"),
+            }
+        }
+    }
+}
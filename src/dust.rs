@@ -0,0 +1,96 @@
+use crate::costs::WSOL_MINT;
+use crate::network;
+use crate::rpc::RpcApi;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::str::FromStr;
+
+// Dust sweeps (DUST_SWEEP): trades leave small balances of the pairs' and routes' tokens in the
+// wallet, from rounding and from legs that returned more than the next one took. Every
+// DUST_SWEEP_INTERVAL_SECS, balances worth at least DUST_THRESHOLD_LAMPORTS are swapped back to
+// SOL, or USDC, through Jupiter, as long as the transaction costs no more than DUST_MAX_FEE_RATIO
+// of what the swap returns. Tokens held on purpose by inventory mode are left alone
+
+// Pair key the sweeps are journaled and calibrated under
+pub const SWEEP_KEY: &str = "dust sweep";
+
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct DustConfig {
+    pub threshold_lamports: u64,
+    pub max_fee_ratio: f64,
+    pub interval_secs: u64,
+    // WSOL, or USDC with DUST_SWEEP_TO=usdc
+    pub target_mint: String,
+}
+
+impl DustConfig {
+    // None unless DUST_SWEEP=true
+    pub fn from_env() -> Option<Self> {
+        if env::var("DUST_SWEEP").map(|v| v != "true").unwrap_or(true) {
+            return None;
+        }
+        let parse = |name: &str, default: f64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Some(Self {
+            threshold_lamports: parse("DUST_THRESHOLD_LAMPORTS", 10_000_000.0) as u64,
+            max_fee_ratio: parse("DUST_MAX_FEE_RATIO", 0.05),
+            interval_secs: parse("DUST_SWEEP_INTERVAL_SECS", 3600.0) as u64,
+            target_mint: match env::var("DUST_SWEEP_TO").unwrap_or_default().to_lowercase().as_str() {
+                "usdc" => network::addresses().usdc.to_string(),
+                "" | "sol" => WSOL_MINT.to_string(),
+                other => panic!("Invalid DUST_SWEEP_TO {}; expected sol or usdc", other),
+            },
+        })
+    }
+
+    // Whether a balance the swap values at `value_lamports` is worth a transaction of `fee_lamports`
+    pub fn worth_sweeping(&self, value_lamports: u64, fee_lamports: u64) -> bool {
+        value_lamports >= self.threshold_lamports && fee_lamports as f64 <= value_lamports as f64 * self.max_fee_ratio
+    }
+}
+
+// A sweep as journaled: the token amount swapped and the target mint's amount the quote promised
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DustSweep {
+    pub amount: u64,
+    pub quoted_out: u64,
+}
+
+// The wallet's balance of each of `mints` beyond what's `held` on purpose, for the mints it has any of
+pub fn dust_balances(
+    rpc: &dyn RpcApi,
+    owner: &Pubkey,
+    mints: &BTreeSet<Pubkey>,
+    held: &HashMap<Pubkey, u64>,
+) -> Result<Vec<(Pubkey, u64)>, Box<dyn std::error::Error>> {
+    let target_mints = [Pubkey::from_str(WSOL_MINT)?, Pubkey::from_str(network::addresses().usdc)?];
+    let mints: Vec<Pubkey> = mints.iter().filter(|mint| !target_mints.contains(mint)).copied().collect();
+    // SPL Token or Token-2022, whichever owns the mint; associated accounts depend on it
+    let accounts: Vec<Pubkey> = rpc
+        .get_multiple_accounts(&mints)?
+        .iter()
+        .zip(&mints)
+        .map(|(mint_account, mint)| {
+            let token_program = mint_account.as_ref().map_or(anchor_spl::token::ID, |account| account.owner);
+            get_associated_token_address_with_program_id(owner, mint, &token_program)
+        })
+        .collect();
+    let balances = rpc
+        .get_multiple_accounts(&accounts)?
+        .into_iter()
+        .zip(mints)
+        .filter_map(|(account, mint)| {
+            let balance = account?
+                .data
+                .get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8)
+                .map(|bytes| u64::from_le_bytes(bytes.try_into().unwrap()))?;
+            let dust = balance.saturating_sub(held.get(&mint).copied().unwrap_or(0));
+            (dust > 0).then_some((mint, dust))
+        })
+        .collect();
+    Ok(balances)
+}
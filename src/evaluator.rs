@@ -0,0 +1,375 @@
+use crate::venue::Venue;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+// Prices returned by the program's price check, both scaled by 1e9, and those of the pair's further
+// venues quoted alongside it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Quote {
+    pub jupiter_price: u64,
+    pub raydium_price: u64,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub venues: Vec<VenuePrice>,
+}
+
+// A further venue's price for the same trade; its pool is the one its leg swaps through
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct VenuePrice {
+    pub venue: Venue,
+    #[serde(default)]
+    pub pool: Option<String>,
+    pub price: u64,
+}
+
+impl Quote {
+    // Decodes the `quote` instruction's return data: the Jupiter-side price then the Raydium price,
+    // little-endian u64s
+    pub fn from_return_data(data: &[u8]) -> Result<Self, String> {
+        if data.len() < 16 {
+            return Err(format!("quote return data is {} bytes, expected 16", data.len()));
+        }
+        Ok(Self {
+            jupiter_price: u64::from_le_bytes(data[0..8].try_into().unwrap()),
+            raydium_price: u64::from_le_bytes(data[8..16].try_into().unwrap()),
+            venues: Vec::new(),
+        })
+    }
+
+    // Every venue's (venue, pool, price): the program's two, whose pools are the pair's own, then
+    // the further venues
+    pub fn prices(&self) -> impl Iterator<Item = (Venue, Option<&str>, u64)> {
+        [(Venue::Jupiter, None, self.jupiter_price), (Venue::Raydium, None, self.raydium_price)]
+            .into_iter()
+            .chain(self.venues.iter().map(|venue| (venue.venue, venue.pool.as_deref(), venue.price)))
+    }
+
+    // The cheapest venue, where the trade buys; the Jupiter side on a tie, as with two venues
+    pub fn buy(&self) -> (Venue, Option<&str>, u64) {
+        self.prices().min_by_key(|(_, _, price)| *price).unwrap()
+    }
+
+    // The dearest venue, where the trade sells
+    pub fn sell(&self) -> (Venue, Option<&str>, u64) {
+        self.prices().max_by_key(|(_, _, price)| *price).unwrap()
+    }
+
+    // Spread between the best buy and sell venues, never less than any two venues' spread
+    pub fn spread(&self) -> u64 {
+        self.sell().2 - self.buy().2
+    }
+}
+
+// MIN_PROFIT_THRESHOLD when unset
+pub const DEFAULT_MIN_PROFIT_PERCENT: f64 = 0.5;
+
+// The least profit worth trading for, beyond gas and slippage. Absolute thresholds are in the
+// loaned token's base units, lamports for the SOL-loan pairs; a USD threshold is converted at the
+// SOL price, so the notional doesn't move it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ProfitThreshold {
+    // Percent of the trade amount
+    Percent(f64),
+    Lamports(u64),
+    Usd(f64),
+}
+
+impl ProfitThreshold {
+    // The threshold for a trade of `trade_amount`, in the same units
+    pub fn cost(&self, trade_amount: u64, sol_price: f64) -> f64 {
+        match *self {
+            ProfitThreshold::Percent(percent) => trade_amount as f64 * percent / 100.0,
+            ProfitThreshold::Lamports(lamports) => lamports as f64,
+            ProfitThreshold::Usd(usd) if sol_price > 0.0 => usd * 1e9 / sol_price,
+            // Without a SOL price, nothing clears a USD threshold
+            ProfitThreshold::Usd(_) => f64::INFINITY,
+        }
+    }
+
+    pub fn as_percent(&self) -> Option<f64> {
+        match *self {
+            ProfitThreshold::Percent(percent) => Some(percent),
+            _ => None,
+        }
+    }
+}
+
+// "0.5" or "0.5%" of the trade amount, "5000000 lamports", or "$2"
+impl FromStr for ProfitThreshold {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        let invalid = || format!("invalid profit threshold {:?}, expected e.g. 0.5%, 5000000 lamports or $2", s);
+        if let Some(usd) = s.strip_prefix('$') {
+            usd.trim().parse().map(ProfitThreshold::Usd).map_err(|_| invalid())
+        } else if let Some(lamports) = s.strip_suffix("lamports") {
+            lamports.trim().parse().map(ProfitThreshold::Lamports).map_err(|_| invalid())
+        } else {
+            s.strip_suffix('%').unwrap_or(s).trim().parse().map(ProfitThreshold::Percent).map_err(|_| invalid())
+        }
+    }
+}
+
+impl fmt::Display for ProfitThreshold {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ProfitThreshold::Percent(percent) => write!(f, "{}%", percent),
+            ProfitThreshold::Lamports(lamports) => write!(f, "{} lamports", lamports),
+            ProfitThreshold::Usd(usd) => write!(f, "${}", usd),
+        }
+    }
+}
+
+// Percentages stay plain numbers, as stored before thresholds had units
+impl Serialize for ProfitThreshold {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            ProfitThreshold::Percent(percent) => serializer.serialize_f64(*percent),
+            _ => serializer.serialize_str(&self.to_string()),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ProfitThreshold {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Stored {
+            Percent(f64),
+            Text(String),
+        }
+        match Stored::deserialize(deserializer)? {
+            Stored::Percent(percent) => Ok(ProfitThreshold::Percent(percent)),
+            Stored::Text(text) => text.parse().map_err(de::Error::custom),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct EvaluatorParams {
+    pub min_profit_threshold: ProfitThreshold,
+    pub slippage_tolerance: f64, // Percent of the trade amount
+    pub estimated_gas_cost: u64,   // Lamports
+    // Chance the transaction lands at its bid, from recent landing rates; 1 until there are enough
+    pub landing_probability: f64,
+    // Lamports an attempt that doesn't land still costs, like the fee of a reverted transaction
+    pub failure_cost: u64,
+}
+
+impl Default for EvaluatorParams {
+    fn default() -> Self {
+        Self {
+            min_profit_threshold: ProfitThreshold::Percent(DEFAULT_MIN_PROFIT_PERCENT),
+            slippage_tolerance: 0.1,
+            estimated_gas_cost: 5000,
+            landing_probability: 1.0,
+            failure_cost: 0,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Evaluation {
+    pub trade_amount: u64,
+    pub price_diff: u64,
+    pub potential_profit: f64,
+    pub threshold_cost: f64,
+    pub gas_cost: f64,
+    pub slippage_cost: f64,
+    pub required_profit: f64,
+    pub landing_probability: f64,
+    pub failure_cost: f64,
+}
+
+// One evaluated quote, identified by the slot it was read at and the pair
+#[derive(Debug, Clone)]
+pub struct Opportunity {
+    pub id: String,
+    pub slot: u64,
+    pub quote: Quote,
+    pub sol_price: f64,
+    pub evaluation: Evaluation,
+    // Size chosen by the pair's strategy, None when it decided to skip
+    pub size: Option<u64>,
+    pub reason: String,
+    // Amount the quote priced, and the mint and balance of each quote vault as its simulation read them
+    pub quoted_amount: u64,
+    pub vaults: Vec<(String, u64)>,
+}
+
+pub fn opportunity_id(slot: u64, token_a: &str, token_b: &str) -> String {
+    let pair_hash = blake3::hash(format!("{}/{}", token_a, token_b).as_bytes());
+    format!("{}-{}", slot, &pair_hash.to_hex()[..8])
+}
+
+impl Evaluation {
+    // Only trades whose expected value is positive are executed; with a certain landing that is
+    // any trade whose profit clears its required profit
+    pub fn is_executable(&self) -> bool {
+        self.expected_value() > 0.0
+    }
+
+    // The margin over the required profit if the trade lands, weighed against what a failed
+    // attempt costs
+    pub fn expected_value(&self) -> f64 {
+        let p = self.landing_probability;
+        p * (self.potential_profit - self.required_profit) - (1.0 - p) * self.failure_cost
+    }
+
+    // Profit left after the costs that scale with the trade; a trade that only fails to cover the
+    // fixed transaction cost can still pay off batched with others
+    pub fn variable_margin(&self) -> f64 {
+        self.potential_profit - self.threshold_cost - self.slippage_cost
+    }
+
+    pub fn expected_profit(&self) -> Option<u64> {
+        if self.is_executable() {
+            Some(self.potential_profit as u64)
+        } else {
+            None
+        }
+    }
+
+    // Step-by-step account of how the decision was reached, for replay and debugging
+    pub fn explain(&self) -> String {
+        let decision = if self.is_executable() { "EXECUTE" } else { "SKIP" };
+        format!(
+            "trade amount:     {}\n\
+             price diff:       {}\n\
+             potential profit: {:.4}\n\
+             threshold cost:   {:.4}\n\
+             gas cost:         {:.4}\n\
+             slippage cost:    {:.4}\n\
+             required profit:  {:.4}\n\
+             landing chance:   {:.2}\n\
+             failure cost:     {:.4}\n\
+             expected value:   {:.4}\n\
+             decision:         {} (expected value {} 0)",
+            self.trade_amount,
+            self.price_diff,
+            self.potential_profit,
+            self.threshold_cost,
+            self.gas_cost,
+            self.slippage_cost,
+            self.required_profit,
+            self.landing_probability,
+            self.failure_cost,
+            self.expected_value(),
+            decision,
+            if self.is_executable() { ">" } else { "<=" },
+        )
+    }
+}
+
+// The whole loan is traded; kept separate so the live monitor and backtests size identically
+pub fn size_trade(loan_amount: u64) -> u64 {
+    loan_amount
+}
+
+pub fn gas_cost_in_usd(estimated_gas_cost: u64, sol_price: f64) -> f64 {
+    (estimated_gas_cost as f64 * sol_price) / 1_000_000_000.0
+}
+
+pub fn evaluate(quote: &Quote, loan_amount: u64, sol_price: f64, params: &EvaluatorParams) -> Evaluation {
+    let trade_amount = size_trade(loan_amount);
+
+    // Calculate potential profit (assuming prices are in the same decimal precision), buying on the
+    // cheapest venue and selling on the dearest
+    let price_diff = quote.spread();
+    let potential_profit = (price_diff as f64 * trade_amount as f64) / 1e9;
+
+    // Calculate minimum required profit including costs
+    let threshold_cost = params.min_profit_threshold.cost(trade_amount, sol_price);
+    let gas_cost = gas_cost_in_usd(params.estimated_gas_cost, sol_price);
+    let slippage_cost = trade_amount as f64 * params.slippage_tolerance / 100.0;
+
+    Evaluation {
+        trade_amount,
+        price_diff,
+        potential_profit,
+        threshold_cost,
+        gas_cost,
+        slippage_cost,
+        required_profit: threshold_cost + gas_cost + slippage_cost,
+        landing_probability: params.landing_probability,
+        failure_cost: gas_cost_in_usd(params.failure_cost, sol_price),
+    }
+}
+
+// Same cost model as `evaluate`, for a cyclic route whose final leg returns `final_amount` of the loaned token
+pub fn evaluate_route(loan_amount: u64, final_amount: u64, sol_price: f64, params: &EvaluatorParams) -> Evaluation {
+    let trade_amount = size_trade(loan_amount);
+    let gain = final_amount.saturating_sub(trade_amount);
+
+    let threshold_cost = params.min_profit_threshold.cost(trade_amount, sol_price);
+    let gas_cost = gas_cost_in_usd(params.estimated_gas_cost, sol_price);
+    let slippage_cost = trade_amount as f64 * params.slippage_tolerance / 100.0;
+
+    Evaluation {
+        trade_amount,
+        price_diff: gain,
+        potential_profit: gain as f64,
+        threshold_cost,
+        gas_cost,
+        slippage_cost,
+        required_profit: threshold_cost + gas_cost + slippage_cost,
+        landing_probability: params.landing_probability,
+        failure_cost: gas_cost_in_usd(params.failure_cost, sol_price),
+    }
+}
+
+// Several route evaluations executed as one batch: profits and size-dependent costs add up, the gas
+// cost is paid once, and the one transaction lands or fails as any of them would
+pub fn evaluate_batch(evaluations: &[Evaluation]) -> Evaluation {
+    let sum = |field: fn(&Evaluation) -> f64| evaluations.iter().map(field).sum::<f64>();
+    let threshold_cost = sum(|e| e.threshold_cost);
+    let slippage_cost = sum(|e| e.slippage_cost);
+    let gas_cost = evaluations.first().map_or(0.0, |e| e.gas_cost);
+
+    Evaluation {
+        trade_amount: evaluations.iter().map(|e| e.trade_amount).max().unwrap_or(0),
+        price_diff: evaluations.iter().map(|e| e.price_diff).sum(),
+        potential_profit: sum(|e| e.potential_profit),
+        threshold_cost,
+        gas_cost,
+        slippage_cost,
+        required_profit: threshold_cost + gas_cost + slippage_cost,
+        landing_probability: evaluations.first().map_or(1.0, |e| e.landing_probability),
+        failure_cost: evaluations.first().map_or(0.0, |e| e.failure_cost),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A route clearing its size-dependent costs by `margin`, short of the fixed gas cost
+    fn small_route(trade_amount: u64, margin: f64) -> Evaluation {
+        Evaluation {
+            trade_amount,
+            price_diff: 10,
+            potential_profit: 30.0 + margin,
+            threshold_cost: 20.0,
+            gas_cost: 50.0,
+            slippage_cost: 10.0,
+            required_profit: 80.0,
+            landing_probability: 1.0,
+            failure_cost: 0.0,
+        }
+    }
+
+    #[test]
+    fn batch_pays_the_gas_cost_once() {
+        let routes = [small_route(1_000, 20.0), small_route(3_000, 40.0)];
+        assert!(routes.iter().all(|route| !route.is_executable() && route.variable_margin() > 0.0));
+
+        let batch = evaluate_batch(&routes);
+        assert_eq!(batch.trade_amount, 3_000);
+        assert_eq!(batch.potential_profit, 120.0);
+        // Both routes' threshold and slippage costs, one transaction's gas
+        assert_eq!(batch.required_profit, 40.0 + 20.0 + 50.0);
+        assert!(batch.is_executable());
+        assert_eq!(batch.variable_margin(), 60.0);
+    }
+}
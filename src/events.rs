@@ -0,0 +1,152 @@
+use crate::journal::{JournalEntry, TradeStatus};
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+
+const PROGRAM_DATA_PREFIX: &str = "Program data: ";
+
+// Off-chain copy of the program's `ArbExecuted` event
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArbExecutedEvent {
+    pub token_a: String,
+    pub token_b: String,
+    pub route: Vec<String>,
+    pub amount_in: u64,
+    pub amount_out: u64,
+    pub flash_loan_fee: u64,
+    pub dex_fees: u64,
+    pub net_profit: u64,
+    // Part of net_profit paid to the program's fee collector
+    #[serde(default)]
+    pub profit_fee: u64,
+    pub slot: u64,
+}
+
+impl ArbExecutedEvent {
+    // Profit before the loan and swap fees, matching the journal's gross profit
+    pub fn gross_profit_lamports(&self) -> i64 {
+        (self.net_profit + self.flash_loan_fee + self.dex_fees) as i64
+    }
+
+    // Journal entry for an execution only known from its event, e.g. one sent by another instance
+    pub fn to_journal_entry(&self, signature: &str) -> JournalEntry {
+        let mut entry = JournalEntry::new(self.token_a.clone(), self.token_b.clone(), self.amount_in, TradeStatus::Executed);
+        entry.slot = self.slot;
+        entry.signature = Some(signature.to_string());
+        // Two-venue trades go A → B → A; anything longer is a multi-leg route
+        if self.route.len() > 3 {
+            entry.route = Some(self.route.join(" → "));
+        }
+        entry.realized_profit_lamports = Some(self.gross_profit_lamports());
+        entry.costs.flash_loan_fee_lamports = self.flash_loan_fee;
+        entry.costs.dex_fee_lamports = self.dex_fees;
+        entry.costs.profit_fee_lamports = self.profit_fee;
+        entry.event = Some(self.clone());
+        entry
+    }
+}
+
+// Anchor prefixes event data with the first 8 bytes of sha256("event:<Name>")
+pub fn event_discriminator() -> [u8; 8] {
+    let hash = solana_sdk::hash::hash(b"event:ArbExecuted").to_bytes();
+    let mut discriminator = [0u8; 8];
+    discriminator.copy_from_slice(&hash[..8]);
+    discriminator
+}
+
+// Every `ArbExecuted` event emitted in a transaction's logs
+pub fn parse_logs(logs: &[String]) -> Vec<ArbExecutedEvent> {
+    let discriminator = event_discriminator();
+    logs.iter()
+        .filter_map(|line| line.strip_prefix(PROGRAM_DATA_PREFIX))
+        .filter_map(|data| base64::decode(data.trim()).ok())
+        .filter(|data| data.len() > 8 && data[..8] == discriminator)
+        .filter_map(|data| decode(&data[8..]))
+        .collect()
+}
+
+// Borsh layout of the event fields, in declaration order
+fn decode(data: &[u8]) -> Option<ArbExecutedEvent> {
+    let mut reader = Reader { data, offset: 0 };
+    let token_a = reader.pubkey()?;
+    let token_b = reader.pubkey()?;
+    let route_len = reader.u32()? as usize;
+    let mut route = Vec::with_capacity(route_len.min(16));
+    for _ in 0..route_len {
+        route.push(reader.pubkey()?);
+    }
+    Some(ArbExecutedEvent {
+        token_a,
+        token_b,
+        route,
+        amount_in: reader.u64()?,
+        amount_out: reader.u64()?,
+        flash_loan_fee: reader.u64()?,
+        dex_fees: reader.u64()?,
+        net_profit: reader.u64()?,
+        profit_fee: reader.u64()?,
+        slot: reader.u64()?,
+    })
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    offset: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.offset..self.offset + len)?;
+        self.offset += len;
+        Some(bytes)
+    }
+
+    fn pubkey(&mut self) -> Option<String> {
+        let bytes: [u8; 32] = self.take(32)?.try_into().ok()?;
+        Some(Pubkey::new_from_array(bytes).to_string())
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        Some(u32::from_le_bytes(self.take(4)?.try_into().ok()?))
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        Some(u64::from_le_bytes(self.take(8)?.try_into().ok()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn executed_events_are_read_from_program_logs() {
+        let (token_a, token_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = event_discriminator().to_vec();
+        data.extend_from_slice(token_a.as_ref());
+        data.extend_from_slice(token_b.as_ref());
+        data.extend_from_slice(&4u32.to_le_bytes());
+        for mint in [token_a, token_b, Pubkey::new_unique(), token_a] {
+            data.extend_from_slice(mint.as_ref());
+        }
+        for value in [1_000u64, 1_090, 5, 10, 75, 3, 42] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        let logs = vec![
+            "Program log: Instruction: TryArbitrage".to_string(),
+            format!("Program data: {}", base64::encode(&data)),
+            // Another program's event, and a truncated one
+            format!("Program data: {}", base64::encode([7u8; 40])),
+            format!("Program data: {}", base64::encode(&data[..60])),
+        ];
+
+        let events = parse_logs(&logs);
+        assert_eq!(events.len(), 1);
+        let event = &events[0];
+        assert_eq!((event.token_a.clone(), event.amount_out, event.profit_fee, event.slot), (token_a.to_string(), 1_090, 3, 42));
+        assert_eq!(event.gross_profit_lamports(), 90);
+        // Longer than A -> B -> A, so journaled as a route
+        let entry = event.to_journal_entry("signature");
+        assert_eq!(entry.route.as_deref().map(|route| route.matches(" → ").count()), Some(3));
+        assert_eq!((entry.realized_profit_lamports, entry.costs.profit_fee_lamports), (Some(90), 3));
+    }
+}
@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// Trigger discipline for a pair once its strategy wants to trade: send straight away, wait until
+// several quotes in a row agree, or hold the trade for the cycle's bundles. Quiet, stable pairs can
+// fire on the first quote, while pairs whose spreads flicker on a single stale pool update are
+// better off waiting for confirmation
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Admission {
+    // Execute and send now
+    Send,
+    // Execute and queue the verified trade for the next bundle window
+    Queue,
+    // Not yet; the opportunity hasn't been confirmed
+    Wait,
+}
+
+pub trait ExecutionPolicy: Send + Sync {
+    fn name(&self) -> &'static str;
+    // Called for every quote of the pair, with whether the strategy wants to trade it
+    fn admit(&self, pair_key: &str, wants_trade: bool) -> Admission;
+}
+
+// The original behaviour: send as soon as the strategy says so
+pub struct Immediate;
+
+impl ExecutionPolicy for Immediate {
+    fn name(&self) -> &'static str {
+        "immediate"
+    }
+
+    fn admit(&self, _pair_key: &str, _wants_trade: bool) -> Admission {
+        Admission::Send
+    }
+}
+
+// Sends only once `required` consecutive quotes were executable; any quote that isn't starts over,
+// as does a send
+pub struct ConfirmingQuotes {
+    pub required: u32,
+    streaks: Mutex<HashMap<String, u32>>,
+}
+
+impl ConfirmingQuotes {
+    pub fn new(required: u32) -> Self {
+        Self {
+            required: required.max(1),
+            streaks: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl ExecutionPolicy for ConfirmingQuotes {
+    fn name(&self) -> &'static str {
+        "confirm"
+    }
+
+    fn admit(&self, pair_key: &str, wants_trade: bool) -> Admission {
+        let mut streaks = self.streaks.lock().unwrap();
+        if !wants_trade {
+            streaks.remove(pair_key);
+            return Admission::Wait;
+        }
+        let streak = streaks.entry(pair_key.to_string()).or_insert(0);
+        *streak += 1;
+        if *streak < self.required {
+            return Admission::Wait;
+        }
+        streaks.remove(pair_key);
+        Admission::Send
+    }
+}
+
+// Holds every trade for the bundles sent at the end of the cycle, needs private submission
+pub struct BundleWindow;
+
+impl ExecutionPolicy for BundleWindow {
+    fn name(&self) -> &'static str {
+        "bundle"
+    }
+
+    fn admit(&self, _pair_key: &str, _wants_trade: bool) -> Admission {
+        Admission::Queue
+    }
+}
+
+// Parses EXECUTION_POLICY syntax: "immediate", "confirm[:<quotes>]" (default 2) or "bundle"
+pub fn from_name(name: &str) -> Result<Box<dyn ExecutionPolicy>, String> {
+    let mut parts = name.split(':');
+    let kind = parts.next().unwrap_or_default();
+    let arg = parts.next();
+
+    match kind {
+        "immediate" => Ok(Box::new(Immediate)),
+        "confirm" => {
+            let required = arg
+                .map(|v| v.parse().map_err(|_| format!("Invalid confirming quote count: {}", v)))
+                .transpose()?;
+            Ok(Box::new(ConfirmingQuotes::new(required.unwrap_or(2))))
+        }
+        "bundle" => Ok(Box::new(BundleWindow)),
+        _ => Err(format!("Unknown execution policy: {}", name)),
+    }
+}
@@ -0,0 +1,172 @@
+use crate::simulation::SimulationError;
+use serde::{Deserialize, Serialize};
+use solana_client::client_error::{ClientError, ClientErrorKind};
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use std::error::Error;
+use std::fmt;
+
+// Anchor custom error codes of the program's ErrorCode variants (6000 + variant index)
+pub const INSUFFICIENT_PROFIT_CODE: u32 = 6001;
+pub const SLIPPAGE_EXCEEDED_CODE: u32 = 6003;
+pub const EXPIRED_CODE: u32 = 6011;
+pub const FLASH_BORROW_FAILED_CODE: u32 = 6017;
+pub const REPAY_SHORTFALL_CODE: u32 = 6018;
+// Leg1SlippageExceeded; legs 2 to 6 follow in order
+pub const LEG_SLIPPAGE_FIRST_CODE: u32 = 6019;
+pub const ORACLE_STALE_CODE: u32 = 6025;
+pub const ROUTE_UNPROFITABLE_CODE: u32 = 6026;
+pub const INSUFFICIENT_COMPUTE_BUDGET_CODE: u32 = 6027;
+const MAX_ROUTE_LEGS: u32 = 6;
+
+// Why an execution attempt failed, so failures can be counted by cause
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FailureClass {
+    Slippage,
+    InsufficientProfit,
+    FlashLoanLiquidity,
+    BlockhashExpired,
+    ComputeExceeded,
+    AccountInUse,
+    OracleStale,
+    Other,
+}
+
+impl fmt::Display for FailureClass {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let label = match self {
+            FailureClass::Slippage => "slippage",
+            FailureClass::InsufficientProfit => "insufficient profit",
+            FailureClass::FlashLoanLiquidity => "flash loan liquidity",
+            FailureClass::BlockhashExpired => "blockhash expired",
+            FailureClass::ComputeExceeded => "compute exceeded",
+            FailureClass::AccountInUse => "account in use",
+            FailureClass::OracleStale => "oracle stale",
+            FailureClass::Other => "other",
+        };
+        f.write_str(label)
+    }
+}
+
+// Classifies a failed execution from its error message and any program logs
+pub fn classify(error: &str, logs: &[String]) -> FailureClass {
+    let text = failure_text(error, logs);
+    let custom_error = |code: u32| format!("custom program error: {:#x}", code);
+
+    if text.contains("slippageexceeded")
+        || text.contains("slippage tolerance exceeded")
+        || text.contains(&custom_error(SLIPPAGE_EXCEEDED_CODE))
+        || failed_leg(error, logs).is_some()
+    {
+        FailureClass::Slippage
+    } else if text.contains("insufficientprofit")
+        || text.contains(&custom_error(INSUFFICIENT_PROFIT_CODE))
+        || text.contains("below required profit")
+        // The route lost money outright, before the loan could be repaid
+        || text.contains("repayshortfall")
+        || text.contains(&custom_error(REPAY_SHORTFALL_CODE))
+        // A batched route fell short of its own minimum
+        || text.contains("routeunprofitable")
+        || text.contains(&custom_error(ROUTE_UNPROFITABLE_CODE))
+    {
+        FailureClass::InsufficientProfit
+    } else if text.contains("insufficient liquidity")
+        || text.contains("insufficientliquidity")
+        || text.contains("insufficient funds")
+        || text.contains("borrow limit")
+        || text.contains("flashborrowfailed")
+        || text.contains(&custom_error(FLASH_BORROW_FAILED_CODE))
+    {
+        FailureClass::FlashLoanLiquidity
+    } else if text.contains("blockhash not found")
+        || text.contains("blockhashnotfound")
+        || text.contains("block height exceeded")
+        // The program's expiry_slot check: landed too late, like an expired blockhash
+        || text.contains("landed after its expiry slot")
+        || text.contains(&custom_error(EXPIRED_CODE))
+    {
+        FailureClass::BlockhashExpired
+    } else if text.contains("exceeded cus meter")
+        || text.contains("computational budget exceeded")
+        // The program's own check, refusing a limit too low for the route before borrowing
+        || text.contains("insufficientcomputebudget")
+        || text.contains(&custom_error(INSUFFICIENT_COMPUTE_BUDGET_CODE))
+    {
+        FailureClass::ComputeExceeded
+    } else if text.contains("account in use") || text.contains("accountinuse") || text.contains("accountloadedtwice") {
+        FailureClass::AccountInUse
+    } else if text.contains("oraclestale") || text.contains("oracle price is stale") || text.contains(&custom_error(ORACLE_STALE_CODE)) {
+        FailureClass::OracleStale
+    } else {
+        FailureClass::Other
+    }
+}
+
+// 1-based position of the leg whose output fell below its minimum, from the program's per-leg
+// slippage errors
+pub fn failed_leg(error: &str, logs: &[String]) -> Option<u8> {
+    let text = failure_text(error, logs);
+    (0..MAX_ROUTE_LEGS)
+        .find(|leg| {
+            text.contains(&format!("leg{}slippageexceeded", leg + 1))
+                || text.contains(&format!("custom program error: {:#x}", LEG_SLIPPAGE_FIRST_CODE + leg))
+        })
+        .map(|leg| leg as u8 + 1)
+}
+
+// Error message and logs as one lowercase string to search
+fn failure_text(error: &str, logs: &[String]) -> String {
+    std::iter::once(error)
+        .chain(logs.iter().map(String::as_str))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .to_lowercase()
+}
+
+// Program logs carried by a failed simulation, either our own or the RPC's preflight on send
+pub fn logs_from_error(error: &(dyn Error + 'static)) -> Vec<String> {
+    if let Some(simulation) = error.downcast_ref::<SimulationError>() {
+        return simulation.logs.clone();
+    }
+    match error.downcast_ref::<ClientError>().map(|e| e.kind()) {
+        Some(ClientErrorKind::RpcError(RpcError::RpcResponseError {
+            data: RpcResponseErrorData::SendTransactionPreflightFailure(result),
+            ..
+        })) => result.logs.clone().unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn anchor_error_logs_are_classified_by_name() {
+        let logs = ["Program log: AnchorError occurred. Error Code: SlippageExceeded. Error Number: 6003. Error Message: Slippage tolerance exceeded.".to_string()];
+        assert_eq!(classify("Transaction simulation failed", &logs), FailureClass::Slippage);
+        let logs = ["Program log: AnchorError occurred. Error Code: OracleStale. Error Number: 6025. Error Message: Oracle price is stale.".to_string()];
+        assert_eq!(classify("Transaction simulation failed", &logs), FailureClass::OracleStale);
+        assert_eq!(classify("Transaction simulation failed", &[]), FailureClass::Other);
+    }
+
+    #[test]
+    fn program_error_codes_map_to_their_class() {
+        let custom_error = |code: u32| format!("Error processing Instruction 2: custom program error: {:#x}", code);
+        for (code, class) in [
+            (INSUFFICIENT_PROFIT_CODE, FailureClass::InsufficientProfit),
+            (SLIPPAGE_EXCEEDED_CODE, FailureClass::Slippage),
+            (EXPIRED_CODE, FailureClass::BlockhashExpired),
+            (FLASH_BORROW_FAILED_CODE, FailureClass::FlashLoanLiquidity),
+            (REPAY_SHORTFALL_CODE, FailureClass::InsufficientProfit),
+            (LEG_SLIPPAGE_FIRST_CODE + 1, FailureClass::Slippage),
+            (ORACLE_STALE_CODE, FailureClass::OracleStale),
+            (ROUTE_UNPROFITABLE_CODE, FailureClass::InsufficientProfit),
+            (INSUFFICIENT_COMPUTE_BUDGET_CODE, FailureClass::ComputeExceeded),
+        ] {
+            assert_eq!(classify(&custom_error(code), &[]), class, "code {}", code);
+        }
+        assert_eq!(failed_leg(&custom_error(LEG_SLIPPAGE_FIRST_CODE + 1), &[]), Some(2));
+        assert_eq!(classify("Blockhash not found", &[]), FailureClass::BlockhashExpired);
+    }
+}
@@ -0,0 +1,58 @@
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use std::env;
+
+// A separate fee payer (FEE_PAYER_KEYPAIR) pays the trades' transaction fees while the wallet keeps
+// signing for its tokens and receiving the profits. Once the payer's balance falls below
+// FEE_PAYER_FLOOR_LAMPORTS, the wallet transfers it back up to FEE_PAYER_TARGET_LAMPORTS, so sends
+// don't stop on an empty payer
+
+pub struct FeePayer {
+    pub keypair: Keypair,
+    pub floor_lamports: u64,
+    pub target_lamports: u64,
+}
+
+impl FeePayer {
+    // None unless FEE_PAYER_KEYPAIR is set
+    pub fn from_env() -> Option<Self> {
+        let path = env::var("FEE_PAYER_KEYPAIR").ok().filter(|v| !v.is_empty())?;
+        let parse = |name: &str, default: u64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        let floor_lamports = parse("FEE_PAYER_FLOOR_LAMPORTS", 50_000_000);
+        Some(Self {
+            keypair: read_keypair_file(&path).expect("Failed to load FEE_PAYER_KEYPAIR"),
+            floor_lamports,
+            target_lamports: parse("FEE_PAYER_TARGET_LAMPORTS", 200_000_000).max(floor_lamports),
+        })
+    }
+
+    pub fn pubkey(&self) -> Pubkey {
+        self.keypair.pubkey()
+    }
+
+    // Lamports to send a payer holding `balance`: none at or above the floor, otherwise up to the target
+    pub fn top_up_amount(&self, balance: u64) -> u64 {
+        if balance >= self.floor_lamports {
+            0
+        } else {
+            self.target_lamports - balance
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payer_is_topped_up_to_the_target_below_its_floor() {
+        let payer = FeePayer {
+            keypair: Keypair::new(),
+            floor_lamports: 50,
+            target_lamports: 200,
+        };
+        assert_eq!(payer.top_up_amount(50), 0);
+        assert_eq!(payer.top_up_amount(49), 151);
+        assert_eq!(payer.top_up_amount(0), 200);
+    }
+}
@@ -0,0 +1,108 @@
+use crate::client::LoanProvider;
+use crate::marginfi::MarginfiConfig;
+use crate::rpc::RpcApi;
+use crate::solend::{SolendConfig, SolendReserve};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use std::env;
+
+// Where trades borrow their start token from
+pub enum FlashLoanProvider {
+    // No loan: the trade runs on the wallet's own balance
+    OwnBalance,
+    // marginfi flash loan instructions wrapped around the trade instruction
+    Marginfi(MarginfiConfig),
+    // Solend flash borrow and repay instructions wrapped around the trade instruction
+    Solend(SolendConfig),
+}
+
+impl FlashLoanProvider {
+    // FLASH_LOAN_PROVIDER is "marginfi" (default) or "solend"; marginfi also needs MARGINFI_ACCOUNT
+    pub fn from_env() -> Self {
+        match env::var("FLASH_LOAN_PROVIDER").unwrap_or_default().as_str() {
+            "solend" => FlashLoanProvider::Solend(SolendConfig::from_env()),
+            "" | "marginfi" => MarginfiConfig::from_env()
+                .map(FlashLoanProvider::Marginfi)
+                .unwrap_or(FlashLoanProvider::OwnBalance),
+            other => panic!("Invalid FLASH_LOAN_PROVIDER: {}", other),
+        }
+    }
+
+    // The program's `loan_provider` argument, and the loan accounts to append to
+    // `remaining_accounts` at `accounts_offset`
+    pub fn program_args(
+        &self,
+        rpc: &dyn RpcApi,
+        mint: &Pubkey,
+        accounts_offset: usize,
+    ) -> Result<(LoanProvider, Vec<AccountMeta>), Box<dyn std::error::Error>> {
+        match self {
+            FlashLoanProvider::Solend(solend) => Ok((
+                LoanProvider::Solend {
+                    accounts_offset: u8::try_from(accounts_offset)?,
+                },
+                solend_reserve(solend, rpc, mint)?.loan_accounts(),
+            )),
+            _ => Ok((LoanProvider::Marginfi, Vec::new())),
+        }
+    }
+
+    // The trade instruction with any instructions the loan needs around it; `first_index` is the
+    // position of the first returned instruction in the transaction
+    pub fn wrap(
+        &self,
+        rpc: &dyn RpcApi,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+        instruction: Instruction,
+        first_index: usize,
+    ) -> Result<Vec<Instruction>, Box<dyn std::error::Error>> {
+        match self {
+            FlashLoanProvider::Marginfi(marginfi) => marginfi.wrap(owner, mint, amount, instruction, first_index),
+            FlashLoanProvider::Solend(solend) => solend_reserve(solend, rpc, mint)?.wrap(owner, mint, amount, instruction, first_index),
+            _ => Ok(vec![instruction]),
+        }
+    }
+}
+
+// The configured reserve for `mint`, read from the chain
+fn solend_reserve(solend: &SolendConfig, rpc: &dyn RpcApi, mint: &Pubkey) -> Result<SolendReserve, Box<dyn std::error::Error>> {
+    let address = solend.reserve_address(mint)?;
+    SolendReserve::decode(&solend.program_id, address, &rpc.get_account_data(&address)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::mock::MockRpc;
+    use solana_sdk::account::Account;
+    use std::collections::HashMap;
+    use std::str::FromStr;
+
+    #[test]
+    fn loan_instructions_follow_the_provider() {
+        let rpc = MockRpc::new(1);
+        let (owner, mint, reserve) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let trade = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: Vec::new(),
+            data: Vec::new(),
+        };
+        let own = FlashLoanProvider::OwnBalance;
+        assert_eq!(own.wrap(&rpc, &owner, &mint, 1, trade.clone(), 0).unwrap(), vec![trade.clone()]);
+        assert!(matches!(own.program_args(&rpc, &mint, 9).unwrap(), (LoanProvider::Marginfi, accounts) if accounts.is_empty()));
+
+        // Solend's reserve is read from the chain and passed to the program after the leg accounts
+        rpc.set_account(reserve, Account { data: vec![0; 400], ..Account::default() });
+        let solend = FlashLoanProvider::Solend(SolendConfig {
+            program_id: Pubkey::from_str(crate::solend::PROGRAM_ID).unwrap(),
+            reserves: HashMap::from([(mint, reserve)]),
+        });
+        let (provider, accounts) = solend.program_args(&rpc, &mint, 9).unwrap();
+        assert!(matches!(provider, LoanProvider::Solend { accounts_offset: 9 }));
+        assert_eq!(accounts[0].pubkey, reserve);
+        assert_eq!(solend.wrap(&rpc, &owner, &mint, 1, trade.clone(), 0).unwrap().len(), 3);
+        assert!(solend.wrap(&rpc, &owner, &Pubkey::new_unique(), 1, trade, 0).is_err());
+    }
+}
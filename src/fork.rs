@@ -0,0 +1,194 @@
+use crate::costs::LAMPORTS_PER_SIGNATURE;
+use crate::rpc::RpcApi;
+use crate::simulation::{SimulatedProfit, SimulationError};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use litesvm::LiteSVM;
+use solana_sdk::account::Account;
+use solana_sdk::bpf_loader_upgradeable::{self, UpgradeableLoaderState};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::transaction::Transaction;
+use solana_sdk::{bpf_loader, bpf_loader_deprecated, sysvar};
+use std::env;
+
+// Mainnet-fork simulation: every account a transaction references is fetched at its current state
+// and loaded into an in-process SVM, along with the programs it invokes, and the transaction is
+// executed there. Unlike simulate_transaction, every balance the transaction changed can be read
+// back afterwards, and the program can be swapped for a local build before it is deployed
+
+// Byte range of the amount field in an SPL token account
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct ForkConfig {
+    // Local build run in place of the deployed arbitrage program, e.g. target/deploy/arbitrage_contract.so
+    pub program_path: Option<String>,
+}
+
+impl ForkConfig {
+    // Enabled by FORK_SIMULATION=true
+    pub fn from_env() -> Option<Self> {
+        if !env::var("FORK_SIMULATION").map(|v| v == "true").unwrap_or(false) {
+            return None;
+        }
+        Some(Self {
+            program_path: env::var("FORK_PROGRAM").ok().filter(|v| !v.is_empty()),
+        })
+    }
+}
+
+// Change in one writable account across the execution
+#[derive(Debug, Clone)]
+pub struct BalanceDelta {
+    pub address: Pubkey,
+    pub lamports: i128,
+    // Set for SPL Token and Token-2022 accounts
+    pub tokens: Option<i128>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ForkOutcome {
+    pub units_consumed: u64,
+    pub deltas: Vec<BalanceDelta>,
+}
+
+impl ForkOutcome {
+    pub fn delta(&self, address: &Pubkey) -> Option<&BalanceDelta> {
+        self.deltas.iter().find(|delta| delta.address == *address)
+    }
+}
+
+// Executes the signed transaction against current mainnet state; a failed execution is a
+// SimulationError with the program logs, like a failed RPC simulation
+pub fn execute(
+    rpc: &dyn RpcApi,
+    transaction: &Transaction,
+    program_id: &Pubkey,
+    config: &ForkConfig,
+) -> Result<ForkOutcome, Box<dyn std::error::Error>> {
+    let keys = &transaction.message.account_keys;
+    let accounts = rpc.get_multiple_accounts_chunked(keys)?;
+
+    // The transaction was signed against a real blockhash the fork doesn't know; LiteSVM doesn't
+    // check blockhashes, so it runs as-is
+    let mut svm = LiteSVM::new();
+    svm.warp_to_slot(rpc.get_slot()?);
+    for (address, account) in keys.iter().zip(&accounts) {
+        if address == program_id {
+            if let Some(path) = &config.program_path {
+                svm.add_program(*address, &std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path, e))?);
+                continue;
+            }
+        }
+        let Some(account) = account else {
+            continue;
+        };
+        if account.owner == sysvar::id() || *address == sysvar::instructions::id() {
+            // The SVM keeps its own sysvars
+            continue;
+        }
+        if account.executable {
+            if let Some(elf) = program_elf(rpc, account)? {
+                svm.add_program(*address, &elf);
+            }
+            continue;
+        }
+        svm.set_account(*address, account.clone())
+            .map_err(|e| format!("Failed to load account {}: {:?}", address, e))?;
+    }
+
+    let meta = svm.send_transaction(transaction.clone()).map_err(|failed| SimulationError {
+        message: failed.err.to_string(),
+        logs: failed.meta.logs.clone(),
+    })?;
+
+    let deltas = keys
+        .iter()
+        .zip(&accounts)
+        .enumerate()
+        .filter(|(index, _)| transaction.message.is_writable(*index))
+        .map(|(_, (address, pre))| {
+            let post = svm.get_account(address);
+            let lamports = |account: Option<&Account>| account.map_or(0, |account| account.lamports) as i128;
+            let is_token_account = |account: Option<&Account>| account.is_some_and(|account| is_token_program(&account.owner));
+            BalanceDelta {
+                address: *address,
+                lamports: lamports(post.as_ref()) - lamports(pre.as_ref()),
+                tokens: (is_token_account(pre.as_ref()) || is_token_account(post.as_ref()))
+                    .then(|| token_amount(post.as_ref()) - token_amount(pre.as_ref())),
+            }
+        })
+        .collect();
+
+    Ok(ForkOutcome {
+        units_consumed: meta.compute_units_consumed,
+        deltas,
+    })
+}
+
+// Profit from a fork execution, measured like simulation::simulate_profit: the owner's lamports and
+// `mint` tokens, with the transaction fee added back when the owner paid it. The fork only charges
+// the base fee per signature, so that is what is added back; the fee reported is the real one,
+// priority fee included
+pub fn simulate_profit(
+    rpc: &dyn RpcApi,
+    transaction: &Transaction,
+    program_id: &Pubkey,
+    owner: &Pubkey,
+    mint: &Pubkey,
+    config: &ForkConfig,
+) -> Result<SimulatedProfit, Box<dyn std::error::Error>> {
+    let outcome = execute(rpc, transaction, program_id, config)?;
+    println!("Fork simulation: {} CU", outcome.units_consumed);
+    for delta in &outcome.deltas {
+        match delta.tokens {
+            Some(tokens) => println!("  {}: {} lamports, {} tokens", delta.address, delta.lamports, tokens),
+            None => println!("  {}: {} lamports", delta.address, delta.lamports),
+        }
+    }
+
+    let base_fee = if transaction.message.account_keys.first() == Some(owner) {
+        LAMPORTS_PER_SIGNATURE * transaction.signatures.len() as u64
+    } else {
+        0
+    };
+    let lamports = outcome.delta(owner).map_or(0, |delta| delta.lamports) + base_fee as i128;
+    let token_account = get_associated_token_address(owner, mint);
+    let tokens = outcome.delta(&token_account).and_then(|delta| delta.tokens).unwrap_or(0);
+    Ok(SimulatedProfit {
+        gross_profit_lamports: i64::try_from(lamports + tokens)?,
+        fee_lamports: rpc.get_fee_for_message(&transaction.message)?,
+        units_consumed: Some(outcome.units_consumed),
+    })
+}
+
+// The ELF of a deployed program. Upgradeable programs keep it in their program data account, after
+// its metadata; builtins have none and are already in the SVM
+fn program_elf(rpc: &dyn RpcApi, program: &Account) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error>> {
+    if program.owner == bpf_loader::id() || program.owner == bpf_loader_deprecated::id() {
+        return Ok(Some(program.data.clone()));
+    }
+    if program.owner != bpf_loader_upgradeable::id() {
+        return Ok(None);
+    }
+    // UpgradeableLoaderState::Program: a u32 tag, then the program data address
+    let programdata_address = program
+        .data
+        .get(4..36)
+        .map(|bytes| Pubkey::new_from_array(bytes.try_into().unwrap()))
+        .ok_or("Malformed upgradeable program account")?;
+    let programdata = rpc.get_account_data(&programdata_address)?;
+    let metadata_len = UpgradeableLoaderState::size_of_programdata_metadata();
+    Ok(programdata.get(metadata_len..).map(<[u8]>::to_vec))
+}
+
+fn is_token_program(owner: &Pubkey) -> bool {
+    *owner == spl_token::id() || *owner == anchor_spl::token_2022::ID
+}
+
+fn token_amount(account: Option<&Account>) -> i128 {
+    account
+        .and_then(|account| account.data.get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8))
+        .and_then(|bytes| bytes.try_into().ok())
+        .map_or(0, u64::from_le_bytes) as i128
+}
@@ -0,0 +1,234 @@
+use crate::route::{Route, RouteLeg, Venue};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::path::Path;
+
+const DEFAULT_DISCOVERY_SLIPPAGE_BPS: u16 = 50;
+
+// A pool the graph may route through; both directions become edges
+#[derive(Debug, Clone, Deserialize)]
+pub struct KnownPool {
+    pub venue: Venue,
+    pub mint_a: String,
+    pub mint_b: String,
+    // Amount of mint_a used to probe the rate; the b->a probe uses the resulting output
+    pub probe_amount: u64,
+    // Pool address, required for every venue but Jupiter so discovered routes can be executed
+    #[serde(default)]
+    pub pool: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Edge {
+    pub from: usize,
+    pub to: usize,
+    pub venue: Venue,
+    pub pool: Option<String>,
+    // Raw output units per raw input unit; units cancel around a cycle
+    pub rate: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct TokenGraph {
+    pub mints: Vec<String>,
+    index: HashMap<String, usize>,
+    pub edges: Vec<Edge>,
+}
+
+impl TokenGraph {
+    fn node(&mut self, mint: &str) -> usize {
+        if let Some(&index) = self.index.get(mint) {
+            return index;
+        }
+        self.mints.push(mint.to_string());
+        self.index.insert(mint.to_string(), self.mints.len() - 1);
+        self.mints.len() - 1
+    }
+
+    pub fn add_edge(&mut self, from: &str, to: &str, venue: Venue, pool: Option<String>, rate: f64) {
+        if rate <= 0.0 || !rate.is_finite() {
+            return;
+        }
+        let from = self.node(from);
+        let to = self.node(to);
+        self.edges.push(Edge { from, to, venue, pool, rate });
+    }
+
+    // Bellman-Ford over -ln(rate): a negative cycle is a sequence of swaps whose rates multiply to more than 1.
+    // Returns the edge indices of one such cycle.
+    pub fn find_negative_cycle(&self, excluded: &[usize]) -> Option<Vec<usize>> {
+        let n = self.mints.len();
+        if n == 0 {
+            return None;
+        }
+
+        // Starting every node at 0 acts as a virtual source connected to all of them
+        let mut dist = vec![0.0f64; n];
+        let mut pred: Vec<Option<usize>> = vec![None; n];
+        let mut relaxed_node = None;
+
+        for _ in 0..n {
+            relaxed_node = None;
+            for (edge_index, edge) in self.edges.iter().enumerate() {
+                if excluded.contains(&edge_index) {
+                    continue;
+                }
+                let weight = -edge.rate.ln();
+                if dist[edge.from] + weight < dist[edge.to] - 1e-12 {
+                    dist[edge.to] = dist[edge.from] + weight;
+                    pred[edge.to] = Some(edge_index);
+                    relaxed_node = Some(edge.to);
+                }
+            }
+            relaxed_node?;
+        }
+
+        // Still relaxing after n rounds: walk back n steps to land inside the cycle, then collect it
+        let mut node = relaxed_node?;
+        for _ in 0..n {
+            node = self.edges[pred[node]?].from;
+        }
+
+        let start = node;
+        let mut cycle = Vec::new();
+        loop {
+            let edge_index = pred[node]?;
+            cycle.push(edge_index);
+            node = self.edges[edge_index].from;
+            if node == start {
+                break;
+            }
+            if cycle.len() > n {
+                return None;
+            }
+        }
+        cycle.reverse();
+        Some(cycle)
+    }
+
+    // Finds up to `max_cycles` distinct profitable cycles by excluding one edge of each cycle found
+    pub fn find_cycles(&self, max_cycles: usize) -> Vec<Vec<usize>> {
+        let mut excluded = Vec::new();
+        let mut cycles = Vec::new();
+        while cycles.len() < max_cycles {
+            match self.find_negative_cycle(&excluded) {
+                Some(cycle) => {
+                    excluded.push(cycle[0]);
+                    cycles.push(cycle);
+                }
+                None => break,
+            }
+        }
+        cycles
+    }
+
+    pub fn cycle_rate(&self, cycle: &[usize]) -> f64 {
+        cycle.iter().map(|&edge| self.edges[edge].rate).product()
+    }
+
+    // Turns a cycle into a route starting at `start_mint`, the only token the flash loan can provide
+    pub fn cycle_to_route(&self, cycle: &[usize], start_mint: &str, loan_amount: u64) -> Option<Route> {
+        let start = *self.index.get(start_mint)?;
+        let offset = cycle.iter().position(|&edge| self.edges[edge].from == start)?;
+
+        let legs: Vec<RouteLeg> = cycle[offset..]
+            .iter()
+            .chain(cycle[..offset].iter())
+            .map(|&edge_index| {
+                let edge = &self.edges[edge_index];
+                RouteLeg {
+                    venue: edge.venue,
+                    input_mint: self.mints[edge.from].clone(),
+                    output_mint: self.mints[edge.to].clone(),
+                    slippage_bps: DEFAULT_DISCOVERY_SLIPPAGE_BPS,
+                    pool: edge.pool.clone(),
+                    dexes: None,
+                }
+            })
+            .collect();
+
+        let name = legs
+            .iter()
+            .map(|leg| leg.input_mint[..4].to_string())
+            .collect::<Vec<_>>()
+            .join(">");
+
+        Some(Route {
+            name: format!("discovered:{}", name),
+            loan_amount,
+            legs,
+        })
+    }
+}
+
+pub fn load_pools(path: &Path) -> Result<Vec<KnownPool>, Box<dyn std::error::Error>> {
+    Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?)
+}
+
+// Probes both directions of every known pool and builds the graph from the observed rates
+pub async fn build_graph(http: &reqwest::Client, pools: &[KnownPool]) -> TokenGraph {
+    let mut graph = TokenGraph::default();
+    for pool in pools {
+        let forward = RouteLeg {
+            venue: pool.venue,
+            input_mint: pool.mint_a.clone(),
+            output_mint: pool.mint_b.clone(),
+            slippage_bps: DEFAULT_DISCOVERY_SLIPPAGE_BPS,
+            pool: pool.pool.clone(),
+            dexes: None,
+        };
+        let out_b = match crate::route::quote_leg(http, &forward, pool.probe_amount).await {
+            Ok(out) if out > 0 => out,
+            Ok(_) => continue,
+            Err(e) => {
+                println!("Failed to probe {} -> {}: {}", pool.mint_a, pool.mint_b, e);
+                continue;
+            }
+        };
+        graph.add_edge(&pool.mint_a, &pool.mint_b, pool.venue, pool.pool.clone(), out_b as f64 / pool.probe_amount as f64);
+
+        let backward = RouteLeg {
+            venue: pool.venue,
+            input_mint: pool.mint_b.clone(),
+            output_mint: pool.mint_a.clone(),
+            slippage_bps: DEFAULT_DISCOVERY_SLIPPAGE_BPS,
+            pool: pool.pool.clone(),
+            dexes: None,
+        };
+        match crate::route::quote_leg(http, &backward, out_b).await {
+            Ok(out_a) => graph.add_edge(&pool.mint_b, &pool.mint_a, pool.venue, pool.pool.clone(), out_a as f64 / out_b as f64),
+            Err(e) => println!("Failed to probe {} -> {}: {}", pool.mint_b, pool.mint_a, e),
+        }
+    }
+    graph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profitable_cycle_becomes_a_route_from_the_loan_mint() {
+        let (sol, usdc, bonk) = ("So11111111111111111111111111111111111111112", "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v", "DezXAZ8z7PnrnRJjz3wXBoRgixCa6xjnB7YaB1pPB263");
+        let mut graph = TokenGraph::default();
+        // SOL -> USDC -> BONK -> SOL multiplies out to 1.02; the way back through USDC loses
+        graph.add_edge(sol, usdc, Venue::Raydium, Some("pool".to_string()), 150.0);
+        graph.add_edge(usdc, bonk, Venue::Jupiter, None, 40.0);
+        graph.add_edge(bonk, sol, Venue::Jupiter, None, 0.017 / 100.0);
+        graph.add_edge(usdc, sol, Venue::Raydium, Some("pool".to_string()), 1.0 / 151.0);
+        // Unusable rates are dropped
+        graph.add_edge(sol, bonk, Venue::Jupiter, None, f64::NAN);
+        assert_eq!(graph.edges.len(), 4);
+
+        let cycles = graph.find_cycles(5);
+        assert_eq!(cycles.len(), 1);
+        assert!((graph.cycle_rate(&cycles[0]) - 1.02).abs() < 1e-9);
+        let route = graph.cycle_to_route(&cycles[0], sol, 1_000_000_000).unwrap();
+        let mints: Vec<&str> = route.legs.iter().map(|leg| leg.input_mint.as_str()).collect();
+        assert_eq!(mints, [sol, usdc, bonk]);
+        assert_eq!(route.name, "discovered:So11>EPjF>DezX");
+        assert_eq!(route.legs[0].venue, Venue::Raydium);
+        assert_eq!(route.legs[0].pool.as_deref(), Some("pool"));
+        assert!(graph.cycle_to_route(&cycles[0], "unknown", 1).is_none());
+    }
+}
@@ -0,0 +1,73 @@
+use std::collections::HashMap;
+use std::env;
+use std::sync::OnceLock;
+use std::time::Duration;
+
+// Every outbound HTTP request (Jupiter quotes, swap instructions and token list, the Raydium and
+// Orca pool lists, webhooks) goes through one pooled client, so the hot path reuses warm
+// connections instead of paying a TLS handshake for each new client. Timeouts are per host: quotes
+// have to fail fast, while the pool lists are large downloads
+
+// Hosts with their own timeout unless HTTP_HOST_TIMEOUTS_MS overrides it
+const DEFAULT_HOST_TIMEOUTS_MS: &[(&str, u64)] = &[("quote-api.jup.ag", 2_000)];
+
+struct HttpConfig {
+    // For hosts without their own
+    timeout: Duration,
+    host_timeouts: HashMap<String, Duration>,
+}
+
+impl HttpConfig {
+    fn from_env() -> Self {
+        let mut host_timeouts: HashMap<String, Duration> = DEFAULT_HOST_TIMEOUTS_MS
+            .iter()
+            .map(|(host, millis)| (host.to_string(), Duration::from_millis(*millis)))
+            .collect();
+        // `<host>=<ms>,...`
+        for entry in env::var("HTTP_HOST_TIMEOUTS_MS").unwrap_or_default().split(',') {
+            if let Some((host, millis)) = entry.trim().split_once('=') {
+                let millis = millis.trim().parse().expect("Invalid HTTP_HOST_TIMEOUTS_MS");
+                host_timeouts.insert(host.trim().to_string(), Duration::from_millis(millis));
+            }
+        }
+        Self {
+            timeout: Duration::from_millis(env_parse("HTTP_TIMEOUT_MS", 30_000)),
+            host_timeouts,
+        }
+    }
+}
+
+fn env_parse(name: &str, default: u64) -> u64 {
+    env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default)
+}
+
+static SHARED: OnceLock<(reqwest::Client, HttpConfig)> = OnceLock::new();
+
+fn shared() -> &'static (reqwest::Client, HttpConfig) {
+    SHARED.get_or_init(|| {
+        let client = reqwest::Client::builder()
+            // Idle connections kept open per host, enough for a cycle's concurrent quotes
+            .pool_max_idle_per_host(env_parse("HTTP_MAX_IDLE_PER_HOST", 16) as usize)
+            .pool_idle_timeout(Duration::from_secs(90))
+            .tcp_keepalive(Duration::from_secs(30))
+            .tcp_nodelay(true)
+            .connect_timeout(Duration::from_millis(env_parse("HTTP_CONNECT_TIMEOUT_MS", 2_000)))
+            .build()
+            .expect("Failed to build HTTP client");
+        (client, HttpConfig::from_env())
+    })
+}
+
+// The shared client; clones share its connection pool
+pub fn client() -> reqwest::Client {
+    shared().0.clone()
+}
+
+// Timeout for a request to `url`, by its host
+pub fn timeout_for(url: &str) -> Duration {
+    let config = &shared().1;
+    reqwest::Url::parse(url)
+        .ok()
+        .and_then(|url| url.host_str().and_then(|host| config.host_timeouts.get(host).copied()))
+        .unwrap_or(config.timeout)
+}
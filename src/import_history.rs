@@ -0,0 +1,63 @@
+use crate::commands;
+use crate::journal::TradeJournal;
+use crate::network;
+use crate::reconcile::{self, OnChainTrade};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Signer};
+use std::env;
+use std::path::Path;
+use std::str::FromStr;
+
+// Rebuilds the trade journal from the program's history on chain, for runs of versions that kept
+// none. Every transaction with an ArbExecuted event becomes an executed entry, as the log listener
+// would have journaled it; failed transactions only count when the wallet paid for them, since
+// anyone's failed calls to the program are in its history
+
+// The trades to journal, oldest first: those with an event, and the failed ones `wallet` sent
+pub fn importable(trades: Vec<OnChainTrade>, wallet: Option<&Pubkey>) -> Vec<OnChainTrade> {
+    trades
+        .into_iter()
+        .filter(|trade| match &trade.error {
+            None => trade.event.is_some(),
+            Some(_) => wallet.is_some() && trade.fee_payer.as_ref() == wallet,
+        })
+        .collect()
+}
+
+// `import-history [--since <unix timestamp>] [--out <path>] [--force]`: writes a journal of the
+// program's finalized trades since the timestamp (default its whole history) to the path (default
+// TRADE_JOURNAL_PATH). An existing journal is only replaced with --force
+pub fn run(args: &[String], wallet_keypair_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let program = Pubkey::from_str(network::addresses().program)?;
+    let since = match commands::flag_value(args, "--since") {
+        Some(since) => since.parse()?,
+        None => 0,
+    };
+    let out = commands::flag_value(args, "--out")
+        .unwrap_or_else(|| env::var("TRADE_JOURNAL_PATH").unwrap_or_else(|_| "trades.jsonl".to_string()));
+    let exists = Path::new(&out).metadata().is_ok_and(|metadata| metadata.len() > 0);
+    if exists && !args.iter().any(|arg| arg == "--force") {
+        return Err(format!("{} already has entries; pass --force to replace it", out).into());
+    }
+    // Without the wallet, only trades with an event are imported
+    let wallet = read_keypair_file(wallet_keypair_path).ok().map(|wallet| wallet.pubkey());
+    let rpc_client = RpcClient::new_with_commitment(
+        env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string()),
+        CommitmentConfig::finalized(),
+    );
+
+    println!("Reading the history of {} since {}", program, since);
+    let trades = importable(reconcile::program_trades(&rpc_client, &program, &program, since)?, wallet.as_ref());
+    if exists {
+        std::fs::remove_file(&out)?;
+    }
+    let journal = TradeJournal::new(&out);
+    for trade in &trades {
+        journal.record(&trade.to_journal_entry())?;
+    }
+    let failed = trades.iter().filter(|trade| trade.error.is_some()).count();
+    println!("Journaled {} trades to {}, {} of them failed", trades.len(), out, failed);
+    Ok(())
+}
@@ -0,0 +1,179 @@
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{BTreeMap, HashMap};
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// Inventory mode (INVENTORY_MODE): on pairs of SOL and a token, the bot holds some of the token so
+// a trade can be one swap instead of a flash-loaned round trip. While the token is held at or above
+// INVENTORY_TARGET_LAMPORTS, an opportunity sells it on the dearer venue and the buy leg and its fee
+// are skipped; below that, SOL buys it on the cheaper venue into inventory, skipping the sell leg,
+// as long as the position stays within INVENTORY_MAX_LAMPORTS. Positions are tracked at what they
+// cost in lamports and kept in INVENTORY_PATH across restarts. Every INVENTORY_REBALANCE_SECS,
+// whatever is held beyond the target is swapped back to SOL
+
+#[derive(Debug, Clone)]
+pub struct InventoryConfig {
+    pub target_lamports: u64,
+    pub max_lamports: u64,
+    pub rebalance_secs: u64,
+    pub path: PathBuf,
+}
+
+impl InventoryConfig {
+    // None unless INVENTORY_MODE=true
+    pub fn from_env() -> Option<Self> {
+        if env::var("INVENTORY_MODE").map(|v| v != "true").unwrap_or(true) {
+            return None;
+        }
+        let parse = |name: &str, default: u64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Some(Self {
+            target_lamports: parse("INVENTORY_TARGET_LAMPORTS", 1_000_000_000),
+            max_lamports: parse("INVENTORY_MAX_LAMPORTS", 5_000_000_000),
+            rebalance_secs: parse("INVENTORY_REBALANCE_SECS", 3600),
+            path: PathBuf::from(env::var("INVENTORY_PATH").unwrap_or_else(|_| "inventory.json".to_string())),
+        })
+    }
+}
+
+// The one leg an inventory trade swaps
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InventorySide {
+    // SOL into the token on the cheaper venue
+    Buy,
+    // Held token into SOL on the dearer venue
+    Sell,
+}
+
+// Token held for one pair, in base units, and the lamports it cost
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct Position {
+    pub amount: u64,
+    pub cost_lamports: u64,
+}
+
+// An inventory trade as journaled: the token it moved and the cost it added to the position on a
+// buy, or took out of it on a sell
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InventoryTrade {
+    pub side: InventorySide,
+    pub token_amount: u64,
+    pub cost_lamports: u64,
+}
+
+impl InventoryTrade {
+    // Profit from the SOL the transaction gained: a buy's spend is held as inventory rather than
+    // lost, and a sell only profits on what it got beyond the tokens' cost
+    pub fn profit_from(&self, sol_gained: i64) -> i64 {
+        match self.side {
+            InventorySide::Buy => sol_gained + self.cost_lamports as i64,
+            InventorySide::Sell => sol_gained - self.cost_lamports as i64,
+        }
+    }
+}
+
+// Positions per pair key
+pub struct InventoryBook {
+    config: InventoryConfig,
+    positions: BTreeMap<String, Position>,
+}
+
+impl InventoryBook {
+    // The book saved at the configured path, empty when there is none
+    pub fn load(config: InventoryConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        let positions = match std::fs::read_to_string(&config.path) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => BTreeMap::new(),
+            Err(e) => return Err(e.into()),
+        };
+        Ok(Self { config, positions })
+    }
+
+    pub fn config(&self) -> &InventoryConfig {
+        &self.config
+    }
+
+    pub fn position(&self, pair_key: &str) -> Position {
+        self.positions.get(pair_key).copied().unwrap_or_default()
+    }
+
+    // Token held per mint across the pairs, each keyed by SOL and its token
+    pub fn holdings(&self) -> HashMap<Pubkey, u64> {
+        let mut holdings = HashMap::new();
+        for (pair_key, position) in &self.positions {
+            if let Some(mint) = pair_key.split_once('/').and_then(|(_, token)| Pubkey::from_str(token).ok()) {
+                *holdings.entry(mint).or_insert(0) += position.amount;
+            }
+        }
+        holdings
+    }
+
+    // The leg a trade of `size` lamports takes, where the buy leg would yield `token_amount`: a sell
+    // while enough is held at the target, else a buy while it fits under the limit, else a sell of
+    // what's held. None when neither fits and the trade should be a round trip
+    pub fn plan(&self, pair_key: &str, size: u64, token_amount: u64) -> Option<InventorySide> {
+        let position = self.position(pair_key);
+        let can_sell = position.amount >= token_amount;
+        if can_sell && position.cost_lamports >= self.config.target_lamports {
+            Some(InventorySide::Sell)
+        } else if position.cost_lamports + size <= self.config.max_lamports {
+            Some(InventorySide::Buy)
+        } else if can_sell {
+            Some(InventorySide::Sell)
+        } else {
+            None
+        }
+    }
+
+    // Adds `token_amount` bought for `lamports` to the pair's position
+    pub fn buy(&mut self, pair_key: &str, token_amount: u64, lamports: u64) -> InventoryTrade {
+        let position = self.positions.entry(pair_key.to_string()).or_default();
+        position.amount += token_amount;
+        position.cost_lamports += lamports;
+        InventoryTrade {
+            side: InventorySide::Buy,
+            token_amount,
+            cost_lamports: lamports,
+        }
+    }
+
+    // Takes `token_amount` sold out of the pair's position, with its share of the cost
+    pub fn sell(&mut self, pair_key: &str, token_amount: u64) -> InventoryTrade {
+        let position = self.positions.entry(pair_key.to_string()).or_default();
+        let token_amount = token_amount.min(position.amount);
+        let cost = (position.cost_lamports as u128 * token_amount as u128 / position.amount.max(1) as u128) as u64;
+        position.amount -= token_amount;
+        position.cost_lamports -= cost;
+        if position.amount == 0 {
+            self.positions.remove(pair_key);
+        }
+        InventoryTrade {
+            side: InventorySide::Sell,
+            token_amount,
+            cost_lamports: cost,
+        }
+    }
+
+    // Per pair, the token amount held beyond the target
+    pub fn excess(&self) -> Vec<(String, u64)> {
+        self.positions
+            .iter()
+            .filter(|(_, position)| position.cost_lamports > self.config.target_lamports)
+            .map(|(pair_key, position)| {
+                let excess_cost = position.cost_lamports - self.config.target_lamports;
+                let amount = position.amount as u128 * excess_cost as u128 / position.cost_lamports as u128;
+                (pair_key.clone(), amount as u64)
+            })
+            .filter(|(_, amount)| *amount > 0)
+            .collect()
+    }
+
+    pub fn save(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let temporary = self.config.path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_string_pretty(&self.positions)?)?;
+        std::fs::rename(&temporary, &self.config.path)?;
+        Ok(())
+    }
+}
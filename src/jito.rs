@@ -0,0 +1,388 @@
+use crate::http_client;
+use crate::landing::Backend;
+use crate::network;
+use crate::rpc::{self, RpcApi};
+use serde::Deserialize;
+use solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcSimulateTransactionConfig, RpcTransactionConfig};
+use solana_client::rpc_request::RpcRequest;
+use solana_client::rpc_response::{RpcResult, RpcSimulateTransactionResult};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::message::Message;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::{
+    Encodable, EncodedConfirmedTransactionWithStatusMeta, EncodedTransaction, UiTransactionEncoding,
+};
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Private submission (PRIVATE_SUBMISSION=true): execution transactions are only ever sent to the
+// Jito block engine, bundle-only, so they never reach a public RPC node's sendTransaction and its
+// forwarding path, where the payload can be copied or front-run before it lands. Each transaction,
+// or each bundle of trades under JITO_BUNDLE_TRADES, tips a Jito tip account, which the block
+// engine requires. Reads, simulations and confirmation still go to the regular RPC; a failed
+// private send is returned, never retried publicly
+
+// Age after which the landed tip statistics are refetched
+const TIP_FLOOR_REFRESH: Duration = Duration::from_secs(30);
+// Most transactions the block engine accepts in one bundle
+pub const MAX_BUNDLE_TRANSACTIONS: usize = 5;
+
+// Tip per transaction: the recent landed tip at `percentile`, raised to `profit_share` of the
+// trade's expected profit when that's more, within [min_lamports, max_lamports]. A trade worth more
+// draws more competition, so it bids more; a cheap one pays only what has been landing
+pub struct TipPolicy {
+    pub min_lamports: u64,
+    pub max_lamports: u64,
+    pub profit_share: f64,
+    // Percentile of recently landed tips bid as the floor: 25, 50, 75, 95 or 99
+    pub percentile: u8,
+    // Floor until the first statistics arrive, or when they can't be fetched
+    pub default_lamports: u64,
+}
+
+impl TipPolicy {
+    pub fn from_env() -> Self {
+        let lamports = |name: &str, default: u64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Self {
+            // The block engine rejects bundles tipping less
+            min_lamports: lamports("JITO_TIP_MIN_LAMPORTS", 1_000),
+            max_lamports: lamports("JITO_TIP_MAX_LAMPORTS", 5_000_000),
+            profit_share: env::var("JITO_TIP_PROFIT_SHARE").ok().and_then(|v| v.parse().ok()).unwrap_or(0.1),
+            percentile: env::var("JITO_TIP_PERCENTILE").ok().and_then(|v| v.parse().ok()).unwrap_or(50),
+            default_lamports: lamports("JITO_TIP_LAMPORTS", 10_000),
+        }
+    }
+
+    // The tip for a trade expected to make `expected_profit`, given the recent landed tip; without
+    // a profit, only the floor
+    pub fn size(&self, floor: Option<u64>, expected_profit: Option<i64>) -> u64 {
+        let floor = floor.unwrap_or(self.default_lamports);
+        let bid = expected_profit.map_or(0.0, |profit| profit.max(0) as f64 * self.profit_share) as u64;
+        floor.max(bid).clamp(self.min_lamports, self.max_lamports.max(self.min_lamports))
+    }
+}
+
+// Landed tip percentiles over the recent window, in SOL
+#[derive(Debug, Deserialize)]
+struct TipFloor {
+    landed_tips_25th_percentile: f64,
+    landed_tips_50th_percentile: f64,
+    landed_tips_75th_percentile: f64,
+    landed_tips_95th_percentile: f64,
+    landed_tips_99th_percentile: f64,
+}
+
+impl TipFloor {
+    fn lamports(&self, percentile: u8) -> u64 {
+        let sol = match percentile {
+            ..=25 => self.landed_tips_25th_percentile,
+            26..=50 => self.landed_tips_50th_percentile,
+            51..=75 => self.landed_tips_75th_percentile,
+            76..=95 => self.landed_tips_95th_percentile,
+            _ => self.landed_tips_99th_percentile,
+        };
+        (sol * LAMPORTS_PER_SOL as f64).round() as u64
+    }
+}
+
+pub struct JitoConfig {
+    pub block_engine_url: String,
+    // Jito's landed tip statistics
+    pub tip_floor_url: String,
+    pub tip: TipPolicy,
+    // Send the trades a cycle finds together, in bundles of up to MAX_BUNDLE_TRANSACTIONS sharing
+    // one tip, instead of each as it's found
+    pub bundle_trades: bool,
+    // Latest landed tip at the policy's percentile, and when it was last fetched or tried
+    floor: Mutex<Option<u64>>,
+    fetched: Mutex<Option<Instant>>,
+    // Tip account the next transaction pays
+    next_tip_account: AtomicUsize,
+}
+
+impl JitoConfig {
+    pub fn new(block_engine_url: String, tip_floor_url: String, tip: TipPolicy) -> Self {
+        Self {
+            block_engine_url,
+            tip_floor_url,
+            tip,
+            bundle_trades: false,
+            floor: Mutex::new(None),
+            fetched: Mutex::new(None),
+            next_tip_account: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn from_env() -> Option<Self> {
+        if !env::var("PRIVATE_SUBMISSION").map(|v| v == "true").unwrap_or(false) {
+            return None;
+        }
+        // Nothing to tip on a cluster without a block engine
+        if network::addresses().jito_tip_accounts.is_empty() {
+            return None;
+        }
+        let url = |name: &str, default: &str| env::var(name).ok().filter(|v| !v.is_empty()).unwrap_or_else(|| default.to_string());
+        let mut config = Self::new(
+            url("JITO_BLOCK_ENGINE_URL", "https://mainnet.block-engine.jito.wtf"),
+            url("JITO_TIP_FLOOR_URL", "https://bundles.jito.wtf/api/v1/bundles/tip_floor"),
+            TipPolicy::from_env(),
+        );
+        config.bundle_trades = env::var("JITO_BUNDLE_TRADES").map(|v| v == "true").unwrap_or(false);
+        Some(config)
+    }
+
+    // Refetches the landed tip statistics once the last fetch is TIP_FLOOR_REFRESH old; a failed
+    // fetch keeps the previous floor
+    pub async fn refresh_tip_floor(&self, http: &reqwest::Client) {
+        {
+            let mut fetched = self.fetched.lock().unwrap();
+            if fetched.is_some_and(|fetched| fetched.elapsed() < TIP_FLOOR_REFRESH) {
+                return;
+            }
+            *fetched = Some(Instant::now());
+        }
+        let fetched = async {
+            let response = http
+                .get(&self.tip_floor_url)
+                .timeout(http_client::timeout_for(&self.tip_floor_url))
+                .send()
+                .await?
+                .error_for_status()?;
+            response.json::<Vec<TipFloor>>().await
+        }
+        .await;
+        match fetched {
+            Ok(floors) => match floors.first() {
+                Some(floor) => *self.floor.lock().unwrap() = Some(floor.lamports(self.tip.percentile)),
+                None => println!("Jito tip floor response was empty"),
+            },
+            Err(e) => println!("Failed to fetch Jito tip floor: {}", e),
+        }
+    }
+
+    // The tip every transaction pays at least, charged as part of its cost
+    pub fn tip_floor(&self) -> u64 {
+        self.tip.size(self.floor(), None)
+    }
+
+    // Transfer of the tip for a trade expected to make `expected_profit`, to the next tip account
+    // in turn, so consecutive transactions don't write-lock the same one
+    pub fn tip_instruction(&self, payer: &Pubkey, expected_profit: Option<i64>) -> Instruction {
+        let tip_accounts = network::addresses().jito_tip_accounts;
+        let index = self.next_tip_account.fetch_add(1, Ordering::Relaxed) % tip_accounts.len();
+        let tip_account = Pubkey::from_str(tip_accounts[index]).unwrap();
+        system_instruction::transfer(payer, &tip_account, self.tip.size(self.floor(), expected_profit))
+    }
+
+    fn floor(&self) -> Option<u64> {
+        *self.floor.lock().unwrap()
+    }
+}
+
+// Groups trades, given the accounts each contests, into bundles of up to MAX_BUNDLE_TRANSACTIONS
+// in which no two trades contest the same account; each trade joins the first bundle it fits, so
+// the order given is kept within a bundle
+pub fn pack_bundles(contested: &[&HashSet<Pubkey>]) -> Vec<Vec<usize>> {
+    let mut bundles: Vec<(Vec<usize>, HashSet<Pubkey>)> = Vec::new();
+    for (index, accounts) in contested.iter().enumerate() {
+        let fits = bundles.iter_mut().find(|(trades, taken)| {
+            trades.len() < MAX_BUNDLE_TRANSACTIONS && taken.is_disjoint(accounts)
+        });
+        match fits {
+            Some((trades, taken)) => {
+                trades.push(index);
+                taken.extend(accounts.iter());
+            }
+            None => bundles.push((vec![index], (*accounts).clone())),
+        }
+    }
+    bundles.into_iter().map(|(trades, _)| trades).collect()
+}
+
+// Wraps the regular RpcApi, sending through the block engine instead
+pub struct PrivateRpc {
+    inner: Arc<dyn RpcApi>,
+    // The block engine's sendTransaction, restricted to bundles, and its sendBundle
+    block_engine: RpcClient,
+    bundles: RpcClient,
+    // Signature status polling after a private send
+    status: RpcClient,
+}
+
+impl PrivateRpc {
+    pub fn new(inner: Arc<dyn RpcApi>, config: &JitoConfig, rpc_url: &str) -> Self {
+        let base = config.block_engine_url.trim_end_matches('/');
+        Self {
+            inner,
+            block_engine: RpcClient::new(format!("{}/api/v1/transactions?bundleOnly=true", base)),
+            bundles: RpcClient::new(format!("{}/api/v1/bundles", base)),
+            status: RpcClient::new(rpc_url.to_string()),
+        }
+    }
+
+    fn send_private(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        let encoded = encode(transaction)?;
+        let signature: String = self.block_engine.send(
+            RpcRequest::SendTransaction,
+            serde_json::json!([encoded, { "encoding": "base64" }]),
+        )?;
+        Signature::from_str(&signature)
+            .map_err(|e| ClientErrorKind::Custom(format!("Invalid signature from block engine: {}", e)).into())
+    }
+}
+
+fn encode(transaction: &Transaction) -> ClientResult<String> {
+    match transaction.encode(UiTransactionEncoding::Base64) {
+        EncodedTransaction::Binary(encoded, _) => Ok(encoded),
+        _ => Err(ClientErrorKind::Custom("Failed to encode transaction".to_string()).into()),
+    }
+}
+
+impl RpcApi for PrivateRpc {
+    fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    fn get_slot(&self) -> ClientResult<Slot> {
+        self.inner.get_slot()
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.inner.get_latest_blockhash()
+    }
+
+    fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)> {
+        self.inner.get_latest_blockhash_with_height()
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.inner.get_account(pubkey)
+    }
+
+    fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        self.inner.get_multiple_accounts_with_commitment(pubkeys, commitment)
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.inner.simulate_transaction_with_config(transaction, config)
+    }
+
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        self.inner.get_fee_for_message(message)
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        let (_, last_valid_block_height) = self.inner.get_latest_blockhash_with_height()?;
+        Ok(self.send_and_confirm_transaction_timed(transaction, last_valid_block_height)?.0)
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.inner.get_transaction_with_config(signature, config)
+    }
+
+    fn send_and_confirm_transaction_timed(
+        &self,
+        transaction: &Transaction,
+        last_valid_block_height: u64,
+    ) -> ClientResult<(Signature, Duration, Backend)> {
+        let signature = self.send_private(transaction)?;
+        let sent = Instant::now();
+        rpc::await_confirmation(&self.status, &signature, last_valid_block_height, &|| {
+            self.send_private(transaction).map(|_| ())
+        })?;
+        Ok((signature, sent.elapsed(), Backend::Jito))
+    }
+
+    fn send_and_confirm_bundle_timed(
+        &self,
+        transactions: &[Transaction],
+        last_valid_block_height: u64,
+    ) -> ClientResult<(Vec<Signature>, Duration)> {
+        let encoded = transactions.iter().map(encode).collect::<ClientResult<Vec<_>>>()?;
+        let bundle_id: String = self.bundles.send(
+            RpcRequest::Custom { method: "sendBundle" },
+            serde_json::json!([encoded, { "encoding": "base64" }]),
+        )?;
+        println!("Sent bundle {} ({} transactions)", bundle_id, transactions.len());
+        let sent = Instant::now();
+        // The bundle lands whole or not at all, so every transaction confirms or fails together. A
+        // bundle isn't resent; the block engine drops it once the blockhash expires
+        for transaction in transactions {
+            rpc::await_confirmation(&self.status, &transaction.signatures[0], last_valid_block_height, &|| Ok(()))?;
+        }
+        Ok((transactions.iter().map(|transaction| transaction.signatures[0]).collect(), sent.elapsed()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tips_scale_with_profit_within_the_limits() {
+        let policy = TipPolicy {
+            min_lamports: 1_000,
+            max_lamports: 100_000,
+            profit_share: 0.1,
+            percentile: 50,
+            default_lamports: 10_000,
+        };
+        assert_eq!(policy.size(None, None), 10_000);
+        assert_eq!(policy.size(Some(20_000), Some(50_000)), 20_000);
+        assert_eq!(policy.size(Some(20_000), Some(500_000)), 50_000);
+        assert_eq!(policy.size(Some(20_000), Some(5_000_000)), 100_000);
+        assert_eq!(policy.size(Some(10), Some(-5_000)), 1_000);
+
+        let floor = TipFloor {
+            landed_tips_25th_percentile: 0.000_001,
+            landed_tips_50th_percentile: 0.000_01,
+            landed_tips_75th_percentile: 0.000_1,
+            landed_tips_95th_percentile: 0.001,
+            landed_tips_99th_percentile: 0.01,
+        };
+        assert_eq!([25, 50, 75, 95, 99].map(|percentile| floor.lamports(percentile)), [1_000, 10_000, 100_000, 1_000_000, 10_000_000]);
+    }
+
+    #[test]
+    fn trades_contesting_an_account_go_in_separate_bundles() {
+        let (pool, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let contested = [
+            HashSet::from([pool]),
+            HashSet::from([pool]),
+            HashSet::from([other]),
+            HashSet::from([pool, other]),
+            HashSet::from([Pubkey::new_unique()]),
+        ];
+        // Each trade joins the first bundle it doesn't contest an account of
+        assert_eq!(pack_bundles(&contested.iter().collect::<Vec<_>>()), vec![vec![0, 2, 4], vec![1], vec![3]]);
+        // And no bundle grows past the block engine's limit
+        let independent: Vec<HashSet<Pubkey>> = (0..7).map(|_| HashSet::from([Pubkey::new_unique()])).collect();
+        assert_eq!(pack_bundles(&independent.iter().collect::<Vec<_>>()), vec![vec![0, 1, 2, 3, 4], vec![5, 6]]);
+    }
+}
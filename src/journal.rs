@@ -0,0 +1,279 @@
+use crate::costs::{CostBreakdown, WSOL_MINT};
+use crate::dust::{self, DustSweep};
+use crate::evaluator::Quote;
+use crate::events::ArbExecutedEvent;
+use crate::failure::FailureClass;
+use crate::inventory::InventoryTrade;
+use crate::landing::Landing;
+use crate::latency::Stage;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TradeStatus {
+    Executed,
+    Failed,
+    // Sent, but its blockhash expired before it landed; it never reached the chain and cost nothing
+    Expired,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    #[serde(default)]
+    pub opportunity_id: String,
+    // UUID assigned when the trade was decided, keying its records in the audit log
+    #[serde(default)]
+    pub trade_id: String,
+    #[serde(default)]
+    pub slot: u64,
+    pub timestamp: u64,
+    pub token_a: String,
+    pub token_b: String,
+    // Name of the multi-leg route, for route trades
+    #[serde(default)]
+    pub route: Option<String>,
+    pub loan_amount: u64,
+    pub status: TradeStatus,
+    pub signature: Option<String>,
+    pub error: Option<String>,
+    // Cause of a failed execution, classified from the error and program logs
+    #[serde(default)]
+    pub failure_class: Option<FailureClass>,
+    // Leg whose output fell short, when the program reported a per-leg slippage error
+    #[serde(default)]
+    pub failed_leg: Option<u8>,
+    #[serde(default)]
+    pub quote: Option<Quote>,
+    #[serde(default)]
+    pub sol_price: f64,
+    // USD per whole profit token as the trade was journaled, and the token's decimals; with
+    // `sol_price` for the costs they value the trade's P&L in USD
+    #[serde(default)]
+    pub profit_mint_usd: Option<f64>,
+    #[serde(default)]
+    pub profit_mint_decimals: Option<u8>,
+    #[serde(default)]
+    pub expected_profit_lamports: i64,
+    // Gross profit measured from the confirmed transaction's balances
+    #[serde(default)]
+    pub realized_profit_lamports: Option<i64>,
+    // Shortfall of the realized profit against the expected profit, in bps of the expected profit
+    #[serde(default)]
+    pub profit_slippage_bps: Option<i64>,
+    pub costs: CostBreakdown,
+    // The program's own account of the execution, when it emitted one
+    #[serde(default)]
+    pub event: Option<ArbExecutedEvent>,
+    // Milliseconds the opportunity spent in each pipeline stage before the outcome was known
+    #[serde(default)]
+    pub stage_latency_ms: BTreeMap<Stage, u64>,
+    // Where the transaction was sent, at what bid, and whether and when it landed
+    #[serde(default)]
+    pub landing: Option<Landing>,
+    // The single leg swapped against held inventory, for inventory mode trades
+    #[serde(default)]
+    pub inventory: Option<InventoryTrade>,
+    // Residual token swapped back to SOL or USDC, for dust sweeps, whose token A is the swept mint
+    // and token B the one swept to
+    #[serde(default)]
+    pub sweep: Option<DustSweep>,
+    // Config profile the monitor ran under, None without one
+    #[serde(default)]
+    pub config_profile: Option<String>,
+}
+
+impl JournalEntry {
+    pub fn new(token_a: String, token_b: String, loan_amount: u64, status: TradeStatus) -> Self {
+        Self {
+            opportunity_id: String::new(),
+            trade_id: String::new(),
+            slot: 0,
+            timestamp: unix_timestamp(),
+            token_a,
+            token_b,
+            route: None,
+            loan_amount,
+            status,
+            signature: None,
+            error: None,
+            failure_class: None,
+            failed_leg: None,
+            quote: None,
+            sol_price: 0.0,
+            profit_mint_usd: None,
+            profit_mint_decimals: None,
+            expected_profit_lamports: 0,
+            realized_profit_lamports: None,
+            profit_slippage_bps: None,
+            costs: CostBreakdown::default(),
+            event: None,
+            stage_latency_ms: BTreeMap::new(),
+            landing: None,
+            inventory: None,
+            sweep: None,
+            config_profile: None,
+        }
+    }
+
+    // Token the profit is kept in: the start mint of a route, WSOL for a pair, whose trade starts
+    // and ends in SOL, and whatever a dust sweep swapped to
+    pub fn profit_mint(&self) -> &str {
+        match (&self.route, &self.sweep) {
+            (_, Some(_)) => &self.token_b,
+            (Some(_), None) => &self.token_a,
+            (None, None) => WSOL_MINT,
+        }
+    }
+
+    // Dust sweeps all count under one key, apart from the pairs whose tokens they sweep
+    pub fn pair_key(&self) -> String {
+        match (&self.route, &self.sweep) {
+            (_, Some(_)) => dust::SWEEP_KEY.to_string(),
+            (Some(route), None) => route.clone(),
+            (None, None) => format!("{}/{}", self.token_a, self.token_b),
+        }
+    }
+
+    // Gross profit only counts for trades that landed; failed attempts still pay their costs
+    pub fn gross_profit_lamports(&self) -> i64 {
+        match self.status {
+            TradeStatus::Executed => self.expected_profit_lamports,
+            TradeStatus::Failed | TradeStatus::Expired => 0,
+        }
+    }
+
+    pub fn net_profit_lamports(&self) -> i64 {
+        self.gross_profit_lamports() - self.costs.total_lamports()
+    }
+
+    // Gross profit in USD at the prices of execution, None without them. Entries journaled before
+    // valuation are priced at their SOL price when their profit is in SOL
+    pub fn gross_profit_usd(&self) -> Option<f64> {
+        let (price, decimals) = match (self.profit_mint_usd, self.profit_mint_decimals) {
+            (Some(price), Some(decimals)) => (price, decimals),
+            _ if self.profit_mint() == WSOL_MINT && self.sol_price > 0.0 => (self.sol_price, 9),
+            _ => return None,
+        };
+        Some(self.gross_profit_lamports() as f64 / 10f64.powi(decimals as i32) * price)
+    }
+
+    // Costs are always paid in lamports
+    pub fn costs_usd(&self) -> Option<f64> {
+        (self.sol_price > 0.0).then(|| self.costs.total_lamports() as f64 / 1_000_000_000.0 * self.sol_price)
+    }
+
+    pub fn net_profit_usd(&self) -> Option<f64> {
+        Some(self.gross_profit_usd()? - self.costs_usd()?)
+    }
+}
+
+// Append-only JSON lines file, one entry per executed or failed trade
+pub struct TradeJournal {
+    path: PathBuf,
+}
+
+impl TradeJournal {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn record(&self, entry: &JournalEntry) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    pub fn load(&self) -> Result<Vec<JournalEntry>, Box<dyn std::error::Error>> {
+        if !self.path.exists() {
+            return Ok(Vec::new());
+        }
+        let reader = BufReader::new(File::open(&self.path)?);
+        let mut entries = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            entries.push(serde_json::from_str(&line)?);
+        }
+        Ok(entries)
+    }
+
+    pub fn contains_signature(&self, signature: &str) -> Result<bool, Box<dyn std::error::Error>> {
+        Ok(self.load()?.iter().any(|entry| entry.signature.as_deref() == Some(signature)))
+    }
+
+    // The entry with this opportunity or trade ID
+    pub fn find(&self, id: &str) -> Result<Option<JournalEntry>, Box<dyn std::error::Error>> {
+        Ok(self.load()?.into_iter().find(|entry| entry.opportunity_id == id || entry.trade_id == id))
+    }
+}
+
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entries_are_valued_in_usd_by_their_profit_token() {
+        let mut pair = JournalEntry::new("A".to_string(), "B".to_string(), 1_000_000_000, TradeStatus::Executed);
+        pair.sol_price = 150.0;
+        pair.expected_profit_lamports = 10_000_000;
+        pair.costs.base_fee_lamports = 5_000;
+        pair.costs.priority_fee_lamports = 995_000;
+        // A pair's trade starts and ends in SOL
+        assert_eq!(pair.profit_mint(), WSOL_MINT);
+        assert_eq!(pair.pair_key(), "A/B");
+        assert!((pair.net_profit_usd().unwrap() - (1.5 - 0.15)).abs() < 1e-9);
+
+        // A failed attempt earns nothing but still pays its costs
+        pair.status = TradeStatus::Failed;
+        assert_eq!(pair.net_profit_lamports(), -1_000_000);
+
+        // A route keeps its profit in the start token, priced at execution
+        let mut route = JournalEntry::new("USDC".to_string(), "B".to_string(), 1_000_000, TradeStatus::Executed);
+        route.route = Some("cycle".to_string());
+        route.expected_profit_lamports = 2_000_000;
+        route.profit_mint_usd = Some(1.0);
+        route.profit_mint_decimals = Some(6);
+        assert_eq!((route.profit_mint(), route.pair_key().as_str()), ("USDC", "cycle"));
+        assert_eq!(route.gross_profit_usd(), Some(2.0));
+        // Costs are in lamports, so they need the SOL price
+        assert_eq!(route.net_profit_usd(), None);
+    }
+
+    #[test]
+    fn journal_appends_and_reads_back_older_entries() {
+        let path = std::env::temp_dir().join(format!("journal-{}.jsonl", std::process::id()));
+        // Written before the newer fields existed
+        let old = r#"{"timestamp":1,"token_a":"A","token_b":"B","loan_amount":5,"status":"Failed","signature":"old","error":"timeout","costs":{"base_fee_lamports":5000,"priority_fee_lamports":0,"jito_tip_lamports":0,"flash_loan_fee_lamports":0,"dex_fee_lamports":0,"rent_paid_lamports":0,"rent_reclaimed_lamports":0}}"#;
+        std::fs::write(&path, format!("{}\n\n", old)).unwrap();
+        let journal = TradeJournal::new(path.clone());
+        let mut entry = JournalEntry::new("A".to_string(), "B".to_string(), 5, TradeStatus::Executed);
+        entry.signature = Some("new".to_string());
+        entry.expected_profit_lamports = 20_000;
+        journal.record(&entry).unwrap();
+
+        let entries = journal.load().unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].expected_profit_lamports, 0);
+        assert_eq!(entries[0].error.as_deref(), Some("timeout"));
+        assert_eq!(entries[1].signature.as_deref(), Some("new"));
+        assert_eq!(entries[1].net_profit_lamports(), 20_000);
+        assert!(TradeJournal::new(std::env::temp_dir().join("no-such-journal.jsonl")).load().unwrap().is_empty());
+    }
+}
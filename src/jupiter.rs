@@ -0,0 +1,158 @@
+use crate::http_client;
+use crate::network;
+use crate::retry::{self, RetryPolicy};
+use serde::Deserialize;
+use serde_json::{json, Value};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+const QUOTE_API: &str = "https://quote-api.jup.ag/v6/quote";
+const SWAP_INSTRUCTIONS_API: &str = "https://quote-api.jup.ag/v6/swap-instructions";
+
+// A v6 quote; `raw` is the full response, which the swap-instructions endpoint takes back as is
+#[derive(Debug, Clone)]
+pub struct JupiterQuote {
+    pub out_amount: u64,
+    pub raw: Value,
+}
+
+// Jupiter's `shared_accounts_route` instruction for one quote, ready to be forwarded through the program
+#[derive(Debug, Clone)]
+pub struct SwapInstruction {
+    pub accounts: Vec<AccountMeta>,
+    pub data: Vec<u8>,
+}
+
+impl SwapInstruction {
+    // The swap as Jupiter's own instruction, for `owner` to sign directly instead of the program
+    pub fn direct(self, owner: &Pubkey) -> Result<Instruction, Box<dyn std::error::Error>> {
+        let accounts = self
+            .accounts
+            .into_iter()
+            .map(|mut account| {
+                account.is_signer = account.pubkey == *owner;
+                account
+            })
+            .collect();
+        Ok(Instruction {
+            program_id: Pubkey::from_str(network::addresses().jupiter)?,
+            accounts,
+            data: self.data,
+        })
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SwapInstructionsResponse {
+    swap_instruction: InstructionResponse,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct InstructionResponse {
+    accounts: Vec<AccountResponse>,
+    data: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AccountResponse {
+    pubkey: String,
+    is_writable: bool,
+}
+
+// Quotes `amount` of `input_mint`, optionally restricted to the given dexes (e.g. "Raydium")
+pub async fn quote(
+    http: &reqwest::Client,
+    input_mint: &str,
+    output_mint: &str,
+    amount: u64,
+    slippage_bps: u16,
+    dexes: Option<&str>,
+) -> Result<JupiterQuote, Box<dyn std::error::Error>> {
+    let raw: Value = retry::http(&RetryPolicy::HOT, QUOTE_API, || {
+        let mut request = http.get(QUOTE_API).timeout(http_client::timeout_for(QUOTE_API)).query(&[
+            ("inputMint", input_mint.to_string()),
+            ("outputMint", output_mint.to_string()),
+            ("amount", amount.to_string()),
+            ("slippageBps", slippage_bps.to_string()),
+        ]);
+        if let Some(dexes) = dexes {
+            request = request.query(&[("dexes", dexes)]);
+        }
+        async move { request.send().await?.error_for_status()?.json().await }
+    })
+    .await?;
+    let out_amount = raw["outAmount"]
+        .as_str()
+        .ok_or("Jupiter quote has no outAmount")?
+        .parse()?;
+    Ok(JupiterQuote { out_amount, raw })
+}
+
+// Builds the shared accounts route instruction for a quote, with `owner` as the transfer authority
+pub async fn swap_instruction(
+    http: &reqwest::Client,
+    quote: &JupiterQuote,
+    owner: &Pubkey,
+) -> Result<SwapInstruction, Box<dyn std::error::Error>> {
+    let body = json!({
+        "quoteResponse": quote.raw,
+        "userPublicKey": owner.to_string(),
+        "useSharedAccounts": true,
+        "wrapAndUnwrapSol": false,
+    });
+    // Builds an instruction without side effects, so it's as safe to retry as a quote
+    let response: SwapInstructionsResponse = retry::http(&RetryPolicy::HOT, SWAP_INSTRUCTIONS_API, || async {
+        http.post(SWAP_INSTRUCTIONS_API)
+            .timeout(http_client::timeout_for(SWAP_INSTRUCTIONS_API))
+            .json(&body)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+    })
+    .await?;
+
+    let accounts = response
+        .swap_instruction
+        .accounts
+        .iter()
+        .map(|account| {
+            let pubkey = Pubkey::from_str(&account.pubkey)?;
+            // The owner already signs the outer transaction; the program passes its signer flag through
+            Ok(if account.is_writable {
+                AccountMeta::new(pubkey, false)
+            } else {
+                AccountMeta::new_readonly(pubkey, false)
+            })
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+
+    Ok(SwapInstruction {
+        accounts,
+        data: base64::decode(response.swap_instruction.data)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn direct_swap_is_signed_by_the_owner_alone() {
+        let (owner, program_authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let swap = SwapInstruction {
+            accounts: vec![AccountMeta::new_readonly(program_authority, true), AccountMeta::new(owner, false)],
+            data: vec![1, 2, 3],
+        };
+        let instruction = swap.direct(&owner).unwrap();
+        assert_eq!(instruction.program_id.to_string(), network::addresses().jupiter);
+        assert_eq!(instruction.accounts.iter().map(|account| account.is_signer).collect::<Vec<_>>(), [false, true]);
+        assert!(instruction.accounts[1].is_writable);
+        assert_eq!(instruction.data, [1, 2, 3]);
+    }
+}
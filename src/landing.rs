@@ -0,0 +1,273 @@
+use crate::journal::JournalEntry;
+use crate::latency::Stage;
+use crate::network;
+use serde::{Deserialize, Serialize};
+use solana_sdk::clock::Slot;
+use solana_sdk::compute_budget::{self, ComputeBudgetInstruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+use solana_sdk::transaction::Transaction;
+use std::collections::{BTreeMap, VecDeque};
+use std::fmt::Write as _;
+use std::str::FromStr;
+
+// Whether sent transactions land, how many slots after the decision, and at what bid, per
+// submission backend, to tell a backend that drops transactions from one that lands them late
+
+// Latest landed transactions per backend the distributions are taken over
+const WINDOW: usize = 1000;
+// Latest sends per backend landing chances are estimated from, and the fewest that make an estimate
+const RECENT_SENDS: usize = 200;
+const MIN_SAMPLES: usize = 20;
+
+// Where a transaction was submitted
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Backend {
+    // The RPC node's sendTransaction
+    Rpc,
+    // The Jito block engine, one transaction per bundle
+    Jito,
+    // The Jito block engine, in a bundle with other trades
+    JitoBundle,
+}
+
+impl Backend {
+    pub fn name(self) -> &'static str {
+        match self {
+            Backend::Rpc => "rpc",
+            Backend::Jito => "jito",
+            Backend::JitoBundle => "jito_bundle",
+        }
+    }
+}
+
+// The submission of a trade's transaction and its outcome; journal entries of trades that were
+// never sent have none
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Landing {
+    pub backend: Backend,
+    // Priority fee at the transaction's compute unit price and limit, and its Jito tip
+    pub priority_fee_lamports: u64,
+    pub tip_lamports: u64,
+    pub landed: bool,
+    #[serde(default)]
+    pub landed_slot: Option<Slot>,
+    // Slots from the decision to the landing
+    #[serde(default)]
+    pub slot_delay: Option<u64>,
+}
+
+impl Landing {
+    // A transaction about to go out through `backend`, with the bid read from its instructions
+    pub fn sent(backend: Backend, transaction: &Transaction) -> Self {
+        let message = &transaction.message;
+        let (mut unit_price, mut unit_limit, mut tip_lamports) = (0u64, None, 0);
+        for instruction in &message.instructions {
+            let program_id = message.account_keys[instruction.program_id_index as usize];
+            if program_id == compute_budget::id() {
+                match solana_sdk::borsh1::try_from_slice_unchecked::<ComputeBudgetInstruction>(&instruction.data) {
+                    Ok(ComputeBudgetInstruction::SetComputeUnitPrice(price)) => unit_price = price,
+                    Ok(ComputeBudgetInstruction::SetComputeUnitLimit(limit)) => unit_limit = Some(limit),
+                    _ => {}
+                }
+            } else if program_id == system_program::id() {
+                let to = instruction.accounts.get(1).map(|index| message.account_keys[*index as usize]);
+                if let (Ok(SystemInstruction::Transfer { lamports }), Some(to)) =
+                    (bincode::deserialize(&instruction.data), to)
+                {
+                    if is_tip_account(&to) {
+                        tip_lamports += lamports;
+                    }
+                }
+            }
+        }
+        // Without a limit instruction, the runtime's default of 200k units per instruction
+        let unit_limit = unit_limit.map_or(200_000 * message.instructions.len() as u64, u64::from);
+        Self {
+            backend,
+            priority_fee_lamports: (unit_price as u128 * unit_limit as u128 / 1_000_000) as u64,
+            tip_lamports,
+            landed: false,
+            landed_slot: None,
+            slot_delay: None,
+        }
+    }
+
+    // Marks the transaction landed, in `slot` when known, for a trade decided in `decision_slot`
+    pub fn land(&mut self, slot: Option<Slot>, decision_slot: Slot) {
+        self.landed = true;
+        self.landed_slot = slot;
+        self.slot_delay = slot.filter(|_| decision_slot > 0).map(|slot| slot.saturating_sub(decision_slot));
+    }
+}
+
+fn is_tip_account(pubkey: &Pubkey) -> bool {
+    network::addresses()
+        .jito_tip_accounts
+        .iter()
+        .any(|address| Pubkey::from_str(address).is_ok_and(|tip| tip == *pubkey))
+}
+
+#[derive(Debug, Default)]
+struct BackendStats {
+    sent: u64,
+    landed: u64,
+    // Of the latest landed transactions: slots after the decision, and milliseconds from the send
+    // to the confirmation
+    slot_delays: VecDeque<u64>,
+    land_ms: VecDeque<u64>,
+    bid_lamports: u128,
+    // Bid and outcome of the latest sends
+    recent: VecDeque<(u64, bool)>,
+}
+
+// Landing rate and land-latency distributions per backend, over the journal entries observed
+#[derive(Debug, Default)]
+pub struct LandingStats {
+    backends: BTreeMap<Backend, BackendStats>,
+}
+
+impl LandingStats {
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a JournalEntry>) -> Self {
+        let mut stats = Self::default();
+        for entry in entries {
+            stats.observe(entry);
+        }
+        stats
+    }
+
+    pub fn observe(&mut self, entry: &JournalEntry) {
+        let Some(landing) = &entry.landing else {
+            return;
+        };
+        let stats = self.backends.entry(landing.backend).or_default();
+        stats.sent += 1;
+        let bid = landing.priority_fee_lamports + landing.tip_lamports;
+        stats.bid_lamports += bid as u128;
+        if stats.recent.len() == RECENT_SENDS {
+            stats.recent.pop_front();
+        }
+        stats.recent.push_back((bid, landing.landed));
+        if !landing.landed {
+            return;
+        }
+        stats.landed += 1;
+        if let Some(delay) = landing.slot_delay {
+            push(&mut stats.slot_delays, delay);
+        }
+        let land_ms: u64 = [Stage::Send, Stage::Land].iter().filter_map(|stage| entry.stage_latency_ms.get(stage)).sum();
+        push(&mut stats.land_ms, land_ms);
+    }
+
+    // Share of the latest sends through `backend` that landed: of those bidding within a factor of
+    // two of `bid` when there are enough, else of all of them; None while there are too few
+    pub fn landing_probability(&self, backend: Backend, bid: u64) -> Option<f64> {
+        let recent = &self.backends.get(&backend)?.recent;
+        let near: Vec<bool> = recent
+            .iter()
+            .filter(|(sent_bid, _)| *sent_bid <= bid.saturating_mul(2) && sent_bid.saturating_mul(2) >= bid)
+            .map(|(_, landed)| *landed)
+            .collect();
+        let samples = if near.len() >= MIN_SAMPLES {
+            near
+        } else {
+            recent.iter().map(|(_, landed)| *landed).collect()
+        };
+        if samples.len() < MIN_SAMPLES {
+            return None;
+        }
+        Some(samples.iter().filter(|landed| **landed).count() as f64 / samples.len() as f64)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.backends.is_empty()
+    }
+
+    // One line per backend that sent anything
+    pub fn summary(&self) -> String {
+        let mut out = String::new();
+        for (backend, stats) in &self.backends {
+            let _ = write!(
+                out,
+                "  {}: {}/{} landed ({:.1}%), avg bid {} lamports",
+                backend.name(),
+                stats.landed,
+                stats.sent,
+                stats.landed as f64 / stats.sent as f64 * 100.0,
+                stats.bid_lamports / stats.sent as u128
+            );
+            if let Some([p50, p90, _]) = percentiles(&stats.slot_delays) {
+                let _ = write!(out, ", slot delay p50 {} p90 {}", p50, p90);
+            }
+            if let Some([p50, p90, _]) = percentiles(&stats.land_ms) {
+                let _ = write!(out, ", land p50 {} ms p90 {} ms", p50, p90);
+            }
+            out.push('\n');
+        }
+        out
+    }
+
+    // Prometheus text format, alongside the stage latency metrics
+    pub fn metrics(&self) -> String {
+        let mut text = String::from(
+            "# HELP arb_transactions_sent_total Transactions sent, per submission backend\n\
+             # TYPE arb_transactions_sent_total counter\n",
+        );
+        for (backend, stats) in &self.backends {
+            text.push_str(&format!("arb_transactions_sent_total{{backend=\"{}\"}} {}\n", backend.name(), stats.sent));
+        }
+        text.push_str(
+            "# HELP arb_transactions_landed_total Sent transactions that landed, per submission backend\n\
+             # TYPE arb_transactions_landed_total counter\n",
+        );
+        for (backend, stats) in &self.backends {
+            text.push_str(&format!("arb_transactions_landed_total{{backend=\"{}\"}} {}\n", backend.name(), stats.landed));
+        }
+        let summaries = [
+            ("arb_landing_slot_delay", "Slots from the decision to the landing", 1.0),
+            ("arb_landing_latency_seconds", "Time from the send to the confirmation", 1000.0),
+        ];
+        for (index, (name, help, divisor)) in summaries.into_iter().enumerate() {
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} summary\n", name, help, name));
+            for (backend, stats) in &self.backends {
+                let samples = if index == 0 { &stats.slot_delays } else { &stats.land_ms };
+                let Some(values) = percentiles(samples) else {
+                    continue;
+                };
+                for (quantile, value) in ["0.5", "0.9", "1"].iter().zip(values) {
+                    text.push_str(&format!(
+                        "{}{{backend=\"{}\",quantile=\"{}\"}} {}\n",
+                        name,
+                        backend.name(),
+                        quantile,
+                        value as f64 / divisor
+                    ));
+                }
+                let sum: u64 = samples.iter().sum();
+                text.push_str(&format!("{}_sum{{backend=\"{}\"}} {}\n", name, backend.name(), sum as f64 / divisor));
+                text.push_str(&format!("{}_count{{backend=\"{}\"}} {}\n", name, backend.name(), samples.len()));
+            }
+        }
+        text
+    }
+}
+
+fn push(samples: &mut VecDeque<u64>, value: u64) {
+    if samples.len() == WINDOW {
+        samples.pop_front();
+    }
+    samples.push_back(value);
+}
+
+// p50, p90 and the maximum
+fn percentiles(samples: &VecDeque<u64>) -> Option<[u64; 3]> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted: Vec<u64> = samples.iter().copied().collect();
+    sorted.sort();
+    let at = |q: f64| sorted[((sorted.len() - 1) as f64 * q).round() as usize];
+    Some([at(0.5), at(0.9), at(1.0)])
+}
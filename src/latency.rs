@@ -0,0 +1,202 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, VecDeque};
+use std::time::{Duration, Instant};
+
+// Per-stage latency of each opportunity, from the first account read to the transaction landing,
+// to tell races lost on market data from races lost on submission
+
+// Latest samples per stage the percentiles are taken over
+const WINDOW: usize = 1000;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    // Oracle and pool account reads
+    Fetch,
+    // Venue prices and leg quotes
+    Quote,
+    // Evaluator and strategy
+    Decision,
+    // Trade instruction, flash loan wrapping and the pre-send simulation
+    Build,
+    // Blockhash and signature of the transaction that is sent
+    Sign,
+    // Until the RPC node accepted the transaction
+    Send,
+    // From acceptance until the transaction confirmed
+    Land,
+}
+
+impl Stage {
+    pub const ALL: [Stage; 7] = [
+        Stage::Fetch,
+        Stage::Quote,
+        Stage::Decision,
+        Stage::Build,
+        Stage::Sign,
+        Stage::Send,
+        Stage::Land,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Stage::Fetch => "fetch",
+            Stage::Quote => "quote",
+            Stage::Decision => "decision",
+            Stage::Build => "build",
+            Stage::Sign => "sign",
+            Stage::Send => "send",
+            Stage::Land => "land",
+        }
+    }
+}
+
+// Stage timings of one opportunity. Each mark charges the time since the previous mark to a
+// stage; a stage marked twice, like the pair quote and the leg quotes, adds up
+pub struct StageTimer {
+    last: Instant,
+    stages: BTreeMap<Stage, Duration>,
+}
+
+impl StageTimer {
+    pub fn start() -> Self {
+        Self {
+            last: Instant::now(),
+            stages: BTreeMap::new(),
+        }
+    }
+
+    pub fn mark(&mut self, stage: Stage) {
+        let now = Instant::now();
+        *self.stages.entry(stage).or_default() += now - self.last;
+        self.last = now;
+    }
+
+    // Like `mark`, with the last `tail` of the time charged to `tail_stage` instead
+    pub fn mark_split(&mut self, stage: Stage, tail_stage: Stage, tail: Duration) {
+        let now = Instant::now();
+        let elapsed = now - self.last;
+        *self.stages.entry(stage).or_default() += elapsed.saturating_sub(tail);
+        *self.stages.entry(tail_stage).or_default() += tail.min(elapsed);
+        self.last = now;
+    }
+
+    // Charges `duration`, measured elsewhere, to `stage` without moving the last mark
+    pub fn add(&mut self, stage: Stage, duration: Duration) {
+        *self.stages.entry(stage).or_default() += duration;
+    }
+
+    // Drops the time since the previous mark, spent on bookkeeping between stages
+    pub fn skip(&mut self) {
+        self.last = Instant::now();
+    }
+
+    pub fn millis(&self) -> BTreeMap<Stage, u64> {
+        self.stages.iter().map(|(stage, duration)| (*stage, duration.as_millis() as u64)).collect()
+    }
+}
+
+// Latency of every stage across the opportunities observed: percentiles over the latest samples,
+// count and sum over all of them
+#[derive(Default)]
+pub struct LatencyProfile {
+    samples: BTreeMap<Stage, VecDeque<Duration>>,
+    totals: BTreeMap<Stage, (u64, Duration)>,
+}
+
+impl LatencyProfile {
+    pub fn observe(&mut self, timer: &StageTimer) {
+        for (stage, duration) in &timer.stages {
+            let samples = self.samples.entry(*stage).or_default();
+            if samples.len() == WINDOW {
+                samples.pop_front();
+            }
+            samples.push_back(*duration);
+            let (count, sum) = self.totals.entry(*stage).or_default();
+            *count += 1;
+            *sum += *duration;
+        }
+    }
+
+    // p50, p90 and the maximum of the stage's latest samples
+    fn percentiles(&self, stage: Stage) -> Option<[Duration; 3]> {
+        let mut samples: Vec<Duration> = self.samples.get(&stage)?.iter().copied().collect();
+        samples.sort();
+        let at = |q: f64| samples[((samples.len() - 1) as f64 * q).round() as usize];
+        Some([at(0.5), at(0.9), at(1.0)])
+    }
+
+    // Table printed by --profile
+    pub fn summary(&self) -> String {
+        let mut lines = vec![format!("  {:<9} {:>7} {:>9} {:>9} {:>9}", "stage", "count", "p50 ms", "p90 ms", "max ms")];
+        for stage in Stage::ALL {
+            if let Some(percentiles) = self.percentiles(stage) {
+                let [p50, p90, max] = percentiles.map(|duration| duration.as_secs_f64() * 1000.0);
+                lines.push(format!(
+                    "  {:<9} {:>7} {:>9.1} {:>9.1} {:>9.1}",
+                    stage.name(),
+                    self.totals[&stage].0,
+                    p50,
+                    p90,
+                    max
+                ));
+            }
+        }
+        lines.join("\n")
+    }
+
+    // Prometheus text format, for the node exporter's textfile collector
+    pub fn metrics(&self) -> String {
+        let mut text = String::from(
+            "# HELP arb_stage_latency_seconds Latency of each opportunity pipeline stage\n\
+             # TYPE arb_stage_latency_seconds summary\n",
+        );
+        for stage in Stage::ALL {
+            let Some(percentiles) = self.percentiles(stage) else {
+                continue;
+            };
+            for (quantile, duration) in ["0.5", "0.9", "1"].iter().zip(percentiles) {
+                text.push_str(&format!(
+                    "arb_stage_latency_seconds{{stage=\"{}\",quantile=\"{}\"}} {}\n",
+                    stage.name(),
+                    quantile,
+                    duration.as_secs_f64()
+                ));
+            }
+            let (count, sum) = self.totals[&stage];
+            text.push_str(&format!("arb_stage_latency_seconds_sum{{stage=\"{}\"}} {}\n", stage.name(), sum.as_secs_f64()));
+            text.push_str(&format!("arb_stage_latency_seconds_count{{stage=\"{}\"}} {}\n", stage.name(), count));
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stages_are_timed_and_summarised() {
+        let mut timer = StageTimer::start();
+        timer.add(Stage::Fetch, Duration::from_millis(30));
+        timer.add(Stage::Quote, Duration::from_millis(5));
+        // A stage charged twice adds up
+        timer.add(Stage::Quote, Duration::from_millis(7));
+        timer.mark_split(Stage::Build, Stage::Sign, Duration::from_secs(60));
+        let millis = timer.millis();
+        assert_eq!((millis[&Stage::Fetch], millis[&Stage::Quote]), (30, 12));
+        // The tail can't be longer than what elapsed
+        assert_eq!(millis[&Stage::Build], 0);
+
+        let mut profile = LatencyProfile::default();
+        profile.observe(&timer);
+        let mut slow = StageTimer::start();
+        slow.add(Stage::Fetch, Duration::from_millis(90));
+        profile.observe(&slow);
+        let metrics = profile.metrics();
+        assert!(metrics.contains("arb_stage_latency_seconds{stage=\"fetch\",quantile=\"1\"} 0.09\n"));
+        assert!(metrics.contains("arb_stage_latency_seconds_count{stage=\"fetch\"} 2\n"));
+        assert!(!metrics.contains("stage=\"land\""));
+        assert_eq!(profile.summary().lines().count(), 5);
+    }
+}
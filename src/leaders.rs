@@ -0,0 +1,258 @@
+use serde::Deserialize;
+use solana_client::client_error::{ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::clock::{Slot, DEFAULT_MS_PER_SLOT};
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Leader-aware timing for private submission. A bundle only lands in a slot led by a validator
+// running the Jito client, so sending one while the next leaders don't run it just waits in the
+// block engine as the quote goes stale. The epoch's leader schedule and the Jito validator set are
+// kept here; each send checks the leaders about to produce and either goes now or waits for the
+// next Jito leader. A send never falls back to the public RPC
+
+// Age after which the Jito validator set is refetched within an epoch
+const VALIDATORS_REFRESH: Duration = Duration::from_secs(3600);
+
+pub struct LeaderConfig {
+    // Slots after the current one a send can still land in
+    pub lookahead_slots: u64,
+    // Longest wait for a Jito leader before sending regardless
+    pub max_wait_slots: u64,
+    // Jito's validator list, with whether each runs the Jito client
+    pub validators_url: String,
+}
+
+impl LeaderConfig {
+    pub fn from_env() -> Option<Self> {
+        if !env::var("LEADER_AWARE_SUBMISSION").map(|v| v == "true").unwrap_or(false) {
+            return None;
+        }
+        let slots = |name: &str, default: u64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Some(Self {
+            lookahead_slots: slots("LEADER_LOOKAHEAD_SLOTS", 2),
+            max_wait_slots: slots("LEADER_MAX_WAIT_SLOTS", 8),
+            validators_url: env::var("JITO_VALIDATORS_URL")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| "https://kobe.mainnet.jito.network/api/v1/validators".to_string()),
+        })
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum Submission {
+    // A Jito leader is about to produce
+    Jito,
+    // The next Jito leader is this far off
+    WaitThenJito(Duration),
+}
+
+// One epoch's leaders and which of them run the Jito client
+pub struct LeaderSchedule {
+    pub first_slot: Slot,
+    // Leader identity of each slot of the epoch
+    pub leaders: Vec<Pubkey>,
+    pub jito: HashSet<Pubkey>,
+}
+
+impl LeaderSchedule {
+    fn is_jito(&self, slot: Slot) -> bool {
+        slot.checked_sub(self.first_slot)
+            .and_then(|index| self.leaders.get(index as usize))
+            .is_some_and(|leader| self.jito.contains(leader))
+    }
+
+    fn contains(&self, slot: Slot) -> bool {
+        slot >= self.first_slot && slot < self.first_slot + self.leaders.len() as u64
+    }
+
+    // Until the Jito validator set is known, every send goes to the block engine
+    pub fn plan(&self, slot: Slot, config: &LeaderConfig) -> Submission {
+        if self.jito.is_empty() {
+            return Submission::Jito;
+        }
+        let window_end = slot + config.lookahead_slots;
+        if (slot..=window_end).any(|slot| self.is_jito(slot)) {
+            return Submission::Jito;
+        }
+        // Past the wait limit, or past the known schedule, the bundle waits in the block engine
+        match (window_end + 1..=slot + config.max_wait_slots).find(|slot| self.is_jito(*slot)) {
+            Some(next) => Submission::WaitThenJito(Duration::from_millis((next - window_end) * DEFAULT_MS_PER_SLOT)),
+            None => Submission::Jito,
+        }
+    }
+}
+
+// Waits out a WaitThenJito plan. The wait yields to the runtime, and when `cancel` resolves first
+// it fails, giving the send up
+async fn hold(plan: Submission, cancel: impl Future<Output = ()>) -> ClientResult<()> {
+    let Submission::WaitThenJito(wait) = plan else {
+        return Ok(());
+    };
+    println!("Holding transaction {} ms for the next Jito leader", wait.as_millis());
+    tokio::select! {
+        _ = tokio::time::sleep(wait) => Ok(()),
+        _ = cancel => Err(ClientErrorKind::Custom("Leader wait cancelled".to_string()).into()),
+    }
+}
+
+#[derive(Deserialize)]
+struct JitoValidators {
+    validators: Vec<JitoValidator>,
+}
+
+#[derive(Deserialize)]
+struct JitoValidator {
+    vote_account: String,
+    #[serde(default)]
+    running_jito: bool,
+}
+
+pub struct LeaderTracker {
+    config: LeaderConfig,
+    rpc: RpcClient,
+    schedule: Mutex<Option<LeaderSchedule>>,
+    // When the Jito validator set was last fetched or tried
+    validators_fetched: Mutex<Option<Instant>>,
+}
+
+impl LeaderTracker {
+    pub fn new(config: LeaderConfig, rpc_url: &str) -> Self {
+        Self {
+            config,
+            rpc: RpcClient::new_with_commitment(rpc_url.to_string(), CommitmentConfig::processed()),
+            schedule: Mutex::new(None),
+            validators_fetched: Mutex::new(None),
+        }
+    }
+
+    pub fn from_env(rpc_url: &str) -> Option<Self> {
+        LeaderConfig::from_env().map(|config| Self::new(config, rpc_url))
+    }
+
+    // Loads the epoch's leader schedule once the cached one has ended, and the Jito validator set
+    // with it or once VALIDATORS_REFRESH has passed. Failures keep what was loaded before
+    pub async fn refresh(&self, http: &reqwest::Client) {
+        let slot = match self.rpc.get_slot() {
+            Ok(slot) => slot,
+            Err(e) => {
+                println!("Failed to read the slot for the leader schedule: {}", e);
+                return;
+            }
+        };
+        let current = self.schedule.lock().unwrap().as_ref().is_some_and(|schedule| schedule.contains(slot));
+        if !current {
+            match self.load_schedule() {
+                Ok((first_slot, leaders)) => {
+                    let jito = self.schedule.lock().unwrap().take().map(|schedule| schedule.jito).unwrap_or_default();
+                    *self.schedule.lock().unwrap() = Some(LeaderSchedule { first_slot, leaders, jito });
+                    *self.validators_fetched.lock().unwrap() = None;
+                }
+                Err(e) => {
+                    println!("Failed to load the leader schedule: {}", e);
+                    return;
+                }
+            }
+        }
+        {
+            let mut fetched = self.validators_fetched.lock().unwrap();
+            if fetched.is_some_and(|fetched| fetched.elapsed() < VALIDATORS_REFRESH) {
+                return;
+            }
+            *fetched = Some(Instant::now());
+        }
+        match self.load_jito_leaders(http).await {
+            Ok(jito) => {
+                if let Some(schedule) = self.schedule.lock().unwrap().as_mut() {
+                    println!("{} validators run the Jito client", jito.len());
+                    schedule.jito = jito;
+                }
+            }
+            Err(e) => println!("Failed to load the Jito validator set: {}", e),
+        }
+    }
+
+    // How to send now; without a schedule yet, straight to the block engine
+    pub fn plan(&self) -> ClientResult<Submission> {
+        let slot = self.rpc.get_slot()?;
+        Ok(match self.schedule.lock().unwrap().as_ref() {
+            Some(schedule) => schedule.plan(slot, &self.config),
+            None => Submission::Jito,
+        })
+    }
+
+    // Holds a send for the next Jito leader as the plan says, see `hold`
+    pub async fn await_jito_leader(&self, cancel: impl Future<Output = ()>) -> ClientResult<()> {
+        hold(self.plan()?, cancel).await
+    }
+
+    // The current epoch's first slot and the leader of each of its slots
+    fn load_schedule(&self) -> Result<(Slot, Vec<Pubkey>), Box<dyn std::error::Error>> {
+        let epoch = self.rpc.get_epoch_info()?;
+        let first_slot = epoch.absolute_slot - epoch.slot_index;
+        let schedule = self
+            .rpc
+            .get_leader_schedule(Some(epoch.absolute_slot))?
+            .ok_or("No leader schedule for the epoch")?;
+        let mut leaders = vec![Pubkey::default(); epoch.slots_in_epoch as usize];
+        for (identity, indexes) in schedule {
+            let identity = Pubkey::from_str(&identity)?;
+            for index in indexes {
+                if let Some(leader) = leaders.get_mut(index) {
+                    *leader = identity;
+                }
+            }
+        }
+        Ok((first_slot, leaders))
+    }
+
+    // Identities of the validators running the Jito client; the list names vote accounts, which
+    // the RPC's vote accounts map to identities
+    async fn load_jito_leaders(&self, http: &reqwest::Client) -> Result<HashSet<Pubkey>, Box<dyn std::error::Error>> {
+        let validators: JitoValidators = http
+            .get(&self.config.validators_url)
+            .timeout(crate::http_client::timeout_for(&self.config.validators_url))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let vote_accounts = self.rpc.get_vote_accounts()?;
+        let identities: HashMap<String, String> = vote_accounts
+            .current
+            .into_iter()
+            .chain(vote_accounts.delinquent)
+            .map(|account| (account.vote_pubkey, account.node_pubkey))
+            .collect();
+        Ok(validators
+            .validators
+            .iter()
+            .filter(|validator| validator.running_jito)
+            .filter_map(|validator| identities.get(&validator.vote_account))
+            .filter_map(|identity| Pubkey::from_str(identity).ok())
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+
+    #[tokio::test]
+    async fn leader_waits_can_be_cancelled() {
+        assert!(hold(Submission::Jito, std::future::pending()).await.is_ok());
+        assert!(hold(Submission::WaitThenJito(Duration::from_millis(1)), std::future::pending()).await.is_ok());
+        // Cancelled long before the leader comes up
+        let started = Instant::now();
+        assert!(hold(Submission::WaitThenJito(Duration::from_secs(60)), async {}).await.is_err());
+        assert!(started.elapsed() < Duration::from_secs(1));
+    }
+}
@@ -1,246 +1,5301 @@
-use tokio;
+// solana_client's ClientError is large, and every RPC call returns it
+#![allow(clippy::result_large_err)]
+
+use solana_account_decoder::UiAccountEncoding;
 use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig};
 use solana_sdk::{
+    commitment_config::CommitmentConfig,
     pubkey::Pubkey,
-    signature::{Keypair, read_keypair_file},
+    signature::{Keypair, Signature, Signer, read_keypair_file},
 };
+use solana_transaction_status::UiReturnDataEncoding;
 use std::str::FromStr;
 use std::time::Duration;
-use std::collections::HashMap;
-use pyth_sdk_solana::state::PriceAccount;
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use pyth_sdk_solana::state::SolanaPriceAccount;
 use std::env;
 use dotenv::dotenv;
 
+mod account_cache;
+mod adaptive;
+mod allocation;
+mod audit;
+mod backpressure;
+mod backtest;
+mod balances;
+mod client;
+mod close_accounts;
+mod commands;
+mod competition;
+mod compute;
+mod config_profile;
+mod control;
+mod costs;
+mod discovery;
+mod doctor;
+mod dust;
+mod evaluator;
+mod execution_policy;
+mod events;
+mod failure;
+mod fee_payer;
+mod flash_loan;
+mod fork;
+mod graph;
+mod http_client;
+mod import_history;
+mod inventory;
+mod jito;
+mod journal;
+mod jupiter;
+mod landing;
+mod latency;
+mod leaders;
+mod marginfi;
+mod market_data;
+mod network;
+mod notifier;
+mod pair_stats;
+mod pair_store;
+mod quote_check;
+mod raydium_amm;
+mod raydium_clmm;
+mod reconcile;
+mod recorder;
+mod rent;
+mod report;
+mod retry;
+mod risk;
+mod route;
+mod rpc;
+mod rpc_fixture;
+mod rpc_router;
+mod scheduler;
+mod selection;
+mod shredstream;
+mod simulation;
+mod slippage;
+mod snapshot;
+mod solend;
+mod spread_matrix;
+mod spread_stats;
+mod strategy;
+mod test_env;
+mod token_list;
+mod token_safety;
+mod venue;
+mod watchdog;
+mod whirlpool;
+mod withdraw;
+
+use account_cache::{AccountCache, CacheConfig};
+use adaptive::{Fill, ThresholdTuner, TunerConfig};
+use allocation::{Allocation, AllocationConfig};
+use audit::{AuditEvent, AuditLog};
+use backpressure::{BackpressureConfig, OpportunityQueue};
+use competition::{CompetitionConfig, CompetitionTracker};
+use compute::{ComputeConfig, ComputeUnitEstimator};
+use control::{Control, ControlConfig};
+use costs::{CostBreakdown, ExecutionReceipt};
+use discovery::DiscoveryConfig;
+use dust::{DustConfig, DustSweep};
+use evaluator::{Evaluation, EvaluatorParams, Opportunity, ProfitThreshold, Quote, VenuePrice};
+use execution_policy::{Admission, ExecutionPolicy};
+use failure::FailureClass;
+use fee_payer::FeePayer;
+use flash_loan::FlashLoanProvider;
+use fork::ForkConfig;
+use inventory::{InventoryBook, InventoryConfig, InventorySide};
+use jito::{JitoConfig, PrivateRpc, TipPolicy};
+use journal::{JournalEntry, TradeJournal, TradeStatus};
+use landing::{Backend, Landing, LandingStats};
+use latency::{LatencyProfile, Stage, StageTimer};
+use leaders::LeaderTracker;
+use network::Network;
+use notifier::Notifier;
+use pair_stats::{PairAnalytics, PairTracker};
+use pair_store::{PairStore, PairVenue, StoredPair};
+use recorder::MarketRecorder;
+use rent::AccountRent;
+use report::{PerformanceReport, SECONDS_PER_DAY};
+use risk::{RiskConfig, RiskTracker};
+use retry::{RetryBudget, RetryingRpc};
+use route::{Route, RouteLeg, RouteQuote};
+use rpc::RpcApi;
+use rpc_fixture::{RecordingRpc, ReplayRpc};
+use rpc_router::RoutedRpc;
+use quote_check::{QuoteCheckConfig, QuoteSource};
+use scheduler::{PollConfig, PollScheduler, PollTier};
+use shredstream::{ShredStreamConfig, ShredWatcher};
+use simulation::{SimulatedProfit, SimulationError};
+use slippage::{SlippageConfig, SlippageTuner};
+use spread_matrix::SpreadMatrix;
+use strategy::{MarketContext, Strategy};
+use token_list::TokenLists;
+use token_safety::{SafetyConfig, SafetyScorer};
+use watchdog::{Heartbeat, WatchdogConfig};
+
+const MAX_DISCOVERED_CYCLES: usize = 3;
+// Matches the program's MAX_BATCH_ROUTES
+const MAX_BATCH_ROUTES: usize = 4;
+const DISCOVERED_PAIRS_PATH: &str = "discovered-pairs.json";
+// Program Config layout: discriminator, admin, operator, paused, max_trade_size, then the fees
+const CONFIG_FLASH_LOAN_FEE_BPS_OFFSET: usize = 81;
+const CONFIG_PROFIT_FEE_BPS_OFFSET: usize = 117;
 
 struct ArbitrageMonitor {
-    rpc_client: RpcClient,
+    rpc_client: Arc<dyn RpcApi>,
+    // The same RPC, with the accounts each cycle reads cached by slot
+    account_cache: Arc<AccountCache>,
     wallet: Keypair,
+    // Pays the trades' fees instead of the wallet, topped up from it (FEE_PAYER_KEYPAIR)
+    fee_payer: Option<FeePayer>,
     token_pairs: Vec<TokenPair>,
-    min_profit_threshold: f64,
+    min_profit_threshold: ProfitThreshold,
     estimated_gas_cost: u64,
     slippage_tolerance: f64,
+    journal: TradeJournal,
+    // Hash-chained record of each trade's transitions, and the ID of the trade in progress
+    audit: Option<AuditLog>,
+    trade_id: std::sync::Mutex<Option<String>>,
+    notifier: Notifier,
+    recorder: Option<MarketRecorder>,
+    routes: Vec<Route>,
+    known_pools: Vec<graph::KnownPool>,
+    discovery_loan_amount: u64,
+    discovery: Option<DiscoveryConfig>,
+    // Where discovered pairs are proposed, discovered-pairs.json
+    discovered_pairs_path: String,
+    // Scam heuristics discovered pairs must pass, unless DISCOVERY_MIN_SAFETY_SCORE is 0
+    safety_scorer: Option<SafetyScorer>,
+    // Pair loan caps from the global notional cap, reallocated daily (ALLOCATION_TOTAL_LAMPORTS)
+    allocation_config: Option<AllocationConfig>,
+    allocation: Option<Allocation>,
+    pair_tracker: std::sync::Mutex<PairTracker>,
+    // Per-pair performance since the start, and the quotes evaluated per pair on the report day
+    pair_analytics: Arc<std::sync::Mutex<PairAnalytics>>,
+    day_opportunities: std::sync::Mutex<HashMap<String, u64>>,
+    threshold_tuner: Option<std::sync::Mutex<ThresholdTuner>>,
+    // Other wallets arbitraging the pairs, from the ShredStream transactions (COMPETITION_DETECTION)
+    competition: Option<std::sync::Mutex<CompetitionTracker>>,
+    slippage_tuner: std::sync::Mutex<SlippageTuner>,
+    compute_units: std::sync::Mutex<ComputeUnitEstimator>,
+    flash_loan: FlashLoanProvider,
+    expiry_slots: u64,
+    fee_collector: Pubkey,
+    // Pyth SOL/USD feed pricing gas costs
+    sol_price_account: Pubkey,
+    // Pyth USD feeds of the route start mints profits are kept in besides WSOL and the pairs'
+    // oracle-priced tokens (USD_PRICE_FEEDS)
+    usd_feeds: HashMap<Pubkey, Pubkey>,
+    // Tip paid by every execution transaction under private submission (PRIVATE_SUBMISSION)
+    private_submission: Option<JitoConfig>,
+    // Leader schedule and Jito validator set the private sends are timed to
+    leaders: Option<Arc<LeaderTracker>>,
+    // Pre-send checks run on a local mainnet fork instead of simulate_transaction when set
+    fork_simulation: Option<ForkConfig>,
+    // Second source executable quotes are checked against, QUOTE_VALIDATION
+    quote_check: Option<QuoteCheckConfig>,
+    // Execute routes that only miss the fixed transaction cost together in one batch
+    batch_enabled: bool,
+    // Re-evaluate a pair only once an account it's priced from changes
+    event_driven: bool,
+    // Source of the monitored pairs when the store file exists, and the entries last loaded from it
+    pair_store: Option<PairStore>,
+    // Changes made through the control API (CONTROL_API_ADDR)
+    control: Option<Arc<Control>>,
+    stored_pairs: HashMap<String, StoredPair>,
+    http: reqwest::Client,
+    // Stage timings of the opportunity being handled, and their distribution over all of them
+    stage_timer: std::sync::Mutex<StageTimer>,
+    latency: std::sync::Mutex<LatencyProfile>,
+    // The sent transaction of the trade in progress, and landing rates over all of them
+    submission: std::sync::Mutex<Option<Landing>>,
+    landing: std::sync::Mutex<LandingStats>,
+    // Every venue combination's spread per pair at its latest check, for the metrics and --profile
+    spread_matrix: Arc<std::sync::Mutex<SpreadMatrix>>,
+    // Priced P&L of the latest RISK_WINDOW_DAYS, for the risk metrics
+    risk: std::sync::Mutex<RiskTracker>,
+    // Print the latency table every PROFILE_INTERVAL_SECS (--profile)
+    profile: bool,
+    // Prometheus textfile the latency metrics are written to every cycle
+    metrics_path: Option<std::path::PathBuf>,
+    // Beaten after every completed scan cycle, for the watchdog
+    heartbeat: Arc<Heartbeat>,
+    // Token held for WSOL pairs, traded one leg at a time instead of round trips (INVENTORY_MODE)
+    inventory: Option<std::sync::Mutex<InventoryBook>>,
+    // Leftover token balances swept back to SOL or USDC now and then (DUST_SWEEP)
+    dust: Option<DustConfig>,
+    // Trades prepared this cycle, sent together in bundles at its end (JITO_BUNDLE_TRADES)
+    bundle_queue: std::sync::Mutex<Vec<QueuedTrade>>,
+    // Writes to the monitored pools seen in ShredStream entries (SHREDSTREAM_URL)
+    shreds: Option<Arc<ShredWatcher>>,
+    // Realized profit short of the expected profit by more than this many bps of it is alerted on,
+    // once per cycle for the trades it happened to
+    profit_alert_slippage_bps: Option<i64>,
+    profit_alerts: std::sync::Mutex<Vec<String>>,
+    // Least time between attempts on one pair, TRADE_COOLDOWN_SECS, and each pair's last attempt
+    trade_cooldown: Duration,
+    last_attempts: std::sync::Mutex<HashMap<String, std::time::Instant>>,
+    // Config profile the settings came from, journaled on every entry
+    config_profile: Option<String>,
+    // TOKEN_ALLOWLIST and TOKEN_BLOCKLIST, applied to configured and discovered pairs and routes
+    token_lists: TokenLists,
+}
+
+// A route whose profit covers its size-dependent costs but not the transaction cost on its own
+struct BatchCandidate {
+    route: Route,
+    quote: RouteQuote,
+    evaluation: Evaluation,
+}
+
+// Outcome of an execution attempt that didn't fail: sent and landed, or held for the cycle's bundles
+enum Execution {
+    Landed(Signature),
+    Queued(PreparedTrade),
+}
+
+// A verified trade's instructions, without the tip, waiting to be bundled
+struct PreparedTrade {
+    instructions: Vec<solana_sdk::instruction::Instruction>,
+    simulated_profit: i64,
+    // Accounts no other trade in its bundle may write, since it was simulated alone
+    contested: HashSet<Pubkey>,
+}
+
+// A prepared trade with its journal entry and its stage timings up to the build
+struct QueuedTrade {
+    entry: JournalEntry,
+    trade: PreparedTrade,
+    timer: StageTimer,
+}
+
+// An executable opportunity waiting for the executor, with when it was evaluated and its stage
+// timings so far
+struct PendingTrade<'a> {
+    pair: &'a TokenPair,
+    opportunity: Opportunity,
+    size: u64,
+    // Queued for the cycle's bundles by the pair's execution policy
+    bundle: bool,
+    evaluated: std::time::Instant,
+    timer: StageTimer,
+}
+
+// A signed swap straight from the wallet, the last block height it can land at, and the rent the
+// accounts it opens lock up
+struct WalletSwap {
+    transaction: solana_sdk::transaction::Transaction,
+    last_valid_block_height: u64,
+    rent: AccountRent,
 }
 
 struct TokenPair {
     token_a: Pubkey,
     token_b: Pubkey,
     loan_amount: u64, // This is the amount of SOL to borrow and also the amount to trade
+    pool_accounts: Vec<Pubkey>, // Pool state accounts captured by the market data recorder
+    quote_vaults: Vec<Pubkey>, // Base and quote vaults of the Jupiter-side and Raydium pools priced by the quote instruction
+    oracle_accounts: Option<(Pubkey, Pubkey)>, // Pyth USD feeds for token A and token B
+    strategy: Box<dyn Strategy>,
+    execution_policy: Box<dyn ExecutionPolicy>, // When the strategy's trades are sent
+    active: bool, // Cleared when the pair falls out of the volume-ranked selection
+    disabled: bool, // Set when the pair is demoted for persistent unprofitability
+    min_profit_threshold: Option<ProfitThreshold>, // Replaces the global threshold for this pair
+    dexes: Option<String>, // Jupiter dex labels the pair's Jupiter leg may route through
+    poll_interval: Option<Duration>, // Replaces the polling tier's interval for this pair
+    evaluated: AtomicBool, // Set once the pair has been evaluated, for event-driven evaluation
+    slippage_tolerance: Option<f64>, // Replaces the global slippage tolerance in the evaluator
+    slippage_bps: Option<u16>, // Replaces SLIPPAGE_BPS as the pair's starting minimum-output tolerance
+    cooldown: Option<Duration>, // Replaces the global cooldown between attempts
+    venues: Vec<PairVenue>, // Further venues whose prices are compared with the program's two
+}
+
+impl TokenPair {
+    fn key(&self) -> String {
+        format!("{}/{}", self.token_a, self.token_b)
+    }
+
+    // Accounts the pair's quote and evaluation are computed from: its pools, quote vaults and oracles
+    fn priced_from(&self) -> impl Iterator<Item = &Pubkey> {
+        let oracles = self.oracle_accounts.iter().flat_map(|(oracle_a, oracle_b)| [oracle_a, oracle_b]);
+        self.pool_accounts.iter().chain(&self.quote_vaults).chain(oracles)
+    }
+
+    fn from_stored(stored: &StoredPair) -> Result<Self, Box<dyn std::error::Error>> {
+        let address = |address: &String| Pubkey::from_str(address);
+        Ok(Self {
+            token_a: address(&stored.token_a)?,
+            token_b: address(&stored.token_b)?,
+            loan_amount: stored.loan_amount,
+            pool_accounts: stored.pool_accounts.iter().map(address).collect::<Result<_, _>>()?,
+            quote_vaults: stored.quote_vaults.iter().map(address).collect::<Result<_, _>>()?,
+            oracle_accounts: match &stored.oracle_accounts {
+                Some((oracle_a, oracle_b)) => Some((address(oracle_a)?, address(oracle_b)?)),
+                None => None,
+            },
+            strategy: match &stored.strategy {
+                Some(name) => strategy::from_name(name)?,
+                None => Box::new(strategy::PureSpreadStrategy),
+            },
+            execution_policy: match &stored.execution_policy {
+                Some(name) => execution_policy::from_name(name)?,
+                None => Box::new(execution_policy::Immediate),
+            },
+            active: true,
+            disabled: false,
+            min_profit_threshold: stored.min_profit_threshold,
+            dexes: stored.dexes.clone(),
+            poll_interval: stored.poll_interval_ms.map(Duration::from_millis),
+            evaluated: AtomicBool::new(false),
+            slippage_tolerance: stored.slippage_tolerance,
+            slippage_bps: stored.slippage_bps,
+            cooldown: stored.cooldown_secs.map(Duration::from_secs),
+            venues: stored.venues.clone(),
+        })
+    }
+}
+
+// The settings a config profile may change, read the same way at startup and on a profile switch
+
+// MIN_PROFIT_THRESHOLD, or the evaluator's default
+fn min_profit_threshold_from_env() -> Result<ProfitThreshold, String> {
+    match env::var("MIN_PROFIT_THRESHOLD").ok().filter(|v| !v.is_empty()) {
+        Some(threshold) => threshold.parse(),
+        None => Ok(EvaluatorParams::default().min_profit_threshold),
+    }
+}
+
+// SLIPPAGE_TOLERANCE in percent, or the evaluator's default
+fn slippage_tolerance_from_env() -> Result<f64, String> {
+    match env::var("SLIPPAGE_TOLERANCE").ok().filter(|v| !v.is_empty()) {
+        Some(tolerance) => tolerance.parse().map_err(|_| format!("Invalid SLIPPAGE_TOLERANCE {}", tolerance)),
+        None => Ok(EvaluatorParams::default().slippage_tolerance),
+    }
+}
+
+fn discovery_loan_amount_from_env() -> Result<u64, std::num::ParseIntError> {
+    env::var("DISCOVERY_LOAN_AMOUNT")
+        .or_else(|_| env::var("LOAN_AMOUNT"))
+        .unwrap_or_else(|_| "500000000".to_string())
+        .parse()
+}
+
+fn trade_cooldown_from_env() -> Duration {
+    Duration::from_secs(env::var("TRADE_COOLDOWN_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(0))
+}
+
+fn journal_from_env() -> TradeJournal {
+    TradeJournal::new(env::var("TRADE_JOURNAL_PATH").unwrap_or_else(|_| "trades.jsonl".to_string()))
+}
+
+fn slippage_tuner_from_env() -> SlippageTuner {
+    SlippageTuner::new(SlippageConfig::from_env(), env::var("ADAPTIVE_SLIPPAGE").map(|v| v == "true").unwrap_or(false))
 }
 
 impl ArbitrageMonitor {
     pub fn new(
         rpc_url: &str, 
-        wallet_keypair_path: &str,  // Changed parameter name for clarity
+        wallet_keypair_path: &str,
     ) -> Self {
-        let rpc_client = RpcClient::new(rpc_url.to_string());
         let wallet = read_keypair_file(wallet_keypair_path)
             .expect("Failed to load wallet keypair");
+        // RPC_REPLAY_PATH serves a recorded fixture instead of the network; RPC_RECORD_PATH records one
+        let replay_path = env::var("RPC_REPLAY_PATH").ok().filter(|v| !v.is_empty());
+        let record_path = env::var("RPC_RECORD_PATH").ok().filter(|v| !v.is_empty());
+        // Several endpoints in SOLANA_RPC_URLS are routed between by latency and cost instead. Reads
+        // are retried on transient errors; a recording holds the outcome after retries. Private
+        // submission sends through the Jito block engine only; the monitor times its sends to the
+        // Jito leaders if enabled
+        let leaders = JitoConfig::from_env()
+            .and_then(|_| LeaderTracker::from_env(rpc_url))
+            .map(Arc::new);
+        let network = || -> Arc<dyn RpcApi> {
+            let inner: Arc<dyn RpcApi> = match RoutedRpc::from_env() {
+                Some(routed) => Arc::new(routed),
+                None => Arc::new(RpcClient::new(rpc_url.to_string())),
+            };
+            let retrying: Arc<dyn RpcApi> = Arc::new(RetryingRpc::new(inner, RetryBudget::from_env()));
+            match JitoConfig::from_env() {
+                Some(config) => Arc::new(PrivateRpc::new(retrying, &config, rpc_url)),
+                None => retrying,
+            }
+        };
+        let rpc_client: Arc<dyn RpcApi> = if let Some(path) = replay_path {
+            Arc::new(ReplayRpc::load(std::path::Path::new(&path)).expect("Invalid RPC_REPLAY_PATH"))
+        } else if let Some(path) = record_path {
+            Arc::new(RecordingRpc::create(network(), std::path::Path::new(&path)).expect("Invalid RPC_RECORD_PATH"))
+        } else {
+            network()
+        };
+        let mut monitor = Self::with_rpc(rpc_client, wallet);
+        monitor.leaders = leaders;
+        monitor
+    }
+
+    // Monitor on any RpcApi, such as the mock in tests; the rest is configured from the environment
+    fn with_rpc(rpc_client: Arc<dyn RpcApi>, wallet: Keypair) -> Self {
+        let defaults = EvaluatorParams::default();
+        // Must match the program config's fee_collector; defaults to the wallet itself
+        let fee_collector = env::var("FEE_COLLECTOR")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|v| Pubkey::from_str(&v).expect("Invalid FEE_COLLECTOR"))
+            .unwrap_or_else(|| wallet.pubkey());
+        let account_cache = Arc::new(AccountCache::new(rpc_client, CacheConfig::from_env()));
 
         Self {
-            rpc_client,
+            rpc_client: account_cache.clone(),
+            account_cache,
             wallet,  // This is your Phantom wallet keypair
+            fee_payer: FeePayer::from_env(),
             token_pairs: Vec::new(),
-            min_profit_threshold: 0.5,
-            estimated_gas_cost: 5000,
-            slippage_tolerance: 0.1,
+            min_profit_threshold: min_profit_threshold_from_env().expect("Invalid MIN_PROFIT_THRESHOLD"),
+            estimated_gas_cost: defaults.estimated_gas_cost,
+            slippage_tolerance: slippage_tolerance_from_env().expect("Invalid SLIPPAGE_TOLERANCE"),
+            journal: journal_from_env(),
+            audit: Some(env::var("AUDIT_LOG_PATH").unwrap_or_else(|_| "audit.jsonl".to_string()))
+                .filter(|path| !path.is_empty())
+                .map(AuditLog::new),
+            trade_id: std::sync::Mutex::new(None),
+            notifier: Notifier::from_env(),
+            recorder: MarketRecorder::from_env(),
+            routes: Vec::new(),
+            known_pools: Vec::new(),
+            discovery_loan_amount: discovery_loan_amount_from_env().expect("Invalid DISCOVERY_LOAN_AMOUNT"),
+            discovery: None,
+            discovered_pairs_path: DISCOVERED_PAIRS_PATH.to_string(),
+            safety_scorer: None,
+            allocation_config: AllocationConfig::from_env(),
+            allocation: None,
+            pair_tracker: std::sync::Mutex::new(PairTracker::default()),
+            pair_analytics: Arc::new(std::sync::Mutex::new(PairAnalytics::default())),
+            day_opportunities: std::sync::Mutex::new(HashMap::new()),
+            threshold_tuner: None,
+            competition: None,
+            slippage_tuner: std::sync::Mutex::new(slippage_tuner_from_env()),
+            compute_units: std::sync::Mutex::new(ComputeUnitEstimator::new(ComputeConfig::from_env())),
+            flash_loan: FlashLoanProvider::from_env(),
+            expiry_slots: env::var("EXPIRY_SLOTS").ok().and_then(|v| v.parse().ok()).unwrap_or(10),
+            fee_collector,
+            sol_price_account: env::var("PYTH_SOL_USD_ACCOUNT")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| Pubkey::from_str(&v).expect("Invalid PYTH_SOL_USD_ACCOUNT"))
+                .unwrap_or_else(|| Pubkey::from_str(network::addresses().pyth_sol_usd).unwrap()),
+            usd_feeds: env::var("USD_PRICE_FEEDS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|entry| !entry.is_empty())
+                .map(|entry| {
+                    let (mint, feed) = entry.split_once(':').expect("USD_PRICE_FEEDS entries must be <mint>:<feed>");
+                    (
+                        Pubkey::from_str(mint.trim()).expect("Invalid mint in USD_PRICE_FEEDS"),
+                        Pubkey::from_str(feed.trim()).expect("Invalid feed in USD_PRICE_FEEDS"),
+                    )
+                })
+                .collect(),
+            private_submission: JitoConfig::from_env(),
+            leaders: None,
+            fork_simulation: ForkConfig::from_env(),
+            quote_check: QuoteCheckConfig::from_env().expect("Invalid QUOTE_VALIDATION"),
+            batch_enabled: env::var("BATCH_ENABLED").map(|v| v == "true").unwrap_or(false),
+            event_driven: env::var("EVENT_DRIVEN_EVALUATION").map(|v| v == "true").unwrap_or(false),
+            pair_store: None,
+            control: None,
+            stored_pairs: HashMap::new(),
+            http: http_client::client(),
+            stage_timer: std::sync::Mutex::new(StageTimer::start()),
+            latency: std::sync::Mutex::new(LatencyProfile::default()),
+            submission: std::sync::Mutex::new(None),
+            landing: std::sync::Mutex::new(LandingStats::default()),
+            spread_matrix: Arc::default(),
+            risk: std::sync::Mutex::new(RiskTracker::new(&RiskConfig::from_env())),
+            profile: false,
+            metrics_path: env::var("METRICS_PATH").ok().filter(|v| !v.is_empty()).map(std::path::PathBuf::from),
+            heartbeat: Arc::new(Heartbeat::new()),
+            inventory: InventoryConfig::from_env()
+                .map(|config| std::sync::Mutex::new(InventoryBook::load(config).expect("Invalid INVENTORY_PATH"))),
+            dust: DustConfig::from_env(),
+            bundle_queue: std::sync::Mutex::new(Vec::new()),
+            shreds: None,
+            profit_alert_slippage_bps: Some(env::var("PROFIT_ALERT_SLIPPAGE_BPS").unwrap_or_else(|_| "2000".to_string()))
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse().expect("Invalid PROFIT_ALERT_SLIPPAGE_BPS")),
+            profit_alerts: std::sync::Mutex::new(Vec::new()),
+            trade_cooldown: trade_cooldown_from_env(),
+            last_attempts: std::sync::Mutex::new(HashMap::new()),
+            config_profile: env::var("CONFIG_PROFILE").ok().filter(|v| !v.is_empty()),
+            token_lists: TokenLists::from_env().expect("Invalid token list"),
+        }
+    }
+
+    // Per-pair adaptive profit thresholds seeded from the global threshold, or the default
+    // percentage when that is absolute (ADAPTIVE_THRESHOLDS)
+    fn set_threshold_tuner(&mut self) {
+        let enabled = env::var("ADAPTIVE_THRESHOLDS").map(|v| v == "true").unwrap_or(false);
+        let base_threshold = self.min_profit_threshold.as_percent().unwrap_or(evaluator::DEFAULT_MIN_PROFIT_PERCENT);
+        self.threshold_tuner =
+            enabled.then(|| std::sync::Mutex::new(ThresholdTuner::new(TunerConfig::from_env(), base_threshold)));
+    }
+
+    // Competition detection (COMPETITION_DETECTION); a tracker already running keeps what it has seen
+    fn set_competition_detection(&mut self) {
+        if !env::var("COMPETITION_DETECTION").map(|v| v == "true").unwrap_or(false) {
+            self.competition = None;
+        } else if self.competition.is_none() {
+            self.competition = Some(std::sync::Mutex::new(CompetitionTracker::new(CompetitionConfig::from_env())));
+        }
+    }
+
+    // Switches to the named config profile while running, re-reading every setting a profile sets.
+    // Adaptive slippage and thresholds start over from the new profile's values; settings outside
+    // the profiles, such as the RPC endpoints, keep theirs
+    fn switch_profile(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        config_profile::switch(name)?;
+        self.min_profit_threshold = min_profit_threshold_from_env()?;
+        self.slippage_tolerance = slippage_tolerance_from_env()?;
+        self.discovery_loan_amount = discovery_loan_amount_from_env()?;
+        self.trade_cooldown = trade_cooldown_from_env();
+        self.journal = journal_from_env();
+        {
+            let mut slippage = self.slippage_tuner.lock().unwrap();
+            *slippage = slippage_tuner_from_env();
+            for pair in &self.token_pairs {
+                slippage.set_default(&pair.key(), pair.slippage_bps);
+            }
+        }
+        if let Some(jito) = &mut self.private_submission {
+            jito.tip = TipPolicy::from_env();
+        }
+        if let Some(discovery) = &mut self.discovery {
+            *discovery = DiscoveryConfig::from_env();
         }
+        self.set_threshold_tuner();
+        self.set_competition_detection();
+        // Stored pairs carry their own loan and policy; the pair from the environment takes the profile's
+        if self.pair_store.is_none() {
+            let loan_amount = env::var("LOAN_AMOUNT").ok().filter(|v| !v.is_empty()).map(|v| v.parse()).transpose()?;
+            let policy = env::var("EXECUTION_POLICY").ok().filter(|v| !v.is_empty());
+            for pair in &mut self.token_pairs {
+                if let Some(loan_amount) = loan_amount {
+                    pair.loan_amount = loan_amount;
+                }
+                pair.execution_policy = match &policy {
+                    Some(name) => execution_policy::from_name(name)?,
+                    None => Box::new(execution_policy::Immediate),
+                };
+            }
+        }
+        self.config_profile = Some(name.to_string());
+        Ok(())
     }
 
-    pub fn add_token_pair(&mut self, token_a: &str, token_b: &str, amount: u64, loan_amount: u64) {
+    pub fn add_token_pair(&mut self, token_a: &str, token_b: &str, loan_amount: u64, pool_accounts: &[String]) -> &mut TokenPair {
         let pair = TokenPair {
             token_a: Pubkey::from_str(token_a).expect("Invalid token A address"),
             token_b: Pubkey::from_str(token_b).expect("Invalid token B address"),
-            amount_to_trade: amount,
             loan_amount,
+            pool_accounts: pool_accounts
+                .iter()
+                .map(|address| Pubkey::from_str(address).expect("Invalid pool account address"))
+                .collect(),
+            quote_vaults: Vec::new(),
+            oracle_accounts: None,
+            strategy: Box::new(strategy::PureSpreadStrategy),
+            execution_policy: Box::new(execution_policy::Immediate),
+            active: true,
+            disabled: false,
+            min_profit_threshold: None,
+            dexes: None,
+            poll_interval: None,
+            evaluated: AtomicBool::new(false),
+            slippage_tolerance: None,
+            slippage_bps: None,
+            cooldown: None,
+            venues: Vec::new(),
         };
         self.token_pairs.push(pair);
+        self.token_pairs.last_mut().unwrap()
     }
     
-    async fn monitor_opportunities(&self) {
+    // Syncs the monitored pairs with the pair store when its file has changed. New and edited
+    // entries start fresh, removed and disabled ones stop being monitored, and untouched ones keep
+    // their strategy state, selection and demotion
+    fn reload_pairs(&mut self) {
+        let Some(store) = &mut self.pair_store else {
+            return;
+        };
+        let stored = match store.load_if_changed() {
+            Ok(Some(stored)) => stored,
+            Ok(None) => return,
+            Err(e) => {
+                println!("Failed to load pair store {}: {}", store.path.display(), e);
+                return;
+            }
+        };
+        let path = store.path.display().to_string();
+
+        let mut previous: HashMap<String, TokenPair> = self
+            .token_pairs
+            .drain(..)
+            .map(|pair| (format!("{}/{}", pair.token_a, pair.token_b), pair))
+            .collect();
+        for entry in stored.iter().filter(|entry| entry.enabled) {
+            let key = entry.key();
+            if let Err(e) = self.token_lists.check_all([entry.token_a.as_str(), entry.token_b.as_str()]) {
+                println!("Skipping stored pair {}: {}", key, e);
+                continue;
+            }
+            match previous.remove(&key) {
+                Some(pair) if self.stored_pairs.get(&key) == Some(entry) => self.token_pairs.push(pair),
+                _ => match TokenPair::from_stored(entry) {
+                    Ok(pair) => {
+                        self.slippage_tuner.lock().unwrap().set_default(&key, pair.slippage_bps);
+                        self.token_pairs.push(pair);
+                    }
+                    Err(e) => println!("Skipping stored pair {}: {}", key, e),
+                },
+            }
+        }
+        self.stored_pairs = stored.into_iter().map(|entry| (entry.key(), entry)).collect();
+        let keys: Vec<String> = self.token_pairs.iter().map(TokenPair::key).collect();
+        self.spread_matrix.lock().unwrap().retain(keys.iter().map(String::as_str));
+        println!("Monitoring {} pairs from {}", self.token_pairs.len(), path);
+    }
+
+    // Refreshes the cache with the accounts this cycle reads, in one batch: the Pyth feeds pricing
+    // gas and the active pairs, the pool states route legs are built from, and any fee payer. Under
+    // event-driven evaluation the pairs' pools and quote vaults are cached too, to see them change
+    fn prefetch_accounts(&self) {
+        let mut accounts = vec![self.sol_price_account];
+        accounts.extend(self.fee_payer.as_ref().map(FeePayer::pubkey));
+        for pair in self.token_pairs.iter().filter(|pair| pair.active && !pair.disabled) {
+            if let Some((oracle_a, oracle_b)) = pair.oracle_accounts {
+                accounts.extend([oracle_a, oracle_b]);
+            }
+            if self.event_driven {
+                accounts.extend(pair.priced_from());
+            }
+        }
+        for leg in self.routes.iter().flat_map(|route| &route.legs) {
+            if matches!(leg.venue, route::Venue::RaydiumClmm | route::Venue::Whirlpool) {
+                accounts.extend(leg.pool.as_deref().and_then(|pool| Pubkey::from_str(pool).ok()));
+            }
+        }
+        accounts.sort();
+        accounts.dedup();
+        if let Some(shreds) = &self.shreds {
+            shreds.watch(&self.pool_accounts());
+        }
+        // Entries the batch misses are refetched one by one once their TTL runs out
+        if let Err(e) = self.account_cache.prefetch(&accounts) {
+            println!("Failed to prefetch {} accounts: {}", accounts.len(), e);
+        }
+        self.account_cache.subscribe(&accounts);
+    }
+
+    // Accounts a swap through one of the monitored pools writes: the pairs' pool states and quote
+    // vaults, and the pools of the routes' CLMM and Whirlpool legs
+    fn pool_accounts(&self) -> Vec<Pubkey> {
+        let mut accounts: Vec<Pubkey> = self
+            .token_pairs
+            .iter()
+            .filter(|pair| pair.active && !pair.disabled)
+            .flat_map(|pair| pair.pool_accounts.iter().chain(&pair.quote_vaults).copied())
+            .collect();
+        for leg in self.routes.iter().flat_map(|route| &route.legs) {
+            if matches!(leg.venue, route::Venue::RaydiumClmm | route::Venue::Whirlpool) {
+                accounts.extend(leg.pool.as_deref().and_then(|pool| Pubkey::from_str(pool).ok()));
+            }
+        }
+        accounts
+    }
+
+    // Pools written in the shreds since the last cycle, with their cache entries dropped so the
+    // cycle reads them anew
+    fn take_shred_updates(&self) -> HashSet<Pubkey> {
+        let Some(shreds) = &self.shreds else {
+            return HashSet::new();
+        };
+        let touched: HashSet<Pubkey> = shreds.take_touched().into_keys().collect();
+        for pubkey in &touched {
+            self.account_cache.invalidate(pubkey);
+        }
+        self.observe_competition(&shreds.take_transactions());
+        touched
+    }
+
+    // Counts the transactions of other wallets that wrote both of a pair's pools, priced by the
+    // first and second half of its quote vaults, as arbitrages of the pair
+    fn observe_competition(&self, transactions: &[shredstream::PoolTransaction]) {
+        let Some(competition) = &self.competition else {
+            return;
+        };
+        let mut competition = competition.lock().unwrap();
+        for transaction in transactions.iter().filter(|transaction| transaction.signer != self.wallet.pubkey()) {
+            for pair in self.token_pairs.iter().filter(|pair| pair.quote_vaults.len() == 4) {
+                let (first, second) = pair.quote_vaults.split_at(2);
+                let writes = |pool: &[Pubkey]| pool.iter().any(|vault| transaction.written.contains(vault));
+                if writes(first) && writes(second) {
+                    competition.record_arbitrage(&format!("{}/{}", pair.token_a, pair.token_b), transaction.slot);
+                }
+            }
+        }
+    }
+
+    // Notes a check of the pair at `slot` and sets its priority fee for the competition seen
+    fn record_check(&self, pair_key: &str, slot: u64) {
+        let Some(competition) = &self.competition else {
+            return;
+        };
+        let mut competition = competition.lock().unwrap();
+        competition.record_check(pair_key, slot);
+        self.compute_units
+            .lock()
+            .unwrap()
+            .set_price_multiplier(pair_key, competition.fee_multiplier(pair_key));
+    }
+
+    // Counts an evaluated quote of the pair or route towards its analytics
+    fn count_opportunity(&self, pair_key: &str) {
+        self.pair_analytics.lock().unwrap().observe_opportunity(pair_key);
+        *self.day_opportunities.lock().unwrap().entry(pair_key.to_string()).or_insert(0) += 1;
+    }
+
+    // Waits up to `wait` for the next pair or cycle to fall due, cut short by a pool write seen in
+    // the shreds or a change made through the control API
+    async fn pause(&self, wait: Duration) {
+        let shreds = async {
+            match &self.shreds {
+                Some(shreds) => shreds.woken().await,
+                None => std::future::pending().await,
+            }
+        };
+        let control = async {
+            match &self.control {
+                Some(control) => control.woken().await,
+                None => std::future::pending().await,
+            }
+        };
+        tokio::select! {
+            _ = tokio::time::sleep(wait) => {}
+            _ = shreds => {}
+            _ = control => {}
+        }
+    }
+
+    // Applies what the control API changed: pair store edits are reloaded at once, even within the
+    // file's modification time resolution, and a requested profile is switched to. True when the
+    // profile changed
+    async fn take_control_changes(&mut self) -> bool {
+        let Some(control) = self.control.clone() else {
+            return false;
+        };
+        if control.take_pairs_changed() {
+            if let Some(store) = &mut self.pair_store {
+                store.invalidate();
+            }
+        }
+        let Some(profile) = control.take_requested_profile() else {
+            return false;
+        };
+        match self.switch_profile(&profile) {
+            Ok(()) => {
+                control.set_active_profile(self.config_profile.clone());
+                self.notifier.notify("Config profile switched", &format!("Now trading with the {} profile", profile)).await;
+                true
+            }
+            Err(e) => {
+                self.notifier.notify("Config profile switch failed", &format!("{}: {}", profile, e)).await;
+                false
+            }
+        }
+    }
+
+    // Starts timing the stages of a new opportunity
+    fn start_opportunity(&self) {
+        *self.stage_timer.lock().unwrap() = StageTimer::start();
+    }
+
+    fn mark_stage(&self, stage: Stage) {
+        self.stage_timer.lock().unwrap().mark(stage);
+    }
+
+    // Adds the opportunity's stage timings to the latency profile
+    fn finish_opportunity(&self) {
+        self.latency.lock().unwrap().observe(&self.stage_timer.lock().unwrap());
+    }
+
+    // Gives the trade its ID as the decision to make it is taken, and audits the decision. Trades
+    // without an opportunity slot get the current one, which landing delays count from
+    fn begin_trade(&self, entry: &mut JournalEntry, reason: &str) {
+        entry.trade_id = audit::trade_id();
+        if entry.slot == 0 {
+            entry.slot = self.rpc_client.get_slot().unwrap_or(0);
+        }
+        *self.trade_id.lock().unwrap() = Some(entry.trade_id.clone());
+        self.audit(AuditEvent::Decided {
+            key: entry.pair_key(),
+            loan_amount: entry.loan_amount,
+            expected_profit_lamports: entry.expected_profit_lamports,
+            reason: reason.to_string(),
+        });
+    }
+
+    // Appends `event` to the audit log under the trade in progress, if any
+    fn audit(&self, event: AuditEvent) {
+        if let Some(trade_id) = self.trade_id.lock().unwrap().clone() {
+            self.audit_trade(&trade_id, event);
+        }
+    }
+
+    fn audit_trade(&self, trade_id: &str, event: AuditEvent) {
+        if let Some(audit) = &self.audit {
+            if let Err(e) = audit.append(trade_id, event) {
+                println!("Failed to write audit log: {}", e);
+            }
+        }
+    }
+
+    // Writes the latency metrics file, and prints the latency table and spread matrix once
+    // PROFILE_INTERVAL_SECS has passed since `last_profile` under --profile
+    fn report_latency(&self, last_profile: &mut std::time::Instant) {
+        let latency = self.latency.lock().unwrap();
+        let landing = self.landing.lock().unwrap();
+        if let Some(path) = &self.metrics_path {
+            let mut metrics = latency.metrics() + &landing.metrics() + &self.pair_analytics.lock().unwrap().metrics();
+            metrics += &self.risk.lock().unwrap().metrics_at(journal::unix_timestamp()).metrics();
+            metrics += &self.spread_matrix.lock().unwrap().metrics();
+            if let Some(competition) = &self.competition {
+                metrics += &competition.lock().unwrap().metrics();
+            }
+            if let Err(e) = std::fs::write(path, metrics) {
+                println!("Failed to write metrics to {}: {}", path.display(), e);
+            }
+        }
+        let interval = env::var("PROFILE_INTERVAL_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(60);
+        if self.profile && last_profile.elapsed() >= Duration::from_secs(interval) {
+            println!("Stage latency:\n{}", latency.summary());
+            if !landing.is_empty() {
+                print!("Landing by backend:\n{}", landing.summary());
+            }
+            let spread_matrix = self.spread_matrix.lock().unwrap();
+            if !spread_matrix.is_empty() {
+                println!("Spread matrix:\n{}", spread_matrix.summary());
+            }
+            *last_profile = std::time::Instant::now();
+        }
+    }
+
+    async fn monitor_opportunities(&mut self) {
+        // Landing chances and the risk window carry over from the trades journaled before this run
+        match self.journal.load() {
+            Ok(entries) => {
+                *self.landing.lock().unwrap() = LandingStats::from_entries(&entries);
+                *self.risk.lock().unwrap() = RiskTracker::from_entries(&RiskConfig::from_env(), &entries);
+            }
+            Err(e) => println!("Failed to load trade journal for landing rates: {}", e),
+        }
+        self.reload_pairs();
+        self.select_active_pairs().await;
+        self.allocate_capital().await;
+        let mut report_day = journal::unix_timestamp() / SECONDS_PER_DAY;
+        let mut last_discovery: Option<std::time::Instant> = None;
+        let mut last_rebalance = std::time::Instant::now();
+        let mut last_sweep = std::time::Instant::now();
+        let review_window = env::var("PAIR_REVIEW_WINDOW_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(SECONDS_PER_DAY);
+        let mut review_window_start = journal::unix_timestamp();
+        let mut last_profile = std::time::Instant::now();
+        let mut polls = PollScheduler::new(PollConfig::from_env());
+        let mut backpressure = BackpressureConfig::from_env();
+        // Pair selection above isn't a cycle, but may have taken a while
+        self.heartbeat.beat();
         loop {
-            for pair in &self.token_pairs {
-                if let Ok(profitable) = self.check_arbitrage_opportunity(pair).await {
-                    if profitable {
-                        match self.execute_arbitrage(pair).await {
-                            Ok(_) => println!("Successfully executed arbitrage for {:?}-{:?}", 
-                                            pair.token_a, pair.token_b),
-                            Err(e) => println!("Failed to execute arbitrage: {}", e),
-                        }
+            if self.take_control_changes().await {
+                backpressure = BackpressureConfig::from_env();
+            }
+            self.reload_pairs();
+            let touched = self.take_shred_updates();
+            let now = std::time::Instant::now();
+            let cycle_due = polls.due(scheduler::CYCLE_KEY, now);
+            if cycle_due {
+                self.prefetch_accounts();
+            }
+            let changed = self.changed_accounts(touched);
+            // Pairs whose pools just moved are due at once, and go first
+            let moved = |pair: &TokenPair| pair.priced_from().any(|account| changed.contains(account));
+            let mut pairs: Vec<&TokenPair> = self
+                .token_pairs
+                .iter()
+                .filter(|pair| pair.active && !pair.disabled)
+                .filter(|pair| moved(pair) || polls.due(&pair.key(), now))
+                .collect();
+            pairs.sort_by_key(|pair| !moved(pair));
+            let mut executable = OpportunityQueue::new(backpressure.clone());
+            for pair in pairs {
+                let interval = self.poll_interval(pair, polls.config());
+                if !moved(pair) && !self.needs_evaluation(pair) {
+                    polls.schedule(&pair.key(), interval, now);
+                    continue;
+                }
+                if let Some(pending) = self.evaluate_pair(pair).await {
+                    let expected_value = pending.opportunity.evaluation.expected_value();
+                    let evaluated = pending.evaluated;
+                    if let Some(shed) = executable.push(pending, expected_value, evaluated) {
+                        self.shed(shed, "queue full");
+                    }
+                }
+                polls.schedule(&pair.key(), interval, std::time::Instant::now());
+            }
+            // Highest expected value first, without working through opportunities gone stale
+            loop {
+                for stale in executable.shed_stale(std::time::Instant::now()) {
+                    self.shed(stale, "stale");
+                }
+                let Some(pending) = executable.pop() else {
+                    break;
+                };
+                let pair = pending.pair;
+                // An attempt that expired unconfirmed never reached the chain, so the pair is checked
+                // again at once for a fresh attempt on a new quote and blockhash
+                if self.execute_pending(pending).await {
+                    println!("Attempt on {}/{} expired unconfirmed, re-checking", pair.token_a, pair.token_b);
+                    self.handle_pair(pair).await;
+                }
+            }
+            let keys: Vec<String> = self.token_pairs.iter().map(TokenPair::key).collect();
+            polls.retain(|key| key == scheduler::CYCLE_KEY || keys.iter().any(|pair| pair == key));
+
+            // Only the pairs poll faster than the cycle; the rest waits for it
+            if !cycle_due {
+                self.send_bundles().await;
+                self.heartbeat.beat();
+                self.pause(self.until_next_poll(&polls)).await;
+                continue;
+            }
+            let interval = polls.config().interval;
+            polls.schedule(scheduler::CYCLE_KEY, interval, now);
+
+            // Routes that only miss the fixed transaction cost, for batching
+            let mut batch_candidates = Vec::new();
+            for route in &self.routes {
+                batch_candidates.extend(self.handle_route(route).await);
+            }
+
+            // Routes found automatically from the known pools' current rates
+            if !self.known_pools.is_empty() {
+                let graph = graph::build_graph(&self.http, &self.known_pools).await;
+                for cycle in graph.find_cycles(MAX_DISCOVERED_CYCLES) {
+                    if let Some(route) = graph.cycle_to_route(&cycle, costs::WSOL_MINT, self.discovery_loan_amount) {
+                        println!("Discovered cycle {} with rate {:.6}", route.name, graph.cycle_rate(&cycle));
+                        batch_candidates.extend(self.handle_route(&route).await);
+                    }
+                }
+            }
+
+            if self.batch_enabled {
+                self.execute_batches(batch_candidates).await;
+            }
+            self.send_bundles().await;
+            self.top_up_fee_payer().await;
+            self.send_profit_alerts().await;
+            self.report_latency(&mut last_profile);
+            if let Some(jito) = &self.private_submission {
+                jito.refresh_tip_floor(&self.http).await;
+            }
+            if let Some(leaders) = &self.leaders {
+                leaders.refresh(&self.http).await;
+            }
+
+            if let Some(config) = self.discovery.clone() {
+                let due = last_discovery.is_none_or(|last| last.elapsed() >= Duration::from_secs(config.interval_secs));
+                if due {
+                    last_discovery = Some(std::time::Instant::now());
+                    self.run_pair_discovery(&config).await;
+                }
+            }
+
+            let rebalance_secs = self.inventory.as_ref().map(|book| book.lock().unwrap().config().rebalance_secs);
+            if rebalance_secs.is_some_and(|secs| last_rebalance.elapsed() >= Duration::from_secs(secs)) {
+                last_rebalance = std::time::Instant::now();
+                self.rebalance_inventory().await;
+            }
+            if self.dust.as_ref().is_some_and(|config| last_sweep.elapsed() >= Duration::from_secs(config.interval_secs)) {
+                last_sweep = std::time::Instant::now();
+                self.sweep_dust().await;
+            }
+
+            if journal::unix_timestamp() >= review_window_start + review_window {
+                self.demote_unprofitable_pairs(review_window_start).await;
+                review_window_start = journal::unix_timestamp();
+            }
+
+            // Once the UTC day rolls over, summarise the day that just ended
+            let today = journal::unix_timestamp() / SECONDS_PER_DAY;
+            if today != report_day {
+                if let Some(recorder) = &self.recorder {
+                    if let Err(e) = recorder.flush() {
+                        println!("Failed to flush market data: {}", e);
                     }
                 }
+                let opportunities = std::mem::take(&mut *self.day_opportunities.lock().unwrap());
+                send_daily_report(&self.journal, &self.notifier, report_day, &opportunities).await;
+                report_day = today;
+                self.select_active_pairs().await;
+                self.allocate_capital().await;
             }
-            tokio::time::sleep(Duration::from_secs(1)).await;
+
+            self.heartbeat.beat();
+            self.pause(self.until_next_poll(&polls)).await;
         }
     }
 
-    async fn check_arbitrage_opportunity(&self, pair: &TokenPair) -> Result<bool, Box<dyn std::error::Error>> {
-        let program_id = Pubkey::from_str("Your_Program_ID")?;
-        
-        // Create instruction to check prices
-        let instruction = solana_sdk::instruction::Instruction {
-            program_id,
-            accounts: vec![
-                solana_sdk::instruction::AccountMeta::new_readonly(pair.token_a, false),
-                solana_sdk::instruction::AccountMeta::new_readonly(pair.token_b, false),
-                // Add Jupiter program account
-                solana_sdk::instruction::AccountMeta::new_readonly(
-                    Pubkey::from_str("JUP4Fb2cqiRUcaTHdrPC8h2gNsA2ETXiPDD33WcGuJB")?, 
-                    false
-                ),
-                // Add Raydium program account
-                solana_sdk::instruction::AccountMeta::new_readonly(
-                    Pubkey::from_str("675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8")?,
-                    false
-                ),
-            ],
-            data: vec![
-                0, // Instruction discriminator for price check
-                pair.amount_to_trade.to_le_bytes().to_vec(),
-            ].concat(),
-        };
-
-        // Create transaction
-        let recent_blockhash = self.rpc_client.get_latest_blockhash()?;
-        let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
-            &[instruction],
-            Some(&self.wallet.pubkey()),
-            &[&self.wallet],
-            recent_blockhash,
-        );
+    // The accounts that changed since the last poll: pools written in the shreds, and under
+    // event-driven evaluation, cached accounts whose batched or subscribed state moved
+    fn changed_accounts(&self, mut touched: HashSet<Pubkey>) -> HashSet<Pubkey> {
+        if self.event_driven {
+            touched.extend(self.account_cache.take_changed());
+        }
+        touched
+    }
 
-        // Simulate transaction to get prices
-        let result = self.rpc_client.simulate_transaction(&transaction)?;
-        
-        // Parse return data to get prices
-        if let Some(return_data) = result.value.return_data {
-            let data = base64::decode(return_data.data)?;
-            
-            // First 8 bytes: Jupiter price
-            let jupiter_price = u64::from_le_bytes(data[0..8].try_into()?);
-            
-            // Next 8 bytes: Raydium price
-            let raydium_price = u64::from_le_bytes(data[8..16].try_into()?);
-            
-            // Calculate potential profit (assuming prices are in the same decimal precision)
-            let price_diff = if jupiter_price > raydium_price {
-                jupiter_price - raydium_price
-            } else {
-                raydium_price - jupiter_price
-            };
-            
-            let potential_profit = (price_diff as f64 * pair.amount_to_trade as f64) / 1e9; // Convert to SOL
-            
-            // Calculate minimum required profit including costs
-            let gas_cost_in_usd = self.get_gas_cost_in_usd().await?;
-            let required_profit = (pair.amount_to_trade as f64 * self.min_profit_threshold / 100.0) 
-                + gas_cost_in_usd 
-                + (pair.amount_to_trade as f64 * self.slippage_tolerance / 100.0);
-
-            Ok(potential_profit > required_profit)
+    // Whether a due pair none of whose accounts changed is evaluated anyway: always, unless
+    // evaluation is event-driven and the pair has been evaluated on its accounts' current state.
+    // Pairs with no accounts to watch are always evaluated
+    fn needs_evaluation(&self, pair: &TokenPair) -> bool {
+        !self.event_driven || !pair.evaluated.load(Ordering::Relaxed) || pair.priced_from().next().is_none()
+    }
+
+    // The pair's polling tier: long-tail once it has been checked LONG_TAIL_AFTER_EVALUATIONS times
+    // this review window without an executable quote, hot while the shred stream watches its pools,
+    // regular otherwise
+    fn poll_tier(&self, pair: &TokenPair, config: &PollConfig) -> PollTier {
+        let stats = self.pair_tracker.lock().unwrap().stats(&pair.key()).cloned().unwrap_or_default();
+        if stats.executable == 0 && stats.evaluations >= config.long_tail_after_evaluations {
+            PollTier::LongTail
+        } else if self.shreds.is_some() && !(pair.pool_accounts.is_empty() && pair.quote_vaults.is_empty()) {
+            PollTier::Hot
         } else {
-            Err("No return data from price check simulation".into())
+            PollTier::Regular
         }
     }
 
-    async fn get_gas_cost_in_usd(&self) -> Result<f64, Box<dyn std::error::Error>> {
-        let pyth_sol_usd_account = Pubkey::from_str("H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG")?;
-        let account_data = self.rpc_client.get_account_data(&pyth_sol_usd_account)?;
-        
-        let price_account: PriceAccount = pyth_sdk_solana::state::load_price_account(&account_data)?;
-        let price_info = price_account.to_price_feed().get_price_unchecked();
-        
-        let sol_price = price_info.price as f64 * 10f64.powi(price_info.expo);
-        let gas_cost_in_usd = (self.estimated_gas_cost as f64 * sol_price) / 1_000_000_000.0;
-        
-        Ok(gas_cost_in_usd)
+    fn poll_interval(&self, pair: &TokenPair, config: &PollConfig) -> Duration {
+        pair.poll_interval.unwrap_or_else(|| config.interval(self.poll_tier(pair, config)))
     }
 
-    // // Helper function to parse Pyth price data
-    // fn parse_pyth_price(data: &[u8]) -> Result<f64, Box<dyn std::error::Error>> {
-    //     // Price is stored at offset 128 in the account data
-    //     // This is a simplified version - production code should use proper Pyth SDK
-    //     let price_bytes = &data[128..136];
-    //     let price = i64::from_le_bytes(price_bytes.try_into()?);
-    //     let expo_bytes = &data[136..140];
-    //     let expo = i32::from_le_bytes(expo_bytes.try_into()?);
-        
-    //     // Calculate actual price with exponent
-    //     let actual_price = (price as f64) * 10f64.powi(expo);
-        
-    //     Ok(actual_price)
-    // }
+    // Until the next active pair or the cycle is due
+    fn until_next_poll(&self, polls: &PollScheduler) -> Duration {
+        let keys: Vec<String> = self.token_pairs.iter().filter(|pair| pair.active && !pair.disabled).map(TokenPair::key).collect();
+        let now = std::time::Instant::now();
+        polls.until_next(keys.iter().map(String::as_str).chain([scheduler::CYCLE_KEY]), now)
+    }
 
-    async fn execute_arbitrage(&self, pair: &TokenPair) -> Result<(), Box<dyn std::error::Error>> {
-        let program_id = Pubkey::from_str("Your_Program_ID")?;
-        
-        // Use `loan_amount` directly for swaps
-        let sol_borrow_amount = pair.loan_amount;
-
-        // First swap SOL → Token A
-        let instruction = solana_sdk::instruction::Instruction {
-            program_id,
-            accounts: vec![
-                solana_sdk::instruction::AccountMeta::new(self.wallet.pubkey(), true),  // Signer
-                solana_sdk::instruction::AccountMeta::new(pair.token_a, false),         // Token A account
-                solana_sdk::instruction::AccountMeta::new(pair.token_b, false),         // Token B account
-                // Add other required accounts based on your program's needs
-            ],
-            data: vec![
-                0,  // Instruction discriminator for arbitrage execution
-                sol_borrow_amount.to_le_bytes().to_vec(), // Loan amount used as trade amount
-            ].concat(),
-        };
-
-        // Remaining logic for creating and sending the transaction...
-        let recent_blockhash = self.rpc_client.get_latest_blockhash()?;
-        let transaction = solana_sdk::transaction::Transaction::new_signed_with_payer(
-            &[instruction],
-            Some(&self.wallet.pubkey()),
-            &[&self.wallet],
-            recent_blockhash,
+    // Checks a pair for an opportunity and attempts it at once. Returns whether the attempt was
+    // sent but expired before it landed
+    async fn handle_pair(&self, pair: &TokenPair) -> bool {
+        match self.evaluate_pair(pair).await {
+            Some(pending) => self.execute_pending(pending).await,
+            None => false,
+        }
+    }
+
+    // Checks a pair for an opportunity, returning the trade to attempt when the strategy and the
+    // pair's execution policy want one. Its stage timings go with it
+    async fn evaluate_pair<'a>(&self, pair: &'a TokenPair) -> Option<PendingTrade<'a>> {
+        self.start_opportunity();
+        let opportunity = match self.check_arbitrage_opportunity(pair).await {
+            Ok(opportunity) => opportunity,
+            Err(e) => {
+                println!("Failed to check {}/{}: {}", pair.token_a, pair.token_b, e);
+                self.finish_opportunity();
+                return None;
+            }
+        };
+        // A failed check leaves the pair to be evaluated again on its next poll
+        pair.evaluated.store(true, Ordering::Relaxed);
+        let pair_key = format!("{}/{}", pair.token_a, pair.token_b);
+        self.pair_tracker.lock().unwrap().observe(&pair_key, opportunity.size.is_some());
+        self.count_opportunity(&pair_key);
+        self.record_check(&pair_key, opportunity.slot);
+        self.spread_matrix
+            .lock()
+            .unwrap()
+            .record(&pair_key, opportunity.slot, &opportunity.quote, &opportunity.evaluation);
+        let admission = pair.execution_policy.admit(&pair_key, opportunity.size.is_some());
+        if opportunity.size.is_some() && admission == Admission::Wait {
+            println!("Holding {} for confirmation ({} policy)", pair_key, pair.execution_policy.name());
+        }
+        if opportunity.size.is_some() && admission != Admission::Wait && self.cooling_down(pair) {
+            println!("Skipping {} during its cooldown", pair_key);
+            self.finish_opportunity();
+            return None;
+        }
+        if opportunity.size.is_some() && admission != Admission::Wait {
+            if let Err(e) = self.validate_quote(&pair_key, &opportunity).await {
+                println!("Aborting {} opportunity {}: {}", pair_key, opportunity.id, e);
+                self.finish_opportunity();
+                return None;
+            }
+        }
+        match (opportunity.size, admission) {
+            (Some(size), Admission::Send | Admission::Queue) => Some(PendingTrade {
+                pair,
+                opportunity,
+                size,
+                bundle: admission == Admission::Queue,
+                evaluated: std::time::Instant::now(),
+                timer: std::mem::replace(&mut *self.stage_timer.lock().unwrap(), StageTimer::start()),
+            }),
+            _ => {
+                self.finish_opportunity();
+                None
+            }
+        }
+    }
+
+    // Attempts a trade from evaluate_pair. Returns whether the attempt was sent but expired before
+    // it landed
+    async fn execute_pending(&self, pending: PendingTrade<'_>) -> bool {
+        let PendingTrade {
+            pair,
+            opportunity,
+            size,
+            bundle,
+            timer,
+            ..
+        } = pending;
+        *self.stage_timer.lock().unwrap() = timer;
+        self.last_attempts.lock().unwrap().insert(pair.key(), std::time::Instant::now());
+        let mut entry = JournalEntry::new(
+            pair.token_a.to_string(),
+            pair.token_b.to_string(),
+            size,
+            TradeStatus::Executed,
         );
+        entry.opportunity_id = opportunity.id.clone();
+        entry.slot = opportunity.slot;
+        entry.quote = Some(opportunity.quote.clone());
+        entry.sol_price = opportunity.sol_price;
+        entry.expected_profit_lamports = opportunity.evaluation.potential_profit as i64;
+        self.begin_trade(&mut entry, &opportunity.reason);
 
-        let result = self.rpc_client.send_and_confirm_transaction(&transaction)?;
-        println!("Arbitrage transaction executed: {}", result);
-        
-        Ok(())
+        let result = self
+            .execute_arbitrage(pair, size, &opportunity.quote, opportunity.evaluation.required_profit, bundle, &mut entry)
+            .await;
+        if let Ok(Execution::Landed(_)) = &result {
+            println!("Successfully executed arbitrage for {:?}-{:?} ({}: {})", 
+                    pair.token_a, pair.token_b, pair.strategy.name(), opportunity.reason);
+        }
+        let expired = matches!(&result, Err(e) if rpc::expired_unconfirmed(&e.to_string()));
+        if expired {
+            // Nothing reached the chain, so the retry isn't held back by the cooldown
+            self.last_attempts.lock().unwrap().remove(&pair.key());
+        }
+        self.record_or_queue(entry, result);
+        self.finish_opportunity();
+        expired
     }
-}
 
-#[tokio::main]
-async fn main() {
-    // Load environment variables from .env file
-    dotenv().ok();
-    
-    let wallet_keypair_path = "wallet-keypair.json";
-    
-    let program_id = env::var("SOLANA_PROGRAM_ID")
-        .expect("Missing SOLANA_PROGRAM_ID environment variable");
-    
-    let rpc_url = env::var("SOLANA_RPC_URL")
-        .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+    // Checks an executable quote against the QUOTE_VALIDATION source, if one is set
+    async fn validate_quote(&self, pair_key: &str, opportunity: &Opportunity) -> Result<(), String> {
+        let Some(config) = &self.quote_check else {
+            return Ok(());
+        };
+        let fee_bps = [costs::JUPITER_FEE_BPS as u16, costs::RAYDIUM_FEE_BPS as u16];
+        match config.source {
+            QuoteSource::PoolMath => quote_check::check_pool_math(
+                &opportunity.quote,
+                &opportunity.vaults,
+                opportunity.quoted_amount,
+                fee_bps,
+                config.tolerance_bps,
+            ),
+            QuoteSource::Jupiter => {
+                let [(base_mint, _), (quote_mint, _), ..] = opportunity.vaults.as_slice() else {
+                    return Err("Quote vaults could not be decoded".to_string());
+                };
+                let slippage_bps = self.slippage_tuner.lock().unwrap().slippage_bps(pair_key);
+                let best = jupiter::quote(&self.http, base_mint, quote_mint, opportunity.quoted_amount, slippage_bps, None)
+                    .await
+                    .map_err(|e| format!("Jupiter quote failed: {}", e))?;
+                quote_check::check_against_best(&opportunity.quote, best.out_amount, config.tolerance_bps)
+            }
+        }
+    }
 
-    let mut monitor = ArbitrageMonitor::new(
-        &rpc_url,
-        wallet_keypair_path,
-    );
+    // Whether the pair was attempted more recently than its cooldown, or the global one, allows
+    fn cooling_down(&self, pair: &TokenPair) -> bool {
+        let cooldown = pair.cooldown.unwrap_or(self.trade_cooldown);
+        let last_attempts = self.last_attempts.lock().unwrap();
+        last_attempts.get(&pair.key()).is_some_and(|last| last.elapsed() < cooldown)
+    }
 
-    // Add token pairs to monitor using env variables
-    monitor.add_token_pair(
-        &env::var("BONK_TOKEN_ADDRESS").expect("Missing BONK_TOKEN_ADDRESS"),
-        &env::var("GOAT_TOKEN_ADDRESS").expect("Missing GOAT_TOKEN_ADDRESS"),
-        env::var("LOAN_AMOUNT")
-            .unwrap_or_else(|_| "500000000".to_string())
-            .parse()
-            .expect("Invalid LOAN_AMOUNT"),
-    );
+    // Drops an opportunity the executor won't get to, with its stage timings
+    fn shed(&self, pending: PendingTrade<'_>, reason: &str) {
+        println!(
+            "Shedding {} opportunity {} ({}): expected value {:.0} lamports",
+            pending.pair.key(),
+            pending.opportunity.id,
+            reason,
+            pending.opportunity.evaluation.expected_value()
+        );
+        *self.stage_timer.lock().unwrap() = pending.timer;
+        self.finish_opportunity();
+    }
 
-    // Start the monitoring process
-    monitor.monitor_opportunities().await;
-} 
+    // Records the outcome of an execution attempt, or holds a prepared trade for the cycle's bundles
+    fn record_or_queue(&self, entry: JournalEntry, result: Result<Execution, Box<dyn std::error::Error>>) {
+        match result {
+            Ok(Execution::Queued(trade)) => {
+                // Its timings go on once the bundle is sent, and its audit under its own trade ID
+                let timer = std::mem::replace(&mut *self.stage_timer.lock().unwrap(), StageTimer::start());
+                *self.trade_id.lock().unwrap() = None;
+                self.bundle_queue.lock().unwrap().push(QueuedTrade { entry, trade, timer });
+            }
+            Ok(Execution::Landed(signature)) => self.record_execution(entry, Ok(signature)),
+            Err(e) => self.record_execution(entry, Err(e)),
+        }
+    }
 
-// Borrow SOL from Mango
-// SOL → BONK
-// BONK → GOAT
-// GOAT → BONK (back to BONK!)
-// BONK → SOL
\ No newline at end of file
+    // Sends the trades queued this cycle, most profitable first, in bundles of up to
+    // MAX_BUNDLE_TRANSACTIONS. Each bundle tips once, on its last transaction, for the trades'
+    // combined simulated profit, and lands whole or not at all
+    async fn send_bundles(&self) {
+        let Some(jito) = &self.private_submission else {
+            // Without a block engine nothing is bundled; whatever was queued goes out one by one
+            let queued = std::mem::take(&mut *self.bundle_queue.lock().unwrap());
+            for queued in queued {
+                self.send_queued(queued).await;
+            }
+            return;
+        };
+        let mut queued = std::mem::take(&mut *self.bundle_queue.lock().unwrap());
+        queued.sort_by_key(|queued| std::cmp::Reverse(queued.trade.simulated_profit));
+        let contested: Vec<&HashSet<Pubkey>> = queued.iter().map(|queued| &queued.trade.contested).collect();
+        let bundles = jito::pack_bundles(&contested);
+        let mut queued: Vec<Option<QueuedTrade>> = queued.into_iter().map(Some).collect();
+        for indexes in bundles {
+            let bundle: Vec<QueuedTrade> = indexes.iter().filter_map(|&index| queued[index].take()).collect();
+            self.send_bundle(jito, bundle).await;
+        }
+    }
+
+    // Sends a queued trade on its own through the public path, tipped like a direct trade
+    async fn send_queued(&self, queued: QueuedTrade) {
+        *self.stage_timer.lock().unwrap() = queued.timer;
+        *self.trade_id.lock().unwrap() = Some(queued.entry.trade_id.clone());
+        let instructions = self.with_tip(queued.trade.instructions, Some(queued.trade.simulated_profit));
+        let result = match self.sign_with_expiry(&instructions) {
+            Ok((transaction, last_valid_block_height)) => self.send_signed(&transaction, last_valid_block_height).await,
+            Err(e) => Err(e),
+        };
+        self.record_execution(queued.entry, result);
+        self.finish_opportunity();
+    }
+
+    async fn send_bundle(&self, jito: &JitoConfig, bundle: Vec<QueuedTrade>) {
+        let started = std::time::Instant::now();
+        let profit: i64 = bundle.iter().map(|queued| queued.trade.simulated_profit).sum();
+        let signed: Result<Vec<_>, Box<dyn std::error::Error>> = bundle
+            .iter()
+            .enumerate()
+            .map(|(index, queued)| {
+                let mut instructions = queued.trade.instructions.clone();
+                if index == bundle.len() - 1 {
+                    instructions.push(jito.tip_instruction(&self.wallet.pubkey(), Some(profit)));
+                }
+                self.sign_with_expiry(&instructions)
+            })
+            .collect();
+        let sign = started.elapsed();
+        let sent = std::time::Instant::now();
+        let result: Result<_, Box<dyn std::error::Error>> = match &signed {
+            Ok(signed) => {
+                for (queued, (transaction, _)) in bundle.iter().zip(signed) {
+                    self.audit_trade(
+                        &queued.entry.trade_id,
+                        AuditEvent::Signed {
+                            signature: transaction.signatures[0].to_string(),
+                        },
+                    );
+                }
+                let transactions: Vec<_> = signed.iter().map(|(transaction, _)| transaction.clone()).collect();
+                // The bundle lands whole, so it's given up on once its earliest blockhash expires
+                let last_valid_block_height = signed.iter().map(|(_, height)| *height).min().unwrap_or(0);
+                self.await_jito_leader().await.and_then(|_| {
+                    self.rpc_client
+                        .send_and_confirm_bundle_timed(&transactions, last_valid_block_height)
+                        .map_err(Into::into)
+                })
+            }
+            Err(e) => Err(e.to_string().into()),
+        };
+        let elapsed = sent.elapsed();
+        if result.is_ok() {
+            println!("Bundle of {} trades landed", bundle.len());
+        }
+
+        for (index, mut queued) in bundle.into_iter().enumerate() {
+            let land = result.as_ref().map_or(Duration::ZERO, |(_, land)| *land).min(elapsed);
+            queued.timer.add(Stage::Sign, sign);
+            queued.timer.add(Stage::Send, elapsed - land);
+            queued.timer.add(Stage::Land, land);
+            *self.stage_timer.lock().unwrap() = queued.timer;
+            *self.trade_id.lock().unwrap() = Some(queued.entry.trade_id.clone());
+            *self.submission.lock().unwrap() =
+                signed.as_ref().ok().map(|signed| Landing::sent(Backend::JitoBundle, &signed[index].0));
+            let outcome = match &result {
+                Ok((signatures, _)) => Ok(signatures[index]),
+                Err(e) => Err(e.to_string().into()),
+            };
+            self.record_execution(queued.entry, outcome);
+            self.finish_opportunity();
+        }
+    }
+
+    // Fills in the outcome of an execution attempt and appends it to the journal
+    fn record_execution(&self, mut entry: JournalEntry, result: Result<Signature, Box<dyn std::error::Error>>) {
+        entry.stage_latency_ms = self.stage_timer.lock().unwrap().millis();
+        self.value_in_usd(&mut entry);
+        entry.landing = self.submission.lock().unwrap().take();
+        match result {
+            Ok(signature) => {
+                entry.signature = Some(signature.to_string());
+                self.audit(AuditEvent::Confirmed {
+                    signature: signature.to_string(),
+                });
+                // Inventory trades borrow nothing and swap once, outside the program
+                let loan_amount = if entry.inventory.is_some() { 0 } else { entry.loan_amount };
+                let receipt = ExecutionReceipt::fetch(&*self.rpc_client, &signature, loan_amount, entry.profit_mint());
+                let landed_slot = match receipt {
+                    Ok(receipt) => {
+                        entry.costs = receipt.costs;
+                        entry.realized_profit_lamports = Some(match &entry.inventory {
+                            Some(trade) => trade.profit_from(receipt.realized_profit_lamports),
+                            None => receipt.realized_profit_lamports,
+                        });
+                        entry.event = receipt.event;
+                        self.verify_profit(&mut entry);
+                        Some(receipt.slot)
+                    }
+                    Err(e) => {
+                        println!("Failed to fetch execution costs: {}", e);
+                        entry.costs = CostBreakdown::estimate_protocol_fees(loan_amount);
+                        None
+                    }
+                };
+                if let Some(landing) = entry.landing.as_mut() {
+                    landing.land(landed_slot, entry.slot);
+                }
+            }
+            Err(e) => {
+                let logs = failure::logs_from_error(e.as_ref());
+                let class = failure::classify(&e.to_string(), &logs);
+                let failed_leg = failure::failed_leg(&e.to_string(), &logs);
+                match failed_leg {
+                    Some(leg) => println!("Failed to execute arbitrage ({} on leg {}): {}", class, leg, e),
+                    None => println!("Failed to execute arbitrage ({}): {}", class, e),
+                }
+                entry.status = if rpc::expired_unconfirmed(&e.to_string()) {
+                    TradeStatus::Expired
+                } else {
+                    TradeStatus::Failed
+                };
+                entry.error = Some(e.to_string());
+                entry.failure_class = Some(class);
+                entry.failed_leg = failed_leg;
+                self.audit(AuditEvent::Failed {
+                    error: e.to_string(),
+                    failure_class: class,
+                });
+            }
+        }
+        if let Some(tuner) = &self.threshold_tuner {
+            tuner.lock().unwrap().observe(
+                &entry.pair_key(),
+                Fill {
+                    landed: entry.status == TradeStatus::Executed,
+                    expected_profit: entry.expected_profit_lamports,
+                    realized_profit: entry.realized_profit_lamports.unwrap_or(entry.expected_profit_lamports),
+                },
+            );
+        }
+        {
+            let mut slippage = self.slippage_tuner.lock().unwrap();
+            match (entry.failure_class, entry.realized_profit_lamports) {
+                (Some(FailureClass::Slippage), _) => slippage.on_slippage_revert(&entry.pair_key()),
+                (None, Some(realized)) => slippage.on_fill(
+                    &entry.pair_key(),
+                    entry.loan_amount,
+                    entry.expected_profit_lamports,
+                    realized,
+                ),
+                _ => {}
+            }
+        }
+        self.landing.lock().unwrap().observe(&entry);
+        self.pair_analytics.lock().unwrap().observe(&entry);
+        self.risk.lock().unwrap().observe(&entry);
+        entry.config_profile = self.config_profile.clone();
+        match self.journal.record(&entry) {
+            Ok(()) => self.audit(AuditEvent::Journaled),
+            Err(e) => println!("Failed to write trade journal: {}", e),
+        }
+        *self.trade_id.lock().unwrap() = None;
+    }
+
+    // Prices SOL and the trade's profit token as it's journaled, so its P&L adds up in USD with
+    // trades that profit in other tokens. A token without a feed leaves the trade unpriced
+    fn value_in_usd(&self, entry: &mut JournalEntry) {
+        if entry.sol_price <= 0.0 {
+            match self.get_pyth_price_usd(&self.sol_price_account) {
+                Ok(price) => entry.sol_price = price,
+                Err(e) => println!("Failed to read the SOL price for the journal: {}", e),
+            }
+        }
+        if entry.profit_mint() == costs::WSOL_MINT {
+            entry.profit_mint_usd = Some(entry.sol_price).filter(|price| *price > 0.0);
+            entry.profit_mint_decimals = Some(9);
+            return;
+        }
+        let Ok(mint) = Pubkey::from_str(entry.profit_mint()) else {
+            return;
+        };
+        let feed = self.usd_feeds.get(&mint).copied().or_else(|| {
+            self.token_pairs.iter().find_map(|pair| match pair.oracle_accounts {
+                Some((oracle_a, _)) if pair.token_a == mint => Some(oracle_a),
+                Some((_, oracle_b)) if pair.token_b == mint => Some(oracle_b),
+                _ => None,
+            })
+        });
+        let Some(feed) = feed else {
+            return;
+        };
+        let decimals = self
+            .rpc_client
+            .get_account_data(&mint)
+            .map(|data| data.get(balances::MINT_DECIMALS_OFFSET).copied());
+        match (self.get_pyth_price_usd(&feed), decimals) {
+            (Ok(price), Ok(Some(decimals))) => {
+                entry.profit_mint_usd = Some(price);
+                entry.profit_mint_decimals = Some(decimals);
+            }
+            (Err(e), _) => println!("Failed to price {} for the journal: {}", mint, e),
+            (_, Err(e)) => println!("Failed to read the decimals of {}: {}", mint, e),
+            (_, Ok(None)) => println!("{} is not a mint", mint),
+        }
+    }
+
+    // Measures how far the trade's realized profit fell short of the expected profit, and queues an
+    // alert when that's more than PROFIT_ALERT_SLIPPAGE_BPS of it
+    fn verify_profit(&self, entry: &mut JournalEntry) {
+        let Some(realized) = entry.realized_profit_lamports else {
+            return;
+        };
+        entry.profit_slippage_bps = costs::profit_slippage_bps(entry.expected_profit_lamports, realized);
+        match (entry.profit_slippage_bps, self.profit_alert_slippage_bps) {
+            (Some(slippage), Some(tolerance)) if slippage > tolerance => {
+                self.profit_alerts.lock().unwrap().push(format!(
+                    "{} ({}): expected {} lamports, realized {} ({:.2}% short)",
+                    entry.pair_key(),
+                    entry.signature.as_deref().unwrap_or("unsigned"),
+                    entry.expected_profit_lamports,
+                    realized,
+                    slippage as f64 / 100.0
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    // Moves SOL from the wallet to the fee payer once it falls below its floor. The wallet pays for
+    // the transfer itself, since the payer may not have enough left to. Under private submission the
+    // block engine only lands it in a bundle, so it tips like a trade
+    async fn top_up_fee_payer(&self) {
+        let Some(fee_payer) = &self.fee_payer else {
+            return;
+        };
+        let balance = match self.rpc_client.get_multiple_accounts(&[fee_payer.pubkey()]) {
+            Ok(accounts) => accounts[0].as_ref().map_or(0, |account| account.lamports),
+            Err(e) => {
+                println!("Failed to read fee payer balance: {}", e);
+                return;
+            }
+        };
+        let amount = fee_payer.top_up_amount(balance);
+        if amount == 0 {
+            return;
+        }
+        let transfer = solana_sdk::system_instruction::transfer(&self.wallet.pubkey(), &fee_payer.pubkey(), amount);
+        let instructions = self.with_tip(vec![transfer], None);
+        let result = self.rpc_client.get_latest_blockhash().and_then(|recent_blockhash| {
+            self.rpc_client.send_and_confirm_transaction(&solana_sdk::transaction::Transaction::new_signed_with_payer(
+                &instructions,
+                Some(&self.wallet.pubkey()),
+                &[&self.wallet],
+                recent_blockhash,
+            ))
+        });
+        match result {
+            Ok(signature) => {
+                let message = format!(
+                    "Sent {} lamports from {} to fee payer {} at {} lamports: {}",
+                    amount,
+                    self.wallet.pubkey(),
+                    fee_payer.pubkey(),
+                    balance,
+                    signature
+                );
+                println!("{}", message);
+                self.notifier.notify("Fee payer topped up", &message).await;
+            }
+            Err(e) => println!("Failed to top up fee payer {}: {}", fee_payer.pubkey(), e),
+        }
+    }
+
+    // Sends the profit alerts queued since the last cycle as one notification
+    async fn send_profit_alerts(&self) {
+        let alerts = std::mem::take(&mut *self.profit_alerts.lock().unwrap());
+        if alerts.is_empty() {
+            return;
+        }
+        self.notifier
+            .notify(&format!("{} trades realized less profit than expected", alerts.len()), &alerts.join("\n"))
+            .await;
+    }
+
+    // Swaps the wallet's leftover balances of the traded tokens back to SOL or USDC where that's
+    // worth the transaction, journals each sweep and notifies what was swept
+    async fn sweep_dust(&self) {
+        let Some(config) = &self.dust else {
+            return;
+        };
+        let mut mints: BTreeSet<Pubkey> = self.token_pairs.iter().flat_map(|pair| [pair.token_a, pair.token_b]).collect();
+        mints.extend(
+            self.routes
+                .iter()
+                .flat_map(|route| &route.legs)
+                .flat_map(|leg| [&leg.input_mint, &leg.output_mint])
+                .filter_map(|mint| Pubkey::from_str(mint).ok()),
+        );
+        let held = self.inventory.as_ref().map(|book| book.lock().unwrap().holdings()).unwrap_or_default();
+        let balances = match dust::dust_balances(&*self.rpc_client, &self.wallet.pubkey(), &mints, &held) {
+            Ok(balances) => balances,
+            Err(e) => {
+                println!("Failed to read dust balances: {}", e);
+                return;
+            }
+        };
+        let fee = self.evaluator_params_for(dust::SWEEP_KEY).estimated_gas_cost;
+        let slippage_bps = self.slippage_tuner.lock().unwrap().slippage_bps(dust::SWEEP_KEY);
+        let mut swept = Vec::new();
+        for (mint, amount) in balances {
+            let result = async {
+                // Valued in SOL whichever mint it's swept to
+                let value = jupiter::quote(&self.http, &mint.to_string(), costs::WSOL_MINT, amount, slippage_bps, None).await?;
+                let value_out = value.out_amount;
+                if !config.worth_sweeping(value_out, fee) {
+                    return Ok(None);
+                }
+                let quote = if config.target_mint == costs::WSOL_MINT {
+                    value
+                } else {
+                    jupiter::quote(&self.http, &mint.to_string(), &config.target_mint, amount, slippage_bps, None).await?
+                };
+                let swap = self
+                    .wallet_swap_transaction(dust::SWEEP_KEY, &quote, &Pubkey::from_str(&config.target_mint)?)
+                    .await?;
+                // Opening the target's account is paid for by the sweep too
+                if !config.worth_sweeping(value_out, fee + swap.rent.net_lamports().max(0) as u64) {
+                    return Ok(None);
+                }
+                let signature = self.rpc_client.send_and_confirm_transaction(&swap.transaction)?;
+                Ok::<_, Box<dyn std::error::Error>>(Some((quote.out_amount, signature)))
+            }
+            .await;
+            match result {
+                Ok(Some((quoted_out, signature))) => {
+                    self.record_sweep(&mint, &config.target_mint, amount, quoted_out, &signature);
+                    swept.push(format!("{} {} for {} of {}: {}", amount, mint, quoted_out, config.target_mint, signature));
+                }
+                Ok(None) => {}
+                Err(e) => println!("Failed to sweep {}: {}", mint, e),
+            }
+        }
+        if !swept.is_empty() {
+            self.notifier.notify("Dust swept", &swept.join("\n")).await;
+        }
+    }
+
+    // Journals a landed sweep with the costs and proceeds read back from its transaction
+    fn record_sweep(&self, mint: &Pubkey, target_mint: &str, amount: u64, quoted_out: u64, signature: &Signature) {
+        let mut entry = JournalEntry::new(mint.to_string(), target_mint.to_string(), 0, TradeStatus::Executed);
+        entry.sweep = Some(DustSweep { amount, quoted_out });
+        entry.expected_profit_lamports = quoted_out as i64;
+        entry.signature = Some(signature.to_string());
+        match ExecutionReceipt::fetch(&*self.rpc_client, signature, 0, entry.profit_mint()) {
+            Ok(receipt) => {
+                entry.costs = receipt.costs;
+                entry.realized_profit_lamports = Some(receipt.realized_profit_lamports);
+                entry.slot = receipt.slot;
+            }
+            Err(e) => println!("Failed to fetch sweep costs: {}", e),
+        }
+        self.value_in_usd(&mut entry);
+        self.risk.lock().unwrap().observe(&entry);
+        entry.config_profile = self.config_profile.clone();
+        if let Err(e) = self.journal.record(&entry) {
+            println!("Failed to write trade journal: {}", e);
+        }
+    }
+
+    // Disables pairs with no executable opportunities or a net loss over the review window
+    async fn demote_unprofitable_pairs(&mut self, window_start: u64) {
+        let entries = self.journal.load().unwrap_or_else(|e| {
+            println!("Failed to load trade journal for pair review: {}", e);
+            Vec::new()
+        });
+        let demotions = self.pair_tracker.lock().unwrap().review(&entries, window_start);
+        if demotions.is_empty() {
+            return;
+        }
+
+        let mut summary = Vec::new();
+        for demotion in &demotions {
+            if let Some(pair) = self
+                .token_pairs
+                .iter_mut()
+                .find(|pair| format!("{}/{}", pair.token_a, pair.token_b) == demotion.pair_key)
+            {
+                pair.disabled = true;
+                summary.push(format!("{}: {}", demotion.pair_key, demotion.reason()));
+            }
+        }
+
+        self.notifier
+            .notify(&format!("Disabled {} unprofitable pairs", summary.len()), &summary.join("\n"))
+            .await;
+    }
+
+    // Keeps only the top SELECTION_TOP_N pairs by recent volume and spread volatility active.
+    // Runs at startup and on every day rollover, so the active set rotates daily.
+    async fn select_active_pairs(&mut self) {
+        let top_n: usize = match env::var("SELECTION_TOP_N").ok().and_then(|v| v.parse().ok()) {
+            Some(top_n) => top_n,
+            None => return,
+        };
+        let lookback_days: u64 = env::var("SELECTION_LOOKBACK_DAYS").ok().and_then(|v| v.parse().ok()).unwrap_or(7);
+
+        // Pool lists of the venues the pairs trade on: Raydium for the program's own leg, and each
+        // pair's comparison venues
+        let venues: Vec<String> = std::iter::once(route::Venue::Raydium)
+            .chain(self.token_pairs.iter().flat_map(|pair| pair.venues.iter().map(|venue| venue.venue)))
+            .filter_map(|venue| venue.listing())
+            .collect::<BTreeSet<_>>()
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        let listings = match discovery::fetch_listings(&self.http, &venues).await {
+            Ok(listings) => listings,
+            Err(e) => {
+                println!("Failed to fetch pool volumes for pair selection: {}", e);
+                return;
+            }
+        };
+        let volatility = match env::var("RECORD_DIR") {
+            Ok(dir) => {
+                let since = journal::unix_timestamp().saturating_sub(lookback_days * SECONDS_PER_DAY);
+                let snapshots = market_data::load_snapshots(std::path::Path::new(&dir)).unwrap_or_else(|e| {
+                    println!("Failed to load recorded market data for pair selection: {}", e);
+                    Vec::new()
+                });
+                selection::spread_volatility(&snapshots, since)
+            }
+            Err(_) => Default::default(),
+        };
+
+        let pairs: Vec<(String, String)> = self
+            .token_pairs
+            .iter()
+            .map(|pair| (pair.token_a.to_string(), pair.token_b.to_string()))
+            .collect();
+        let ranked = selection::rank_pairs(&pairs, &listings, &volatility);
+
+        let mut summary = Vec::new();
+        for pair in self.token_pairs.iter_mut() {
+            let (a, b) = (pair.token_a.to_string(), pair.token_b.to_string());
+            let position = ranked.iter().position(|score| score.token_a == a && score.token_b == b);
+            pair.active = position.is_some_and(|position| position < top_n);
+            if let Some(position) = position.filter(|position| *position < top_n) {
+                let score = &ranked[position];
+                summary.push(format!(
+                    "{}/{} volume ${:.0} spread vol {:.1} bps",
+                    a, b, score.volume_24h_usd, score.spread_volatility_bps
+                ));
+            }
+        }
+
+        self.notifier
+            .notify(&format!("Active pairs ({} of {})", summary.len(), self.token_pairs.len()), &summary.join("\n"))
+            .await;
+    }
+
+    // The pair's loan amount, within its allocated cap
+    fn loan_amount_for(&self, pair: &TokenPair) -> u64 {
+        match &self.allocation {
+            Some(allocation) => pair.loan_amount.min(allocation.cap(&format!("{}/{}", pair.token_a, pair.token_b))),
+            None => pair.loan_amount,
+        }
+    }
+
+    // Splits the global notional cap across the pairs by their journaled edge and hit rate
+    async fn allocate_capital(&mut self) {
+        let Some(config) = &self.allocation_config else {
+            return;
+        };
+        let entries = match self.journal.load() {
+            Ok(entries) => entries,
+            Err(e) => {
+                println!("Failed to load trade journal for capital allocation: {}", e);
+                return;
+            }
+        };
+        let since = journal::unix_timestamp().saturating_sub(config.lookback_days * SECONDS_PER_DAY);
+        let analytics = PairAnalytics::from_entries(entries.iter().filter(|entry| entry.timestamp >= since));
+        let pairs: Vec<(String, u64)> = self
+            .token_pairs
+            .iter()
+            .map(|pair| (format!("{}/{}", pair.token_a, pair.token_b), pair.loan_amount))
+            .collect();
+        let allocation = allocation::allocate(config, &pairs, &analytics);
+        self.notifier.notify("Pair loan caps", &allocation.summary()).await;
+        self.allocation = Some(allocation);
+    }
+
+    // Proposes pairs listed on enough venues, adding them to the monitored set when auto-add is on
+    async fn run_pair_discovery(&mut self, config: &DiscoveryConfig) {
+        match discovery::discover_pairs(&self.http, config).await {
+            Ok(discovered) => self.take_discovered(config, discovered).await,
+            Err(e) => println!("Pair discovery failed: {}", e),
+        }
+    }
+
+    async fn take_discovered(&mut self, config: &DiscoveryConfig, discovered: Vec<discovery::DiscoveredPair>) {
+        let mut new_pairs: Vec<_> = discovered
+            .into_iter()
+            .filter(|candidate| match self.token_lists.check_all([candidate.token_a.as_str(), candidate.token_b.as_str()]) {
+                Ok(()) => true,
+                Err(e) => {
+                    println!("Dropping discovered pair {}/{}: {}", candidate.symbol_a, candidate.symbol_b, e);
+                    false
+                }
+            })
+            .filter(|candidate| {
+                !self.token_pairs.iter().any(|pair| {
+                    let (a, b) = (pair.token_a.to_string(), pair.token_b.to_string());
+                    (a == candidate.token_a && b == candidate.token_b) || (a == candidate.token_b && b == candidate.token_a)
+                })
+            })
+            .collect();
+        if new_pairs.is_empty() {
+            return;
+        }
+        if let Some(scorer) = &self.safety_scorer {
+            for candidate in new_pairs.iter_mut().filter(|candidate| candidate.safety_score.is_none()) {
+                let signals = scorer.signals(&self.http, candidate).await;
+                println!("Safety of {}/{}: {:.0} from {:?}", candidate.symbol_a, candidate.symbol_b, signals.score(), signals);
+                candidate.safety_score = Some(signals.score());
+            }
+        }
+
+        let describe = |p: &discovery::DiscoveredPair| {
+            let score = p.safety_score.map_or(String::new(), |score| format!(", safety {:.0}", score));
+            format!("{}/{} on {} (${:.0}{})", p.symbol_a, p.symbol_b, p.venues.join(","), p.liquidity_usd, score)
+        };
+
+        // Pairs below the minimum score are neither proposed nor added
+        let min_score = self.safety_scorer.as_ref().map_or(0.0, |scorer| scorer.config.min_score);
+        let (new_pairs, held): (Vec<_>, Vec<_>) =
+            new_pairs.into_iter().partition(|candidate| candidate.safety_score.is_none_or(|score| score >= min_score));
+        if !held.is_empty() {
+            let held = held.iter().map(describe).collect::<Vec<_>>().join("\n");
+            self.notifier
+                .notify(&format!("Discovered pairs below safety score {:.0}", min_score), &held)
+                .await;
+        }
+        if let Err(e) = discovery::write_proposals(&self.discovered_pairs_path, &new_pairs) {
+            println!("Failed to write discovered pairs: {}", e);
+        }
+        if new_pairs.is_empty() {
+            return;
+        }
+
+        if config.auto_add {
+            // Pairs whose pools can't be priced by the program's quote stay proposals
+            let mut added = Vec::new();
+            let mut unpriced = Vec::new();
+            for candidate in &new_pairs {
+                match self.discovered_pair_accounts(candidate) {
+                    Ok(stored) => added.push((describe(candidate), stored)),
+                    Err(e) => unpriced.push(format!("{}: {}", describe(candidate), e)),
+                }
+            }
+            if !added.is_empty() {
+                let (described, stored): (Vec<_>, Vec<_>) = added.into_iter().unzip();
+                match self.adopt_pairs(stored) {
+                    Ok(()) => self.notifier.notify("Discovered pairs added", &described.join("\n")).await,
+                    Err(e) => println!("Failed to add discovered pairs: {}", e),
+                }
+            }
+            if !unpriced.is_empty() {
+                self.notifier.notify("Discovered pairs without quotable pools", &unpriced.join("\n")).await;
+            }
+        } else {
+            let summary = new_pairs.iter().map(describe).collect::<Vec<_>>().join("\n");
+            self.notifier.notify("Discovered pair proposals", &summary).await;
+        }
+    }
+
+    // A discovered pair as the program's quote prices it: its deepest Raydium AMM pool is the
+    // Raydium side and its deepest other pool the Jupiter side, with each pool's vaults read from
+    // its state. The pair starts in SOL when it has it
+    fn discovered_pair_accounts(&self, candidate: &discovery::DiscoveredPair) -> Result<StoredPair, Box<dyn std::error::Error>> {
+        let raydium = candidate.pools.iter().find(|pool| pool.venue == "raydium").ok_or("no Raydium AMM pool")?;
+        let other = candidate
+            .pools
+            .iter()
+            .find(|pool| pool.address != raydium.address)
+            .ok_or("no second pool to compare with")?;
+        let (token_a, token_b) = if candidate.token_b == costs::WSOL_MINT {
+            (&candidate.token_b, &candidate.token_a)
+        } else {
+            (&candidate.token_a, &candidate.token_b)
+        };
+
+        let addresses = [Pubkey::from_str(&other.address)?, Pubkey::from_str(&raydium.address)?];
+        let accounts = self.rpc_client.get_multiple_accounts(&addresses)?;
+        let mut quote_vaults = Vec::new();
+        for (pool, account) in [other, raydium].into_iter().zip(accounts) {
+            let account = account.ok_or_else(|| format!("pool {} not found", pool.address))?;
+            let vaults = match snapshot::decode_account(&account) {
+                snapshot::AccountState::RaydiumAmm { coin_mint, pc_mint, coin_vault, pc_vault, .. } => {
+                    [(coin_mint, coin_vault), (pc_mint, pc_vault)]
+                }
+                snapshot::AccountState::RaydiumClmm { mint_0, mint_1, vault_0, vault_1, .. } => [(mint_0, vault_0), (mint_1, vault_1)],
+                snapshot::AccountState::Whirlpool { mint_a, mint_b, vault_a, vault_b, .. } => [(mint_a, vault_a), (mint_b, vault_b)],
+                _ => return Err(format!("pool {} is not a {} pool", pool.address, pool.venue).into()),
+            };
+            for mint in [token_a, token_b] {
+                let (_, vault) = vaults
+                    .iter()
+                    .find(|(vault_mint, _)| vault_mint == mint)
+                    .ok_or_else(|| format!("pool {} does not hold {}", pool.address, mint))?;
+                quote_vaults.push(vault.clone());
+            }
+        }
+
+        Ok(StoredPair {
+            token_a: token_a.clone(),
+            token_b: token_b.clone(),
+            loan_amount: self.discovery_loan_amount,
+            enabled: true,
+            pool_accounts: vec![raydium.address.clone(), other.address.clone()],
+            quote_vaults,
+            oracle_accounts: None,
+            strategy: None,
+            execution_policy: None,
+            min_profit_threshold: None,
+            dexes: None,
+            poll_interval_ms: None,
+            slippage_tolerance: None,
+            slippage_bps: None,
+            cooldown_secs: None,
+            venues: Vec::new(),
+        })
+    }
+
+    // Adds pairs to the pair store and reloads it, or straight to the monitored pairs without one
+    fn adopt_pairs(&mut self, pairs: Vec<StoredPair>) -> Result<(), Box<dyn std::error::Error>> {
+        match &mut self.pair_store {
+            Some(store) => {
+                let mut stored = store.load()?;
+                stored.extend(pairs);
+                store.save(&stored)?;
+                store.invalidate();
+                self.reload_pairs();
+            }
+            None => {
+                for entry in &pairs {
+                    self.token_pairs.push(TokenPair::from_stored(entry)?);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Executes the route if it clears the required profit. A route that only misses the fixed
+    // transaction cost is returned instead, since it may still pay off batched with others
+    async fn handle_route(&self, route: &Route) -> Option<BatchCandidate> {
+        self.start_opportunity();
+        let checked = self.check_route(route).await;
+        if checked.is_ok() {
+            self.count_opportunity(&route.name);
+        }
+        let candidate = match checked {
+            Ok((quote, evaluation)) if evaluation.is_executable() => {
+                let mut entry = JournalEntry::new(
+                    route.start_mint().to_string(),
+                    route.legs[0].output_mint.clone(),
+                    route.loan_amount,
+                    TradeStatus::Executed,
+                );
+                entry.route = Some(route.name.clone());
+                entry.expected_profit_lamports = evaluation.potential_profit as i64;
+                self.begin_trade(
+                    &mut entry,
+                    &format!("Route clears the required profit of {:.0}", evaluation.required_profit),
+                );
+
+                let result = self.execute_route(route, &quote, evaluation.required_profit, false).await;
+                if let Ok(Execution::Landed(_)) = &result {
+                    println!("Successfully executed route {} ({} legs)", route.name, route.legs.len());
+                }
+                self.record_or_queue(entry, result);
+                None
+            }
+            Ok((quote, evaluation)) if self.batch_enabled && evaluation.variable_margin() > 0.0 => Some(BatchCandidate {
+                route: route.clone(),
+                quote,
+                evaluation,
+            }),
+            Ok(_) => None,
+            Err(e) => {
+                println!("Failed to quote route {}: {}", route.name, e);
+                None
+            }
+        };
+        self.finish_opportunity();
+        candidate
+    }
+
+    // Groups the candidates by borrowed token and executes the most profitable few of each group in
+    // one try_arbitrage_batch, when together they clear the required profit with the transaction
+    // cost paid once
+    async fn execute_batches(&self, mut candidates: Vec<BatchCandidate>) {
+        candidates.sort_by(|a, b| b.evaluation.variable_margin().total_cmp(&a.evaluation.variable_margin()));
+        let mut start_mints: Vec<String> = candidates.iter().map(|c| c.route.start_mint().to_string()).collect();
+        start_mints.sort();
+        start_mints.dedup();
+
+        for start_mint in start_mints {
+            let batch: Vec<&BatchCandidate> = candidates
+                .iter()
+                .filter(|c| c.route.start_mint() == start_mint)
+                .take(MAX_BATCH_ROUTES)
+                .collect();
+            if batch.len() < 2 {
+                continue;
+            }
+            let evaluations: Vec<Evaluation> = batch.iter().map(|c| c.evaluation).collect();
+            let evaluation = evaluator::evaluate_batch(&evaluations);
+            if !evaluation.is_executable() {
+                continue;
+            }
+
+            let name = batch.iter().map(|c| c.route.name.as_str()).collect::<Vec<_>>().join(" + ");
+            let loan_amount = batch.iter().map(|c| c.route.loan_amount).max().unwrap_or(0);
+            let mut entry = JournalEntry::new(start_mint.clone(), batch[0].route.legs[0].output_mint.clone(), loan_amount, TradeStatus::Executed);
+            entry.route = Some(name.clone());
+            entry.expected_profit_lamports = evaluation.potential_profit as i64;
+            self.begin_trade(
+                &mut entry,
+                &format!("{} routes together clear the required profit of {:.0}", batch.len(), evaluation.required_profit),
+            );
+
+            // Each route must at least cover its own size-dependent costs
+            let routes: Vec<(&Route, &RouteQuote, u64)> = batch
+                .iter()
+                .map(|c| (&c.route, &c.quote, (c.evaluation.threshold_cost + c.evaluation.slippage_cost).max(1.0).ceil() as u64))
+                .collect();
+            // The routes were quoted and evaluated in their own opportunities
+            self.start_opportunity();
+            let result = self.send_routes(&name, &routes, loan_amount, evaluation.required_profit, false).await;
+            if let Ok(Execution::Landed(signature)) = &result {
+                println!("Batch transaction executed: {} ({} routes: {})", signature, batch.len(), name);
+            }
+            self.record_or_queue(entry, result);
+            self.finish_opportunity();
+        }
+    }
+
+    // Quotes every leg of the route and evaluates the cycle
+    async fn check_route(&self, route: &Route) -> Result<(RouteQuote, Evaluation), Box<dyn std::error::Error>> {
+        let sol_price = self.get_sol_price_usd().await?;
+        self.mark_stage(Stage::Fetch);
+        let quote = route.quote(&self.http).await?;
+        self.mark_stage(Stage::Quote);
+        let evaluation = evaluator::evaluate_route(
+            route.loan_amount,
+            quote.final_amount(),
+            sol_price,
+            &self.evaluator_params_for(&route.name),
+        );
+        self.mark_stage(Stage::Decision);
+
+        Ok((quote, evaluation))
+    }
+
+    async fn check_arbitrage_opportunity(&self, pair: &TokenPair) -> Result<Opportunity, Box<dyn std::error::Error>> {
+        let sol_price = self.get_sol_price_usd().await?;
+        let oracle_price = match &pair.oracle_accounts {
+            Some((oracle_a, oracle_b)) => Some(self.get_pair_oracle_price(oracle_a, oracle_b)?),
+            None => None,
+        };
+        self.mark_stage(Stage::Fetch);
+        let loan_amount = self.loan_amount_for(pair);
+        let (quote, slot, vaults) = self.fetch_quote(pair, loan_amount).await?;
+        self.mark_stage(Stage::Quote);
+
+        let pair_key = format!("{}/{}", pair.token_a, pair.token_b);
+        let params = self.evaluator_params_for(&pair_key);
+        let decision = pair.strategy.decide(&MarketContext {
+            pair_key: &pair_key,
+            quote: &quote,
+            loan_amount,
+            sol_price,
+            oracle_price,
+            params: &params,
+        });
+        self.mark_stage(Stage::Decision);
+
+        let opportunity = Opportunity {
+            id: evaluator::opportunity_id(slot, &pair.token_a.to_string(), &pair.token_b.to_string()),
+            slot,
+            quote,
+            sol_price,
+            evaluation: decision.evaluation,
+            size: decision.size,
+            reason: decision.reason,
+            quoted_amount: loan_amount,
+            vaults,
+        };
+
+        if let Some(recorder) = &self.recorder {
+            let mut accounts = pair.pool_accounts.clone();
+            accounts.push(self.sol_price_account);
+            if let Err(e) = recorder.record(
+                &*self.rpc_client,
+                &pair.token_a,
+                &pair.token_b,
+                loan_amount,
+                &opportunity,
+                &accounts,
+            ) {
+                println!("Failed to record market data: {}", e);
+            }
+        }
+
+        Ok(opportunity)
+    }
+
+    // One-shot look at a pair at `amount`: both venues' prices, the evaluator and strategy
+    // decisions, every leg's quote and the fees the trade would pay. With `full`, the execution
+    // transaction is also built and simulated. Nothing is sent
+    async fn simulate_pair(&self, pair: &TokenPair, amount: u64, full: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let pair_key = format!("{}/{}", pair.token_a, pair.token_b);
+        println!("Simulating {} with {} lamports", pair_key, amount);
+
+        let (quote, slot, _) = self.fetch_quote(pair, amount).await?;
+        let sol_price = self.get_sol_price_usd().await?;
+        let (buy, _, buy_price) = quote.buy();
+        let (sell, _, sell_price) = quote.sell();
+        let spread_bps = quote.spread() as f64 * 10_000.0 / buy_price.max(1) as f64;
+        println!(
+            "Prices at slot {}: Jupiter-side {}, Raydium {}, SOL ${:.2}",
+            slot, quote.jupiter_price, quote.raydium_price, sol_price
+        );
+        for venue in &quote.venues {
+            println!("  {:?} {}: {}", venue.venue, venue.pool.as_deref().unwrap_or("best route"), venue.price);
+        }
+        println!("Buy on {:?} at {}, sell on {:?} at {} (spread {:.2} bps)", buy, buy_price, sell, sell_price, spread_bps);
+
+        let params = self.evaluator_params_for(&pair_key);
+        let evaluation = evaluator::evaluate(&quote, amount, sol_price, &params);
+        println!("{}", evaluation.explain());
+        let oracle_price = match &pair.oracle_accounts {
+            Some((oracle_a, oracle_b)) => Some(self.get_pair_oracle_price(oracle_a, oracle_b)?),
+            None => None,
+        };
+        let decision = pair.strategy.decide(&MarketContext {
+            pair_key: &pair_key,
+            quote: &quote,
+            loan_amount: amount,
+            sol_price,
+            oracle_price,
+            params: &params,
+        });
+        println!("Strategy {}: {} ({})", pair.strategy.name(), if decision.size.is_some() { "trade" } else { "skip" }, decision.reason);
+
+        let route = self.pair_route(pair, amount, &quote);
+        let route_quote = route.quote(&self.http).await?;
+        println!("Route quotes:");
+        for (leg, (output, min_output)) in route.legs.iter().zip(route_quote.leg_outputs.iter().zip(&route_quote.min_outputs)) {
+            println!("  {:?} {} -> {}: {} (min {})", leg.venue, leg.input_mint, leg.output_mint, output, min_output);
+        }
+
+        // The program charges its configured loan fee (Solend loans pay the reserve's instead) and
+        // takes its profit share from what is left after the loan is repaid
+        let config = self.rpc_client.get_account_data(&client::config_address(&client::program_id()))?;
+        let config_bps = |offset: usize| {
+            config.get(offset..offset + 2).map_or(0, |bytes| u16::from_le_bytes(bytes.try_into().unwrap()) as u64)
+        };
+        let flash_loan_fee = match self.flash_loan {
+            FlashLoanProvider::OwnBalance => 0,
+            _ => costs::bps_of(amount, config_bps(CONFIG_FLASH_LOAN_FEE_BPS_OFFSET)),
+        };
+        let gross_profit = route_quote.final_amount() as i64 - amount as i64;
+        let repaid_profit = (gross_profit - flash_loan_fee as i64).max(0) as u64;
+        let fee_lamports = self.compute_units.lock().unwrap().fee_lamports(&route.name);
+        let fees = CostBreakdown {
+            base_fee_lamports: costs::LAMPORTS_PER_SIGNATURE,
+            priority_fee_lamports: fee_lamports - costs::LAMPORTS_PER_SIGNATURE,
+            flash_loan_fee_lamports: flash_loan_fee,
+            profit_fee_lamports: costs::bps_of(repaid_profit, config_bps(CONFIG_PROFIT_FEE_BPS_OFFSET)),
+            ..CostBreakdown::default()
+        };
+        let net_profit = gross_profit - fees.total_lamports();
+        println!("Fees:");
+        println!("  base fee:         {}", fees.base_fee_lamports);
+        println!("  priority fee:     {}", fees.priority_fee_lamports);
+        println!("  flash loan fee:   {}", fees.flash_loan_fee_lamports);
+        println!("  profit share:     {}", fees.profit_fee_lamports);
+        // Already taken out of the quoted outputs
+        println!("  dex fees (quoted): {}", CostBreakdown::estimate_protocol_fees(amount).dex_fee_lamports);
+        println!("Quoted gross profit {}, net expected profit {} lamports", gross_profit, net_profit);
+
+        if full {
+            // No minimum profit, so the simulation shows the actual outcome instead of reverting
+            let (setup, instruction, start_mint) =
+                self.routes_instruction(&[(&route, &route_quote, 0)], amount, 0.0).await?;
+            let mut budget = self
+                .compute_units
+                .lock()
+                .unwrap()
+                .budget_instructions_with_limit(compute::MAX_COMPUTE_UNITS);
+            budget.extend(setup);
+            let instructions = self.with_tip(self.with_flash_loan(budget, instruction, &start_mint, amount)?, None);
+            let transaction = self.sign_transaction(&instructions)?;
+            match self.simulate_execution(&route.name, &transaction, &start_mint) {
+                Ok(simulated) => println!(
+                    "Simulated execution: {} CU, gross profit {}, fee {}, net profit {} lamports",
+                    simulated.units_consumed.map_or("?".to_string(), |units| units.to_string()),
+                    simulated.gross_profit_lamports,
+                    simulated.fee_lamports,
+                    simulated.net_profit_lamports()
+                ),
+                Err(e) => {
+                    println!("Simulated execution failed: {}", e);
+                    if let Some(failed) = e.downcast_ref::<SimulationError>() {
+                        for line in &failed.logs {
+                            println!("  {}", line);
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Global parameters with the pair's own profit threshold and slippage tolerance if it has them,
+    // its adaptively tuned profit threshold, when tuning is on, and
+    // its fee from calibrated compute units once it has been simulated. Tuning and the competition
+    // bump only move percentage thresholds; absolute ones are the operator's floor
+    fn evaluator_params_for(&self, pair_key: &str) -> EvaluatorParams {
+        let mut params = self.evaluator_params();
+        let pair = self.token_pairs.iter().find(|pair| format!("{}/{}", pair.token_a, pair.token_b) == pair_key);
+        if let Some(threshold) = pair.and_then(|pair| pair.min_profit_threshold) {
+            params.min_profit_threshold = threshold;
+        }
+        if let Some(tolerance) = pair.and_then(|pair| pair.slippage_tolerance) {
+            params.slippage_tolerance = tolerance;
+        }
+        if let ProfitThreshold::Percent(percent) = &mut params.min_profit_threshold {
+            if let Some(tuner) = &self.threshold_tuner {
+                *percent = tuner.lock().unwrap().threshold(pair_key);
+            }
+            if let Some(competition) = &self.competition {
+                *percent += competition.lock().unwrap().threshold_bump(pair_key);
+            }
+        }
+        let compute_units = self.compute_units.lock().unwrap();
+        if compute_units.is_calibrated(pair_key) {
+            params.estimated_gas_cost = compute_units.fee_lamports(pair_key);
+        }
+        // What a transaction that doesn't land costs: the fee when it lands reverted on the public
+        // path, nothing through the block engine, which drops a bundle that would revert
+        params.failure_cost = match &self.private_submission {
+            Some(_) => 0,
+            None => params.estimated_gas_cost,
+        };
+        let backend = match &self.private_submission {
+            Some(jito) if jito.bundle_trades => Backend::JitoBundle,
+            Some(_) => Backend::Jito,
+            None => Backend::Rpc,
+        };
+        if let Some(jito) = &self.private_submission {
+            params.estimated_gas_cost += jito.tip_floor();
+        }
+        // Landing chance at this bid: the priority fee and tip, without the base fee
+        let bid = params.estimated_gas_cost.saturating_sub(costs::LAMPORTS_PER_SIGNATURE);
+        if let Some(probability) = self.landing.lock().unwrap().landing_probability(backend, bid) {
+            params.landing_probability = probability;
+        }
+        params
+    }
+
+    fn evaluator_params(&self) -> EvaluatorParams {
+        EvaluatorParams {
+            min_profit_threshold: self.min_profit_threshold,
+            slippage_tolerance: self.slippage_tolerance,
+            estimated_gas_cost: self.estimated_gas_cost,
+            ..EvaluatorParams::default()
+        }
+    }
+
+    // Returns the prices quoted for a trade of `amount`, the pair's further venues included, the
+    // slot the simulation ran at, and the mint and balance of each vault it read
+    async fn fetch_quote(&self, pair: &TokenPair, amount: u64) -> Result<(Quote, u64, Vec<(String, u64)>), Box<dyn std::error::Error>> {
+        if pair.quote_vaults.len() != 4 {
+            return Err("QUOTE_VAULTS needs four vault accounts for this pair".into());
+        }
+
+        // Create instruction to check prices: the program's `quote` reads both pools' reserves
+        let instruction = client::quote(
+            &client::program_id(),
+            &pair.quote_vaults,
+            &client::QuoteArgs {
+                amount: evaluator::size_trade(amount),
+                fee_bps: [costs::JUPITER_FEE_BPS as u16, costs::RAYDIUM_FEE_BPS as u16],
+            },
+        )?;
+
+        // Unsigned and without a blockhash: the node skips signature checks and swaps in its latest
+        // blockhash, and returns the vaults as it read them
+        let message = solana_sdk::message::Message::new(&[instruction], Some(&self.wallet.pubkey()));
+        let transaction = solana_sdk::transaction::Transaction::new_unsigned(message);
+        let config = RpcSimulateTransactionConfig {
+            sig_verify: false,
+            replace_recent_blockhash: true,
+            commitment: Some(CommitmentConfig::processed()),
+            encoding: None,
+            accounts: Some(RpcSimulateTransactionAccountsConfig {
+                encoding: Some(UiAccountEncoding::Base64),
+                addresses: pair.quote_vaults.iter().map(|vault| vault.to_string()).collect(),
+            }),
+            min_context_slot: None,
+            inner_instructions: false,
+        };
+        let result = self.rpc_client.simulate_transaction_with_config(&transaction, config)?;
+        let simulated = result.value;
+        if let Some(err) = simulated.err {
+            return Err(Box::new(SimulationError {
+                message: err.to_string(),
+                logs: simulated.logs.unwrap_or_default(),
+            }));
+        }
+        let vaults = simulated.accounts.unwrap_or_default();
+        if let Some(index) = (0..pair.quote_vaults.len()).find(|&index| !matches!(vaults.get(index), Some(Some(_)))) {
+            return Err(format!("Quote vault {} not found", pair.quote_vaults[index]).into());
+        }
+
+        // Parse return data to get prices, only when the program itself set it
+        let return_data = simulated.return_data.ok_or("No return data from price check simulation")?;
+        if return_data.program_id != client::PROGRAM_ID {
+            return Err(format!("Price check return data came from {}", return_data.program_id).into());
+        }
+        let (encoded, UiReturnDataEncoding::Base64) = return_data.data;
+        let mut quote = Quote::from_return_data(&base64::decode(encoded)?)?;
+        quote.venues = self.quote_venues(pair, amount).await;
+        // Decoded here rather than by the program, for quote validation; anything that isn't a token
+        // account is left out
+        let balances = vaults
+            .iter()
+            .flatten()
+            .filter_map(|vault| match snapshot::decode_account(&vault.decode::<solana_sdk::account::Account>()?) {
+                snapshot::AccountState::TokenAccount { mint, amount, .. } => Some((mint, amount)),
+                _ => None,
+            })
+            .collect();
+        Ok((quote, result.context.slot, balances))
+    }
+
+    // The further venues' prices for a trade of `amount`, token A to token B like the program's, each
+    // from Jupiter restricted to the venue. A venue that fails to quote is left out of the comparison
+    async fn quote_venues(&self, pair: &TokenPair, amount: u64) -> Vec<VenuePrice> {
+        let slippage_bps = self.slippage_tuner.lock().unwrap().slippage_bps(&pair.key());
+        let mut prices = Vec::with_capacity(pair.venues.len());
+        for venue in &pair.venues {
+            let leg = RouteLeg {
+                venue: venue.venue,
+                input_mint: pair.token_a.to_string(),
+                output_mint: pair.token_b.to_string(),
+                slippage_bps,
+                pool: venue.pool.clone(),
+                dexes: if venue.venue == route::Venue::Jupiter { pair.dexes.clone() } else { None },
+            };
+            match route::quote_leg(&self.http, &leg, evaluator::size_trade(amount)).await {
+                Ok(price) => prices.push(VenuePrice {
+                    venue: venue.venue,
+                    pool: venue.pool.clone(),
+                    price,
+                }),
+                Err(e) => println!("Failed to quote {} on {:?}: {}", pair.key(), venue.venue, e),
+            }
+        }
+        prices
+    }
+
+    async fn get_sol_price_usd(&self) -> Result<f64, Box<dyn std::error::Error>> {
+        self.get_pyth_price_usd(&self.sol_price_account)
+    }
+
+    fn get_pyth_price_usd(&self, price_account: &Pubkey) -> Result<f64, Box<dyn std::error::Error>> {
+        let account_data = self.rpc_client.get_account_data(price_account)?;
+        
+        let price_feed: &SolanaPriceAccount = pyth_sdk_solana::state::load_price_account(&account_data)?;
+        // The SDK's Pubkey comes from a newer solana crate than ours
+        let price_info = price_feed.to_price_feed(&price_account.to_bytes().into()).get_price_unchecked();
+        
+        Ok(price_info.price as f64 * 10f64.powi(price_info.expo))
+    }
+
+    // Token B per token A implied by the two USD feeds, scaled by 1e9 like the venue prices
+    fn get_pair_oracle_price(&self, oracle_a: &Pubkey, oracle_b: &Pubkey) -> Result<u64, Box<dyn std::error::Error>> {
+        let price_a = self.get_pyth_price_usd(oracle_a)?;
+        let price_b = self.get_pyth_price_usd(oracle_b)?;
+        if price_b <= 0.0 {
+            return Err("Oracle price for token B is not positive".into());
+        }
+        Ok((price_a / price_b * 1e9) as u64)
+    }
+
+    // Executes the pair's two-venue trade as a route built from the quote's direction, with the
+    // pair's current slippage on every leg. Under inventory mode only the leg the inventory allows
+    // is swapped, and the entry records it
+    async fn execute_arbitrage(
+        &self,
+        pair: &TokenPair,
+        size: u64,
+        quote: &Quote,
+        required_profit: f64,
+        bundle: bool,
+        entry: &mut JournalEntry,
+    ) -> Result<Execution, Box<dyn std::error::Error>> {
+        // Borrow exactly the size the strategy chose and trade all of it
+        self.stage_timer.lock().unwrap().skip();
+        let route = self.pair_route(pair, size, quote);
+        let route_quote = route.quote(&self.http).await?;
+        self.mark_stage(Stage::Quote);
+        if let Some(side) = self.inventory_side(&route, &route_quote) {
+            return self.execute_inventory_leg(&route, &route_quote, side, required_profit, entry).await;
+        }
+        self.execute_route(&route, &route_quote, required_profit, bundle).await
+    }
+
+    // The inventory leg for a WSOL pair's route, None outside inventory mode, for pairs not
+    // against SOL, or when the position can neither take the buy nor cover the sell
+    fn inventory_side(&self, route: &Route, quote: &RouteQuote) -> Option<InventorySide> {
+        let book = self.inventory.as_ref()?.lock().unwrap();
+        if route.legs.len() != 2 || route.start_mint() != costs::WSOL_MINT {
+            return None;
+        }
+        book.plan(&route.name, route.loan_amount, quote.leg_outputs[0])
+    }
+
+    // Swaps one leg of the route from the wallet, buying the token into inventory with SOL or
+    // selling held token for SOL, and updates the position once it lands. The expected profit is
+    // then the sale's margin over the token's cost, or nothing for a buy. Opening the output
+    // token's account costs rent, which the opportunity's profit has to cover too
+    async fn execute_inventory_leg(
+        &self,
+        route: &Route,
+        quote: &RouteQuote,
+        side: InventorySide,
+        required_profit: f64,
+        entry: &mut JournalEntry,
+    ) -> Result<Execution, Box<dyn std::error::Error>> {
+        let leg = match side {
+            InventorySide::Buy => 0,
+            InventorySide::Sell => 1,
+        };
+        let output_mint = Pubkey::from_str(&route.legs[leg].output_mint)?;
+        let swap = self.wallet_swap_transaction(&route.name, &quote.leg_quotes[leg], &output_mint).await?;
+        let profit_after_rent = entry.expected_profit_lamports - swap.rent.net_lamports();
+        if profit_after_rent as f64 <= required_profit {
+            return Err(format!(
+                "Expected profit {} after {} lamports of rent below required profit {:.0}",
+                profit_after_rent,
+                swap.rent.net_lamports(),
+                required_profit
+            )
+            .into());
+        }
+        self.mark_stage(Stage::Build);
+        let signature = self.send_signed(&swap.transaction, swap.last_valid_block_height).await?;
+        println!("Inventory {:?} executed: {}", side, signature);
+
+        let mut book = self.inventory.as_ref().ok_or("Inventory mode is off")?.lock().unwrap();
+        // A buy is booked at its guaranteed output, so the position never counts more than arrived
+        let trade = match side {
+            InventorySide::Buy => book.buy(&route.name, quote.min_outputs[0], route.loan_amount),
+            InventorySide::Sell => book.sell(&route.name, quote.leg_outputs[0]),
+        };
+        if let Err(e) = book.save() {
+            println!("Failed to save inventory: {}", e);
+        }
+        entry.expected_profit_lamports = match side {
+            InventorySide::Buy => 0,
+            InventorySide::Sell => quote.leg_outputs[1] as i64 - trade.cost_lamports as i64,
+        };
+        entry.inventory = Some(trade);
+        Ok(Execution::Landed(signature))
+    }
+
+    // One Jupiter swap straight from the wallet's token accounts, outside the program and without
+    // a loan, opening the wallet's account for the output mint if needed
+    async fn wallet_swap_transaction(
+        &self,
+        key: &str,
+        quote: &jupiter::JupiterQuote,
+        output_mint: &Pubkey,
+    ) -> Result<WalletSwap, Box<dyn std::error::Error>> {
+        let owner = self.wallet.pubkey();
+        let swap = jupiter::swap_instruction(&self.http, quote, &owner).await?.direct(&owner)?;
+        let token_program = self.rpc_client.get_account(output_mint)?.owner;
+        let mut instructions = self.compute_units.lock().unwrap().budget_instructions(key);
+        instructions.push(
+            anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &owner,
+                &owner,
+                output_mint,
+                &token_program,
+            ),
+        );
+        instructions.push(swap);
+        let instructions = self.with_tip(instructions, None);
+        let rent = rent::account_rent(&*self.rpc_client, &instructions)?;
+        let (transaction, last_valid_block_height) = self.sign_with_expiry(&instructions)?;
+        Ok(WalletSwap {
+            transaction,
+            last_valid_block_height,
+            rent,
+        })
+    }
+
+    // Sells what the inventory holds beyond its target back to SOL and notifies what was sold
+    async fn rebalance_inventory(&self) {
+        let Some(inventory) = &self.inventory else {
+            return;
+        };
+        let excess = inventory.lock().unwrap().excess();
+        let mut sold = Vec::new();
+        for (pair_key, amount) in excess {
+            let Some((_, token)) = pair_key.split_once('/') else {
+                continue;
+            };
+            let slippage_bps = self.slippage_tuner.lock().unwrap().slippage_bps(&pair_key);
+            let result = async {
+                let quote = jupiter::quote(&self.http, token, costs::WSOL_MINT, amount, slippage_bps, None).await?;
+                let swap = self
+                    .wallet_swap_transaction(&pair_key, &quote, &Pubkey::from_str(costs::WSOL_MINT)?)
+                    .await?;
+                let signature = self.rpc_client.send_and_confirm_transaction(&swap.transaction)?;
+                Ok::<_, Box<dyn std::error::Error>>((quote.out_amount, signature))
+            }
+            .await;
+            match result {
+                Ok((lamports, signature)) => {
+                    let mut book = inventory.lock().unwrap();
+                    let trade = book.sell(&pair_key, amount);
+                    if let Err(e) = book.save() {
+                        println!("Failed to save inventory: {}", e);
+                    }
+                    sold.push(format!(
+                        "{} sold {} for {} lamports (cost {}): {}",
+                        pair_key, trade.token_amount, lamports, trade.cost_lamports, signature
+                    ));
+                }
+                Err(e) => println!("Failed to rebalance {}: {}", pair_key, e),
+            }
+        }
+        if !sold.is_empty() {
+            self.notifier.notify("Inventory rebalanced to SOL", &sold.join("\n")).await;
+        }
+    }
+
+    // The pair's two-venue trade of `size` as a route, in the quote's direction
+    fn pair_route(&self, pair: &TokenPair, size: u64, quote: &Quote) -> Route {
+        let pair_key = format!("{}/{}", pair.token_a, pair.token_b);
+        let slippage_bps = self.slippage_tuner.lock().unwrap().slippage_bps(&pair_key);
+        let mut route = Route::for_pair(
+            &pair.token_a.to_string(),
+            &pair.token_b.to_string(),
+            size,
+            quote,
+            slippage_bps,
+            pair.pool_accounts.first().map(|pool| pool.to_string()),
+        );
+        // Only the Jupiter leg between the pair's tokens is restricted, not the SOL legs around it
+        let (token_a, token_b) = (pair.token_a.to_string(), pair.token_b.to_string());
+        for leg in route.legs.iter_mut().filter(|leg| leg.venue == route::Venue::Jupiter) {
+            if [&leg.input_mint, &leg.output_mint] == [&token_a, &token_b] || [&leg.input_mint, &leg.output_mint] == [&token_b, &token_a] {
+                leg.dexes = pair.dexes.clone();
+            }
+        }
+        route
+    }
+
+    async fn execute_route(
+        &self,
+        route: &Route,
+        quote: &RouteQuote,
+        required_profit: f64,
+        bundle: bool,
+    ) -> Result<Execution, Box<dyn std::error::Error>> {
+        let result = self
+            .send_routes(&route.name, &[(route, quote, 0)], route.loan_amount, required_profit, bundle)
+            .await?;
+        if let Execution::Landed(signature) = &result {
+            println!("Route transaction executed: {}", signature);
+        }
+
+        Ok(result)
+    }
+
+    // Sends `routes` on one loan of `loan_amount`: a single route through try_arbitrage, several
+    // through try_arbitrage_batch with each route's own minimum profit. All routes start in the same
+    // token. Under JITO_BUNDLE_TRADES, or when `bundle` asks for it, the verified trade is queued for
+    // the cycle's bundles instead
+    async fn send_routes(
+        &self,
+        key: &str,
+        routes: &[(&Route, &RouteQuote, u64)],
+        loan_amount: u64,
+        required_profit: f64,
+        bundle: bool,
+    ) -> Result<Execution, Box<dyn std::error::Error>> {
+        self.stage_timer.lock().unwrap().skip();
+        let (setup, instruction, start_mint) = self.routes_instruction(routes, loan_amount, required_profit).await?;
+        if self.private_submission.as_ref().is_some_and(|jito| bundle || jito.bundle_trades) {
+            let contested = self.contested_accounts(routes, &instruction);
+            let (instructions, simulated_profit) =
+                self.prepare_verified(key, &setup, instruction, &start_mint, loan_amount, required_profit)?;
+            return Ok(Execution::Queued(PreparedTrade {
+                instructions,
+                simulated_profit,
+                contested,
+            }));
+        }
+        Ok(Execution::Landed(
+            self.send_verified(key, &setup, instruction, &start_mint, loan_amount, required_profit).await?,
+        ))
+    }
+
+    // Accounts the trade instruction writes other than the wallet and the wallet's, vault's and fee
+    // collector's token accounts, which trades pass through and leave as they found them: the
+    // venues' pools and any loan reserve
+    fn contested_accounts(
+        &self,
+        routes: &[(&Route, &RouteQuote, u64)],
+        instruction: &solana_sdk::instruction::Instruction,
+    ) -> HashSet<Pubkey> {
+        let owners = [
+            self.wallet.pubkey(),
+            client::vault_authority_address(&client::program_id()),
+            self.fee_collector,
+        ];
+        let mut passed_through: HashSet<Pubkey> = HashSet::from([self.wallet.pubkey()]);
+        let mints = routes
+            .iter()
+            .flat_map(|(route, _, _)| route.legs.iter().flat_map(|leg| [&leg.input_mint, &leg.output_mint]))
+            .filter_map(|mint| Pubkey::from_str(mint).ok());
+        for mint in mints {
+            for owner in &owners {
+                for token_program in [anchor_spl::token::ID, anchor_spl::token_2022::ID] {
+                    passed_through.insert(anchor_spl::associated_token::get_associated_token_address_with_program_id(
+                        owner,
+                        &mint,
+                        &token_program,
+                    ));
+                }
+            }
+        }
+        instruction
+            .accounts
+            .iter()
+            .filter(|meta| meta.is_writable && !passed_through.contains(&meta.pubkey))
+            .map(|meta| meta.pubkey)
+            .collect()
+    }
+
+    // The trade instruction for `routes`, the instructions opening the vaults its legs swap through
+    // that don't exist yet, and the mint the routes start in
+    async fn routes_instruction(
+        &self,
+        routes: &[(&Route, &RouteQuote, u64)],
+        loan_amount: u64,
+        required_profit: f64,
+    ) -> Result<(Vec<solana_sdk::instruction::Instruction>, solana_sdk::instruction::Instruction, Pubkey), Box<dyn std::error::Error>>
+    {
+        let program_id = client::program_id();
+
+        let owner = self.wallet.pubkey();
+        let start_mint = Pubkey::from_str(routes[0].0.start_mint())?;
+        // SPL Token or Token-2022, whichever owns the start mint; associated accounts depend on it
+        let token_program = self.rpc_client.get_account(&start_mint)?.owner;
+        let token_account = |wallet: &Pubkey| {
+            anchor_spl::associated_token::get_associated_token_address_with_program_id(wallet, &start_mint, &token_program)
+        };
+        let start_account = token_account(&owner);
+        // Legs swap between the program's vaults, so their accounts are built for the vault authority
+        let vault_authority = client::vault_authority_address(&program_id);
+        let is_wsol = routes[0].0.start_mint() == costs::WSOL_MINT;
+        let mut remaining_accounts = Vec::new();
+        let mut batch_routes = Vec::with_capacity(routes.len());
+        for (route, quote, min_profit) in routes {
+            let (legs, accounts) = route
+                .encode_legs(&self.http, &*self.rpc_client, quote, &vault_authority, remaining_accounts.len())
+                .await?;
+            remaining_accounts.extend(accounts);
+            batch_routes.push(client::BatchRoute {
+                amount: route.loan_amount,
+                min_profit_lamports: *min_profit,
+                legs,
+            });
+        }
+        // Loan accounts, if the provider needs any, follow the leg accounts
+        let (loan_provider, loan_accounts) =
+            self.flash_loan.program_args(&*self.rpc_client, &start_mint, remaining_accounts.len())?;
+        remaining_accounts.extend(loan_accounts);
+        // The program refuses to trade once this slot has passed
+        let expiry_slot = self.rpc_client.get_slot()? + self.expiry_slots;
+
+        let accounts = client::TryArbitrageAccounts {
+            user: owner,
+            token_a_account: start_account,
+            vault: token_account(&vault_authority),
+            token_a_mint: start_mint,
+            // WSOL routes need it to open the vault
+            associated_token_program: is_wsol.then_some(anchor_spl::associated_token::ID),
+            token_program,
+            profit_destination: start_account,
+            fee_collector_account: token_account(&self.fee_collector),
+            // The profit minimum below is converted at this feed's SOL price
+            price_oracle: Some(self.sol_price_account),
+        };
+        // Minimum balance gain, enforced on-chain
+        let min_profit_lamports = required_profit.max(0.0).ceil() as u64;
+        // Each leg's venue accounts and any loan accounts, addressed by the offsets in the instruction data
+        let instruction = if batch_routes.len() == 1 {
+            let args = client::TryArbitrageArgs {
+                amount: loan_amount,
+                min_profit_lamports,
+                expiry_slot,
+                loan_provider,
+                legs: batch_routes.remove(0).legs,
+            };
+            client::try_arbitrage(&program_id, &accounts, &args, remaining_accounts)?
+        } else {
+            let args = client::TryArbitrageBatchArgs {
+                amount: loan_amount,
+                min_profit_lamports,
+                expiry_slot,
+                loan_provider,
+                routes: batch_routes,
+            };
+            client::try_arbitrage_batch(&program_id, &accounts, &args, remaining_accounts)?
+        };
+
+        // Vaults the routes pass through that don't exist yet are opened in the same transaction
+        let route_list: Vec<&Route> = routes.iter().map(|(route, _, _)| *route).collect();
+        let setup = route::open_vaults(&*self.rpc_client, &route_list, &owner, &vault_authority)?;
+        Ok((setup, instruction, start_mint))
+    }
+
+    // Simulates the execution at the maximum compute limit, then sends it with a tight limit
+    // calibrated from the units the pair or route consumed
+    async fn send_verified(
+        &self,
+        key: &str,
+        setup: &[solana_sdk::instruction::Instruction],
+        instruction: solana_sdk::instruction::Instruction,
+        profit_mint: &Pubkey,
+        loan_amount: u64,
+        required_profit: f64,
+    ) -> Result<Signature, Box<dyn std::error::Error>> {
+        let (instructions, simulated_profit) =
+            self.prepare_verified(key, setup, instruction, profit_mint, loan_amount, required_profit)?;
+        // The tip is sized on the simulated profit
+        let instructions = self.with_tip(instructions, Some(simulated_profit));
+        let (transaction, last_valid_block_height) = self.sign_with_expiry(&instructions)?;
+        self.send_signed(&transaction, last_valid_block_height).await
+    }
+
+    // Sends a signed trade transaction, recording where it went and how long it took to land. Fails
+    // once the chain passes `last_valid_block_height` without it landing
+    async fn send_signed(
+        &self,
+        transaction: &solana_sdk::transaction::Transaction,
+        last_valid_block_height: u64,
+    ) -> Result<Signature, Box<dyn std::error::Error>> {
+        self.mark_stage(Stage::Sign);
+        self.audit(AuditEvent::Signed {
+            signature: transaction.signatures[0].to_string(),
+        });
+        // A send that fails counts under the backend it was meant for
+        let backend = if self.private_submission.is_some() { Backend::Jito } else { Backend::Rpc };
+        *self.submission.lock().unwrap() = Some(Landing::sent(backend, transaction));
+        self.await_jito_leader().await?;
+        let (signature, land, backend) =
+            self.rpc_client.send_and_confirm_transaction_timed(transaction, last_valid_block_height)?;
+        if let Some(landing) = self.submission.lock().unwrap().as_mut() {
+            landing.backend = backend;
+        }
+        self.stage_timer.lock().unwrap().mark_split(Stage::Send, Stage::Land, land);
+        Ok(signature)
+    }
+
+    // Simulates the execution at the maximum compute limit and returns the instructions to send,
+    // with the calibrated limit and without the tip, along with the simulated profit
+    fn prepare_verified(
+        &self,
+        key: &str,
+        setup: &[solana_sdk::instruction::Instruction],
+        instruction: solana_sdk::instruction::Instruction,
+        profit_mint: &Pubkey,
+        loan_amount: u64,
+        required_profit: f64,
+    ) -> Result<(Vec<solana_sdk::instruction::Instruction>, i64), Box<dyn std::error::Error>> {
+        let mut budget = self
+            .compute_units
+            .lock()
+            .unwrap()
+            .budget_instructions_with_limit(compute::MAX_COMPUTE_UNITS);
+        budget.extend_from_slice(setup);
+        let instructions = self.with_tip(self.with_flash_loan(budget, instruction.clone(), profit_mint, loan_amount)?, None);
+        let simulated_profit =
+            self.verify_simulated_profit(key, &self.sign_transaction(&instructions)?, profit_mint, required_profit)?;
+        self.audit(AuditEvent::Built {
+            simulated_profit_lamports: simulated_profit,
+        });
+
+        let mut budget = self.compute_units.lock().unwrap().budget_instructions(key);
+        budget.extend_from_slice(setup);
+        let instructions = self.with_flash_loan(budget, instruction, profit_mint, loan_amount)?;
+        self.mark_stage(Stage::Build);
+        Ok((instructions, simulated_profit))
+    }
+
+    // Appends the trade instruction to `instructions`, with the instructions the flash loan
+    // provider needs around it
+    fn with_flash_loan(
+        &self,
+        mut instructions: Vec<solana_sdk::instruction::Instruction>,
+        instruction: solana_sdk::instruction::Instruction,
+        mint: &Pubkey,
+        amount: u64,
+    ) -> Result<Vec<solana_sdk::instruction::Instruction>, Box<dyn std::error::Error>> {
+        let wrapped = self
+            .flash_loan
+            .wrap(&*self.rpc_client, &self.wallet.pubkey(), mint, amount, instruction, instructions.len())?;
+        instructions.extend(wrapped);
+        Ok(instructions)
+    }
+
+    // Under leader-aware private submission, holds a send until a Jito leader is close. A change
+    // made through the control API meanwhile cancels the wait and fails the send, since the held
+    // trade may be for a pair that was just disabled or repriced
+    async fn await_jito_leader(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let (Some(_), Some(leaders)) = (&self.private_submission, &self.leaders) else {
+            return Ok(());
+        };
+        let control = async {
+            match &self.control {
+                Some(control) => control.woken().await,
+                None => std::future::pending().await,
+            }
+        };
+        Ok(leaders.await_jito_leader(control).await?)
+    }
+
+    // Under private submission, appends the Jito tip for a trade expected to make
+    // `expected_profit`, or the floor tip before the profit is known
+    fn with_tip(
+        &self,
+        mut instructions: Vec<solana_sdk::instruction::Instruction>,
+        expected_profit: Option<i64>,
+    ) -> Vec<solana_sdk::instruction::Instruction> {
+        if let Some(jito) = &self.private_submission {
+            instructions.push(jito.tip_instruction(&self.wallet.pubkey(), expected_profit));
+        }
+        instructions
+    }
+
+    // Signed by the wallet, and paid for by the fee payer when there is one
+    fn sign_transaction(
+        &self,
+        instructions: &[solana_sdk::instruction::Instruction],
+    ) -> Result<solana_sdk::transaction::Transaction, Box<dyn std::error::Error>> {
+        Ok(self.sign_with_expiry(instructions)?.0)
+    }
+
+    // The signed transaction and the last block height it can land at, for sends that give up on it
+    // once its blockhash expires
+    fn sign_with_expiry(
+        &self,
+        instructions: &[solana_sdk::instruction::Instruction],
+    ) -> Result<(solana_sdk::transaction::Transaction, u64), Box<dyn std::error::Error>> {
+        let (recent_blockhash, last_valid_block_height) = self.rpc_client.get_latest_blockhash_with_height()?;
+        let transaction = match &self.fee_payer {
+            Some(fee_payer) => solana_sdk::transaction::Transaction::new_signed_with_payer(
+                instructions,
+                Some(&fee_payer.pubkey()),
+                &[&fee_payer.keypair, &self.wallet],
+                recent_blockhash,
+            ),
+            None => solana_sdk::transaction::Transaction::new_signed_with_payer(
+                instructions,
+                Some(&self.wallet.pubkey()),
+                &[&self.wallet],
+                recent_blockhash,
+            ),
+        };
+        Ok((transaction, last_valid_block_height))
+    }
+
+    // Simulates the fully built transaction and refuses to send it unless the profit measured from
+    // simulated balances still clears the required profit; quotes can go stale while the
+    // transaction is being built. Returns the simulated net profit
+    fn verify_simulated_profit(
+        &self,
+        key: &str,
+        transaction: &solana_sdk::transaction::Transaction,
+        profit_mint: &Pubkey,
+        required_profit: f64,
+    ) -> Result<i64, Box<dyn std::error::Error>> {
+        let simulated = self.simulate_execution(key, transaction, profit_mint)?;
+        let net_profit = simulated.net_profit_lamports();
+        if (net_profit as f64) <= required_profit {
+            return Err(format!(
+                "Simulated net profit {} below required profit {:.0}",
+                net_profit, required_profit
+            )
+            .into());
+        }
+        Ok(net_profit)
+    }
+
+    // Profit of the signed transaction on the RPC node or the local fork, calibrating the compute
+    // units of `key` from it
+    fn simulate_execution(
+        &self,
+        key: &str,
+        transaction: &solana_sdk::transaction::Transaction,
+        profit_mint: &Pubkey,
+    ) -> Result<SimulatedProfit, Box<dyn std::error::Error>> {
+        let owner = self.wallet.pubkey();
+        let simulated = match &self.fork_simulation {
+            Some(config) => {
+                fork::simulate_profit(&*self.rpc_client, transaction, &client::program_id(), &owner, profit_mint, config)?
+            }
+            None => simulation::simulate_profit(&*self.rpc_client, transaction, &owner, profit_mint)?,
+        };
+        if let Some(units) = simulated.units_consumed {
+            self.compute_units.lock().unwrap().observe(key, units);
+        }
+        Ok(simulated)
+    }
+}
+
+// `opportunities` are the quotes the monitor evaluated per pair that day, empty when it didn't count them
+pub async fn send_daily_report(journal: &TradeJournal, notifier: &Notifier, day: u64, opportunities: &HashMap<String, u64>) {
+    let entries = match journal.load() {
+        Ok(entries) => entries,
+        Err(e) => {
+            println!("Failed to load trade journal for report: {}", e);
+            return;
+        }
+    };
+
+    let mut report = PerformanceReport::for_day(&entries, day);
+    report.pair_performance.set_opportunities(opportunities);
+    // The risk window ends with the reported day
+    let day_end = (day + 1) * SECONDS_PER_DAY - 1;
+    report.risk = Some(RiskTracker::from_entries(&RiskConfig::from_env(), &entries).metrics_at(day_end));
+    let report_dir = env::var("REPORT_DIR").unwrap_or_else(|_| "reports".to_string());
+    match report.write_to_dir(std::path::Path::new(&report_dir)) {
+        Ok(path) => println!("Wrote performance report to {}", path),
+        Err(e) => println!("Failed to write performance report: {}", e),
+    }
+    notifier
+        .notify(&format!("Daily report {}", report.date()), &report.render())
+        .await;
+}
+
+#[tokio::main]
+async fn main() {
+    // Load environment variables from .env file
+    dotenv().ok();
+
+    let args: Vec<String> = env::args().collect();
+    // Devnet and localnet replace the mainnet addresses before anything reads them
+    let network = Network::from_args(&args).expect("Invalid --network");
+    network::select(network);
+    network.apply_settings();
+    // So does a config profile, after the network's registry
+    if let Some(profile) = config_profile::from_args(&args) {
+        config_profile::apply(&profile).expect("Invalid --config-profile");
+    }
+    match args.get(1).map(String::as_str) {
+        Some("report") => return commands::report().await,
+        Some("backtest") => return commands::backtest(&args[2..]),
+        Some("replay") => return commands::replay(&args[2..]),
+        Some("audit") => return commands::audit(&args[2..]),
+        Some("discover") => return commands::discover().await,
+        Some("listen") => return commands::listen(),
+        Some("test-env") => return commands::test_env(&args[2..]),
+        Some("doctor") => return commands::doctor(&args[2..]),
+        Some("withdraw-profits") => return commands::withdraw_profits(&args[2..]),
+        Some("pairs") => return commands::pairs(&args[2..]),
+        Some("balances") => return commands::balances().await,
+        Some("snapshot") => return commands::snapshot(&args[2..]),
+        Some("close-empty-accounts") => return commands::close_empty_accounts(&args[2..]),
+        Some("reconcile") => return commands::reconcile(&args[2..]),
+        Some("import-history") => return commands::import_history(&args[2..]),
+        _ => {}
+    }
+    
+    let wallet_keypair_path = "wallet-keypair.json";
+    
+    let rpc_url = env::var("SOLANA_RPC_URL")
+        .unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+
+    let mut monitor = ArbitrageMonitor::new(
+        &rpc_url,
+        wallet_keypair_path,
+    );
+
+    if network == Network::Devnet {
+        let rpc = RpcClient::new(rpc_url.clone());
+        network::verify_devnet(&rpc).expect("Refusing to start in devnet mode");
+        if let Err(e) = network::airdrop_if_low(&rpc, &monitor.wallet) {
+            println!("Devnet airdrop failed: {}", e);
+        }
+        // A config profile brings its own thresholds
+        if monitor.config_profile.is_none() {
+            monitor.min_profit_threshold = ProfitThreshold::Percent(network::DEVNET_MIN_PROFIT_THRESHOLD);
+            monitor.slippage_tolerance = network::DEVNET_SLIPPAGE_TOLERANCE;
+            println!("Running on devnet at {} with relaxed thresholds", rpc_url);
+        } else {
+            println!("Running on devnet at {}", rpc_url);
+        }
+    }
+
+    // Pairs come from the pair store once it exists (see the `pairs` command), otherwise from env variables
+    let pair_store = PairStore::from_env();
+    if pair_store.exists() {
+        monitor.pair_store = Some(pair_store);
+    } else {
+        let token_a = env::var("BONK_TOKEN_ADDRESS").expect("Missing BONK_TOKEN_ADDRESS");
+        let token_b = env::var("GOAT_TOKEN_ADDRESS").expect("Missing GOAT_TOKEN_ADDRESS");
+        monitor.token_lists.check_all([token_a.as_str(), token_b.as_str()]).expect("Refusing to trade the configured pair");
+        let pair = monitor.add_token_pair(
+            &token_a,
+            &token_b,
+            env::var("LOAN_AMOUNT")
+                .unwrap_or_else(|_| "500000000".to_string())
+                .parse()
+                .expect("Invalid LOAN_AMOUNT"),
+            &env::var("POOL_ACCOUNTS")
+                .unwrap_or_default()
+                .split(',')
+                .map(str::trim)
+                .filter(|address| !address.is_empty())
+                .map(String::from)
+                .collect::<Vec<_>>(),
+        );
+        pair.quote_vaults = env::var("QUOTE_VAULTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|address| !address.is_empty())
+            .map(|address| Pubkey::from_str(address).expect("Invalid QUOTE_VAULTS address"))
+            .collect();
+        pair.venues = pair_store::parse_venues(&env::var("PAIR_VENUES").unwrap_or_default()).expect("Invalid PAIR_VENUES");
+        for venue in &pair.venues {
+            venue.validate().expect("Invalid PAIR_VENUES");
+        }
+        if let Some(name) = env::var("STRATEGY").ok().filter(|v| !v.is_empty()) {
+            pair.strategy = strategy::from_name(&name).expect("Invalid STRATEGY");
+        }
+        if let Some(name) = env::var("EXECUTION_POLICY").ok().filter(|v| !v.is_empty()) {
+            pair.execution_policy = execution_policy::from_name(&name).expect("Invalid EXECUTION_POLICY");
+        }
+        let oracle_a = env::var("TOKEN_A_PYTH_ACCOUNT").ok().filter(|v| !v.is_empty());
+        let oracle_b = env::var("TOKEN_B_PYTH_ACCOUNT").ok().filter(|v| !v.is_empty());
+        if let (Some(oracle_a), Some(oracle_b)) = (oracle_a, oracle_b) {
+            pair.oracle_accounts = Some((
+                Pubkey::from_str(&oracle_a).expect("Invalid TOKEN_A_PYTH_ACCOUNT"),
+                Pubkey::from_str(&oracle_b).expect("Invalid TOKEN_B_PYTH_ACCOUNT"),
+            ));
+        }
+    }
+
+    // `simulate [<token A> <token B>] [--amount <lamports>] [--full]`: the first configured pair, or the
+    // given one with any `pairs add` options, quoted once without starting the loop
+    if args.get(1).map(String::as_str) == Some("simulate") {
+        monitor.reload_pairs();
+        let flags = &args[2..];
+        let tokens: Vec<&String> = flags.iter().take_while(|arg| !arg.starts_with("--")).collect();
+        let stored;
+        let pair = match tokens.as_slice() {
+            [] => monitor.token_pairs.first().expect("No pair configured"),
+            [token_a, token_b] => {
+                let key = format!("{}/{}", token_a, token_b);
+                match monitor.token_pairs.iter().find(|pair| format!("{}/{}", pair.token_a, pair.token_b) == key) {
+                    Some(pair) => pair,
+                    None => {
+                        let entry = commands::stored_pair_from_args(token_a.to_string(), token_b.to_string(), flags, None)
+                            .expect("Invalid pair options");
+                        entry.validate().expect("Invalid pair");
+                        stored = TokenPair::from_stored(&entry).expect("Invalid pair");
+                        &stored
+                    }
+                }
+            }
+            _ => panic!("Usage: simulate [<token A> <token B>] [--amount <lamports>] [--full] [pairs add options]"),
+        };
+        let amount = commands::flag_value(flags, "--amount")
+            .map(|v| v.parse().expect("Invalid --amount"))
+            .unwrap_or(pair.loan_amount);
+        if let Err(e) = monitor.simulate_pair(pair, amount, flags.iter().any(|arg| arg == "--full")).await {
+            println!("Simulation failed: {}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // Multi-leg cyclic routes, e.g. SOL → BONK → GOAT → BONK → SOL
+    if let Some(routes_file) = env::var("ROUTES_FILE").ok().filter(|v| !v.is_empty()) {
+        monitor.routes = route::load_routes(std::path::Path::new(&routes_file)).expect("Invalid ROUTES_FILE");
+        let token_lists = &monitor.token_lists;
+        monitor.routes.retain(|route| {
+            let mints = route.legs.iter().flat_map(|leg| [leg.input_mint.as_str(), leg.output_mint.as_str()]);
+            match token_lists.check_all(mints) {
+                Ok(()) => true,
+                Err(e) => {
+                    println!("Skipping route {}: {}", route.name, e);
+                    false
+                }
+            }
+        });
+    }
+
+    // Pools the route discovery graph is built from
+    if let Some(pools_file) = env::var("POOLS_FILE").ok().filter(|v| !v.is_empty()) {
+        monitor.known_pools = graph::load_pools(std::path::Path::new(&pools_file)).expect("Invalid POOLS_FILE");
+        // Discovered cycles never pass through a listed-out mint
+        let token_lists = &monitor.token_lists;
+        monitor
+            .known_pools
+            .retain(|pool| token_lists.check_all([pool.mint_a.as_str(), pool.mint_b.as_str()]).is_ok());
+    }
+
+    // Per-pair adaptive profit thresholds, and wider thresholds and higher priority fees on pairs
+    // other bots arbitrage in the same slots
+    monitor.set_threshold_tuner();
+    monitor.set_competition_detection();
+
+    // Periodic pair discovery from the venues' token and pool lists
+    if env::var("DISCOVERY_ENABLED").map(|v| v == "true").unwrap_or(false) {
+        monitor.discovery = Some(DiscoveryConfig::from_env());
+        let safety = SafetyConfig::from_env();
+        if safety.min_score > 0.0 {
+            monitor.safety_scorer = Some(SafetyScorer::new(safety, &rpc_url));
+        }
+    }
+
+    // Journal ArbExecuted events from transactions this process did not send
+    if env::var("EVENT_LISTENER").map(|v| v == "true").unwrap_or(false) {
+        std::thread::spawn(commands::listen);
+    }
+
+    // Per-stage latency table every PROFILE_INTERVAL_SECS
+    monitor.profile = args.iter().any(|arg| arg == "--profile");
+
+    // Scan at once when ShredStream shows a swap through a monitored pool
+    if let Some(config) = ShredStreamConfig::from_env() {
+        let watcher = Arc::new(ShredWatcher::new());
+        shredstream::spawn(config, Arc::clone(&watcher));
+        monitor.shreds = Some(watcher);
+    }
+
+    // Pair store edits and config profile switches over HTTP, applied by the running monitor at
+    // once, and its pair analytics and spread matrix
+    if let Some(config) = ControlConfig::from_env() {
+        let control = Arc::new(Control::new(
+            monitor.pair_store.as_ref().map(|store| store.path.clone()),
+            monitor.token_lists.clone(),
+            Arc::clone(&monitor.pair_analytics),
+            Arc::clone(&monitor.spread_matrix),
+        ));
+        control.set_active_profile(monitor.config_profile.clone());
+        control::spawn(config, Arc::clone(&control));
+        monitor.control = Some(control);
+    }
+
+    // Alert, or exit for a restart, when no scan cycle completes for WATCHDOG_TIMEOUT_SECS
+    watchdog::spawn(Arc::clone(&monitor.heartbeat), WatchdogConfig::from_env());
+
+    // Start the monitoring process
+    monitor.monitor_opportunities().await;
+} 
+
+// Borrow SOL (marginfi or Solend)
+// SOL → BONK
+// BONK → GOAT
+// GOAT → BONK (back to BONK!)
+// BONK → SOL
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anchor_spl::token::spl_token;
+    use leaders::{LeaderConfig, LeaderSchedule, Submission};
+    use retry::RetryPolicy;
+    use rpc::mock::MockRpc;
+    use solana_client::client_error::{ClientError, ClientErrorKind};
+    use solana_account_decoder::UiAccount;
+    use solana_client::rpc_response::RpcSimulateTransactionResult;
+    use solana_sdk::account::Account;
+    use solana_sdk::instruction::{Instruction, InstructionError};
+    use solana_sdk::program_pack::Pack;
+    use solana_sdk::transaction::TransactionError;
+    use solana_transaction_status::UiTransactionReturnData;
+
+    const LOAN_AMOUNT: u64 = 1_000_000_000;
+
+    // Pyth v2 price account whose aggregate price is `price` * 10^`expo`
+    fn pyth_price_account(price: i64, expo: i32) -> Account {
+        let mut data = vec![0u8; 3312];
+        data[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        data[4..8].copy_from_slice(&2u32.to_le_bytes());
+        data[8..12].copy_from_slice(&3u32.to_le_bytes());
+        data[20..24].copy_from_slice(&expo.to_le_bytes());
+        data[208..216].copy_from_slice(&price.to_le_bytes());
+        // Aggregate status: trading
+        data[224..228].copy_from_slice(&1u32.to_le_bytes());
+        Account {
+            lamports: 1_000_000,
+            data,
+            owner: Pubkey::default(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+        let mut data = vec![0; spl_token::state::Account::LEN];
+        let account = spl_token::state::Account {
+            mint: *mint,
+            owner: *owner,
+            amount,
+            state: spl_token::state::AccountState::Initialized,
+            ..Default::default()
+        };
+        spl_token::state::Account::pack(account, &mut data).unwrap();
+        Account {
+            lamports: 2_039_280,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn system_account(lamports: u64) -> Account {
+        Account {
+            lamports,
+            data: Vec::new(),
+            owner: solana_sdk::system_program::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    fn simulation(err: Option<TransactionError>, logs: Vec<String>) -> RpcSimulateTransactionResult {
+        RpcSimulateTransactionResult {
+            err,
+            logs: Some(logs),
+            accounts: None,
+            units_consumed: None,
+            return_data: None,
+            inner_instructions: None,
+        }
+    }
+
+    // Simulated return data of the program's `quote`: both pools' outputs, with the four vaults read
+    fn quote_simulation(quote: Quote) -> RpcSimulateTransactionResult {
+        let mut data = quote.jupiter_price.to_le_bytes().to_vec();
+        data.extend_from_slice(&quote.raydium_price.to_le_bytes());
+        let vault = token_account(&Pubkey::new_unique(), &Pubkey::new_unique(), 1_000_000_000);
+        let encoded = UiAccount::encode(&Pubkey::new_unique(), &vault, UiAccountEncoding::Base64, None, None);
+        RpcSimulateTransactionResult {
+            accounts: Some(vec![Some(encoded); 4]),
+            return_data: Some(UiTransactionReturnData {
+                program_id: client::PROGRAM_ID.to_string(),
+                data: (base64::encode(data), UiReturnDataEncoding::Base64),
+            }),
+            ..simulation(None, Vec::new())
+        }
+    }
+
+    // A monitor trading on the wallet's own balance with one pair, over a mock with the SOL price at $150
+    fn monitor(rpc: &Arc<MockRpc>) -> ArbitrageMonitor {
+        rpc.set_account(Pubkey::from_str(network::MAINNET.pyth_sol_usd).unwrap(), pyth_price_account(15_000_000_000, -8));
+        monitor_over(rpc.clone())
+    }
+
+    fn monitor_over(rpc: Arc<dyn RpcApi>) -> ArbitrageMonitor {
+        let mut monitor = ArbitrageMonitor::with_rpc(rpc, Keypair::new());
+        monitor.flash_loan = FlashLoanProvider::OwnBalance;
+        // Tests that check the audit trail give it a path of their own
+        monitor.audit = None;
+        let pair = monitor.add_token_pair(
+            &Pubkey::new_unique().to_string(),
+            &Pubkey::new_unique().to_string(),
+            LOAN_AMOUNT,
+            &[],
+        );
+        pair.quote_vaults = (0..4).map(|_| Pubkey::new_unique()).collect();
+        monitor
+    }
+
+    #[tokio::test]
+    async fn wide_spread_is_sized_to_the_loan() {
+        let rpc = Arc::new(MockRpc::new(250_000_000));
+        let monitor = monitor(&rpc);
+        let quote = Quote {
+            jupiter_price: 1_010_000_000,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        };
+        rpc.push_simulation(quote_simulation(quote.clone()));
+
+        let opportunity = monitor.check_arbitrage_opportunity(&monitor.token_pairs[0]).await.unwrap();
+        assert_eq!(opportunity.slot, 250_000_000);
+        assert!(opportunity.id.starts_with("250000000-"));
+        assert_eq!(opportunity.quote.jupiter_price, quote.jupiter_price);
+        assert_eq!(opportunity.quote.raydium_price, quote.raydium_price);
+        assert_eq!(opportunity.size, Some(evaluator::size_trade(LOAN_AMOUNT)));
+        assert!(opportunity.evaluation.is_executable());
+    }
+
+    #[tokio::test]
+    async fn quotes_diverging_from_pool_math_are_aborted() {
+        let rpc = Arc::new(MockRpc::new(250_000_000));
+        let mut monitor = monitor(&rpc);
+        monitor.quote_check = Some(QuoteCheckConfig {
+            source: QuoteSource::PoolMath,
+            tolerance_bps: 50,
+        });
+        let (base, quote_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let reserves = [(base, 1_000), (quote_mint, 1_020), (base, 1_000), (quote_mint, 1_000)].map(|(mint, lots)| (mint, lots * LOAN_AMOUNT));
+        let simulation = |quote: Quote| {
+            let vaults = reserves.iter().map(|(mint, amount)| {
+                let vault = token_account(mint, &Pubkey::new_unique(), *amount);
+                Some(UiAccount::encode(&Pubkey::new_unique(), &vault, UiAccountEncoding::Base64, None, None))
+            });
+            RpcSimulateTransactionResult {
+                accounts: Some(vaults.collect()),
+                ..quote_simulation(quote)
+            }
+        };
+        let honest = Quote {
+            jupiter_price: quote_check::constant_product_out(LOAN_AMOUNT, reserves[0].1, reserves[1].1, costs::JUPITER_FEE_BPS as u16),
+            raydium_price: quote_check::constant_product_out(LOAN_AMOUNT, reserves[2].1, reserves[3].1, costs::RAYDIUM_FEE_BPS as u16),
+            venues: Vec::new(),
+        };
+
+        rpc.push_simulation(simulation(honest.clone()));
+        assert!(monitor.evaluate_pair(&monitor.token_pairs[0]).await.is_some());
+
+        // A decoding bug inflating one side 5% looks like a wide spread
+        rpc.push_simulation(simulation(Quote {
+            jupiter_price: honest.jupiter_price * 105 / 100,
+            ..honest.clone()
+        }));
+        assert!(monitor.evaluate_pair(&monitor.token_pairs[0]).await.is_none());
+
+        let error = quote_check::check_against_best(&honest, honest.raydium_price, 50).unwrap_err();
+        assert!(error.starts_with("Jupiter-side price"));
+    }
+
+    #[test]
+    fn trades_buy_and_sell_on_the_best_of_all_venues() {
+        let (token_a, token_b) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+        let (raydium, whirlpool, clmm) = (Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string());
+        let quote = Quote {
+            jupiter_price: 1_001_000_000,
+            raydium_price: 1_000_000_000,
+            venues: vec![
+                VenuePrice {
+                    venue: route::Venue::Whirlpool,
+                    pool: Some(whirlpool.clone()),
+                    price: 995_000_000,
+                },
+                VenuePrice {
+                    venue: route::Venue::RaydiumClmm,
+                    pool: Some(clmm.clone()),
+                    price: 1_008_000_000,
+                },
+            ],
+        };
+        let two_venues = Quote {
+            venues: Vec::new(),
+            ..quote.clone()
+        };
+        let params = EvaluatorParams::default();
+
+        // Only the widest spread across all four venues clears the threshold
+        assert_eq!(quote.spread(), 13_000_000);
+        assert!(evaluator::evaluate(&quote, LOAN_AMOUNT, 150.0, &params).is_executable());
+        assert!(!evaluator::evaluate(&two_venues, LOAN_AMOUNT, 150.0, &params).is_executable());
+        let route = Route::for_pair(&token_a, &token_b, LOAN_AMOUNT, &quote, 50, Some(raydium.clone()));
+        assert_eq!((route.legs[1].venue, route.legs[1].pool.as_deref()), (route::Venue::Whirlpool, Some(whirlpool.as_str())));
+        assert_eq!((route.legs[2].venue, route.legs[2].pool.as_deref()), (route::Venue::RaydiumClmm, Some(clmm.as_str())));
+        // With the program's two alone, the trade is the pairwise one through the pair's Raydium pool
+        let route = Route::for_pair(&token_a, &token_b, LOAN_AMOUNT, &two_venues, 50, Some(raydium.clone()));
+        assert_eq!((route.legs[1].venue, route.legs[1].pool.as_deref()), (route::Venue::Raydium, Some(raydium.as_str())));
+        assert_eq!((route.legs[2].venue, route.legs[2].pool.as_deref()), (route::Venue::Jupiter, None));
+
+        let venues = pair_store::parse_venues(&format!("whirlpool:{}, jupiter", whirlpool)).unwrap();
+        assert_eq!(venues[0].venue, route::Venue::Whirlpool);
+        assert!(venues.iter().all(|venue| venue.validate().is_ok()));
+        assert!("raydium_clmm".parse::<PairVenue>().unwrap().validate().is_err());
+    }
+
+    #[tokio::test]
+    async fn spread_matrix_tracks_combinations_below_the_threshold() {
+        let rpc = Arc::new(MockRpc::new(250_000_000));
+        let monitor = monitor(&rpc);
+        rpc.push_simulation(quote_simulation(Quote {
+            jupiter_price: 1_000_100_000,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        }));
+        assert!(monitor.evaluate_pair(&monitor.token_pairs[0]).await.is_none());
+
+        let key = monitor.token_pairs[0].key();
+        let matrix = monitor.spread_matrix.lock().unwrap();
+        let spreads = matrix.spreads(&key);
+        assert_eq!(spreads.len(), 2);
+        let (buy, sell, spread) = spreads[1];
+        assert_eq!((buy, sell), ("raydium", "jupiter_side"));
+        assert!((spread - 1.0).abs() < 1e-9);
+        assert!(spreads[0].2 < 0.0);
+        // Recorded though short of the spread the trade needs
+        assert!(matrix.required_bps(&key).unwrap() > spread);
+        assert!(matrix
+            .metrics()
+            .contains(&format!("arb_venue_spread_bps{{pair=\"{}\",buy=\"raydium\",sell=\"jupiter_side\"}} 1\n", key)));
+    }
+
+    #[tokio::test]
+    async fn spread_matrix_is_served_live_by_the_control_api() {
+        let rpc = Arc::new(MockRpc::new(250_000_000));
+        let monitor = monitor(&rpc);
+        let control = Control::new(None, TokenLists::default(), Arc::default(), Arc::clone(&monitor.spread_matrix));
+        let url = control::serve_local(Arc::new(control));
+        let spreads = || async {
+            let response = reqwest::get(format!("{}/spreads", url)).await.unwrap();
+            response.json::<serde_json::Value>().await.unwrap()
+        };
+        assert_eq!(spreads().await, serde_json::json!([]));
+
+        rpc.push_simulation(quote_simulation(Quote {
+            jupiter_price: 1_000_100_000,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        }));
+        monitor.evaluate_pair(&monitor.token_pairs[0]).await;
+
+        let served = spreads().await;
+        assert_eq!(served[0]["pair"], monitor.token_pairs[0].key());
+        assert_eq!(served[0]["prices"]["raydium"], 1_000_000_000u64);
+        assert_eq!(served[0]["spreads"][1], serde_json::json!({ "buy": "raydium", "sell": "jupiter_side", "spread_bps": 1.0 }));
+        assert!(served[0]["required_bps"].as_f64().unwrap() > 1.0);
+    }
+
+    #[tokio::test]
+    async fn recorded_session_replays_the_same_opportunity() {
+        let path = std::env::temp_dir().join(format!("rpc-fixture-{}.jsonl", std::process::id()));
+        let rpc = Arc::new(MockRpc::new(250_000_000));
+        rpc.set_account(Pubkey::from_str(network::MAINNET.pyth_sol_usd).unwrap(), pyth_price_account(15_000_000_000, -8));
+        rpc.push_simulation(quote_simulation(Quote {
+            jupiter_price: 1_010_000_000,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        }));
+        let recorded = {
+            let monitor = monitor_over(Arc::new(RecordingRpc::create(rpc, &path).unwrap()));
+            monitor.check_arbitrage_opportunity(&monitor.token_pairs[0]).await.unwrap()
+        };
+
+        // A fresh wallet and pair: only what came over RPC is replayed
+        let monitor = monitor_over(Arc::new(ReplayRpc::load(&path).unwrap()));
+        let replayed = monitor.check_arbitrage_opportunity(&monitor.token_pairs[0]).await.unwrap();
+        std::fs::remove_file(&path).ok();
+        assert_eq!(replayed.slot, recorded.slot);
+        assert_eq!(replayed.quote.jupiter_price, recorded.quote.jupiter_price);
+        assert_eq!(replayed.quote.raydium_price, recorded.quote.raydium_price);
+        assert_eq!(replayed.sol_price, recorded.sol_price);
+        assert_eq!(replayed.size, recorded.size);
+    }
+
+    fn stored_pair(loan_amount: u64) -> StoredPair {
+        StoredPair {
+            token_a: Pubkey::new_unique().to_string(),
+            token_b: Pubkey::new_unique().to_string(),
+            loan_amount,
+            enabled: true,
+            pool_accounts: Vec::new(),
+            quote_vaults: Vec::new(),
+            oracle_accounts: None,
+            strategy: None,
+            execution_policy: None,
+            min_profit_threshold: None,
+            dexes: None,
+            poll_interval_ms: None,
+            slippage_tolerance: None,
+            slippage_bps: None,
+            cooldown_secs: None,
+            venues: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn pairs_added_through_the_control_api_are_monitored_at_once() {
+        let path = std::env::temp_dir().join(format!("control-monitor-{}.json", std::process::id()));
+        let store = PairStore::new(path.clone());
+        store.save(&[stored_pair(LOAN_AMOUNT)]).unwrap();
+        let mut monitor = ArbitrageMonitor::with_rpc(Arc::new(MockRpc::new(1)), Keypair::new());
+        monitor.pair_store = Some(store);
+        monitor.reload_pairs();
+        let control = Arc::new(Control::new(
+            Some(path.clone()),
+            TokenLists::default(),
+            Arc::clone(&monitor.pair_analytics),
+            Arc::clone(&monitor.spread_matrix),
+        ));
+        monitor.control = Some(Arc::clone(&control));
+        let url = control::serve_local(control);
+
+        // Within the same modification time tick as the load above, as often as not
+        let added = stored_pair(LOAN_AMOUNT);
+        let response = reqwest::Client::new().post(format!("{}/pairs", url)).json(&added).send().await.unwrap();
+        assert!(response.status().is_success());
+        tokio::time::timeout(Duration::from_secs(1), monitor.pause(Duration::from_secs(60))).await.unwrap();
+        monitor.take_control_changes().await;
+        monitor.reload_pairs();
+        std::fs::remove_file(&path).ok();
+
+        let keys: Vec<String> = monitor.token_pairs.iter().map(|pair| format!("{}/{}", pair.token_a, pair.token_b)).collect();
+        assert_eq!(keys.len(), 2);
+        assert_eq!(keys[1], added.key());
+    }
+
+    // Switching rewrites the process environment, which the other tests read, so the switch runs in
+    // a copy of this test binary running only this test
+    #[tokio::test]
+    async fn config_profiles_are_switched_through_the_control_api() {
+        if env::var("PROFILE_SWITCH_CHILD").is_err() {
+            let status = std::process::Command::new(env::current_exe().unwrap())
+                .args(["tests::config_profiles_are_switched_through_the_control_api", "--exact"])
+                .env("PROFILE_SWITCH_CHILD", "1")
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        config_profile::apply("aggressive").unwrap();
+        let mut monitor = ArbitrageMonitor::with_rpc(Arc::new(MockRpc::new(1)), Keypair::new());
+        monitor.set_threshold_tuner();
+        monitor.set_competition_detection();
+        let control = Arc::new(Control::new(
+            None,
+            TokenLists::default(),
+            Arc::clone(&monitor.pair_analytics),
+            Arc::clone(&monitor.spread_matrix),
+        ));
+        control.set_active_profile(monitor.config_profile.clone());
+        monitor.control = Some(Arc::clone(&control));
+        let url = control::serve_local(control);
+        let http = reqwest::Client::new();
+        assert_eq!(monitor.min_profit_threshold, ProfitThreshold::Percent(0.2));
+        assert!(monitor.threshold_tuner.is_some());
+
+        let unknown = http.put(format!("{}/profile", url)).json(&serde_json::json!({ "name": "reckless" })).send().await.unwrap();
+        assert_eq!(unknown.status(), reqwest::StatusCode::BAD_REQUEST);
+        let requested = http.put(format!("{}/profile", url)).json(&serde_json::json!({ "name": "conservative" })).send().await.unwrap();
+        assert_eq!(requested.status(), reqwest::StatusCode::ACCEPTED);
+        tokio::time::timeout(Duration::from_secs(1), monitor.pause(Duration::from_secs(60))).await.unwrap();
+        assert!(monitor.take_control_changes().await);
+
+        assert_eq!(monitor.min_profit_threshold, ProfitThreshold::Percent(1.0));
+        assert_eq!(monitor.slippage_tolerance, 0.1);
+        assert_eq!(monitor.trade_cooldown, Duration::from_secs(30));
+        assert!(monitor.competition.is_some());
+        // ADAPTIVE_THRESHOLDS was only set by the aggressive profile
+        assert!(monitor.threshold_tuner.is_none());
+        assert_eq!(BackpressureConfig::from_env().capacity, 2);
+        let profile: serde_json::Value = http.get(format!("{}/profile", url)).send().await.unwrap().json().await.unwrap();
+        assert_eq!(profile["active"], "conservative");
+
+        http.put(format!("{}/profile", url)).json(&serde_json::json!({ "name": "aggressive" })).send().await.unwrap();
+        assert!(monitor.take_control_changes().await);
+        assert_eq!(monitor.min_profit_threshold, ProfitThreshold::Percent(0.2));
+        assert_eq!(monitor.trade_cooldown, Duration::ZERO);
+        assert!(monitor.competition.is_none());
+        assert!(monitor.threshold_tuner.is_some());
+        assert!(!monitor.take_control_changes().await);
+    }
+
+    // A pool account of `owner` with the given pubkeys written at their offsets
+    fn pool_account(owner: &str, len: usize, fields: &[(usize, Pubkey)]) -> Account {
+        let mut data = vec![0; len];
+        for (offset, pubkey) in fields {
+            data[*offset..*offset + 32].copy_from_slice(pubkey.as_ref());
+        }
+        Account {
+            lamports: 1,
+            data,
+            owner: Pubkey::from_str(owner).unwrap(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    // A token/SOL candidate listed on a Raydium AMM pool and a Whirlpool, set up over the mock,
+    // with the vaults the program's quote should read: the Whirlpool's, then Raydium's, SOL first
+    fn discovered_pair(rpc: &MockRpc) -> (discovery::DiscoveredPair, Vec<Pubkey>) {
+        let (token, sol) = (Pubkey::new_unique(), Pubkey::from_str(costs::WSOL_MINT).unwrap());
+        let (raydium, whirlpool) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let vaults: Vec<Pubkey> = (0..4).map(|_| Pubkey::new_unique()).collect();
+        // Raydium AMM v4: coin vault, pc vault, coin mint, pc mint
+        let amm = [(336, vaults[3]), (368, vaults[2]), (400, token), (432, sol)];
+        rpc.set_account(raydium, pool_account(network::addresses().raydium_amm, 752, &amm));
+        // Whirlpool: mint A, vault A, mint B, vault B
+        let pool = [(101, sol), (133, vaults[0]), (181, token), (213, vaults[1])];
+        rpc.set_account(whirlpool, pool_account(whirlpool::PROGRAM_ID, 653, &pool));
+
+        let listed = |venue: &str, address: &Pubkey, liquidity_usd: f64| discovery::ListedPool {
+            venue: venue.to_string(),
+            address: address.to_string(),
+            liquidity_usd,
+        };
+        let candidate = discovery::DiscoveredPair {
+            token_a: token.to_string(),
+            token_b: sol.to_string(),
+            symbol_a: "TOKEN".to_string(),
+            symbol_b: "SOL".to_string(),
+            venues: vec!["jupiter".to_string(), "orca".to_string(), "raydium".to_string()],
+            liquidity_usd: 2_000_000.0,
+            volume_24h_usd: 0.0,
+            lp_mint: None,
+            lp_burned_share: None,
+            safety_score: None,
+            pools: vec![listed("orca", &whirlpool, 2_000_000.0), listed("raydium", &raydium, 1_000_000.0)],
+        };
+        (candidate, vaults)
+    }
+
+    #[tokio::test]
+    async fn auto_added_pairs_are_quoted_on_their_pools() {
+        let dir = std::env::temp_dir().join(format!("discovery-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let store = PairStore::new(dir.join("pairs.json"));
+        store.save(&[stored_pair(LOAN_AMOUNT)]).unwrap();
+
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        monitor.token_pairs.clear();
+        monitor.pair_store = Some(store);
+        monitor.reload_pairs();
+        monitor.discovered_pairs_path = dir.join("discovered-pairs.json").display().to_string();
+        let (candidate, vaults) = discovered_pair(&rpc);
+        // A pair without a Raydium AMM pool has nothing for the program's Raydium side
+        let unpriced = discovery::DiscoveredPair {
+            token_a: Pubkey::new_unique().to_string(),
+            pools: candidate.pools[..1].to_vec(),
+            ..candidate.clone()
+        };
+        let config = DiscoveryConfig {
+            auto_add: true,
+            ..DiscoveryConfig::from_env()
+        };
+        monitor.take_discovered(&config, vec![candidate.clone(), unpriced]).await;
+
+        // Added through the store, starting in SOL
+        let stored = PairStore::new(dir.join("pairs.json")).load().unwrap();
+        std::fs::remove_dir_all(&dir).ok();
+        assert_eq!(stored.len(), 2);
+        assert_eq!(monitor.token_pairs.len(), 2);
+        let pair = &monitor.token_pairs[1];
+        assert_eq!(pair.token_a.to_string(), costs::WSOL_MINT);
+        assert_eq!(pair.token_b.to_string(), candidate.token_a);
+        let pools: Vec<String> = pair.pool_accounts.iter().map(Pubkey::to_string).collect();
+        assert_eq!(pools, vec![candidate.pools[1].address.clone(), candidate.pools[0].address.clone()]);
+        assert_eq!(pair.quote_vaults, vaults);
+
+        rpc.push_simulation(quote_simulation(Quote {
+            jupiter_price: 1_010_000_000,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        }));
+        monitor.check_arbitrage_opportunity(pair).await.unwrap();
+        let (_, config) = rpc.simulated().remove(0);
+        let read: Vec<String> = vaults.iter().map(Pubkey::to_string).collect();
+        assert_eq!(config.accounts.unwrap().addresses, read);
+    }
+
+    #[tokio::test]
+    async fn discovered_pairs_below_the_safety_score_are_held() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        monitor.token_pairs.clear();
+        let proposals = std::env::temp_dir().join(format!("discovered-pairs-{}.json", std::process::id()));
+        monitor.discovered_pairs_path = proposals.display().to_string();
+        let safety = SafetyConfig {
+            min_score: 60.0,
+            birdeye_url: "http://127.0.0.1:9".to_string(),
+            birdeye_api_key: None,
+        };
+        monitor.safety_scorer = Some(SafetyScorer::new(safety, "http://127.0.0.1:9"));
+        // Scored already, so the scorer never reaches its endpoints
+        let (safe, vaults) = discovered_pair(&rpc);
+        let (risky, _) = discovered_pair(&rpc);
+        let candidates = vec![
+            discovery::DiscoveredPair {
+                safety_score: Some(80.0),
+                ..safe.clone()
+            },
+            discovery::DiscoveredPair {
+                safety_score: Some(40.0),
+                ..risky
+            },
+        ];
+        let proposed = |path: &std::path::Path| -> Vec<String> {
+            let pairs: Vec<discovery::DiscoveredPair> = serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap();
+            pairs.into_iter().map(|pair| pair.token_a).collect()
+        };
+
+        // Held back from the proposals
+        let mut config = DiscoveryConfig {
+            auto_add: false,
+            ..DiscoveryConfig::from_env()
+        };
+        monitor.take_discovered(&config, candidates.clone()).await;
+        assert_eq!(proposed(&proposals), vec![safe.token_a.clone()]);
+        assert!(monitor.token_pairs.is_empty());
+
+        // And from auto-add; the pair that passes is added and evaluated on its pools
+        config.auto_add = true;
+        monitor.take_discovered(&config, candidates).await;
+        assert_eq!(proposed(&proposals), vec![safe.token_a.clone()]);
+        std::fs::remove_file(&proposals).ok();
+        assert_eq!(monitor.token_pairs.len(), 1);
+        assert_eq!(monitor.token_pairs[0].token_b.to_string(), safe.token_a);
+        assert_eq!(monitor.token_pairs[0].quote_vaults, vaults);
+
+        rpc.push_simulation(quote_simulation(Quote {
+            jupiter_price: 1_010_000_000,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        }));
+        let opportunity = monitor.check_arbitrage_opportunity(&monitor.token_pairs[0]).await.unwrap();
+        assert!(opportunity.evaluation.is_executable());
+    }
+
+    #[test]
+    fn pair_store_changes_reach_the_monitor() {
+        let path = std::env::temp_dir().join(format!("pairs-{}.json", std::process::id()));
+        let store = PairStore::new(path.clone());
+        let mut pairs = vec![stored_pair(LOAN_AMOUNT), stored_pair(LOAN_AMOUNT)];
+        pairs[1].min_profit_threshold = Some(ProfitThreshold::Percent(2.0));
+        store.save(&pairs).unwrap();
+
+        let mut monitor = ArbitrageMonitor::with_rpc(Arc::new(MockRpc::new(1)), Keypair::new());
+        monitor.pair_store = Some(store);
+        monitor.reload_pairs();
+        assert_eq!(monitor.token_pairs.len(), 2);
+        assert_eq!(monitor.evaluator_params_for(&pairs[1].key()).min_profit_threshold, ProfitThreshold::Percent(2.0));
+        monitor.token_pairs[0].disabled = true;
+
+        // Resize the second pair and disable a new third one
+        std::thread::sleep(Duration::from_millis(10));
+        pairs[1].loan_amount = 2 * LOAN_AMOUNT;
+        pairs.push(StoredPair {
+            enabled: false,
+            ..stored_pair(LOAN_AMOUNT)
+        });
+        PairStore::new(path.clone()).save(&pairs).unwrap();
+        monitor.reload_pairs();
+        std::fs::remove_file(&path).ok();
+
+        assert_eq!(monitor.token_pairs.len(), 2);
+        // The untouched pair keeps its demotion; the edited one starts fresh
+        assert!(monitor.token_pairs[0].disabled);
+        assert_eq!(monitor.token_pairs[1].loan_amount, 2 * LOAN_AMOUNT);
+        assert!(!monitor.token_pairs[1].disabled);
+    }
+
+    #[test]
+    fn safety_score_weighs_each_scam_heuristic() {
+        let established = token_safety::SafetySignals {
+            top_holders_share: Some(0.2),
+            age_secs: Some(90 * SECONDS_PER_DAY),
+            lp_burned_share: Some(1.0),
+            removable_share: Some(0.0),
+        };
+        assert_eq!(established.score(), 100.0);
+        // A week old, 60% held by ten accounts, half the LP burned and the rest with one holder
+        let risky = token_safety::SafetySignals {
+            top_holders_share: Some(0.6),
+            age_secs: Some(7 * SECONDS_PER_DAY),
+            lp_burned_share: Some(0.5),
+            removable_share: Some(0.5),
+        };
+        let expected = 25.0 * (0.5 + 7.0 / 30.0 + 0.5 + 0.5);
+        assert!((risky.score() - expected).abs() < 1e-9);
+        assert!(risky.score() < 60.0);
+        // Unread signals earn nothing
+        assert_eq!(token_safety::SafetySignals::default().score(), 0.0);
+    }
+
+    #[test]
+    fn listed_out_mints_are_never_monitored() {
+        let path = std::env::temp_dir().join(format!("pairs-listed-{}.json", std::process::id()));
+        let store = PairStore::new(path.clone());
+        let pairs = vec![stored_pair(LOAN_AMOUNT), stored_pair(LOAN_AMOUNT), stored_pair(LOAN_AMOUNT)];
+        store.save(&pairs).unwrap();
+
+        let mut monitor = ArbitrageMonitor::with_rpc(Arc::new(MockRpc::new(1)), Keypair::new());
+        monitor.pair_store = Some(store);
+        monitor.token_lists.blocklist.insert(pairs[0].token_b.clone());
+        monitor.token_lists.allowlist = Some(HashSet::from([
+            pairs[0].token_a.clone(),
+            pairs[1].token_a.clone(),
+            pairs[1].token_b.clone(),
+            pairs[2].token_a.clone(),
+        ]));
+        monitor.reload_pairs();
+        std::fs::remove_file(&path).ok();
+
+        // Blocked, allowed, and one token outside the allowlist
+        let monitored: Vec<String> = monitor.token_pairs.iter().map(TokenPair::key).collect();
+        assert_eq!(monitored, [pairs[1].key()]);
+        assert!(monitor.token_lists.check(&pairs[0].token_b).unwrap_err().contains("TOKEN_BLOCKLIST"));
+        assert!(monitor.token_lists.check(&pairs[2].token_b).unwrap_err().contains("TOKEN_ALLOWLIST"));
+    }
+
+    #[test]
+    fn stored_pairs_override_slippage_and_cooldown() {
+        let path = std::env::temp_dir().join(format!("pair-overrides-{}.json", std::process::id()));
+        let store = PairStore::new(path.clone());
+        let pairs = vec![
+            StoredPair {
+                slippage_tolerance: Some(0.8),
+                slippage_bps: Some(120),
+                cooldown_secs: Some(60),
+                ..stored_pair(LOAN_AMOUNT)
+            },
+            stored_pair(LOAN_AMOUNT),
+        ];
+        store.save(&pairs).unwrap();
+        let mut monitor = ArbitrageMonitor::with_rpc(Arc::new(MockRpc::new(1)), Keypair::new());
+        monitor.pair_store = Some(store);
+        monitor.reload_pairs();
+        std::fs::remove_file(&path).ok();
+
+        let (tuned, plain) = (pairs[0].key(), pairs[1].key());
+        assert_eq!(monitor.evaluator_params_for(&tuned).slippage_tolerance, 0.8);
+        assert_eq!(monitor.evaluator_params_for(&plain).slippage_tolerance, monitor.slippage_tolerance);
+        let slippage = monitor.slippage_tuner.lock().unwrap();
+        assert_eq!(slippage.slippage_bps(&tuned), 120);
+        assert_eq!(slippage.slippage_bps(&plain), SlippageConfig::from_env().default_bps);
+        drop(slippage);
+
+        // Attempted just now: only the pair with a cooldown waits
+        for pair in &monitor.token_pairs {
+            monitor.last_attempts.lock().unwrap().insert(pair.key(), std::time::Instant::now());
+        }
+        assert!(monitor.cooling_down(&monitor.token_pairs[0]));
+        assert!(!monitor.cooling_down(&monitor.token_pairs[1]));
+        assert!(StoredPair {
+            slippage_tolerance: Some(150.0),
+            ..stored_pair(LOAN_AMOUNT)
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[test]
+    fn opportunity_flood_sheds_lowest_expected_value_and_stale_entries() {
+        let mut queue = OpportunityQueue::new(BackpressureConfig {
+            capacity: 3,
+            max_age: Duration::from_millis(500),
+        });
+        let now = std::time::Instant::now();
+        assert_eq!(queue.push("old", 9_000.0, now - Duration::from_secs(1)), None);
+        assert_eq!(queue.push("small", 1_000.0, now), None);
+        assert_eq!(queue.push("large", 5_000.0, now), None);
+        // Full: the lowest expected value goes, whether queued earlier or just arriving
+        assert_eq!(queue.push("medium", 3_000.0, now), Some("small"));
+        assert_eq!(queue.push("tiny", 10.0, now), Some("tiny"));
+
+        // The most valuable one sat too long to be worth executing
+        assert_eq!(queue.shed_stale(now), vec!["old"]);
+        assert_eq!(queue.pop(), Some("large"));
+        assert_eq!(queue.pop(), Some("medium"));
+        assert_eq!(queue.pop(), None);
+    }
+
+    #[test]
+    fn confirming_policy_waits_for_consecutive_executable_quotes() {
+        let pair = TokenPair::from_stored(&StoredPair {
+            execution_policy: Some("confirm:2".to_string()),
+            ..stored_pair(LOAN_AMOUNT)
+        })
+        .unwrap();
+        let key = format!("{}/{}", pair.token_a, pair.token_b);
+        let policy = &pair.execution_policy;
+        assert_eq!(policy.admit(&key, true), Admission::Wait);
+        // A quote that isn't executable breaks the streak
+        assert_eq!(policy.admit(&key, false), Admission::Wait);
+        assert_eq!(policy.admit(&key, true), Admission::Wait);
+        assert_eq!(policy.admit(&key, true), Admission::Send);
+        // And a send starts the next one over
+        assert_eq!(policy.admit(&key, true), Admission::Wait);
+
+        let unset = TokenPair::from_stored(&stored_pair(LOAN_AMOUNT)).unwrap();
+        assert_eq!(unset.execution_policy.admit(&key, true), Admission::Send);
+        assert!(execution_policy::from_name("bundle").is_ok_and(|policy| policy.admit(&key, true) == Admission::Queue));
+        assert!(StoredPair {
+            execution_policy: Some("eventually".to_string()),
+            ..stored_pair(LOAN_AMOUNT)
+        }
+        .validate()
+        .is_err());
+    }
+
+    #[tokio::test]
+    async fn narrow_spread_is_skipped() {
+        let rpc = Arc::new(MockRpc::new(250_000_000));
+        let monitor = monitor(&rpc);
+        rpc.push_simulation(quote_simulation(Quote {
+            jupiter_price: 1_000_100_000,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        }));
+
+        let opportunity = monitor.check_arbitrage_opportunity(&monitor.token_pairs[0]).await.unwrap();
+        assert_eq!(opportunity.size, None);
+        assert!(!opportunity.evaluation.is_executable());
+    }
+
+    #[tokio::test]
+    async fn quotes_are_simulated_unsigned_on_the_nodes_blockhash() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let monitor = monitor(&rpc);
+        let quote = Quote {
+            jupiter_price: 1_010_000_000,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        };
+        rpc.push_simulation(quote_simulation(quote.clone()));
+        monitor.check_arbitrage_opportunity(&monitor.token_pairs[0]).await.unwrap();
+
+        let (transaction, config) = rpc.simulated().remove(0);
+        assert_eq!(transaction.signatures, vec![Signature::default()]);
+        assert_eq!(transaction.message.recent_blockhash, solana_sdk::hash::Hash::default());
+        assert!(config.replace_recent_blockhash && !config.sig_verify);
+        assert_eq!(config.commitment, Some(CommitmentConfig::processed()));
+        let vaults: Vec<String> = monitor.token_pairs[0].quote_vaults.iter().map(|vault| vault.to_string()).collect();
+        assert_eq!(config.accounts.unwrap().addresses, vaults);
+
+        // Return data set by another program isn't a quote
+        let mut foreign = quote_simulation(quote);
+        foreign.return_data.as_mut().unwrap().program_id = Pubkey::new_unique().to_string();
+        rpc.push_simulation(foreign);
+        assert!(monitor.check_arbitrage_opportunity(&monitor.token_pairs[0]).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn quote_needs_four_vaults() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        monitor.token_pairs[0].quote_vaults.truncate(2);
+        assert!(monitor.check_arbitrage_opportunity(&monitor.token_pairs[0]).await.is_err());
+    }
+
+    // Mock state for send_verified: the wallet and its token account before the trade, and a
+    // simulation in which the token account gains `gain`
+    fn script_trade(rpc: &MockRpc, wallet: &Pubkey, mint: &Pubkey, gain: u64) {
+        let token_address = anchor_spl::associated_token::get_associated_token_address(wallet, mint);
+        let lamports = 10_000_000_000;
+        rpc.set_account(*wallet, system_account(lamports));
+        rpc.set_account(token_address, token_account(mint, wallet, 0));
+
+        let post = [
+            (*wallet, system_account(lamports - rpc.fee_lamports)),
+            (token_address, token_account(mint, wallet, gain)),
+        ];
+        rpc.push_simulation(RpcSimulateTransactionResult {
+            accounts: Some(
+                post.iter()
+                    .map(|(address, account)| Some(UiAccount::encode(address, account, UiAccountEncoding::Base64, None, None)))
+                    .collect(),
+            ),
+            units_consumed: Some(180_000),
+            ..simulation(None, Vec::new())
+        });
+    }
+
+    fn trade_instruction() -> Instruction {
+        Instruction {
+            program_id: client::program_id(),
+            accounts: Vec::new(),
+            data: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn profitable_simulation_is_sent_with_calibrated_limit() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let monitor = monitor(&rpc);
+        let mint = Pubkey::new_unique();
+        script_trade(&rpc, &monitor.wallet.pubkey(), &mint, 1_000_000);
+
+        monitor
+            .send_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 500_000.0).await
+            .unwrap();
+        assert_eq!(rpc.sent().len(), 1);
+        // The sent transaction's limit comes from the units the simulation consumed
+        assert!(monitor.compute_units.lock().unwrap().is_calibrated("route"));
+    }
+
+    #[tokio::test]
+    async fn fee_payer_pays_for_trades_and_is_topped_up_below_its_floor() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let fee_payer = Keypair::new();
+        rpc.set_account(fee_payer.pubkey(), system_account(10_000_000));
+        monitor.fee_payer = Some(FeePayer {
+            keypair: fee_payer,
+            floor_lamports: 50_000_000,
+            target_lamports: 200_000_000,
+        });
+        let payer = monitor.fee_payer.as_ref().unwrap().pubkey();
+
+        // Trades are paid for by the fee payer and still signed by the wallet
+        let mut instruction = trade_instruction();
+        instruction.accounts.push(solana_sdk::instruction::AccountMeta::new(monitor.wallet.pubkey(), true));
+        let transaction = monitor.sign_transaction(&[instruction]).unwrap();
+        assert_eq!(transaction.message.account_keys[0], payer);
+        assert_eq!(transaction.message.account_keys[1], monitor.wallet.pubkey());
+        assert_eq!(transaction.signatures.len(), 2);
+
+        // Below the floor, the wallet sends it back up to the target and pays for the transfer
+        monitor.top_up_fee_payer().await;
+        let sent = rpc.sent();
+        assert_eq!(sent.len(), 1);
+        assert_eq!(sent[0].message.account_keys[0], monitor.wallet.pubkey());
+        assert_eq!(
+            sent[0].message.instructions[0].data,
+            solana_sdk::system_instruction::transfer(&monitor.wallet.pubkey(), &payer, 190_000_000).data
+        );
+        assert_eq!(monitor.fee_payer.as_ref().unwrap().top_up_amount(50_000_000), 0);
+    }
+
+    #[tokio::test]
+    async fn fee_payer_top_up_tips_under_private_submission() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let fee_payer = Keypair::new();
+        rpc.set_account(fee_payer.pubkey(), system_account(10_000_000));
+        monitor.fee_payer = Some(FeePayer {
+            keypair: fee_payer,
+            floor_lamports: 50_000_000,
+            target_lamports: 200_000_000,
+        });
+        monitor.private_submission = Some(JitoConfig::new(String::new(), String::new(), TipPolicy::from_env()));
+
+        // A bundle-only send without a tip would never land
+        monitor.top_up_fee_payer().await;
+        let sent = &rpc.sent()[0];
+        assert_eq!(sent.message.instructions.len(), 2);
+        let tip = &sent.message.instructions[1];
+        assert_eq!(sent.message.account_keys[tip.program_id_index as usize], solana_sdk::system_program::id());
+        let tip_account = sent.message.account_keys[tip.accounts[1] as usize].to_string();
+        assert!(network::MAINNET.jito_tip_accounts.contains(&tip_account.as_str()));
+    }
+
+    #[tokio::test]
+    async fn sent_trade_is_timed_by_stage() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let monitor = monitor(&rpc);
+        let mint = Pubkey::new_unique();
+        script_trade(&rpc, &monitor.wallet.pubkey(), &mint, 1_000_000);
+
+        monitor.start_opportunity();
+        monitor
+            .send_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 500_000.0).await
+            .unwrap();
+        monitor.finish_opportunity();
+        let stages: Vec<Stage> = monitor.stage_timer.lock().unwrap().millis().into_keys().collect();
+        assert_eq!(stages, [Stage::Build, Stage::Sign, Stage::Send, Stage::Land]);
+        let metrics = monitor.latency.lock().unwrap().metrics();
+        assert!(metrics.contains("arb_stage_latency_seconds_count{stage=\"land\"} 1"));
+        assert!(!metrics.contains("stage=\"quote\""));
+    }
+
+    #[tokio::test]
+    async fn send_that_outlives_its_blockhash_is_journaled_as_expired() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let journal_path = std::env::temp_dir().join(format!("expired-{}.jsonl", std::process::id()));
+        monitor.journal = TradeJournal::new(&journal_path);
+        let mint = Pubkey::new_unique();
+        script_trade(&rpc, &monitor.wallet.pubkey(), &mint, 1_000_000);
+        rpc.expire_next_send();
+
+        let entry = JournalEntry::new(mint.to_string(), Pubkey::new_unique().to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+        let result = monitor.send_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 500_000.0).await;
+        let error = result.as_ref().unwrap_err().to_string();
+        // Given up on past the blockhash's last valid block height
+        assert!(error.contains(&format!("block height exceeded {}", 1 + solana_sdk::clock::MAX_PROCESSING_AGE)));
+        assert!(rpc::expired_unconfirmed(&error));
+        monitor.record_execution(entry, result);
+
+        let journaled = &monitor.journal.load().unwrap()[0];
+        assert_eq!(journaled.status, TradeStatus::Expired);
+        assert_eq!(journaled.failure_class, Some(FailureClass::BlockhashExpired));
+        assert_eq!(journaled.gross_profit_lamports(), 0);
+        std::fs::remove_file(&journal_path).ok();
+    }
+
+    #[tokio::test]
+    async fn sent_trade_is_audited_under_one_trade_id() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let dir = std::env::temp_dir();
+        let audit_path = dir.join(format!("audit-{}.jsonl", std::process::id()));
+        let journal_path = dir.join(format!("trades-{}.jsonl", std::process::id()));
+        monitor.audit = Some(AuditLog::new(&audit_path));
+        monitor.journal = TradeJournal::new(&journal_path);
+        let mint = Pubkey::new_unique();
+        script_trade(&rpc, &monitor.wallet.pubkey(), &mint, 1_000_000);
+
+        let mut entry = JournalEntry::new(mint.to_string(), Pubkey::new_unique().to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+        monitor.begin_trade(&mut entry, "spread");
+        let result = monitor.send_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 500_000.0).await;
+        monitor.record_execution(entry, result);
+
+        let log = AuditLog::new(&audit_path);
+        let records = log.load().unwrap();
+        let trade_id = monitor.journal.load().unwrap()[0].trade_id.clone();
+        assert!(records.iter().all(|record| record.trade_id == trade_id));
+        let events: Vec<String> = records
+            .iter()
+            .map(|record| serde_json::to_value(&record.event).unwrap()["type"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(events, ["decided", "built", "signed", "confirmed", "journaled"]);
+        assert_eq!(log.verify().unwrap(), 5);
+
+        // Rewriting history breaks the chain
+        let tampered = std::fs::read_to_string(&audit_path).unwrap().replace("\"spread\"", "\"oracle\"");
+        std::fs::write(&audit_path, tampered).unwrap();
+        assert!(log.verify().is_err());
+        std::fs::remove_file(&audit_path).ok();
+        std::fs::remove_file(&journal_path).ok();
+    }
+
+    #[tokio::test]
+    async fn private_submission_sizes_and_rotates_jito_tips() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let policy = TipPolicy {
+            min_lamports: 1_000,
+            max_lamports: 100_000,
+            profit_share: 0.5,
+            percentile: 50,
+            default_lamports: 10_000,
+        };
+        // The floor until statistics arrive, a share of the profit above it, and never past the cap
+        assert_eq!(policy.size(None, None), 10_000);
+        assert_eq!(policy.size(Some(5_000), Some(60_000)), 30_000);
+        assert_eq!(policy.size(Some(500), Some(-1)), 1_000);
+        assert_eq!(policy.size(Some(5_000), Some(1_000_000)), 100_000);
+        monitor.private_submission = Some(JitoConfig::new(String::new(), String::new(), policy));
+        let mint = Pubkey::new_unique();
+        script_trade(&rpc, &monitor.wallet.pubkey(), &mint, 1_000_000);
+        // The evaluator charges the tip with the fee
+        assert_eq!(monitor.evaluator_params_for("route").estimated_gas_cost, monitor.estimated_gas_cost + 10_000);
+
+        monitor
+            .send_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 500_000.0).await
+            .unwrap();
+        let sent = &rpc.sent()[0];
+        let tip = sent.message.instructions.last().unwrap();
+        let account_keys = &sent.message.account_keys;
+        assert_eq!(account_keys[tip.program_id_index as usize], solana_sdk::system_program::id());
+        let tip_account = account_keys[tip.accounts[1] as usize].to_string();
+        // The simulated transaction tipped the first account, the sent one the second
+        assert_eq!(tip_account, network::MAINNET.jito_tip_accounts[1]);
+        let next = monitor.private_submission.as_ref().unwrap().tip_instruction(&monitor.wallet.pubkey(), None);
+        assert_eq!(next.accounts[1].pubkey.to_string(), network::MAINNET.jito_tip_accounts[2]);
+    }
+
+    #[tokio::test]
+    async fn sent_trades_are_counted_per_backend() {
+        let rpc = Arc::new(MockRpc::new(40));
+        let mut monitor = monitor(&rpc);
+        let journal_path = std::env::temp_dir().join(format!("landing-trades-{}.jsonl", std::process::id()));
+        monitor.journal = TradeJournal::new(&journal_path);
+        let mint = Pubkey::new_unique();
+        script_trade(&rpc, &monitor.wallet.pubkey(), &mint, 1_000_000);
+
+        let mut entry = JournalEntry::new(mint.to_string(), Pubkey::new_unique().to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+        monitor.begin_trade(&mut entry, "spread");
+        let result = monitor.send_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 500_000.0).await;
+        monitor.record_execution(entry, result);
+        // Refused before sending, so not a submission
+        let mut entry = JournalEntry::new(mint.to_string(), Pubkey::new_unique().to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+        monitor.begin_trade(&mut entry, "spread");
+        monitor.record_execution(entry, Err("Simulated net profit 0 below required profit 1".into()));
+
+        let entries = monitor.journal.load().unwrap();
+        let landing = entries[0].landing.as_ref().unwrap();
+        assert_eq!((landing.backend, landing.landed, entries[0].slot), (Backend::Rpc, true, 40));
+        assert!(entries[1].landing.is_none());
+        let metrics = monitor.landing.lock().unwrap().metrics();
+        assert!(metrics.contains("arb_transactions_sent_total{backend=\"rpc\"} 1"));
+        assert!(metrics.contains("arb_transactions_landed_total{backend=\"rpc\"} 1"));
+        let report = PerformanceReport::for_day(&entries, entries[0].timestamp / SECONDS_PER_DAY).render();
+        assert!(report.contains("rpc: 1/1 landed (100.0%)"));
+        std::fs::remove_file(&journal_path).ok();
+    }
+
+    #[test]
+    fn network_registry_holds_each_clusters_addresses() {
+        assert_eq!("localnet".parse::<Network>(), Ok(Network::Localnet));
+        assert_eq!(Network::Localnet.genesis_hash(), None);
+        assert_eq!(Network::Localnet.addresses().raydium_amm, network::MAINNET.raydium_amm);
+        // The AMM authority is the program's PDA on every cluster
+        for addresses in [&network::MAINNET, &network::DEVNET] {
+            let program = Pubkey::from_str(addresses.raydium_amm).unwrap();
+            let (authority, _) = Pubkey::find_program_address(&[b"amm authority"], &program);
+            assert_eq!(authority.to_string(), addresses.raydium_amm_authority);
+        }
+        assert_ne!(network::DEVNET.pyth_sol_usd, network::MAINNET.pyth_sol_usd);
+        assert!(network::DEVNET.jito_tip_accounts.is_empty());
+        // The bot signs for the program at the same id on every cluster
+        assert_eq!(network::DEVNET.program, client::PROGRAM_ID);
+        // Nothing selected in tests, so lookups resolve to mainnet
+        assert_eq!(network::addresses().usdc, network::MAINNET.usdc);
+    }
+
+    #[test]
+    fn journal_entries_record_the_config_profile() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let journal_path = std::env::temp_dir().join(format!("profiles-{}.jsonl", std::process::id()));
+        monitor.journal = TradeJournal::new(&journal_path);
+        let record = |monitor: &ArbitrageMonitor| {
+            let entry = JournalEntry::new(Pubkey::new_unique().to_string(), Pubkey::new_unique().to_string(), LOAN_AMOUNT, TradeStatus::Failed);
+            monitor.record_execution(entry, Err("Simulated net profit 0 below required profit 1".into()));
+        };
+        record(&monitor);
+        monitor.config_profile = Some("conservative".to_string());
+        record(&monitor);
+        record(&monitor);
+
+        let entries = monitor.journal.load().unwrap();
+        std::fs::remove_file(&journal_path).ok();
+        assert_eq!(entries[0].config_profile, None);
+        assert_eq!(entries[2].config_profile.as_deref(), Some("conservative"));
+        let report = PerformanceReport::for_day(&entries, entries[0].timestamp / SECONDS_PER_DAY).render();
+        assert!(report.contains("conservative: 2 attempts"));
+
+        assert!(config_profile::settings("aggressive").unwrap().contains(&("EXECUTION_POLICY", "immediate")));
+        assert!(config_profile::settings("reckless").is_err());
+    }
+
+    #[test]
+    fn profits_in_different_tokens_add_up_in_usd() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let (usdc, usdc_feed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        let mint = spl_token::state::Mint {
+            decimals: 6,
+            is_initialized: true,
+            ..Default::default()
+        };
+        spl_token::state::Mint::pack(mint, &mut data).unwrap();
+        rpc.set_account(usdc, Account { data, ..system_account(1) });
+        rpc.set_account(usdc_feed, pyth_price_account(100_000_000, -8));
+        monitor.usd_feeds.insert(usdc, usdc_feed);
+
+        // 0.1 SOL from a pair at $150, and 5 USDC from a route starting in USDC
+        let mut pair = JournalEntry::new(Pubkey::new_unique().to_string(), String::new(), LOAN_AMOUNT, TradeStatus::Executed);
+        pair.expected_profit_lamports = 100_000_000;
+        let mut route = JournalEntry::new(usdc.to_string(), String::new(), LOAN_AMOUNT, TradeStatus::Executed);
+        route.route = Some("usdc-route".to_string());
+        route.expected_profit_lamports = 5_000_000;
+        route.costs.base_fee_lamports = 10_000_000;
+        // Nothing prices this one
+        let mut unpriced = route.clone();
+        unpriced.token_a = Pubkey::new_unique().to_string();
+        for entry in [&mut pair, &mut route, &mut unpriced] {
+            monitor.value_in_usd(entry);
+        }
+        assert_eq!((pair.sol_price, pair.profit_mint_usd, pair.profit_mint_decimals), (150.0, Some(150.0), Some(9)));
+        assert_eq!((route.profit_mint_usd, route.profit_mint_decimals), (Some(1.0), Some(6)));
+        assert_eq!(unpriced.profit_mint_usd, None);
+
+        let day = pair.timestamp / SECONDS_PER_DAY;
+        let report = PerformanceReport::for_day(&[pair, route, unpriced], day);
+        assert!((report.gross_profit_usd - 20.0).abs() < 1e-9);
+        // Less the route's 0.01 SOL fee
+        assert!((report.net_profit_usd - 18.5).abs() < 1e-9);
+        assert_eq!(report.unpriced, 1);
+        assert_eq!(report.pairs.len(), 2);
+        assert!((report.pairs[1].1 - 3.5).abs() < 1e-9);
+        assert!(report.render().contains("Net P&L: $18.50"));
+    }
+
+    #[tokio::test]
+    async fn pair_performance_is_served_live_by_the_control_api() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let monitor = monitor(&rpc);
+        let control = Control::new(None, TokenLists::default(), Arc::clone(&monitor.pair_analytics), Arc::default());
+        let url = control::serve_local(Arc::new(control));
+        let analytics = || async {
+            let response = reqwest::get(format!("{}/analytics", url)).await.unwrap();
+            response.json::<serde_json::Value>().await.unwrap()
+        };
+        assert_eq!(analytics().await, serde_json::json!([]));
+
+        let mut entry = JournalEntry::new("A".to_string(), "B".to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+        entry.sol_price = 150.0;
+        entry.expected_profit_lamports = 100_000_000;
+        monitor.count_opportunity("A/B");
+        monitor.count_opportunity("A/B");
+        monitor.pair_analytics.lock().unwrap().observe(&entry);
+
+        let served = analytics().await;
+        assert_eq!(served[0]["pair"], "A/B");
+        assert_eq!(served[0]["opportunities"], 2);
+        assert_eq!(served[0]["executed"], 1);
+        assert_eq!(served[0]["win_rate"], 1.0);
+        assert_eq!(served[0]["avg_profit_slippage_bps"], serde_json::Value::Null);
+    }
+
+    #[test]
+    fn notional_cap_is_split_by_edge_and_hit_rate() {
+        let trades = |pair: &'static str, count: usize, winners: usize, profit_lamports: i64| {
+            (0..count).map(move |index| {
+                let mut entry = JournalEntry::new(pair.to_string(), "B".to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+                entry.sol_price = 100.0;
+                entry.expected_profit_lamports = if index < winners { profit_lamports } else { 0 };
+                entry
+            })
+        };
+        // A earns $1 on every trade, C $1 on half of them, and D has too few trades to judge
+        let entries: Vec<JournalEntry> = trades("A", 10, 10, 10_000_000)
+            .chain(trades("C", 10, 5, 10_000_000))
+            .chain(trades("D", 3, 3, 100_000_000))
+            .collect();
+        let analytics = PairAnalytics::from_entries(&entries);
+        let config = AllocationConfig {
+            total_lamports: 10 * LOAN_AMOUNT,
+            lookback_days: 7,
+            exploration_floor: 0.05,
+            min_attempts: 10,
+        };
+        let pairs: Vec<(String, u64)> = ["A/B", "C/B", "D/B"].iter().map(|key| (key.to_string(), 10 * LOAN_AMOUNT)).collect();
+        let allocation = allocation::allocate(&config, &pairs, &analytics);
+        let floor = LOAN_AMOUNT / 2;
+        assert_eq!(allocation.cap("D/B"), floor);
+        // A weighs $1 x 100%, C $0.50 x 50%: the 8.5 SOL above the floors go 4:1
+        assert_eq!(allocation.cap("A/B"), floor + LOAN_AMOUNT * 68 / 10);
+        assert_eq!(allocation.cap("C/B"), floor + LOAN_AMOUNT * 17 / 10);
+        assert_eq!(allocation.cap("new/B"), floor);
+
+        // A pair's own loan amount bounds its cap, and the rest goes to the others
+        let mut pairs = pairs;
+        pairs[0].1 = 2 * LOAN_AMOUNT;
+        let allocation = allocation::allocate(&config, &pairs, &analytics);
+        assert_eq!(allocation.cap("A/B"), 2 * LOAN_AMOUNT);
+        assert_eq!(allocation.cap("C/B"), 10 * LOAN_AMOUNT - 2 * LOAN_AMOUNT - floor);
+
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let pair_key = format!("{}/{}", monitor.token_pairs[0].token_a, monitor.token_pairs[0].token_b);
+        assert_eq!(monitor.loan_amount_for(&monitor.token_pairs[0]), monitor.token_pairs[0].loan_amount);
+        monitor.allocation = Some(Allocation {
+            caps: [(pair_key, 1_000)].into_iter().collect(),
+            floor: 0,
+        });
+        assert_eq!(monitor.loan_amount_for(&monitor.token_pairs[0]), 1_000);
+    }
+
+    #[test]
+    fn risk_metrics_come_from_the_journal_window() {
+        let start = 1_000 * SECONDS_PER_DAY;
+        let entry = |day: u64, status, profit_lamports, cost_lamports| {
+            let mut entry = JournalEntry::new(Pubkey::new_unique().to_string(), String::new(), LOAN_AMOUNT, status);
+            entry.timestamp = start + day * SECONDS_PER_DAY + 100;
+            entry.sol_price = 100.0;
+            entry.expected_profit_lamports = profit_lamports;
+            entry.costs.base_fee_lamports = cost_lamports;
+            entry
+        };
+        let mut unpriced = entry(2, TradeStatus::Failed, 0, 900_000_000);
+        unpriced.sol_price = 0.0;
+        let entries = [
+            // A $100 loss that has left the 30 day window
+            entry(0, TradeStatus::Failed, 0, 1_000_000_000),
+            // +$10 and -$4 on one day, nothing the next, then -$8 and +$3
+            entry(40, TradeStatus::Executed, 100_000_000, 0),
+            entry(40, TradeStatus::Failed, 0, 40_000_000),
+            entry(42, TradeStatus::Failed, 0, 80_000_000),
+            unpriced,
+            entry(42, TradeStatus::Executed, 30_000_000, 0),
+        ];
+        let risk = RiskTracker::from_entries(&RiskConfig { window_days: 30 }, &entries)
+            .metrics_at(start + 42 * SECONDS_PER_DAY + 1_000);
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-6;
+        assert!(close(risk.max_drawdown_usd, 12.0));
+        assert!(close(risk.current_drawdown_usd, 9.0));
+        assert!(close(risk.largest_loss_usd, 8.0));
+        assert!(close(risk.fee_to_profit_ratio.unwrap(), 12.0 / 13.0));
+        // Daily P&L of 6, 0 and -5
+        assert_eq!(risk.days, 3);
+        assert!(close(risk.daily_pnl_variance, 546.0 / 27.0));
+        assert!(risk.metrics().contains("# TYPE arb_risk_daily_pnl_variance gauge\narb_risk_daily_pnl_variance 20."));
+
+        let mut report = PerformanceReport::for_day(&entries, 1_042);
+        report.risk = Some(risk);
+        assert!(report.render().contains("Max drawdown: $12.00 (current $9.00)"));
+    }
+
+    #[test]
+    fn inventory_skips_a_leg_within_its_limits() {
+        let path = std::env::temp_dir().join(format!("inventory-{}.json", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let config = InventoryConfig {
+            target_lamports: 2 * LOAN_AMOUNT,
+            max_lamports: 3 * LOAN_AMOUNT,
+            rebalance_secs: 3600,
+            path: path.clone(),
+        };
+        let mut book = InventoryBook::load(config.clone()).unwrap();
+        let pair = format!("{}/{}", costs::WSOL_MINT, Pubkey::new_unique());
+
+        // Buys up to the target, then sells down while at it
+        assert_eq!(book.plan(&pair, LOAN_AMOUNT, 500), Some(InventorySide::Buy));
+        let bought = book.buy(&pair, 500, LOAN_AMOUNT);
+        assert_eq!(bought.profit_from(-(LOAN_AMOUNT as i64)), 0);
+        book.buy(&pair, 600, LOAN_AMOUNT);
+        assert_eq!(book.plan(&pair, LOAN_AMOUNT, 500), Some(InventorySide::Sell));
+        let sold = book.sell(&pair, 550);
+        assert_eq!(sold.cost_lamports, LOAN_AMOUNT);
+        assert_eq!(sold.profit_from(1_100_000_000), 100_000_000);
+        // Below the target it buys again, but not past the limit, and can't sell more than it holds
+        assert_eq!(book.plan(&pair, LOAN_AMOUNT, 500), Some(InventorySide::Buy));
+        assert_eq!(book.plan(&pair, 3 * LOAN_AMOUNT, 500), Some(InventorySide::Sell));
+        assert_eq!(book.plan(&pair, 3 * LOAN_AMOUNT, 2_000), None);
+
+        // Only what's held beyond the target is rebalanced, and the book survives a restart
+        book.buy(&pair, 500, LOAN_AMOUNT);
+        book.buy(&pair, 525, LOAN_AMOUNT / 2);
+        assert_eq!(book.excess(), vec![(pair.clone(), 315)]);
+        book.save().unwrap();
+        let reloaded = InventoryBook::load(config).unwrap();
+        assert_eq!(reloaded.position(&pair), book.position(&pair));
+        let _ = std::fs::remove_file(&path);
+
+        // Inventory trades never reach the program, so reconciliation doesn't look for them there
+        let mut entry = JournalEntry::new(costs::WSOL_MINT.to_string(), Pubkey::new_unique().to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+        entry.signature = Some(Signature::new_unique().to_string());
+        entry.inventory = Some(sold);
+        assert!(reconcile::reconcile(&[entry], &[], 0).is_empty());
+    }
+
+    #[test]
+    fn dust_beyond_held_inventory_is_swept_when_worth_the_fee() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let wallet = Pubkey::new_unique();
+        let (dusty, held, empty) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let wsol = Pubkey::from_str(costs::WSOL_MINT).unwrap();
+        for (mint, amount) in [(dusty, 700), (held, 1_000), (wsol, 5_000)] {
+            rpc.set_account(mint, mint_account(6));
+            rpc.set_account(
+                anchor_spl::associated_token::get_associated_token_address(&wallet, &mint),
+                token_account(&mint, &wallet, amount),
+            );
+        }
+        rpc.set_account(empty, mint_account(6));
+        // Inventory holds most of one balance; SOL itself and mints without an account aren't dust
+        let mints = BTreeSet::from([dusty, held, empty, wsol]);
+        let balances = dust::dust_balances(&*rpc, &wallet, &mints, &HashMap::from([(held, 900)])).unwrap();
+        assert_eq!(balances.len(), 2);
+        assert!(balances.contains(&(dusty, 700)) && balances.contains(&(held, 100)));
+
+        let config = DustConfig {
+            threshold_lamports: 10_000_000,
+            max_fee_ratio: 0.05,
+            interval_secs: 3600,
+            target_mint: costs::WSOL_MINT.to_string(),
+        };
+        assert!(config.worth_sweeping(20_000_000, 1_000_000));
+        assert!(!config.worth_sweeping(20_000_000, 1_000_001));
+        assert!(!config.worth_sweeping(9_000_000, 5_000));
+
+        // Sweeps are journaled under their own key, with the proceeds as their profit
+        let mut entry = JournalEntry::new(dusty.to_string(), network::MAINNET.usdc.to_string(), 0, TradeStatus::Executed);
+        entry.sweep = Some(DustSweep {
+            amount: 700,
+            quoted_out: 12_500_000,
+        });
+        entry.expected_profit_lamports = 12_500_000;
+        entry.profit_mint_usd = Some(1.0);
+        entry.profit_mint_decimals = Some(6);
+        assert_eq!(entry.pair_key(), dust::SWEEP_KEY);
+        assert_eq!(entry.profit_mint(), network::MAINNET.usdc);
+        assert_eq!(entry.gross_profit_usd(), Some(12.5));
+    }
+
+    #[test]
+    fn pair_performance_is_tracked_per_pair() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let monitor = monitor(&rpc);
+        let entry = |status, expected_profit, fee, slippage| {
+            let mut entry = JournalEntry::new("A".to_string(), "B".to_string(), LOAN_AMOUNT, status);
+            entry.sol_price = 150.0;
+            entry.expected_profit_lamports = expected_profit;
+            entry.costs.base_fee_lamports = fee;
+            entry.profit_slippage_bps = slippage;
+            entry
+        };
+        // A winner, a trade that only paid its fee and a failed attempt, out of three quotes
+        let entries = [
+            entry(TradeStatus::Executed, 100_000_000, 5_000, Some(100)),
+            entry(TradeStatus::Executed, 0, 10_000_000, Some(300)),
+            entry(TradeStatus::Failed, 0, 5_000, None),
+        ];
+        for entry in &entries {
+            monitor.count_opportunity("A/B");
+            monitor.pair_analytics.lock().unwrap().observe(entry);
+        }
+
+        let analytics = monitor.pair_analytics.lock().unwrap();
+        let stats = analytics.get("A/B").unwrap();
+        assert_eq!((stats.opportunities, stats.attempts, stats.executed), (Some(3), 3, 2));
+        assert_eq!(stats.win_rate(), 0.5);
+        assert!((stats.avg_realized_profit_usd().unwrap() - (15.0 - 0.00075 - 1.5) / 2.0).abs() < 1e-9);
+        assert_eq!(stats.avg_profit_slippage_bps(), Some(200.0));
+        assert_eq!(stats.fee_spend_lamports, 10_010_000);
+        let metrics = analytics.metrics();
+        assert!(metrics.contains("arb_pair_opportunities_total{pair=\"A/B\"} 3\n"));
+        assert!(metrics.contains("arb_pair_win_rate{pair=\"A/B\"} 0.5\n"));
+
+        // The daily report has the same figures, with the quotes the monitor counted that day
+        let mut report = PerformanceReport::for_day(&entries, entries[0].timestamp / SECONDS_PER_DAY);
+        assert_eq!(report.pair_performance.get("A/B").unwrap().opportunities, None);
+        report.pair_performance.set_opportunities(&monitor.day_opportunities.lock().unwrap());
+        assert_eq!(report.pair_performance.get("A/B"), Some(stats));
+        assert!(report
+            .render()
+            .contains("A/B: 3 opportunities, 2/3 executed, 50.0% won, avg profit $6.75, avg slippage 200 bps"));
+    }
+
+    #[test]
+    fn journal_is_reconciled_against_the_chain() {
+        let event = |net_profit| events::ArbExecutedEvent {
+            token_a: costs::WSOL_MINT.to_string(),
+            token_b: Pubkey::new_unique().to_string(),
+            route: Vec::new(),
+            amount_in: LOAN_AMOUNT,
+            amount_out: LOAN_AMOUNT + net_profit,
+            flash_loan_fee: 0,
+            dex_fees: 0,
+            net_profit,
+            profit_fee: 0,
+            slot: 100,
+        };
+        let on_chain = |signature: &str, error: Option<&str>, event| reconcile::OnChainTrade {
+            signature: signature.to_string(),
+            slot: 100,
+            block_time: Some(2_000),
+            fee_payer: None,
+            error: error.map(str::to_string),
+            logs: Vec::new(),
+            event,
+            receipt: None,
+        };
+        let journaled = |signature: &str, status, realized, timestamp| {
+            let mut entry = JournalEntry::new(costs::WSOL_MINT.to_string(), String::new(), LOAN_AMOUNT, status);
+            entry.signature = Some(signature.to_string());
+            entry.realized_profit_lamports = realized;
+            entry.timestamp = timestamp;
+            entry
+        };
+        let entries = [
+            journaled("matched", TradeStatus::Executed, Some(1_000), 2_000),
+            journaled("reverted", TradeStatus::Executed, Some(1_000), 2_000),
+            journaled("unreceipted", TradeStatus::Executed, None, 2_000),
+            journaled("dropped", TradeStatus::Executed, None, 2_000),
+            // Older than the walked history
+            journaled("old", TradeStatus::Executed, None, 500),
+        ];
+        let trades = [
+            on_chain("matched", None, Some(event(1_000))),
+            on_chain("reverted", Some("custom program error: 0x1783"), None),
+            on_chain("unreceipted", None, Some(event(500))),
+            on_chain("crashed", None, Some(event(700))),
+        ];
+
+        let discrepancies = reconcile::reconcile(&entries, &trades, 1_000);
+        let described: Vec<String> = discrepancies.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            described,
+            [
+                "reverted journaled as Executed but failed on chain: custom program error: 0x1783",
+                "unreceipted journaled with realized profit none but the program reported 500",
+                "unrecorded trade crashed in slot 100",
+                "dropped journaled as executed but not among the wallet's program transactions",
+            ]
+        );
+        // The crashed trade is journaled from its event
+        let recovered = trades[3].to_journal_entry();
+        assert_eq!(recovered.status, TradeStatus::Executed);
+        assert_eq!(recovered.realized_profit_lamports, Some(700));
+        assert_eq!(recovered.timestamp, 2_000);
+
+        // A history import keeps the trades with events, and the failed ones the wallet paid for
+        let wallet = Pubkey::new_unique();
+        let mut history = trades.to_vec();
+        history[1].fee_payer = Some(wallet);
+        history.push(on_chain("someone else's", Some("custom program error: 0x1783"), None));
+        let imported: Vec<String> = import_history::importable(history, Some(&wallet))
+            .into_iter()
+            .map(|trade| trade.signature)
+            .collect();
+        assert_eq!(imported, ["matched", "reverted", "unreceipted", "crashed"]);
+        let failed = trades[1].to_journal_entry();
+        assert_eq!(failed.status, TradeStatus::Failed);
+        assert!(failed.failure_class.is_some());
+    }
+
+    #[test]
+    fn realized_profit_well_short_of_expected_is_alerted() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        monitor.profit_alert_slippage_bps = Some(2_000);
+        let mut entry = JournalEntry::new(costs::WSOL_MINT.to_string(), String::new(), LOAN_AMOUNT, TradeStatus::Executed);
+        entry.expected_profit_lamports = 1_000_000;
+
+        entry.realized_profit_lamports = Some(900_000);
+        monitor.verify_profit(&mut entry);
+        assert_eq!(entry.profit_slippage_bps, Some(1_000));
+        // Beating the expectation is negative slippage
+        entry.realized_profit_lamports = Some(1_100_000);
+        monitor.verify_profit(&mut entry);
+        assert_eq!(entry.profit_slippage_bps, Some(-1_000));
+        assert!(monitor.profit_alerts.lock().unwrap().is_empty());
+
+        entry.realized_profit_lamports = Some(700_000);
+        monitor.verify_profit(&mut entry);
+        assert_eq!(entry.profit_slippage_bps, Some(3_000));
+        let alerts = monitor.profit_alerts.lock().unwrap();
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].contains("30.00% short"));
+        // Profit is measured in the route's start mint, and in WSOL for a pair
+        assert_eq!(entry.profit_mint(), costs::WSOL_MINT);
+        entry.token_a = Pubkey::new_unique().to_string();
+        entry.route = Some("route".to_string());
+        assert_eq!(entry.profit_mint(), entry.token_a);
+    }
+
+    #[test]
+    fn trades_unlikely_to_land_are_skipped() {
+        // A quarter of the sends bidding around 1000 lamports landed, all of those around 100000
+        let sends = (0..40).map(|index| {
+            let mut entry = JournalEntry::new(String::new(), String::new(), LOAN_AMOUNT, TradeStatus::Executed);
+            let bid = if index < 20 { 1_000 } else { 100_000 };
+            entry.landing = Some(Landing {
+                backend: Backend::Rpc,
+                priority_fee_lamports: bid,
+                tip_lamports: 0,
+                landed: !(5..20).contains(&index),
+                landed_slot: None,
+                slot_delay: None,
+            });
+            entry
+        });
+        let stats = LandingStats::from_entries(&sends.collect::<Vec<_>>());
+        assert_eq!(stats.landing_probability(Backend::Rpc, 1_500), Some(0.25));
+        assert_eq!(stats.landing_probability(Backend::Rpc, 100_000), Some(1.0));
+        // No sends near this bid, so all of them
+        assert_eq!(stats.landing_probability(Backend::Rpc, 10_000), Some(0.625));
+        assert_eq!(stats.landing_probability(Backend::Jito, 1_000), None);
+
+        // Clears its required profit by a lamport
+        let mut params = EvaluatorParams {
+            failure_cost: 10_000_000,
+            ..EvaluatorParams::default()
+        };
+        let final_amount = LOAN_AMOUNT + 6_000_001;
+        assert!(evaluator::evaluate_route(LOAN_AMOUNT, final_amount, 150.0, &params).is_executable());
+        params.landing_probability = 0.5;
+        let evaluation = evaluator::evaluate_route(LOAN_AMOUNT, final_amount, 150.0, &params);
+        assert!(evaluation.potential_profit > evaluation.required_profit);
+        assert!(!evaluation.is_executable());
+    }
+
+    #[test]
+    fn profit_thresholds_can_be_absolute() {
+        assert_eq!("0.5".parse::<ProfitThreshold>(), Ok(ProfitThreshold::Percent(0.5)));
+        assert_eq!("0.3%".parse::<ProfitThreshold>(), Ok(ProfitThreshold::Percent(0.3)));
+        assert_eq!("5000000 lamports".parse::<ProfitThreshold>(), Ok(ProfitThreshold::Lamports(5_000_000)));
+        assert_eq!("$2".parse::<ProfitThreshold>(), Ok(ProfitThreshold::Usd(2.0)));
+        assert!("2 SOL".parse::<ProfitThreshold>().is_err());
+
+        // Stored percentages are still plain numbers
+        let mut pair = stored_pair(LOAN_AMOUNT);
+        let mut json = serde_json::to_value(&pair).unwrap();
+        json["min_profit_threshold"] = serde_json::json!(1.5);
+        pair = serde_json::from_value(json).unwrap();
+        assert_eq!(pair.min_profit_threshold, Some(ProfitThreshold::Percent(1.5)));
+        pair.min_profit_threshold = Some(ProfitThreshold::Usd(2.0));
+        let json = serde_json::to_value(&pair).unwrap();
+        assert_eq!(json["min_profit_threshold"], "$2");
+        assert_eq!(serde_json::from_value::<StoredPair>(json).unwrap().min_profit_threshold, pair.min_profit_threshold);
+
+        // A percentage scales with the notional; $1 at $100/SOL is 0.01 SOL at any size
+        let threshold_cost = |threshold, loan_amount| {
+            let params = EvaluatorParams {
+                min_profit_threshold: threshold,
+                ..EvaluatorParams::default()
+            };
+            evaluator::evaluate_route(loan_amount, loan_amount, 100.0, &params).threshold_cost
+        };
+        assert_eq!(threshold_cost(ProfitThreshold::Percent(1.0), LOAN_AMOUNT), LOAN_AMOUNT as f64 / 100.0);
+        assert_eq!(threshold_cost(ProfitThreshold::Percent(1.0), LOAN_AMOUNT * 10), LOAN_AMOUNT as f64 / 10.0);
+        for loan_amount in [LOAN_AMOUNT, LOAN_AMOUNT * 10] {
+            assert_eq!(threshold_cost(ProfitThreshold::Usd(1.0), loan_amount), 10_000_000.0);
+            assert_eq!(threshold_cost(ProfitThreshold::Lamports(2_000_000), loan_amount), 2_000_000.0);
+        }
+    }
+
+    #[tokio::test]
+    async fn queued_trades_share_one_bundle_and_tip() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let journal_path = std::env::temp_dir().join(format!("bundled-trades-{}.jsonl", std::process::id()));
+        monitor.journal = TradeJournal::new(&journal_path);
+        let mut jito = JitoConfig::new(String::new(), String::new(), TipPolicy::from_env());
+        jito.bundle_trades = true;
+        monitor.private_submission = Some(jito);
+
+        for gain in [1_000_000, 2_000_000] {
+            let mint = Pubkey::new_unique();
+            script_trade(&rpc, &monitor.wallet.pubkey(), &mint, gain);
+            let mut entry = JournalEntry::new(mint.to_string(), Pubkey::new_unique().to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+            monitor.begin_trade(&mut entry, "spread");
+            let (instructions, simulated_profit) = monitor
+                .prepare_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 500_000.0)
+                .unwrap();
+            let trade = PreparedTrade {
+                instructions,
+                simulated_profit,
+                contested: HashSet::new(),
+            };
+            monitor.record_or_queue(entry, Ok(Execution::Queued(trade)));
+        }
+        assert!(rpc.sent().is_empty());
+
+        monitor.send_bundles().await;
+        let sent = rpc.sent();
+        let tips = |transaction: &solana_sdk::transaction::Transaction| {
+            let keys = &transaction.message.account_keys;
+            transaction
+                .message
+                .instructions
+                .iter()
+                .filter(|instruction| keys[instruction.program_id_index as usize] == solana_sdk::system_program::id())
+                .count()
+        };
+        // One bundle, tipping once on its last transaction
+        assert_eq!(sent.iter().map(tips).collect::<Vec<_>>(), [0, 1]);
+        let entries = monitor.journal.load().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert!(entries.iter().all(|entry| entry.status == TradeStatus::Executed && entry.signature.is_some()));
+        // The more profitable trade goes first
+        assert_eq!(entries[0].signature, Some(sent[0].signatures[0].to_string()));
+        std::fs::remove_file(&journal_path).ok();
+
+        // A trade writing a pool another trade in the bundle writes waits for the next one
+        let (pool, other) = (HashSet::from([Pubkey::new_unique()]), HashSet::new());
+        assert_eq!(jito::pack_bundles(&[&pool, &other, &pool]), [vec![0, 1], vec![2]]);
+    }
+
+    #[tokio::test]
+    async fn queued_trades_go_out_alone_without_a_block_engine() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let journal_path = std::env::temp_dir().join(format!("unbundled-trades-{}.jsonl", std::process::id()));
+        monitor.journal = TradeJournal::new(&journal_path);
+        monitor.private_submission = None;
+
+        let mint = Pubkey::new_unique();
+        script_trade(&rpc, &monitor.wallet.pubkey(), &mint, 1_000_000);
+        let mut entry = JournalEntry::new(mint.to_string(), Pubkey::new_unique().to_string(), LOAN_AMOUNT, TradeStatus::Executed);
+        monitor.begin_trade(&mut entry, "spread");
+        let (instructions, simulated_profit) = monitor
+            .prepare_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 500_000.0)
+            .unwrap();
+        let trade = PreparedTrade {
+            instructions,
+            simulated_profit,
+            contested: HashSet::new(),
+        };
+        monitor.record_or_queue(entry, Ok(Execution::Queued(trade)));
+
+        // The queue is sent through the RPC rather than dropped
+        monitor.send_bundles().await;
+        let sent = rpc.sent();
+        assert_eq!(sent.len(), 1);
+        let entries = monitor.journal.load().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].signature, Some(sent[0].signatures[0].to_string()));
+        assert!(monitor.bundle_queue.lock().unwrap().is_empty());
+        std::fs::remove_file(&journal_path).ok();
+    }
+
+    #[test]
+    fn shred_writes_to_watched_pools_wake_the_monitor() {
+        let watcher = ShredWatcher::new();
+        let pool = Pubkey::new_unique();
+        watcher.watch(&[pool]);
+        let entries = |pool_meta: solana_sdk::instruction::AccountMeta| {
+            let instruction = Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: vec![pool_meta, solana_sdk::instruction::AccountMeta::new(Pubkey::new_unique(), false)],
+                data: Vec::new(),
+            };
+            let message = solana_sdk::message::Message::new(&[instruction], Some(&Pubkey::new_unique()));
+            let entry = solana_entry::entry::Entry {
+                num_hashes: 1,
+                hash: solana_sdk::hash::Hash::default(),
+                transactions: vec![solana_sdk::transaction::Transaction::new_unsigned(message).into()],
+            };
+            bincode::serialize(&vec![entry]).unwrap()
+        };
+
+        // Reading the pool isn't swapping through it
+        watcher.observe(7, &entries(solana_sdk::instruction::AccountMeta::new_readonly(pool, false))).unwrap();
+        assert!(watcher.take_touched().is_empty());
+        watcher.observe(8, &entries(solana_sdk::instruction::AccountMeta::new(pool, false))).unwrap();
+        assert_eq!(watcher.take_touched(), HashMap::from([(pool, 8)]));
+        // The write left a wake-up for the pause between cycles
+        tokio::runtime::Runtime::new().unwrap().block_on(async {
+            tokio::time::timeout(Duration::from_secs(1), watcher.woken()).await.unwrap();
+        });
+    }
+
+    #[test]
+    fn pairs_are_reevaluated_only_once_their_accounts_change() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        monitor.event_driven = true;
+        let vaults = monitor.token_pairs[0].quote_vaults.clone();
+        let owner = Pubkey::new_unique();
+        for vault in &vaults {
+            rpc.set_account(*vault, token_account(&Pubkey::new_unique(), &owner, 1_000));
+        }
+        let changed_vaults = |monitor: &ArbitrageMonitor| {
+            let changed = monitor.changed_accounts(HashSet::new());
+            vaults.iter().filter(|vault| changed.contains(vault)).count()
+        };
+
+        // Never evaluated, so evaluated whether or not anything changed
+        assert!(monitor.needs_evaluation(&monitor.token_pairs[0]));
+        monitor.prefetch_accounts();
+        assert_eq!(changed_vaults(&monitor), 4);
+        monitor.token_pairs[0].evaluated.store(true, Ordering::Relaxed);
+        assert!(!monitor.needs_evaluation(&monitor.token_pairs[0]));
+
+        // Refetched unchanged, then one vault's balance moves
+        monitor.prefetch_accounts();
+        assert_eq!(changed_vaults(&monitor), 0);
+        rpc.set_account(vaults[2], token_account(&Pubkey::new_unique(), &owner, 2_000));
+        monitor.prefetch_accounts();
+        let changed = monitor.changed_accounts(HashSet::new());
+        assert!(monitor.token_pairs[0].priced_from().any(|account| changed.contains(account)));
+        assert_eq!(vaults.iter().filter(|vault| changed.contains(vault)).count(), 1);
+    }
+
+    #[test]
+    fn pairs_are_polled_on_their_tiers_interval() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        let config = PollConfig {
+            hot_interval: Duration::from_millis(200),
+            interval: Duration::from_secs(1),
+            long_tail_interval: Duration::from_secs(10),
+            long_tail_after_evaluations: 3,
+            jitter: 0.1,
+        };
+        let key = monitor.token_pairs[0].key();
+        assert_eq!(monitor.poll_tier(&monitor.token_pairs[0], &config), PollTier::Regular);
+        // Subscribed to through the shred stream
+        monitor.shreds = Some(Arc::new(ShredWatcher::new()));
+        assert_eq!(monitor.poll_tier(&monitor.token_pairs[0], &config), PollTier::Hot);
+        // Checked enough times without finding anything
+        for _ in 0..3 {
+            monitor.pair_tracker.lock().unwrap().observe(&key, false);
+        }
+        assert_eq!(monitor.poll_interval(&monitor.token_pairs[0], &config), Duration::from_secs(10));
+        monitor.token_pairs[0].poll_interval = Some(Duration::from_millis(500));
+        assert_eq!(monitor.poll_interval(&monitor.token_pairs[0], &config), Duration::from_millis(500));
+
+        let mut polls = PollScheduler::new(config);
+        let now = std::time::Instant::now();
+        assert!(polls.due(&key, now));
+        polls.schedule(&key, Duration::from_secs(10), now);
+        assert!(!polls.due(&key, now + Duration::from_secs(8)));
+        assert!(polls.due(&key, now + Duration::from_secs(11)));
+        // Jittered by at most a tenth either way
+        let wait = polls.until_next([key.as_str()], now);
+        assert!(wait >= Duration::from_secs(9) && wait <= Duration::from_secs(11));
+    }
+
+    #[test]
+    fn pairs_arbitraged_by_others_get_wider_thresholds_and_higher_fees() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let mut monitor = monitor(&rpc);
+        monitor.compute_units = std::sync::Mutex::new(ComputeUnitEstimator::new(ComputeConfig {
+            price_micro_lamports: 1_000,
+            margin: 0.1,
+            default_units: 400_000,
+        }));
+        monitor.competition = Some(std::sync::Mutex::new(CompetitionTracker::new(CompetitionConfig {
+            window: 4,
+            threshold_bump: 0.5,
+            fee_multiplier: 3.0,
+        })));
+        let watcher = Arc::new(ShredWatcher::new());
+        monitor.shreds = Some(Arc::clone(&watcher));
+        let pair = &monitor.token_pairs[0];
+        let pair_key = format!("{}/{}", pair.token_a, pair.token_b);
+        let vaults = pair.quote_vaults.clone();
+        watcher.watch(&vaults);
+        let entries = |signer: Pubkey, written: [Pubkey; 2]| {
+            let instruction = Instruction {
+                program_id: Pubkey::new_unique(),
+                accounts: written.iter().map(|vault| solana_sdk::instruction::AccountMeta::new(*vault, false)).collect(),
+                data: Vec::new(),
+            };
+            let message = solana_sdk::message::Message::new(&[instruction], Some(&signer));
+            let entry = solana_entry::entry::Entry {
+                num_hashes: 1,
+                hash: solana_sdk::hash::Hash::default(),
+                transactions: vec![solana_sdk::transaction::Transaction::new_unsigned(message).into()],
+            };
+            bincode::serialize(&vec![entry]).unwrap()
+        };
+
+        // Our own arbitrage and another wallet's swap through one of the pools aren't competition
+        watcher.observe(100, &entries(monitor.wallet.pubkey(), [vaults[0], vaults[2]])).unwrap();
+        watcher.observe(100, &entries(Pubkey::new_unique(), [vaults[0], vaults[1]])).unwrap();
+        watcher.observe(103, &entries(Pubkey::new_unique(), [vaults[1], vaults[3]])).unwrap();
+        monitor.take_shred_updates();
+        // The checks at 102 and 103 saw the arbitrage in 103
+        for slot in [100, 102, 103, 110] {
+            monitor.record_check(&pair_key, slot);
+        }
+
+        let competition = monitor.competition.as_ref().unwrap().lock().unwrap();
+        assert_eq!(competition.score(&pair_key), 0.5);
+        drop(competition);
+        let threshold = monitor.evaluator_params_for(&pair_key).min_profit_threshold.as_percent().unwrap();
+        assert!((threshold - (monitor.min_profit_threshold.as_percent().unwrap() + 0.25)).abs() < 1e-9);
+        let compute_units = monitor.compute_units.lock().unwrap();
+        assert_eq!(compute_units.price(&pair_key), 2_000);
+        assert!(compute_units
+            .budget_instructions(&pair_key)
+            .contains(&solana_sdk::compute_budget::ComputeBudgetInstruction::set_compute_unit_price(2_000)));
+    }
+
+    #[test]
+    fn sends_are_timed_to_the_next_jito_leader() {
+        let (jito, other) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // Slots 100-103 are led by a validator without the Jito client, 104 onwards by one with it
+        let schedule = LeaderSchedule {
+            first_slot: 100,
+            leaders: [vec![other; 4], vec![jito; 4]].concat(),
+            jito: [jito].into_iter().collect(),
+        };
+        let mut config = LeaderConfig {
+            lookahead_slots: 1,
+            max_wait_slots: 8,
+            validators_url: String::new(),
+        };
+
+        assert_eq!(schedule.plan(103, &config), Submission::Jito);
+        // Two slots until 104 falls within the lookahead
+        assert_eq!(
+            schedule.plan(101, &config),
+            Submission::WaitThenJito(Duration::from_millis(2 * solana_sdk::clock::DEFAULT_MS_PER_SLOT))
+        );
+        config.max_wait_slots = 2;
+        assert_eq!(schedule.plan(100, &config), Submission::Jito);
+    }
+
+    #[test]
+    fn bulk_reads_go_to_the_cheapest_endpoint() {
+        let (premium, cheap) = (Arc::new(MockRpc::new(1)), Arc::new(MockRpc::new(1)));
+        let account = Pubkey::new_unique();
+        cheap.set_account(account, mint_account(6));
+        let routed = RoutedRpc::new(vec![
+            ("premium".to_string(), premium.clone() as Arc<dyn RpcApi>, 10.0),
+            ("cheap".to_string(), cheap.clone() as Arc<dyn RpcApi>, 1.0),
+        ]);
+
+        assert_eq!(routed.get_account(&account).unwrap(), mint_account(6));
+        assert!(routed.get_multiple_accounts(&[account]).unwrap()[0].is_some());
+    }
+
+    #[test]
+    fn transient_rpc_errors_are_retried_within_the_budget() {
+        let policy = RetryPolicy {
+            retries: 3,
+            base_delay: Duration::ZERO,
+            max_delay: Duration::ZERO,
+        };
+        let budget = RetryBudget::new(1.0, 0.0);
+        let reset = || ClientError::from(std::io::Error::new(std::io::ErrorKind::ConnectionReset, "reset"));
+
+        let mut calls = 0;
+        let result = retry::rpc(&policy, &budget, "getSlot", || {
+            calls += 1;
+            if calls == 1 { Err(reset()) } else { Ok(7) }
+        });
+        assert_eq!((result.unwrap(), calls), (7, 2));
+
+        // The budget is spent, so the next transient failure is returned at once
+        calls = 0;
+        assert!(retry::rpc(&policy, &RetryBudget::new(0.0, 0.0), "getSlot", || {
+            calls += 1;
+            Err::<u64, _>(reset())
+        })
+        .is_err());
+        assert_eq!(calls, 1);
+        // As is an error about the request itself
+        assert!(!retry::is_retryable_rpc(&ClientErrorKind::Custom("AccountNotFound".to_string()).into()));
+    }
+
+    #[tokio::test]
+    async fn unprofitable_simulation_is_not_sent() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let monitor = monitor(&rpc);
+        let mint = Pubkey::new_unique();
+        script_trade(&rpc, &monitor.wallet.pubkey(), &mint, 1_000_000);
+
+        let result = monitor.send_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 2_000_000.0).await;
+        assert!(result.unwrap_err().to_string().contains("below required profit"));
+        assert!(rpc.sent().is_empty());
+    }
+
+    fn mint_account(decimals: u8) -> Account {
+        let mut data = vec![0; spl_token::state::Mint::LEN];
+        let mint = spl_token::state::Mint {
+            decimals,
+            is_initialized: true,
+            ..Default::default()
+        };
+        spl_token::state::Mint::pack(mint, &mut data).unwrap();
+        Account {
+            lamports: 1_461_600,
+            data,
+            owner: spl_token::id(),
+            executable: false,
+            rent_epoch: 0,
+        }
+    }
+
+    #[test]
+    fn withdrawal_keeps_the_retained_buffers() {
+        let rpc = MockRpc::new(1);
+        let wallet = Keypair::new();
+        let mint = Pubkey::new_unique();
+        rpc.set_account(wallet.pubkey(), system_account(3_000_000_000));
+        rpc.set_account(mint, mint_account(6));
+        rpc.set_account(
+            anchor_spl::associated_token::get_associated_token_address(&wallet.pubkey(), &mint),
+            token_account(&mint, &wallet.pubkey(), 5_000_000),
+        );
+        let config = withdraw::WithdrawConfig {
+            destination: Pubkey::new_unique(),
+            retain_lamports: 500_000_000,
+            retain: HashMap::from([(mint, 1_000_000)]),
+        };
+        let assets = [withdraw::Asset::Token(mint), withdraw::Asset::Sol];
+
+        let sweeps = withdraw::plan(&rpc, &wallet.pubkey(), &config, &assets, None).unwrap();
+        assert_eq!(sweeps[0].amount, 4_000_000);
+        // The destination's new token account and the fee stay behind with the SOL buffer
+        let rent = solana_sdk::rent::Rent::default().minimum_balance(165);
+        assert_eq!(sweeps[1].amount, 2_500_000_000 - rent - costs::LAMPORTS_PER_SIGNATURE);
+
+        withdraw::execute(&rpc, &wallet, &config.destination, &sweeps).unwrap();
+        // Create the destination's token account, transfer the tokens, transfer the SOL
+        assert_eq!(rpc.sent()[0].message.instructions.len(), 3);
+
+        // A partial amount is capped by what the buffer leaves
+        assert!(withdraw::plan(&rpc, &wallet.pubkey(), &config, &assets[..1], Some(4_500_000)).is_err());
+        let partial = withdraw::plan(&rpc, &wallet.pubkey(), &config, &assets[..1], Some(2_000_000)).unwrap();
+        assert_eq!(partial[0].amount, 2_000_000);
+    }
+
+    // The program's bundled IDL; every instruction the client builds has to match it
+    const IDL: &str = include_str!("../idl/arbitrage_contract.json");
+
+    #[test]
+    fn client_instructions_match_the_idl() {
+        let idl: serde_json::Value = serde_json::from_str(IDL).unwrap();
+        let program_id = client::program_id();
+        let key = Pubkey::new_unique();
+        let accounts = client::TryArbitrageAccounts {
+            user: key,
+            token_a_account: key,
+            vault: key,
+            token_a_mint: key,
+            associated_token_program: Some(anchor_spl::associated_token::ID),
+            token_program: spl_token::id(),
+            profit_destination: key,
+            fee_collector_account: key,
+            price_oracle: Some(key),
+        };
+        let config = client::ConfigParams {
+            operator: [0; 32],
+            paused: false,
+            max_trade_size: 0,
+            flash_loan_fee_bps: 0,
+            max_slippage_bps: 0,
+            fee_collector: [0; 32],
+            profit_fee_bps: 0,
+            allow_unlisted_jupiter_pools: false,
+            price_oracle: [0; 32],
+        };
+        let whitelist = client::WhitelistArgs {
+            programs: Vec::new(),
+            pools: Vec::new(),
+        };
+        let trade = client::TryArbitrageArgs {
+            amount: 0,
+            min_profit_lamports: 0,
+            expiry_slot: 0,
+            loan_provider: client::LoanProvider::Marginfi,
+            legs: Vec::new(),
+        };
+        let batch = client::TryArbitrageBatchArgs {
+            amount: 0,
+            min_profit_lamports: 0,
+            expiry_slot: 0,
+            loan_provider: client::LoanProvider::Marginfi,
+            routes: Vec::new(),
+        };
+        let built = [
+            ("initializeConfig", client::initialize_config(&program_id, &key, &config).unwrap()),
+            ("initializeWhitelist", client::initialize_whitelist(&program_id, &key, &whitelist).unwrap()),
+            ("tryArbitrage", client::try_arbitrage(&program_id, &accounts, &trade, Vec::new()).unwrap()),
+            ("tryArbitrageBatch", client::try_arbitrage_batch(&program_id, &accounts, &batch, Vec::new()).unwrap()),
+            (
+                "quote",
+                client::quote(&program_id, &[key; 4], &client::QuoteArgs { amount: 0, fee_bps: [0; 2] }).unwrap(),
+            ),
+        ];
+
+        for (name, instruction) in built {
+            let declared = idl["instructions"].as_array().unwrap().iter().find(|i| i["name"] == name).unwrap();
+            // Anchor hashes the snake_case name
+            let snake: String = name
+                .chars()
+                .flat_map(|c| if c.is_uppercase() { vec!['_', c.to_ascii_lowercase()] } else { vec![c] })
+                .collect();
+            assert_eq!(instruction.data[..8], client::sighash(&snake), "{} discriminator", name);
+            let metas = declared["accounts"].as_array().unwrap();
+            assert_eq!(instruction.accounts.len(), metas.len(), "{} account count", name);
+            for (meta, account) in instruction.accounts.iter().zip(metas) {
+                let flags = (account["isMut"].as_bool().unwrap(), account["isSigner"].as_bool().unwrap());
+                assert_eq!((meta.is_writable, meta.is_signer), flags, "{} account {}", name, account["name"]);
+            }
+        }
+    }
+
+    #[test]
+    fn snapshot_decodes_accounts_by_owner() {
+        let (mint, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        match snapshot::decode_account(&token_account(&mint, &owner, 42)) {
+            snapshot::AccountState::TokenAccount { mint: decoded, amount, .. } => {
+                assert_eq!(decoded, mint.to_string());
+                assert_eq!(amount, 42);
+            }
+            state => panic!("decoded as {:?}", state),
+        }
+        let price = snapshot::decode_account(&pyth_price_account(15_000_000_000, -8));
+        assert!(matches!(price, snapshot::AccountState::PythPrice { value, .. } if (value - 150.0).abs() < 1e-9));
+        assert!(matches!(snapshot::decode_account(&system_account(1)), snapshot::AccountState::Unknown));
+    }
+
+    #[test]
+    fn only_empty_unconfigured_accounts_are_closed() {
+        let (configured, old) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let account = |mint: Pubkey, amount: u64| balances::TokenAccount {
+            address: Pubkey::new_unique(),
+            mint,
+            amount,
+            lamports: 2_039_280,
+            token_program: spl_token::id(),
+        };
+        let accounts = vec![account(configured, 0), account(old, 0), account(old, 5)];
+        let closable = close_accounts::closable(accounts, &[configured].into_iter().collect());
+        assert_eq!(closable.len(), 1);
+        assert_eq!((closable[0].mint, closable[0].amount), (old, 0));
+    }
+
+    #[test]
+    fn rent_counts_only_accounts_left_open() {
+        let rpc = MockRpc::new(1);
+        let wallet = Pubkey::new_unique();
+        let (new_mint, held_mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let held = anchor_spl::associated_token::get_associated_token_address(&wallet, &held_mint);
+        rpc.set_account(held, token_account(&held_mint, &wallet, 0));
+        let stale = Pubkey::new_unique();
+        rpc.set_account(stale, Account { lamports: 2_039_280, ..token_account(&held_mint, &wallet, 0) });
+        let temporary = Pubkey::new_unique();
+        let create_ata = |mint: &Pubkey| {
+            anchor_spl::associated_token::spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &wallet,
+                &wallet,
+                mint,
+                &spl_token::id(),
+            )
+        };
+        let close = |account: &Pubkey| spl_token::instruction::close_account(&spl_token::id(), account, &wallet, &wallet, &[]).unwrap();
+
+        let instructions = vec![
+            // A new associated account locks its rent; an existing one costs nothing
+            create_ata(&new_mint),
+            create_ata(&held_mint),
+            // A temporary WSOL account closed in the same transaction costs nothing either
+            solana_sdk::system_instruction::create_account(&wallet, &temporary, 2_039_280, 165, &spl_token::id()),
+            close(&temporary),
+            // Closing an old account frees its rent
+            close(&stale),
+        ];
+        let rent = rent::account_rent(&rpc, &instructions).unwrap();
+        assert_eq!(rent.locked_lamports, solana_sdk::rent::Rent::default().minimum_balance(165));
+        assert_eq!(rent.reclaimed_lamports, 2_039_280);
+        assert_eq!(rent.net_lamports(), rent.locked_lamports as i64 - 2_039_280);
+
+        // Journaled rent shows up in the day's report
+        let mut entry = JournalEntry::new(new_mint.to_string(), String::new(), 0, TradeStatus::Executed);
+        entry.costs.rent_paid_lamports = rent.locked_lamports;
+        let report = PerformanceReport::for_day(&[entry.clone()], entry.timestamp / SECONDS_PER_DAY);
+        assert_eq!(report.rent_locked_lamports, rent.locked_lamports);
+        assert!(report.render().contains("Rent: 0.00203928 SOL locked, 0 SOL reclaimed"));
+    }
+
+    #[test]
+    fn prefetched_accounts_are_read_from_the_batch() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let monitor = monitor(&rpc);
+        monitor.prefetch_accounts();
+
+        // A later change on chain isn't seen until the next cycle's batch
+        rpc.set_account(monitor.sol_price_account, pyth_price_account(16_000_000_000, -8));
+        assert_eq!(monitor.get_pyth_price_usd(&monitor.sol_price_account).unwrap(), 150.0);
+        monitor.prefetch_accounts();
+        assert_eq!(monitor.get_pyth_price_usd(&monitor.sol_price_account).unwrap(), 160.0);
+    }
+
+    #[test]
+    fn account_cache_keeps_the_latest_slot_and_refetches_stale_entries() {
+        let rpc = Arc::new(MockRpc::new(10));
+        let feed = Pubkey::new_unique();
+        rpc.set_account(feed, pyth_price_account(15_000_000_000, -8));
+        let config = |ttl| CacheConfig {
+            ttl,
+            subscribed_ttl: ttl,
+            ws_url: None,
+        };
+
+        // A notification from before the batch's slot doesn't replace it
+        let cache = AccountCache::new(rpc.clone(), config(Duration::from_secs(60)));
+        cache.prefetch(&[feed]).unwrap();
+        cache.store(feed, Some(pyth_price_account(14_000_000_000, -8)), 9);
+        assert_eq!(cache.slot(&feed), Some(10));
+        assert_eq!(cache.get_account(&feed).unwrap(), pyth_price_account(15_000_000_000, -8));
+
+        // Past its TTL the entry is refetched instead of served
+        let cache = AccountCache::new(rpc.clone(), config(Duration::ZERO));
+        cache.prefetch(&[feed]).unwrap();
+        rpc.set_account(feed, pyth_price_account(16_000_000_000, -8));
+        std::thread::sleep(Duration::from_millis(2));
+        assert_eq!(cache.get_account(&feed).unwrap(), pyth_price_account(16_000_000_000, -8));
+    }
+
+    #[tokio::test]
+    async fn failed_simulation_is_classified_from_its_logs() {
+        let rpc = Arc::new(MockRpc::new(1));
+        let monitor = monitor(&rpc);
+        let mint = Pubkey::new_unique();
+        // Leg2SlippageExceeded
+        rpc.push_simulation(simulation(
+            Some(TransactionError::InstructionError(1, InstructionError::Custom(6020))),
+            vec!["Program log: AnchorError occurred. Error Code: Leg2SlippageExceeded.".to_string()],
+        ));
+
+        let error = monitor
+            .send_verified("route", &[], trade_instruction(), &mint, LOAN_AMOUNT, 0.0).await
+            .unwrap_err();
+        let logs = failure::logs_from_error(error.as_ref());
+        assert_eq!(failure::classify(&error.to_string(), &logs), FailureClass::Slippage);
+        assert_eq!(failure::failed_leg(&error.to_string(), &logs), Some(2));
+        assert!(rpc.sent().is_empty());
+    }
+}
@@ -0,0 +1,157 @@
+use crate::client::sighash;
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar;
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+
+pub const PROGRAM_ID: &str = "MFv2hWf31Z9kbCa1snEPYctwafyvdvnV7FRRPwv1a7A";
+// Main marginfi lending group on mainnet
+const MAIN_GROUP: &str = "4qp6Fx6tnZkY5Wropq9wUYgtFxXKwE6viZxFHg3rdAG8";
+
+// marginfi flash loans must be top-level instructions of the same transaction, so the bot wraps
+// the trade in start_flashloan / borrow / ... / repay / end_flashloan instead of the program CPI-ing
+#[derive(Debug, Clone)]
+pub struct MarginfiConfig {
+    pub program_id: Pubkey,
+    pub group: Pubkey,
+    // marginfi account owned by the wallet; it should hold no other positions, since the
+    // end_flashloan health check is sent without observation accounts
+    pub account: Pubkey,
+    // Bank lending each mint the bot borrows
+    pub banks: HashMap<Pubkey, Pubkey>,
+}
+
+impl MarginfiConfig {
+    // None unless MARGINFI_ACCOUNT is set
+    pub fn from_env() -> Option<Self> {
+        let account = env::var("MARGINFI_ACCOUNT").ok().filter(|v| !v.is_empty())?;
+        let banks = env::var("MARGINFI_BANKS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (mint, bank) = entry.split_once(':').expect("MARGINFI_BANKS entries must be <mint>:<bank>");
+                (
+                    Pubkey::from_str(mint.trim()).expect("Invalid mint in MARGINFI_BANKS"),
+                    Pubkey::from_str(bank.trim()).expect("Invalid bank in MARGINFI_BANKS"),
+                )
+            })
+            .collect();
+
+        Some(Self {
+            program_id: Pubkey::from_str(PROGRAM_ID).unwrap(),
+            group: env::var("MARGINFI_GROUP")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| Pubkey::from_str(&v).expect("Invalid MARGINFI_GROUP"))
+                .unwrap_or_else(|| Pubkey::from_str(MAIN_GROUP).unwrap()),
+            account: Pubkey::from_str(&account).expect("Invalid MARGINFI_ACCOUNT"),
+            banks,
+        })
+    }
+
+    // `inner` wrapped in a flash loan of `amount` of `mint` into the owner's associated token
+    // account. `first_index` is the position of the first returned instruction in the transaction,
+    // which start_flashloan needs to locate its end_flashloan
+    pub fn wrap(
+        &self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+        inner: Instruction,
+        first_index: usize,
+    ) -> Result<Vec<Instruction>, Box<dyn std::error::Error>> {
+        let bank = *self
+            .banks
+            .get(mint)
+            .ok_or_else(|| format!("No marginfi bank configured for mint {}", mint))?;
+        let token_account = get_associated_token_address(owner, mint);
+        let (vault, _) = Pubkey::find_program_address(&[b"liquidity_vault", bank.as_ref()], &self.program_id);
+        let (vault_authority, _) = Pubkey::find_program_address(&[b"liquidity_vault_auth", bank.as_ref()], &self.program_id);
+        let end_index = (first_index + 4) as u64;
+
+        let start = Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new(self.account, false),
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+            ],
+            data: [sighash("lending_account_start_flashloan").to_vec(), end_index.to_le_bytes().to_vec()].concat(),
+        };
+        let borrow = Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(self.group, false),
+                AccountMeta::new(self.account, false),
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new(bank, false),
+                AccountMeta::new(token_account, false),
+                AccountMeta::new(vault_authority, false),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(anchor_spl::token::ID, false),
+            ],
+            data: [sighash("lending_account_borrow").to_vec(), amount.to_le_bytes().to_vec()].concat(),
+        };
+        let repay = Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new_readonly(self.group, false),
+                AccountMeta::new(self.account, false),
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new(bank, false),
+                AccountMeta::new(token_account, false),
+                AccountMeta::new(vault, false),
+                AccountMeta::new_readonly(anchor_spl::token::ID, false),
+            ],
+            // repay_all: Some(true) closes the liability including any origination fee
+            data: [sighash("lending_account_repay").to_vec(), amount.to_le_bytes().to_vec(), vec![1, 1]].concat(),
+        };
+        let end = Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new(self.account, false),
+                AccountMeta::new_readonly(*owner, true),
+            ],
+            data: sighash("lending_account_end_flashloan").to_vec(),
+        };
+
+        Ok(vec![start, borrow, inner, repay, end])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flash_loan_wraps_the_trade_and_ends_after_the_repay() {
+        let (mint, bank, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let config = MarginfiConfig {
+            program_id: Pubkey::from_str(PROGRAM_ID).unwrap(),
+            group: Pubkey::from_str(MAIN_GROUP).unwrap(),
+            account: Pubkey::new_unique(),
+            banks: HashMap::from([(mint, bank)]),
+        };
+        let inner = Instruction {
+            program_id: Pubkey::new_unique(),
+            accounts: Vec::new(),
+            data: vec![9],
+        };
+
+        let wrapped = config.wrap(&owner, &mint, 500, inner.clone(), 2).unwrap();
+        assert_eq!(wrapped.len(), 5);
+        assert_eq!(wrapped[2], inner);
+        // The end instruction lands at index 2 + 4
+        assert_eq!(wrapped[0].data[8..], 6u64.to_le_bytes());
+        assert_eq!(wrapped[1].data, [sighash("lending_account_borrow").to_vec(), 500u64.to_le_bytes().to_vec()].concat());
+        assert_eq!(wrapped[1].accounts[4].pubkey, get_associated_token_address(&owner, &mint));
+        assert_eq!(wrapped[3].data[16..], [1, 1]);
+        assert_eq!(wrapped[4].data, sighash("lending_account_end_flashloan"));
+        assert!(config.wrap(&owner, &Pubkey::new_unique(), 500, inner, 2).is_err());
+    }
+}
@@ -0,0 +1,73 @@
+use crate::evaluator::Quote;
+use flate2::read::MultiGzDecoder;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::path::Path;
+
+// Raw account bytes captured alongside a quote, base64 encoded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecordedAccount {
+    pub pubkey: String,
+    pub data: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketSnapshot {
+    #[serde(default)]
+    pub opportunity_id: String,
+    pub slot: u64,
+    pub timestamp: u64,
+    pub token_a: String,
+    pub token_b: String,
+    pub loan_amount: u64,
+    pub quote: Quote,
+    pub sol_price: f64,
+    #[serde(default)]
+    pub accounts: Vec<RecordedAccount>,
+}
+
+impl MarketSnapshot {
+    pub fn pair_key(&self) -> String {
+        format!("{}/{}", self.token_a, self.token_b)
+    }
+}
+
+// Loads snapshots from a single file or every `.jsonl`/`.jsonl.gz` file in a directory, ordered by slot
+pub fn load_snapshots(path: &Path) -> Result<Vec<MarketSnapshot>, Box<dyn std::error::Error>> {
+    let mut files = Vec::new();
+    if path.is_dir() {
+        for entry in std::fs::read_dir(path)? {
+            let file = entry?.path();
+            let name = file.to_string_lossy();
+            if name.ends_with(".jsonl") || name.ends_with(".jsonl.gz") {
+                files.push(file);
+            }
+        }
+        files.sort();
+    } else {
+        files.push(path.to_path_buf());
+    }
+
+    let mut snapshots = Vec::new();
+    for file in files {
+        let raw = File::open(&file)?;
+        // The recorder appends one gzip member per flush, so read every member
+        let reader: Box<dyn Read> = if file.to_string_lossy().ends_with(".gz") {
+            Box::new(MultiGzDecoder::new(raw))
+        } else {
+            Box::new(raw)
+        };
+
+        for line in BufReader::new(reader).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            snapshots.push(serde_json::from_str::<MarketSnapshot>(&line)?);
+        }
+    }
+
+    snapshots.sort_by_key(|s| s.slot);
+    Ok(snapshots)
+}
@@ -0,0 +1,227 @@
+use crate::client;
+use crate::commands;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::signature::{Keypair, Signer};
+use std::env;
+use std::str::FromStr;
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+
+// Cluster the bot runs against, and the registry of the well-known addresses on each. Mainnet is
+// configured from the environment as always; devnet and localnet swap in their built-in settings
+// below, so a new setup can run the whole flow without real funds
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Devnet,
+    // A local solana-test-validator, such as the one `test-env` starts with cloned mainnet state
+    Localnet,
+}
+
+impl FromStr for Network {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "mainnet" | "mainnet-beta" => Ok(Network::Mainnet),
+            "devnet" => Ok(Network::Devnet),
+            "localnet" | "localhost" => Ok(Network::Localnet),
+            _ => Err(format!("Unknown network: {}", name)),
+        }
+    }
+}
+
+impl Network {
+    // `--network <name>`, falling back to NETWORK and then mainnet
+    pub fn from_args(args: &[String]) -> Result<Self, String> {
+        commands::flag_value(args, "--network")
+            .or_else(|| env::var("NETWORK").ok().filter(|v| !v.is_empty()))
+            .map_or(Ok(Network::Mainnet), |name| name.parse())
+    }
+
+    // None for localnet, whose genesis is made fresh by every new ledger
+    pub fn genesis_hash(self) -> Option<&'static str> {
+        match self {
+            Network::Mainnet => Some(MAINNET_GENESIS_HASH),
+            Network::Devnet => Some(DEVNET_GENESIS_HASH),
+            Network::Localnet => None,
+        }
+    }
+
+    pub fn addresses(self) -> &'static Addresses {
+        match self {
+            Network::Mainnet => &MAINNET,
+            Network::Devnet => &DEVNET,
+            Network::Localnet => &LOCALNET,
+        }
+    }
+
+    // Overwrites the environment with the network's settings. <PREFIX>_<NAME> takes precedence
+    // over the built-in value for <NAME>, e.g. DEVNET_SOLANA_RPC_URL for a private devnet endpoint
+    // or DEVNET_QUOTE_VAULTS for devnet pools. Mainnet leaves the environment as it is
+    pub fn apply_settings(self) {
+        let (prefix, settings) = match self {
+            Network::Mainnet => return,
+            Network::Devnet => ("DEVNET", DEVNET_SETTINGS),
+            Network::Localnet => ("LOCALNET", LOCALNET_SETTINGS),
+        };
+        for (name, value) in settings {
+            let value = env::var(format!("{}_{}", prefix, name)).unwrap_or_else(|_| value.to_string());
+            env::set_var(name, value);
+        }
+    }
+}
+
+// Well-known programs and accounts on one cluster, looked up by what they are instead of by address
+#[derive(Debug)]
+pub struct Addresses {
+    // The arbitrage program, deployed from the same keypair on every cluster
+    pub program: &'static str,
+    pub jupiter: &'static str,
+    pub raydium_amm: &'static str,
+    // Authority PDA shared by every Raydium AMM v4 pool
+    pub raydium_amm_authority: &'static str,
+    // Pyth SOL/USD feed used to price gas costs
+    pub pyth_sol_usd: &'static str,
+    // The native mint, the same on every cluster
+    pub wsol: &'static str,
+    pub usdc: &'static str,
+    // Published Jito tip accounts, used to pay tips and to pick them out of executed transactions;
+    // none where there is no block engine
+    pub jito_tip_accounts: &'static [&'static str],
+}
+
+pub const MAINNET: Addresses = Addresses {
+    program: client::PROGRAM_ID,
+    jupiter: "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4",
+    raydium_amm: "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8",
+    raydium_amm_authority: "5Q544fKrFoe6tsEbD7S8EmxGTJYAKtTVhAW5Q5pge4j1",
+    pyth_sol_usd: "H6ARHf6YXhGYeQfUzQNGk6rDNnLBQKrenN712K4AQJEG",
+    wsol: "So11111111111111111111111111111111111111112",
+    usdc: "EPjFWdd5AufqSSqeM2qN1xzybapC8G4wEGGkZwyTDt1v",
+    jito_tip_accounts: &[
+        "96gYZGLnJYVFmbjzopPSU6QiEV5fGqZNyN9nmNhvrZU5",
+        "HFqU5x63VTqvQss8hp11i4wVV8bD44PvwucfZ2bU7gRe",
+        "Cw8CFyM9FkoMi7K7Crf6HNQqf4uEMzpKw6QNghXLvLkY",
+        "ADaUMid9yfUytqMBgopwjb2DTLSokTSzL1zt6iGPaS49",
+        "DfXygSm4jCyNCybVYYK6DwvWqjKee8pbDmJGcLWNDXjh",
+        "ADuUkR4vqLUMWXxW9gh6D6L8pMSawimctcNZ5pGwDcEt",
+        "DttWaMuVvTiduZRnguLF7jNxTgiMBZ1hyAumKUiL2KRL",
+        "3AVi9Tg9Uo68tJfuvoKvqKNWKkC5wPdSSdeBnizKZ6jT",
+    ],
+};
+
+// Jupiter has no devnet deployment; its mainnet id is kept so Jupiter-quoted swaps fail at the
+// quote rather than at an unknown program
+pub const DEVNET: Addresses = Addresses {
+    program: MAINNET.program,
+    jupiter: MAINNET.jupiter,
+    raydium_amm: "HWy1jotHpo6UqeQxx49dpYYdQB8wj9Qk9MdxwjLvDHB8",
+    raydium_amm_authority: "DbQqP6ehDYmeYjcBaMRuA8tAJY1EjDUz9DpwSLjaQqfC",
+    pyth_sol_usd: "J83w4HKfqxwcq3BEMMkPFSppX3gqekLyLJBexebFVkix",
+    wsol: MAINNET.wsol,
+    // Circle's devnet USDC
+    usdc: "4zMMC9srt5Ri5X14GAgXhaHii3GnPAEERYPJgZJDncDU",
+    jito_tip_accounts: &[],
+};
+
+// A local validator runs on accounts cloned from mainnet, at their mainnet addresses
+pub const LOCALNET: Addresses = MAINNET;
+
+static SELECTED: OnceLock<Network> = OnceLock::new();
+
+// Picks the network whose registry `addresses()` returns for the rest of the process; only the
+// first call counts
+pub fn select(network: Network) {
+    let _ = SELECTED.set(network);
+}
+
+// The selected network's registry, mainnet's until one is selected
+pub fn addresses() -> &'static Addresses {
+    SELECTED.get().copied().unwrap_or(Network::Mainnet).addresses()
+}
+
+pub const MAINNET_GENESIS_HASH: &str = "5eykt4UsFv8P8NJdTREpY1vzqKqZKvdpKuc147dw2N9d";
+pub const DEVNET_GENESIS_HASH: &str = "EtWTRABZaYq6iMfeYKouRu166VU2xqa1wcaWoxPkrZBG";
+
+// Devnet replacements for the mainnet settings. Jupiter has no devnet deployment and the program only
+// accepts the mainnet Raydium and Solend program ids, so devnet trades run from the wallet's own
+// balance and anything pointing at mainnet pools is cleared
+const DEVNET_SETTINGS: &[(&str, &str)] = &[
+    ("SOLANA_RPC_URL", "https://api.devnet.solana.com"),
+    ("SOLANA_RPC_URLS", ""),
+    ("SOLANA_WS_URL", "wss://api.devnet.solana.com"),
+    ("PYTH_SOL_USD_ACCOUNT", DEVNET.pyth_sol_usd),
+    // WSOL against Circle's devnet USDC
+    ("BONK_TOKEN_ADDRESS", DEVNET.wsol),
+    ("GOAT_TOKEN_ADDRESS", DEVNET.usdc),
+    // 0.1 SOL, which an airdrop covers
+    ("LOAN_AMOUNT", "100000000"),
+    ("DISCOVERY_LOAN_AMOUNT", "100000000"),
+    ("FLASH_LOAN_PROVIDER", "marginfi"),
+    ("MARGINFI_ACCOUNT", ""),
+    ("QUOTE_VAULTS", ""),
+    ("POOL_ACCOUNTS", ""),
+    ("PAIR_VENUES", ""),
+    ("TOKEN_A_PYTH_ACCOUNT", ""),
+    ("TOKEN_B_PYTH_ACCOUNT", ""),
+    ("ROUTES_FILE", ""),
+    ("POOLS_FILE", ""),
+    // Mainnet pairs stay in pairs.json; devnet pairs go in their own store
+    ("PAIRS_FILE", "pairs-devnet.json"),
+    ("DISCOVERY_ENABLED", "false"),
+    ("ADAPTIVE_THRESHOLDS", "false"),
+    ("TRADE_JOURNAL_PATH", "trades-devnet.jsonl"),
+    ("RECORD_DIR", ""),
+];
+
+// Localnet keeps the mainnet configuration, whose accounts `test-env` clones, and points the bot at
+// the validator's default ports
+const LOCALNET_SETTINGS: &[(&str, &str)] = &[
+    ("SOLANA_RPC_URL", "http://127.0.0.1:8899"),
+    ("SOLANA_RPC_URLS", ""),
+    ("SOLANA_WS_URL", "ws://127.0.0.1:8900"),
+    ("TRADE_JOURNAL_PATH", "trades-localnet.jsonl"),
+];
+
+// Relaxed so quotes on thin devnet pools still reach simulation and execution
+pub const DEVNET_MIN_PROFIT_THRESHOLD: f64 = 0.0;
+pub const DEVNET_SLIPPAGE_TOLERANCE: f64 = 1.0;
+
+// The wallet is topped up when it holds less than this
+const DEVNET_MIN_BALANCE_SOL: u64 = 1;
+// The devnet faucet's largest single airdrop
+const DEVNET_AIRDROP_SOL: u64 = 2;
+const AIRDROP_TIMEOUT: Duration = Duration::from_secs(60);
+
+// Refuses an RPC endpoint that isn't devnet, so a leftover mainnet URL can't trade real funds
+pub fn verify_devnet(rpc: &RpcClient) -> Result<(), Box<dyn std::error::Error>> {
+    let genesis_hash = rpc.get_genesis_hash()?.to_string();
+    if genesis_hash != DEVNET_GENESIS_HASH {
+        return Err(format!("{} is not a devnet endpoint (genesis hash {})", rpc.url(), genesis_hash).into());
+    }
+    Ok(())
+}
+
+// Airdrops SOL to the wallet when it runs low; the faucet is rate limited, so a refused airdrop is
+// reported and the caller carries on with what the wallet has
+pub fn airdrop_if_low(rpc: &RpcClient, wallet: &Keypair) -> Result<(), Box<dyn std::error::Error>> {
+    let balance = rpc.get_balance(&wallet.pubkey())?;
+    if balance >= DEVNET_MIN_BALANCE_SOL * LAMPORTS_PER_SOL {
+        return Ok(());
+    }
+    println!("Wallet holds {} lamports; requesting a {} SOL devnet airdrop", balance, DEVNET_AIRDROP_SOL);
+    let signature = rpc.request_airdrop(&wallet.pubkey(), DEVNET_AIRDROP_SOL * LAMPORTS_PER_SOL)?;
+    let started = Instant::now();
+    while !rpc.confirm_transaction_with_commitment(&signature, CommitmentConfig::confirmed())?.value {
+        if started.elapsed() > AIRDROP_TIMEOUT {
+            return Err(format!("Airdrop {} was not confirmed", signature).into());
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+    println!("Airdropped {} SOL to {}", DEVNET_AIRDROP_SOL, wallet.pubkey());
+    Ok(())
+}
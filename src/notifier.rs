@@ -0,0 +1,48 @@
+use crate::http_client;
+use std::env;
+
+pub enum NotifyTarget {
+    Console,
+    // Slack/Discord style incoming webhook taking a JSON `text`/`content` body
+    Webhook(String),
+}
+
+pub struct Notifier {
+    targets: Vec<NotifyTarget>,
+    http: reqwest::Client,
+}
+
+impl Notifier {
+    // Console is always enabled; webhooks come from a comma separated NOTIFY_WEBHOOK_URLS
+    pub fn from_env() -> Self {
+        let mut targets = vec![NotifyTarget::Console];
+        if let Ok(urls) = env::var("NOTIFY_WEBHOOK_URLS") {
+            targets.extend(
+                urls.split(',')
+                    .map(str::trim)
+                    .filter(|url| !url.is_empty())
+                    .map(|url| NotifyTarget::Webhook(url.to_string())),
+            );
+        }
+
+        Self {
+            targets,
+            http: http_client::client(),
+        }
+    }
+
+    pub async fn notify(&self, subject: &str, body: &str) {
+        for target in &self.targets {
+            match target {
+                NotifyTarget::Console => println!("[{}]\n{}", subject, body),
+                NotifyTarget::Webhook(url) => {
+                    let text = format!("*{}*\n{}", subject, body);
+                    let payload = serde_json::json!({ "text": text, "content": text });
+                    if let Err(e) = self.http.post(url).timeout(http_client::timeout_for(url)).json(&payload).send().await {
+                        println!("Failed to deliver notification to {}: {}", url, e);
+                    }
+                }
+            }
+        }
+    }
+}
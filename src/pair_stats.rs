@@ -0,0 +1,302 @@
+use crate::journal::{JournalEntry, TradeStatus};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+
+#[derive(Debug, Clone, Default)]
+pub struct PairStats {
+    pub evaluations: u64,
+    pub executable: u64,
+}
+
+impl PairStats {
+    pub fn hit_rate(&self) -> f64 {
+        if self.evaluations == 0 {
+            0.0
+        } else {
+            self.executable as f64 / self.evaluations as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Demotion {
+    pub pair_key: String,
+    pub stats: PairStats,
+    pub net_profit_lamports: i64,
+}
+
+impl Demotion {
+    pub fn reason(&self) -> String {
+        if self.stats.executable == 0 {
+            format!("no executable opportunities in {} evaluations", self.stats.evaluations)
+        } else {
+            format!(
+                "net loss of {} lamports ({:.2}% hit rate)",
+                -self.net_profit_lamports,
+                self.stats.hit_rate() * 100.0
+            )
+        }
+    }
+}
+
+// Per-pair hit counters for the current review window
+#[derive(Debug, Default)]
+pub struct PairTracker {
+    stats: HashMap<String, PairStats>,
+}
+
+impl PairTracker {
+    pub fn stats(&self, pair_key: &str) -> Option<&PairStats> {
+        self.stats.get(pair_key)
+    }
+
+    pub fn observe(&mut self, pair_key: &str, executable: bool) {
+        let stats = self.stats.entry(pair_key.to_string()).or_default();
+        stats.evaluations += 1;
+        if executable {
+            stats.executable += 1;
+        }
+    }
+
+    // Pairs that produced nothing executable, or lost money, since `window_start`; resets the window
+    pub fn review(&mut self, journal: &[JournalEntry], window_start: u64) -> Vec<Demotion> {
+        let mut net_by_pair: HashMap<String, i64> = HashMap::new();
+        for entry in journal.iter().filter(|e| e.timestamp >= window_start) {
+            *net_by_pair.entry(entry.pair_key()).or_insert(0) += entry.net_profit_lamports();
+        }
+
+        let demotions = self
+            .stats
+            .iter()
+            .filter_map(|(pair_key, stats)| {
+                let net_profit_lamports = net_by_pair.get(pair_key).copied().unwrap_or(0);
+                if stats.executable == 0 || net_profit_lamports < 0 {
+                    Some(Demotion {
+                        pair_key: pair_key.clone(),
+                        stats: stats.clone(),
+                        net_profit_lamports,
+                    })
+                } else {
+                    None
+                }
+            })
+            .collect();
+
+        self.stats.clear();
+        demotions
+    }
+}
+
+// What a pair's trades paid, to tell the pairs worth capital from the ones that only look busy.
+// Opportunities are the pair's evaluated quotes, which only the monitor sees; None over journal
+// entries alone
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PairPerformance {
+    pub opportunities: Option<u64>,
+    pub attempts: u64,
+    pub executed: u64,
+    pub winning: u64,
+    pub fee_spend_lamports: i64,
+    pub fee_spend_usd: f64,
+    // Net USD profit of the executed trades that could be priced
+    realized_profit_usd: f64,
+    priced_trades: u64,
+    // Realized profit against the quote, of the trades it was measured for
+    profit_slippage_bps: i64,
+    slippage_samples: u64,
+}
+
+impl PairPerformance {
+    // Share of executed trades that made money
+    pub fn win_rate(&self) -> f64 {
+        if self.executed == 0 {
+            0.0
+        } else {
+            self.winning as f64 / self.executed as f64
+        }
+    }
+
+    pub fn avg_realized_profit_usd(&self) -> Option<f64> {
+        (self.priced_trades > 0).then(|| self.realized_profit_usd / self.priced_trades as f64)
+    }
+
+    pub fn avg_profit_slippage_bps(&self) -> Option<f64> {
+        (self.slippage_samples > 0).then(|| self.profit_slippage_bps as f64 / self.slippage_samples as f64)
+    }
+
+    fn observe(&mut self, entry: &JournalEntry) {
+        self.attempts += 1;
+        self.fee_spend_lamports += entry.costs.total_lamports();
+        self.fee_spend_usd += entry.costs_usd().unwrap_or(0.0);
+        if entry.status != TradeStatus::Executed {
+            return;
+        }
+        self.executed += 1;
+        let net_profit_usd = entry.net_profit_usd();
+        if net_profit_usd.map_or(entry.net_profit_lamports() > 0, |net| net > 0.0) {
+            self.winning += 1;
+        }
+        if let Some(net) = net_profit_usd {
+            self.realized_profit_usd += net;
+            self.priced_trades += 1;
+        }
+        if let Some(bps) = entry.profit_slippage_bps {
+            self.profit_slippage_bps += bps;
+            self.slippage_samples += 1;
+        }
+    }
+}
+
+// A metric's name, help text and value for one pair, if it has one
+type Gauge = (&'static str, &'static str, fn(&PairPerformance) -> Option<f64>);
+
+// Per-pair performance, keyed like the journal's pair keys
+#[derive(Debug, Default)]
+pub struct PairAnalytics {
+    pairs: BTreeMap<String, PairPerformance>,
+}
+
+impl PairAnalytics {
+    pub fn from_entries<'a>(entries: impl IntoIterator<Item = &'a JournalEntry>) -> Self {
+        let mut analytics = Self::default();
+        for entry in entries {
+            analytics.observe(entry);
+        }
+        analytics
+    }
+
+    pub fn observe(&mut self, entry: &JournalEntry) {
+        self.pairs.entry(entry.pair_key()).or_default().observe(entry);
+    }
+
+    // The monitor evaluated a quote for the pair
+    pub fn observe_opportunity(&mut self, pair_key: &str) {
+        let opportunities = &mut self.pairs.entry(pair_key.to_string()).or_default().opportunities;
+        *opportunities = Some(opportunities.unwrap_or(0) + 1);
+    }
+
+    // Fills in the opportunities the monitor counted over the same period
+    pub fn set_opportunities(&mut self, counts: &HashMap<String, u64>) {
+        for (pair_key, count) in counts {
+            self.pairs.entry(pair_key.clone()).or_default().opportunities = Some(*count);
+        }
+    }
+
+    pub fn get(&self, pair_key: &str) -> Option<&PairPerformance> {
+        self.pairs.get(pair_key)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    // Highest realized profit first
+    fn ranked(&self) -> Vec<(&String, &PairPerformance)> {
+        let mut pairs: Vec<(&String, &PairPerformance)> = self.pairs.iter().collect();
+        pairs.sort_by(|a, b| b.1.realized_profit_usd.total_cmp(&a.1.realized_profit_usd));
+        pairs
+    }
+
+    // One line per pair, highest realized profit first
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for (pair, stats) in self.ranked() {
+            let _ = writeln!(
+                out,
+                "  {}: {} opportunities, {}/{} executed, {:.1}% won, avg profit {}, avg slippage {}, fees {} SOL (${:.2})",
+                pair,
+                stats.opportunities.map_or("-".to_string(), |count| count.to_string()),
+                stats.executed,
+                stats.attempts,
+                stats.win_rate() * 100.0,
+                stats.avg_realized_profit_usd().map_or("-".to_string(), |usd| format!("${:.2}", usd)),
+                stats.avg_profit_slippage_bps().map_or("-".to_string(), |bps| format!("{:.0} bps", bps)),
+                stats.fee_spend_lamports as f64 / 1_000_000_000.0,
+                stats.fee_spend_usd
+            );
+        }
+        out
+    }
+
+    // The render's figures per pair, for the control API; null where render shows `-`
+    pub fn to_json(&self) -> serde_json::Value {
+        let pairs: Vec<serde_json::Value> = self
+            .ranked()
+            .into_iter()
+            .map(|(pair, stats)| {
+                serde_json::json!({
+                    "pair": pair,
+                    "opportunities": stats.opportunities,
+                    "attempts": stats.attempts,
+                    "executed": stats.executed,
+                    "win_rate": stats.win_rate(),
+                    "avg_realized_profit_usd": stats.avg_realized_profit_usd(),
+                    "avg_profit_slippage_bps": stats.avg_profit_slippage_bps(),
+                    "fee_spend_lamports": stats.fee_spend_lamports,
+                    "fee_spend_usd": stats.fee_spend_usd,
+                })
+            })
+            .collect();
+        serde_json::Value::Array(pairs)
+    }
+
+    // Prometheus text format, labelled by pair
+    pub fn metrics(&self) -> String {
+        let gauges: [Gauge; 7] = [
+            ("arb_pair_opportunities_total", "Quotes evaluated for the pair", |s| s.opportunities.map(|n| n as f64)),
+            ("arb_pair_attempts_total", "Execution attempts for the pair", |s| Some(s.attempts as f64)),
+            ("arb_pair_executed_total", "Executed trades of the pair", |s| Some(s.executed as f64)),
+            ("arb_pair_win_rate", "Share of the pair's executed trades that made money", |s| Some(s.win_rate())),
+            ("arb_pair_avg_realized_profit_usd", "Average net USD profit of the pair's executed trades", |s| {
+                s.avg_realized_profit_usd()
+            }),
+            ("arb_pair_avg_profit_slippage_bps", "Average shortfall of the pair's realized profit against the quote", |s| {
+                s.avg_profit_slippage_bps()
+            }),
+            ("arb_pair_fee_spend_lamports_total", "Lamports the pair's attempts cost", |s| {
+                Some(s.fee_spend_lamports as f64)
+            }),
+        ];
+        let mut text = String::new();
+        for (name, help, value) in gauges {
+            let kind = if name.ends_with("_total") { "counter" } else { "gauge" };
+            text.push_str(&format!("# HELP {} {}\n# TYPE {} {}\n", name, help, name, kind));
+            for (pair, stats) in &self.pairs {
+                if let Some(value) = value(stats) {
+                    text.push_str(&format!("{}{{pair=\"{}\"}} {}\n", name, pair, value));
+                }
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn idle_and_losing_pairs_are_demoted_at_review() {
+        let mut tracker = PairTracker::default();
+        for (pair, executable) in [("idle", false), ("idle", false), ("losing", true), ("losing", false), ("earning", true)] {
+            tracker.observe(pair, executable);
+        }
+        assert_eq!(tracker.stats("losing").unwrap().hit_rate(), 0.5);
+        let entry = |token_a: &str, profit_lamports, fee_lamports, timestamp| {
+            let mut entry = JournalEntry::new(token_a.to_string(), String::new(), 1_000_000_000, TradeStatus::Executed);
+            entry.route = Some(token_a.to_string());
+            entry.expected_profit_lamports = profit_lamports;
+            entry.costs.base_fee_lamports = fee_lamports;
+            entry.timestamp = timestamp;
+            entry
+        };
+        // The earning pair's old loss falls before the window
+        let journal = [entry("losing", 1_000, 6_000, 100), entry("earning", 9_000, 5_000, 100), entry("earning", 0, 50_000, 10)];
+
+        let mut demoted: Vec<String> = tracker.review(&journal, 50).iter().map(|demotion| demotion.reason()).collect();
+        demoted.sort();
+        assert_eq!(demoted, ["net loss of 5000 lamports (50.00% hit rate)", "no executable opportunities in 2 evaluations"]);
+        // The window starts over
+        assert!(tracker.stats("earning").is_none());
+    }
+}
@@ -0,0 +1,244 @@
+use crate::evaluator::ProfitThreshold;
+use crate::execution_policy;
+use crate::strategy;
+use crate::venue::Venue;
+use serde::{Deserialize, Serialize};
+use solana_sdk::pubkey::Pubkey;
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+use std::time::SystemTime;
+
+// Monitored pairs kept in a JSON file and managed with the `pairs` commands or the control API. Once
+// the file exists it replaces the single pair configured through BONK_TOKEN_ADDRESS and friends, and
+// the running monitor picks up every change to it on its next cycle
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StoredPair {
+    pub token_a: String,
+    pub token_b: String,
+    pub loan_amount: u64,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+    // Pool state accounts; the first is the Raydium pool the pair trades through
+    #[serde(default)]
+    pub pool_accounts: Vec<String>,
+    // The four vaults priced by the program's `quote`
+    #[serde(default)]
+    pub quote_vaults: Vec<String>,
+    // Pyth USD feeds for token A and token B
+    #[serde(default)]
+    pub oracle_accounts: Option<(String, String)>,
+    // STRATEGY syntax, e.g. zscore:2.5:300; the pure spread strategy when unset
+    #[serde(default)]
+    pub strategy: Option<String>,
+    // EXECUTION_POLICY syntax, e.g. confirm:2; immediate when unset
+    #[serde(default)]
+    pub execution_policy: Option<String>,
+    // Replaces MIN_PROFIT_THRESHOLD for this pair, in the same syntax
+    #[serde(default)]
+    pub min_profit_threshold: Option<ProfitThreshold>,
+    // Jupiter dex labels the pair's Jupiter leg may route through, e.g. "Orca V2,Meteora"
+    #[serde(default)]
+    pub dexes: Option<String>,
+    // Checked this often instead of on its polling tier's interval
+    #[serde(default)]
+    pub poll_interval_ms: Option<u64>,
+    // Replaces SLIPPAGE_TOLERANCE, the percent the evaluator allows for slippage
+    #[serde(default)]
+    pub slippage_tolerance: Option<f64>,
+    // Replaces SLIPPAGE_BPS, the starting tolerance of the trade's minimum outputs
+    #[serde(default)]
+    pub slippage_bps: Option<u16>,
+    // Replaces TRADE_COOLDOWN_SECS
+    #[serde(default)]
+    pub cooldown_secs: Option<u64>,
+    // Venues compared with the program's two, e.g. a Whirlpool and a Raydium CLMM pool on the pair
+    #[serde(default)]
+    pub venues: Vec<PairVenue>,
+}
+
+// A further venue the pair is quoted on, through Jupiter restricted to the venue. Every venue but
+// Jupiter needs the pool its leg swaps through
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PairVenue {
+    pub venue: Venue,
+    #[serde(default)]
+    pub pool: Option<String>,
+}
+
+// "whirlpool:<pool>", or "jupiter" for Jupiter's best route
+impl FromStr for PairVenue {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (venue, pool) = match s.split_once(':') {
+            Some((venue, pool)) => (venue, Some(pool.trim().to_string())),
+            None => (s, None),
+        };
+        Ok(Self {
+            venue: venue.parse()?,
+            pool,
+        })
+    }
+}
+
+impl PairVenue {
+    pub fn validate(&self) -> Result<(), String> {
+        match &self.pool {
+            Some(pool) => Pubkey::from_str(pool).map(|_| ()).map_err(|_| format!("Invalid {:?} pool {}", self.venue, pool)),
+            None if self.venue.needs_pool() => Err(format!("{:?} venues need a pool address", self.venue)),
+            None => Ok(()),
+        }
+    }
+}
+
+// Comma separated venues, as PAIR_VENUES and `pairs add --venues` take them
+pub fn parse_venues(list: &str) -> Result<Vec<PairVenue>, String> {
+    list.split(',').map(str::trim).filter(|venue| !venue.is_empty()).map(str::parse).collect()
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+impl StoredPair {
+    pub fn key(&self) -> String {
+        format!("{}/{}", self.token_a, self.token_b)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        let address = |address: &str| Pubkey::from_str(address).map_err(|_| format!("Invalid address {}", address));
+        address(&self.token_a)?;
+        address(&self.token_b)?;
+        for account in self.pool_accounts.iter().chain(&self.quote_vaults) {
+            address(account)?;
+        }
+        if let Some((oracle_a, oracle_b)) = &self.oracle_accounts {
+            address(oracle_a)?;
+            address(oracle_b)?;
+        }
+        if !self.quote_vaults.is_empty() && self.quote_vaults.len() != 4 {
+            return Err(format!("Pair {} needs four quote vaults, not {}", self.key(), self.quote_vaults.len()));
+        }
+        for venue in &self.venues {
+            venue.validate().map_err(|e| format!("Pair {}: {}", self.key(), e))?;
+        }
+        if let Some(name) = &self.strategy {
+            strategy::from_name(name)?;
+        }
+        if let Some(name) = &self.execution_policy {
+            execution_policy::from_name(name)?;
+        }
+        if self.slippage_tolerance.is_some_and(|tolerance| !(0.0..100.0).contains(&tolerance)) {
+            return Err(format!("Pair {} needs a slippage tolerance between 0 and 100 percent", self.key()));
+        }
+        if self.slippage_bps.is_some_and(|bps| bps > 10_000) {
+            return Err(format!("Pair {} needs slippage of at most 10000 bps", self.key()));
+        }
+        if self.loan_amount == 0 {
+            return Err(format!("Pair {} needs a loan amount", self.key()));
+        }
+        Ok(())
+    }
+}
+
+pub struct PairStore {
+    pub path: PathBuf,
+    // Modification time of the file when it was last loaded through load_if_changed
+    loaded: Option<SystemTime>,
+}
+
+impl PairStore {
+    pub fn new(path: PathBuf) -> Self {
+        Self { path, loaded: None }
+    }
+
+    // PAIRS_FILE, default pairs.json
+    pub fn from_env() -> Self {
+        Self::new(PathBuf::from(
+            env::var("PAIRS_FILE").ok().filter(|v| !v.is_empty()).unwrap_or_else(|| "pairs.json".to_string()),
+        ))
+    }
+
+    pub fn exists(&self) -> bool {
+        self.path.exists()
+    }
+
+    // Every stored pair; none when the file doesn't exist yet
+    pub fn load(&self) -> Result<Vec<StoredPair>, Box<dyn std::error::Error>> {
+        if !self.exists() {
+            return Ok(Vec::new());
+        }
+        let pairs: Vec<StoredPair> = serde_json::from_str(&std::fs::read_to_string(&self.path)?)?;
+        for pair in &pairs {
+            pair.validate()?;
+        }
+        Ok(pairs)
+    }
+
+    // The stored pairs if the file changed since the last call
+    pub fn load_if_changed(&mut self) -> Result<Option<Vec<StoredPair>>, Box<dyn std::error::Error>> {
+        let modified = std::fs::metadata(&self.path)?.modified()?;
+        if self.loaded == Some(modified) {
+            return Ok(None);
+        }
+        let pairs = self.load()?;
+        self.loaded = Some(modified);
+        Ok(Some(pairs))
+    }
+
+    // The next load_if_changed reads the file even if its modification time looks unchanged, for
+    // writes made by the monitor itself
+    pub fn invalidate(&mut self) {
+        self.loaded = None;
+    }
+
+    // Written to a temporary file and renamed over the store, so the monitor never reads half a file
+    pub fn save(&self, pairs: &[StoredPair]) -> Result<(), Box<dyn std::error::Error>> {
+        let temporary = self.path.with_extension("json.tmp");
+        std::fs::write(&temporary, serde_json::to_string_pretty(pairs)?)?;
+        std::fs::rename(&temporary, &self.path)?;
+        Ok(())
+    }
+
+    // Stores the pair, replacing the one with its key; true when it replaced one
+    pub fn put(&self, pair: StoredPair) -> Result<bool, Box<dyn std::error::Error>> {
+        pair.validate()?;
+        let mut pairs = self.load()?;
+        let replaced = match pairs.iter_mut().find(|stored| stored.key() == pair.key()) {
+            Some(stored) => {
+                *stored = pair;
+                true
+            }
+            None => {
+                pairs.push(pair);
+                false
+            }
+        };
+        self.save(&pairs)?;
+        Ok(replaced)
+    }
+
+    // The removed pair; None when no pair has the key
+    pub fn remove(&self, key: &str) -> Result<Option<StoredPair>, Box<dyn std::error::Error>> {
+        let mut pairs = self.load()?;
+        let Some(position) = pairs.iter().position(|pair| pair.key() == key) else {
+            return Ok(None);
+        };
+        let removed = pairs.remove(position);
+        self.save(&pairs)?;
+        Ok(Some(removed))
+    }
+
+    // False when no pair has the key
+    pub fn set_enabled(&self, key: &str, enabled: bool) -> Result<bool, Box<dyn std::error::Error>> {
+        let mut pairs = self.load()?;
+        let Some(pair) = pairs.iter_mut().find(|pair| pair.key() == key) else {
+            return Ok(false);
+        };
+        pair.enabled = enabled;
+        self.save(&pairs)?;
+        Ok(true)
+    }
+}
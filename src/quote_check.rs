@@ -0,0 +1,137 @@
+use crate::evaluator::Quote;
+use std::env;
+
+// Cross-source validation of the program's quote before a trade is attempted. A wrong vault order,
+// a misread account or a decoding bug can make the `quote` simulation report a spread no pool
+// offers, and the bot would pay fees chasing it. With QUOTE_VALIDATION=pool, both prices are
+// recomputed off-chain from the vault balances the same simulation returned; with
+// QUOTE_VALIDATION=jupiter, neither price may beat Jupiter's best route for the trade. Either way a
+// difference beyond QUOTE_VALIDATION_TOLERANCE_BPS aborts the trade
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuoteSource {
+    PoolMath,
+    Jupiter,
+}
+
+#[derive(Debug, Clone)]
+pub struct QuoteCheckConfig {
+    pub source: QuoteSource,
+    pub tolerance_bps: u64,
+}
+
+impl QuoteCheckConfig {
+    // None when QUOTE_VALIDATION is unset or "off"
+    pub fn from_env() -> Result<Option<Self>, String> {
+        let source = match env::var("QUOTE_VALIDATION").unwrap_or_default().as_str() {
+            "" | "off" => return Ok(None),
+            "pool" => QuoteSource::PoolMath,
+            "jupiter" => QuoteSource::Jupiter,
+            other => return Err(format!("Unknown QUOTE_VALIDATION source: {}", other)),
+        };
+        Ok(Some(Self {
+            source,
+            tolerance_bps: env::var("QUOTE_VALIDATION_TOLERANCE_BPS").ok().and_then(|v| v.parse().ok()).unwrap_or(50),
+        }))
+    }
+}
+
+// x * y = k output for `amount_in` after the pool's fee, as the program's `quote` computes it
+pub fn constant_product_out(amount_in: u64, reserve_in: u64, reserve_out: u64, fee_bps: u16) -> u64 {
+    let amount_in = amount_in as u128 * 10_000u128.saturating_sub(fee_bps as u128) / 10_000;
+    let denominator = reserve_in as u128 + amount_in;
+    if denominator == 0 {
+        return 0;
+    }
+    u64::try_from(amount_in * reserve_out as u128 / denominator).unwrap_or(u64::MAX)
+}
+
+// How far `quoted` is from `reference`, in bps of the reference
+pub fn divergence_bps(quoted: u64, reference: u64) -> f64 {
+    quoted.abs_diff(reference) as f64 * 10_000.0 / reference.max(1) as f64
+}
+
+// Recomputes both prices for `amount` from the four vaults' (mint, balance), in the quote's account
+// order: first base, first quote, second base, second quote
+pub fn check_pool_math(
+    quote: &Quote,
+    vaults: &[(String, u64)],
+    amount: u64,
+    fee_bps: [u16; 2],
+    tolerance_bps: u64,
+) -> Result<(), String> {
+    let [(first_base, first_base_amount), (first_quote, first_quote_amount), (second_base, second_base_amount), (second_quote, second_quote_amount)] =
+        vaults
+    else {
+        return Err(format!("Pool math needs four decoded quote vaults, got {}", vaults.len()));
+    };
+    if first_base != second_base || first_quote != second_quote {
+        return Err(format!(
+            "Quote vaults price {}/{} against {}/{}",
+            first_base, first_quote, second_base, second_quote
+        ));
+    }
+    let prices = [
+        ("Jupiter-side", quote.jupiter_price, constant_product_out(amount, *first_base_amount, *first_quote_amount, fee_bps[0])),
+        ("Raydium", quote.raydium_price, constant_product_out(amount, *second_base_amount, *second_quote_amount, fee_bps[1])),
+    ];
+    for (venue, quoted, expected) in prices {
+        let divergence = divergence_bps(quoted, expected);
+        if divergence > tolerance_bps as f64 {
+            return Err(format!(
+                "{} price {} diverges {:.0} bps from pool math {}",
+                venue, quoted, divergence, expected
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Neither venue may pay more than `best_out`, the most an independent router finds for the trade,
+// by more than the tolerance
+pub fn check_against_best(quote: &Quote, best_out: u64, tolerance_bps: u64) -> Result<(), String> {
+    for (venue, quoted) in [("Jupiter-side", quote.jupiter_price), ("Raydium", quote.raydium_price)] {
+        if quoted > best_out && divergence_bps(quoted, best_out) > tolerance_bps as f64 {
+            return Err(format!(
+                "{} price {} beats Jupiter's best route {} by {:.0} bps",
+                venue,
+                quoted,
+                best_out,
+                divergence_bps(quoted, best_out)
+            ));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn quote(jupiter_price: u64, raydium_price: u64) -> Quote {
+        Quote {
+            jupiter_price,
+            raydium_price,
+            venues: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn quotes_are_checked_against_pool_math_and_the_best_route() {
+        // 1% of a 1:2 pool, less a 25 bps fee
+        let expected = constant_product_out(1_000, 100_000, 200_000, 25);
+        assert_eq!(expected, 1_974);
+        let vaults = [("A".to_string(), 100_000), ("B".to_string(), 200_000), ("A".to_string(), 100_000), ("B".to_string(), 200_000)];
+        assert!(check_pool_math(&quote(1_974, 1_979), &vaults, 1_000, [25, 25], 50).is_ok());
+        assert!(check_pool_math(&quote(1_974, 2_100), &vaults, 1_000, [25, 25], 50).unwrap_err().starts_with("Raydium price 2100"));
+        assert!(check_pool_math(&quote(1_974, 1_974), &vaults[..3], 1_000, [25, 25], 50).is_err());
+        let mut swapped = vaults.clone();
+        swapped.swap(2, 3);
+        assert!(check_pool_math(&quote(1_974, 1_974), &swapped, 1_000, [25, 25], 50).is_err());
+
+        // Only beating the best route is suspect
+        assert!(check_against_best(&quote(900, 1_004), 1_000, 50).is_ok());
+        assert!(check_against_best(&quote(900, 1_010), 1_000, 50).is_err());
+        assert_eq!(divergence_bps(1_010, 1_000), 100.0);
+    }
+}
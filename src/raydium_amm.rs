@@ -0,0 +1,126 @@
+use crate::network;
+use crate::rpc::RpcApi;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+// Offsets in the AMM v4 pool state account
+const COIN_VAULT_OFFSET: usize = 336;
+const PC_VAULT_OFFSET: usize = 368;
+const COIN_MINT_OFFSET: usize = 400;
+const PC_MINT_OFFSET: usize = 432;
+const OPEN_ORDERS_OFFSET: usize = 496;
+const MARKET_OFFSET: usize = 528;
+const MARKET_PROGRAM_OFFSET: usize = 560;
+const TARGET_ORDERS_OFFSET: usize = 592;
+// Offsets in the pool's OpenBook market account
+const MARKET_VAULT_SIGNER_NONCE_OFFSET: usize = 45;
+const MARKET_COIN_VAULT_OFFSET: usize = 117;
+const MARKET_PC_VAULT_OFFSET: usize = 165;
+const MARKET_EVENT_QUEUE_OFFSET: usize = 253;
+const MARKET_BIDS_OFFSET: usize = 285;
+const MARKET_ASKS_OFFSET: usize = 317;
+
+// Leg accounts for a `swap_base_in` on `pool` selling `input_mint`, in the order the program
+// expects: program, pool, authority, open orders, target orders, coin vault, pc vault, OpenBook
+// program, market, bids, asks, event queue, market coin vault, market pc vault, market vault
+// signer, source, destination, output mint. The source and destination are `owner`'s associated
+// token accounts
+pub fn leg_accounts(
+    rpc: &dyn RpcApi,
+    pool: &Pubkey,
+    input_mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Vec<AccountMeta>, Box<dyn std::error::Error>> {
+    let data = rpc.get_account_data(pool)?;
+    let pubkey_at = |data: &[u8], offset: usize| -> Result<Pubkey, Box<dyn std::error::Error>> {
+        let bytes = data
+            .get(offset..offset + 32)
+            .ok_or_else(|| format!("Raydium pool {} or its market is too short", pool))?;
+        Ok(Pubkey::new_from_array(bytes.try_into()?))
+    };
+
+    let coin_mint = pubkey_at(&data, COIN_MINT_OFFSET)?;
+    let pc_mint = pubkey_at(&data, PC_MINT_OFFSET)?;
+    let output_mint = if *input_mint == coin_mint {
+        pc_mint
+    } else if *input_mint == pc_mint {
+        coin_mint
+    } else {
+        return Err(format!("Mint {} is not traded by Raydium pool {}", input_mint, pool).into());
+    };
+
+    // The swap settles through the pool's OpenBook market, whose queues and vaults it also takes
+    let market = pubkey_at(&data, MARKET_OFFSET)?;
+    let market_program = pubkey_at(&data, MARKET_PROGRAM_OFFSET)?;
+    let market_data = rpc.get_account_data(&market)?;
+    let nonce = market_data
+        .get(MARKET_VAULT_SIGNER_NONCE_OFFSET..MARKET_VAULT_SIGNER_NONCE_OFFSET + 8)
+        .ok_or_else(|| format!("Raydium market {} is too short", market))?;
+    let vault_signer = Pubkey::create_program_address(&[market.as_ref(), nonce], &market_program)?;
+
+    // SPL Token or Token-2022 per mint, for the owner's associated token accounts
+    let mints = rpc.get_multiple_accounts(&[*input_mint, output_mint])?;
+    let token_program = |index: usize| -> Result<Pubkey, Box<dyn std::error::Error>> {
+        Ok(mints[index].as_ref().ok_or("Raydium pool mint not found")?.owner)
+    };
+
+    Ok(vec![
+        AccountMeta::new_readonly(Pubkey::from_str(network::addresses().raydium_amm)?, false),
+        AccountMeta::new(*pool, false),
+        AccountMeta::new_readonly(Pubkey::from_str(network::addresses().raydium_amm_authority)?, false),
+        AccountMeta::new(pubkey_at(&data, OPEN_ORDERS_OFFSET)?, false),
+        AccountMeta::new(pubkey_at(&data, TARGET_ORDERS_OFFSET)?, false),
+        AccountMeta::new(pubkey_at(&data, COIN_VAULT_OFFSET)?, false),
+        AccountMeta::new(pubkey_at(&data, PC_VAULT_OFFSET)?, false),
+        AccountMeta::new_readonly(market_program, false),
+        AccountMeta::new(market, false),
+        AccountMeta::new(pubkey_at(&market_data, MARKET_BIDS_OFFSET)?, false),
+        AccountMeta::new(pubkey_at(&market_data, MARKET_ASKS_OFFSET)?, false),
+        AccountMeta::new(pubkey_at(&market_data, MARKET_EVENT_QUEUE_OFFSET)?, false),
+        AccountMeta::new(pubkey_at(&market_data, MARKET_COIN_VAULT_OFFSET)?, false),
+        AccountMeta::new(pubkey_at(&market_data, MARKET_PC_VAULT_OFFSET)?, false),
+        AccountMeta::new_readonly(vault_signer, false),
+        AccountMeta::new(get_associated_token_address_with_program_id(owner, input_mint, &token_program(0)?), false),
+        AccountMeta::new(get_associated_token_address_with_program_id(owner, &output_mint, &token_program(1)?), false),
+        AccountMeta::new_readonly(output_mint, false),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::mock::MockRpc;
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn swap_accounts_name_the_pool_market_and_owner_accounts() {
+        let rpc = MockRpc::new(1);
+        let (pool, market, owner) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let (coin_mint, pc_mint, market_program) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0u8; TARGET_ORDERS_OFFSET + 32];
+        for (offset, key) in [(COIN_MINT_OFFSET, coin_mint), (PC_MINT_OFFSET, pc_mint), (MARKET_OFFSET, market), (MARKET_PROGRAM_OFFSET, market_program)] {
+            data[offset..offset + 32].copy_from_slice(key.as_ref());
+        }
+        rpc.set_account(pool, Account { data, ..Account::default() });
+        // A nonce the market's vault signer derives with
+        let nonce = (0u64..).find(|nonce| Pubkey::create_program_address(&[market.as_ref(), &nonce.to_le_bytes()], &market_program).is_ok()).unwrap();
+        let mut market_data = vec![0u8; MARKET_ASKS_OFFSET + 32];
+        market_data[MARKET_VAULT_SIGNER_NONCE_OFFSET..MARKET_VAULT_SIGNER_NONCE_OFFSET + 8].copy_from_slice(&nonce.to_le_bytes());
+        rpc.set_account(market, Account { data: market_data, ..Account::default() });
+        rpc.set_account(coin_mint, Account { owner: anchor_spl::token::ID, ..Account::default() });
+        rpc.set_account(pc_mint, Account { owner: anchor_spl::token_2022::ID, ..Account::default() });
+
+        let selling_pc = leg_accounts(&rpc, &pool, &pc_mint, &owner).unwrap();
+        assert_eq!(selling_pc.len(), 18);
+        assert_eq!((selling_pc[7].pubkey, selling_pc[8].pubkey), (market_program, market));
+        assert_eq!(
+            selling_pc[15].pubkey,
+            get_associated_token_address_with_program_id(&owner, &pc_mint, &anchor_spl::token_2022::ID)
+        );
+        assert_eq!(selling_pc[16].pubkey, get_associated_token_address_with_program_id(&owner, &coin_mint, &anchor_spl::token::ID));
+        assert_eq!(selling_pc[17].pubkey, coin_mint);
+        assert!(leg_accounts(&rpc, &pool, &Pubkey::new_unique(), &owner).is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use crate::rpc::RpcApi;
+use anchor_spl::associated_token::get_associated_token_address_with_program_id;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+pub const PROGRAM_ID: &str = "CAMMCzo5YL8w4VFF8KVHrK22GGUsp5VTaW7grrKgrWqK";
+const MEMO_PROGRAM_ID: &str = "MemoSq4gqABAXKb96qnH8TysNcWxMyWCqXgDLGmfcHr";
+
+// Offsets in the CLMM pool state account
+const AMM_CONFIG_OFFSET: usize = 9;
+const MINT_0_OFFSET: usize = 73;
+const MINT_1_OFFSET: usize = 105;
+const VAULT_0_OFFSET: usize = 137;
+const VAULT_1_OFFSET: usize = 169;
+const OBSERVATION_OFFSET: usize = 201;
+const TICK_SPACING_OFFSET: usize = 235;
+const TICK_CURRENT_OFFSET: usize = 269;
+// Ticks per tick array, in units of the pool's tick spacing
+const TICK_ARRAY_SIZE: i32 = 60;
+// Tick arrays passed per swap: the current one and the next two in the swap direction
+const TICK_ARRAYS_PER_SWAP: i32 = 3;
+
+// Leg accounts for a `swap_v2` on `pool` selling `input_mint`, in the order the program expects:
+// program, amm config, pool, source, destination, input vault, output vault, observation, token
+// program, Token-2022 program, memo program, input mint, output mint, tick arrays. The source and
+// destination are `owner`'s associated token accounts
+pub fn leg_accounts(
+    rpc: &dyn RpcApi,
+    pool: &Pubkey,
+    input_mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Vec<AccountMeta>, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str(PROGRAM_ID)?;
+    let data = rpc.get_account_data(pool)?;
+    let bytes_at = |offset: usize, len: usize| {
+        data.get(offset..offset + len)
+            .ok_or_else(|| format!("Raydium CLMM pool {} is too short", pool))
+    };
+    let pubkey_at = |offset: usize| -> Result<Pubkey, Box<dyn std::error::Error>> {
+        Ok(Pubkey::new_from_array(bytes_at(offset, 32)?.try_into()?))
+    };
+
+    let mint_0 = pubkey_at(MINT_0_OFFSET)?;
+    let mint_1 = pubkey_at(MINT_1_OFFSET)?;
+    let zero_for_one = *input_mint == mint_0;
+    if !zero_for_one && *input_mint != mint_1 {
+        return Err(format!("Mint {} is not traded by Raydium CLMM pool {}", input_mint, pool).into());
+    }
+    let (output_mint, input_vault, output_vault) = if zero_for_one {
+        (mint_1, pubkey_at(VAULT_0_OFFSET)?, pubkey_at(VAULT_1_OFFSET)?)
+    } else {
+        (mint_0, pubkey_at(VAULT_1_OFFSET)?, pubkey_at(VAULT_0_OFFSET)?)
+    };
+    let tick_spacing = u16::from_le_bytes(bytes_at(TICK_SPACING_OFFSET, 2)?.try_into()?);
+    let tick_current = i32::from_le_bytes(bytes_at(TICK_CURRENT_OFFSET, 4)?.try_into()?);
+
+    // SPL Token or Token-2022 per mint, for the owner's associated token accounts
+    let mints = rpc.get_multiple_accounts(&[*input_mint, output_mint])?;
+    let token_program = |index: usize| -> Result<Pubkey, Box<dyn std::error::Error>> {
+        Ok(mints[index].as_ref().ok_or("Raydium CLMM mint not found")?.owner)
+    };
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(program_id, false),
+        AccountMeta::new_readonly(pubkey_at(AMM_CONFIG_OFFSET)?, false),
+        AccountMeta::new(*pool, false),
+        AccountMeta::new(get_associated_token_address_with_program_id(owner, input_mint, &token_program(0)?), false),
+        AccountMeta::new(get_associated_token_address_with_program_id(owner, &output_mint, &token_program(1)?), false),
+        AccountMeta::new(input_vault, false),
+        AccountMeta::new(output_vault, false),
+        AccountMeta::new(pubkey_at(OBSERVATION_OFFSET)?, false),
+        AccountMeta::new_readonly(anchor_spl::token::ID, false),
+        AccountMeta::new_readonly(anchor_spl::token_2022::ID, false),
+        AccountMeta::new_readonly(Pubkey::from_str(MEMO_PROGRAM_ID)?, false),
+        AccountMeta::new_readonly(*input_mint, false),
+        AccountMeta::new_readonly(output_mint, false),
+    ];
+    accounts.extend(
+        tick_arrays(&program_id, pool, tick_current, tick_spacing, zero_for_one)
+            .into_iter()
+            .map(|tick_array| AccountMeta::new(tick_array, false)),
+    );
+    Ok(accounts)
+}
+
+// Tick arrays a swap starting at `tick_current` walks through. Assumes the neighbouring arrays are
+// initialized, which holds for the liquid pools worth routing through
+fn tick_arrays(program_id: &Pubkey, pool: &Pubkey, tick_current: i32, tick_spacing: u16, zero_for_one: bool) -> Vec<Pubkey> {
+    let ticks_per_array = tick_spacing as i32 * TICK_ARRAY_SIZE;
+    let start = tick_current.div_euclid(ticks_per_array) * ticks_per_array;
+    // Selling token 0 moves the price, and the current tick, down
+    let step = if zero_for_one { -ticks_per_array } else { ticks_per_array };
+    (0..TICK_ARRAYS_PER_SWAP)
+        .map(|i| {
+            let start_index = start + i * step;
+            Pubkey::find_program_address(&[b"tick_array", pool.as_ref(), &start_index.to_be_bytes()], program_id).0
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::mock::MockRpc;
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn swap_accounts_follow_the_direction_sold() {
+        let rpc = MockRpc::new(1);
+        let (pool, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mint_0, mint_1, vault_0, vault_1) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0u8; TICK_CURRENT_OFFSET + 4];
+        for (offset, key) in [(MINT_0_OFFSET, mint_0), (MINT_1_OFFSET, mint_1), (VAULT_0_OFFSET, vault_0), (VAULT_1_OFFSET, vault_1)] {
+            data[offset..offset + 32].copy_from_slice(key.as_ref());
+        }
+        data[TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2].copy_from_slice(&10u16.to_le_bytes());
+        data[TICK_CURRENT_OFFSET..TICK_CURRENT_OFFSET + 4].copy_from_slice(&(-5i32).to_le_bytes());
+        rpc.set_account(pool, Account { data, ..Account::default() });
+        rpc.set_account(mint_0, Account { owner: anchor_spl::token::ID, ..Account::default() });
+        rpc.set_account(mint_1, Account { owner: anchor_spl::token_2022::ID, ..Account::default() });
+
+        let selling_1 = leg_accounts(&rpc, &pool, &mint_1, &owner).unwrap();
+        assert_eq!(selling_1.len(), 16);
+        assert_eq!((selling_1[5].pubkey, selling_1[6].pubkey), (vault_1, vault_0));
+        assert_eq!(
+            selling_1[3].pubkey,
+            get_associated_token_address_with_program_id(&owner, &mint_1, &anchor_spl::token_2022::ID)
+        );
+        // Tick -5 is in the array starting at -600, and selling token 0 walks down from it
+        let program_id = Pubkey::from_str(PROGRAM_ID).unwrap();
+        assert_eq!(tick_arrays(&program_id, &pool, -5, 10, true)[1], tick_arrays(&program_id, &pool, -1_195, 10, false)[0]);
+        assert!(leg_accounts(&rpc, &pool, &Pubkey::new_unique(), &owner).is_err());
+    }
+}
@@ -0,0 +1,272 @@
+use crate::commands;
+use crate::costs::{ExecutionReceipt, WSOL_MINT};
+use crate::events::{self, ArbExecutedEvent};
+use crate::failure;
+use crate::journal::{self, JournalEntry, TradeJournal, TradeStatus};
+use solana_client::rpc_client::{GetConfirmedSignaturesForAddress2Config, RpcClient};
+use solana_client::rpc_config::RpcTransactionConfig;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Signature, Signer};
+use solana_transaction_status::UiTransactionEncoding;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+// Checks the journal against the chain: the wallet's finalized transactions that called the
+// program are matched to journal entries by signature, so a trade that went unjournaled, e.g.
+// because the process crashed between the send and the write, or was journaled with the wrong
+// outcome, is found
+
+// Signatures per getSignaturesForAddress page, the most the RPC returns
+const PAGE_SIZE: usize = 1000;
+
+// A program transaction, as the chain recorded it
+#[derive(Debug, Clone)]
+pub struct OnChainTrade {
+    pub signature: String,
+    pub slot: u64,
+    pub block_time: Option<i64>,
+    pub fee_payer: Option<Pubkey>,
+    // The transaction error of a trade that failed on chain
+    pub error: Option<String>,
+    pub logs: Vec<String>,
+    pub event: Option<ArbExecutedEvent>,
+    // Its costs, read from its status metadata
+    pub receipt: Option<ExecutionReceipt>,
+}
+
+impl OnChainTrade {
+    // Journal entry for a trade only known from the chain
+    pub fn to_journal_entry(&self) -> JournalEntry {
+        let mut entry = match &self.event {
+            Some(event) => event.to_journal_entry(&self.signature),
+            None => {
+                let mut entry = JournalEntry::new(String::new(), String::new(), 0, TradeStatus::Failed);
+                entry.signature = Some(self.signature.clone());
+                entry
+            }
+        };
+        entry.slot = self.slot;
+        if let Some(receipt) = &self.receipt {
+            entry.costs = receipt.costs.clone();
+        }
+        if let Some(block_time) = self.block_time {
+            entry.timestamp = block_time as u64;
+        }
+        if let Some(error) = &self.error {
+            entry.status = TradeStatus::Failed;
+            entry.failure_class = Some(failure::classify(error, &self.logs));
+            entry.error = Some(error.clone());
+        }
+        entry
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum Discrepancy {
+    // On chain, but not in the journal
+    Unrecorded(Box<OnChainTrade>),
+    // Journaled as executed but failed on chain, or the other way around
+    StatusMismatch {
+        signature: String,
+        journaled: TradeStatus,
+        on_chain_error: Option<String>,
+    },
+    // The journaled realized profit isn't the one the program's event reports
+    ProfitMismatch {
+        signature: String,
+        journaled: Option<i64>,
+        on_chain: i64,
+    },
+    // Journaled as executed in the window with a signature the chain doesn't have
+    NotOnChain { signature: String },
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Discrepancy::Unrecorded(trade) => match &trade.error {
+                Some(error) => write!(f, "unrecorded failed trade {} in slot {}: {}", trade.signature, trade.slot, error),
+                None => write!(f, "unrecorded trade {} in slot {}", trade.signature, trade.slot),
+            },
+            Discrepancy::StatusMismatch {
+                signature,
+                journaled,
+                on_chain_error,
+            } => match on_chain_error {
+                Some(error) => write!(f, "{} journaled as {:?} but failed on chain: {}", signature, journaled, error),
+                None => write!(f, "{} journaled as {:?} but succeeded on chain", signature, journaled),
+            },
+            Discrepancy::ProfitMismatch {
+                signature,
+                journaled,
+                on_chain,
+            } => write!(
+                f,
+                "{} journaled with realized profit {} but the program reported {}",
+                signature,
+                journaled.map_or("none".to_string(), |profit| profit.to_string()),
+                on_chain
+            ),
+            Discrepancy::NotOnChain { signature } => {
+                write!(f, "{} journaled as executed but not among the wallet's program transactions", signature)
+            }
+        }
+    }
+}
+
+// Matches the chain's trades to the journal's entries by signature. Entries of executed trades are
+// only expected on chain when they're newer than `since`, the start of the walked history, and
+// when they went through the program, which inventory trades and dust sweeps don't
+pub fn reconcile(entries: &[JournalEntry], on_chain: &[OnChainTrade], since: u64) -> Vec<Discrepancy> {
+    let journaled: HashMap<&str, &JournalEntry> = entries
+        .iter()
+        .filter_map(|entry| entry.signature.as_deref().map(|signature| (signature, entry)))
+        .collect();
+    let mut discrepancies = Vec::new();
+    for trade in on_chain {
+        let Some(entry) = journaled.get(trade.signature.as_str()) else {
+            discrepancies.push(Discrepancy::Unrecorded(Box::new(trade.clone())));
+            continue;
+        };
+        if (entry.status == TradeStatus::Executed) != trade.error.is_none() {
+            discrepancies.push(Discrepancy::StatusMismatch {
+                signature: trade.signature.clone(),
+                journaled: entry.status,
+                on_chain_error: trade.error.clone(),
+            });
+        } else if let Some(event) = &trade.event {
+            if entry.realized_profit_lamports != Some(event.gross_profit_lamports()) {
+                discrepancies.push(Discrepancy::ProfitMismatch {
+                    signature: trade.signature.clone(),
+                    journaled: entry.realized_profit_lamports,
+                    on_chain: event.gross_profit_lamports(),
+                });
+            }
+        }
+    }
+    let on_chain: HashSet<&str> = on_chain.iter().map(|trade| trade.signature.as_str()).collect();
+    let expected = |entry: &&JournalEntry| {
+        entry.status == TradeStatus::Executed && entry.timestamp >= since && entry.inventory.is_none() && entry.sweep.is_none()
+    };
+    for entry in entries.iter().filter(expected) {
+        if let Some(signature) = entry.signature.as_deref().filter(|signature| !on_chain.contains(signature)) {
+            discrepancies.push(Discrepancy::NotOnChain {
+                signature: signature.to_string(),
+            });
+        }
+    }
+    discrepancies
+}
+
+// The finalized transactions of `address` since `since` that called the program, oldest first
+pub fn program_trades(
+    rpc_client: &RpcClient,
+    address: &Pubkey,
+    program: &Pubkey,
+    since: u64,
+) -> Result<Vec<OnChainTrade>, Box<dyn std::error::Error>> {
+    let mut trades = Vec::new();
+    let mut before = None;
+    loop {
+        let page = rpc_client.get_signatures_for_address_with_config(
+            address,
+            GetConfirmedSignaturesForAddress2Config {
+                before,
+                limit: Some(PAGE_SIZE),
+                commitment: Some(CommitmentConfig::finalized()),
+                ..GetConfirmedSignaturesForAddress2Config::default()
+            },
+        )?;
+        let done = page.len() < PAGE_SIZE;
+        for status in &page {
+            if status.block_time.is_some_and(|block_time| (block_time as u64) < since) {
+                trades.reverse();
+                return Ok(trades);
+            }
+            let signature = Signature::from_str(&status.signature)?;
+            let config = RpcTransactionConfig {
+                encoding: Some(UiTransactionEncoding::Base64),
+                commitment: Some(CommitmentConfig::finalized()),
+                max_supported_transaction_version: Some(0),
+            };
+            let confirmed = rpc_client.get_transaction_with_config(&signature, config)?;
+            let Some(transaction) = confirmed.transaction.transaction.decode() else {
+                continue;
+            };
+            let account_keys = transaction.message.static_account_keys();
+            if !account_keys.contains(program) {
+                continue;
+            }
+            let meta = confirmed.transaction.meta.as_ref();
+            let logs: Option<Vec<String>> = meta.and_then(|meta| meta.log_messages.clone().into());
+            let logs = logs.unwrap_or_default();
+            let event = events::parse_logs(&logs).into_iter().next();
+            // A trade's event replaces the balance-derived profit, and one that failed made none,
+            // so the profit mint doesn't matter
+            let loan_amount = event.as_ref().map_or(0, |event| event.amount_in);
+            let receipt = ExecutionReceipt::from_confirmed(confirmed, loan_amount, WSOL_MINT).ok();
+            trades.push(OnChainTrade {
+                signature: status.signature.clone(),
+                slot: status.slot,
+                block_time: status.block_time,
+                fee_payer: account_keys.first().copied(),
+                error: status.err.as_ref().map(|err| err.to_string()),
+                logs,
+                event,
+                receipt,
+            });
+        }
+        match page.last() {
+            Some(last) if !done => before = Some(Signature::from_str(&last.signature)?),
+            _ => break,
+        }
+    }
+    trades.reverse();
+    Ok(trades)
+}
+
+// `reconcile [--since <unix timestamp>] [--record]`: walks the wallet's program transactions since
+// the timestamp (default a day ago) and prints every one that doesn't match the journal, exiting
+// non-zero if there are any. --record appends entries for the unrecorded ones
+pub fn run(args: &[String], wallet_keypair_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let wallet = read_keypair_file(wallet_keypair_path)?;
+    let program = Pubkey::from_str(&env::var("SOLANA_PROGRAM_ID")?)?;
+    let since = match commands::flag_value(args, "--since") {
+        Some(since) => since.parse()?,
+        None => journal::unix_timestamp().saturating_sub(crate::report::SECONDS_PER_DAY),
+    };
+    let rpc_client = RpcClient::new_with_commitment(
+        env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string()),
+        CommitmentConfig::finalized(),
+    );
+    let journal = commands::journal_from_env();
+
+    let trades = program_trades(&rpc_client, &wallet.pubkey(), &program, since)?;
+    let discrepancies = reconcile(&journal.load()?, &trades, since);
+    println!("{} program transactions since {}, {} discrepancies", trades.len(), since, discrepancies.len());
+    for discrepancy in &discrepancies {
+        println!("  {}", discrepancy);
+    }
+    if args.iter().any(|arg| arg == "--record") {
+        record_unrecorded(&journal, &discrepancies)?;
+    }
+    if !discrepancies.is_empty() {
+        return Err(format!("{} trades don't match the journal", discrepancies.len()).into());
+    }
+    Ok(())
+}
+
+fn record_unrecorded(journal: &TradeJournal, discrepancies: &[Discrepancy]) -> Result<(), Box<dyn std::error::Error>> {
+    let mut recorded = 0;
+    for discrepancy in discrepancies {
+        if let Discrepancy::Unrecorded(trade) = discrepancy {
+            journal.record(&trade.to_journal_entry())?;
+            recorded += 1;
+        }
+    }
+    println!("Journaled {} unrecorded trades", recorded);
+    Ok(())
+}
@@ -0,0 +1,121 @@
+use crate::evaluator::Opportunity;
+use crate::journal::unix_timestamp;
+use crate::market_data::{MarketSnapshot, RecordedAccount};
+use crate::rpc::RpcApi;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashMap;
+use std::env;
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// Snapshots are buffered and flushed as one gzip member per batch into hourly files
+const FLUSH_BATCH_SIZE: usize = 50;
+
+pub struct MarketRecorder {
+    dir: PathBuf,
+    interval: Duration,
+    last_recorded: Mutex<HashMap<String, Instant>>,
+    pending: Mutex<Vec<MarketSnapshot>>,
+}
+
+impl MarketRecorder {
+    // Enabled by setting RECORD_DIR; RECORD_INTERVAL_SECS sets the per-pair snapshot frequency
+    pub fn from_env() -> Option<Self> {
+        let dir = env::var("RECORD_DIR").ok()?;
+        let interval_secs = env::var("RECORD_INTERVAL_SECS")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .unwrap_or(5);
+
+        Some(Self {
+            dir: PathBuf::from(dir),
+            interval: Duration::from_secs(interval_secs),
+            last_recorded: Mutex::new(HashMap::new()),
+            pending: Mutex::new(Vec::new()),
+        })
+    }
+
+    pub fn is_due(&self, pair_key: &str) -> bool {
+        let last_recorded = self.last_recorded.lock().unwrap();
+        match last_recorded.get(pair_key) {
+            Some(last) => last.elapsed() >= self.interval,
+            None => true,
+        }
+    }
+
+    // Captures the pool/oracle accounts at the current slot together with the quote that was acted on
+    pub fn record(
+        &self,
+        rpc_client: &dyn RpcApi,
+        token_a: &Pubkey,
+        token_b: &Pubkey,
+        loan_amount: u64,
+        opportunity: &Opportunity,
+        accounts: &[Pubkey],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Executable opportunities are always captured so they can be replayed later
+        let pair_key = format!("{}/{}", token_a, token_b);
+        if !self.is_due(&pair_key) && opportunity.size.is_none() {
+            return Ok(());
+        }
+
+        let response = rpc_client.get_multiple_accounts_with_commitment(accounts, rpc_client.commitment())?;
+        let recorded = accounts
+            .iter()
+            .zip(response.value)
+            .filter_map(|(pubkey, account)| {
+                account.map(|account| RecordedAccount {
+                    pubkey: pubkey.to_string(),
+                    data: base64::encode(&account.data),
+                })
+            })
+            .collect();
+
+        let snapshot = MarketSnapshot {
+            opportunity_id: opportunity.id.clone(),
+            slot: response.context.slot,
+            timestamp: unix_timestamp(),
+            token_a: token_a.to_string(),
+            token_b: token_b.to_string(),
+            loan_amount,
+            quote: opportunity.quote.clone(),
+            sol_price: opportunity.sol_price,
+            accounts: recorded,
+        };
+
+        self.last_recorded.lock().unwrap().insert(pair_key, Instant::now());
+        let should_flush = {
+            let mut pending = self.pending.lock().unwrap();
+            pending.push(snapshot);
+            pending.len() >= FLUSH_BATCH_SIZE
+        };
+        if should_flush {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let batch: Vec<MarketSnapshot> = std::mem::take(&mut *self.pending.lock().unwrap());
+        if batch.is_empty() {
+            return Ok(());
+        }
+
+        std::fs::create_dir_all(&self.dir)?;
+        let hour = chrono::Utc::now().format("%Y%m%d-%H");
+        let path = self.dir.join(format!("market-{}.jsonl.gz", hour));
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        for snapshot in &batch {
+            writeln!(encoder, "{}", serde_json::to_string(snapshot)?)?;
+        }
+        encoder.finish()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,103 @@
+use crate::rpc::RpcApi;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::system_instruction::SystemInstruction;
+use solana_sdk::system_program;
+use std::collections::HashMap;
+use std::str::FromStr;
+
+// Rent a transaction locks up in the accounts its instructions create, and frees from the accounts
+// they close, worked out before it's sent so the profitability check can count it. Covers
+// associated token accounts that don't exist yet, accounts created through the system program,
+// such as temporary WSOL accounts and nonce accounts, and address lookup tables. An account
+// created and closed in the same transaction costs nothing
+
+const ADDRESS_LOOKUP_TABLE_PROGRAM: &str = "AddressLookupTab1e1111111111111111111111111";
+// Lookup table header, before any addresses are extended into it
+const LOOKUP_TABLE_META_LEN: usize = 56;
+const TOKEN_ACCOUNT_LEN: usize = 165;
+// With the ImmutableOwner extension every Token-2022 associated account has
+const TOKEN_2022_ACCOUNT_LEN: usize = 170;
+const CLOSE_ACCOUNT_TAG: u8 = 9;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AccountRent {
+    pub locked_lamports: u64,
+    pub reclaimed_lamports: u64,
+}
+
+impl AccountRent {
+    // What the transaction costs in rent; negative when it frees more than it locks
+    pub fn net_lamports(&self) -> i64 {
+        self.locked_lamports as i64 - self.reclaimed_lamports as i64
+    }
+}
+
+// The rent `instructions` lock and free, with the accounts they'd create or close read from `rpc`
+pub fn account_rent(rpc: &dyn RpcApi, instructions: &[Instruction]) -> Result<AccountRent, Box<dyn std::error::Error>> {
+    let associated_token_program = anchor_spl::associated_token::ID;
+    let lookup_table_program = Pubkey::from_str(ADDRESS_LOOKUP_TABLE_PROGRAM)?;
+    // Rent per account created, and whether it's only created when it doesn't exist yet
+    let mut created: HashMap<Pubkey, (u64, bool)> = HashMap::new();
+    let mut closed = Vec::new();
+    let address = |instruction: &Instruction, index: usize| instruction.accounts.get(index).map(|meta| meta.pubkey);
+
+    for instruction in instructions {
+        let program_id = instruction.program_id;
+        if program_id == associated_token_program {
+            // Create and CreateIdempotent alike; either way nothing is paid for an existing account
+            if matches!(instruction.data.first(), None | Some(0) | Some(1)) {
+                let len = match address(instruction, 5) {
+                    Some(token_program) if token_program == anchor_spl::token_2022::ID => TOKEN_2022_ACCOUNT_LEN,
+                    _ => TOKEN_ACCOUNT_LEN,
+                };
+                if let Some(account) = address(instruction, 1) {
+                    created.insert(account, (Rent::default().minimum_balance(len), true));
+                }
+            }
+        } else if program_id == system_program::id() {
+            match bincode::deserialize(&instruction.data) {
+                Ok(SystemInstruction::CreateAccount { lamports, .. })
+                | Ok(SystemInstruction::CreateAccountWithSeed { lamports, .. }) => {
+                    if let Some(account) = address(instruction, 1) {
+                        created.insert(account, (lamports, false));
+                    }
+                }
+                _ => {}
+            }
+        } else if program_id == lookup_table_program {
+            // CreateLookupTable; the program funds the table from the payer
+            if instruction.data.starts_with(&[0, 0, 0, 0]) {
+                if let Some(table) = address(instruction, 0) {
+                    created.insert(table, (Rent::default().minimum_balance(LOOKUP_TABLE_META_LEN), false));
+                }
+            }
+        } else if (program_id == anchor_spl::token::ID || program_id == anchor_spl::token_2022::ID)
+            && instruction.data.first() == Some(&CLOSE_ACCOUNT_TAG)
+        {
+            if let Some(account) = address(instruction, 0) {
+                closed.push(account);
+            }
+        }
+    }
+
+    let mut rent = AccountRent::default();
+    let mut lookups: Vec<Pubkey> = created.iter().filter(|(_, (_, if_missing))| *if_missing).map(|(key, _)| *key).collect();
+    lookups.extend(closed.iter().filter(|account| !created.contains_key(account)));
+    let existing: HashMap<Pubkey, u64> = lookups
+        .iter()
+        .zip(rpc.get_multiple_accounts_chunked(&lookups)?)
+        .filter_map(|(key, account)| Some((*key, account?.lamports)))
+        .collect();
+    for (account, (lamports, if_missing)) in &created {
+        let exists = *if_missing && existing.contains_key(account);
+        if !exists && !closed.contains(account) {
+            rent.locked_lamports += lamports;
+        }
+    }
+    for account in closed.iter().filter(|account| !created.contains_key(account)) {
+        rent.reclaimed_lamports += existing.get(account).copied().unwrap_or(0);
+    }
+    Ok(rent)
+}
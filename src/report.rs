@@ -0,0 +1,237 @@
+use crate::failure::FailureClass;
+use crate::journal::{JournalEntry, TradeStatus};
+use crate::landing::LandingStats;
+use crate::pair_stats::PairAnalytics;
+use crate::risk::RiskMetrics;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::Write as _;
+use std::path::Path;
+
+pub const SECONDS_PER_DAY: u64 = 86_400;
+const PAIRS_SHOWN: usize = 3;
+
+#[derive(Debug, Default)]
+pub struct PerformanceReport {
+    pub day: u64,
+    pub attempts: usize,
+    pub trades: usize,
+    pub winning_trades: usize,
+    pub fee_spend_lamports: i64,
+    // Rent put into accounts the trades opened and taken back from those they closed, part of the
+    // fee spend
+    pub rent_locked_lamports: u64,
+    pub rent_reclaimed_lamports: u64,
+    // P&L valued in USD at execution, so SOL and token profits add up; entries that couldn't be
+    // priced are counted instead
+    pub gross_profit_usd: f64,
+    pub net_profit_usd: f64,
+    pub fee_spend_usd: f64,
+    pub unpriced: usize,
+    // Net USD P&L per pair, best first
+    pub pairs: Vec<(String, f64)>,
+    // Attempts and net USD P&L per config profile they ran under, by name; empty when none did
+    pub profiles: Vec<(String, usize, f64)>,
+    // Each pair's opportunities, executions, win rate, profit, slippage and fees over the day
+    pub pair_performance: PairAnalytics,
+    // Drawdown, P&L variance, largest loss and fee ratio over the risk window ending with the day
+    pub risk: Option<RiskMetrics>,
+    // Failed attempts per failure class, most frequent first
+    pub failures: Vec<(FailureClass, usize)>,
+    // Landing rate and latency of the day's sent transactions per submission backend
+    pub landing: LandingStats,
+}
+
+impl PerformanceReport {
+    // Summarise the journal entries that fall on the given UTC day (days since the epoch)
+    pub fn for_day(entries: &[JournalEntry], day: u64) -> Self {
+        let mut report = Self {
+            day,
+            ..Default::default()
+        };
+        let mut pair_pnl: HashMap<String, f64> = HashMap::new();
+        let mut failures: HashMap<FailureClass, usize> = HashMap::new();
+        let mut profiles: BTreeMap<String, (usize, f64)> = BTreeMap::new();
+
+        for entry in entries.iter().filter(|e| e.timestamp / SECONDS_PER_DAY == day) {
+            report.attempts += 1;
+            report.landing.observe(entry);
+            report.pair_performance.observe(entry);
+            report.fee_spend_lamports += entry.costs.total_lamports();
+            report.rent_locked_lamports += entry.costs.rent_paid_lamports;
+            report.rent_reclaimed_lamports += entry.costs.rent_reclaimed_lamports;
+            if let Some(profile) = &entry.config_profile {
+                let (attempts, net) = profiles.entry(profile.clone()).or_insert((0, 0.0));
+                *attempts += 1;
+                *net += entry.net_profit_usd().unwrap_or(0.0);
+            }
+            match (entry.gross_profit_usd(), entry.costs_usd()) {
+                (Some(gross), Some(costs)) => {
+                    report.gross_profit_usd += gross;
+                    report.fee_spend_usd += costs;
+                    report.net_profit_usd += gross - costs;
+                    *pair_pnl.entry(entry.pair_key()).or_insert(0.0) += gross - costs;
+                }
+                _ => report.unpriced += 1,
+            }
+
+            if entry.status == TradeStatus::Executed {
+                report.trades += 1;
+                // Unpriced trades are judged in their raw units, as before valuation
+                if entry.net_profit_usd().map_or(entry.net_profit_lamports() > 0, |net| net > 0.0) {
+                    report.winning_trades += 1;
+                }
+            } else {
+                *failures.entry(entry.failure_class.unwrap_or(FailureClass::Other)).or_insert(0) += 1;
+            }
+        }
+
+        report.pairs = pair_pnl.into_iter().collect();
+        report.pairs.sort_by(|a, b| b.1.total_cmp(&a.1));
+        report.profiles = profiles.into_iter().map(|(profile, (attempts, net))| (profile, attempts, net)).collect();
+        report.failures = failures.into_iter().collect();
+        report.failures.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        report
+    }
+
+    pub fn win_rate(&self) -> f64 {
+        ratio(self.winning_trades, self.trades)
+    }
+
+    pub fn landing_rate(&self) -> f64 {
+        ratio(self.trades, self.attempts)
+    }
+
+    pub fn date(&self) -> String {
+        chrono::DateTime::from_timestamp((self.day * SECONDS_PER_DAY) as i64, 0)
+            .map(|dt| dt.date_naive().to_string())
+            .unwrap_or_else(|| self.day.to_string())
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "Performance report for {}", self.date());
+        let _ = writeln!(out, "Trades: {} landed / {} attempted", self.trades, self.attempts);
+        let _ = writeln!(out, "Landing rate: {:.1}%", self.landing_rate() * 100.0);
+        let _ = writeln!(out, "Win rate: {:.1}%", self.win_rate() * 100.0);
+        let _ = writeln!(out, "Gross P&L: ${:.2}", self.gross_profit_usd);
+        let _ = writeln!(
+            out,
+            "Fee spend: {} SOL (${:.2})",
+            lamports_to_sol(self.fee_spend_lamports),
+            self.fee_spend_usd
+        );
+        if self.rent_locked_lamports > 0 || self.rent_reclaimed_lamports > 0 {
+            let _ = writeln!(
+                out,
+                "Rent: {} SOL locked, {} SOL reclaimed",
+                lamports_to_sol(self.rent_locked_lamports as i64),
+                lamports_to_sol(self.rent_reclaimed_lamports as i64)
+            );
+        }
+        let _ = writeln!(out, "Net P&L: ${:.2}", self.net_profit_usd);
+        if self.unpriced > 0 {
+            let _ = writeln!(out, "Unpriced attempts (not in the USD figures): {}", self.unpriced);
+        }
+
+        if !self.pairs.is_empty() {
+            let _ = writeln!(out, "Top pairs:");
+            for (pair, pnl) in self.pairs.iter().take(PAIRS_SHOWN) {
+                let _ = writeln!(out, "  {} ${:.2}", pair, pnl);
+            }
+            let _ = writeln!(out, "Bottom pairs:");
+            for (pair, pnl) in self.pairs.iter().rev().take(PAIRS_SHOWN) {
+                let _ = writeln!(out, "  {} ${:.2}", pair, pnl);
+            }
+        }
+
+        if !self.profiles.is_empty() {
+            let _ = writeln!(out, "Config profiles:");
+            for (profile, attempts, net) in &self.profiles {
+                let _ = writeln!(out, "  {}: {} attempts, ${:.2}", profile, attempts, net);
+            }
+        }
+
+        if let Some(risk) = &self.risk {
+            let _ = writeln!(out, "Risk:");
+            out.push_str(&risk.render());
+        }
+
+        if !self.pair_performance.is_empty() {
+            let _ = writeln!(out, "Per pair:");
+            out.push_str(&self.pair_performance.render());
+        }
+
+        if !self.failures.is_empty() {
+            let _ = writeln!(out, "Failures:");
+            for (class, count) in &self.failures {
+                let _ = writeln!(out, "  {}: {}", class, count);
+            }
+        }
+
+        if !self.landing.is_empty() {
+            let _ = writeln!(out, "Submission backends:");
+            out.push_str(&self.landing.summary());
+        }
+        out
+    }
+
+    // Writes `report-YYYY-MM-DD.txt` into the report directory and returns its path
+    pub fn write_to_dir(&self, dir: &Path) -> Result<String, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(format!("report-{}.txt", self.date()));
+        std::fs::write(&path, self.render())?;
+        Ok(path.display().to_string())
+    }
+}
+
+pub fn lamports_to_sol(lamports: i64) -> f64 {
+    lamports as f64 / 1_000_000_000.0
+}
+
+fn ratio(numerator: usize, denominator: usize) -> f64 {
+    if denominator == 0 {
+        0.0
+    } else {
+        numerator as f64 / denominator as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn report_covers_only_the_days_entries() {
+        let day = 20_000;
+        let entry = |pair: &str, status, profit_lamports, fee_lamports| {
+            let mut entry = JournalEntry::new(pair.to_string(), "B".to_string(), 1_000_000_000, status);
+            entry.timestamp = day * SECONDS_PER_DAY + 60;
+            entry.sol_price = 100.0;
+            entry.expected_profit_lamports = profit_lamports;
+            entry.costs.base_fee_lamports = fee_lamports;
+            entry.costs.rent_paid_lamports = 2_039_280;
+            entry
+        };
+        let mut yesterday = entry("A", TradeStatus::Executed, 900_000_000, 0);
+        yesterday.timestamp -= SECONDS_PER_DAY;
+        let entries = [
+            entry("A", TradeStatus::Executed, 50_000_000, 10_000_000),
+            entry("C", TradeStatus::Executed, 0, 10_000_000),
+            entry("C", TradeStatus::Failed, 0, 10_000_000),
+            yesterday,
+        ];
+
+        let report = PerformanceReport::for_day(&entries, day);
+        assert_eq!((report.attempts, report.trades, report.winning_trades), (3, 2, 1));
+        assert!((report.gross_profit_usd - 5.0).abs() < 1e-9);
+        // Each attempt's fee and the rent it locked
+        assert_eq!(report.fee_spend_lamports, 3 * 12_039_280);
+        assert!((report.net_profit_usd - (5.0 - 3.0 * 0.012_039_28 * 100.0)).abs() < 1e-9);
+        assert_eq!(report.pairs[0].0, "A/B");
+        assert_eq!(report.failures, vec![(FailureClass::Other, 1)]);
+        let rendered = report.render();
+        assert!(rendered.contains("Performance report for 2024-10-04"));
+        assert!(rendered.contains("Trades: 2 landed / 3 attempted"));
+        assert!(rendered.contains("Rent: 0.00611784 SOL locked, 0 SOL reclaimed"));
+    }
+}
@@ -0,0 +1,286 @@
+use crate::landing::Backend;
+use crate::rpc::RpcApi;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_config::{RpcSimulateTransactionConfig, RpcTransactionConfig};
+use solana_client::rpc_request::{RpcError, RpcResponseErrorData};
+use solana_client::rpc_response::{RpcResult, RpcSimulateTransactionResult};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::hash_map::RandomState;
+use std::env;
+use std::future::Future;
+use std::hash::{BuildHasher, Hasher};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+
+// Retries for RPC and HTTP calls. Only errors that say nothing about the request itself are
+// retried (connection failures, timeouts, rate limits, unhealthy nodes), with exponential backoff
+// and full jitter. Each method has its own policy: reads are retried, sends never are, since a send
+// that timed out may still land. A budget caps retries to a fraction of successful calls, so an
+// outage doesn't multiply the load on the endpoint
+
+#[derive(Debug, Clone, Copy)]
+pub struct RetryPolicy {
+    pub retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl RetryPolicy {
+    // Account, slot and blockhash reads
+    pub const READ: RetryPolicy = RetryPolicy {
+        retries: 3,
+        base_delay: Duration::from_millis(50),
+        max_delay: Duration::from_secs(1),
+    };
+    // Simulations and quotes, which go stale quickly
+    pub const HOT: RetryPolicy = RetryPolicy {
+        retries: 1,
+        base_delay: Duration::from_millis(25),
+        max_delay: Duration::from_millis(100),
+    };
+    // Token and pool lists, fetched off the hot path
+    pub const BULK: RetryPolicy = RetryPolicy {
+        retries: 3,
+        base_delay: Duration::from_millis(500),
+        max_delay: Duration::from_secs(5),
+    };
+
+    // Up to base_delay * 2^attempt, capped, drawn uniformly
+    fn delay(&self, attempt: u32) -> Duration {
+        let ceiling = self.base_delay.saturating_mul(1 << attempt.min(16)).min(self.max_delay);
+        let jitter = RandomState::new().build_hasher().finish() as f64 / u64::MAX as f64;
+        ceiling.mul_f64(jitter)
+    }
+}
+
+// Delay before each reconnect attempt of a subscription: doubling from `base` up to `max`,
+// jittered, and starting over once a connection holds
+pub struct Backoff {
+    policy: RetryPolicy,
+    attempt: u32,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, max: Duration) -> Self {
+        Self {
+            policy: RetryPolicy {
+                retries: u32::MAX,
+                base_delay: base,
+                max_delay: max,
+            },
+            attempt: 0,
+        }
+    }
+
+    pub fn next_delay(&mut self) -> Duration {
+        let delay = self.policy.delay(self.attempt);
+        self.attempt = self.attempt.saturating_add(1);
+        delay
+    }
+
+    pub fn reset(&mut self) {
+        self.attempt = 0;
+    }
+}
+
+// Token bucket of retries: each retry takes a token, each success returns a fraction of one
+pub struct RetryBudget {
+    tokens: Mutex<f64>,
+    max_tokens: f64,
+    refill_per_success: f64,
+}
+
+impl RetryBudget {
+    pub fn new(max_tokens: f64, refill_per_success: f64) -> Self {
+        Self {
+            tokens: Mutex::new(max_tokens),
+            max_tokens,
+            refill_per_success,
+        }
+    }
+
+    // RETRY_BUDGET retries, refilled at one per ten successful calls
+    pub fn from_env() -> Self {
+        Self::new(env::var("RETRY_BUDGET").ok().and_then(|v| v.parse().ok()).unwrap_or(20.0), 0.1)
+    }
+
+    fn withdraw(&self) -> bool {
+        let mut tokens = self.tokens.lock().unwrap();
+        if *tokens < 1.0 {
+            return false;
+        }
+        *tokens -= 1.0;
+        true
+    }
+
+    fn deposit(&self) {
+        let mut tokens = self.tokens.lock().unwrap();
+        *tokens = (*tokens + self.refill_per_success).min(self.max_tokens);
+    }
+}
+
+// JSON-RPC codes of a node that is behind or can't serve the request yet
+const RPC_NODE_UNHEALTHY: i64 = -32005;
+const RPC_BLOCK_NOT_AVAILABLE: i64 = -32004;
+
+pub fn is_retryable_rpc(error: &ClientError) -> bool {
+    match error.kind() {
+        ClientErrorKind::Io(_) => true,
+        ClientErrorKind::Reqwest(e) => is_retryable_http(e),
+        ClientErrorKind::RpcError(RpcError::RpcResponseError { code, data, .. }) => {
+            matches!(*code, RPC_NODE_UNHEALTHY | RPC_BLOCK_NOT_AVAILABLE)
+                || matches!(data, RpcResponseErrorData::NodeUnhealthy { .. })
+        }
+        _ => false,
+    }
+}
+
+pub fn is_retryable_http(error: &reqwest::Error) -> bool {
+    error.is_timeout()
+        || error.is_connect()
+        || error
+            .status()
+            .is_some_and(|status| status == reqwest::StatusCode::TOO_MANY_REQUESTS || status.is_server_error())
+}
+
+// `call` under `policy`, for the blocking RPC client
+pub fn rpc<T>(
+    policy: &RetryPolicy,
+    budget: &RetryBudget,
+    method: &str,
+    mut call: impl FnMut() -> ClientResult<T>,
+) -> ClientResult<T> {
+    let mut attempt = 0;
+    loop {
+        match call() {
+            Ok(value) => {
+                budget.deposit();
+                return Ok(value);
+            }
+            Err(e) if attempt < policy.retries && is_retryable_rpc(&e) && budget.withdraw() => {
+                println!("Retrying {} after attempt {} failed: {}", method, attempt + 1, e);
+                std::thread::sleep(policy.delay(attempt));
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+fn http_budget() -> &'static RetryBudget {
+    static BUDGET: OnceLock<RetryBudget> = OnceLock::new();
+    BUDGET.get_or_init(RetryBudget::from_env)
+}
+
+// The request `request` builds and sends, under `policy`, drawing on the HTTP retry budget
+pub async fn http<T, F, Fut>(policy: &RetryPolicy, url: &str, mut request: F) -> Result<T, reqwest::Error>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T, reqwest::Error>>,
+{
+    let mut attempt = 0;
+    loop {
+        match request().await {
+            Ok(value) => {
+                http_budget().deposit();
+                return Ok(value);
+            }
+            Err(e) if attempt < policy.retries && is_retryable_http(&e) && http_budget().withdraw() => {
+                println!("Retrying {} after attempt {} failed: {}", url, attempt + 1, e);
+                tokio::time::sleep(policy.delay(attempt)).await;
+                attempt += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// Wraps another RpcApi with a policy per method
+pub struct RetryingRpc {
+    inner: Arc<dyn RpcApi>,
+    budget: RetryBudget,
+}
+
+impl RetryingRpc {
+    pub fn new(inner: Arc<dyn RpcApi>, budget: RetryBudget) -> Self {
+        Self { inner, budget }
+    }
+
+    fn call<T>(&self, policy: &RetryPolicy, method: &str, call: impl FnMut() -> ClientResult<T>) -> ClientResult<T> {
+        rpc(policy, &self.budget, method, call)
+    }
+}
+
+impl RpcApi for RetryingRpc {
+    fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    fn get_slot(&self) -> ClientResult<Slot> {
+        self.call(&RetryPolicy::READ, "getSlot", || self.inner.get_slot())
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.call(&RetryPolicy::READ, "getLatestBlockhash", || self.inner.get_latest_blockhash())
+    }
+
+    fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)> {
+        self.call(&RetryPolicy::READ, "getLatestBlockhash", || self.inner.get_latest_blockhash_with_height())
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.call(&RetryPolicy::READ, "getAccountInfo", || self.inner.get_account(pubkey))
+    }
+
+    fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        self.call(&RetryPolicy::READ, "getMultipleAccounts", || {
+            self.inner.get_multiple_accounts_with_commitment(pubkeys, commitment)
+        })
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.call(&RetryPolicy::HOT, "simulateTransaction", || {
+            self.inner.simulate_transaction_with_config(transaction, config.clone())
+        })
+    }
+
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        self.call(&RetryPolicy::READ, "getFeeForMessage", || self.inner.get_fee_for_message(message))
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.inner.send_and_confirm_transaction(transaction)
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.call(&RetryPolicy::READ, "getTransaction", || self.inner.get_transaction_with_config(signature, config))
+    }
+
+    fn send_and_confirm_transaction_timed(
+        &self,
+        transaction: &Transaction,
+        last_valid_block_height: u64,
+    ) -> ClientResult<(Signature, Duration, Backend)> {
+        self.inner.send_and_confirm_transaction_timed(transaction, last_valid_block_height)
+    }
+}
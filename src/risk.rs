@@ -0,0 +1,153 @@
+use crate::journal::JournalEntry;
+use crate::report::SECONDS_PER_DAY;
+use std::collections::{BTreeMap, VecDeque};
+use std::env;
+use std::fmt::Write as _;
+
+// Risk figures from the journal's USD P&L over the latest RISK_WINDOW_DAYS, to set loss limits
+// from what the bot has actually done. Entries that couldn't be priced are left out, as in the
+// daily report
+
+pub struct RiskConfig {
+    pub window_days: u64,
+}
+
+impl RiskConfig {
+    pub fn from_env() -> Self {
+        Self {
+            window_days: env::var("RISK_WINDOW_DAYS").ok().and_then(|v| v.parse().ok()).unwrap_or(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PricedTrade {
+    timestamp: u64,
+    gross_usd: f64,
+    costs_usd: f64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RiskMetrics {
+    // Largest fall of the cumulative net P&L from a previous peak, and its fall from the latest peak
+    pub max_drawdown_usd: f64,
+    pub current_drawdown_usd: f64,
+    // Variance of the net P&L per UTC day, days without trades counting as zero
+    pub daily_pnl_variance: f64,
+    // Net loss of the worst single attempt, 0 without losing ones
+    pub largest_loss_usd: f64,
+    // Costs over gross profit; None without gross profit
+    pub fee_to_profit_ratio: Option<f64>,
+    pub days: u64,
+}
+
+impl RiskMetrics {
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "  Max drawdown: ${:.2} (current ${:.2})", self.max_drawdown_usd, self.current_drawdown_usd);
+        let _ = writeln!(
+            out,
+            "  Daily P&L std dev: ${:.2} over {} days",
+            self.daily_pnl_variance.sqrt(),
+            self.days
+        );
+        let _ = writeln!(out, "  Largest loss: ${:.2}", self.largest_loss_usd);
+        if let Some(ratio) = self.fee_to_profit_ratio {
+            let _ = writeln!(out, "  Fee to profit: {:.2}", ratio);
+        }
+        out
+    }
+
+    // Prometheus text format
+    pub fn metrics(&self) -> String {
+        let gauges = [
+            ("arb_risk_max_drawdown_usd", "Largest fall of cumulative net P&L from a peak", Some(self.max_drawdown_usd)),
+            ("arb_risk_current_drawdown_usd", "Fall of cumulative net P&L from its latest peak", Some(self.current_drawdown_usd)),
+            ("arb_risk_daily_pnl_variance", "Variance of daily net USD P&L", Some(self.daily_pnl_variance)),
+            ("arb_risk_largest_loss_usd", "Net loss of the worst single attempt", Some(self.largest_loss_usd)),
+            ("arb_risk_fee_to_profit_ratio", "Costs over gross profit", self.fee_to_profit_ratio),
+        ];
+        let mut text = String::new();
+        for (name, help, value) in gauges {
+            text.push_str(&format!("# HELP {} {} over the risk window\n# TYPE {} gauge\n", name, help, name));
+            if let Some(value) = value {
+                text.push_str(&format!("{} {}\n", name, value));
+            }
+        }
+        text
+    }
+}
+
+// The priced attempts of the latest window, oldest first
+pub struct RiskTracker {
+    window_secs: u64,
+    trades: VecDeque<PricedTrade>,
+}
+
+impl RiskTracker {
+    pub fn new(config: &RiskConfig) -> Self {
+        Self {
+            window_secs: config.window_days * SECONDS_PER_DAY,
+            trades: VecDeque::new(),
+        }
+    }
+
+    pub fn from_entries<'a>(config: &RiskConfig, entries: impl IntoIterator<Item = &'a JournalEntry>) -> Self {
+        let mut tracker = Self::new(config);
+        for entry in entries {
+            tracker.observe(entry);
+        }
+        tracker
+    }
+
+    pub fn observe(&mut self, entry: &JournalEntry) {
+        let (Some(gross_usd), Some(costs_usd)) = (entry.gross_profit_usd(), entry.costs_usd()) else {
+            return;
+        };
+        self.trades.push_back(PricedTrade {
+            timestamp: entry.timestamp,
+            gross_usd,
+            costs_usd,
+        });
+        let cutoff = entry.timestamp.saturating_sub(self.window_secs);
+        while self.trades.front().is_some_and(|trade| trade.timestamp < cutoff) {
+            self.trades.pop_front();
+        }
+    }
+
+    // The figures over the window ending at `now`, inclusive
+    pub fn metrics_at(&self, now: u64) -> RiskMetrics {
+        let start = now.saturating_sub(self.window_secs);
+        let mut trades: Vec<&PricedTrade> =
+            self.trades.iter().filter(|trade| trade.timestamp > start && trade.timestamp <= now).collect();
+        trades.sort_by_key(|trade| trade.timestamp);
+
+        let mut metrics = RiskMetrics::default();
+        let (mut cumulative, mut peak, mut gross, mut costs) = (0.0f64, 0.0f64, 0.0, 0.0);
+        let mut daily: BTreeMap<u64, f64> = BTreeMap::new();
+        for trade in &trades {
+            let net = trade.gross_usd - trade.costs_usd;
+            cumulative += net;
+            peak = peak.max(cumulative);
+            metrics.max_drawdown_usd = metrics.max_drawdown_usd.max(peak - cumulative);
+            metrics.largest_loss_usd = metrics.largest_loss_usd.max(-net);
+            gross += trade.gross_usd;
+            costs += trade.costs_usd;
+            *daily.entry(trade.timestamp / SECONDS_PER_DAY).or_insert(0.0) += net;
+        }
+        metrics.current_drawdown_usd = peak - cumulative;
+        metrics.fee_to_profit_ratio = (gross > 0.0).then(|| costs / gross);
+
+        // Every day from the first trade to the window's end
+        if let Some(&first_day) = daily.keys().next() {
+            let last_day = now / SECONDS_PER_DAY;
+            metrics.days = last_day - first_day + 1;
+            let mean = daily.values().sum::<f64>() / metrics.days as f64;
+            let squares: f64 = (first_day..=last_day)
+                .map(|day| (daily.get(&day).copied().unwrap_or(0.0) - mean).powi(2))
+                .sum();
+            metrics.daily_pnl_variance = squares / metrics.days as f64;
+        }
+        metrics
+    }
+}
@@ -0,0 +1,351 @@
+use crate::client;
+use crate::costs::WSOL_MINT;
+use crate::evaluator::Quote;
+use crate::jupiter::{self, JupiterQuote};
+use crate::network;
+use crate::raydium_amm;
+use crate::raydium_clmm;
+use crate::rpc::RpcApi;
+use crate::whirlpool;
+pub use crate::venue::Venue;
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, spl_associated_token_account};
+use serde::{Deserialize, Serialize};
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use std::path::Path;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RouteLeg {
+    pub venue: Venue,
+    pub input_mint: String,
+    pub output_mint: String,
+    pub slippage_bps: u16,
+    // Pool address; every venue but Jupiter needs it to build the leg's accounts
+    #[serde(default)]
+    pub pool: Option<String>,
+    // Jupiter dex labels a Jupiter leg may route through, e.g. "Orca V2,Meteora"; any when unset
+    #[serde(default)]
+    pub dexes: Option<String>,
+}
+
+impl RouteLeg {
+    // Raydium AMM `swap_base_in` accounts for the leg's pool, including its OpenBook market's
+    pub fn raydium_accounts(&self, rpc: &dyn RpcApi, owner: &Pubkey) -> Result<Vec<AccountMeta>, Box<dyn std::error::Error>> {
+        let pool = self
+            .pool
+            .as_deref()
+            .ok_or_else(|| format!("Raydium leg {} -> {} has no pool", self.input_mint, self.output_mint))?;
+        raydium_amm::leg_accounts(rpc, &Pubkey::from_str(pool)?, &Pubkey::from_str(&self.input_mint)?, owner)
+    }
+
+    // Raydium CLMM `swap_v2` accounts for the leg's pool, including the tick arrays from its current tick
+    pub fn raydium_clmm_accounts(&self, rpc: &dyn RpcApi, owner: &Pubkey) -> Result<Vec<AccountMeta>, Box<dyn std::error::Error>> {
+        let pool = self
+            .pool
+            .as_deref()
+            .ok_or_else(|| format!("Raydium CLMM leg {} -> {} has no pool", self.input_mint, self.output_mint))?;
+        raydium_clmm::leg_accounts(rpc, &Pubkey::from_str(pool)?, &Pubkey::from_str(&self.input_mint)?, owner)
+    }
+
+    // Orca Whirlpool `swap` accounts for the leg's pool, including the tick arrays from its current tick
+    pub fn whirlpool_accounts(&self, rpc: &dyn RpcApi, owner: &Pubkey) -> Result<Vec<AccountMeta>, Box<dyn std::error::Error>> {
+        let pool = self
+            .pool
+            .as_deref()
+            .ok_or_else(|| format!("Whirlpool leg {} -> {} has no pool", self.input_mint, self.output_mint))?;
+        whirlpool::leg_accounts(rpc, &Pubkey::from_str(pool)?, &Pubkey::from_str(&self.input_mint)?, owner)
+    }
+}
+
+// A cyclic route, e.g. SOL -> BONK -> GOAT -> BONK -> SOL, started with a flash loan of `loan_amount`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Route {
+    pub name: String,
+    pub loan_amount: u64,
+    pub legs: Vec<RouteLeg>,
+}
+
+#[derive(Debug, Clone)]
+pub struct RouteQuote {
+    // Quoted output of every leg, in order
+    pub leg_outputs: Vec<u64>,
+    // Per-leg minimum output after applying the leg's slippage setting
+    pub min_outputs: Vec<u64>,
+    // Full Jupiter quote of every leg, needed to build Jupiter swap instructions
+    pub leg_quotes: Vec<JupiterQuote>,
+}
+
+impl RouteQuote {
+    pub fn final_amount(&self) -> u64 {
+        self.leg_outputs.last().copied().unwrap_or(0)
+    }
+}
+
+impl Route {
+    // The pair's trade as a route: SOL → A, A → B on the cheapest of the quote's venues, B → A on
+    // the dearest, A → SOL. Venues quoted without a pool of their own use the pair's Raydium pool.
+    // Named like the pair so per-pair settings and estimates line up
+    pub fn for_pair(
+        token_a: &str,
+        token_b: &str,
+        loan_amount: u64,
+        quote: &Quote,
+        slippage_bps: u16,
+        raydium_pool: Option<String>,
+    ) -> Self {
+        let (buy, buy_pool, _) = quote.buy();
+        let (sell, sell_pool, _) = quote.sell();
+        let leg = |(venue, pool): (Venue, Option<&str>), input_mint: &str, output_mint: &str| RouteLeg {
+            venue,
+            input_mint: input_mint.to_string(),
+            output_mint: output_mint.to_string(),
+            slippage_bps,
+            pool: if venue.needs_pool() { pool.map(str::to_string).or_else(|| raydium_pool.clone()) } else { None },
+            dexes: None,
+        };
+
+        let mut legs = vec![leg((buy, buy_pool), token_a, token_b), leg((sell, sell_pool), token_b, token_a)];
+        if token_a != WSOL_MINT {
+            legs.insert(0, leg((Venue::Jupiter, None), WSOL_MINT, token_a));
+            legs.push(leg((Venue::Jupiter, None), token_a, WSOL_MINT));
+        }
+
+        Self {
+            name: format!("{}/{}", token_a, token_b),
+            loan_amount,
+            legs,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if self.legs.len() < 2 {
+            return Err(format!("Route {} needs at least two legs", self.name));
+        }
+        for pair in self.legs.windows(2) {
+            if pair[0].output_mint != pair[1].input_mint {
+                return Err(format!(
+                    "Route {}: leg output {} does not feed the next leg input {}",
+                    self.name, pair[0].output_mint, pair[1].input_mint
+                ));
+            }
+        }
+        let first = &self.legs[0];
+        let last = &self.legs[self.legs.len() - 1];
+        if first.input_mint != last.output_mint {
+            return Err(format!("Route {} is not cyclic: starts in {} but ends in {}", self.name, first.input_mint, last.output_mint));
+        }
+        for leg in &self.legs {
+            Pubkey::from_str(&leg.input_mint).map_err(|_| format!("Invalid mint {}", leg.input_mint))?;
+            Pubkey::from_str(&leg.output_mint).map_err(|_| format!("Invalid mint {}", leg.output_mint))?;
+            if leg.slippage_bps >= 10_000 {
+                return Err(format!("Route {}: slippage must be below 10000 bps", self.name));
+            }
+            if leg.venue.needs_pool() && leg.pool.is_none() {
+                return Err(format!("Route {}: {:?} legs need a pool address", self.name, leg.venue));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn start_mint(&self) -> &str {
+        &self.legs[0].input_mint
+    }
+
+    // Quotes every leg in sequence, feeding each leg's output into the next
+    pub async fn quote(&self, http: &reqwest::Client) -> Result<RouteQuote, Box<dyn std::error::Error>> {
+        let mut amount = self.loan_amount;
+        let mut leg_outputs = Vec::with_capacity(self.legs.len());
+        let mut min_outputs = Vec::with_capacity(self.legs.len());
+        let mut leg_quotes = Vec::with_capacity(self.legs.len());
+
+        for leg in &self.legs {
+            let quote = jupiter_quote(http, leg, amount).await?;
+            amount = quote.out_amount;
+            leg_outputs.push(amount);
+            min_outputs.push(apply_slippage(amount, leg.slippage_bps));
+            leg_quotes.push(quote);
+        }
+
+        Ok(RouteQuote {
+            leg_outputs,
+            min_outputs,
+            leg_quotes,
+        })
+    }
+
+    // The program's `legs` argument (venue, input mint, output mint, min out, accounts offset,
+    // accounts len, venue data), plus the remaining accounts those offsets point into, starting at
+    // `first_account`. Jupiter legs carry Jupiter's own `shared_accounts_route` instruction, which
+    // the program forwards unchanged
+    pub async fn encode_legs(
+        &self,
+        http: &reqwest::Client,
+        rpc: &dyn RpcApi,
+        quote: &RouteQuote,
+        owner: &Pubkey,
+        first_account: usize,
+    ) -> Result<(Vec<client::RouteLeg>, Vec<AccountMeta>), Box<dyn std::error::Error>> {
+        let mut legs = Vec::with_capacity(self.legs.len());
+        let mut remaining_accounts = Vec::new();
+        for ((leg, min_out), leg_quote) in self.legs.iter().zip(&quote.min_outputs).zip(&quote.leg_quotes) {
+            let (accounts, venue_data) = match leg.venue {
+                Venue::Jupiter => {
+                    let swap = jupiter::swap_instruction(http, leg_quote, owner).await?;
+                    let mut accounts = vec![AccountMeta::new_readonly(Pubkey::from_str(network::addresses().jupiter)?, false)];
+                    accounts.extend(swap.accounts);
+                    (accounts, swap.data)
+                }
+                Venue::Raydium => (leg.raydium_accounts(rpc, owner)?, Vec::new()),
+                Venue::RaydiumClmm => (leg.raydium_clmm_accounts(rpc, owner)?, Vec::new()),
+                Venue::Whirlpool => (leg.whirlpool_accounts(rpc, owner)?, Vec::new()),
+            };
+            legs.push(client::RouteLeg {
+                venue: leg.venue.id(),
+                input_mint: Pubkey::from_str(&leg.input_mint)?.to_bytes(),
+                output_mint: Pubkey::from_str(&leg.output_mint)?.to_bytes(),
+                min_amount_out: *min_out,
+                accounts_offset: u8::try_from(first_account + remaining_accounts.len())?,
+                accounts_len: u8::try_from(accounts.len())?,
+                venue_data,
+            });
+            remaining_accounts.extend(accounts);
+        }
+        Ok((legs, remaining_accounts))
+    }
+}
+
+// Idempotent creates, paid by `payer`, for the vault accounts `routes` swap through that don't exist
+// yet. Every leg's accounts name the vault authority's associated account for its input and output
+// mints, but the program only opens the start mint's vault, and only for WSOL, which it closes again
+pub fn open_vaults(
+    rpc: &dyn RpcApi,
+    routes: &[&Route],
+    payer: &Pubkey,
+    vault_authority: &Pubkey,
+) -> Result<Vec<Instruction>, Box<dyn std::error::Error>> {
+    let mut mints: Vec<Pubkey> = Vec::new();
+    for route in routes {
+        for leg in &route.legs {
+            for mint in [&leg.input_mint, &leg.output_mint] {
+                if mint == WSOL_MINT && route.start_mint() == WSOL_MINT {
+                    continue;
+                }
+                let mint = Pubkey::from_str(mint)?;
+                if !mints.contains(&mint) {
+                    mints.push(mint);
+                }
+            }
+        }
+    }
+    // SPL Token or Token-2022, whichever owns the mint; the vault's address depends on it
+    let vaults = rpc
+        .get_multiple_accounts(&mints)?
+        .into_iter()
+        .zip(&mints)
+        .map(|(account, mint)| {
+            let token_program = account.ok_or_else(|| format!("Mint {} not found", mint))?.owner;
+            Ok((get_associated_token_address_with_program_id(vault_authority, mint, &token_program), *mint, token_program))
+        })
+        .collect::<Result<Vec<_>, Box<dyn std::error::Error>>>()?;
+    let addresses: Vec<Pubkey> = vaults.iter().map(|(address, _, _)| *address).collect();
+    Ok(rpc
+        .get_multiple_accounts(&addresses)?
+        .into_iter()
+        .zip(vaults)
+        .filter(|(account, _)| account.is_none())
+        .map(|(_, (_, mint, token_program))| {
+            spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                payer,
+                vault_authority,
+                &mint,
+                &token_program,
+            )
+        })
+        .collect())
+}
+
+// Quotes a single leg through the Jupiter API, restricted to the leg's venue
+pub async fn quote_leg(http: &reqwest::Client, leg: &RouteLeg, amount: u64) -> Result<u64, Box<dyn std::error::Error>> {
+    Ok(jupiter_quote(http, leg, amount).await?.out_amount)
+}
+
+async fn jupiter_quote(http: &reqwest::Client, leg: &RouteLeg, amount: u64) -> Result<JupiterQuote, Box<dyn std::error::Error>> {
+    let dexes = leg.dexes.as_deref().or(leg.venue.jupiter_dexes());
+    jupiter::quote(http, &leg.input_mint, &leg.output_mint, amount, leg.slippage_bps, dexes).await
+}
+
+pub fn apply_slippage(amount: u64, slippage_bps: u16) -> u64 {
+    (amount as u128 * (10_000 - slippage_bps as u128) / 10_000) as u64
+}
+
+pub fn load_routes(path: &Path) -> Result<Vec<Route>, Box<dyn std::error::Error>> {
+    let routes: Vec<Route> = serde_json::from_str(&std::fs::read_to_string(path)?)?;
+    for route in &routes {
+        route.validate()?;
+    }
+    Ok(routes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::mock::MockRpc;
+    use solana_sdk::account::Account;
+
+    fn leg(input_mint: &Pubkey, output_mint: &Pubkey) -> RouteLeg {
+        RouteLeg {
+            venue: Venue::Raydium,
+            input_mint: input_mint.to_string(),
+            output_mint: output_mint.to_string(),
+            slippage_bps: 50,
+            pool: Some(Pubkey::new_unique().to_string()),
+            dexes: None,
+        }
+    }
+
+    fn owned_by(owner: Pubkey) -> Account {
+        Account {
+            lamports: 1_000_000,
+            owner,
+            ..Account::default()
+        }
+    }
+
+    #[test]
+    fn missing_intermediate_vaults_are_opened() {
+        let rpc = MockRpc::new(100);
+        let wsol = Pubkey::from_str(WSOL_MINT).unwrap();
+        let (mint_a, mint_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        rpc.set_account(mint_a, owned_by(anchor_spl::token::ID));
+        rpc.set_account(mint_b, owned_by(anchor_spl::token_2022::ID));
+        let (payer, vault_authority) = (Pubkey::new_unique(), Pubkey::new_unique());
+        // A's vault is left from an earlier trade
+        rpc.set_account(
+            get_associated_token_address_with_program_id(&vault_authority, &mint_a, &anchor_spl::token::ID),
+            owned_by(anchor_spl::token::ID),
+        );
+        let route = Route {
+            name: "cycle".to_string(),
+            loan_amount: 1_000_000,
+            legs: vec![leg(&wsol, &mint_a), leg(&mint_a, &mint_b), leg(&mint_b, &wsol)],
+        };
+
+        // The WSOL vault is the program's to open and close
+        let instructions = open_vaults(&rpc, &[&route], &payer, &vault_authority).unwrap();
+        assert_eq!(
+            instructions,
+            vec![spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                &payer,
+                &vault_authority,
+                &mint_b,
+                &anchor_spl::token_2022::ID,
+            )]
+        );
+
+        // Once it exists, nothing is left to open
+        let vault_b = get_associated_token_address_with_program_id(&vault_authority, &mint_b, &anchor_spl::token_2022::ID);
+        rpc.set_account(vault_b, owned_by(anchor_spl::token_2022::ID));
+        assert!(open_vaults(&rpc, &[&route], &payer, &vault_authority).unwrap().is_empty());
+    }
+}
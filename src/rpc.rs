@@ -0,0 +1,375 @@
+use crate::landing::Backend;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcSendTransactionConfig, RpcSimulateTransactionConfig, RpcTransactionConfig};
+use solana_client::rpc_response::{RpcResult, RpcSimulateTransactionResult};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::time::{Duration, Instant};
+
+// getMultipleAccounts limit
+pub const MAX_ACCOUNTS_PER_REQUEST: usize = 100;
+// Between signature status checks while waiting for a sent transaction, as RpcClient does
+const CONFIRMATION_POLL_INTERVAL: Duration = Duration::from_millis(500);
+// Between resends of a transaction that hasn't confirmed yet, while its blockhash is still valid
+const REBROADCAST_INTERVAL: Duration = Duration::from_secs(2);
+// In the error for a sent transaction whose blockhash expired before it confirmed
+const EXPIRED_UNCONFIRMED: &str = "expired unconfirmed";
+
+// Every RPC call the bot makes, so the monitor and the account builders can run against a mock.
+// Methods mirror RpcClient's, which implements the trait by delegation
+pub trait RpcApi: Send + Sync {
+    fn commitment(&self) -> CommitmentConfig;
+    fn get_slot(&self) -> ClientResult<Slot>;
+    fn get_latest_blockhash(&self) -> ClientResult<Hash>;
+    // The latest blockhash and the last block height a transaction using it can land at
+    fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)>;
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account>;
+    fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Vec<Option<Account>>>;
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult>;
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64>;
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature>;
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta>;
+
+    fn get_account_data(&self, pubkey: &Pubkey) -> ClientResult<Vec<u8>> {
+        Ok(self.get_account(pubkey)?.data)
+    }
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        Ok(self.get_multiple_accounts_with_commitment(pubkeys, self.commitment())?.value)
+    }
+
+    // The signature, how long the transaction took to confirm once the node accepted it, and where
+    // it was submitted; implementations that can't tell acceptance from confirmation report no wait.
+    // Gives up once the chain passes `last_valid_block_height` without the transaction confirming
+    fn send_and_confirm_transaction_timed(
+        &self,
+        transaction: &Transaction,
+        _last_valid_block_height: u64,
+    ) -> ClientResult<(Signature, Duration, Backend)> {
+        Ok((self.send_and_confirm_transaction(transaction)?, Duration::ZERO, Backend::Rpc))
+    }
+
+    // Sends `transactions` as one bundle, landing all of them in order or none, and returns their
+    // signatures and how long the bundle took to confirm once accepted; only the block engine
+    // takes bundles
+    fn send_and_confirm_bundle_timed(
+        &self,
+        _transactions: &[Transaction],
+        _last_valid_block_height: u64,
+    ) -> ClientResult<(Vec<Signature>, Duration)> {
+        Err(ClientErrorKind::Custom("Bundles need private submission".to_string()).into())
+    }
+
+    // Any number of accounts, in as few getMultipleAccounts requests as the limit allows
+    fn get_multiple_accounts_chunked(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        let mut accounts = Vec::with_capacity(pubkeys.len());
+        for chunk in pubkeys.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+            accounts.extend(self.get_multiple_accounts(chunk)?);
+        }
+        Ok(accounts)
+    }
+}
+
+impl RpcApi for RpcClient {
+    fn commitment(&self) -> CommitmentConfig {
+        RpcClient::commitment(self)
+    }
+
+    fn get_slot(&self) -> ClientResult<Slot> {
+        RpcClient::get_slot(self)
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        RpcClient::get_latest_blockhash(self)
+    }
+
+    fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)> {
+        RpcClient::get_latest_blockhash_with_commitment(self, RpcClient::commitment(self))
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        RpcClient::get_account(self, pubkey)
+    }
+
+    fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        RpcClient::get_multiple_accounts_with_commitment(self, pubkeys, commitment)
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        RpcClient::simulate_transaction_with_config(self, transaction, config)
+    }
+
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        RpcClient::get_fee_for_message(self, message)
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        RpcClient::send_and_confirm_transaction(self, transaction)
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        RpcClient::get_transaction_with_config(self, signature, config)
+    }
+
+    fn get_account_data(&self, pubkey: &Pubkey) -> ClientResult<Vec<u8>> {
+        RpcClient::get_account_data(self, pubkey)
+    }
+
+    fn get_multiple_accounts(&self, pubkeys: &[Pubkey]) -> ClientResult<Vec<Option<Account>>> {
+        RpcClient::get_multiple_accounts(self, pubkeys)
+    }
+
+    // send_and_confirm_transaction's send and status polling, with the polling timed
+    fn send_and_confirm_transaction_timed(
+        &self,
+        transaction: &Transaction,
+        last_valid_block_height: u64,
+    ) -> ClientResult<(Signature, Duration, Backend)> {
+        let signature = RpcClient::send_transaction(self, transaction)?;
+        let sent = Instant::now();
+        // Resent as is, so it keeps its signature; preflight already passed the first time
+        let config = RpcSendTransactionConfig {
+            skip_preflight: true,
+            ..RpcSendTransactionConfig::default()
+        };
+        await_confirmation(self, &signature, last_valid_block_height, &|| {
+            RpcClient::send_transaction_with_config(self, transaction, config).map(|_| ())
+        })?;
+        Ok((signature, sent.elapsed(), Backend::Rpc))
+    }
+}
+
+// Polls `rpc` until the sent transaction confirms or fails, resending it with `rebroadcast` every
+// REBROADCAST_INTERVAL, until the chain passes `last_valid_block_height` and it can no longer land
+pub fn await_confirmation(
+    rpc: &RpcClient,
+    signature: &Signature,
+    last_valid_block_height: u64,
+    rebroadcast: &dyn Fn() -> ClientResult<()>,
+) -> ClientResult<()> {
+    let mut last_sent = Instant::now();
+    loop {
+        // Read before the status, so a transaction that lands in the last valid block still counts
+        let expired = rpc.get_block_height_with_commitment(CommitmentConfig::processed())? > last_valid_block_height;
+        match rpc.get_signature_status(signature)? {
+            Some(Ok(())) => return Ok(()),
+            Some(Err(e)) => return Err(e.into()),
+            None if expired => return Err(expired_error(signature, last_valid_block_height)),
+            None => {
+                if last_sent.elapsed() >= REBROADCAST_INTERVAL {
+                    // A resend that fails leaves the first copy in flight
+                    if let Err(e) = rebroadcast() {
+                        println!("Failed to rebroadcast {}: {}", signature, e);
+                    }
+                    last_sent = Instant::now();
+                }
+                std::thread::sleep(CONFIRMATION_POLL_INTERVAL);
+            }
+        }
+    }
+}
+
+// For a sent transaction the chain moved past the last valid block height of without confirming;
+// classified as an expired blockhash
+pub fn expired_error(signature: &Signature, last_valid_block_height: u64) -> ClientError {
+    ClientErrorKind::Custom(format!(
+        "Transaction {} {}: block height exceeded {}",
+        signature, EXPIRED_UNCONFIRMED, last_valid_block_height
+    ))
+    .into()
+}
+
+// Whether an execution error is a sent transaction expiring unconfirmed, which never landed and
+// can be tried again with a fresh blockhash
+pub fn expired_unconfirmed(error: &str) -> bool {
+    error.contains(EXPIRED_UNCONFIRMED)
+}
+
+#[cfg(test)]
+pub mod mock {
+    use super::*;
+    use solana_client::client_error::{ClientError, ClientErrorKind};
+    use solana_client::rpc_response::{Response, RpcResponseContext};
+    use solana_sdk::clock::MAX_PROCESSING_AGE;
+    use std::collections::{HashMap, VecDeque};
+    use std::sync::Mutex;
+
+    // Deterministic RpcApi: accounts come from a fixed map, simulations return scripted results in
+    // order, and sent transactions are recorded instead of sent
+    #[derive(Default)]
+    pub struct MockRpc {
+        pub slot: Slot,
+        pub fee_lamports: u64,
+        accounts: Mutex<HashMap<Pubkey, Account>>,
+        simulations: Mutex<VecDeque<RpcSimulateTransactionResult>>,
+        sent: Mutex<Vec<Transaction>>,
+        simulated: Mutex<Vec<(Transaction, RpcSimulateTransactionConfig)>>,
+        // Sends left to expire unconfirmed, as if the chain moved past their blockhash
+        expiring_sends: Mutex<usize>,
+    }
+
+    impl MockRpc {
+        pub fn new(slot: Slot) -> Self {
+            Self {
+                slot,
+                fee_lamports: 5000,
+                ..Default::default()
+            }
+        }
+
+        pub fn set_account(&self, pubkey: Pubkey, account: Account) {
+            self.accounts.lock().unwrap().insert(pubkey, account);
+        }
+
+        // Result of the next simulation; simulating with none left is an error
+        pub fn push_simulation(&self, result: RpcSimulateTransactionResult) {
+            self.simulations.lock().unwrap().push_back(result);
+        }
+
+        // The next send is recorded but never confirms, and fails once its blockhash expires
+        pub fn expire_next_send(&self) {
+            *self.expiring_sends.lock().unwrap() += 1;
+        }
+
+        // Every simulated transaction with the config it was simulated with
+        pub fn simulated(&self) -> Vec<(Transaction, RpcSimulateTransactionConfig)> {
+            self.simulated.lock().unwrap().clone()
+        }
+
+        pub fn sent(&self) -> Vec<Transaction> {
+            self.sent.lock().unwrap().clone()
+        }
+
+        fn response<T>(&self, value: T) -> Response<T> {
+            Response {
+                context: RpcResponseContext {
+                    slot: self.slot,
+                    api_version: None,
+                },
+                value,
+            }
+        }
+    }
+
+    fn error(message: String) -> ClientError {
+        ClientErrorKind::Custom(message).into()
+    }
+
+    impl RpcApi for MockRpc {
+        fn commitment(&self) -> CommitmentConfig {
+            CommitmentConfig::confirmed()
+        }
+
+        fn get_slot(&self) -> ClientResult<Slot> {
+            Ok(self.slot)
+        }
+
+        fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+            Ok(Hash::new_from_array([self.slot as u8; 32]))
+        }
+
+        fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)> {
+            Ok((self.get_latest_blockhash()?, self.slot + MAX_PROCESSING_AGE as u64))
+        }
+
+        fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+            self.accounts
+                .lock()
+                .unwrap()
+                .get(pubkey)
+                .cloned()
+                .ok_or_else(|| error(format!("AccountNotFound: pubkey={}", pubkey)))
+        }
+
+        fn get_multiple_accounts_with_commitment(
+            &self,
+            pubkeys: &[Pubkey],
+            _commitment: CommitmentConfig,
+        ) -> RpcResult<Vec<Option<Account>>> {
+            let accounts = self.accounts.lock().unwrap();
+            Ok(self.response(pubkeys.iter().map(|pubkey| accounts.get(pubkey).cloned()).collect()))
+        }
+
+        fn simulate_transaction_with_config(
+            &self,
+            transaction: &Transaction,
+            config: RpcSimulateTransactionConfig,
+        ) -> RpcResult<RpcSimulateTransactionResult> {
+            self.simulated.lock().unwrap().push((transaction.clone(), config));
+            let result = self.simulations.lock().unwrap().pop_front();
+            Ok(self.response(result.ok_or_else(|| error("No scripted simulation result".to_string()))?))
+        }
+
+        fn get_fee_for_message(&self, _message: &Message) -> ClientResult<u64> {
+            Ok(self.fee_lamports)
+        }
+
+        fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+            self.sent.lock().unwrap().push(transaction.clone());
+            Ok(transaction.signatures[0])
+        }
+
+        fn send_and_confirm_transaction_timed(
+            &self,
+            transaction: &Transaction,
+            last_valid_block_height: u64,
+        ) -> ClientResult<(Signature, Duration, Backend)> {
+            let signature = self.send_and_confirm_transaction(transaction)?;
+            let mut expiring = self.expiring_sends.lock().unwrap();
+            if *expiring > 0 {
+                *expiring -= 1;
+                return Err(expired_error(&signature, last_valid_block_height));
+            }
+            Ok((signature, Duration::ZERO, Backend::Rpc))
+        }
+
+        fn send_and_confirm_bundle_timed(
+            &self,
+            transactions: &[Transaction],
+            _last_valid_block_height: u64,
+        ) -> ClientResult<(Vec<Signature>, Duration)> {
+            self.sent.lock().unwrap().extend_from_slice(transactions);
+            Ok((transactions.iter().map(|transaction| transaction.signatures[0]).collect(), Duration::ZERO))
+        }
+
+        fn get_transaction_with_config(
+            &self,
+            signature: &Signature,
+            _config: RpcTransactionConfig,
+        ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+            Err(error(format!("Transaction {} not found", signature)))
+        }
+    }
+}
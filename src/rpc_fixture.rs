@@ -0,0 +1,269 @@
+use crate::rpc::RpcApi;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_config::{RpcSimulateTransactionConfig, RpcTransactionConfig};
+use solana_client::rpc_response::{RpcResult, RpcSimulateTransactionResult};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::{HashMap, VecDeque};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+// RPC fixtures: RecordingRpc writes every call the bot makes, with its response, to a JSON lines
+// file, and ReplayRpc serves a recorded file back, so a session that misbehaved can be rerun
+// deterministically as a regression test.
+//
+// Calls are matched by method and request: the addresses for account reads, the signature for
+// transaction lookups. Calls carrying a transaction (simulations, fees, sends) depend on the wallet
+// and blockhash, so they are matched by method alone, in recorded order. A call made more often than
+// it was recorded gets the last response again, so polling loops keep running
+
+#[derive(Debug, Serialize, Deserialize)]
+struct FixtureEntry {
+    method: String,
+    request: String,
+    // The response, or the error message of a failed call
+    response: Result<serde_json::Value, String>,
+}
+
+// Wraps another RpcApi and appends each call to the fixture file
+pub struct RecordingRpc {
+    inner: Arc<dyn RpcApi>,
+    file: Mutex<File>,
+}
+
+impl RecordingRpc {
+    pub fn create(inner: Arc<dyn RpcApi>, path: &Path) -> std::io::Result<Self> {
+        Ok(Self {
+            inner,
+            file: Mutex::new(OpenOptions::new().create(true).append(true).open(path)?),
+        })
+    }
+
+    fn record<T: Serialize>(&self, method: &str, request: String, result: ClientResult<T>) -> ClientResult<T> {
+        let entry = FixtureEntry {
+            method: method.to_string(),
+            request,
+            response: match &result {
+                Ok(value) => serde_json::to_value(value).map_err(|e| e.to_string()),
+                Err(e) => Err(e.to_string()),
+            },
+        };
+        // A fixture that can't be written must not stop the bot
+        if let Ok(line) = serde_json::to_string(&entry) {
+            if let Err(e) = writeln!(self.file.lock().unwrap(), "{}", line) {
+                println!("Failed to write RPC fixture: {}", e);
+            }
+        }
+        result
+    }
+}
+
+fn address_list(pubkeys: &[Pubkey]) -> String {
+    pubkeys.iter().map(Pubkey::to_string).collect::<Vec<_>>().join(",")
+}
+
+impl RpcApi for RecordingRpc {
+    fn commitment(&self) -> CommitmentConfig {
+        self.inner.commitment()
+    }
+
+    fn get_slot(&self) -> ClientResult<Slot> {
+        self.record("get_slot", String::new(), self.inner.get_slot())
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.record("get_latest_blockhash", String::new(), self.inner.get_latest_blockhash())
+    }
+
+    fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)> {
+        self.record(
+            "get_latest_blockhash_with_height",
+            String::new(),
+            self.inner.get_latest_blockhash_with_height(),
+        )
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.record("get_account", pubkey.to_string(), self.inner.get_account(pubkey))
+    }
+
+    fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        self.record(
+            "get_multiple_accounts",
+            address_list(pubkeys),
+            self.inner.get_multiple_accounts_with_commitment(pubkeys, commitment),
+        )
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.record(
+            "simulate_transaction",
+            String::new(),
+            self.inner.simulate_transaction_with_config(transaction, config),
+        )
+    }
+
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        self.record("get_fee_for_message", String::new(), self.inner.get_fee_for_message(message))
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.record(
+            "send_and_confirm_transaction",
+            String::new(),
+            self.inner.send_and_confirm_transaction(transaction),
+        )
+    }
+
+    // Recorded without the confirmation time, which replays as none
+    fn send_and_confirm_bundle_timed(
+        &self,
+        transactions: &[Transaction],
+        last_valid_block_height: u64,
+    ) -> ClientResult<(Vec<Signature>, Duration)> {
+        let mut land = Duration::ZERO;
+        let sent = self.inner.send_and_confirm_bundle_timed(transactions, last_valid_block_height).map(|(signatures, elapsed)| {
+            land = elapsed;
+            signatures
+        });
+        Ok((self.record("send_and_confirm_bundle", String::new(), sent)?, land))
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.record(
+            "get_transaction",
+            signature.to_string(),
+            self.inner.get_transaction_with_config(signature, config),
+        )
+    }
+}
+
+// Recorded results by method and parameters, in call order
+type Responses = HashMap<(String, String), VecDeque<Result<serde_json::Value, String>>>;
+
+// Serves the responses of a recorded fixture file; a call that was never recorded is an error
+pub struct ReplayRpc {
+    responses: Mutex<Responses>,
+}
+
+impl ReplayRpc {
+    pub fn load(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut responses: HashMap<_, VecDeque<_>> = HashMap::new();
+        for (index, line) in std::fs::read_to_string(path)?.lines().enumerate() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let entry: FixtureEntry =
+                serde_json::from_str(line).map_err(|e| format!("Invalid fixture entry on line {}: {}", index + 1, e))?;
+            responses.entry((entry.method, entry.request)).or_default().push_back(entry.response);
+        }
+        Ok(Self {
+            responses: Mutex::new(responses),
+        })
+    }
+
+    fn replay<T: DeserializeOwned>(&self, method: &str, request: String) -> ClientResult<T> {
+        let mut responses = self.responses.lock().unwrap();
+        let queue = responses
+            .get_mut(&(method.to_string(), request.clone()))
+            .filter(|queue| !queue.is_empty())
+            .ok_or_else(|| error(format!("No recorded response for {}({})", method, request)))?;
+        let response = if queue.len() > 1 { queue.pop_front().unwrap() } else { queue[0].clone() };
+        match response {
+            Ok(value) => serde_json::from_value(value)
+                .map_err(|e| error(format!("Invalid recorded response for {}: {}", method, e))),
+            Err(message) => Err(error(message)),
+        }
+    }
+}
+
+fn error(message: String) -> ClientError {
+    ClientErrorKind::Custom(message).into()
+}
+
+impl RpcApi for ReplayRpc {
+    // RpcClient's default
+    fn commitment(&self) -> CommitmentConfig {
+        CommitmentConfig::finalized()
+    }
+
+    fn get_slot(&self) -> ClientResult<Slot> {
+        self.replay("get_slot", String::new())
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.replay("get_latest_blockhash", String::new())
+    }
+
+    fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)> {
+        self.replay("get_latest_blockhash_with_height", String::new())
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.replay("get_account", pubkey.to_string())
+    }
+
+    fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        _commitment: CommitmentConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        self.replay("get_multiple_accounts", address_list(pubkeys))
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        _transaction: &Transaction,
+        _config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.replay("simulate_transaction", String::new())
+    }
+
+    fn get_fee_for_message(&self, _message: &Message) -> ClientResult<u64> {
+        self.replay("get_fee_for_message", String::new())
+    }
+
+    fn send_and_confirm_transaction(&self, _transaction: &Transaction) -> ClientResult<Signature> {
+        self.replay("send_and_confirm_transaction", String::new())
+    }
+
+    fn send_and_confirm_bundle_timed(
+        &self,
+        _transactions: &[Transaction],
+        _last_valid_block_height: u64,
+    ) -> ClientResult<(Vec<Signature>, Duration)> {
+        Ok((self.replay("send_and_confirm_bundle", String::new())?, Duration::ZERO))
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        _config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.replay("get_transaction", signature.to_string())
+    }
+}
@@ -0,0 +1,253 @@
+use crate::landing::Backend;
+use crate::rpc::RpcApi;
+use solana_client::client_error::{ClientError, ClientErrorKind, Result as ClientResult};
+use solana_client::rpc_client::RpcClient;
+use solana_client::rpc_config::{RpcSimulateTransactionConfig, RpcTransactionConfig};
+use solana_client::rpc_response::{RpcResult, RpcSimulateTransactionResult};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::hash::Hash;
+use solana_sdk::message::Message;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::Signature;
+use solana_sdk::transaction::Transaction;
+use solana_transaction_status::EncodedConfirmedTransactionWithStatusMeta;
+use std::collections::HashMap;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Routes calls across several RPC endpoints (SOLANA_RPC_URLS). Latency-sensitive calls (slot,
+// blockhash, simulation, send) go to the endpoint currently answering that method fastest, bulk
+// reads to the cheapest by SOLANA_RPC_COSTS. Every call updates its endpoint's latency for the
+// method; a measurement older than PROBE_AFTER, or none yet, sends the next call there to retake it
+
+// Weight of the newest sample in the moving average
+const LATENCY_ALPHA: f64 = 0.2;
+const PROBE_AFTER: Duration = Duration::from_secs(30);
+// Latency charged for a call that never reached the node, so a failing endpoint stops being fastest
+const UNREACHABLE_PENALTY: Duration = Duration::from_secs(5);
+
+struct Latency {
+    average_ms: f64,
+    measured: Instant,
+}
+
+struct Endpoint {
+    url: String,
+    rpc: Arc<dyn RpcApi>,
+    // Relative cost per request
+    cost: f64,
+    latency: Mutex<HashMap<&'static str, Latency>>,
+}
+
+impl Endpoint {
+    // Average latency of `method`, unless it's unmeasured or due for a probe
+    fn latency_ms(&self, method: &str) -> Option<f64> {
+        let latency = self.latency.lock().unwrap();
+        let latency = latency.get(method)?;
+        (latency.measured.elapsed() < PROBE_AFTER).then_some(latency.average_ms)
+    }
+
+    fn observe(&self, method: &'static str, elapsed: Duration) {
+        let sample = elapsed.as_secs_f64() * 1000.0;
+        let mut latency = self.latency.lock().unwrap();
+        let latency = latency.entry(method).or_insert(Latency {
+            average_ms: sample,
+            measured: Instant::now(),
+        });
+        latency.average_ms += LATENCY_ALPHA * (sample - latency.average_ms);
+        latency.measured = Instant::now();
+    }
+}
+
+pub struct RoutedRpc {
+    endpoints: Vec<Endpoint>,
+    // Endpoint each latency-sensitive method was last routed to outside probes, to log changes
+    fastest: Mutex<HashMap<&'static str, usize>>,
+}
+
+impl RoutedRpc {
+    // `endpoints` as (url, rpc, relative cost); the first sets the commitment
+    pub fn new(endpoints: Vec<(String, Arc<dyn RpcApi>, f64)>) -> Self {
+        Self {
+            endpoints: endpoints
+                .into_iter()
+                .map(|(url, rpc, cost)| Endpoint {
+                    url,
+                    rpc,
+                    cost,
+                    latency: Mutex::new(HashMap::new()),
+                })
+                .collect(),
+            fastest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // RpcClients for SOLANA_RPC_URLS, when it lists more than one endpoint
+    pub fn from_env() -> Option<Self> {
+        let urls: Vec<String> = env::var("SOLANA_RPC_URLS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|url| !url.is_empty())
+            .map(String::from)
+            .collect();
+        if urls.len() < 2 {
+            return None;
+        }
+        let costs: Vec<f64> = env::var("SOLANA_RPC_COSTS")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|cost| !cost.is_empty())
+            .map(|cost| cost.parse().expect("Invalid SOLANA_RPC_COSTS"))
+            .collect();
+        let endpoints = urls
+            .into_iter()
+            .enumerate()
+            .map(|(i, url)| {
+                let rpc: Arc<dyn RpcApi> = Arc::new(RpcClient::new(url.clone()));
+                (url, rpc, costs.get(i).copied().unwrap_or(1.0))
+            })
+            .collect();
+        Some(Self::new(endpoints))
+    }
+
+    // Unmeasured or stale endpoints first, then the lowest average latency
+    fn fastest(&self, method: &'static str) -> usize {
+        let index = (0..self.endpoints.len())
+            .min_by(|&a, &b| {
+                let latency = |i: usize| self.endpoints[i].latency_ms(method).unwrap_or(f64::NEG_INFINITY);
+                latency(a).total_cmp(&latency(b))
+            })
+            .unwrap();
+        // Probes aren't logged
+        if let Some(latency) = self.endpoints[index].latency_ms(method) {
+            let previous = self.fastest.lock().unwrap().insert(method, index);
+            if previous.is_some_and(|previous| previous != index) {
+                println!("Routing {} to {} ({:.1} ms)", method, self.endpoints[index].url, latency);
+            }
+        }
+        index
+    }
+
+    // The lowest cost, the lowest latency among equals
+    fn cheapest(&self, method: &'static str) -> usize {
+        (0..self.endpoints.len())
+            .min_by(|&a, &b| {
+                let key = |i: usize| {
+                    let endpoint = &self.endpoints[i];
+                    (endpoint.cost, endpoint.latency_ms(method).unwrap_or(0.0))
+                };
+                let (a, b) = (key(a), key(b));
+                a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1))
+            })
+            .unwrap()
+    }
+
+    fn call<T>(
+        &self,
+        index: usize,
+        method: &'static str,
+        f: impl FnOnce(&dyn RpcApi) -> ClientResult<T>,
+    ) -> ClientResult<T> {
+        let endpoint = &self.endpoints[index];
+        let started = Instant::now();
+        let result = f(endpoint.rpc.as_ref());
+        let elapsed = match &result {
+            Ok(_) => started.elapsed(),
+            Err(e) => charged(e, started),
+        };
+        endpoint.observe(method, elapsed);
+        result
+    }
+
+    fn fast<T>(&self, method: &'static str, f: impl FnOnce(&dyn RpcApi) -> ClientResult<T>) -> ClientResult<T> {
+        self.call(self.fastest(method), method, f)
+    }
+
+    fn bulk<T>(&self, method: &'static str, f: impl FnOnce(&dyn RpcApi) -> ClientResult<T>) -> ClientResult<T> {
+        self.call(self.cheapest(method), method, f)
+    }
+}
+
+// An error the node returned, like a failed preflight, still measures the endpoint
+fn charged(error: &ClientError, started: Instant) -> Duration {
+    match error.kind() {
+        ClientErrorKind::Io(_) | ClientErrorKind::Reqwest(_) => UNREACHABLE_PENALTY,
+        _ => started.elapsed(),
+    }
+}
+
+impl RpcApi for RoutedRpc {
+    fn commitment(&self) -> CommitmentConfig {
+        self.endpoints[0].rpc.commitment()
+    }
+
+    fn get_slot(&self) -> ClientResult<Slot> {
+        self.fast("getSlot", |rpc| rpc.get_slot())
+    }
+
+    fn get_latest_blockhash(&self) -> ClientResult<Hash> {
+        self.fast("getLatestBlockhash", |rpc| rpc.get_latest_blockhash())
+    }
+
+    fn get_latest_blockhash_with_height(&self) -> ClientResult<(Hash, u64)> {
+        self.fast("getLatestBlockhash", |rpc| rpc.get_latest_blockhash_with_height())
+    }
+
+    fn get_account(&self, pubkey: &Pubkey) -> ClientResult<Account> {
+        self.bulk("getAccountInfo", |rpc| rpc.get_account(pubkey))
+    }
+
+    fn get_multiple_accounts_with_commitment(
+        &self,
+        pubkeys: &[Pubkey],
+        commitment: CommitmentConfig,
+    ) -> RpcResult<Vec<Option<Account>>> {
+        self.bulk("getMultipleAccounts", |rpc| rpc.get_multiple_accounts_with_commitment(pubkeys, commitment))
+    }
+
+    fn simulate_transaction_with_config(
+        &self,
+        transaction: &Transaction,
+        config: RpcSimulateTransactionConfig,
+    ) -> RpcResult<RpcSimulateTransactionResult> {
+        self.fast("simulateTransaction", |rpc| rpc.simulate_transaction_with_config(transaction, config))
+    }
+
+    fn get_fee_for_message(&self, message: &Message) -> ClientResult<u64> {
+        self.bulk("getFeeForMessage", |rpc| rpc.get_fee_for_message(message))
+    }
+
+    fn send_and_confirm_transaction(&self, transaction: &Transaction) -> ClientResult<Signature> {
+        self.fast("sendTransaction", |rpc| rpc.send_and_confirm_transaction(transaction))
+    }
+
+    fn get_transaction_with_config(
+        &self,
+        signature: &Signature,
+        config: RpcTransactionConfig,
+    ) -> ClientResult<EncodedConfirmedTransactionWithStatusMeta> {
+        self.bulk("getTransaction", |rpc| rpc.get_transaction_with_config(signature, config))
+    }
+
+    // Only the time until the node accepted the transaction measures the endpoint
+    fn send_and_confirm_transaction_timed(
+        &self,
+        transaction: &Transaction,
+        last_valid_block_height: u64,
+    ) -> ClientResult<(Signature, Duration, Backend)> {
+        let endpoint = &self.endpoints[self.fastest("sendTransaction")];
+        let started = Instant::now();
+        let result = endpoint.rpc.send_and_confirm_transaction_timed(transaction, last_valid_block_height);
+        let elapsed = match &result {
+            Ok((_, land, _)) => started.elapsed().saturating_sub(*land),
+            Err(e) => charged(e, started),
+        };
+        endpoint.observe("sendTransaction", elapsed);
+        result
+    }
+}
@@ -0,0 +1,102 @@
+use std::collections::hash_map::RandomState;
+use std::collections::HashMap;
+use std::env;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, Instant};
+
+// Per-pair polling: instead of checking every pair once a second, each pair is checked on its own
+// interval, so the RPC budget goes to the pairs that find opportunities. Pairs whose pools the
+// shred stream subscribes to are hot (HOT_POLL_INTERVAL_MS) and are also checked as soon as a pool
+// is written; pairs that found nothing executable in LONG_TAIL_AFTER_EVALUATIONS checks this review
+// window are long-tail (LONG_TAIL_POLL_INTERVAL_MS); the rest, and the routes, discovery and
+// housekeeping, run every POLL_INTERVAL_MS. Every interval is spread by up to POLL_JITTER either
+// way, so pairs on the same interval don't all hit the RPC in the same tick
+
+// Key the routes, discovery and housekeeping are scheduled under
+pub const CYCLE_KEY: &str = "cycle";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PollTier {
+    Hot,
+    Regular,
+    LongTail,
+}
+
+#[derive(Debug, Clone)]
+pub struct PollConfig {
+    pub hot_interval: Duration,
+    pub interval: Duration,
+    pub long_tail_interval: Duration,
+    pub long_tail_after_evaluations: u64,
+    // Fraction of an interval it's randomly lengthened or shortened by
+    pub jitter: f64,
+}
+
+impl PollConfig {
+    pub fn from_env() -> Self {
+        let parse = |name: &str, default: u64| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Self {
+            hot_interval: Duration::from_millis(parse("HOT_POLL_INTERVAL_MS", 200)),
+            interval: Duration::from_millis(parse("POLL_INTERVAL_MS", 1000)),
+            long_tail_interval: Duration::from_millis(parse("LONG_TAIL_POLL_INTERVAL_MS", 10_000)),
+            long_tail_after_evaluations: parse("LONG_TAIL_AFTER_EVALUATIONS", 50),
+            jitter: env::var("POLL_JITTER").ok().and_then(|v| v.parse().ok()).unwrap_or(0.1f64).clamp(0.0, 1.0),
+        }
+    }
+
+    pub fn interval(&self, tier: PollTier) -> Duration {
+        match tier {
+            PollTier::Hot => self.hot_interval,
+            PollTier::Regular => self.interval,
+            PollTier::LongTail => self.long_tail_interval,
+        }
+    }
+}
+
+// When each pair, and the cycle, is next due
+pub struct PollScheduler {
+    config: PollConfig,
+    next_due: HashMap<String, Instant>,
+}
+
+impl PollScheduler {
+    pub fn new(config: PollConfig) -> Self {
+        Self {
+            config,
+            next_due: HashMap::new(),
+        }
+    }
+
+    pub fn config(&self) -> &PollConfig {
+        &self.config
+    }
+
+    // Whether `key` is due at `now`; anything never scheduled is
+    pub fn due(&self, key: &str, now: Instant) -> bool {
+        self.next_due.get(key).is_none_or(|due| *due <= now)
+    }
+
+    // Makes `key` due again one jittered `interval` after `now`
+    pub fn schedule(&mut self, key: &str, interval: Duration, now: Instant) {
+        let factor = 1.0 + self.config.jitter * (2.0 * random_fraction() - 1.0);
+        self.next_due.insert(key.to_string(), now + interval.mul_f64(factor));
+    }
+
+    // How long until the next of `keys` is due, none when one already is
+    pub fn until_next<'a>(&self, keys: impl IntoIterator<Item = &'a str>, now: Instant) -> Duration {
+        keys.into_iter()
+            .map(|key| self.next_due.get(key).map_or(Duration::ZERO, |due| due.saturating_duration_since(now)))
+            .min()
+            .unwrap_or(self.config.interval)
+    }
+
+    // Forgets keys that are no longer polled, e.g. removed pairs
+    pub fn retain(&mut self, keep: impl Fn(&str) -> bool) {
+        self.next_due.retain(|key, _| keep(key));
+    }
+}
+
+// Uniform in [0, 1)
+fn random_fraction() -> f64 {
+    (RandomState::new().build_hasher().finish() >> 11) as f64 / (1u64 << 53) as f64
+}
@@ -0,0 +1,126 @@
+use crate::discovery::{self, Listing};
+use crate::market_data::MarketSnapshot;
+use crate::spread_stats::spread_bps;
+use std::collections::HashMap;
+
+#[derive(Debug, Clone)]
+pub struct PairScore {
+    pub token_a: String,
+    pub token_b: String,
+    pub volume_24h_usd: f64,
+    // Standard deviation of the Jupiter/Raydium spread over the lookback, in bps
+    pub spread_volatility_bps: f64,
+    pub score: f64,
+}
+
+// Per-pair spread standard deviation from recorded snapshots newer than `since`
+pub fn spread_volatility(snapshots: &[MarketSnapshot], since: u64) -> HashMap<(String, String), f64> {
+    let mut spreads: HashMap<(String, String), Vec<f64>> = HashMap::new();
+    for snapshot in snapshots.iter().filter(|s| s.timestamp >= since) {
+        spreads
+            .entry(discovery::pair_key(&snapshot.token_a, &snapshot.token_b))
+            .or_default()
+            .push(spread_bps(&snapshot.quote));
+    }
+
+    spreads
+        .into_iter()
+        .map(|(key, values)| {
+            let mean = values.iter().sum::<f64>() / values.len() as f64;
+            let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+            (key, variance.sqrt())
+        })
+        .collect()
+}
+
+// Ranks pairs by the average of their volume rank and spread volatility rank, best first
+pub fn rank_pairs(
+    pairs: &[(String, String)],
+    listings: &HashMap<(String, String), Listing>,
+    volatility: &HashMap<(String, String), f64>,
+) -> Vec<PairScore> {
+    let mut scores: Vec<PairScore> = pairs
+        .iter()
+        .map(|(a, b)| {
+            let key = discovery::pair_key(a, b);
+            PairScore {
+                token_a: a.clone(),
+                token_b: b.clone(),
+                volume_24h_usd: listings.get(&key).map_or(0.0, |l| l.volume_24h_usd),
+                spread_volatility_bps: volatility.get(&key).copied().unwrap_or(0.0),
+                score: 0.0,
+            }
+        })
+        .collect();
+
+    let count = scores.len().max(1) as f64;
+    let volume_ranks = ranks(&scores.iter().map(|s| s.volume_24h_usd).collect::<Vec<_>>());
+    let volatility_ranks = ranks(&scores.iter().map(|s| s.spread_volatility_bps).collect::<Vec<_>>());
+    for (index, score) in scores.iter_mut().enumerate() {
+        // Rank 0 is the highest value; convert to a 0..1 score where 1 is best
+        score.score = 1.0 - (volume_ranks[index] + volatility_ranks[index]) as f64 / (2.0 * count);
+    }
+
+    scores.sort_by(|a, b| b.score.total_cmp(&a.score));
+    scores
+}
+
+// Position of each value when sorted descending
+fn ranks(values: &[f64]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+    let mut ranks = vec![0; values.len()];
+    for (rank, index) in order.into_iter().enumerate() {
+        ranks[index] = rank;
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::evaluator::Quote;
+
+    fn snapshot(token_a: &str, timestamp: u64, jupiter_price: u64) -> MarketSnapshot {
+        serde_json::from_value(serde_json::json!({
+            "slot": 1,
+            "timestamp": timestamp,
+            "token_a": token_a,
+            "token_b": "B",
+            "loan_amount": 1_000_000_000u64,
+            "quote": Quote {
+                jupiter_price,
+                raydium_price: 1_000_000_000,
+                venues: Vec::new(),
+            },
+            "sol_price": 150.0,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn pairs_are_ranked_by_volume_and_spread_volatility() {
+        // A's spread swings between 0 and 200 bps, C's holds steady, and the stale one is left out
+        let snapshots = [
+            snapshot("A", 100, 1_000_000_000),
+            snapshot("A", 100, 1_020_202_020),
+            snapshot("C", 100, 1_010_000_000),
+            snapshot("C", 100, 1_010_000_000),
+            snapshot("C", 10, 1_500_000_000),
+        ];
+        let volatility = spread_volatility(&snapshots, 50);
+        assert!((volatility[&discovery::pair_key("A", "B")] - 100.0).abs() < 0.1);
+        assert_eq!(volatility[&discovery::pair_key("B", "C")], 0.0);
+
+        let mut listings = HashMap::new();
+        listings.insert(discovery::pair_key("A", "B"), Listing { volume_24h_usd: 1_000.0, ..Listing::default() });
+        listings.insert(discovery::pair_key("C", "B"), Listing { volume_24h_usd: 5_000.0, ..Listing::default() });
+        let pairs = [("A".to_string(), "B".to_string()), ("C".to_string(), "B".to_string()), ("D".to_string(), "B".to_string())];
+        let ranked = rank_pairs(&pairs, &listings, &volatility);
+        let order: Vec<&str> = ranked.iter().map(|score| score.token_a.as_str()).collect();
+        // A and C each lead on one measure; D on neither
+        assert_eq!(order[2], "D");
+        assert_eq!(ranked[0].score, ranked[1].score);
+        assert_eq!(ranked[2].volume_24h_usd, 0.0);
+    }
+}
@@ -0,0 +1,177 @@
+use crate::retry::Backoff;
+use solana_entry::entry::Entry;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::Notify;
+
+// Early pool updates from Jito ShredStream (SHREDSTREAM_URL). A ShredStream proxy reassembles the
+// leader's shreds into entries as they're broadcast, before any RPC node has replayed the slot, so
+// a swap through a monitored pool shows up here a few hundred milliseconds before accountSubscribe
+// reports it. A watched account written by a transaction in the entries wakes the monitor loop at
+// once, with the pairs on that pool scanned first and the account's cache entry dropped. Accounts
+// only reached through address lookup tables aren't seen
+
+// Delay before reconnecting to the proxy, doubling per failed attempt
+const RECONNECT_BASE_DELAY: Duration = Duration::from_millis(500);
+const RECONNECT_MAX_DELAY: Duration = Duration::from_secs(30);
+// The proxy's entry subscription, from its shredstream.proto
+const SUBSCRIBE_ENTRIES_PATH: &str = "/shredstream.ShredstreamProxy/SubscribeEntries";
+
+pub struct ShredStreamConfig {
+    // The proxy's gRPC endpoint, e.g. http://127.0.0.1:9999
+    pub url: String,
+}
+
+impl ShredStreamConfig {
+    pub fn from_env() -> Option<Self> {
+        env::var("SHREDSTREAM_URL")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .map(|url| Self { url })
+    }
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct SubscribeEntriesRequest {}
+
+#[derive(Clone, PartialEq, prost::Message)]
+struct EntriesMessage {
+    #[prost(uint64, tag = "1")]
+    slot: u64,
+    // Bincode-serialized Vec<Entry>
+    #[prost(bytes = "vec", tag = "2")]
+    entries: Vec<u8>,
+}
+
+// A transaction seen in the shreds that wrote two or more watched accounts, as an arbitrage
+// through the monitored pools would
+#[derive(Debug, Clone, PartialEq)]
+pub struct PoolTransaction {
+    pub slot: Slot,
+    pub signer: Pubkey,
+    pub written: HashSet<Pubkey>,
+}
+
+// Pool accounts the monitor watches, and those a shred transaction wrote since it last looked
+pub struct ShredWatcher {
+    watched: Mutex<HashSet<Pubkey>>,
+    // With the slot of the latest write
+    touched: Mutex<HashMap<Pubkey, Slot>>,
+    transactions: Mutex<Vec<PoolTransaction>>,
+    wake: Notify,
+}
+
+impl ShredWatcher {
+    pub fn new() -> Self {
+        Self {
+            watched: Mutex::new(HashSet::new()),
+            touched: Mutex::new(HashMap::new()),
+            transactions: Mutex::new(Vec::new()),
+            wake: Notify::new(),
+        }
+    }
+
+    // Replaces the watched accounts
+    pub fn watch(&self, pubkeys: &[Pubkey]) {
+        *self.watched.lock().unwrap() = pubkeys.iter().copied().collect();
+    }
+
+    // Notes the watched accounts written by the transactions in `entries`, serialized as the proxy
+    // sends them, and wakes the monitor if there are any
+    pub fn observe(&self, slot: Slot, entries: &[u8]) -> Result<(), bincode::Error> {
+        let entries: Vec<Entry> = bincode::deserialize(entries)?;
+        let watched = self.watched.lock().unwrap();
+        let mut touched = self.touched.lock().unwrap();
+        let before = touched.len();
+        for transaction in entries.iter().flat_map(|entry| &entry.transactions) {
+            let message = &transaction.message;
+            let keys = message.static_account_keys();
+            let written: HashSet<Pubkey> = keys
+                .iter()
+                .enumerate()
+                .filter(|(index, key)| message.is_maybe_writable(*index) && watched.contains(key))
+                .map(|(_, key)| *key)
+                .collect();
+            for key in &written {
+                touched.insert(*key, slot);
+            }
+            if written.len() >= 2 {
+                self.transactions.lock().unwrap().push(PoolTransaction {
+                    slot,
+                    signer: keys[0],
+                    written,
+                });
+            }
+        }
+        if touched.len() > before {
+            self.wake.notify_one();
+        }
+        Ok(())
+    }
+
+    // The accounts written since the last call
+    pub fn take_touched(&self) -> HashMap<Pubkey, Slot> {
+        std::mem::take(&mut *self.touched.lock().unwrap())
+    }
+
+    // The transactions that wrote two or more watched accounts since the last call
+    pub fn take_transactions(&self) -> Vec<PoolTransaction> {
+        std::mem::take(&mut *self.transactions.lock().unwrap())
+    }
+
+    // Resolves once a watched account has been written, at once if one was since the last wake
+    pub async fn woken(&self) {
+        self.wake.notified().await
+    }
+}
+
+// Streams entries from the proxy into `watcher` for as long as the process runs, reconnecting with
+// backoff whenever the stream fails or ends
+pub fn spawn(config: ShredStreamConfig, watcher: Arc<ShredWatcher>) {
+    tokio::spawn(async move {
+        let mut backoff = Backoff::new(RECONNECT_BASE_DELAY, RECONNECT_MAX_DELAY);
+        loop {
+            match subscribe(&config.url).await {
+                Ok(mut stream) => {
+                    backoff.reset();
+                    println!("Subscribed to ShredStream at {}", config.url);
+                    loop {
+                        match stream.message().await {
+                            Ok(Some(message)) => {
+                                if let Err(e) = watcher.observe(message.slot, &message.entries) {
+                                    println!("Failed to decode ShredStream entries for slot {}: {}", message.slot, e);
+                                }
+                            }
+                            Ok(None) => break,
+                            Err(e) => {
+                                println!("ShredStream failed: {}", e);
+                                break;
+                            }
+                        }
+                    }
+                    println!("ShredStream closed, reconnecting");
+                }
+                Err(e) => println!("Failed to subscribe to ShredStream at {}: {}", config.url, e),
+            }
+            tokio::time::sleep(backoff.next_delay()).await;
+        }
+    });
+}
+
+async fn subscribe(url: &str) -> Result<tonic::Streaming<EntriesMessage>, Box<dyn std::error::Error + Send + Sync>> {
+    let channel = tonic::transport::Endpoint::from_shared(url.to_string())?.connect().await?;
+    let mut client = tonic::client::Grpc::new(channel);
+    client.ready().await?;
+    let response = client
+        .server_streaming(
+            tonic::Request::new(SubscribeEntriesRequest {}),
+            tonic::codegen::http::uri::PathAndQuery::from_static(SUBSCRIBE_ENTRIES_PATH),
+            tonic::codec::ProstCodec::<SubscribeEntriesRequest, EntriesMessage>::default(),
+        )
+        .await?;
+    Ok(response.into_inner())
+}
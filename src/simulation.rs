@@ -0,0 +1,104 @@
+use crate::rpc::RpcApi;
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_account_decoder::{UiAccount, UiAccountEncoding};
+use solana_client::rpc_config::{RpcSimulateTransactionAccountsConfig, RpcSimulateTransactionConfig};
+use solana_sdk::{account::Account, commitment_config::CommitmentConfig, pubkey::Pubkey, transaction::Transaction};
+use std::fmt;
+
+// Byte range of the amount field in an SPL token account
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+
+// What a simulated execution would have paid the owner, before it is sent
+#[derive(Debug, Clone)]
+pub struct SimulatedProfit {
+    // Lamports plus units of `mint` gained by the owner, with the transaction fee added back
+    pub gross_profit_lamports: i64,
+    pub fee_lamports: u64,
+    pub units_consumed: Option<u64>,
+}
+
+impl SimulatedProfit {
+    pub fn net_profit_lamports(&self) -> i64 {
+        self.gross_profit_lamports - self.fee_lamports as i64
+    }
+}
+
+// A simulation that failed on-chain, with its program logs kept for failure classification
+#[derive(Debug)]
+pub struct SimulationError {
+    pub message: String,
+    pub logs: Vec<String>,
+}
+
+impl fmt::Display for SimulationError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Simulation failed: {}", self.message)
+    }
+}
+
+impl std::error::Error for SimulationError {}
+
+// Simulates the signed transaction and measures the owner's lamport and `mint` token balance
+// change from the simulated post-state, against the current pre-state
+pub fn simulate_profit(
+    rpc_client: &dyn RpcApi,
+    transaction: &Transaction,
+    owner: &Pubkey,
+    mint: &Pubkey,
+) -> Result<SimulatedProfit, Box<dyn std::error::Error>> {
+    let token_account = get_associated_token_address(owner, mint);
+    let addresses = [*owner, token_account];
+
+    let commitment = CommitmentConfig::processed();
+    let pre = rpc_client.get_multiple_accounts_with_commitment(&addresses, commitment)?.value;
+    let pre_lamports = pre[0].as_ref().map_or(0, |account| account.lamports);
+    let pre_tokens = pre[1].as_ref().map_or(0, |account| token_amount(&account.data));
+
+    let config = RpcSimulateTransactionConfig {
+        sig_verify: false,
+        replace_recent_blockhash: false,
+        commitment: Some(commitment),
+        encoding: None,
+        accounts: Some(RpcSimulateTransactionAccountsConfig {
+            encoding: Some(UiAccountEncoding::Base64),
+            addresses: addresses.iter().map(|address| address.to_string()).collect(),
+        }),
+        min_context_slot: None,
+        inner_instructions: false,
+    };
+    let result = rpc_client.simulate_transaction_with_config(transaction, config)?.value;
+    if let Some(err) = result.err {
+        return Err(Box::new(SimulationError {
+            message: err.to_string(),
+            logs: result.logs.unwrap_or_default(),
+        }));
+    }
+
+    let post = result.accounts.unwrap_or_default();
+    let post_lamports = post.first().cloned().flatten().map_or(0, |account| account.lamports);
+    let post_tokens = post.get(1).cloned().flatten().map_or(0, |account| ui_token_amount(&account));
+
+    let fee_lamports = rpc_client.get_fee_for_message(&transaction.message)?;
+    // Added back only when the owner pays it rather than a separate fee payer
+    let fee_paid = if transaction.message.account_keys.first() == Some(owner) { fee_lamports } else { 0 };
+    let gross_profit_lamports = post_lamports as i64 - pre_lamports as i64
+        + fee_paid as i64
+        + post_tokens as i64
+        - pre_tokens as i64;
+
+    Ok(SimulatedProfit {
+        gross_profit_lamports,
+        fee_lamports,
+        units_consumed: result.units_consumed,
+    })
+}
+
+fn ui_token_amount(account: &UiAccount) -> u64 {
+    account.decode::<Account>().map_or(0, |account| token_amount(&account.data))
+}
+
+fn token_amount(data: &[u8]) -> u64 {
+    data.get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .map_or(0, u64::from_le_bytes)
+}
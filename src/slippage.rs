@@ -0,0 +1,142 @@
+use std::collections::HashMap;
+use std::env;
+
+#[derive(Debug, Clone)]
+pub struct SlippageConfig {
+    pub default_bps: u16,
+    pub min_bps: u16,
+    pub max_bps: u16,
+    pub widen_step_bps: u16,
+    pub tighten_step_bps: u16,
+    // Observed shortfall is multiplied by this to leave headroom
+    pub headroom: f64,
+}
+
+impl SlippageConfig {
+    pub fn from_env() -> Self {
+        let parse = |name: &str, default: u16| env::var(name).ok().and_then(|v| v.parse().ok()).unwrap_or(default);
+        Self {
+            default_bps: parse("SLIPPAGE_BPS", 300),
+            min_bps: parse("SLIPPAGE_MIN_BPS", 20),
+            max_bps: parse("SLIPPAGE_MAX_BPS", 500),
+            widen_step_bps: parse("SLIPPAGE_WIDEN_STEP_BPS", 25),
+            tighten_step_bps: parse("SLIPPAGE_TIGHTEN_STEP_BPS", 5),
+            headroom: 2.0,
+        }
+    }
+}
+
+// Per-pair slippage used for `minimum_amount_out`, moved by execution feedback
+pub struct SlippageTuner {
+    config: SlippageConfig,
+    adaptive: bool,
+    per_pair: HashMap<String, u16>,
+    // Pairs' own starting points in place of SLIPPAGE_BPS
+    defaults: HashMap<String, u16>,
+}
+
+impl SlippageTuner {
+    pub fn new(config: SlippageConfig, adaptive: bool) -> Self {
+        Self {
+            config,
+            adaptive,
+            per_pair: HashMap::new(),
+            defaults: HashMap::new(),
+        }
+    }
+
+    pub fn slippage_bps(&self, pair_key: &str) -> u16 {
+        self.per_pair
+            .get(pair_key)
+            .or_else(|| self.defaults.get(pair_key))
+            .copied()
+            .unwrap_or(self.config.default_bps)
+    }
+
+    // Sets the pair's starting slippage, SLIPPAGE_BPS when None, and forgets what was tuned from
+    // the previous one
+    pub fn set_default(&mut self, pair_key: &str, bps: Option<u16>) {
+        self.per_pair.remove(pair_key);
+        match bps {
+            Some(bps) => self.defaults.insert(pair_key.to_string(), bps),
+            None => self.defaults.remove(pair_key),
+        };
+    }
+
+    // A SlippageExceeded revert means the tolerance was too tight for the market
+    pub fn on_slippage_revert(&mut self, pair_key: &str) {
+        if !self.adaptive {
+            return;
+        }
+        let current = self.slippage_bps(pair_key);
+        let next = current.saturating_add(self.config.widen_step_bps).min(self.config.max_bps);
+        if next != current {
+            println!("Widened slippage for {}: {} -> {} bps after revert", pair_key, current, next);
+        }
+        self.per_pair.insert(pair_key.to_string(), next);
+    }
+
+    // A landed trade shows how far the fill fell short of the quote; tighten towards that with headroom
+    pub fn on_fill(&mut self, pair_key: &str, trade_amount: u64, expected_profit: i64, realized_profit: i64) {
+        if !self.adaptive || trade_amount == 0 {
+            return;
+        }
+        let shortfall = (expected_profit - realized_profit).max(0) as f64;
+        let observed_bps = shortfall * 10_000.0 / trade_amount as f64;
+        let floor = ((observed_bps * self.config.headroom).ceil() as u16).max(self.config.min_bps);
+
+        let current = self.slippage_bps(pair_key);
+        let next = current
+            .saturating_sub(self.config.tighten_step_bps)
+            .max(floor)
+            .min(self.config.max_bps);
+        if next != current {
+            println!("Adjusted slippage for {}: {} -> {} bps (fill shortfall {:.1} bps)", pair_key, current, next, observed_bps);
+        }
+        self.per_pair.insert(pair_key.to_string(), next);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> SlippageConfig {
+        SlippageConfig {
+            default_bps: 100,
+            min_bps: 20,
+            max_bps: 120,
+            widen_step_bps: 25,
+            tighten_step_bps: 5,
+            headroom: 2.0,
+        }
+    }
+
+    #[test]
+    fn slippage_widens_on_reverts_and_tightens_towards_fills() {
+        let mut tuner = SlippageTuner::new(config(), true);
+        tuner.on_slippage_revert("A/B");
+        assert_eq!(tuner.slippage_bps("A/B"), 120);
+        tuner.on_slippage_revert("A/B");
+        assert_eq!(tuner.slippage_bps("A/B"), 120);
+        // A fill 10 bps short of its quote keeps twice that
+        tuner.on_fill("A/B", 1_000_000, 2_000, 1_000);
+        assert_eq!(tuner.slippage_bps("A/B"), 115);
+        for _ in 0..30 {
+            tuner.on_fill("A/B", 1_000_000, 2_000, 1_000);
+        }
+        assert_eq!(tuner.slippage_bps("A/B"), 20);
+
+        // Other pairs start from SLIPPAGE_BPS
+        assert_eq!(tuner.slippage_bps("C/D"), 100);
+        // A pair's own default replaces SLIPPAGE_BPS and what was tuned
+        tuner.set_default("A/B", Some(60));
+        assert_eq!(tuner.slippage_bps("A/B"), 60);
+        tuner.set_default("A/B", None);
+        assert_eq!(tuner.slippage_bps("A/B"), 100);
+
+        let mut fixed = SlippageTuner::new(config(), false);
+        fixed.on_slippage_revert("A/B");
+        assert_eq!(fixed.slippage_bps("A/B"), 100);
+    }
+}
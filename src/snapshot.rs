@@ -0,0 +1,409 @@
+use crate::commands;
+use crate::journal::unix_timestamp;
+use crate::network;
+use crate::pair_store::PairStore;
+use crate::raydium_clmm;
+use crate::rpc::MAX_ACCOUNTS_PER_REQUEST;
+use crate::route::{self, Venue};
+use crate::test_env;
+use crate::whirlpool;
+use anchor_spl::token::spl_token;
+use pyth_sdk_solana::state::SolanaPriceAccount;
+use serde::Serialize;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+use std::env;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+// Decoded state of every pool, vault and oracle the configured pairs and routes read, captured at
+// one slot, to compare what the bot computed with what the chain held
+
+const TOKEN_ACCOUNT_LEN: usize = 165;
+
+// Raydium AMM v4 AmmInfo: header fields, then fees and the pending PnL, then the vaults and mints
+const AMM_STATUS_OFFSET: usize = 0;
+const AMM_COIN_DECIMALS_OFFSET: usize = 32;
+const AMM_PC_DECIMALS_OFFSET: usize = 40;
+const AMM_NEED_TAKE_PNL_COIN_OFFSET: usize = 192;
+const AMM_NEED_TAKE_PNL_PC_OFFSET: usize = 200;
+const AMM_COIN_VAULT_OFFSET: usize = 336;
+const AMM_PC_VAULT_OFFSET: usize = 368;
+const AMM_COIN_MINT_OFFSET: usize = 400;
+const AMM_PC_MINT_OFFSET: usize = 432;
+
+// Raydium CLMM PoolState
+const CLMM_MINT_0_OFFSET: usize = 73;
+const CLMM_MINT_1_OFFSET: usize = 105;
+const CLMM_VAULT_0_OFFSET: usize = 137;
+const CLMM_VAULT_1_OFFSET: usize = 169;
+const CLMM_TICK_SPACING_OFFSET: usize = 235;
+const CLMM_LIQUIDITY_OFFSET: usize = 237;
+const CLMM_SQRT_PRICE_OFFSET: usize = 253;
+const CLMM_TICK_CURRENT_OFFSET: usize = 269;
+
+// Orca Whirlpool
+const WHIRLPOOL_TICK_SPACING_OFFSET: usize = 41;
+const WHIRLPOOL_FEE_RATE_OFFSET: usize = 45;
+const WHIRLPOOL_LIQUIDITY_OFFSET: usize = 49;
+const WHIRLPOOL_SQRT_PRICE_OFFSET: usize = 65;
+const WHIRLPOOL_TICK_CURRENT_OFFSET: usize = 81;
+const WHIRLPOOL_MINT_A_OFFSET: usize = 101;
+const WHIRLPOOL_VAULT_A_OFFSET: usize = 133;
+const WHIRLPOOL_MINT_B_OFFSET: usize = 181;
+const WHIRLPOOL_VAULT_B_OFFSET: usize = 213;
+
+// Account state by the program that owns it; numbers that can exceed 2^53 are strings so JSON
+// readers don't round them
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum AccountState {
+    TokenAccount {
+        mint: String,
+        owner: String,
+        amount: u64,
+    },
+    PythPrice {
+        price: i64,
+        conf: u64,
+        expo: i32,
+        status: String,
+        publish_slot: u64,
+        value: f64,
+    },
+    RaydiumAmm {
+        status: u64,
+        coin_mint: String,
+        pc_mint: String,
+        coin_vault: String,
+        pc_vault: String,
+        coin_decimals: u64,
+        pc_decimals: u64,
+        need_take_pnl_coin: u64,
+        need_take_pnl_pc: u64,
+    },
+    RaydiumClmm {
+        mint_0: String,
+        mint_1: String,
+        vault_0: String,
+        vault_1: String,
+        tick_spacing: u16,
+        liquidity: String,
+        sqrt_price_x64: String,
+        tick_current: i32,
+    },
+    Whirlpool {
+        mint_a: String,
+        mint_b: String,
+        vault_a: String,
+        vault_b: String,
+        tick_spacing: u16,
+        fee_rate: u16,
+        liquidity: String,
+        sqrt_price_x64: String,
+        tick_current: i32,
+    },
+    // Owned by a program this bot doesn't decode; only the raw data is kept
+    Unknown,
+    Missing,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AccountSnapshot {
+    pub address: String,
+    // Where the account comes from, e.g. "pair <A>/<B> quote vault 2"
+    pub label: String,
+    pub owner: Option<String>,
+    pub lamports: u64,
+    pub state: AccountState,
+    // Base64 account data
+    pub data: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Snapshot {
+    // Slot every account was read at; accounts past the first request's hundred may be newer
+    pub slot: u64,
+    pub timestamp: u64,
+    pub accounts: Vec<AccountSnapshot>,
+}
+
+// Little-endian fields of an account's data; None past its end
+struct Fields<'a>(&'a [u8]);
+
+impl Fields<'_> {
+    fn bytes<const N: usize>(&self, offset: usize) -> Option<[u8; N]> {
+        self.0.get(offset..offset + N).map(|bytes| bytes.try_into().unwrap())
+    }
+
+    fn pubkey(&self, offset: usize) -> Option<String> {
+        self.bytes(offset).map(|bytes| Pubkey::new_from_array(bytes).to_string())
+    }
+
+    fn u16(&self, offset: usize) -> Option<u16> {
+        self.bytes(offset).map(u16::from_le_bytes)
+    }
+
+    fn u64(&self, offset: usize) -> Option<u64> {
+        self.bytes(offset).map(u64::from_le_bytes)
+    }
+
+    fn u128(&self, offset: usize) -> Option<String> {
+        self.bytes(offset).map(|bytes| u128::from_le_bytes(bytes).to_string())
+    }
+
+    fn i32(&self, offset: usize) -> Option<i32> {
+        self.bytes(offset).map(i32::from_le_bytes)
+    }
+}
+
+// Decodes the account from its owner and layout; an account that doesn't fit is Unknown
+pub fn decode_account(account: &Account) -> AccountState {
+    let owner = account.owner.to_string();
+    let decoded = if account.owner == spl_token::id() || account.owner == anchor_spl::token_2022::ID {
+        decode_token_account(&account.data)
+    } else if owner == network::addresses().raydium_amm {
+        decode_raydium_amm(&Fields(&account.data))
+    } else if owner == raydium_clmm::PROGRAM_ID {
+        decode_raydium_clmm(&Fields(&account.data))
+    } else if owner == whirlpool::PROGRAM_ID {
+        decode_whirlpool(&Fields(&account.data))
+    } else {
+        // Pyth price accounts carry their own magic number, whichever program owns them
+        decode_pyth_price(&account.data)
+    };
+    decoded.unwrap_or(AccountState::Unknown)
+}
+
+fn decode_token_account(data: &[u8]) -> Option<AccountState> {
+    // Mints are shorter than token accounts, which Token-2022 extends past the base layout
+    if data.len() < TOKEN_ACCOUNT_LEN {
+        return None;
+    }
+    let fields = Fields(data);
+    Some(AccountState::TokenAccount {
+        mint: fields.pubkey(0)?,
+        owner: fields.pubkey(32)?,
+        amount: fields.u64(64)?,
+    })
+}
+
+fn decode_raydium_amm(fields: &Fields) -> Option<AccountState> {
+    Some(AccountState::RaydiumAmm {
+        status: fields.u64(AMM_STATUS_OFFSET)?,
+        coin_mint: fields.pubkey(AMM_COIN_MINT_OFFSET)?,
+        pc_mint: fields.pubkey(AMM_PC_MINT_OFFSET)?,
+        coin_vault: fields.pubkey(AMM_COIN_VAULT_OFFSET)?,
+        pc_vault: fields.pubkey(AMM_PC_VAULT_OFFSET)?,
+        coin_decimals: fields.u64(AMM_COIN_DECIMALS_OFFSET)?,
+        pc_decimals: fields.u64(AMM_PC_DECIMALS_OFFSET)?,
+        need_take_pnl_coin: fields.u64(AMM_NEED_TAKE_PNL_COIN_OFFSET)?,
+        need_take_pnl_pc: fields.u64(AMM_NEED_TAKE_PNL_PC_OFFSET)?,
+    })
+}
+
+fn decode_raydium_clmm(fields: &Fields) -> Option<AccountState> {
+    Some(AccountState::RaydiumClmm {
+        mint_0: fields.pubkey(CLMM_MINT_0_OFFSET)?,
+        mint_1: fields.pubkey(CLMM_MINT_1_OFFSET)?,
+        vault_0: fields.pubkey(CLMM_VAULT_0_OFFSET)?,
+        vault_1: fields.pubkey(CLMM_VAULT_1_OFFSET)?,
+        tick_spacing: fields.u16(CLMM_TICK_SPACING_OFFSET)?,
+        liquidity: fields.u128(CLMM_LIQUIDITY_OFFSET)?,
+        sqrt_price_x64: fields.u128(CLMM_SQRT_PRICE_OFFSET)?,
+        tick_current: fields.i32(CLMM_TICK_CURRENT_OFFSET)?,
+    })
+}
+
+fn decode_whirlpool(fields: &Fields) -> Option<AccountState> {
+    Some(AccountState::Whirlpool {
+        mint_a: fields.pubkey(WHIRLPOOL_MINT_A_OFFSET)?,
+        mint_b: fields.pubkey(WHIRLPOOL_MINT_B_OFFSET)?,
+        vault_a: fields.pubkey(WHIRLPOOL_VAULT_A_OFFSET)?,
+        vault_b: fields.pubkey(WHIRLPOOL_VAULT_B_OFFSET)?,
+        tick_spacing: fields.u16(WHIRLPOOL_TICK_SPACING_OFFSET)?,
+        fee_rate: fields.u16(WHIRLPOOL_FEE_RATE_OFFSET)?,
+        liquidity: fields.u128(WHIRLPOOL_LIQUIDITY_OFFSET)?,
+        sqrt_price_x64: fields.u128(WHIRLPOOL_SQRT_PRICE_OFFSET)?,
+        tick_current: fields.i32(WHIRLPOOL_TICK_CURRENT_OFFSET)?,
+    })
+}
+
+fn decode_pyth_price(data: &[u8]) -> Option<AccountState> {
+    let price: &SolanaPriceAccount = pyth_sdk_solana::state::load_price_account(data).ok()?;
+    Some(AccountState::PythPrice {
+        price: price.agg.price,
+        conf: price.agg.conf,
+        expo: price.expo,
+        status: format!("{:?}", price.agg.status),
+        publish_slot: price.agg.pub_slot,
+        value: price.agg.price as f64 * 10f64.powi(price.expo),
+    })
+}
+
+// Vaults of a decoded pool, to be captured alongside it
+fn pool_vaults(state: &AccountState) -> Vec<&str> {
+    match state {
+        AccountState::RaydiumAmm { coin_vault, pc_vault, .. } => vec![coin_vault, pc_vault],
+        AccountState::RaydiumClmm { vault_0, vault_1, .. } => vec![vault_0, vault_1],
+        AccountState::Whirlpool { vault_a, vault_b, .. } => vec![vault_a, vault_b],
+        _ => Vec::new(),
+    }
+}
+
+// Labelled addresses read by the configured pairs (the pair store, or the pair in the environment)
+// and the routes in ROUTES_FILE, plus the SOL/USD feed
+fn configured_accounts() -> Result<Vec<(Pubkey, String)>, Box<dyn std::error::Error>> {
+    let sol_feed = env::var("PYTH_SOL_USD_ACCOUNT")
+        .ok()
+        .filter(|v| !v.is_empty())
+        .unwrap_or_else(|| network::addresses().pyth_sol_usd.to_string());
+    let mut accounts = vec![(Pubkey::from_str(&sol_feed)?, "SOL/USD feed".to_string())];
+    let mut add_pair = |key: &str, pools: Vec<Pubkey>, vaults: Vec<Pubkey>, oracles: Option<(Pubkey, Pubkey)>| {
+        accounts.extend(pools.into_iter().enumerate().map(|(i, pool)| (pool, format!("pair {} pool {}", key, i))));
+        accounts.extend(vaults.into_iter().enumerate().map(|(i, vault)| (vault, format!("pair {} quote vault {}", key, i))));
+        if let Some((oracle_a, oracle_b)) = oracles {
+            accounts.push((oracle_a, format!("pair {} oracle A", key)));
+            accounts.push((oracle_b, format!("pair {} oracle B", key)));
+        }
+    };
+
+    let store = PairStore::from_env();
+    if store.exists() {
+        let address = |address: &String| Pubkey::from_str(address);
+        for pair in store.load()?.iter().filter(|pair| pair.enabled) {
+            let oracles = match &pair.oracle_accounts {
+                Some((oracle_a, oracle_b)) => Some((address(oracle_a)?, address(oracle_b)?)),
+                None => None,
+            };
+            add_pair(
+                &pair.key(),
+                pair.pool_accounts.iter().map(address).collect::<Result<_, _>>()?,
+                pair.quote_vaults.iter().map(address).collect::<Result<_, _>>()?,
+                oracles,
+            );
+        }
+    } else if let (Ok(token_a), Ok(token_b)) = (env::var("BONK_TOKEN_ADDRESS"), env::var("GOAT_TOKEN_ADDRESS")) {
+        let oracles = test_env::address_list("TOKEN_A_PYTH_ACCOUNT")?
+            .into_iter()
+            .zip(test_env::address_list("TOKEN_B_PYTH_ACCOUNT")?)
+            .next();
+        add_pair(
+            &format!("{}/{}", token_a, token_b),
+            test_env::address_list("POOL_ACCOUNTS")?,
+            test_env::address_list("QUOTE_VAULTS")?,
+            oracles,
+        );
+    }
+
+    if let Some(routes_file) = env::var("ROUTES_FILE").ok().filter(|v| !v.is_empty()) {
+        for route in route::load_routes(std::path::Path::new(&routes_file))? {
+            for (i, leg) in route.legs.iter().enumerate() {
+                if let (Some(pool), true) = (&leg.pool, leg.venue != Venue::Jupiter) {
+                    accounts.push((Pubkey::from_str(pool)?, format!("route {} leg {} {:?} pool", route.name, i, leg.venue)));
+                }
+            }
+        }
+    }
+
+    // An account shared by several pairs is captured once, under its first label
+    let mut seen = std::collections::HashSet::new();
+    accounts.retain(|(address, _)| seen.insert(*address));
+    Ok(accounts)
+}
+
+// The slot the accounts were read at, and the accounts in `addresses` order
+type Fetched = (u64, Vec<Option<Account>>);
+
+fn fetch(rpc: &RpcClient, addresses: &[Pubkey]) -> Result<Fetched, Box<dyn std::error::Error>> {
+    let mut slot = None;
+    let mut accounts = Vec::with_capacity(addresses.len());
+    for chunk in addresses.chunks(MAX_ACCOUNTS_PER_REQUEST) {
+        let response = rpc.get_multiple_accounts_with_commitment(chunk, rpc.commitment())?;
+        slot.get_or_insert(response.context.slot);
+        accounts.extend(response.value);
+    }
+    Ok((slot.unwrap_or(0), accounts))
+}
+
+fn account_snapshot(address: Pubkey, label: String, account: Option<Account>) -> AccountSnapshot {
+    match account {
+        Some(account) => AccountSnapshot {
+            address: address.to_string(),
+            label,
+            owner: Some(account.owner.to_string()),
+            lamports: account.lamports,
+            state: decode_account(&account),
+            data: Some(base64::encode(&account.data)),
+        },
+        None => AccountSnapshot {
+            address: address.to_string(),
+            label,
+            owner: None,
+            lamports: 0,
+            state: AccountState::Missing,
+            data: None,
+        },
+    }
+}
+
+// Captures the configured accounts, then the vaults of every pool among them
+pub fn capture(rpc: &RpcClient) -> Result<Snapshot, Box<dyn std::error::Error>> {
+    let configured = configured_accounts()?;
+    let addresses: Vec<Pubkey> = configured.iter().map(|(address, _)| *address).collect();
+    let (slot, fetched) = fetch(rpc, &addresses)?;
+    let mut accounts: Vec<AccountSnapshot> = configured
+        .into_iter()
+        .zip(fetched)
+        .map(|((address, label), account)| account_snapshot(address, label, account))
+        .collect();
+
+    let mut vaults = Vec::new();
+    for snapshot in &accounts {
+        for vault in pool_vaults(&snapshot.state) {
+            let vault = Pubkey::from_str(vault)?;
+            if !addresses.contains(&vault) && !vaults.iter().any(|(address, _)| *address == vault) {
+                vaults.push((vault, format!("{} vault", snapshot.label)));
+            }
+        }
+    }
+    let vault_addresses: Vec<Pubkey> = vaults.iter().map(|(address, _)| *address).collect();
+    let (_, fetched) = fetch(rpc, &vault_addresses)?;
+    accounts.extend(
+        vaults
+            .into_iter()
+            .zip(fetched)
+            .map(|((address, label), account)| account_snapshot(address, label, account)),
+    );
+
+    Ok(Snapshot {
+        slot,
+        timestamp: unix_timestamp(),
+        accounts,
+    })
+}
+
+// `snapshot [--out <path>]`, by default snapshots/snapshot-<slot>.json
+pub fn run(args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let rpc_url = env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+    let snapshot = capture(&RpcClient::new(rpc_url))?;
+    let path = match commands::flag_value(args, "--out") {
+        Some(path) => PathBuf::from(path),
+        None => {
+            std::fs::create_dir_all("snapshots")?;
+            PathBuf::from(format!("snapshots/snapshot-{}.json", snapshot.slot))
+        }
+    };
+    std::fs::write(&path, serde_json::to_string_pretty(&snapshot)?)?;
+
+    let missing = snapshot.accounts.iter().filter(|account| matches!(account.state, AccountState::Missing)).count();
+    println!(
+        "Wrote {} accounts at slot {} to {}{}",
+        snapshot.accounts.len(),
+        snapshot.slot,
+        path.display(),
+        if missing > 0 { format!(" ({} not found)", missing) } else { String::new() }
+    );
+    Ok(())
+}
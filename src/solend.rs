@@ -0,0 +1,168 @@
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_sdk::instruction::{AccountMeta, Instruction};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::sysvar;
+use std::collections::HashMap;
+use std::env;
+use std::str::FromStr;
+
+pub const PROGRAM_ID: &str = "So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo";
+
+// Offsets in Solend's reserve account
+const LENDING_MARKET_OFFSET: usize = 10;
+const LIQUIDITY_SUPPLY_OFFSET: usize = 75;
+const FEE_RECEIVER_OFFSET: usize = 339;
+const FLASH_BORROW_RESERVE_LIQUIDITY: u8 = 19;
+const FLASH_REPAY_RESERVE_LIQUIDITY: u8 = 20;
+
+// Solend reserves the bot flash-borrows from. Solend refuses flash loans made through CPI, so like
+// marginfi the bot wraps the trade in flash_borrow / ... / flash_repay instead of the program CPI-ing
+#[derive(Debug, Clone)]
+pub struct SolendConfig {
+    pub program_id: Pubkey,
+    // Reserve lending each mint the bot borrows
+    pub reserves: HashMap<Pubkey, Pubkey>,
+}
+
+impl SolendConfig {
+    pub fn from_env() -> Self {
+        let reserves = env::var("SOLEND_RESERVES")
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+            .map(|entry| {
+                let (mint, reserve) = entry.split_once(':').expect("SOLEND_RESERVES entries must be <mint>:<reserve>");
+                (
+                    Pubkey::from_str(mint.trim()).expect("Invalid mint in SOLEND_RESERVES"),
+                    Pubkey::from_str(reserve.trim()).expect("Invalid reserve in SOLEND_RESERVES"),
+                )
+            })
+            .collect();
+
+        Self {
+            program_id: Pubkey::from_str(PROGRAM_ID).unwrap(),
+            reserves,
+        }
+    }
+
+    pub fn reserve_address(&self, mint: &Pubkey) -> Result<Pubkey, Box<dyn std::error::Error>> {
+        Ok(*self
+            .reserves
+            .get(mint)
+            .ok_or_else(|| format!("No Solend reserve configured for mint {}", mint))?)
+    }
+}
+
+// The accounts of a reserve the flash loan instructions take, read from the reserve account
+#[derive(Debug, Clone)]
+pub struct SolendReserve {
+    pub program_id: Pubkey,
+    pub address: Pubkey,
+    pub liquidity_supply: Pubkey,
+    pub fee_receiver: Pubkey,
+    pub lending_market: Pubkey,
+    pub market_authority: Pubkey,
+}
+
+impl SolendReserve {
+    pub fn decode(program_id: &Pubkey, address: Pubkey, data: &[u8]) -> Result<Self, Box<dyn std::error::Error>> {
+        let pubkey_at = |offset: usize| -> Result<Pubkey, Box<dyn std::error::Error>> {
+            let bytes: [u8; 32] = data
+                .get(offset..offset + 32)
+                .ok_or_else(|| format!("Solend reserve {} is too short", address))?
+                .try_into()?;
+            Ok(Pubkey::new_from_array(bytes))
+        };
+        let lending_market = pubkey_at(LENDING_MARKET_OFFSET)?;
+        let (market_authority, _) = Pubkey::find_program_address(&[lending_market.as_ref()], program_id);
+        Ok(Self {
+            program_id: *program_id,
+            address,
+            liquidity_supply: pubkey_at(LIQUIDITY_SUPPLY_OFFSET)?,
+            fee_receiver: pubkey_at(FEE_RECEIVER_OFFSET)?,
+            lending_market,
+            market_authority,
+        })
+    }
+
+    // The program's loan accounts: the reserve, whose flash_loan_fee_wad the route must cover
+    pub fn loan_accounts(&self) -> Vec<AccountMeta> {
+        vec![AccountMeta::new_readonly(self.address, false)]
+    }
+
+    // `inner` wrapped in a flash loan of `amount` of `mint` into the owner's associated token
+    // account. `first_index` is the position of the first returned instruction in the transaction,
+    // which the repay names as its borrow. The whole fee goes to the reserve's fee receiver, which
+    // also stands in as the host fee receiver
+    pub fn wrap(
+        &self,
+        owner: &Pubkey,
+        mint: &Pubkey,
+        amount: u64,
+        inner: Instruction,
+        first_index: usize,
+    ) -> Result<Vec<Instruction>, Box<dyn std::error::Error>> {
+        let token_account = get_associated_token_address(owner, mint);
+        let borrow_index = u8::try_from(first_index)?;
+
+        let borrow = Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new(self.liquidity_supply, false),
+                AccountMeta::new(token_account, false),
+                AccountMeta::new(self.address, false),
+                AccountMeta::new_readonly(self.lending_market, false),
+                AccountMeta::new_readonly(self.market_authority, false),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                AccountMeta::new_readonly(anchor_spl::token::ID, false),
+            ],
+            data: [vec![FLASH_BORROW_RESERVE_LIQUIDITY], amount.to_le_bytes().to_vec()].concat(),
+        };
+        let repay = Instruction {
+            program_id: self.program_id,
+            accounts: vec![
+                AccountMeta::new(token_account, false),
+                AccountMeta::new(self.liquidity_supply, false),
+                AccountMeta::new(self.fee_receiver, false),
+                AccountMeta::new(self.fee_receiver, false),
+                AccountMeta::new(self.address, false),
+                AccountMeta::new_readonly(self.lending_market, false),
+                AccountMeta::new_readonly(*owner, true),
+                AccountMeta::new_readonly(sysvar::instructions::id(), false),
+                AccountMeta::new_readonly(anchor_spl::token::ID, false),
+            ],
+            data: [vec![FLASH_REPAY_RESERVE_LIQUIDITY], amount.to_le_bytes().to_vec(), vec![borrow_index]].concat(),
+        };
+
+        Ok(vec![borrow, inner, repay])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flash_loan_wraps_the_trade_and_repays_its_own_borrow() {
+        let program_id = Pubkey::from_str(PROGRAM_ID).unwrap();
+        let (lending_market, liquidity_supply, fee_receiver) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0u8; FEE_RECEIVER_OFFSET + 32];
+        data[LENDING_MARKET_OFFSET..LENDING_MARKET_OFFSET + 32].copy_from_slice(lending_market.as_ref());
+        data[LIQUIDITY_SUPPLY_OFFSET..LIQUIDITY_SUPPLY_OFFSET + 32].copy_from_slice(liquidity_supply.as_ref());
+        data[FEE_RECEIVER_OFFSET..].copy_from_slice(fee_receiver.as_ref());
+        let reserve = SolendReserve::decode(&program_id, Pubkey::new_unique(), &data).unwrap();
+        assert_eq!((reserve.lending_market, reserve.liquidity_supply, reserve.fee_receiver), (lending_market, liquidity_supply, fee_receiver));
+        assert!(SolendReserve::decode(&program_id, reserve.address, &data[..FEE_RECEIVER_OFFSET]).is_err());
+
+        let (owner, mint) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let inner = Instruction { program_id: Pubkey::new_unique(), accounts: Vec::new(), data: Vec::new() };
+        let wrapped = reserve.wrap(&owner, &mint, 500, inner.clone(), 2).unwrap();
+        assert_eq!(wrapped[1], inner);
+        assert_eq!(wrapped[0].data, [vec![FLASH_BORROW_RESERVE_LIQUIDITY], 500u64.to_le_bytes().to_vec()].concat());
+        assert_eq!(wrapped[2].data, [vec![FLASH_REPAY_RESERVE_LIQUIDITY], 500u64.to_le_bytes().to_vec(), vec![2]].concat());
+        assert_eq!(wrapped[0].accounts[1].pubkey, get_associated_token_address(&owner, &mint));
+        assert!(wrapped[2].accounts[6].is_signer);
+        assert!(reserve.wrap(&owner, &mint, 500, inner, 256).is_err());
+    }
+}
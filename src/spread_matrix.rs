@@ -0,0 +1,153 @@
+use crate::evaluator::{Evaluation, Quote};
+use std::collections::{BTreeMap, HashSet};
+
+// Spread of every venue combination of each pair at its latest check, tradable or not, to watch an
+// edge form before it clears the threshold. A combination's spread is the sell venue's price over
+// the buy venue's in bps; a negative one loses in that direction. Alongside is the spread the pair
+// needs, its required profit as a price difference in bps of the best buy price
+
+#[derive(Debug, Clone)]
+struct PairSpreads {
+    slot: u64,
+    // Venue labels and prices in the quote's order: the program's two, then the further venues
+    prices: Vec<(String, u64)>,
+    required_bps: f64,
+}
+
+#[derive(Debug, Default)]
+pub struct SpreadMatrix {
+    pairs: BTreeMap<String, PairSpreads>,
+}
+
+// Signed spread of buying at `buy` and selling at `sell`
+pub fn spread_bps(buy: u64, sell: u64) -> f64 {
+    (sell as f64 - buy as f64) * 10_000.0 / buy.max(1) as f64
+}
+
+impl SpreadMatrix {
+    // Replaces the pair's matrix with the quote of its latest check
+    pub fn record(&mut self, pair_key: &str, slot: u64, quote: &Quote, evaluation: &Evaluation) {
+        let mut prices = vec![
+            ("jupiter_side".to_string(), quote.jupiter_price),
+            ("raydium".to_string(), quote.raydium_price),
+        ];
+        for venue in &quote.venues {
+            let label = match &venue.pool {
+                Some(pool) => format!("{}:{}", venue.venue.name(), &pool[..pool.len().min(8)]),
+                None => venue.venue.name().to_string(),
+            };
+            prices.push((label, venue.price));
+        }
+        let required_diff = evaluation.required_profit * 1e9 / evaluation.trade_amount.max(1) as f64;
+        self.pairs.insert(
+            pair_key.to_string(),
+            PairSpreads {
+                slot,
+                prices,
+                required_bps: required_diff * 10_000.0 / quote.buy().2.max(1) as f64,
+            },
+        );
+    }
+
+    // Drops the pairs no longer monitored
+    pub fn retain<'a>(&mut self, pair_keys: impl IntoIterator<Item = &'a str>) {
+        let monitored: HashSet<&str> = pair_keys.into_iter().collect();
+        self.pairs.retain(|key, _| monitored.contains(key.as_str()));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.pairs.is_empty()
+    }
+
+    // (buy venue, sell venue, spread bps) of every ordered combination of the pair's venues
+    pub fn spreads(&self, pair_key: &str) -> Vec<(&str, &str, f64)> {
+        let Some(pair) = self.pairs.get(pair_key) else {
+            return Vec::new();
+        };
+        let mut spreads = Vec::new();
+        for (buy, buy_price) in &pair.prices {
+            for (sell, sell_price) in pair.prices.iter().filter(|(sell, _)| sell != buy) {
+                spreads.push((buy.as_str(), sell.as_str(), spread_bps(*buy_price, *sell_price)));
+            }
+        }
+        spreads
+    }
+
+    // The spread the pair needs at its latest check, in bps
+    #[cfg(test)]
+    pub fn required_bps(&self, pair_key: &str) -> Option<f64> {
+        self.pairs.get(pair_key).map(|pair| pair.required_bps)
+    }
+
+    // Each pair with its slot, needed spread, venue prices and every combination's spread
+    pub fn to_json(&self) -> serde_json::Value {
+        let pairs: Vec<serde_json::Value> = self
+            .pairs
+            .iter()
+            .map(|(key, pair)| {
+                let spreads: Vec<serde_json::Value> = self
+                    .spreads(key)
+                    .into_iter()
+                    .map(|(buy, sell, spread)| serde_json::json!({ "buy": buy, "sell": sell, "spread_bps": spread }))
+                    .collect();
+                let prices: serde_json::Map<String, serde_json::Value> =
+                    pair.prices.iter().map(|(label, price)| (label.clone(), (*price).into())).collect();
+                serde_json::json!({
+                    "pair": key,
+                    "slot": pair.slot,
+                    "required_bps": pair.required_bps,
+                    "prices": prices,
+                    "spreads": spreads,
+                })
+            })
+            .collect();
+        serde_json::Value::Array(pairs)
+    }
+
+    // A buy-by-sell grid per pair
+    pub fn summary(&self) -> String {
+        let mut lines = Vec::new();
+        for (key, pair) in &self.pairs {
+            lines.push(format!("  {} at slot {}, needs {:.1} bps", key, pair.slot, pair.required_bps));
+            let width = pair.prices.iter().map(|(label, _)| label.len()).max().unwrap_or(0).max(10);
+            let mut header = format!("    {:<width$}", "buy \\ sell", width = width);
+            for (sell, _) in &pair.prices {
+                header.push_str(&format!(" {:>width$}", sell, width = width));
+            }
+            lines.push(header);
+            for (buy, buy_price) in &pair.prices {
+                let mut row = format!("    {:<width$}", buy, width = width);
+                for (sell, sell_price) in &pair.prices {
+                    let cell = if sell == buy { "-".to_string() } else { format!("{:.1}", spread_bps(*buy_price, *sell_price)) };
+                    row.push_str(&format!(" {:>width$}", cell, width = width));
+                }
+                lines.push(row);
+            }
+        }
+        lines.join("\n")
+    }
+
+    // Prometheus text format, one gauge per pair and venue combination
+    pub fn metrics(&self) -> String {
+        let mut text = String::from(
+            "# HELP arb_venue_spread_bps Spread of buying a pair on one venue and selling on another at its latest check\n\
+             # TYPE arb_venue_spread_bps gauge\n",
+        );
+        for key in self.pairs.keys() {
+            for (buy, sell, spread) in self.spreads(key) {
+                text.push_str(&format!(
+                    "arb_venue_spread_bps{{pair=\"{}\",buy=\"{}\",sell=\"{}\"}} {}\n",
+                    key, buy, sell, spread
+                ));
+            }
+        }
+        text.push_str(
+            "# HELP arb_pair_required_spread_bps Spread a pair needs to execute at its latest check\n\
+             # TYPE arb_pair_required_spread_bps gauge\n",
+        );
+        for (key, pair) in &self.pairs {
+            text.push_str(&format!("arb_pair_required_spread_bps{{pair=\"{}\"}} {}\n", key, pair.required_bps));
+        }
+        text
+    }
+}
@@ -0,0 +1,113 @@
+use crate::evaluator::Quote;
+use std::collections::VecDeque;
+
+// Spread in bps between the best buy and sell venue prices of a quote
+pub fn spread_bps(quote: &Quote) -> f64 {
+    let buy = quote.buy().2 as f64;
+    let sell = quote.sell().2 as f64;
+    let mid = (buy + sell) / 2.0;
+    if mid <= 0.0 {
+        return 0.0;
+    }
+    (sell - buy) * 10_000.0 / mid
+}
+
+// Mean and standard deviation over the last `capacity` spreads
+#[derive(Debug, Clone)]
+pub struct RollingStats {
+    capacity: usize,
+    values: VecDeque<f64>,
+    sum: f64,
+    sum_sq: f64,
+}
+
+impl RollingStats {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(2),
+            values: VecDeque::with_capacity(capacity),
+            sum: 0.0,
+            sum_sq: 0.0,
+        }
+    }
+
+    pub fn push(&mut self, value: f64) {
+        if self.values.len() == self.capacity {
+            if let Some(old) = self.values.pop_front() {
+                self.sum -= old;
+                self.sum_sq -= old * old;
+            }
+        }
+        self.values.push_back(value);
+        self.sum += value;
+        self.sum_sq += value * value;
+    }
+
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    pub fn is_warm(&self) -> bool {
+        self.values.len() == self.capacity
+    }
+
+    pub fn mean(&self) -> f64 {
+        if self.values.is_empty() {
+            0.0
+        } else {
+            self.sum / self.values.len() as f64
+        }
+    }
+
+    pub fn std_dev(&self) -> f64 {
+        if self.values.len() < 2 {
+            return 0.0;
+        }
+        let n = self.values.len() as f64;
+        let variance = (self.sum_sq - self.sum * self.sum / n) / n;
+        variance.max(0.0).sqrt()
+    }
+
+    // How many standard deviations `value` sits above the rolling mean
+    pub fn z_score(&self, value: f64) -> f64 {
+        let std_dev = self.std_dev();
+        if std_dev == 0.0 {
+            0.0
+        } else {
+            (value - self.mean()) / std_dev
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolling_stats_cover_only_the_window() {
+        let mut stats = RollingStats::new(3);
+        for value in [100.0, 1.0, 2.0, 3.0] {
+            stats.push(value);
+        }
+        // The first value has left the window
+        assert!(stats.is_warm());
+        assert_eq!(stats.mean(), 2.0);
+        assert!((stats.std_dev() - (2.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        assert!((stats.z_score(3.0) - 1.0 / (2.0f64 / 3.0).sqrt()).abs() < 1e-9);
+        // A flat history gives no z-score at all
+        let mut flat = RollingStats::new(2);
+        flat.push(5.0);
+        flat.push(5.0);
+        assert_eq!(flat.z_score(50.0), 0.0);
+    }
+
+    #[test]
+    fn spread_is_measured_against_the_mid_price() {
+        let quote = Quote {
+            jupiter_price: 1_010_000_000,
+            raydium_price: 990_000_000,
+            venues: Vec::new(),
+        };
+        assert!((spread_bps(&quote) - 200.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,225 @@
+use crate::evaluator::{self, Evaluation, EvaluatorParams, Quote};
+use crate::spread_stats::{self, RollingStats};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+// Everything a strategy may look at when deciding on one quote
+pub struct MarketContext<'a> {
+    pub pair_key: &'a str,
+    pub quote: &'a Quote,
+    pub loan_amount: u64,
+    pub sol_price: f64,
+    // Reference price for token_b per token_a from the pair's oracles, scaled by 1e9
+    pub oracle_price: Option<u64>,
+    pub params: &'a EvaluatorParams,
+}
+
+#[derive(Debug, Clone)]
+pub struct Decision {
+    pub evaluation: Evaluation,
+    // Trade size when the strategy wants to execute
+    pub size: Option<u64>,
+    pub reason: String,
+}
+
+impl Decision {
+    pub fn skip(evaluation: Evaluation, reason: impl Into<String>) -> Self {
+        Self {
+            evaluation,
+            size: None,
+            reason: reason.into(),
+        }
+    }
+
+    pub fn execute(evaluation: Evaluation, size: u64, reason: impl Into<String>) -> Self {
+        Self {
+            evaluation,
+            size: Some(size),
+            reason: reason.into(),
+        }
+    }
+}
+
+// Decides whether a quote is worth executing and at what size
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &'static str;
+    fn decide(&self, ctx: &MarketContext) -> Decision;
+}
+
+// The original rule: trade the full size whenever the spread covers the required profit
+pub struct PureSpreadStrategy;
+
+impl Strategy for PureSpreadStrategy {
+    fn name(&self) -> &'static str {
+        "spread"
+    }
+
+    fn decide(&self, ctx: &MarketContext) -> Decision {
+        let evaluation = evaluator::evaluate(ctx.quote, ctx.loan_amount, ctx.sol_price, ctx.params);
+        if evaluation.is_executable() {
+            Decision::execute(evaluation, evaluation.trade_amount, "spread covers required profit")
+        } else {
+            Decision::skip(evaluation, "spread below required profit")
+        }
+    }
+}
+
+// Only trades when one venue has drifted away from the oracle, i.e. the spread is a venue
+// mispricing rather than both venues disagreeing with a stale reference
+pub struct OracleDeviationStrategy {
+    pub min_deviation_bps: u64,
+}
+
+impl Strategy for OracleDeviationStrategy {
+    fn name(&self) -> &'static str {
+        "oracle"
+    }
+
+    fn decide(&self, ctx: &MarketContext) -> Decision {
+        let evaluation = evaluator::evaluate(ctx.quote, ctx.loan_amount, ctx.sol_price, ctx.params);
+        let oracle_price = match ctx.oracle_price {
+            Some(price) if price > 0 => price,
+            _ => return Decision::skip(evaluation, "no oracle price for pair"),
+        };
+
+        let deviation_bps = |price: u64| price.abs_diff(oracle_price) as u128 * 10_000 / oracle_price as u128;
+        let max_deviation = ctx.quote.prices().map(|(_, _, price)| deviation_bps(price)).max().unwrap_or(0);
+
+        if max_deviation < self.min_deviation_bps as u128 {
+            Decision::skip(evaluation, format!("venue deviation {} bps below {} bps", max_deviation, self.min_deviation_bps))
+        } else if !evaluation.is_executable() {
+            Decision::skip(evaluation, "spread below required profit")
+        } else {
+            Decision::execute(evaluation, evaluation.trade_amount, format!("venue deviates {} bps from oracle", max_deviation))
+        }
+    }
+}
+
+// Mean reversion trigger: fires when the current spread is K standard deviations above its
+// rolling mean, so pairs whose spread is a permanent fee/oracle artifact never trigger
+pub struct ZScoreStrategy {
+    pub k: f64,
+    pub window: usize,
+    stats: Mutex<HashMap<String, RollingStats>>,
+}
+
+impl ZScoreStrategy {
+    pub fn new(k: f64, window: usize) -> Self {
+        Self {
+            k,
+            window,
+            stats: Mutex::new(HashMap::new()),
+        }
+    }
+}
+
+impl Strategy for ZScoreStrategy {
+    fn name(&self) -> &'static str {
+        "zscore"
+    }
+
+    fn decide(&self, ctx: &MarketContext) -> Decision {
+        let evaluation = evaluator::evaluate(ctx.quote, ctx.loan_amount, ctx.sol_price, ctx.params);
+        let spread = spread_stats::spread_bps(ctx.quote);
+
+        let mut stats = self.stats.lock().unwrap();
+        let pair_stats = stats
+            .entry(ctx.pair_key.to_string())
+            .or_insert_with(|| RollingStats::new(self.window));
+
+        // Score against the history before this sample so a spike cannot dampen itself
+        let warm = pair_stats.is_warm();
+        let z = pair_stats.z_score(spread);
+        let mean = pair_stats.mean();
+        pair_stats.push(spread);
+
+        if !warm {
+            Decision::skip(evaluation, format!("warming up spread stats ({}/{})", pair_stats.len(), self.window))
+        } else if z < self.k {
+            Decision::skip(evaluation, format!("spread {:.1} bps is {:.2} sd above mean {:.1} bps", spread, z, mean))
+        } else if !evaluation.is_executable() {
+            Decision::skip(evaluation, "spread below required profit")
+        } else {
+            Decision::execute(evaluation, evaluation.trade_amount, format!("spread z-score {:.2} >= {:.2}", z, self.k))
+        }
+    }
+}
+
+// Builds a strategy from its config name, e.g. `spread`, `oracle:50` or `zscore:2.5:300`
+pub fn from_name(name: &str) -> Result<Box<dyn Strategy>, String> {
+    let mut parts = name.split(':');
+    let kind = parts.next().unwrap_or_default();
+    let arg = parts.next();
+
+    match kind {
+        "spread" => Ok(Box::new(PureSpreadStrategy)),
+        "oracle" => Ok(Box::new(OracleDeviationStrategy {
+            min_deviation_bps: arg
+                .map(|v| v.parse().map_err(|_| format!("Invalid oracle deviation: {}", v)))
+                .transpose()?
+                .unwrap_or(50),
+        })),
+        "zscore" => {
+            let k = arg.map(|v| v.parse().map_err(|_| format!("Invalid z-score threshold: {}", v))).transpose()?;
+            let window = parts
+                .next()
+                .map(|v| v.parse().map_err(|_| format!("Invalid z-score window: {}", v)))
+                .transpose()?;
+            Ok(Box::new(ZScoreStrategy::new(k.unwrap_or(2.0), window.unwrap_or(300))))
+        }
+        _ => Err(format!("Unknown strategy: {}", name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LOAN_AMOUNT: u64 = 1_000_000_000;
+
+    fn quote(jupiter_price: u64) -> Quote {
+        Quote {
+            jupiter_price,
+            raydium_price: 1_000_000_000,
+            venues: Vec::new(),
+        }
+    }
+
+    fn decide(strategy: &dyn Strategy, quote: &Quote, oracle_price: Option<u64>) -> Option<u64> {
+        let params = EvaluatorParams::default();
+        let ctx = MarketContext {
+            pair_key: "A/B",
+            quote,
+            loan_amount: LOAN_AMOUNT,
+            sol_price: 150.0,
+            oracle_price,
+            params: &params,
+        };
+        strategy.decide(&ctx).size
+    }
+
+    #[test]
+    fn oracle_strategy_only_trades_a_venue_off_the_oracle() {
+        let strategy = from_name("oracle:50").unwrap();
+        let wide = quote(1_010_000_000);
+        assert_eq!(decide(strategy.as_ref(), &wide, Some(1_000_000_000)), Some(LOAN_AMOUNT));
+        // Both venues within 50 bps of a reference between them
+        assert_eq!(decide(strategy.as_ref(), &wide, Some(1_005_000_000)), None);
+        assert_eq!(decide(strategy.as_ref(), &wide, None), None);
+        // Off the oracle, but too narrow to pay
+        assert_eq!(decide(strategy.as_ref(), &quote(1_000_100_000), Some(990_000_000)), None);
+        assert_eq!(decide(&PureSpreadStrategy, &wide, None), Some(LOAN_AMOUNT));
+    }
+
+    #[test]
+    fn zscore_strategy_waits_for_a_spike_over_its_window() {
+        let strategy = from_name("zscore:2:4").unwrap();
+        // A wide but steady spread never fires, and neither does anything before the window fills
+        for jupiter_price in [1_010_000_000, 1_011_000_000, 1_010_000_000, 1_011_000_000, 1_010_000_000] {
+            assert_eq!(decide(strategy.as_ref(), &quote(jupiter_price), None), None);
+        }
+        assert_eq!(decide(strategy.as_ref(), &quote(1_030_000_000), None), Some(LOAN_AMOUNT));
+        assert!(from_name("zscore:x").is_err());
+        assert!(from_name("momentum").is_err());
+    }
+}
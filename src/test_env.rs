@@ -0,0 +1,247 @@
+use crate::client;
+use crate::graph;
+use crate::network;
+use crate::pair_store::PairStore;
+use crate::route;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::commitment_config::CommitmentConfig;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signer};
+use solana_sdk::transaction::Transaction;
+use std::collections::BTreeSet;
+use std::env;
+use std::process::{Child, Command, Stdio};
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+
+// Local solana-test-validator with the configured pairs' mainnet accounts cloned into it and the
+// program deployed from its build output, so the bot can run end to end without real funds
+
+const VALIDATOR_STARTUP_TIMEOUT: Duration = Duration::from_secs(120);
+
+#[derive(Debug, Clone)]
+pub struct TestEnvConfig {
+    // Cluster the accounts are cloned from
+    pub clone_url: String,
+    // Compiled program, deployed at `program_id`
+    pub program_path: String,
+    pub program_id: Pubkey,
+    pub ledger_dir: String,
+    pub rpc_port: u16,
+    pub airdrop_sol: u64,
+    // Accounts cloned as they are on `clone_url`; upgradeable programs bring their program data
+    pub accounts: BTreeSet<Pubkey>,
+    // Whitelisted on the local program along with the venue programs
+    pub pools: BTreeSet<Pubkey>,
+}
+
+impl TestEnvConfig {
+    // Clones the tokens, pools, quote vaults and Pyth feeds the monitor is configured with, including
+    // the pair store's, every pool in ROUTES_FILE and POOLS_FILE, the venue and loan programs, and
+    // anything listed in TEST_ENV_CLONE
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let mut accounts = BTreeSet::new();
+        let mut pools = BTreeSet::new();
+        for name in ["BONK_TOKEN_ADDRESS", "GOAT_TOKEN_ADDRESS", "TOKEN_A_PYTH_ACCOUNT", "TOKEN_B_PYTH_ACCOUNT"] {
+            accounts.extend(address_list(name)?);
+        }
+        accounts.extend(address_list("QUOTE_VAULTS")?);
+        accounts.extend(address_list("TEST_ENV_CLONE")?);
+        pools.extend(address_list("POOL_ACCOUNTS")?);
+        for pair in PairStore::from_env().load()? {
+            for address in [&pair.token_a, &pair.token_b].into_iter().chain(&pair.quote_vaults) {
+                accounts.insert(Pubkey::from_str(address)?);
+            }
+            if let Some((oracle_a, oracle_b)) = &pair.oracle_accounts {
+                accounts.insert(Pubkey::from_str(oracle_a)?);
+                accounts.insert(Pubkey::from_str(oracle_b)?);
+            }
+            for pool in &pair.pool_accounts {
+                pools.insert(Pubkey::from_str(pool)?);
+            }
+        }
+
+        if let Some(routes_file) = env::var("ROUTES_FILE").ok().filter(|v| !v.is_empty()) {
+            for route in route::load_routes(std::path::Path::new(&routes_file))? {
+                for leg in &route.legs {
+                    accounts.insert(Pubkey::from_str(&leg.input_mint)?);
+                    if let Some(pool) = &leg.pool {
+                        pools.insert(Pubkey::from_str(pool)?);
+                    }
+                }
+            }
+        }
+        if let Some(pools_file) = env::var("POOLS_FILE").ok().filter(|v| !v.is_empty()) {
+            for pool in graph::load_pools(std::path::Path::new(&pools_file))? {
+                accounts.insert(Pubkey::from_str(&pool.mint_a)?);
+                accounts.insert(Pubkey::from_str(&pool.mint_b)?);
+                if let Some(pool) = &pool.pool {
+                    pools.insert(Pubkey::from_str(pool)?);
+                }
+            }
+        }
+        accounts.extend(pools.iter().copied());
+        for program in venue_programs()? {
+            accounts.insert(program);
+        }
+
+        let program_id = network::addresses().program;
+        Ok(Self {
+            clone_url: env::var("TEST_ENV_CLONE_URL")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| "https://api.mainnet-beta.solana.com".to_string()),
+            program_path: env::var("TEST_ENV_PROGRAM")
+                .unwrap_or_else(|_| "target/deploy/arbitrage_contract.so".to_string()),
+            program_id: Pubkey::from_str(program_id)?,
+            ledger_dir: env::var("TEST_ENV_LEDGER").unwrap_or_else(|_| "test-ledger".to_string()),
+            rpc_port: env::var("TEST_ENV_RPC_PORT").ok().and_then(|v| v.parse().ok()).unwrap_or(8899),
+            airdrop_sol: env::var("TEST_ENV_AIRDROP_SOL").ok().and_then(|v| v.parse().ok()).unwrap_or(100),
+            accounts,
+            pools,
+        })
+    }
+
+    pub fn rpc_url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.rpc_port)
+    }
+
+    // The validator serves websockets on the port after the RPC port
+    pub fn ws_url(&self) -> String {
+        format!("ws://127.0.0.1:{}", self.rpc_port + 1)
+    }
+}
+
+// Comma separated addresses in an environment variable; unset or empty is none
+pub fn address_list(name: &str) -> Result<Vec<Pubkey>, Box<dyn std::error::Error>> {
+    env::var(name)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|address| !address.is_empty())
+        .map(|address| Pubkey::from_str(address).map_err(|e| format!("Invalid {} address {}: {}", name, address, e).into()))
+        .collect()
+}
+
+// Programs the routes swap through or borrow from
+fn venue_programs() -> Result<Vec<Pubkey>, Box<dyn std::error::Error>> {
+    [
+        network::MAINNET.jupiter,
+        network::MAINNET.raydium_amm,
+        crate::raydium_clmm::PROGRAM_ID,
+        crate::whirlpool::PROGRAM_ID,
+        crate::marginfi::PROGRAM_ID,
+        crate::solend::PROGRAM_ID,
+    ]
+    .iter()
+    .map(|id| Ok(Pubkey::from_str(id)?))
+    .collect()
+}
+
+// Starts solana-test-validator on a fresh ledger and waits until its RPC answers
+pub fn start_validator(config: &TestEnvConfig) -> Result<Child, Box<dyn std::error::Error>> {
+    let mut command = Command::new("solana-test-validator");
+    command
+        .arg("--reset")
+        .arg("--quiet")
+        .args(["--ledger", &config.ledger_dir])
+        .args(["--rpc-port", &config.rpc_port.to_string()])
+        .args(["--url", &config.clone_url])
+        .args(["--bpf-program", &config.program_id.to_string(), &config.program_path]);
+    for account in &config.accounts {
+        command.args(["--clone", &account.to_string()]);
+    }
+    let mut validator = command
+        .stdout(Stdio::null())
+        .spawn()
+        .map_err(|e| format!("Failed to start solana-test-validator: {}", e))?;
+
+    // Cloning fetches every account from the source cluster before the RPC comes up
+    let rpc = RpcClient::new(config.rpc_url());
+    let started = Instant::now();
+    while rpc.get_health().is_err() {
+        if let Some(status) = validator.try_wait()? {
+            return Err(format!("solana-test-validator exited with {}", status).into());
+        }
+        if started.elapsed() > VALIDATOR_STARTUP_TIMEOUT {
+            validator.kill().ok();
+            return Err("Timed out waiting for solana-test-validator".into());
+        }
+        std::thread::sleep(Duration::from_secs(1));
+    }
+    Ok(validator)
+}
+
+// Funds the wallet and initializes the program's config and whitelist with the wallet as admin,
+// operator and fee collector
+pub fn prepare(config: &TestEnvConfig, wallet: &Keypair) -> Result<(), Box<dyn std::error::Error>> {
+    let rpc = RpcClient::new_with_commitment(config.rpc_url(), CommitmentConfig::confirmed());
+    let signature = rpc.request_airdrop(&wallet.pubkey(), config.airdrop_sol * LAMPORTS_PER_SOL)?;
+    let started = Instant::now();
+    while !rpc.confirm_transaction(&signature)? {
+        if started.elapsed() > VALIDATOR_STARTUP_TIMEOUT {
+            return Err("Airdrop was not confirmed".into());
+        }
+        std::thread::sleep(Duration::from_millis(500));
+    }
+
+    let admin = wallet.pubkey();
+    let params = client::ConfigParams {
+        operator: admin.to_bytes(),
+        paused: false,
+        max_trade_size: u64::MAX,
+        flash_loan_fee_bps: 0,
+        max_slippage_bps: 100,
+        fee_collector: admin.to_bytes(),
+        profit_fee_bps: 0,
+        // A throwaway validator: Jupiter legs may take any pool of the listed programs
+        allow_unlisted_jupiter_pools: true,
+        // Cloned feeds stop updating, so trades aren't checked against one
+        price_oracle: [0; 32],
+    };
+    let whitelist = client::WhitelistArgs {
+        programs: venue_programs()?.iter().map(|program| program.to_bytes()).collect(),
+        pools: config.pools.iter().map(|pool| pool.to_bytes()).collect(),
+    };
+    let instructions = [
+        client::initialize_config(&config.program_id, &admin, &params)?,
+        client::initialize_whitelist(&config.program_id, &admin, &whitelist)?,
+    ];
+    let transaction =
+        Transaction::new_signed_with_payer(&instructions, Some(&admin), &[wallet], rpc.get_latest_blockhash()?);
+    rpc.send_and_confirm_transaction(&transaction)?;
+    Ok(())
+}
+
+// Starts the validator, prepares the program and runs the monitor against it until it exits; with
+// --setup-only, leaves the validator running for manual testing instead
+pub fn run(args: &[String], wallet_keypair_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let config = TestEnvConfig::from_env()?;
+    let wallet = read_keypair_file(wallet_keypair_path)?;
+    println!(
+        "Starting solana-test-validator with {} accounts cloned from {}",
+        config.accounts.len(),
+        config.clone_url
+    );
+    let mut validator = start_validator(&config)?;
+
+    let result = prepare(&config, &wallet).and_then(|_| {
+        println!("Program {} deployed and initialized at {}", config.program_id, config.rpc_url());
+        if args.iter().any(|arg| arg == "--setup-only") {
+            println!("Validator running; press Ctrl-C to stop it");
+            validator.wait()?;
+            return Ok(());
+        }
+        let status = Command::new(env::current_exe()?)
+            .env("SOLANA_RPC_URL", config.rpc_url())
+            .env("SOLANA_WS_URL", config.ws_url())
+            .status()?;
+        println!("Monitor exited with {}", status);
+        Ok(())
+    });
+
+    validator.kill().ok();
+    validator.wait().ok();
+    result
+}
@@ -0,0 +1,73 @@
+use solana_sdk::pubkey::Pubkey;
+use std::collections::HashSet;
+use std::env;
+use std::str::FromStr;
+
+// Global mint lists every pair and route must pass, however it was configured or discovered. A mint
+// in TOKEN_BLOCKLIST, such as a known scam token or a compliance-restricted asset, is never traded;
+// when TOKEN_ALLOWLIST is set, nothing outside it is either
+
+#[derive(Debug, Clone, Default)]
+pub struct TokenLists {
+    // None allows any mint the blocklist doesn't name
+    pub allowlist: Option<HashSet<String>>,
+    pub blocklist: HashSet<String>,
+}
+
+impl TokenLists {
+    // TOKEN_ALLOWLIST and TOKEN_BLOCKLIST, comma separated mints; unset or empty lists nothing
+    pub fn from_env() -> Result<Self, String> {
+        let list = |name: &str| -> Result<Option<HashSet<String>>, String> {
+            let Some(value) = env::var(name).ok().filter(|v| !v.trim().is_empty()) else {
+                return Ok(None);
+            };
+            value
+                .split(',')
+                .map(str::trim)
+                .filter(|mint| !mint.is_empty())
+                .map(|mint| {
+                    Pubkey::from_str(mint).map_err(|_| format!("Invalid {} mint {}", name, mint))?;
+                    Ok(mint.to_string())
+                })
+                .collect::<Result<HashSet<_>, _>>()
+                .map(Some)
+        };
+        Ok(Self {
+            allowlist: list("TOKEN_ALLOWLIST")?,
+            blocklist: list("TOKEN_BLOCKLIST")?.unwrap_or_default(),
+        })
+    }
+
+    // Why `mint` may not be traded, if it may not
+    pub fn check(&self, mint: &str) -> Result<(), String> {
+        if self.blocklist.contains(mint) {
+            return Err(format!("{} is on TOKEN_BLOCKLIST", mint));
+        }
+        if self.allowlist.as_ref().is_some_and(|allowed| !allowed.contains(mint)) {
+            return Err(format!("{} is not on TOKEN_ALLOWLIST", mint));
+        }
+        Ok(())
+    }
+
+    // The first of `mints` that may not be traded
+    pub fn check_all<'a>(&self, mints: impl IntoIterator<Item = &'a str>) -> Result<(), String> {
+        mints.into_iter().try_for_each(|mint| self.check(mint))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn blocklist_wins_over_the_allowlist() {
+        let lists = TokenLists {
+            allowlist: Some(HashSet::from(["A".to_string(), "B".to_string()])),
+            blocklist: HashSet::from(["B".to_string()]),
+        };
+        assert!(lists.check("A").is_ok());
+        assert_eq!(lists.check("B").unwrap_err(), "B is on TOKEN_BLOCKLIST");
+        assert_eq!(lists.check_all(["A", "C", "B"]).unwrap_err(), "C is not on TOKEN_ALLOWLIST");
+        assert!(TokenLists::default().check("C").is_ok());
+    }
+}
@@ -0,0 +1,180 @@
+use crate::discovery::DiscoveredPair;
+use crate::http_client;
+use crate::network;
+use crate::retry::{self, RetryPolicy};
+use serde::{Deserialize, Serialize};
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::pubkey::Pubkey;
+use std::env;
+use std::str::FromStr;
+
+// Scam heuristics for discovered pairs, beyond the verified list and authority checks. Four signals
+// are worth 25 points each of a 100 point score:
+// - holder concentration: share of supply in the ten largest token accounts, full marks at 30% or
+//   less and none at 90% or more, for the more concentrated of the two tokens
+// - LP lock: share of the deepest Raydium AMM pool's LP tokens burned
+// - creation age: full marks at 30 days, from Birdeye, for the younger of the two tokens
+// - single signer removal: share of that pool's liquidity the largest LP holder could withdraw alone
+// A signal that can't be read earns nothing, so a pair with no Raydium AMM pool, or scored without a
+// Birdeye key, can't reach full marks. SOL and USDC always pass the token signals. Discovered pairs
+// need DISCOVERY_MIN_SAFETY_SCORE
+
+const SIGNAL_POINTS: f64 = 25.0;
+const TOP_HOLDERS: usize = 10;
+const MATURE_AGE_SECS: u64 = 30 * 24 * 60 * 60;
+
+#[derive(Debug, Clone)]
+pub struct SafetyConfig {
+    // 0 turns scoring off
+    pub min_score: f64,
+    pub birdeye_url: String,
+    pub birdeye_api_key: Option<String>,
+}
+
+impl SafetyConfig {
+    pub fn from_env() -> Self {
+        Self {
+            min_score: env::var("DISCOVERY_MIN_SAFETY_SCORE").ok().and_then(|v| v.parse().ok()).unwrap_or(60.0),
+            birdeye_url: env::var("BIRDEYE_API_URL")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .unwrap_or_else(|| "https://public-api.birdeye.so".to_string()),
+            birdeye_api_key: env::var("BIRDEYE_API_KEY").ok().filter(|v| !v.is_empty()),
+        }
+    }
+}
+
+// What the score is made of; None where the signal couldn't be read
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SafetySignals {
+    pub top_holders_share: Option<f64>,
+    pub age_secs: Option<u64>,
+    pub lp_burned_share: Option<f64>,
+    pub removable_share: Option<f64>,
+}
+
+impl SafetySignals {
+    // Out of 100
+    pub fn score(&self) -> f64 {
+        let concentration = self.top_holders_share.map_or(0.0, |share| 1.0 - ((share - 0.3) / 0.6).clamp(0.0, 1.0));
+        let age = self.age_secs.map_or(0.0, |age| (age as f64 / MATURE_AGE_SECS as f64).min(1.0));
+        let lock = self.lp_burned_share.map_or(0.0, |share| share.clamp(0.0, 1.0));
+        let removable = self.removable_share.map_or(0.0, |share| 1.0 - share.clamp(0.0, 1.0));
+        SIGNAL_POINTS * (concentration + age + lock + removable)
+    }
+}
+
+#[derive(Deserialize)]
+struct BirdeyeResponse {
+    data: Option<BirdeyeCreation>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BirdeyeCreation {
+    block_unix_time: u64,
+}
+
+pub struct SafetyScorer {
+    pub config: SafetyConfig,
+    rpc: RpcClient,
+}
+
+impl SafetyScorer {
+    pub fn new(config: SafetyConfig, rpc_url: &str) -> Self {
+        Self {
+            config,
+            rpc: RpcClient::new(rpc_url.to_string()),
+        }
+    }
+
+    // Reads the pair's signals; a failed read leaves its signal out and is logged
+    pub async fn signals(&self, http: &reqwest::Client, pair: &DiscoveredPair) -> SafetySignals {
+        let mut signals = SafetySignals {
+            lp_burned_share: pair.lp_burned_share,
+            ..SafetySignals::default()
+        };
+        let mut shares = Vec::new();
+        let mut ages = Vec::new();
+        for mint in [&pair.token_a, &pair.token_b] {
+            if is_established(mint) {
+                continue;
+            }
+            match self.top_holders_share(mint) {
+                Ok(share) => shares.push(Some(share)),
+                Err(e) => {
+                    println!("Failed to read the holders of {}: {}", mint, e);
+                    shares.push(None);
+                }
+            }
+            match self.age_secs(http, mint).await {
+                Ok(age) => ages.push(age),
+                Err(e) => {
+                    println!("Failed to read the age of {}: {}", mint, e);
+                    ages.push(None);
+                }
+            }
+        }
+        signals.top_holders_share = shares.into_iter().try_fold(0.0f64, |worst, share| Some(worst.max(share?)));
+        signals.age_secs = ages.into_iter().try_fold(u64::MAX, |youngest, age| Some(youngest.min(age?)));
+        // Burned LP tokens still count in the pool, so the holder's claim shrinks with the burn
+        if let Some(lp_mint) = &pair.lp_mint {
+            match self.largest_holder_share(lp_mint) {
+                Ok(share) => signals.removable_share = Some(share * (1.0 - pair.lp_burned_share.unwrap_or(0.0))),
+                Err(e) => println!("Failed to read the LP holders of {}: {}", lp_mint, e),
+            }
+        }
+        signals
+    }
+
+    // Share of the supply in the TOP_HOLDERS largest accounts
+    fn top_holders_share(&self, mint: &str) -> Result<f64, Box<dyn std::error::Error>> {
+        let mint = Pubkey::from_str(mint)?;
+        let supply: f64 = self.rpc.get_token_supply(&mint)?.amount.parse()?;
+        let held = self
+            .rpc
+            .get_token_largest_accounts(&mint)?
+            .iter()
+            .take(TOP_HOLDERS)
+            .map(|account| account.amount.amount.parse::<f64>())
+            .sum::<Result<f64, _>>()?;
+        Ok(if supply > 0.0 { held / supply } else { 1.0 })
+    }
+
+    // Share of the supply in the largest account; burned tokens are out of the supply
+    fn largest_holder_share(&self, mint: &str) -> Result<f64, Box<dyn std::error::Error>> {
+        let mint = Pubkey::from_str(mint)?;
+        let supply: f64 = self.rpc.get_token_supply(&mint)?.amount.parse()?;
+        let largest = match self.rpc.get_token_largest_accounts(&mint)?.first() {
+            Some(account) => account.amount.amount.parse()?,
+            None => 0.0,
+        };
+        Ok(if supply > 0.0 { largest / supply } else { 0.0 })
+    }
+
+    // Seconds since the mint was created; None without a Birdeye key
+    async fn age_secs(&self, http: &reqwest::Client, mint: &str) -> Result<Option<u64>, Box<dyn std::error::Error>> {
+        let Some(api_key) = &self.config.birdeye_api_key else {
+            return Ok(None);
+        };
+        let url = format!("{}/defi/token_creation_info?address={}", self.config.birdeye_url, mint);
+        let response: BirdeyeResponse = retry::http(&RetryPolicy::BULK, &url, || async {
+            http.get(&url)
+                .header("X-API-KEY", api_key)
+                .header("x-chain", "solana")
+                .timeout(http_client::timeout_for(&url))
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+        })
+        .await?;
+        Ok(response.data.map(|created| crate::journal::unix_timestamp().saturating_sub(created.block_unix_time)))
+    }
+}
+
+fn is_established(mint: &str) -> bool {
+    let addresses = network::addresses();
+    mint == addresses.wsol || mint == addresses.usdc
+}
@@ -0,0 +1,88 @@
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Venue {
+    Jupiter,
+    Raydium,
+    RaydiumClmm,
+    Whirlpool,
+}
+
+impl Venue {
+    // Venue id used in the program's route instruction data
+    pub fn id(&self) -> u8 {
+        match self {
+            Venue::Jupiter => 0,
+            Venue::Raydium => 1,
+            Venue::RaydiumClmm => 2,
+            Venue::Whirlpool => 3,
+        }
+    }
+
+    // The venue's name in routes files
+    pub fn name(&self) -> &'static str {
+        match self {
+            Venue::Jupiter => "jupiter",
+            Venue::Raydium => "raydium",
+            Venue::RaydiumClmm => "raydium_clmm",
+            Venue::Whirlpool => "whirlpool",
+        }
+    }
+
+    // Jupiter's label for the venue, to restrict quotes to it
+    pub fn jupiter_dexes(&self) -> Option<&'static str> {
+        match self {
+            Venue::Jupiter => None,
+            Venue::Raydium => Some("Raydium"),
+            Venue::RaydiumClmm => Some("Raydium CLMM"),
+            Venue::Whirlpool => Some("Whirlpool"),
+        }
+    }
+
+    // Venues whose legs swap through a specific pool
+    pub fn needs_pool(&self) -> bool {
+        *self != Venue::Jupiter
+    }
+
+    // The discovery pool list carrying the venue's pools; Jupiter routes over the others
+    pub fn listing(&self) -> Option<&'static str> {
+        match self {
+            Venue::Jupiter => None,
+            Venue::Raydium | Venue::RaydiumClmm => Some("raydium"),
+            Venue::Whirlpool => Some("orca"),
+        }
+    }
+}
+
+// The names routes files use: jupiter, raydium, raydium_clmm or whirlpool
+impl FromStr for Venue {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "jupiter" => Ok(Venue::Jupiter),
+            "raydium" => Ok(Venue::Raydium),
+            "raydium_clmm" => Ok(Venue::RaydiumClmm),
+            "whirlpool" => Ok(Venue::Whirlpool),
+            other => Err(format!("Unknown venue {}, expected jupiter, raydium, raydium_clmm or whirlpool", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_round_trip_and_map_to_pool_lists() {
+        for venue in [Venue::Jupiter, Venue::Raydium, Venue::RaydiumClmm, Venue::Whirlpool] {
+            assert_eq!(venue.name().parse::<Venue>(), Ok(venue));
+        }
+        assert_eq!(Venue::Whirlpool.listing(), Some("orca"));
+        assert_eq!(Venue::RaydiumClmm.listing(), Venue::Raydium.listing());
+        assert_eq!(Venue::Jupiter.listing(), None);
+        assert!("orca".parse::<Venue>().is_err());
+    }
+}
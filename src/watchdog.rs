@@ -0,0 +1,91 @@
+use crate::notifier::Notifier;
+use std::env;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// Catches a monitor loop that stopped making progress, e.g. on an RPC call that never returns.
+// The loop beats once per completed scan cycle; a separate thread alerts when no beat arrived for
+// WATCHDOG_TIMEOUT_SECS, and with WATCHDOG_RESTART exits the process so its supervisor (systemd,
+// Docker) starts a fresh one. The hung call runs on the monitor's own thread, so nothing short of
+// that can cancel it
+
+// How often the watchdog thread looks at the heartbeat
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+pub struct WatchdogConfig {
+    // Longest time without a completed cycle; zero disables the watchdog
+    pub timeout: Duration,
+    pub restart: bool,
+}
+
+impl WatchdogConfig {
+    pub fn from_env() -> Self {
+        Self {
+            timeout: Duration::from_secs(
+                env::var("WATCHDOG_TIMEOUT_SECS").ok().and_then(|v| v.parse().ok()).unwrap_or(300),
+            ),
+            restart: env::var("WATCHDOG_RESTART").map(|v| v == "true").unwrap_or(false),
+        }
+    }
+}
+
+// Time of the last completed scan cycle, shared with the watchdog thread
+pub struct Heartbeat {
+    last: Mutex<Instant>,
+}
+
+impl Heartbeat {
+    pub fn new() -> Self {
+        Self {
+            last: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub fn beat(&self) {
+        *self.last.lock().unwrap() = Instant::now();
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.last.lock().unwrap().elapsed()
+    }
+}
+
+// Starts the watchdog thread over `heartbeat` unless the config disables it
+pub fn spawn(heartbeat: Arc<Heartbeat>, config: WatchdogConfig) {
+    if config.timeout.is_zero() {
+        return;
+    }
+    std::thread::spawn(move || {
+        // The notifier is async; the monitor's runtime may be the one that's stuck
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("Failed to start watchdog runtime");
+        let notifier = Notifier::from_env();
+        // Alerted for the current hang, so it's reported once and its end too
+        let mut alerted = false;
+        loop {
+            std::thread::sleep(CHECK_INTERVAL);
+            let elapsed = heartbeat.elapsed();
+            if elapsed < config.timeout {
+                if alerted {
+                    alerted = false;
+                    runtime.block_on(notifier.notify("Monitor loop recovered", "Scan cycles are completing again"));
+                }
+                continue;
+            }
+            if alerted {
+                continue;
+            }
+            alerted = true;
+            let action = if config.restart { "Exiting for a restart." } else { "Still waiting." };
+            runtime.block_on(notifier.notify(
+                "Monitor loop stalled",
+                &format!("No scan cycle has completed for {}s. {}", elapsed.as_secs(), action),
+            ));
+            if config.restart {
+                std::process::exit(1);
+            }
+        }
+    });
+}
@@ -0,0 +1,113 @@
+use crate::rpc::RpcApi;
+use anchor_spl::associated_token::get_associated_token_address;
+use solana_sdk::instruction::AccountMeta;
+use solana_sdk::pubkey::Pubkey;
+use std::str::FromStr;
+
+pub const PROGRAM_ID: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+
+// Offsets in the Whirlpool account
+const TICK_SPACING_OFFSET: usize = 41;
+const TICK_CURRENT_OFFSET: usize = 81;
+const MINT_A_OFFSET: usize = 101;
+const VAULT_A_OFFSET: usize = 133;
+const MINT_B_OFFSET: usize = 181;
+const VAULT_B_OFFSET: usize = 213;
+// Ticks per tick array, in units of the pool's tick spacing
+const TICK_ARRAY_SIZE: i32 = 88;
+
+// Leg accounts for a `swap` on `whirlpool` selling `input_mint`, in the order the program expects:
+// program, token program, whirlpool, token account A, vault A, token account B, vault B, three tick
+// arrays, oracle, output mint. The token accounts are `owner`'s associated token accounts
+pub fn leg_accounts(
+    rpc: &dyn RpcApi,
+    whirlpool: &Pubkey,
+    input_mint: &Pubkey,
+    owner: &Pubkey,
+) -> Result<Vec<AccountMeta>, Box<dyn std::error::Error>> {
+    let program_id = Pubkey::from_str(PROGRAM_ID)?;
+    let data = rpc.get_account_data(whirlpool)?;
+    let bytes_at = |offset: usize, len: usize| {
+        data.get(offset..offset + len)
+            .ok_or_else(|| format!("Whirlpool {} is too short", whirlpool))
+    };
+    let pubkey_at = |offset: usize| -> Result<Pubkey, Box<dyn std::error::Error>> {
+        Ok(Pubkey::new_from_array(bytes_at(offset, 32)?.try_into()?))
+    };
+
+    let mint_a = pubkey_at(MINT_A_OFFSET)?;
+    let mint_b = pubkey_at(MINT_B_OFFSET)?;
+    let a_to_b = *input_mint == mint_a;
+    if !a_to_b && *input_mint != mint_b {
+        return Err(format!("Mint {} is not traded by whirlpool {}", input_mint, whirlpool).into());
+    }
+    let tick_spacing = u16::from_le_bytes(bytes_at(TICK_SPACING_OFFSET, 2)?.try_into()?);
+    let tick_current = i32::from_le_bytes(bytes_at(TICK_CURRENT_OFFSET, 4)?.try_into()?);
+    let (oracle, _) = Pubkey::find_program_address(&[b"oracle", whirlpool.as_ref()], &program_id);
+
+    let mut accounts = vec![
+        AccountMeta::new_readonly(program_id, false),
+        AccountMeta::new_readonly(anchor_spl::token::ID, false),
+        AccountMeta::new(*whirlpool, false),
+        AccountMeta::new(get_associated_token_address(owner, &mint_a), false),
+        AccountMeta::new(pubkey_at(VAULT_A_OFFSET)?, false),
+        AccountMeta::new(get_associated_token_address(owner, &mint_b), false),
+        AccountMeta::new(pubkey_at(VAULT_B_OFFSET)?, false),
+    ];
+    accounts.extend(
+        tick_arrays(&program_id, whirlpool, tick_current, tick_spacing, a_to_b)
+            .into_iter()
+            .map(|tick_array| AccountMeta::new(tick_array, false)),
+    );
+    accounts.push(AccountMeta::new(oracle, false));
+    accounts.push(AccountMeta::new_readonly(if a_to_b { mint_b } else { mint_a }, false));
+    Ok(accounts)
+}
+
+// The three tick arrays a swap starting at `tick_current` walks through. Assumes the neighbouring
+// arrays are initialized, which holds for the liquid pools worth routing through
+fn tick_arrays(program_id: &Pubkey, whirlpool: &Pubkey, tick_current: i32, tick_spacing: u16, a_to_b: bool) -> Vec<Pubkey> {
+    let ticks_per_array = tick_spacing as i32 * TICK_ARRAY_SIZE;
+    let start = tick_current.div_euclid(ticks_per_array) * ticks_per_array;
+    // Selling token A moves the price, and the current tick, down
+    let step = if a_to_b { -ticks_per_array } else { ticks_per_array };
+    (0..3)
+        .map(|i| {
+            // Whirlpool seeds tick arrays with the start index as a decimal string
+            let start_index = (start + i * step).to_string();
+            Pubkey::find_program_address(&[b"tick_array", whirlpool.as_ref(), start_index.as_bytes()], program_id).0
+        })
+        .collect()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::rpc::mock::MockRpc;
+    use solana_sdk::account::Account;
+
+    #[test]
+    fn swap_accounts_follow_the_direction_sold() {
+        let rpc = MockRpc::new(1);
+        let (whirlpool, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let (mint_a, mint_b, vault_a, vault_b) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let mut data = vec![0u8; VAULT_B_OFFSET + 32];
+        for (offset, key) in [(MINT_A_OFFSET, mint_a), (MINT_B_OFFSET, mint_b), (VAULT_A_OFFSET, vault_a), (VAULT_B_OFFSET, vault_b)] {
+            data[offset..offset + 32].copy_from_slice(key.as_ref());
+        }
+        data[TICK_SPACING_OFFSET..TICK_SPACING_OFFSET + 2].copy_from_slice(&64u16.to_le_bytes());
+        rpc.set_account(whirlpool, Account { data, ..Account::default() });
+
+        let selling_a = leg_accounts(&rpc, &whirlpool, &mint_a, &owner).unwrap();
+        let selling_b = leg_accounts(&rpc, &whirlpool, &mint_b, &owner).unwrap();
+        assert_eq!(selling_a.len(), 12);
+        // The token accounts keep their A/B positions; the tick arrays and output mint follow the direction
+        assert_eq!(selling_a[..7], selling_b[..7]);
+        assert_eq!(selling_a[3].pubkey, get_associated_token_address(&owner, &mint_a));
+        assert_eq!((selling_a[11].pubkey, selling_b[11].pubkey), (mint_b, mint_a));
+        assert_eq!(selling_a[7], selling_b[7]);
+        assert_ne!(selling_a[8], selling_b[8]);
+        assert!(leg_accounts(&rpc, &whirlpool, &Pubkey::new_unique(), &owner).is_err());
+    }
+}
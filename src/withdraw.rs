@@ -0,0 +1,279 @@
+use crate::commands;
+use crate::costs::LAMPORTS_PER_SIGNATURE;
+use crate::doctor;
+use crate::flash_loan::FlashLoanProvider;
+use crate::rpc::RpcApi;
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, spl_associated_token_account};
+use anchor_spl::token_2022::spl_token_2022;
+use solana_client::rpc_client::RpcClient;
+use solana_sdk::instruction::Instruction;
+use solana_sdk::native_token::LAMPORTS_PER_SOL;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent::Rent;
+use solana_sdk::signature::{read_keypair_file, Keypair, Signature, Signer};
+use solana_sdk::system_instruction;
+use solana_sdk::transaction::Transaction;
+use std::collections::{BTreeSet, HashMap};
+use std::env;
+use std::fmt;
+use std::str::FromStr;
+
+// Sweeps profits from the hot wallet to a cold address. Trades pay their output back to the wallet's
+// token account before they end, so the program's vaults never hold profit between trades and the
+// wallet's accounts are the only place to sweep from
+
+// Byte offsets in SPL token accounts and mints
+const TOKEN_AMOUNT_OFFSET: usize = 64;
+const MINT_DECIMALS_OFFSET: usize = 44;
+// Token account without extensions, as created for the destination
+const TOKEN_ACCOUNT_LEN: usize = 165;
+const DEFAULT_RETAIN_LAMPORTS: u64 = LAMPORTS_PER_SOL / 2;
+
+#[derive(Debug, Clone)]
+pub struct WithdrawConfig {
+    // Cold wallet receiving the profits; its token accounts are created as needed
+    pub destination: Pubkey,
+    // Lamports kept in the wallet for fees and rent
+    pub retain_lamports: u64,
+    // Base units kept in the wallet's token account, by mint
+    pub retain: HashMap<Pubkey, u64>,
+}
+
+impl WithdrawConfig {
+    // WITHDRAW_DESTINATION, WITHDRAW_RETAIN_SOL in lamports, and WITHDRAW_RETAIN as <mint>:<amount>
+    // entries. A start mint without an entry keeps its loan amount when trades run on the wallet's
+    // own balance
+    pub fn from_env() -> Result<Self, Box<dyn std::error::Error>> {
+        let destination = env::var("WITHDRAW_DESTINATION")
+            .ok()
+            .filter(|v| !v.is_empty())
+            .ok_or("Missing WITHDRAW_DESTINATION")?;
+        let mut retain = HashMap::new();
+        if matches!(FlashLoanProvider::from_env(), FlashLoanProvider::OwnBalance) {
+            retain.extend(doctor::start_mints()?);
+        }
+        for entry in env::var("WITHDRAW_RETAIN").unwrap_or_default().split(',').map(str::trim) {
+            if entry.is_empty() {
+                continue;
+            }
+            let (mint, amount) = entry.split_once(':').ok_or("WITHDRAW_RETAIN entries must be <mint>:<amount>")?;
+            retain.insert(Pubkey::from_str(mint.trim())?, amount.trim().parse()?);
+        }
+
+        Ok(Self {
+            destination: Pubkey::from_str(&destination)?,
+            retain_lamports: env::var("WITHDRAW_RETAIN_SOL")
+                .ok()
+                .filter(|v| !v.is_empty())
+                .map(|v| v.parse())
+                .transpose()?
+                .unwrap_or(DEFAULT_RETAIN_LAMPORTS),
+            retain,
+        })
+    }
+}
+
+// Native SOL or an SPL token, by mint
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Asset {
+    Sol,
+    Token(Pubkey),
+}
+
+impl FromStr for Asset {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "sol" | "SOL" => Ok(Asset::Sol),
+            mint => Pubkey::from_str(mint)
+                .map(Asset::Token)
+                .map_err(|e| format!("Invalid mint {}: {}", mint, e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Sweep {
+    pub asset: Asset,
+    pub balance: u64,
+    pub retained: u64,
+    pub amount: u64,
+    pub decimals: u8,
+    // Owner of the mint, for tokens
+    pub token_program: Option<Pubkey>,
+}
+
+impl fmt::Display for Sweep {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let units = |amount: u64| amount as f64 / 10f64.powi(self.decimals as i32);
+        let name = match self.asset {
+            Asset::Sol => "SOL".to_string(),
+            Asset::Token(mint) => mint.to_string(),
+        };
+        write!(
+            f,
+            "{}: balance {}, retained {}, withdrawing {}",
+            name,
+            units(self.balance),
+            units(self.retained),
+            units(self.amount)
+        )
+    }
+}
+
+// Everything above the retained amount, or `requested` if that much is available
+fn withdrawable(balance: u64, retained: u64, requested: Option<u64>) -> Result<u64, String> {
+    let available = balance.saturating_sub(retained);
+    match requested {
+        Some(requested) if requested > available => Err(format!(
+            "Requested {} but only {} is available above the retained {}",
+            requested, available, retained
+        )),
+        Some(requested) => Ok(requested),
+        None => Ok(available),
+    }
+}
+
+// What would be withdrawn of each asset. `requested` is a partial amount in base units, for a single
+// asset. Creating the destination's missing token accounts costs the wallet rent, which is held back
+// from the SOL sweep along with the transaction fee
+pub fn plan(
+    rpc: &dyn RpcApi,
+    wallet: &Pubkey,
+    config: &WithdrawConfig,
+    assets: &[Asset],
+    requested: Option<u64>,
+) -> Result<Vec<Sweep>, Box<dyn std::error::Error>> {
+    if requested.is_some() && assets.len() != 1 {
+        return Err("A partial amount needs a single asset; pass --mint".into());
+    }
+    let mut sweeps = Vec::new();
+    let mut rent = 0;
+    for asset in assets {
+        let Asset::Token(mint) = asset else {
+            continue;
+        };
+        let mint_account = rpc.get_account(mint)?;
+        let decimals = *mint_account.data.get(MINT_DECIMALS_OFFSET).ok_or("Malformed mint account")?;
+        let token_program = mint_account.owner;
+        let source = get_associated_token_address_with_program_id(wallet, mint, &token_program);
+        let destination = get_associated_token_address_with_program_id(&config.destination, mint, &token_program);
+        let accounts = rpc.get_multiple_accounts(&[source, destination])?;
+        let balance = accounts[0]
+            .as_ref()
+            .and_then(|account| account.data.get(TOKEN_AMOUNT_OFFSET..TOKEN_AMOUNT_OFFSET + 8))
+            .map_or(0, |bytes| u64::from_le_bytes(bytes.try_into().unwrap()));
+        let retained = config.retain.get(mint).copied().unwrap_or(0);
+        let amount = withdrawable(balance, retained, requested)?;
+        if amount > 0 && accounts[1].is_none() {
+            rent += Rent::default().minimum_balance(TOKEN_ACCOUNT_LEN);
+        }
+        sweeps.push(Sweep {
+            asset: *asset,
+            balance,
+            retained,
+            amount,
+            decimals,
+            token_program: Some(token_program),
+        });
+    }
+
+    if assets.contains(&Asset::Sol) {
+        let balance = rpc.get_multiple_accounts(&[*wallet])?[0].as_ref().map_or(0, |account| account.lamports);
+        let retained = config.retain_lamports + rent + LAMPORTS_PER_SIGNATURE;
+        sweeps.push(Sweep {
+            asset: Asset::Sol,
+            balance,
+            retained,
+            amount: withdrawable(balance, retained, requested)?,
+            decimals: 9,
+            token_program: None,
+        });
+    }
+    Ok(sweeps)
+}
+
+// Sends every non-zero sweep in one transaction, creating the destination's token accounts first
+pub fn execute(
+    rpc: &dyn RpcApi,
+    wallet: &Keypair,
+    destination: &Pubkey,
+    sweeps: &[Sweep],
+) -> Result<Signature, Box<dyn std::error::Error>> {
+    let owner = wallet.pubkey();
+    let mut instructions: Vec<Instruction> = Vec::new();
+    for sweep in sweeps.iter().filter(|sweep| sweep.amount > 0) {
+        match (sweep.asset, sweep.token_program) {
+            (Asset::Sol, _) => instructions.push(system_instruction::transfer(&owner, destination, sweep.amount)),
+            (Asset::Token(mint), Some(token_program)) => {
+                instructions.push(
+                    spl_associated_token_account::instruction::create_associated_token_account_idempotent(
+                        &owner,
+                        destination,
+                        &mint,
+                        &token_program,
+                    ),
+                );
+                // Works for SPL Token and Token-2022 alike
+                instructions.push(spl_token_2022::instruction::transfer_checked(
+                    &token_program,
+                    &get_associated_token_address_with_program_id(&owner, &mint, &token_program),
+                    &mint,
+                    &get_associated_token_address_with_program_id(destination, &mint, &token_program),
+                    &owner,
+                    &[],
+                    sweep.amount,
+                    sweep.decimals,
+                )?);
+            }
+            (Asset::Token(mint), None) => return Err(format!("No token program for {}", mint).into()),
+        }
+    }
+    if instructions.is_empty() {
+        return Err("Nothing to withdraw".into());
+    }
+
+    let transaction =
+        Transaction::new_signed_with_payer(&instructions, Some(&owner), &[wallet], rpc.get_latest_blockhash()?);
+    Ok(rpc.send_and_confirm_transaction(&transaction)?)
+}
+
+// `withdraw-profits [--mint <mint|sol>] [--amount <base units>] [--dry-run]`
+pub fn run(args: &[String], wallet_keypair_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let config = WithdrawConfig::from_env()?;
+    let wallet = read_keypair_file(wallet_keypair_path)?;
+    if config.destination == wallet.pubkey() {
+        return Err("WITHDRAW_DESTINATION is the bot wallet itself".into());
+    }
+    let rpc_url = env::var("SOLANA_RPC_URL").unwrap_or_else(|_| "https://api.mainnet-beta.solana.com".to_string());
+    let rpc = RpcClient::new(rpc_url);
+
+    // SOL and every mint trades start in or that has a retained amount, unless one is named
+    let assets = match commands::flag_value(args, "--mint") {
+        Some(asset) => vec![asset.parse()?],
+        None => {
+            let mut mints: BTreeSet<Pubkey> = doctor::start_mints()?.into_keys().collect();
+            mints.extend(config.retain.keys().copied());
+            std::iter::once(Asset::Sol).chain(mints.into_iter().map(Asset::Token)).collect()
+        }
+    };
+    let requested = commands::flag_value(args, "--amount").map(|v| v.parse::<u64>()).transpose()?;
+
+    let sweeps = plan(&rpc, &wallet.pubkey(), &config, &assets, requested)?;
+    println!("Withdrawing from {} to {}:", wallet.pubkey(), config.destination);
+    for sweep in &sweeps {
+        println!("  {}", sweep);
+    }
+    if sweeps.iter().all(|sweep| sweep.amount == 0) {
+        println!("Nothing above the retained amounts");
+        return Ok(());
+    }
+    if args.iter().any(|arg| arg == "--dry-run") {
+        println!("Dry run; nothing sent");
+        return Ok(());
+    }
+    let signature = execute(&rpc, &wallet, &config.destination, &sweeps)?;
+    println!("Withdrawn in {}", signature);
+    Ok(())
+}
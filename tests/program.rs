@@ -0,0 +1,1153 @@
+// End-to-end tests of the arbitrage program under solana-program-test. The program runs natively,
+// or as the compiled arbitrage_contract.so when SBF_OUT_DIR points at one (build it with `anchor build`);
+// the venues and the flash loan are native mocks registered at the real program ids, so the program's
+// CPIs reach them unchanged
+use anchor_lang::{AccountDeserialize, AccountSerialize, InstructionData, ToAccountMetas};
+use anchor_spl::associated_token::get_associated_token_address;
+use anchor_spl::token::spl_token;
+use solana_program_test::{processor, BanksClientError, ProgramTest, ProgramTestContext};
+use solana_sdk::account::Account;
+use solana_sdk::account_info::AccountInfo;
+use solana_sdk::clock::Clock;
+use solana_sdk::compute_budget::ComputeBudgetInstruction;
+use solana_sdk::entrypoint::ProgramResult;
+use solana_sdk::instruction::{get_stack_height, AccountMeta, Instruction, InstructionError, TRANSACTION_LEVEL_STACK_HEIGHT};
+use solana_sdk::program::{invoke, invoke_signed};
+use solana_sdk::program_error::ProgramError;
+use solana_sdk::program_pack::Pack;
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::signature::{Keypair, Signer};
+use solana_sdk::sysvar::instructions::{load_current_index_checked, load_instruction_at_checked};
+use solana_sdk::transaction::{Transaction, TransactionError};
+use std::str::FromStr;
+
+#[allow(dead_code)]
+#[path = "../src/client.rs"]
+mod client;
+#[allow(dead_code)]
+#[path = "../src/solend.rs"]
+mod solend;
+
+const WHIRLPOOL_PROGRAM_ID: &str = "whirLbMiicVdio4qvUfM5KAg6Ct8VwpYzGff3uctyCc";
+const JUPITER_PROGRAM_ID: &str = "JUP6LkbZbjS1jKKwapdHNy74zcZ3tLUZoi5QNyVTaV4";
+const SOLEND_PROGRAM_ID: &str = "So1endDq2YkqhipRh3WViPa8hdiSpxWy6z3Z6tMCpAo";
+const RAYDIUM_PROGRAM_ID: &str = "675kPX9MHTjS2zt1qfr1NYHuzeLXfQM9H24wFSUt1Mp8";
+const VENUE_JUPITER: u8 = 0;
+const VENUE_RAYDIUM: u8 = 1;
+const VENUE_WHIRLPOOL: u8 = 3;
+const JUPITER_SHARED_ACCOUNTS_ROUTE: [u8; 8] = [193, 32, 155, 51, 65, 214, 156, 129];
+const JUPITER_ROUTE_FIXED_ACCOUNTS: usize = 13;
+const JUPITER_ROUTE_TRAILER_LEN: usize = 19;
+const RAYDIUM_SWAP_BASE_IN: u8 = 9;
+// Raydium's ExceededSlippage
+const RAYDIUM_EXCEEDED_SLIPPAGE: u32 = 30;
+const SOLEND_FLASH_BORROW: u8 = 19;
+const SOLEND_FLASH_REPAY: u8 = 20;
+const SOLEND_FLASH_LOAN_FEE_OFFSET: usize = 314;
+// Reserve offsets of the lending market, liquidity supply and fee receiver, and the reserve's size
+const SOLEND_RESERVE_OFFSETS: [usize; 3] = [10, 75, 339];
+const SOLEND_RESERVE_LEN: usize = 619;
+// Stand-ins for Solend's FlashBorrowCpi and FlashRepayCpi errors
+const SOLEND_FLASH_BORROW_CPI: u32 = 46;
+const SOLEND_FLASH_REPAY_CPI: u32 = 47;
+const WAD: u128 = 1_000_000_000_000_000_000;
+
+// Program error codes the tests expect
+const INSUFFICIENT_PROFIT: u32 = 6001;
+const INVALID_LEG_ACCOUNTS: u32 = 6005;
+const FLASH_BORROW_FAILED: u32 = 6017;
+const REPAY_SHORTFALL: u32 = 6018;
+const EXPIRED: u32 = 6011;
+const INVALID_FEE_COLLECTOR: u32 = 6012;
+const NOT_WHITELISTED: u32 = 6013;
+const WHITELIST_FULL: u32 = 6014;
+const LOCKED: u32 = 6015;
+const LEG1_SLIPPAGE_EXCEEDED: u32 = 6019;
+const ORACLE_STALE: u32 = 6025;
+const ROUTE_UNPROFITABLE: u32 = 6026;
+const INSUFFICIENT_COMPUTE_BUDGET: u32 = 6027;
+// Anchor's ConstraintHasOne
+const NOT_ADMIN: u32 = 2001;
+
+const LOAN_AMOUNT: u64 = 1_000_000;
+// 0.3%, charged on repayment
+const FLASH_LOAN_FEE_WAD: u64 = 3_000_000_000_000_000;
+const PROFIT_FEE_BPS: u16 = 1000;
+const POOL_LIQUIDITY: u64 = 1_000_000_000;
+
+// Anchor's entrypoint ties the accounts slice to the accounts' own lifetime, which processor! can't take
+fn arbitrage_program(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let accounts = Box::leak(Box::new(accounts.to_vec()));
+    arbitrage_contract::entry(program_id, accounts, data)
+}
+
+fn whirlpool_program_id() -> Pubkey {
+    Pubkey::from_str(WHIRLPOOL_PROGRAM_ID).unwrap()
+}
+
+fn jupiter_program_id() -> Pubkey {
+    Pubkey::from_str(JUPITER_PROGRAM_ID).unwrap()
+}
+
+fn solend_program_id() -> Pubkey {
+    Pubkey::from_str(SOLEND_PROGRAM_ID).unwrap()
+}
+
+fn raydium_program_id() -> Pubkey {
+    Pubkey::from_str(RAYDIUM_PROGRAM_ID).unwrap()
+}
+
+fn token_transfer<'a>(
+    token_program: &AccountInfo<'a>,
+    from: &AccountInfo<'a>,
+    to: &AccountInfo<'a>,
+    authority: &AccountInfo<'a>,
+    amount: u64,
+    seeds: &[&[&[u8]]],
+) -> ProgramResult {
+    let instruction = spl_token::instruction::transfer(token_program.key, from.key, to.key, authority.key, &[], amount)?;
+    invoke_signed(&instruction, &[from.clone(), to.clone(), authority.clone(), token_program.clone()], seeds)
+}
+
+// Whirlpool `swap` at a fixed rate. The pool account holds the rate for each direction in bps
+// (a_to_b u64, b_to_a u64) and, being a PDA of this program, owns and signs for the pool vaults
+fn mock_whirlpool(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [token_program, authority, pool, owner_a, vault_a, owner_b, vault_b, ..] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if data.len() < 42 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let amount = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let a_to_b = data[41] == 1;
+    let rate = {
+        let rates = pool.try_borrow_data()?;
+        let offset = if a_to_b { 0 } else { 8 };
+        u64::from_le_bytes(rates[offset..offset + 8].try_into().unwrap())
+    };
+    let amount_out = (amount as u128 * rate as u128 / 10_000) as u64;
+
+    let (source, pool_in, pool_out, destination) =
+        if a_to_b { (owner_a, vault_a, vault_b, owner_b) } else { (owner_b, vault_b, vault_a, owner_a) };
+    token_transfer(token_program, source, pool_in, authority, amount, &[])?;
+    let (mint_a, mint_b) = (token_mint(vault_a)?, token_mint(vault_b)?);
+    let (_, bump) = pool_address(program_id, &mint_a, &mint_b);
+    let seeds: &[&[u8]] = &[b"pool", mint_a.as_ref(), mint_b.as_ref(), &[bump]];
+    token_transfer(token_program, pool_out, destination, pool, amount_out, &[seeds])
+}
+
+// Raydium AMM v4 `swap_base_in` at a fixed rate, with the pool laid out like the mock whirlpool's.
+// Like Raydium, it takes exactly the instruction's 18 accounts and fails when the output falls
+// short of the instruction's minimum_amount_out
+fn mock_raydium(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    let [token_program, pool, _, _, _, coin_vault, pc_vault, _, _, _, _, _, _, _, _, source, destination, owner] = accounts else {
+        return Err(ProgramError::NotEnoughAccountKeys);
+    };
+    if data.len() != 17 || data[0] != RAYDIUM_SWAP_BASE_IN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let amount = u64::from_le_bytes(data[1..9].try_into().unwrap());
+    let minimum_amount_out = u64::from_le_bytes(data[9..17].try_into().unwrap());
+    let (mint_a, mint_b) = (token_mint(coin_vault)?, token_mint(pc_vault)?);
+    let a_to_b = token_mint(source)? == mint_a;
+    let rate = {
+        let rates = pool.try_borrow_data()?;
+        let offset = if a_to_b { 0 } else { 8 };
+        u64::from_le_bytes(rates[offset..offset + 8].try_into().unwrap())
+    };
+    let amount_out = (amount as u128 * rate as u128 / 10_000) as u64;
+    if amount_out < minimum_amount_out {
+        return Err(ProgramError::Custom(RAYDIUM_EXCEEDED_SLIPPAGE));
+    }
+
+    let (pool_in, pool_out) = if a_to_b { (coin_vault, pc_vault) } else { (pc_vault, coin_vault) };
+    token_transfer(token_program, source, pool_in, owner, amount, &[])?;
+    let (_, bump) = pool_address(program_id, &mint_a, &mint_b);
+    let seeds: &[&[u8]] = &[b"pool", mint_a.as_ref(), mint_b.as_ref(), &[bump]];
+    token_transfer(token_program, pool_out, destination, pool, amount_out, &[seeds])
+}
+
+// Jupiter `shared_accounts_route` over a one-step route plan through the mock whirlpool: the plan's
+// data is the swap direction, its accounts the whirlpool's swap accounts, and the user transfer
+// authority signs the swap as the whirlpool's token authority
+fn mock_jupiter(_program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if accounts.len() <= JUPITER_ROUTE_FIXED_ACCOUNTS || data.len() < 8 + 1 + JUPITER_ROUTE_TRAILER_LEN {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let in_amount = &data[data.len() - JUPITER_ROUTE_TRAILER_LEN..][..8];
+    let authority = &accounts[2];
+    let plan = &accounts[JUPITER_ROUTE_FIXED_ACCOUNTS..];
+
+    // Whirlpool swap: amount, threshold, sqrt price limit, amount_specified_is_input, a_to_b
+    let mut swap = vec![0; 8];
+    swap.extend_from_slice(in_amount);
+    swap.extend_from_slice(&[0; 8 + 16]);
+    swap.extend_from_slice(&[1, data[8]]);
+    let mut metas = vec![AccountMeta::new_readonly(*plan[1].key, false), AccountMeta::new_readonly(*authority.key, true)];
+    metas.extend(plan[2..].iter().map(|account| AccountMeta {
+        pubkey: *account.key,
+        is_signer: false,
+        is_writable: account.is_writable,
+    }));
+    let instruction = Instruction {
+        program_id: *plan[0].key,
+        accounts: metas,
+        data: swap,
+    };
+    let mut account_infos = vec![authority.clone()];
+    account_infos.extend_from_slice(plan);
+    invoke(&instruction, &account_infos)
+}
+
+// Solend flash_borrow_reserve_liquidity and flash_repay_reserve_liquidity, charging the reserve's
+// flash_loan_fee_wad like the real program. Like Solend, both must be top-level instructions, and a
+// borrow needs a repay of the same amount later in the transaction that names it as its borrow
+fn mock_solend(program_id: &Pubkey, accounts: &[AccountInfo], data: &[u8]) -> ProgramResult {
+    if data.len() < 9 {
+        return Err(ProgramError::InvalidInstructionData);
+    }
+    let amount = u64::from_le_bytes(data[1..9].try_into().unwrap());
+    let top_level = get_stack_height() == TRANSACTION_LEVEL_STACK_HEIGHT;
+    match data[0] {
+        SOLEND_FLASH_BORROW => {
+            let [supply, destination, _reserve, lending_market, market_authority, instructions, token_program, ..] = accounts else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            if !top_level {
+                return Err(ProgramError::Custom(SOLEND_FLASH_BORROW_CPI));
+            }
+            let borrow_index = load_current_index_checked(instructions)?;
+            let repaid = (borrow_index as usize + 1..)
+                .map_while(|index| load_instruction_at_checked(index, instructions).ok())
+                .any(|instruction| {
+                    instruction.program_id == *program_id
+                        && instruction.data.len() == 10
+                        && instruction.data[0] == SOLEND_FLASH_REPAY
+                        && instruction.data[1..9] == data[1..9]
+                        && instruction.data[9] as u16 == borrow_index
+                });
+            if !repaid {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let (_, bump) = Pubkey::find_program_address(&[lending_market.key.as_ref()], program_id);
+            let seeds: &[&[u8]] = &[lending_market.key.as_ref(), &[bump]];
+            token_transfer(token_program, supply, destination, market_authority, amount, &[seeds])
+        }
+        SOLEND_FLASH_REPAY => {
+            let [source, supply, fee_receiver, _host_fee_receiver, reserve, _lending_market, authority, instructions, token_program, ..] =
+                accounts
+            else {
+                return Err(ProgramError::NotEnoughAccountKeys);
+            };
+            if !top_level {
+                return Err(ProgramError::Custom(SOLEND_FLASH_REPAY_CPI));
+            }
+            let borrow = data.get(9).and_then(|&index| load_instruction_at_checked(index as usize, instructions).ok());
+            if !borrow.is_some_and(|borrow| {
+                borrow.program_id == *program_id && borrow.data[0] == SOLEND_FLASH_BORROW && borrow.data[1..9] == data[1..9]
+            }) {
+                return Err(ProgramError::InvalidInstructionData);
+            }
+            let fee_wad = {
+                let data = reserve.try_borrow_data()?;
+                u64::from_le_bytes(data[SOLEND_FLASH_LOAN_FEE_OFFSET..SOLEND_FLASH_LOAN_FEE_OFFSET + 8].try_into().unwrap())
+            };
+            let fee = (amount as u128 * fee_wad as u128).div_ceil(WAD) as u64;
+            token_transfer(token_program, source, supply, authority, amount, &[])?;
+            token_transfer(token_program, source, fee_receiver, authority, fee, &[])
+        }
+        _ => Err(ProgramError::InvalidInstructionData),
+    }
+}
+
+fn token_mint(account: &AccountInfo) -> Result<Pubkey, ProgramError> {
+    Ok(spl_token::state::Account::unpack(&account.try_borrow_data()?)?.mint)
+}
+
+fn pool_address(program_id: &Pubkey, mint_a: &Pubkey, mint_b: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[b"pool", mint_a.as_ref(), mint_b.as_ref()], program_id)
+}
+
+fn mint_account() -> Account {
+    let mut data = vec![0; spl_token::state::Mint::LEN];
+    let mint = spl_token::state::Mint {
+        decimals: 6,
+        is_initialized: true,
+        ..Default::default()
+    };
+    spl_token::state::Mint::pack(mint, &mut data).unwrap();
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+// Pyth v2 price account with a trading aggregate last published at `pub_slot`
+fn pyth_price_account(pub_slot: u64) -> Account {
+    let mut data = vec![0; 3312];
+    data[0..4].copy_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+    data[8..12].copy_from_slice(&3u32.to_le_bytes());
+    data[224..228].copy_from_slice(&1u32.to_le_bytes());
+    data[232..240].copy_from_slice(&pub_slot.to_le_bytes());
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: arbitrage_contract::PYTH_PROGRAM_ID,
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+fn token_account(mint: &Pubkey, owner: &Pubkey, amount: u64) -> Account {
+    let mut data = vec![0; spl_token::state::Account::LEN];
+    let account = spl_token::state::Account {
+        mint: *mint,
+        owner: *owner,
+        amount,
+        state: spl_token::state::AccountState::Initialized,
+        ..Default::default()
+    };
+    spl_token::state::Account::pack(account, &mut data).unwrap();
+    Account {
+        lamports: 1_000_000_000,
+        data,
+        owner: spl_token::id(),
+        executable: false,
+        rent_epoch: 0,
+    }
+}
+
+// A two-leg cycle A -> B -> A through one mock whirlpool, funded by a mock Solend reserve
+struct Harness {
+    context: ProgramTestContext,
+    program_id: Pubkey,
+    mint_a: Pubkey,
+    mint_b: Pubkey,
+    token_a_account: Pubkey,
+    profit_destination: Pubkey,
+    fee_collector_account: Pubkey,
+    leg_accounts: [Vec<AccountMeta>; 2],
+    reserve: solend::SolendReserve,
+    price_oracle: Option<Pubkey>,
+    // Venue both legs go through: the whirlpool directly, or Jupiter routing through it
+    venue: u8,
+    // The config as last set by the admin
+    config: arbitrage_contract::ConfigParams,
+}
+
+impl Harness {
+    // `rates` are the pool's A -> B and B -> A rates in bps
+    async fn start(rates: [u64; 2]) -> Self {
+        let program_id = client::program_id();
+        let mut program_test = ProgramTest::new("arbitrage_contract", program_id, processor!(arbitrage_program));
+        program_test.add_program("mock_whirlpool", whirlpool_program_id(), processor!(mock_whirlpool));
+        program_test.add_program("mock_solend", solend_program_id(), processor!(mock_solend));
+        program_test.add_program("mock_jupiter", jupiter_program_id(), processor!(mock_jupiter));
+        program_test.add_program("mock_raydium", raydium_program_id(), processor!(mock_raydium));
+
+        let mint_a = Pubkey::new_unique();
+        let mint_b = Pubkey::new_unique();
+        program_test.add_account(mint_a, mint_account());
+        program_test.add_account(mint_b, mint_account());
+
+        // Pool: rates, and a vault per mint owned by the pool itself
+        let (pool, _) = pool_address(&whirlpool_program_id(), &mint_a, &mint_b);
+        let mut pool_data = rates[0].to_le_bytes().to_vec();
+        pool_data.extend_from_slice(&rates[1].to_le_bytes());
+        program_test.add_account(
+            pool,
+            Account {
+                lamports: 1_000_000_000,
+                data: pool_data,
+                owner: whirlpool_program_id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        let pool_vault_a = Pubkey::new_unique();
+        let pool_vault_b = Pubkey::new_unique();
+        program_test.add_account(pool_vault_a, token_account(&mint_a, &pool, POOL_LIQUIDITY));
+        program_test.add_account(pool_vault_b, token_account(&mint_b, &pool, POOL_LIQUIDITY));
+
+        // The program's vaults for both mints
+        let vault_authority = client::vault_authority_address(&program_id);
+        let vault_a = get_associated_token_address(&vault_authority, &mint_a);
+        let vault_b = get_associated_token_address(&vault_authority, &mint_b);
+        program_test.add_account(vault_a, token_account(&mint_a, &vault_authority, 0));
+        program_test.add_account(vault_b, token_account(&mint_b, &vault_authority, 0));
+
+        // Solend reserve lending token A
+        let reserve = Pubkey::new_unique();
+        let lending_market = Pubkey::new_unique();
+        let (market_authority, _) = Pubkey::find_program_address(&[lending_market.as_ref()], &solend_program_id());
+        let supply = Pubkey::new_unique();
+        let fee_receiver = Pubkey::new_unique();
+        let mut reserve_data = vec![0; SOLEND_RESERVE_LEN];
+        for (offset, address) in SOLEND_RESERVE_OFFSETS.iter().zip([lending_market, supply, fee_receiver]) {
+            reserve_data[*offset..offset + 32].copy_from_slice(address.as_ref());
+        }
+        reserve_data[SOLEND_FLASH_LOAN_FEE_OFFSET..SOLEND_FLASH_LOAN_FEE_OFFSET + 8].copy_from_slice(&FLASH_LOAN_FEE_WAD.to_le_bytes());
+        let reserve = solend::SolendReserve::decode(&solend_program_id(), reserve, &reserve_data).unwrap();
+        program_test.add_account(
+            reserve.address,
+            Account {
+                lamports: 1_000_000_000,
+                data: reserve_data,
+                owner: solend_program_id(),
+                executable: false,
+                rent_epoch: 0,
+            },
+        );
+        program_test.add_account(supply, token_account(&mint_a, &market_authority, POOL_LIQUIDITY));
+        program_test.add_account(fee_receiver, token_account(&mint_a, &Pubkey::new_unique(), 0));
+
+        let fee_collector = Pubkey::new_unique();
+        let fee_collector_account = Pubkey::new_unique();
+        program_test.add_account(fee_collector_account, token_account(&mint_a, &fee_collector, 0));
+
+        let mut context = program_test.start_with_context().await;
+        let operator = context.payer.pubkey();
+        // The loan is paid into the operator's associated token account
+        let token_a_account = get_associated_token_address(&operator, &mint_a);
+        let profit_destination = Pubkey::new_unique();
+        context.set_account(&token_a_account, &token_account(&mint_a, &operator, 0).into());
+        context.set_account(&profit_destination, &token_account(&mint_a, &operator, 0).into());
+
+        let mut harness = Self {
+            context,
+            program_id,
+            mint_a,
+            mint_b,
+            token_a_account,
+            profit_destination,
+            fee_collector_account,
+            leg_accounts: [
+                Self::whirlpool_leg(pool, [vault_a, pool_vault_a, vault_b, pool_vault_b], mint_b),
+                Self::whirlpool_leg(pool, [vault_a, pool_vault_a, vault_b, pool_vault_b], mint_a),
+            ],
+            reserve,
+            price_oracle: None,
+            venue: VENUE_WHIRLPOOL,
+            config: arbitrage_contract::ConfigParams {
+                operator,
+                paused: false,
+                max_trade_size: u64::MAX,
+                flash_loan_fee_bps: 0,
+                max_slippage_bps: 100,
+                fee_collector,
+                profit_fee_bps: PROFIT_FEE_BPS,
+                allow_unlisted_jupiter_pools: false,
+                price_oracle: Pubkey::default(),
+            },
+        };
+        harness.initialize(pool).await;
+        harness
+    }
+
+    // [program, token program, whirlpool, vault A, pool vault A, vault B, pool vault B, three tick
+    // arrays and the oracle (unused by the mock), output mint]
+    fn whirlpool_leg(pool: Pubkey, vaults: [Pubkey; 4], output_mint: Pubkey) -> Vec<AccountMeta> {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(whirlpool_program_id(), false),
+            AccountMeta::new_readonly(spl_token::id(), false),
+            AccountMeta::new(pool, false),
+        ];
+        accounts.extend(vaults.iter().map(|vault| AccountMeta::new(*vault, false)));
+        accounts.extend((0..4).map(|_| AccountMeta::new_readonly(Pubkey::new_unique(), false)));
+        accounts.push(AccountMeta::new_readonly(output_mint, false));
+        accounts
+    }
+
+    // Moves both legs to a Raydium AMM pool at the whirlpool's rates, with vaults of its own, and
+    // lists it in the whitelist
+    async fn use_raydium(&mut self) {
+        let whirlpool = self.leg_accounts[0][2].pubkey;
+        let rates = self.account(whirlpool).await.data;
+        let (pool, _) = pool_address(&raydium_program_id(), &self.mint_a, &self.mint_b);
+        let pool_account = Account {
+            lamports: 1_000_000_000,
+            data: rates,
+            owner: raydium_program_id(),
+            executable: false,
+            rent_epoch: 0,
+        };
+        self.context.set_account(&pool, &pool_account.into());
+        let (coin_vault, pc_vault) = (Pubkey::new_unique(), Pubkey::new_unique());
+        self.context.set_account(&coin_vault, &token_account(&self.mint_a, &pool, POOL_LIQUIDITY).into());
+        self.context.set_account(&pc_vault, &token_account(&self.mint_b, &pool, POOL_LIQUIDITY).into());
+
+        let vault_authority = client::vault_authority_address(&self.program_id);
+        let vault_a = get_associated_token_address(&vault_authority, &self.mint_a);
+        let vault_b = get_associated_token_address(&vault_authority, &self.mint_b);
+        self.leg_accounts = [
+            Self::raydium_leg(pool, [coin_vault, pc_vault], [vault_a, vault_b], self.mint_b),
+            Self::raydium_leg(pool, [coin_vault, pc_vault], [vault_b, vault_a], self.mint_a),
+        ];
+        self.venue = VENUE_RAYDIUM;
+        self.update_whitelist(vec![raydium_program_id()], vec![pool]).await.unwrap();
+    }
+
+    // [program, pool, authority, open orders, target orders, pool coin and pc vaults, OpenBook
+    // program, market, bids, asks, event queue, market coin and pc vaults, vault signer, source,
+    // destination, output mint]; the accounts between the pool and its vaults, and the market's,
+    // are unused by the mock
+    fn raydium_leg(pool: Pubkey, pool_vaults: [Pubkey; 2], [source, destination]: [Pubkey; 2], output_mint: Pubkey) -> Vec<AccountMeta> {
+        let mut accounts = vec![
+            AccountMeta::new_readonly(raydium_program_id(), false),
+            AccountMeta::new(pool, false),
+            AccountMeta::new_readonly(Pubkey::new_unique(), false),
+        ];
+        accounts.extend((0..2).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+        accounts.extend(pool_vaults.iter().map(|vault| AccountMeta::new(*vault, false)));
+        accounts.push(AccountMeta::new_readonly(Pubkey::new_unique(), false));
+        accounts.extend((0..6).map(|_| AccountMeta::new(Pubkey::new_unique(), false)));
+        accounts.push(AccountMeta::new_readonly(Pubkey::new_unique(), false));
+        accounts.push(AccountMeta::new(source, false));
+        accounts.push(AccountMeta::new(destination, false));
+        accounts.push(AccountMeta::new_readonly(output_mint, false));
+        accounts
+    }
+
+    async fn initialize(&mut self, pool: Pubkey) {
+        let admin = self.context.payer.pubkey();
+        let config = &self.config;
+        let params = client::ConfigParams {
+            operator: config.operator.to_bytes(),
+            paused: config.paused,
+            max_trade_size: config.max_trade_size,
+            flash_loan_fee_bps: config.flash_loan_fee_bps,
+            max_slippage_bps: config.max_slippage_bps,
+            fee_collector: config.fee_collector.to_bytes(),
+            profit_fee_bps: config.profit_fee_bps,
+            allow_unlisted_jupiter_pools: config.allow_unlisted_jupiter_pools,
+            price_oracle: config.price_oracle.to_bytes(),
+        };
+        let whitelist = client::WhitelistArgs {
+            programs: vec![whirlpool_program_id().to_bytes()],
+            pools: vec![pool.to_bytes()],
+        };
+        let instructions = vec![
+            client::initialize_config(&self.program_id, &admin, &params).unwrap(),
+            client::initialize_whitelist(&self.program_id, &admin, &whitelist).unwrap(),
+        ];
+        self.send(instructions).await.unwrap();
+    }
+
+    // Changes the config with update_config. The bot never sends the admin instructions, so they are
+    // built with the program's own Anchor client
+    async fn update_config(&mut self, change: impl FnOnce(&mut arbitrage_contract::ConfigParams)) -> Result<(), BanksClientError> {
+        change(&mut self.config);
+        let instruction = Instruction {
+            program_id: self.program_id,
+            accounts: arbitrage_contract::accounts::UpdateConfig {
+                config: client::config_address(&self.program_id),
+                admin: self.context.payer.pubkey(),
+            }
+            .to_account_metas(None),
+            data: arbitrage_contract::instruction::UpdateConfig {
+                params: self.config.clone(),
+            }
+            .data(),
+        };
+        self.send(vec![instruction]).await
+    }
+
+    async fn update_whitelist(&mut self, programs: Vec<Pubkey>, pools: Vec<Pubkey>) -> Result<(), BanksClientError> {
+        let instruction = Instruction {
+            program_id: self.program_id,
+            accounts: arbitrage_contract::accounts::UpdateWhitelist {
+                whitelist: client::whitelist_address(&self.program_id),
+                config: client::config_address(&self.program_id),
+                admin: self.context.payer.pubkey(),
+            }
+            .to_account_metas(None),
+            data: arbitrage_contract::instruction::UpdateWhitelist { programs, pools }.data(),
+        };
+        self.send(vec![instruction]).await
+    }
+
+    async fn migrate(&mut self) -> Result<(), BanksClientError> {
+        let instruction = Instruction {
+            program_id: self.program_id,
+            accounts: arbitrage_contract::accounts::Migrate {
+                config: client::config_address(&self.program_id),
+                whitelist: client::whitelist_address(&self.program_id),
+                admin: self.context.payer.pubkey(),
+                system_program: solana_sdk::system_program::id(),
+            }
+            .to_account_metas(None),
+            data: arbitrage_contract::instruction::Migrate {}.data(),
+        };
+        self.send(vec![instruction]).await
+    }
+
+    // Rewrites the config and whitelist as accounts of layout `version` would hold them, funded for
+    // their size at that version
+    async fn downgrade(&mut self, version: u8) {
+        let config_address = client::config_address(&self.program_id);
+        let whitelist_address = client::whitelist_address(&self.program_id);
+        let rent = self.context.banks_client.get_rent().await.unwrap();
+
+        let mut config = self.account(config_address).await;
+        config.data.truncate(8 + arbitrage_contract::Config::V0_LEN);
+        if version > 0 {
+            config.data.push(version);
+        }
+        config.lamports = rent.minimum_balance(config.data.len());
+        self.context.set_account(&config_address, &config.into());
+
+        // The whitelist's version byte follows its bump; before versioning, that byte was padding
+        let mut whitelist = self.account(whitelist_address).await;
+        let current = arbitrage_contract::Whitelist::try_deserialize(&mut whitelist.data.as_slice()).unwrap();
+        let version_offset = 8 + 4 + 32 * current.programs.len() + 4 + 32 * current.pools.len() + 1;
+        whitelist.data[version_offset] = version;
+        if version == 0 {
+            whitelist.data.truncate(8 + arbitrage_contract::Whitelist::V0_LEN);
+        }
+        whitelist.lamports = rent.minimum_balance(whitelist.data.len());
+        self.context.set_account(&whitelist_address, &whitelist.into());
+    }
+
+    async fn account(&mut self, address: Pubkey) -> Account {
+        self.context.banks_client.get_account(address).await.unwrap().unwrap()
+    }
+
+    // Sets the config's reentrancy lock as a try_arbitrage running further up the stack holds it
+    async fn set_locked(&mut self, locked: bool) {
+        let address = client::config_address(&self.program_id);
+        let mut account = self.account(address).await;
+        let mut config = self.config().await;
+        config.locked = locked;
+        let mut data = Vec::new();
+        config.try_serialize(&mut data).unwrap();
+        account.data[..data.len()].copy_from_slice(&data);
+        self.context.set_account(&address, &account.into());
+    }
+
+    async fn config(&mut self) -> arbitrage_contract::Config {
+        let account = self.account(client::config_address(&self.program_id)).await;
+        arbitrage_contract::Config::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    async fn whitelist(&mut self) -> arbitrage_contract::Whitelist {
+        let account = self.account(client::whitelist_address(&self.program_id)).await;
+        arbitrage_contract::Whitelist::try_deserialize(&mut account.data.as_slice()).unwrap()
+    }
+
+    // Runs the cycle on a Solend loan of LOAN_AMOUNT, with `leg_minimums` as each leg's min_amount_out
+    async fn try_arbitrage(&mut self, min_profit_lamports: u64, leg_minimums: [u64; 2], compute_units: u32) -> Result<(), BanksClientError> {
+        let expiry_slot = self.expiry_slot().await?;
+        self.try_arbitrage_until(expiry_slot, min_profit_lamports, leg_minimums, compute_units).await
+    }
+
+    // The same, landing no later than `expiry_slot`
+    async fn try_arbitrage_until(
+        &mut self,
+        expiry_slot: u64,
+        min_profit_lamports: u64,
+        leg_minimums: [u64; 2],
+        compute_units: u32,
+    ) -> Result<(), BanksClientError> {
+        let (remaining_accounts, loan_provider) = self.remaining_accounts();
+        let args = client::TryArbitrageArgs {
+            amount: LOAN_AMOUNT,
+            min_profit_lamports,
+            expiry_slot,
+            loan_provider,
+            legs: self.legs(leg_minimums),
+        };
+        let instruction = client::try_arbitrage(&self.program_id, &self.trade_accounts(), &args, remaining_accounts).unwrap();
+        self.send_on_loan(compute_units, instruction).await
+    }
+
+    // Runs the cycle once per route, each on its (amount, min_profit_lamports), on one Solend loan of
+    // LOAN_AMOUNT
+    async fn try_arbitrage_batch(&mut self, min_profit_lamports: u64, routes: &[(u64, u64)]) -> Result<(), BanksClientError> {
+        let (remaining_accounts, loan_provider) = self.remaining_accounts();
+        let args = client::TryArbitrageBatchArgs {
+            amount: LOAN_AMOUNT,
+            min_profit_lamports,
+            expiry_slot: self.expiry_slot().await?,
+            loan_provider,
+            // Every route runs through the same pool, so they share its leg accounts
+            routes: routes
+                .iter()
+                .map(|&(amount, min_profit_lamports)| client::BatchRoute {
+                    amount,
+                    min_profit_lamports,
+                    legs: self.legs([0, 0]),
+                })
+                .collect(),
+        };
+        let instruction = client::try_arbitrage_batch(&self.program_id, &self.trade_accounts(), &args, remaining_accounts).unwrap();
+        self.send_on_loan(COMPUTE_UNITS * 2, instruction).await
+    }
+
+    // Sends the trade between the bot's Solend borrow and repay, after the compute limit
+    async fn send_on_loan(&mut self, compute_units: u32, instruction: Instruction) -> Result<(), BanksClientError> {
+        let mut instructions = vec![ComputeBudgetInstruction::set_compute_unit_limit(compute_units)];
+        let wrapped = self
+            .reserve
+            .wrap(&self.context.payer.pubkey(), &self.mint_a, LOAN_AMOUNT, instruction, instructions.len())
+            .unwrap();
+        instructions.extend(wrapped);
+        self.send(instructions).await
+    }
+
+    // The cycle's two legs, whose accounts start the remaining accounts
+    fn legs(&self, leg_minimums: [u64; 2]) -> Vec<client::RouteLeg> {
+        let mints = [self.mint_a, self.mint_b, self.mint_a];
+        let mut offset = 0;
+        self.venue_accounts()
+            .iter()
+            .enumerate()
+            .map(|(index, accounts)| {
+                let venue_data = match self.venue {
+                    // Route plan: the swap direction; in_amount is filled in by the program
+                    VENUE_JUPITER => [&JUPITER_SHARED_ACCOUNTS_ROUTE[..], &[(index == 0) as u8], &[0; JUPITER_ROUTE_TRAILER_LEN]].concat(),
+                    _ => Vec::new(),
+                };
+                let leg = client::RouteLeg {
+                    venue: self.venue,
+                    input_mint: mints[index].to_bytes(),
+                    output_mint: mints[index + 1].to_bytes(),
+                    min_amount_out: leg_minimums[index],
+                    accounts_offset: offset,
+                    accounts_len: accounts.len() as u8,
+                    venue_data,
+                };
+                offset += accounts.len() as u8;
+                leg
+            })
+            .collect()
+    }
+
+    // Each leg's accounts for the harness's venue. A Jupiter leg's route plan is the whirlpool leg's
+    // accounts, without the output mint that `shared_accounts_route` already names
+    fn venue_accounts(&self) -> Vec<Vec<AccountMeta>> {
+        if self.venue != VENUE_JUPITER {
+            return self.leg_accounts.to_vec();
+        }
+        let vault_authority = client::vault_authority_address(&self.program_id);
+        let mints = [self.mint_a, self.mint_b, self.mint_a];
+        (0..2)
+            .map(|index| {
+                let (input_mint, output_mint) = (mints[index], mints[index + 1]);
+                let mut accounts = vec![
+                    AccountMeta::new_readonly(jupiter_program_id(), false),
+                    AccountMeta::new_readonly(spl_token::id(), false),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                    AccountMeta::new_readonly(vault_authority, false),
+                    AccountMeta::new(get_associated_token_address(&vault_authority, &input_mint), false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new(Pubkey::new_unique(), false),
+                    AccountMeta::new(get_associated_token_address(&vault_authority, &output_mint), false),
+                    AccountMeta::new_readonly(input_mint, false),
+                    AccountMeta::new_readonly(output_mint, false),
+                    AccountMeta::new_readonly(jupiter_program_id(), false),
+                    AccountMeta::new_readonly(anchor_spl::token_2022::ID, false),
+                    AccountMeta::new_readonly(Pubkey::new_unique(), false),
+                    AccountMeta::new_readonly(jupiter_program_id(), false),
+                ];
+                let plan = &self.leg_accounts[index];
+                accounts.extend(plan[..plan.len() - 1].iter().cloned());
+                accounts
+            })
+            .collect()
+    }
+
+    // Both legs' accounts followed by the Solend reserve
+    fn remaining_accounts(&self) -> (Vec<AccountMeta>, client::LoanProvider) {
+        let mut remaining_accounts: Vec<AccountMeta> = self.venue_accounts().into_iter().flatten().collect();
+        let loan_provider = client::LoanProvider::Solend {
+            accounts_offset: remaining_accounts.len() as u8,
+        };
+        remaining_accounts.extend(self.reserve.loan_accounts());
+        (remaining_accounts, loan_provider)
+    }
+
+    fn trade_accounts(&self) -> client::TryArbitrageAccounts {
+        let vault_authority = client::vault_authority_address(&self.program_id);
+        client::TryArbitrageAccounts {
+            user: self.context.payer.pubkey(),
+            token_a_account: self.token_a_account,
+            vault: get_associated_token_address(&vault_authority, &self.mint_a),
+            token_a_mint: self.mint_a,
+            associated_token_program: None,
+            token_program: spl_token::id(),
+            profit_destination: self.profit_destination,
+            fee_collector_account: self.fee_collector_account,
+            price_oracle: self.price_oracle,
+        }
+    }
+
+    async fn expiry_slot(&mut self) -> Result<u64, BanksClientError> {
+        Ok(self.context.banks_client.get_sysvar::<Clock>().await?.slot + 100)
+    }
+
+    async fn send(&mut self, instructions: Vec<Instruction>) -> Result<(), BanksClientError> {
+        let payer = &self.context.payer;
+        let blockhash = self.context.banks_client.get_latest_blockhash().await?;
+        let transaction = Transaction::new_signed_with_payer(&instructions, Some(&payer.pubkey()), &[payer], blockhash);
+        self.context.banks_client.process_transaction(transaction).await
+    }
+
+    async fn balance(&mut self, account: Pubkey) -> u64 {
+        let account = self.context.banks_client.get_account(account).await.unwrap().unwrap();
+        spl_token::state::Account::unpack(&account.data).unwrap().amount
+    }
+}
+
+// The program's custom error code, if the transaction failed with one
+fn custom_error(result: Result<(), BanksClientError>) -> Option<u32> {
+    match result {
+        Err(BanksClientError::TransactionError(TransactionError::InstructionError(_, InstructionError::Custom(code)))) => Some(code),
+        Err(BanksClientError::SimulationError {
+            err: TransactionError::InstructionError(_, InstructionError::Custom(code)),
+            ..
+        }) => Some(code),
+        _ => None,
+    }
+}
+
+fn flash_loan_fee() -> u64 {
+    (LOAN_AMOUNT as u128 * FLASH_LOAN_FEE_WAD as u128).div_ceil(WAD) as u64
+}
+
+const COMPUTE_UNITS: u32 = 400_000;
+
+#[tokio::test]
+async fn profitable_route_pays_out_profit() {
+    // A -> B at 1.02, B -> A at 1.00
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    let amount_out = LOAN_AMOUNT * 102 / 100;
+    harness.try_arbitrage(10_000, [amount_out, amount_out], COMPUTE_UNITS).await.unwrap();
+
+    let profit = amount_out - LOAN_AMOUNT - flash_loan_fee();
+    let profit_fee = profit * PROFIT_FEE_BPS as u64 / 10_000;
+    assert_eq!(harness.balance(harness.profit_destination).await, profit - profit_fee);
+    assert_eq!(harness.balance(harness.fee_collector_account).await, profit_fee);
+    // The loan and its fee went back to the reserve, leaving nothing behind
+    assert_eq!(harness.balance(harness.token_a_account).await, 0);
+}
+
+#[tokio::test]
+async fn profit_below_minimum_reverts() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    let result = harness.try_arbitrage(50_000, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(INSUFFICIENT_PROFIT));
+}
+
+#[tokio::test]
+async fn leg_below_its_minimum_reverts() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    // The first leg delivers 1.02x, short of the 1.03x the route was quoted at
+    let result = harness.try_arbitrage(0, [LOAN_AMOUNT * 103 / 100, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(LEG1_SLIPPAGE_EXCEEDED));
+}
+
+#[tokio::test]
+async fn route_short_of_loan_and_fee_reverts() {
+    // A flat cycle returns the loan but not the reserve's fee
+    let mut harness = Harness::start([10_000, 10_000]).await;
+    let result = harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(REPAY_SHORTFALL));
+}
+
+#[tokio::test]
+async fn compute_limit_below_route_reverts_before_borrowing() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    // The two legs and Solend's borrow and repay need at least 160k
+    let result = harness.try_arbitrage(0, [0, 0], 120_000).await;
+    assert_eq!(custom_error(result), Some(INSUFFICIENT_COMPUTE_BUDGET));
+}
+
+#[tokio::test]
+async fn batch_pays_out_the_routes_combined_profit() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    // Two halves of the loan, each its own cycle, with a minimum each of them clears
+    harness.try_arbitrage_batch(10_000, &[(LOAN_AMOUNT / 2, 1), (LOAN_AMOUNT / 2, 1)]).await.unwrap();
+
+    // The loan fee is paid once, out of both routes' gains together
+    let profit = LOAN_AMOUNT * 2 / 100 - flash_loan_fee();
+    let profit_fee = profit * PROFIT_FEE_BPS as u64 / 10_000;
+    assert_eq!(harness.balance(harness.profit_destination).await, profit - profit_fee);
+    assert_eq!(harness.balance(harness.fee_collector_account).await, profit_fee);
+    assert_eq!(harness.balance(harness.token_a_account).await, 0);
+}
+
+#[tokio::test]
+async fn batch_route_below_its_own_minimum_reverts() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    // The batch as a whole has no minimum, but the second route gains 10k of the 20k it must
+    let result = harness.try_arbitrage_batch(0, &[(LOAN_AMOUNT / 2, 0), (LOAN_AMOUNT / 2, 20_000)]).await;
+    assert_eq!(custom_error(result), Some(ROUTE_UNPROFITABLE));
+}
+
+#[tokio::test]
+async fn batch_below_its_combined_minimum_reverts() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    // Each route clears its own minimum, but together they net 17k after the loan fee
+    let result = harness.try_arbitrage_batch(20_000, &[(LOAN_AMOUNT / 2, 1), (LOAN_AMOUNT / 2, 1)]).await;
+    assert_eq!(custom_error(result), Some(INSUFFICIENT_PROFIT));
+}
+
+#[tokio::test]
+async fn stale_oracle_reverts() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    // Far enough along that a feed can be older than the window
+    let slot = 1_000;
+    let clock = harness.context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    harness.context.set_sysvar(&Clock { slot, ..clock });
+    let oracle = Pubkey::new_unique();
+    harness.update_config(|config| config.price_oracle = oracle).await.unwrap();
+    harness.price_oracle = Some(oracle);
+
+    let stale = slot - arbitrage_contract::MAX_ORACLE_AGE_SLOTS - 1;
+    harness.context.set_account(&oracle, &pyth_price_account(stale).into());
+    let result = harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(ORACLE_STALE));
+
+    // Published within the window, the same trade goes through
+    harness.context.set_account(&oracle, &pyth_price_account(stale + 1).into());
+    harness.try_arbitrage(1, [0, 0], COMPUTE_UNITS).await.unwrap();
+}
+
+#[tokio::test]
+async fn configured_oracle_must_be_passed_and_owned_by_pyth() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    let slot = harness.context.banks_client.get_sysvar::<Clock>().await.unwrap().slot;
+    let oracle = Pubkey::new_unique();
+    harness.context.set_account(&oracle, &pyth_price_account(slot).into());
+    harness.update_config(|config| config.price_oracle = oracle).await.unwrap();
+
+    // Leaving the feed out doesn't skip the check
+    let result = harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(ORACLE_STALE));
+
+    // Nor does passing a fresh feed other than the configured one
+    let other = Pubkey::new_unique();
+    harness.context.set_account(&other, &pyth_price_account(slot).into());
+    harness.price_oracle = Some(other);
+    let result = harness.try_arbitrage(1, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(ORACLE_STALE));
+
+    // A lookalike of the configured feed that the Pyth program doesn't own
+    let mut forged = pyth_price_account(slot);
+    forged.owner = Pubkey::new_unique();
+    harness.context.set_account(&oracle, &forged.into());
+    harness.price_oracle = Some(oracle);
+    let result = harness.try_arbitrage(2, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(ORACLE_STALE));
+
+    harness.context.set_account(&oracle, &pyth_price_account(slot).into());
+    harness.try_arbitrage(3, [0, 0], COMPUTE_UNITS).await.unwrap();
+}
+
+#[tokio::test]
+async fn raydium_leg_swaps_with_the_pool_and_market_accounts_and_the_quoted_minimum() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    harness.use_raydium().await;
+    let amount_out = LOAN_AMOUNT * 102 / 100;
+    harness.try_arbitrage(1, [amount_out, amount_out], COMPUTE_UNITS).await.unwrap();
+
+    // The leg's minimum reaches Raydium as is, which refuses the swap before the program's own check
+    let result = harness.try_arbitrage(2, [LOAN_AMOUNT * 103 / 100, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(RAYDIUM_EXCEEDED_SLIPPAGE));
+
+    // A leg missing any of the market accounts is refused
+    harness.leg_accounts[0].remove(9);
+    let result = harness.try_arbitrage(3, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(INVALID_LEG_ACCOUNTS));
+}
+
+#[tokio::test]
+async fn jupiter_leg_through_unlisted_pool_accounts_is_rejected() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    harness.venue = VENUE_JUPITER;
+    let programs = vec![whirlpool_program_id(), jupiter_program_id()];
+    // Only the pool state is listed, not its vaults, tick arrays or oracle
+    let pool = harness.leg_accounts[0][2].pubkey;
+    harness.update_whitelist(programs.clone(), vec![pool]).await.unwrap();
+    let result = harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(NOT_WHITELISTED));
+
+    // With every account of the pool listed, the route goes through
+    let pool_accounts: Vec<Pubkey> = harness
+        .leg_accounts
+        .iter()
+        .flat_map(|leg| leg[2..leg.len() - 1].iter().map(|account| account.pubkey))
+        .collect();
+    harness.update_whitelist(programs, pool_accounts).await.unwrap();
+    harness.try_arbitrage(1, [0, 0], COMPUTE_UNITS).await.unwrap();
+}
+
+#[tokio::test]
+async fn jupiter_leg_may_use_unlisted_pools_once_allowed() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    harness.venue = VENUE_JUPITER;
+    harness.update_whitelist(vec![whirlpool_program_id(), jupiter_program_id()], Vec::new()).await.unwrap();
+    harness.update_config(|config| config.allow_unlisted_jupiter_pools = true).await.unwrap();
+    harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await.unwrap();
+
+    // The AMM program itself must still be listed
+    harness.update_whitelist(vec![jupiter_program_id()], Vec::new()).await.unwrap();
+    let result = harness.try_arbitrage(1, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(NOT_WHITELISTED));
+}
+
+#[tokio::test]
+async fn solend_trade_without_the_borrow_reverts() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    // The trade instruction alone, with no Solend borrow ahead of it to fund the token account
+    let (remaining_accounts, loan_provider) = harness.remaining_accounts();
+    let args = client::TryArbitrageArgs {
+        amount: LOAN_AMOUNT,
+        min_profit_lamports: 0,
+        expiry_slot: harness.expiry_slot().await.unwrap(),
+        loan_provider,
+        legs: harness.legs([0, 0]),
+    };
+    let instruction = client::try_arbitrage(&harness.program_id, &harness.trade_accounts(), &args, remaining_accounts).unwrap();
+    let result = harness.send(vec![ComputeBudgetInstruction::set_compute_unit_limit(COMPUTE_UNITS), instruction]).await;
+    assert_eq!(custom_error(result), Some(FLASH_BORROW_FAILED));
+}
+
+#[tokio::test]
+async fn trade_landing_after_its_expiry_slot_reverts() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    let slot = 1_000;
+    let clock = harness.context.banks_client.get_sysvar::<Clock>().await.unwrap();
+    harness.context.set_sysvar(&Clock { slot, ..clock });
+    let result = harness.try_arbitrage_until(slot - 1, 0, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(EXPIRED));
+
+    // The expiry slot itself is still in time
+    harness.try_arbitrage_until(slot, 1, [0, 0], COMPUTE_UNITS).await.unwrap();
+}
+
+#[tokio::test]
+async fn trade_is_refused_while_the_lock_is_held() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    harness.set_locked(true).await;
+    let result = harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(LOCKED));
+
+    // A trade releases the lock when it completes, and one that reverts never leaves it set
+    harness.set_locked(false).await;
+    let result = harness.try_arbitrage(50_000, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(INSUFFICIENT_PROFIT));
+    assert!(!harness.config().await.locked);
+    harness.try_arbitrage(1, [0, 0], COMPUTE_UNITS).await.unwrap();
+    assert!(!harness.config().await.locked);
+    harness.try_arbitrage(2, [0, 0], COMPUTE_UNITS).await.unwrap();
+}
+
+#[tokio::test]
+async fn venues_off_the_whitelist_are_rejected() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    let pool = harness.leg_accounts[0][2].pubkey;
+    // The pool is listed but not its AMM program
+    harness.update_whitelist(Vec::new(), vec![pool]).await.unwrap();
+    let result = harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(NOT_WHITELISTED));
+
+    // The program is listed but not the pool
+    harness.update_whitelist(vec![whirlpool_program_id()], vec![Pubkey::new_unique()]).await.unwrap();
+    let result = harness.try_arbitrage(1, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(NOT_WHITELISTED));
+
+    // More programs than the account holds
+    let programs = (0..=arbitrage_contract::MAX_WHITELISTED_PROGRAMS).map(|_| Pubkey::new_unique()).collect();
+    let result = harness.update_whitelist(programs, vec![pool]).await;
+    assert_eq!(custom_error(result), Some(WHITELIST_FULL));
+
+    harness.update_whitelist(vec![whirlpool_program_id()], vec![pool]).await.unwrap();
+    harness.try_arbitrage(2, [0, 0], COMPUTE_UNITS).await.unwrap();
+}
+
+#[tokio::test]
+async fn only_the_admin_updates_the_whitelist() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    let impostor = Keypair::new();
+    let instruction = Instruction {
+        program_id: harness.program_id,
+        accounts: arbitrage_contract::accounts::UpdateWhitelist {
+            whitelist: client::whitelist_address(&harness.program_id),
+            config: client::config_address(&harness.program_id),
+            admin: impostor.pubkey(),
+        }
+        .to_account_metas(None),
+        data: arbitrage_contract::instruction::UpdateWhitelist {
+            programs: vec![Pubkey::new_unique()],
+            pools: Vec::new(),
+        }
+        .data(),
+    };
+    let payer = &harness.context.payer;
+    let blockhash = harness.context.banks_client.get_latest_blockhash().await.unwrap();
+    let transaction = Transaction::new_signed_with_payer(&[instruction], Some(&payer.pubkey()), &[payer, &impostor], blockhash);
+    let result = harness.context.banks_client.process_transaction(transaction).await;
+    assert_eq!(custom_error(result), Some(NOT_ADMIN));
+    assert_eq!(harness.whitelist().await.programs, vec![whirlpool_program_id()]);
+}
+
+#[tokio::test]
+async fn profit_share_goes_only_to_the_configured_collector() {
+    let mut harness = Harness::start([10_200, 10_000]).await;
+    let collector_account = harness.fee_collector_account;
+    // An account of the borrowed token, but not the collector's
+    let owner = Pubkey::new_unique();
+    let account = Pubkey::new_unique();
+    harness.context.set_account(&account, &token_account(&harness.mint_a, &owner, 0).into());
+    harness.fee_collector_account = account;
+    let result = harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await;
+    assert_eq!(custom_error(result), Some(INVALID_FEE_COLLECTOR));
+
+    // Once the admin names its owner as the collector, the share is paid there
+    harness.update_config(|config| config.fee_collector = owner).await.unwrap();
+    harness.try_arbitrage(1, [0, 0], COMPUTE_UNITS).await.unwrap();
+    let profit = LOAN_AMOUNT * 2 / 100 - flash_loan_fee();
+    let profit_fee = profit * PROFIT_FEE_BPS as u64 / 10_000;
+    assert_eq!(harness.balance(account).await, profit_fee);
+    assert_eq!(harness.balance(collector_account).await, 0);
+
+    // Without a share the destination gets the whole profit
+    harness.update_config(|config| config.profit_fee_bps = 0).await.unwrap();
+    let paid_out = harness.balance(harness.profit_destination).await;
+    harness.try_arbitrage(2, [0, 0], COMPUTE_UNITS).await.unwrap();
+    assert_eq!(harness.balance(harness.profit_destination).await - paid_out, profit);
+    assert_eq!(harness.balance(account).await, profit_fee);
+}
+
+#[tokio::test]
+async fn accounts_from_before_each_layout_are_migrated() {
+    for version in [0, 1] {
+        let mut harness = Harness::start([10_200, 10_000]).await;
+        let whitelist = harness.whitelist().await;
+        harness.downgrade(version).await;
+        // The outdated config doesn't even load as the current one
+        assert!(harness.try_arbitrage(0, [0, 0], COMPUTE_UNITS).await.is_err());
+
+        harness.migrate().await.unwrap();
+        let config_address = client::config_address(&harness.program_id);
+        let config_account = harness.account(config_address).await;
+        assert_eq!(config_account.data.len(), 8 + arbitrage_contract::Config::LEN);
+        let config = harness.config().await;
+        assert_eq!(config.version, arbitrage_contract::STATE_VERSION);
+        assert_eq!(config.fee_collector, harness.config.fee_collector);
+        // Added by v2 and zero-filled by the realloc: no oracle
+        assert_eq!(config.price_oracle, Pubkey::default());
+        let migrated = harness.whitelist().await;
+        assert_eq!(migrated.version, arbitrage_contract::STATE_VERSION);
+        assert_eq!((migrated.programs, migrated.pools), (whitelist.programs, whitelist.pools));
+        harness.try_arbitrage(1, [0, 0], COMPUTE_UNITS).await.unwrap();
+
+        // Migrating current accounts again changes nothing
+        harness.migrate().await.unwrap();
+        assert_eq!(harness.account(config_address).await.data, config_account.data);
+    }
+}